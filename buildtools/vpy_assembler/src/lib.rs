@@ -7,7 +7,7 @@ pub mod m6809;
 mod error;
 
 pub use error::AssemblyError;
-pub use m6809::{assemble_m6809, set_include_dir, BinaryEmitter, load_vectrex_symbols};
+pub use m6809::{assemble_m6809, assemble_object, AssembledObject, ObjectConstant, ObjectLabel, direct_page_stats, set_include_dir, BinaryEmitter, load_vectrex_symbols, DirectPageStats};
 
 use std::collections::HashMap;
 // Note: Will be used when assembler is fully implemented
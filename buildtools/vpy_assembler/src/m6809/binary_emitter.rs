@@ -1,7 +1,7 @@
 // M6809 Binary Code Emitter - Direct machine code generation
 // Eliminates dependency on lwasm by providing integrated binary emission with precise mapping
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use super::asm_to_binary::{UnresolvedRef, RefType};
 
 /// Represents a symbol reference that needs to be resolved in the second pass
@@ -12,6 +12,7 @@ struct SymbolRef {
     is_relative: bool,  // true for relative branches, false for absolute
     ref_size: u8,       // 1 for 8-bit offset, 2 for 16-bit address
     addend: i16,        // Add/sub offset applied to the resolved symbol address
+    line: usize,        // ASM source line of the reference
 }
 
 /// M6809 binary code emitter with address and symbol tracking
@@ -25,7 +26,12 @@ pub struct BinaryEmitter {
     current_line: usize,                    // Current VPy source line
     object_mode: bool,                      // Object mode: allow unresolved symbols
     unresolved_refs: Vec<UnresolvedRef>,    // Unresolved symbols (for object mode)
+    label_refs: Vec<UnresolvedRef>,         // Absolute references to own labels (object mode, relocated by the linker)
+    asm_line: usize,                        // Current ASM source line
+    label_lines: HashMap<String, usize>,    // Label (uppercase) -> line defining it
     use_long_branches: bool,                // Multi-bank mode: use long branches (LBEQ/LBNE/LBRA) instead of short
+    direct_page: Option<u8>,                // SETDP page for automatic direct addressing (None: always extended)
+    direct_page_hits: BTreeMap<u8, usize>,  // Accesses made direct by SETDP, per page
 }
 
 impl BinaryEmitter {
@@ -41,7 +47,12 @@ impl BinaryEmitter {
             current_line: 0,
             object_mode: false,
             unresolved_refs: Vec::new(),
+            label_refs: Vec::new(),
+            asm_line: 0,
+            label_lines: HashMap::new(),
             use_long_branches: false,
+            direct_page: Some(0),
+            direct_page_hits: BTreeMap::new(),
         }
     }
     
@@ -55,12 +66,53 @@ impl BinaryEmitter {
     pub fn set_long_branches(&mut self, enabled: bool) {
         self.use_long_branches = enabled;
     }
+
+    /// Sets the page assumed in DP (SETDP); addresses on it use direct addressing
+    pub fn set_direct_page(&mut self, page: Option<u8>) {
+        self.direct_page = page;
+    }
+
+    /// Page assumed in DP, if any
+    pub fn direct_page(&self) -> Option<u8> {
+        self.direct_page
+    }
+
+    /// Counts an access that SETDP turned into direct addressing
+    pub(crate) fn record_direct_page_hit(&mut self, page: u8) {
+        *self.direct_page_hits.entry(page).or_insert(0) += 1;
+    }
+
+    /// Accesses made direct by SETDP, per page ($00 is the default page and is not counted)
+    pub fn direct_page_hits(&self) -> &BTreeMap<u8, usize> {
+        &self.direct_page_hits
+    }
+
+    /// Whether label branches are promoted to their long forms
+    pub fn long_branches(&self) -> bool {
+        self.use_long_branches
+    }
     
     /// Get list of unresolved symbols (for object mode)
     pub fn take_unresolved_refs(&mut self) -> Vec<UnresolvedRef> {
         std::mem::take(&mut self.unresolved_refs)
     }
 
+    /// Absolute references to labels of this source (object mode): their
+    /// values move with the code when the linker places it
+    pub fn take_label_refs(&mut self) -> Vec<UnresolvedRef> {
+        std::mem::take(&mut self.label_refs)
+    }
+
+    /// Sets the ASM source line being assembled (for object-mode references)
+    pub fn set_asm_line(&mut self, line: usize) {
+        self.asm_line = line;
+    }
+
+    /// Labels (uppercase) with the ASM line defining them
+    pub fn label_lines(&self) -> &HashMap<String, usize> {
+        &self.label_lines
+    }
+
     /// Sets the current source line (for debug mapping)
     pub fn set_source_line(&mut self, line: usize) {
         self.current_line = line;
@@ -72,7 +124,7 @@ impl BinaryEmitter {
     }
 
     /// Records bidirectional line ↔ offset mapping
-    fn record_line_mapping(&mut self) {
+    pub(crate) fn record_line_mapping(&mut self) {
         let offset = self.current_offset();
         self.line_to_offset.insert(self.current_line, offset);
         self.offset_to_line.insert(offset, self.current_line);
@@ -99,6 +151,7 @@ impl BinaryEmitter {
         // Store both original and uppercase variants for case-insensitive lookup
         self.symbols.insert(label.to_string(), label_address);
         self.symbols.insert(label.to_uppercase(), label_address);
+        self.label_lines.entry(label.to_uppercase()).or_insert(self.asm_line);
     }
 
     /// Records a symbol reference to resolve in the second pass
@@ -124,6 +177,7 @@ impl BinaryEmitter {
             is_relative,
             ref_size,
             addend,
+            line: self.asm_line,
         });
     }
 
@@ -1191,6 +1245,22 @@ impl BinaryEmitter {
         for sym_ref in &self.symbol_refs {
             // Search first in local symbols (case-sensitive)
             let target_addr_opt = if let Some(&addr) = self.symbols.get(&sym_ref.symbol) {
+                if self.object_mode && !sym_ref.is_relative {
+                    // The label moves with the code: the linker patches it again
+                    if sym_ref.ref_size != 2 {
+                        return Err(format!(
+                            "Line {}: 8-bit reference to label '{}' cannot be relocated",
+                            sym_ref.line, sym_ref.symbol
+                        ));
+                    }
+                    self.label_refs.push(UnresolvedRef {
+                        symbol: sym_ref.symbol.to_uppercase(),
+                        offset: sym_ref.offset,
+                        ref_type: RefType::Absolute16,
+                        addend: sym_ref.addend,
+                        line: sym_ref.line,
+                    });
+                }
                 Some(addr)
             } else {
                 // Search in equates with uppercase (BIOS/INCLUDE symbols are uppercase)
@@ -1220,6 +1290,8 @@ impl BinaryEmitter {
                             symbol: upper_symbol.clone(),
                             offset: sym_ref.offset,
                             ref_type,
+                            addend: sym_ref.addend,
+                            line: sym_ref.line,
                         });
                         
                         // Use placeholder 0x0000 (already emitted during first pass)
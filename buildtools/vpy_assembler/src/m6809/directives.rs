@@ -0,0 +1,323 @@
+//! Source-level directives: macros, conditional assembly, REPT and STRUCT
+//!
+//! Runs before the assembler passes and turns them into plain lines.
+//! Syntax follows lwasm:
+//! - `name MACRO` ... `ENDM`; `\1`-`\9` are the arguments and `\@` a number
+//!   unique to each expansion. Labels starting with `@` are local to one
+//!   expansion.
+//! - `IF`/`IFNE expr`, `IFEQ`, `IFGT`, `IFGE`, `IFLT`, `IFLE`, `IFDEF sym`,
+//!   `IFNDEF sym`, `ELSE`, `ENDIF` (or `ENDC`)
+//! - `REPT count` ... `ENDR`
+//! - `name STRUCT` ... `ENDSTRUCT`: `field RMB n` lines define `name.field`
+//!   as the field offset and `sizeof{name}` as the total size
+//!
+//! ALIGN, INCBIN and SETDP emit bytes or change assembler state, so they are
+//! handled by the passes themselves.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+
+use super::asm_to_binary::{evaluate_expression, parse_equ_directive_raw, process_include_file, resolve_include_path};
+
+/// Nesting of macro expansions, REPT blocks and includes deeper than this is
+/// reported as recursion
+const MAX_DEPTH: usize = 32;
+
+/// A source line and its 1-based line number, for error messages
+type Line = (usize, String);
+
+struct Preprocessor {
+    /// EQU values known so far, for IF/REPT expressions
+    equates: HashMap<String, u16>,
+    /// Labels defined so far, for IFDEF
+    labels: HashSet<String>,
+    /// Macro bodies by uppercase name
+    macros: HashMap<String, Vec<Line>>,
+    /// Structure sizes by uppercase name
+    structs: HashMap<String, u16>,
+    expansions: usize,
+}
+
+/// One IF level: whether its lines are assembled, whether the enclosing
+/// level is, and whether a branch has already been taken
+struct Conditional {
+    active: bool,
+    parent_active: bool,
+    taken: bool,
+}
+
+/// Expands macros, REPT blocks, STRUCT definitions and conditionals.
+/// `equates` holds the symbols known before the source (BIOS, includes).
+/// Also returns the source line each output line came from: macro and
+/// include contents map to the line that invoked them.
+pub fn preprocess(source: &str, equates: &HashMap<String, u16>) -> Result<(String, Vec<usize>), String> {
+    let mut pre = Preprocessor {
+        equates: equates.clone(),
+        labels: HashSet::new(),
+        macros: HashMap::new(),
+        structs: HashMap::new(),
+        expansions: 0,
+    };
+    let lines: Vec<Line> = source.lines().enumerate().map(|(i, l)| (i + 1, l.to_string())).collect();
+    let mut out = Vec::with_capacity(lines.len());
+    pre.expand(&lines, &mut out, 0)?;
+    let numbers = out.iter().map(|(number, _)| *number).collect();
+    let mut text = out.into_iter().map(|(_, line)| line).collect::<Vec<_>>().join("\n");
+    text.push('\n');
+    Ok((text, numbers))
+}
+
+/// Comment-free code of a line
+fn code_of(line: &str) -> &str {
+    match line.find(';') {
+        Some(idx) => &line[..idx],
+        None => line,
+    }
+    .trim()
+}
+
+/// Splits code into an optional `label:` and the first word with its operand
+fn split_statement(code: &str) -> (Option<&str>, &str, &str) {
+    let (first, rest) = code.split_once(char::is_whitespace).unwrap_or((code, ""));
+    if let Some(label) = first.strip_suffix(':') {
+        let rest = rest.trim();
+        let (word, operand) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        return (Some(label), word, operand.trim());
+    }
+    (None, first, rest.trim())
+}
+
+/// `name MACRO` / `name STRUCT` (lwasm) or `MACRO name` (asm6809)
+fn block_name<'a>(code: &'a str, keyword: &str) -> Option<&'a str> {
+    let mut words = code.split_whitespace();
+    let (first, second) = (words.next()?, words.next());
+    if first.eq_ignore_ascii_case(keyword) {
+        return second;
+    }
+    second.filter(|w| w.eq_ignore_ascii_case(keyword)).map(|_| first.trim_end_matches(':'))
+}
+
+impl Preprocessor {
+    fn expand(&mut self, lines: &[Line], out: &mut Vec<Line>, depth: usize) -> Result<(), String> {
+        if depth > MAX_DEPTH {
+            let number = lines.first().map_or(0, |l| l.0);
+            return Err(format!("Error at line {}: macros or INCLUDEs nest too deeply (recursive?)", number));
+        }
+        let mut conditionals: Vec<Conditional> = Vec::new();
+        let mut i = 0;
+        while i < lines.len() {
+            let (number, line) = &lines[i];
+            let number = *number;
+            let fail = |e: String| format!("Error at line {}: {} (code: '{}')", number, e, line.trim());
+            let code = code_of(line);
+            let (label, word, operand) = split_statement(code);
+            let keyword = word.to_ascii_uppercase();
+            let active = conditionals.last().is_none_or(|c| c.active);
+            i += 1;
+
+            // Conditionals are tracked even inside skipped blocks, for nesting
+            match keyword.as_str() {
+                "IF" | "IFNE" | "IFEQ" | "IFGT" | "IFGE" | "IFLT" | "IFLE" | "IFDEF" | "IFNDEF" => {
+                    let condition = active && self.condition(&keyword, operand).map_err(fail)?;
+                    conditionals.push(Conditional { active: condition, parent_active: active, taken: condition });
+                    continue;
+                }
+                "ELSE" => {
+                    let c = conditionals.last_mut().ok_or_else(|| fail("ELSE without IF".to_string()))?;
+                    c.active = c.parent_active && !c.taken;
+                    c.taken = true;
+                    continue;
+                }
+                "ENDIF" | "ENDC" => {
+                    conditionals.pop().ok_or_else(|| fail(format!("{} without IF", keyword)))?;
+                    continue;
+                }
+                _ => {}
+            }
+            if !active {
+                continue;
+            }
+
+            if let Some(name) = block_name(code, "MACRO") {
+                let (body, end) = block(lines, i, &["MACRO"], &["ENDM"]).ok_or_else(|| fail("MACRO without ENDM".to_string()))?;
+                self.macros.insert(name.to_ascii_uppercase(), body);
+                i = end;
+                continue;
+            }
+            if let Some(name) = block_name(code, "STRUCT") {
+                let (body, end) = block(lines, i, &["STRUCT"], &["ENDSTRUCT", "ENDS"])
+                    .ok_or_else(|| fail("STRUCT without ENDSTRUCT".to_string()))?;
+                self.define_struct(name, number, &body, out)?;
+                i = end;
+                continue;
+            }
+            match keyword.as_str() {
+                "REPT" => {
+                    let (body, end) = block(lines, i, &["REPT"], &["ENDR"]).ok_or_else(|| fail("REPT without ENDR".to_string()))?;
+                    let count = self.value(operand).map_err(fail)?;
+                    for _ in 0..count {
+                        self.expand(&body, out, depth + 1)?;
+                    }
+                    i = end;
+                    continue;
+                }
+                "ENDM" | "ENDR" | "ENDSTRUCT" | "ENDS" => return Err(fail(format!("{} without matching block", keyword))),
+                "INCLUDE" => self.include(operand, number, out, depth)?,
+                _ => {}
+            }
+
+            if let Some(body) = self.macros.get(&keyword).cloned() {
+                if let Some(label) = label {
+                    self.define_label(label, number, out);
+                }
+                self.expansions += 1;
+                let args: Vec<&str> = if operand.is_empty() { Vec::new() } else { operand.split(',').map(str::trim).collect() };
+                let expanded: Vec<Line> = body.iter().map(|(_, l)| (number, substitute(l, &args, self.expansions))).collect();
+                self.expand(&expanded, out, depth + 1)?;
+                continue;
+            }
+
+            if let Some((name, expr)) = parse_equ_directive_raw(code) {
+                if let Ok(value) = evaluate_expression(&expr, &self.equates) {
+                    self.equates.insert(name, value);
+                }
+            } else if let Some(label) = label {
+                self.labels.insert(label.to_ascii_uppercase());
+            }
+            out.push((number, line.clone()));
+        }
+        if !conditionals.is_empty() {
+            return Err(format!("Error: {} IF block(s) without ENDIF", conditionals.len()));
+        }
+        Ok(())
+    }
+
+    fn define_label(&mut self, label: &str, number: usize, out: &mut Vec<Line>) {
+        self.labels.insert(label.to_ascii_uppercase());
+        out.push((number, format!("{}:", label)));
+    }
+
+    fn value(&self, expr: &str) -> Result<u16, String> {
+        evaluate_expression(expr, &self.equates).map_err(|e| match e.strip_prefix("SYMBOL:") {
+            Some(symbol) => format!("Undefined symbol in expression: {}", symbol),
+            None => e,
+        })
+    }
+
+    fn condition(&self, keyword: &str, operand: &str) -> Result<bool, String> {
+        let defined = |name: &str| {
+            let name = name.trim().to_ascii_uppercase();
+            self.equates.contains_key(&name) || self.labels.contains(&name) || self.macros.contains_key(&name)
+        };
+        Ok(match keyword {
+            "IFDEF" => defined(operand),
+            "IFNDEF" => !defined(operand),
+            _ => {
+                let value = self.value(operand)? as i16;
+                match keyword {
+                    "IFEQ" => value == 0,
+                    "IFGT" => value > 0,
+                    "IFGE" => value >= 0,
+                    "IFLT" => value < 0,
+                    "IFLE" => value <= 0,
+                    _ => value != 0,
+                }
+            }
+        })
+    }
+
+    /// Included files contribute their EQU, MACRO and STRUCT definitions;
+    /// code in them is still ignored, as before
+    fn include(&mut self, operand: &str, number: usize, out: &mut Vec<Line>, depth: usize) -> Result<(), String> {
+        let path = operand.trim().trim_matches('"');
+        let _ = process_include_file(path, &mut self.equates);
+        let Some(content) = resolve_include_path(path).and_then(|p| fs::read_to_string(p).ok()) else {
+            return Ok(());
+        };
+        let lines: Vec<Line> = content.lines().enumerate().map(|(i, l)| (i + 1, l.to_string())).collect();
+        let mut included = Vec::new();
+        self.expand(&lines, &mut included, depth + 1).map_err(|e| format!("{} (in {})", e, path))?;
+        out.extend(
+            included
+                .into_iter()
+                .filter(|(_, l)| parse_equ_directive_raw(code_of(l)).is_some())
+                .map(|(_, l)| (number, l)),
+        );
+        Ok(())
+    }
+
+    /// Emits `name.field EQU offset` for each field and `sizeof{name} EQU size`
+    fn define_struct(&mut self, name: &str, start: usize, body: &[Line], out: &mut Vec<Line>) -> Result<(), String> {
+        let mut offset: u16 = 0;
+        for (number, line) in body {
+            let code = code_of(line);
+            if code.is_empty() {
+                continue;
+            }
+            let mut words = code.split_whitespace();
+            let field = words.next().unwrap_or_default().trim_end_matches(':');
+            let kind = words.next().unwrap_or_default().to_ascii_uppercase();
+            let count = words.next().unwrap_or("1");
+            let size = match kind.as_str() {
+                "RMB" | "DS" => self.value(count),
+                "FCB" | "DB" => Ok(1),
+                "FDB" | "DW" => Ok(2),
+                other => self.structs.get(other).copied().ok_or_else(|| format!("Unknown field type '{}'", other)),
+            }
+            .map_err(|e| format!("Error at line {}: {} in STRUCT {}", number, e, name))?;
+            self.emit_equ(&format!("{}.{}", name, field), offset, start, out);
+            offset = offset.wrapping_add(size);
+        }
+        self.emit_equ(&format!("sizeof{{{}}}", name), offset, start, out);
+        self.structs.insert(name.to_ascii_uppercase(), offset);
+        Ok(())
+    }
+
+    fn emit_equ(&mut self, name: &str, value: u16, line: usize, out: &mut Vec<Line>) {
+        self.equates.insert(name.to_ascii_uppercase(), value);
+        out.push((line, format!("{} EQU ${:04X}", name, value)));
+    }
+}
+
+/// Body of a block starting at `start` up to its matching end keyword, and
+/// the index after the end line. Nested blocks of the same kind are kept.
+fn block(lines: &[Line], start: usize, open: &[&str], close: &[&str]) -> Option<(Vec<Line>, usize)> {
+    let mut nesting = 0;
+    for (i, (_, line)) in lines.iter().enumerate().skip(start) {
+        let code = code_of(line);
+        let first = split_statement(code).1.to_ascii_uppercase();
+        if open.iter().any(|k| block_name(code, k).is_some() || first == *k) {
+            nesting += 1;
+        } else if close.contains(&first.as_str()) {
+            if nesting == 0 {
+                return Some((lines[start..i].to_vec(), i + 1));
+            }
+            nesting -= 1;
+        }
+    }
+    None
+}
+
+/// Replaces `\1`-`\9` and `\@` in a macro line and renames `@local` labels
+fn substitute(line: &str, args: &[&str], expansion: usize) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek().copied()) {
+            ('\\', Some(d @ '1'..='9')) => {
+                chars.next();
+                let index = d as usize - '1' as usize;
+                out.push_str(args.get(index).copied().unwrap_or_default());
+            }
+            ('\\', Some('@')) => {
+                chars.next();
+                out.push_str(&expansion.to_string());
+            }
+            ('@', Some(n)) if n.is_alphanumeric() || n == '_' => {
+                out.push_str(&format!("_M{}_", expansion));
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
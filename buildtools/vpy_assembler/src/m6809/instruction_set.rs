@@ -0,0 +1,553 @@
+//! MC6809 instruction set: opcode table and operand encoder
+//!
+//! Every documented 6809 instruction in every addressing mode it supports
+//! (Motorola MC6809 datasheet, programming aid tables). Page-2/3 opcodes are
+//! stored with their prefix byte, e.g. `$108E` is `LDY #`.
+//!
+//! Operand syntax (lwasm-compatible):
+//! - `#expr` immediate, `<expr` forced direct, `>expr` forced extended;
+//!   a bare address uses direct mode when it is on the SETDP page ($00 by default)
+//! - `,R` `n,R` `A,R` `B,R` `D,R` `,R+` `,R++` `,-R` `,--R` `n,PCR` indexed
+//!   (`<n,R` / `>n,R` force an 8/16-bit offset), `[...]` indirect, `[addr]`
+//!   extended indirect
+//! - branch targets are labels or addresses
+//! - `*` is the address of the current instruction (`BRA *`, `LDX #*+5`)
+
+use std::collections::HashMap;
+
+use super::asm_to_binary::{evaluate_expression, expand_local_label, parse_symbol_and_addend};
+use super::binary_emitter::BinaryEmitter;
+
+/// How an instruction's operand is written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Form {
+    /// Inherent, immediate, direct, indexed and/or extended (see the opcode columns)
+    General,
+    /// 8-bit PC-relative branch
+    Branch8,
+    /// 16-bit PC-relative branch
+    Branch16,
+    /// TFR/EXG register pair
+    RegisterPair,
+    /// PSHS/PULS register list (may include U)
+    SystemStack,
+    /// PSHU/PULU register list (may include S)
+    UserStack,
+}
+
+/// One mnemonic and its opcode in each addressing mode
+#[derive(Debug, Clone, Copy)]
+pub struct Opcode {
+    pub mnemonic: &'static str,
+    pub form: Form,
+    pub inherent: Option<u16>,
+    pub immediate: Option<u16>,
+    pub direct: Option<u16>,
+    pub indexed: Option<u16>,
+    pub extended: Option<u16>,
+    /// Width of the immediate operand in bytes
+    pub immediate_size: u8,
+}
+
+const fn entry(mnemonic: &'static str, form: Form, modes: [i32; 5], immediate_size: u8) -> Opcode {
+    const fn opt(v: i32) -> Option<u16> {
+        if v < 0 { None } else { Some(v as u16) }
+    }
+    Opcode {
+        mnemonic,
+        form,
+        inherent: opt(modes[0]),
+        immediate: opt(modes[1]),
+        direct: opt(modes[2]),
+        indexed: opt(modes[3]),
+        extended: opt(modes[4]),
+        immediate_size,
+    }
+}
+
+const NO: i32 = -1;
+
+/// Inherent only
+const fn inherent(m: &'static str, op: i32) -> Opcode {
+    entry(m, Form::General, [op, NO, NO, NO, NO], 0)
+}
+/// Immediate/direct/indexed/extended; the column opcodes are op, op+$10, op+$20, op+$30
+const fn alu(m: &'static str, op: i32, size: u8) -> Opcode {
+    entry(m, Form::General, [NO, op, op + 0x10, op + 0x20, op + 0x30], size)
+}
+/// Like `alu` without immediate mode (stores, JSR); `op` is the unused immediate slot
+const fn store(m: &'static str, op: i32) -> Opcode {
+    entry(m, Form::General, [NO, NO, op + 0x10, op + 0x20, op + 0x30], 0)
+}
+/// Read-modify-write memory: direct op, indexed op+$60, extended op+$70
+const fn memory(m: &'static str, op: i32) -> Opcode {
+    entry(m, Form::General, [NO, NO, op, op + 0x60, op + 0x70], 0)
+}
+const fn immediate8(m: &'static str, op: i32) -> Opcode {
+    entry(m, Form::General, [NO, op, NO, NO, NO], 1)
+}
+const fn indexed(m: &'static str, op: i32) -> Opcode {
+    entry(m, Form::General, [NO, NO, NO, op, NO], 0)
+}
+const fn special(m: &'static str, form: Form, op: i32) -> Opcode {
+    entry(m, form, [op, NO, NO, NO, NO], 0)
+}
+
+pub const OPCODES: &[Opcode] = &[
+    // Inherent
+    inherent("NOP", 0x12), inherent("SYNC", 0x13), inherent("DAA", 0x19), inherent("SEX", 0x1D),
+    inherent("RTS", 0x39), inherent("ABX", 0x3A), inherent("RTI", 0x3B), inherent("MUL", 0x3D),
+    inherent("SWI", 0x3F), inherent("SWI2", 0x103F), inherent("SWI3", 0x113F),
+    inherent("NEGA", 0x40), inherent("COMA", 0x43), inherent("LSRA", 0x44), inherent("RORA", 0x46),
+    inherent("ASRA", 0x47), inherent("ASLA", 0x48), inherent("LSLA", 0x48), inherent("ROLA", 0x49),
+    inherent("DECA", 0x4A), inherent("INCA", 0x4C), inherent("TSTA", 0x4D), inherent("CLRA", 0x4F),
+    inherent("NEGB", 0x50), inherent("COMB", 0x53), inherent("LSRB", 0x54), inherent("RORB", 0x56),
+    inherent("ASRB", 0x57), inherent("ASLB", 0x58), inherent("LSLB", 0x58), inherent("ROLB", 0x59),
+    inherent("DECB", 0x5A), inherent("INCB", 0x5C), inherent("TSTB", 0x5D), inherent("CLRB", 0x5F),
+    // Read-modify-write memory
+    memory("NEG", 0x00), memory("COM", 0x03), memory("LSR", 0x04), memory("ROR", 0x06),
+    memory("ASR", 0x07), memory("ASL", 0x08), memory("LSL", 0x08), memory("ROL", 0x09),
+    memory("DEC", 0x0A), memory("INC", 0x0C), memory("TST", 0x0D), memory("JMP", 0x0E),
+    memory("CLR", 0x0F),
+    // 8-bit accumulator A
+    alu("SUBA", 0x80, 1), alu("CMPA", 0x81, 1), alu("SBCA", 0x82, 1), alu("ANDA", 0x84, 1),
+    alu("BITA", 0x85, 1), alu("LDA", 0x86, 1), store("STA", 0x87), alu("EORA", 0x88, 1),
+    alu("ADCA", 0x89, 1), alu("ORA", 0x8A, 1), alu("ADDA", 0x8B, 1),
+    // 8-bit accumulator B
+    alu("SUBB", 0xC0, 1), alu("CMPB", 0xC1, 1), alu("SBCB", 0xC2, 1), alu("ANDB", 0xC4, 1),
+    alu("BITB", 0xC5, 1), alu("LDB", 0xC6, 1), store("STB", 0xC7), alu("EORB", 0xC8, 1),
+    alu("ADCB", 0xC9, 1), alu("ORB", 0xCA, 1), alu("ADDB", 0xCB, 1),
+    // 16-bit
+    alu("SUBD", 0x83, 2), alu("ADDD", 0xC3, 2), alu("LDD", 0xCC, 2), store("STD", 0xCD),
+    alu("CMPX", 0x8C, 2), alu("CPX", 0x8C, 2), alu("LDX", 0x8E, 2), store("STX", 0x8F),
+    alu("LDU", 0xCE, 2), store("STU", 0xCF), store("JSR", 0x8D),
+    alu("CMPD", 0x1083, 2), alu("CPD", 0x1083, 2), alu("CMPY", 0x108C, 2), alu("CPY", 0x108C, 2),
+    alu("LDY", 0x108E, 2), store("STY", 0x108F), alu("LDS", 0x10CE, 2), store("STS", 0x10CF),
+    alu("CMPU", 0x1183, 2), alu("CMPS", 0x118C, 2),
+    // Condition codes, LEA
+    immediate8("ORCC", 0x1A), immediate8("ANDCC", 0x1C), immediate8("CWAI", 0x3C),
+    indexed("LEAX", 0x30), indexed("LEAY", 0x31), indexed("LEAS", 0x32), indexed("LEAU", 0x33),
+    // Register transfer and stacks
+    special("EXG", Form::RegisterPair, 0x1E), special("TFR", Form::RegisterPair, 0x1F),
+    special("PSHS", Form::SystemStack, 0x34), special("PULS", Form::SystemStack, 0x35),
+    special("PSHU", Form::UserStack, 0x36), special("PULU", Form::UserStack, 0x37),
+    // Short branches
+    special("BRA", Form::Branch8, 0x20), special("BRN", Form::Branch8, 0x21),
+    special("BHI", Form::Branch8, 0x22), special("BLS", Form::Branch8, 0x23),
+    special("BCC", Form::Branch8, 0x24), special("BHS", Form::Branch8, 0x24),
+    special("BCS", Form::Branch8, 0x25), special("BLO", Form::Branch8, 0x25),
+    special("BNE", Form::Branch8, 0x26), special("BEQ", Form::Branch8, 0x27),
+    special("BVC", Form::Branch8, 0x28), special("BVS", Form::Branch8, 0x29),
+    special("BPL", Form::Branch8, 0x2A), special("BMI", Form::Branch8, 0x2B),
+    special("BGE", Form::Branch8, 0x2C), special("BLT", Form::Branch8, 0x2D),
+    special("BGT", Form::Branch8, 0x2E), special("BLE", Form::Branch8, 0x2F),
+    special("BSR", Form::Branch8, 0x8D),
+    // Long branches
+    special("LBRA", Form::Branch16, 0x16), special("LBSR", Form::Branch16, 0x17),
+    special("LBRN", Form::Branch16, 0x1021), special("LBHI", Form::Branch16, 0x1022),
+    special("LBLS", Form::Branch16, 0x1023), special("LBCC", Form::Branch16, 0x1024),
+    special("LBHS", Form::Branch16, 0x1024), special("LBCS", Form::Branch16, 0x1025),
+    special("LBLO", Form::Branch16, 0x1025), special("LBNE", Form::Branch16, 0x1026),
+    special("LBEQ", Form::Branch16, 0x1027), special("LBVC", Form::Branch16, 0x1028),
+    special("LBVS", Form::Branch16, 0x1029), special("LBPL", Form::Branch16, 0x102A),
+    special("LBMI", Form::Branch16, 0x102B), special("LBGE", Form::Branch16, 0x102C),
+    special("LBLT", Form::Branch16, 0x102D), special("LBGT", Form::Branch16, 0x102E),
+    special("LBLE", Form::Branch16, 0x102F),
+];
+
+/// Table entry for a mnemonic (case-insensitive)
+pub fn lookup(mnemonic: &str) -> Option<&'static Opcode> {
+    OPCODES.iter().find(|op| op.mnemonic.eq_ignore_ascii_case(mnemonic))
+}
+
+
+/// Assembly context shared by every operand
+struct Context<'a> {
+    equates: &'a HashMap<String, u16>,
+    last_global: &'a str,
+    /// Address of the instruction being encoded
+    pc: u16,
+}
+
+/// An operand value: a number, or a symbol resolved in the second pass
+enum Value {
+    Known(i32),
+    Symbol(String, i16),
+}
+
+/// Encodes one CPU instruction. Returns None when `mnemonic` is not a 6809
+/// instruction (directives are handled by the caller).
+pub fn emit_instruction(
+    emitter: &mut BinaryEmitter,
+    mnemonic: &str,
+    operand: &str,
+    equates: &HashMap<String, u16>,
+    last_global: &str,
+) -> Option<Result<(), String>> {
+    let op = lookup(mnemonic)?;
+    let ctx = Context { equates, last_global, pc: emitter.current_address };
+    let operand = operand.trim();
+    Some(match op.form {
+        Form::General => encode_general(emitter, op, operand, &ctx),
+        Form::Branch8 | Form::Branch16 => encode_branch(emitter, op, operand, &ctx),
+        Form::RegisterPair => register_pair(operand).map(|postbyte| {
+            emit_opcode(emitter, op.inherent.unwrap_or_default());
+            emitter.emit(postbyte);
+        }),
+        Form::SystemStack | Form::UserStack => register_list(operand, op.form).map(|postbyte| {
+            emit_opcode(emitter, op.inherent.unwrap_or_default());
+            emitter.emit(postbyte);
+        }),
+    })
+}
+
+/// Emits a 1-byte opcode or a prefixed page-2/3 opcode
+fn emit_opcode(emitter: &mut BinaryEmitter, opcode: u16) {
+    emitter.record_line_mapping();
+    if opcode > 0xFF {
+        emitter.emit((opcode >> 8) as u8);
+    }
+    emitter.emit(opcode as u8);
+}
+
+fn opcode_len(opcode: u16) -> u16 {
+    if opcode > 0xFF { 2 } else { 1 }
+}
+
+fn encode_general(emitter: &mut BinaryEmitter, op: &Opcode, operand: &str, ctx: &Context) -> Result<(), String> {
+    let unsupported = |mode: &str| format!("{} does not support {} addressing", op.mnemonic, mode);
+
+    if let Some(opcode) = op.inherent {
+        if !operand.is_empty() {
+            return Err(format!("{} takes no operand (got '{}')", op.mnemonic, operand));
+        }
+        emit_opcode(emitter, opcode);
+        return Ok(());
+    }
+    if operand.is_empty() {
+        return Err(format!("{} needs an operand", op.mnemonic));
+    }
+    // "ASL A" style accumulator operands map to the inherent forms
+    if op.immediate.is_none() && (operand.eq_ignore_ascii_case("A") || operand.eq_ignore_ascii_case("B")) {
+        let accumulator = format!("{}{}", op.mnemonic, operand.to_ascii_uppercase());
+        if let Some(inherent) = lookup(&accumulator).and_then(|o| o.inherent) {
+            emit_opcode(emitter, inherent);
+            return Ok(());
+        }
+    }
+
+    if let Some(expr) = operand.strip_prefix('#') {
+        let opcode = op.immediate.ok_or_else(|| unsupported("immediate"))?;
+        let value = evaluate(expr, ctx)?;
+        emit_opcode(emitter, opcode);
+        return emit_value(emitter, value, op.immediate_size, expr);
+    }
+
+    if operand.starts_with('[') || operand.contains(',') {
+        let opcode = op.indexed.ok_or_else(|| unsupported("indexed"))?;
+        // Address just past the postbyte, for PC-relative offsets
+        let after_postbyte = emitter.current_address.wrapping_add(opcode_len(opcode) + 1);
+        let (postbyte, extra) = parse_indexed(operand, after_postbyte, ctx)?;
+        emit_opcode(emitter, opcode);
+        emitter.emit(postbyte);
+        return match extra {
+            Extra::None => Ok(()),
+            Extra::Byte(value) => emit_value(emitter, value, 1, operand),
+            Extra::Word(value) => emit_value(emitter, value, 2, operand),
+            Extra::Relative(symbol, addend, size) => {
+                emitter.add_symbol_ref_with_addend(&symbol, true, size, addend);
+                for _ in 0..size {
+                    emitter.emit(0x00);
+                }
+                Ok(())
+            }
+        };
+    }
+
+    let (expr, forced) = match operand.as_bytes()[0] {
+        b'<' => (&operand[1..], Some(false)),
+        b'>' => (&operand[1..], Some(true)),
+        _ => (operand, None),
+    };
+    let value = evaluate(expr, ctx)?;
+    let extended = match (&value, forced) {
+        (_, Some(extended)) => extended,
+        (Value::Known(addr), None) => {
+            !(0..=0xFFFF).contains(addr) || emitter.direct_page() != Some((*addr >> 8) as u8)
+        }
+        (Value::Symbol(..), None) => true,
+    };
+    if extended {
+        emit_opcode(emitter, op.extended.ok_or_else(|| unsupported("extended"))?);
+        emit_value(emitter, value, 2, expr)
+    } else {
+        emit_opcode(emitter, op.direct.ok_or_else(|| unsupported("direct"))?);
+        if let (Value::Known(addr), None) = (&value, forced) {
+            let page = (*addr >> 8) as u8;
+            if page != 0 {
+                emitter.record_direct_page_hit(page);
+            }
+        }
+        match value {
+            // <$C880 keeps the low byte, as with SETDP $C8
+            Value::Known(addr) => {
+                emitter.emit(addr as u8);
+                Ok(())
+            }
+            symbol => emit_value(emitter, symbol, 1, expr),
+        }
+    }
+}
+
+/// Emits an 8- or 16-bit operand, or a placeholder with a symbol reference
+fn emit_value(emitter: &mut BinaryEmitter, value: Value, size: u8, text: &str) -> Result<(), String> {
+    match value {
+        Value::Known(v) if size == 1 => {
+            if !(-128..=255).contains(&v) {
+                return Err(format!("Value out of 8-bit range: {} ({})", v, text.trim()));
+            }
+            emitter.emit(v as u8);
+        }
+        Value::Known(v) => {
+            if !(-32768..=65535).contains(&v) {
+                return Err(format!("Value out of 16-bit range: {} ({})", v, text.trim()));
+            }
+            emitter.emit_word(v as u16);
+        }
+        Value::Symbol(symbol, addend) => {
+            emitter.add_symbol_ref_with_addend(&symbol, false, size, addend);
+            for _ in 0..size {
+                emitter.emit(0x00);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Evaluates an operand expression; unknown labels are left for the second pass
+fn evaluate(expr: &str, ctx: &Context) -> Result<Value, String> {
+    let expr = expr.trim();
+    if expr.is_empty() {
+        return Err("Missing operand value".to_string());
+    }
+    if let Some(rest) = expr.strip_prefix('*') {
+        return evaluate(&format!("${:04X}{}", ctx.pc, rest), ctx);
+    }
+    let bytes = expr.as_bytes();
+    if bytes.len() == 3 && bytes[0] == b'\'' && bytes[2] == b'\'' {
+        return Ok(Value::Known(bytes[1] as i32));
+    }
+    if let Some(digits) = expr.strip_prefix('%') {
+        return i32::from_str_radix(digits, 2).map(Value::Known).map_err(|_| format!("Invalid binary value: {}", expr));
+    }
+    if let Some(rest) = expr.strip_prefix('-') {
+        return match evaluate(rest, ctx)? {
+            Value::Known(v) => Ok(Value::Known(-v)),
+            Value::Symbol(..) => Err(format!("Cannot negate an unresolved symbol: {}", expr)),
+        };
+    }
+    match evaluate_expression(expr, ctx.equates) {
+        Ok(v) => Ok(Value::Known(v as i32)),
+        Err(e) if e.starts_with("SYMBOL:") => {
+            let (symbol, addend) = parse_symbol_and_addend(&e)?;
+            if symbol.is_empty() || !symbol.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '.') {
+                return Err(format!("Invalid operand: {}", expr));
+            }
+            if ["A", "B", "D", "X", "Y", "U", "S", "PC", "CC", "DP"].contains(&symbol.to_ascii_uppercase().as_str()) {
+                return Err(format!("Register {} is not an address", symbol));
+            }
+            Ok(Value::Symbol(expand_local_label(&symbol, ctx.last_global), addend))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Bytes following an indexed postbyte
+enum Extra {
+    None,
+    Byte(Value),
+    Word(Value),
+    /// PC-relative offset to a symbol, resolved in the second pass (size in bytes)
+    Relative(String, i16, u8),
+}
+
+/// Indexed postbyte and the offset bytes that follow it. `after_postbyte` is
+/// the address of the first offset byte, used for `n,PCR` with a known target.
+fn parse_indexed(operand: &str, after_postbyte: u16, ctx: &Context) -> Result<(u8, Extra), String> {
+    let (inner, indirect) = match operand.strip_prefix('[') {
+        Some(rest) => (rest.strip_suffix(']').ok_or_else(|| format!("Missing ']' in {}", operand))?.trim(), true),
+        None => (operand, false),
+    };
+    let indirect_bit = if indirect { 0x10 } else { 0x00 };
+
+    let Some((offset, register)) = inner.rsplit_once(',') else {
+        // [address]: extended indirect
+        return Ok((0x9F, Extra::Word(evaluate(inner, ctx)?)));
+    };
+    let (offset, register) = (offset.trim(), register.trim().to_ascii_uppercase());
+    let register_bits = |name: &str| match name {
+        "X" => Ok(0x00),
+        "Y" => Ok(0x20),
+        "U" => Ok(0x40),
+        "S" => Ok(0x60),
+        _ => Err(format!("Invalid index register '{}' in {}", name, operand)),
+    };
+
+    // Auto increment / decrement
+    let auto = if let Some(r) = register.strip_suffix("++") {
+        Some((0x81, r))
+    } else if let Some(r) = register.strip_suffix('+') {
+        Some((0x80, r))
+    } else if let Some(r) = register.strip_prefix("--") {
+        Some((0x83, r))
+    } else {
+        register.strip_prefix('-').map(|r| (0x82, r))
+    };
+    if let Some((mode, r)) = auto {
+        if !offset.is_empty() {
+            return Err(format!("Auto increment/decrement takes no offset: {}", operand));
+        }
+        if indirect && (mode == 0x80 || mode == 0x82) {
+            return Err(format!("Single increment/decrement cannot be indirect: {}", operand));
+        }
+        return Ok((mode | register_bits(r.trim())? | indirect_bit, Extra::None));
+    }
+
+    let (expr, forced) = match offset.as_bytes().first() {
+        Some(b'<') => (&offset[1..], Some(1)),
+        Some(b'>') => (&offset[1..], Some(2)),
+        _ => (offset, None),
+    };
+
+    if register == "PC" || register == "PCR" {
+        let value = evaluate(expr, ctx)?;
+        // n,PC is a literal offset; n,PCR is a target address
+        let value = match value {
+            Value::Known(target) if register == "PCR" => {
+                let short = target - (after_postbyte as i32 + 1);
+                if forced != Some(2) && (-128..=127).contains(&short) {
+                    return Ok((0x8C | indirect_bit, Extra::Byte(Value::Known(short))));
+                }
+                let long = (target - (after_postbyte as i32 + 2)) as i16;
+                return Ok((0x8D | indirect_bit, Extra::Word(Value::Known(long as i32))));
+            }
+            value => value,
+        };
+        return Ok(match value {
+            Value::Known(n) if forced != Some(2) && (-128..=127).contains(&n) => (0x8C | indirect_bit, Extra::Byte(Value::Known(n))),
+            Value::Known(n) => (0x8D | indirect_bit, Extra::Word(Value::Known(n))),
+            Value::Symbol(symbol, addend) if forced == Some(1) => (0x8C | indirect_bit, Extra::Relative(symbol, addend, 1)),
+            Value::Symbol(symbol, addend) => (0x8D | indirect_bit, Extra::Relative(symbol, addend, 2)),
+        });
+    }
+
+    let register = register_bits(&register)?;
+    match offset.to_ascii_uppercase().as_str() {
+        "" => return Ok((0x84 | register | indirect_bit, Extra::None)),
+        "A" => return Ok((0x86 | register | indirect_bit, Extra::None)),
+        "B" => return Ok((0x85 | register | indirect_bit, Extra::None)),
+        "D" => return Ok((0x8B | register | indirect_bit, Extra::None)),
+        _ => {}
+    }
+    Ok(match evaluate(expr, ctx)? {
+        Value::Known(0) if forced.is_none() => (0x84 | register | indirect_bit, Extra::None),
+        Value::Known(n) if forced.is_none() && !indirect && (-16..=15).contains(&n) => ((n as u8 & 0x1F) | register, Extra::None),
+        Value::Known(n) if forced != Some(2) && (-128..=127).contains(&n) => (0x88 | register | indirect_bit, Extra::Byte(Value::Known(n))),
+        Value::Known(n) => (0x89 | register | indirect_bit, Extra::Word(Value::Known(n))),
+        symbol if forced == Some(1) => (0x88 | register | indirect_bit, Extra::Byte(symbol)),
+        symbol => (0x89 | register | indirect_bit, Extra::Word(symbol)),
+    })
+}
+
+/// Long form of a short branch opcode
+fn long_branch(opcode: u16) -> u16 {
+    match opcode {
+        0x20 => 0x16,
+        0x8D => 0x17,
+        op => 0x1000 | op,
+    }
+}
+
+fn encode_branch(emitter: &mut BinaryEmitter, op: &Opcode, operand: &str, ctx: &Context) -> Result<(), String> {
+    if operand.is_empty() {
+        return Err(format!("{} needs a target", op.mnemonic));
+    }
+    let (opcode, size) = match op.form {
+        Form::Branch16 => (op.inherent.unwrap_or_default(), 2),
+        _ if emitter.long_branches() => (long_branch(op.inherent.unwrap_or_default()), 2),
+        _ => (op.inherent.unwrap_or_default(), 1),
+    };
+    // Equates are not consulted: BIOS entry points are resolved as symbols in the
+    // second pass, so only a literal number (or `*`) is known here
+    let no_equates = HashMap::new();
+    let raw = Context { equates: &no_equates, last_global: ctx.last_global, pc: ctx.pc };
+    match evaluate(operand, &raw)? {
+        Value::Known(target) => {
+            if !(-32768..=65535).contains(&target) {
+                return Err(format!("Branch target out of range: {}", operand));
+            }
+            let next = emitter.current_address.wrapping_add(opcode_len(opcode) + size as u16);
+            let offset = (target as u16).wrapping_sub(next) as i16;
+            if size == 1 && !(-128..=127).contains(&offset) {
+                return Err(format!("Branch target {} is {} bytes away, out of 8-bit range", operand, offset));
+            }
+            emit_opcode(emitter, opcode);
+            if size == 2 {
+                emitter.emit_word(offset as u16);
+            } else {
+                emitter.emit(offset as u8);
+            }
+        }
+        Value::Symbol(target, addend) => {
+            emit_opcode(emitter, opcode);
+            emitter.add_symbol_ref_with_addend(&target, true, size, addend);
+            for _ in 0..size {
+                emitter.emit(0x00);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// TFR/EXG postbyte: source register in the high nibble, destination in the low
+fn register_pair(operand: &str) -> Result<u8, String> {
+    let (source, destination) = operand.split_once(',').ok_or_else(|| format!("Expected two registers: {}", operand))?;
+    let code = |name: &str| match name.trim().to_ascii_uppercase().as_str() {
+        "D" => Ok(0x0),
+        "X" => Ok(0x1),
+        "Y" => Ok(0x2),
+        "U" => Ok(0x3),
+        "S" => Ok(0x4),
+        "PC" => Ok(0x5),
+        "A" => Ok(0x8),
+        "B" => Ok(0x9),
+        "CC" => Ok(0xA),
+        "DP" => Ok(0xB),
+        other => Err(format!("Invalid register for TFR/EXG: {}", other)),
+    };
+    Ok((code(source)? << 4) | code(destination)?)
+}
+
+/// PSH/PUL postbyte; the other stack pointer occupies bit 6
+fn register_list(operand: &str, form: Form) -> Result<u8, String> {
+    if operand.is_empty() {
+        return Err("Empty register list".to_string());
+    }
+    let mut postbyte = 0u8;
+    for register in operand.split(',').map(|r| r.trim().to_ascii_uppercase()) {
+        postbyte |= match register.as_str() {
+            "CC" => 0x01,
+            "A" => 0x02,
+            "B" => 0x04,
+            "D" => 0x06,
+            "DP" => 0x08,
+            "X" => 0x10,
+            "Y" => 0x20,
+            "U" if form == Form::SystemStack => 0x40,
+            "S" if form == Form::UserStack => 0x40,
+            "PC" => 0x80,
+            _ => return Err(format!("Invalid register in stack list: '{}'", register)),
+        };
+    }
+    Ok(postbyte)
+}
@@ -4,6 +4,8 @@
 
 pub mod binary_emitter;
 pub mod asm_to_binary;
+pub mod directives;
+pub mod instruction_set;
 
 pub use binary_emitter::BinaryEmitter;
-pub use asm_to_binary::{assemble_m6809, set_include_dir, load_vectrex_symbols};
+pub use asm_to_binary::{assemble_m6809, assemble_object, AssembledObject, ObjectConstant, ObjectLabel, direct_page_stats, set_include_dir, load_vectrex_symbols, DirectPageStats};
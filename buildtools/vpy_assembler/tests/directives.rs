@@ -0,0 +1,233 @@
+//! Macro, conditional, REPT, STRUCT, ALIGN, INCBIN and SETDP directives, label
+//! forms and `*`
+
+use vpy_assembler::{assemble_m6809, direct_page_stats};
+
+fn assemble(source: &str) -> Vec<u8> {
+    match assemble_m6809(source, 0x0000, false, false) {
+        Ok((binary, ..)) => binary,
+        Err(e) => panic!("failed to assemble: {}\n{}", e, source),
+    }
+}
+
+fn fails(source: &str) -> String {
+    assemble_m6809(source, 0x0000, false, false).expect_err("should not assemble")
+}
+
+#[test]
+fn macro_with_parameters() {
+    let source = "\
+MOVE    MACRO
+        LDA \\1
+        STA \\2
+        ENDM
+        MOVE #1,$C880
+        MOVE $C881,<$10
+";
+    assert_eq!(assemble(source), vec![0x86, 0x01, 0xB7, 0xC8, 0x80, 0xB6, 0xC8, 0x81, 0x97, 0x10]);
+}
+
+#[test]
+fn macro_local_labels_are_unique_per_expansion() {
+    let source = "\
+DELAY   MACRO
+        LDB #\\1
+@loop:
+        DECB
+        BNE @loop
+        ENDM
+        DELAY 3
+        DELAY 5
+";
+    assert_eq!(assemble(source), vec![0xC6, 0x03, 0x5A, 0x26, 0xFD, 0xC6, 0x05, 0x5A, 0x26, 0xFD]);
+}
+
+#[test]
+fn unique_expansion_number_and_nested_macros() {
+    let source = "\
+INNER   MACRO
+        FCB \\1
+        ENDM
+OUTER   MACRO
+        INNER \\1
+        INNER \\@
+        ENDM
+        OUTER 7
+        OUTER 9
+";
+    // Expansions are numbered in order: OUTER=1, INNER=2, INNER=3, OUTER=4 ...
+    assert_eq!(assemble(source), vec![7, 1, 9, 4]);
+}
+
+#[test]
+fn conditional_assembly() {
+    let source = "\
+TARGET  EQU 2
+        IF TARGET-2
+        FCB 1
+        ELSE
+        FCB 2
+        ENDIF
+        IFEQ TARGET-2
+        FCB 3
+        ENDC
+        IFDEF TARGET
+        IFNDEF MISSING
+        FCB 4
+        ELSE
+        FCB 5
+        ENDIF
+        ENDIF
+        IFDEF MISSING
+        IF UNDEFINED_SYMBOL
+        FCB 6
+        ENDIF
+        ENDIF
+        IFGT TARGET
+        FCB 7
+        ENDIF
+";
+    assert_eq!(assemble(source), vec![2, 3, 4, 7]);
+}
+
+#[test]
+fn conditional_macro_definitions() {
+    let source = "\
+        IFDEF VECFEVER
+PLOT    MACRO
+        FCB 1
+        ENDM
+        ELSE
+PLOT    MACRO
+        FCB 2
+        ENDM
+        ENDIF
+        PLOT
+";
+    assert_eq!(assemble(source), vec![2]);
+}
+
+#[test]
+fn rept_unrolls_blocks() {
+    let source = "\
+COUNT   EQU 3
+        REPT COUNT
+        ASLA
+        REPT 2
+        NOP
+        ENDR
+        ENDR
+";
+    assert_eq!(assemble(source), vec![0x48, 0x12, 0x12, 0x48, 0x12, 0x12, 0x48, 0x12, 0x12]);
+}
+
+#[test]
+fn struct_offsets() {
+    let source = "\
+Vec     STRUCT
+x       RMB 1
+y       RMB 1
+        ENDSTRUCT
+Ship    STRUCT
+pos     Vec
+speed   FDB
+flags   FCB
+        ENDSTRUCT
+        LDA Ship.flags,X
+        LDD Ship.speed,U
+        LEAX sizeof{Ship},X
+        LDB #sizeof{Vec}
+";
+    assert_eq!(assemble(source), vec![0xA6, 0x04, 0xEC, 0x42, 0x30, 0x05, 0xC6, 0x02]);
+}
+
+#[test]
+fn align_pads_to_boundary() {
+    let source = "\
+        FCB 1,2,3
+        ALIGN 8
+        FCB 4
+        ALIGN 4,$FF
+        FCB 5
+        ALIGN 4
+";
+    assert_eq!(assemble(source), vec![1, 2, 3, 0, 0, 0, 0, 0, 4, 0xFF, 0xFF, 0xFF, 5, 0, 0, 0]);
+}
+
+#[test]
+fn incbin_inserts_file_contents() {
+    let path = std::env::temp_dir().join(format!("vpy_incbin_{}.bin", std::process::id()));
+    std::fs::write(&path, [0xDE, 0xAD, 0xBE, 0xEF]).unwrap();
+    let source = format!("        NOP\n        INCBIN \"{}\"\n        RTS\n", path.display());
+    let binary = assemble(&source);
+    std::fs::remove_file(&path).ok();
+    assert_eq!(binary, vec![0x12, 0xDE, 0xAD, 0xBE, 0xEF, 0x39]);
+}
+
+#[test]
+fn setdp_selects_direct_addressing() {
+    let source = "\
+        LDA $10
+        SETDP $D0
+        LDA $D00D
+        LDA $10
+        SETDP
+        LDA $D00D
+        SETDP $C800
+        STA $C880
+";
+    assert_eq!(
+        assemble(source),
+        vec![0x96, 0x10, 0x96, 0x0D, 0xB6, 0x00, 0x10, 0xB6, 0xD0, 0x0D, 0x97, 0x80]
+    );
+}
+
+#[test]
+fn direct_page_stats_count_auto_selected_accesses() {
+    let source = "\
+        SETDP $C8
+        LDA $C880
+        STA $C881
+        LDA <$10
+        SETDP $D0
+        LDB $D00D
+        LDB $C882
+";
+    let stats = direct_page_stats(source, 0).expect("stats");
+    assert_eq!(stats.accesses.get(&0xC8), Some(&2));
+    assert_eq!(stats.accesses.get(&0xD0), Some(&1));
+    assert_eq!(stats.bytes_saved(), 3);
+}
+
+#[test]
+fn labels_with_and_without_colon_and_current_location() {
+    let source = "\
+START   LDA #1
+        BRA *
+LOOP:   LDB #2 ; code after a label
+        BNE LOOP
+HERE    EQU *
+        FDB *,HERE
+.local  NOP
+RTS
+        BRA START
+";
+    let (binary, _, symbols, _) = assemble_m6809(source, 0x0000, false, false).expect("assemble");
+    assert_eq!(
+        binary,
+        vec![0x86, 0x01, 0x20, 0xFE, 0xC6, 0x02, 0x26, 0xFC, 0x00, 0x08, 0x00, 0x08, 0x12, 0x39, 0x20, 0xF0]
+    );
+    assert_eq!(symbols.get("START"), Some(&0x0000));
+    assert_eq!(symbols.get("LOOP"), Some(&0x0004));
+    assert_eq!(symbols.get("LOOP.local"), Some(&0x000C));
+}
+
+#[test]
+fn unbalanced_blocks_are_errors() {
+    assert!(fails("        IF 1\n        NOP\n").contains("without ENDIF"));
+    assert!(fails("        ENDIF\n").contains("ENDIF without IF"));
+    assert!(fails("M       MACRO\n        NOP\n").contains("MACRO without ENDM"));
+    assert!(fails("        REPT 2\n        NOP\n").contains("REPT without ENDR"));
+    assert!(fails("        IF NOT_DEFINED\n        ENDIF\n").contains("Undefined symbol"));
+    assert!(fails("LOOP    MACRO\n        LOOP\n        ENDM\n        LOOP\n").contains("nest too deeply"));
+}
@@ -0,0 +1,308 @@
+//! MC6809 encoding matrix: every opcode in every addressing mode, checked
+//! byte-for-byte against the Motorola programming-aid tables.
+
+use vpy_assembler::assemble_m6809;
+use vpy_assembler::m6809::instruction_set::{Form, OPCODES};
+
+const NA: i32 = -1;
+
+/// mnemonic, [inherent, immediate, direct, indexed, extended], immediate width.
+/// Transcribed from the MC6809 datasheet; page-2/3 opcodes include the prefix.
+const REFERENCE: &[(&str, [i32; 5], usize)] = &[
+    ("NOP", [0x12, NA, NA, NA, NA], 0),
+    ("SYNC", [0x13, NA, NA, NA, NA], 0),
+    ("DAA", [0x19, NA, NA, NA, NA], 0),
+    ("SEX", [0x1D, NA, NA, NA, NA], 0),
+    ("RTS", [0x39, NA, NA, NA, NA], 0),
+    ("ABX", [0x3A, NA, NA, NA, NA], 0),
+    ("RTI", [0x3B, NA, NA, NA, NA], 0),
+    ("MUL", [0x3D, NA, NA, NA, NA], 0),
+    ("SWI", [0x3F, NA, NA, NA, NA], 0),
+    ("SWI2", [0x103F, NA, NA, NA, NA], 0),
+    ("SWI3", [0x113F, NA, NA, NA, NA], 0),
+    ("NEGA", [0x40, NA, NA, NA, NA], 0),
+    ("COMA", [0x43, NA, NA, NA, NA], 0),
+    ("LSRA", [0x44, NA, NA, NA, NA], 0),
+    ("RORA", [0x46, NA, NA, NA, NA], 0),
+    ("ASRA", [0x47, NA, NA, NA, NA], 0),
+    ("ASLA", [0x48, NA, NA, NA, NA], 0),
+    ("LSLA", [0x48, NA, NA, NA, NA], 0),
+    ("ROLA", [0x49, NA, NA, NA, NA], 0),
+    ("DECA", [0x4A, NA, NA, NA, NA], 0),
+    ("INCA", [0x4C, NA, NA, NA, NA], 0),
+    ("TSTA", [0x4D, NA, NA, NA, NA], 0),
+    ("CLRA", [0x4F, NA, NA, NA, NA], 0),
+    ("NEGB", [0x50, NA, NA, NA, NA], 0),
+    ("COMB", [0x53, NA, NA, NA, NA], 0),
+    ("LSRB", [0x54, NA, NA, NA, NA], 0),
+    ("RORB", [0x56, NA, NA, NA, NA], 0),
+    ("ASRB", [0x57, NA, NA, NA, NA], 0),
+    ("ASLB", [0x58, NA, NA, NA, NA], 0),
+    ("LSLB", [0x58, NA, NA, NA, NA], 0),
+    ("ROLB", [0x59, NA, NA, NA, NA], 0),
+    ("DECB", [0x5A, NA, NA, NA, NA], 0),
+    ("INCB", [0x5C, NA, NA, NA, NA], 0),
+    ("TSTB", [0x5D, NA, NA, NA, NA], 0),
+    ("CLRB", [0x5F, NA, NA, NA, NA], 0),
+    ("NEG", [NA, NA, 0x00, 0x60, 0x70], 0),
+    ("COM", [NA, NA, 0x03, 0x63, 0x73], 0),
+    ("LSR", [NA, NA, 0x04, 0x64, 0x74], 0),
+    ("ROR", [NA, NA, 0x06, 0x66, 0x76], 0),
+    ("ASR", [NA, NA, 0x07, 0x67, 0x77], 0),
+    ("ASL", [NA, NA, 0x08, 0x68, 0x78], 0),
+    ("LSL", [NA, NA, 0x08, 0x68, 0x78], 0),
+    ("ROL", [NA, NA, 0x09, 0x69, 0x79], 0),
+    ("DEC", [NA, NA, 0x0A, 0x6A, 0x7A], 0),
+    ("INC", [NA, NA, 0x0C, 0x6C, 0x7C], 0),
+    ("TST", [NA, NA, 0x0D, 0x6D, 0x7D], 0),
+    ("JMP", [NA, NA, 0x0E, 0x6E, 0x7E], 0),
+    ("CLR", [NA, NA, 0x0F, 0x6F, 0x7F], 0),
+    ("SUBA", [NA, 0x80, 0x90, 0xA0, 0xB0], 1),
+    ("CMPA", [NA, 0x81, 0x91, 0xA1, 0xB1], 1),
+    ("SBCA", [NA, 0x82, 0x92, 0xA2, 0xB2], 1),
+    ("ANDA", [NA, 0x84, 0x94, 0xA4, 0xB4], 1),
+    ("BITA", [NA, 0x85, 0x95, 0xA5, 0xB5], 1),
+    ("LDA", [NA, 0x86, 0x96, 0xA6, 0xB6], 1),
+    ("STA", [NA, NA, 0x97, 0xA7, 0xB7], 0),
+    ("EORA", [NA, 0x88, 0x98, 0xA8, 0xB8], 1),
+    ("ADCA", [NA, 0x89, 0x99, 0xA9, 0xB9], 1),
+    ("ORA", [NA, 0x8A, 0x9A, 0xAA, 0xBA], 1),
+    ("ADDA", [NA, 0x8B, 0x9B, 0xAB, 0xBB], 1),
+    ("SUBB", [NA, 0xC0, 0xD0, 0xE0, 0xF0], 1),
+    ("CMPB", [NA, 0xC1, 0xD1, 0xE1, 0xF1], 1),
+    ("SBCB", [NA, 0xC2, 0xD2, 0xE2, 0xF2], 1),
+    ("ANDB", [NA, 0xC4, 0xD4, 0xE4, 0xF4], 1),
+    ("BITB", [NA, 0xC5, 0xD5, 0xE5, 0xF5], 1),
+    ("LDB", [NA, 0xC6, 0xD6, 0xE6, 0xF6], 1),
+    ("STB", [NA, NA, 0xD7, 0xE7, 0xF7], 0),
+    ("EORB", [NA, 0xC8, 0xD8, 0xE8, 0xF8], 1),
+    ("ADCB", [NA, 0xC9, 0xD9, 0xE9, 0xF9], 1),
+    ("ORB", [NA, 0xCA, 0xDA, 0xEA, 0xFA], 1),
+    ("ADDB", [NA, 0xCB, 0xDB, 0xEB, 0xFB], 1),
+    ("SUBD", [NA, 0x83, 0x93, 0xA3, 0xB3], 2),
+    ("ADDD", [NA, 0xC3, 0xD3, 0xE3, 0xF3], 2),
+    ("LDD", [NA, 0xCC, 0xDC, 0xEC, 0xFC], 2),
+    ("STD", [NA, NA, 0xDD, 0xED, 0xFD], 0),
+    ("CMPX", [NA, 0x8C, 0x9C, 0xAC, 0xBC], 2),
+    ("CPX", [NA, 0x8C, 0x9C, 0xAC, 0xBC], 2),
+    ("LDX", [NA, 0x8E, 0x9E, 0xAE, 0xBE], 2),
+    ("STX", [NA, NA, 0x9F, 0xAF, 0xBF], 0),
+    ("LDU", [NA, 0xCE, 0xDE, 0xEE, 0xFE], 2),
+    ("STU", [NA, NA, 0xDF, 0xEF, 0xFF], 0),
+    ("JSR", [NA, NA, 0x9D, 0xAD, 0xBD], 0),
+    ("CMPD", [NA, 0x1083, 0x1093, 0x10A3, 0x10B3], 2),
+    ("CPD", [NA, 0x1083, 0x1093, 0x10A3, 0x10B3], 2),
+    ("CMPY", [NA, 0x108C, 0x109C, 0x10AC, 0x10BC], 2),
+    ("CPY", [NA, 0x108C, 0x109C, 0x10AC, 0x10BC], 2),
+    ("LDY", [NA, 0x108E, 0x109E, 0x10AE, 0x10BE], 2),
+    ("STY", [NA, NA, 0x109F, 0x10AF, 0x10BF], 0),
+    ("LDS", [NA, 0x10CE, 0x10DE, 0x10EE, 0x10FE], 2),
+    ("STS", [NA, NA, 0x10DF, 0x10EF, 0x10FF], 0),
+    ("CMPU", [NA, 0x1183, 0x1193, 0x11A3, 0x11B3], 2),
+    ("CMPS", [NA, 0x118C, 0x119C, 0x11AC, 0x11BC], 2),
+    ("ORCC", [NA, 0x1A, NA, NA, NA], 1),
+    ("ANDCC", [NA, 0x1C, NA, NA, NA], 1),
+    ("CWAI", [NA, 0x3C, NA, NA, NA], 1),
+    ("LEAX", [NA, NA, NA, 0x30, NA], 0),
+    ("LEAY", [NA, NA, NA, 0x31, NA], 0),
+    ("LEAS", [NA, NA, NA, 0x32, NA], 0),
+    ("LEAU", [NA, NA, NA, 0x33, NA], 0),
+];
+
+/// Relative branches: mnemonic and opcode (short or long as written)
+const BRANCHES: &[(&str, u16)] = &[
+    ("BRA", 0x20), ("BRN", 0x21), ("BHI", 0x22), ("BLS", 0x23), ("BCC", 0x24), ("BHS", 0x24),
+    ("BCS", 0x25), ("BLO", 0x25), ("BNE", 0x26), ("BEQ", 0x27), ("BVC", 0x28), ("BVS", 0x29),
+    ("BPL", 0x2A), ("BMI", 0x2B), ("BGE", 0x2C), ("BLT", 0x2D), ("BGT", 0x2E), ("BLE", 0x2F),
+    ("BSR", 0x8D),
+    ("LBRA", 0x16), ("LBSR", 0x17), ("LBRN", 0x1021), ("LBHI", 0x1022), ("LBLS", 0x1023),
+    ("LBCC", 0x1024), ("LBHS", 0x1024), ("LBCS", 0x1025), ("LBLO", 0x1025), ("LBNE", 0x1026),
+    ("LBEQ", 0x1027), ("LBVC", 0x1028), ("LBVS", 0x1029), ("LBPL", 0x102A), ("LBMI", 0x102B),
+    ("LBGE", 0x102C), ("LBLT", 0x102D), ("LBGT", 0x102E), ("LBLE", 0x102F),
+];
+
+/// Register-operand instructions: mnemonic, opcode
+const REGISTER_OPS: &[(&str, u8)] = &[
+    ("EXG", 0x1E), ("TFR", 0x1F), ("PSHS", 0x34), ("PULS", 0x35), ("PSHU", 0x36), ("PULU", 0x37),
+];
+
+fn assemble(source: &str) -> Vec<u8> {
+    let source = format!("    {}\n", source);
+    match assemble_m6809(&source, 0x0000, false, false) {
+        Ok((binary, ..)) => binary,
+        Err(e) => panic!("'{}' failed to assemble: {}", source.trim(), e),
+    }
+}
+
+fn opcode_bytes(opcode: i32) -> Vec<u8> {
+    if opcode > 0xFF {
+        vec![(opcode >> 8) as u8, opcode as u8]
+    } else {
+        vec![opcode as u8]
+    }
+}
+
+fn expect(source: &str, expected: &[u8]) {
+    assert_eq!(assemble(source), expected, "encoding of '{}'", source);
+}
+
+#[test]
+fn every_opcode_in_every_mode() {
+    let mut checked = 0;
+    for &(mnemonic, [inherent, immediate, direct, indexed, extended], width) in REFERENCE {
+        if inherent != NA {
+            expect(mnemonic, &opcode_bytes(inherent));
+            checked += 1;
+        }
+        if immediate != NA {
+            let (operand, bytes): (&str, &[u8]) = if width == 1 { ("#$5A", &[0x5A]) } else { ("#$1234", &[0x12, 0x34]) };
+            expect(&format!("{} {}", mnemonic, operand), &[opcode_bytes(immediate), bytes.to_vec()].concat());
+            checked += 1;
+        }
+        if direct != NA {
+            let bytes = [opcode_bytes(direct), vec![0x42]].concat();
+            expect(&format!("{} <$42", mnemonic), &bytes);
+            expect(&format!("{} $42", mnemonic), &bytes);
+            checked += 1;
+        }
+        if indexed != NA {
+            expect(&format!("{} ,X", mnemonic), &[opcode_bytes(indexed), vec![0x84]].concat());
+            expect(&format!("{} [$1234,Y]", mnemonic), &[opcode_bytes(indexed), vec![0xB9, 0x12, 0x34]].concat());
+            checked += 1;
+        }
+        if extended != NA {
+            let bytes = [opcode_bytes(extended), vec![0xC8, 0x80]].concat();
+            expect(&format!("{} $C880", mnemonic), &bytes);
+            expect(&format!("{} >$0042", mnemonic), &[opcode_bytes(extended), vec![0x00, 0x42]].concat());
+            checked += 1;
+        }
+    }
+    let pairs = REFERENCE.iter().flat_map(|r| r.1).filter(|&op| op != NA).count();
+    assert_eq!(checked, pairs);
+    assert_eq!(pairs, 245);
+}
+
+#[test]
+fn reference_table_covers_instruction_set() {
+    for op in OPCODES {
+        let modes = [op.inherent, op.immediate, op.direct, op.indexed, op.extended].map(|m| m.map_or(NA, i32::from));
+        match op.form {
+            Form::General => {
+                let reference = REFERENCE.iter().find(|r| r.0 == op.mnemonic);
+                let reference = reference.unwrap_or_else(|| panic!("{} missing from the reference table", op.mnemonic));
+                assert_eq!(modes, reference.1, "{} opcode columns", op.mnemonic);
+                assert_eq!(op.immediate_size as usize, reference.2, "{} immediate width", op.mnemonic);
+            }
+            Form::Branch8 | Form::Branch16 => {
+                assert!(BRANCHES.iter().any(|b| b.0 == op.mnemonic && i32::from(b.1) == modes[0]), "{}", op.mnemonic);
+            }
+            _ => assert!(REGISTER_OPS.iter().any(|r| r.0 == op.mnemonic && i32::from(r.1) == modes[0]), "{}", op.mnemonic),
+        }
+    }
+    let general = OPCODES.iter().filter(|op| op.form == Form::General).count();
+    assert_eq!(general, REFERENCE.len());
+}
+
+#[test]
+fn indexed_postbytes() {
+    // operand, postbyte and offset bytes (LDA = $A6)
+    let cases: &[(&str, &[u8])] = &[
+        (",X", &[0x84]), (",Y", &[0xA4]), (",U", &[0xC4]), (",S", &[0xE4]),
+        ("0,X", &[0x84]), ("5,X", &[0x05]), ("-16,Y", &[0x30]), ("15,U", &[0x4F]), ("-1,S", &[0x7F]),
+        ("16,X", &[0x88, 0x10]), ("-128,Y", &[0xA8, 0x80]), ("<5,X", &[0x88, 0x05]),
+        ("128,U", &[0xC9, 0x00, 0x80]), ("$1234,S", &[0xE9, 0x12, 0x34]), (">5,X", &[0x89, 0x00, 0x05]),
+        ("A,X", &[0x86]), ("B,Y", &[0xA5]), ("D,U", &[0xCB]),
+        (",X+", &[0x80]), (",Y++", &[0xA1]), (",-U", &[0xC2]), (",--S", &[0xE3]),
+        ("5,PC", &[0x8C, 0x05]), ("$1234,PC", &[0x8D, 0x12, 0x34]),
+        // Indirect
+        ("[,X]", &[0x94]), ("[0,X]", &[0x94]), ("[5,Y]", &[0xB8, 0x05]), ("[$1234,U]", &[0xD9, 0x12, 0x34]),
+        ("[A,S]", &[0xF6]), ("[B,X]", &[0x95]), ("[D,Y]", &[0xBB]),
+        ("[,X++]", &[0x91]), ("[,--Y]", &[0xB3]),
+        ("[5,PC]", &[0x9C, 0x05]), ("[$1234,PC]", &[0x9D, 0x12, 0x34]),
+        ("[$C880]", &[0x9F, 0xC8, 0x80]),
+    ];
+    for (operand, bytes) in cases {
+        expect(&format!("LDA {}", operand), &[&[0xA6][..], bytes].concat());
+    }
+}
+
+#[test]
+fn pc_relative_to_labels() {
+    // 8-bit forced and default 16-bit PCR to a label, indirect too
+    let source = "    LEAX <TARGET,PCR\n    LDA TARGET,PCR\n    LDD [TARGET,PCR]\nTARGET:\n    NOP\n";
+    let (binary, ..) = assemble_m6809(source, 0x0000, false, false).unwrap();
+    assert_eq!(binary, vec![0x30, 0x8C, 0x08, 0xA6, 0x8D, 0x00, 0x04, 0xEC, 0x9D, 0x00, 0x00, 0x12]);
+
+    // A numeric PCR operand is a target address
+    expect("LEAX $0010,PCR", &[0x30, 0x8C, 0x0D]);
+}
+
+#[test]
+fn branches_to_labels_addresses_and_star() {
+    for &(mnemonic, opcode) in BRANCHES {
+        let opcode = opcode_bytes(i32::from(opcode));
+        let long = mnemonic.starts_with("LB");
+        let len = opcode.len() + if long { 2 } else { 1 };
+        let back = -(len as i16);
+        let to_self = [opcode.clone(), if long { back.to_be_bytes().to_vec() } else { vec![back as u8] }].concat();
+
+        // Branch to itself: by label, by address (assembled at $0000) and with `*`
+        let source = format!("HERE:\n    {} HERE\n", mnemonic);
+        let (binary, ..) = assemble_m6809(&source, 0xC000, false, false).unwrap();
+        assert_eq!(binary, to_self, "{} to label", mnemonic);
+        expect(&format!("{} 0", mnemonic), &to_self);
+        expect(&format!("{} *", mnemonic), &to_self);
+    }
+    // A number is a target address, not an offset
+    expect("BRA 10", &[0x20, 0x08]);
+    expect("LBRA $0100", &[0x16, 0x00, 0xFD]);
+    expect("BNE *+2", &[0x26, 0x00]);
+    assert!(assemble_m6809("    BRA $0100\n", 0, false, false).is_err());
+}
+
+#[test]
+fn long_branch_mode_promotes_short_branches() {
+    let source = "    BEQ DONE\n    BSR DONE\n    BRA DONE\nDONE:\n    RTS\n";
+    let (binary, ..) = assemble_m6809(source, 0x0000, false, true).unwrap();
+    assert_eq!(binary, vec![0x10, 0x27, 0x00, 0x06, 0x17, 0x00, 0x03, 0x16, 0x00, 0x00, 0x39]);
+}
+
+#[test]
+fn register_operands() {
+    expect("TFR A,B", &[0x1F, 0x89]);
+    expect("TFR X,Y", &[0x1F, 0x12]);
+    expect("EXG D,U", &[0x1E, 0x03]);
+    expect("EXG CC,DP", &[0x1E, 0xAB]);
+    expect("TFR S,PC", &[0x1F, 0x45]);
+    expect("PSHS CC,A,B,DP,X,Y,U,PC", &[0x34, 0xFF]);
+    expect("PULS D,X,PC", &[0x35, 0x96]);
+    expect("PSHU CC,D,DP,X,Y,S,PC", &[0x36, 0xFF]);
+    expect("PULU A,Y", &[0x37, 0x22]);
+    assert!(assemble_m6809("    PSHS S\n", 0, false, false).is_err());
+    assert!(assemble_m6809("    PULU U\n", 0, false, false).is_err());
+}
+
+#[test]
+fn accumulator_operands_and_mode_errors() {
+    expect("ASL A", &[0x48]);
+    expect("NEG B", &[0x50]);
+    expect("CLR A", &[0x4F]);
+    expect("LDA #'A'", &[0x86, 0x41]);
+    expect("LDB #-1", &[0xC6, 0xFF]);
+    expect("ANDCC #%11101111", &[0x1C, 0xEF]);
+    for bad in ["STA #1", "LEAX $1234", "MUL A", "CLR #0", "LDA [,X+]", "LDA 5,Q", "LDA #256"] {
+        assert!(assemble_m6809(&format!("    {}\n", bad), 0, false, false).is_err(), "'{}' should fail", bad);
+    }
+}
+
+#[test]
+fn symbols_in_operands() {
+    let source = "VALUE EQU $C880\n    LDX #TABLE+2\n    STD VALUE\n    JSR TABLE\n    LDA <VALUE\nTABLE:\n    FCB 1,2,3\n";
+    let (binary, ..) = assemble_m6809(source, 0x1000, false, false).unwrap();
+    assert_eq!(
+        binary,
+        vec![0x8E, 0x10, 0x0D, 0xFD, 0xC8, 0x80, 0xBD, 0x10, 0x0B, 0x96, 0x80, 0x01, 0x02, 0x03]
+    );
+    // `*` is the address of the instruction itself
+    expect("LDX #*+3", &[0x8E, 0x00, 0x03]);
+}
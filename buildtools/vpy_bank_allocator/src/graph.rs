@@ -9,7 +9,7 @@
 //! - Minimizes cross-bank calls for better performance
 
 use std::collections::{HashMap, HashSet};
-use vpy_parser::{Module, Item, Function, Stmt, Expr};
+use vpy_parser::{Module, Item, Function, Stmt, Expr, AssignTarget};
 
 /// Node in the call graph (represents a function)
 #[derive(Debug, Clone)]
@@ -332,6 +332,18 @@ fn find_assets_in_stmt(stmt: &Stmt, assets: &mut HashSet<String>) {
                 find_assets_in_stmt(s, assets);
             }
         },
+        Stmt::Switch { cases, default, .. } => {
+            for (_, case_body) in cases {
+                for s in case_body {
+                    find_assets_in_stmt(s, assets);
+                }
+            }
+            if let Some(default_b) = default {
+                for s in default_b {
+                    find_assets_in_stmt(s, assets);
+                }
+            }
+        },
         _ => {}
     }
 }
@@ -413,6 +425,14 @@ fn count_statements(body: &[Stmt]) -> usize {
             Stmt::For { body, .. } => {
                 count += count_statements(body);
             },
+            Stmt::Switch { cases, default, .. } => {
+                for (_, case_body) in cases {
+                    count += count_statements(case_body);
+                }
+                if let Some(default_b) = default {
+                    count += count_statements(default_b);
+                }
+            },
             _ => {}
         }
     }
@@ -439,7 +459,20 @@ fn find_calls_in_stmt(stmt: &Stmt, calls: &mut Vec<String>) {
         Stmt::Return(Some(expr), _) => {
             find_calls_in_expr(expr, calls);
         },
-        Stmt::Assign { value, .. } => {
+        Stmt::Assign { target, value, .. } | Stmt::CompoundAssign { target, value, .. } => {
+            match target {
+                AssignTarget::Index { target, index, .. } => {
+                    find_calls_in_expr(target, calls);
+                    find_calls_in_expr(index, calls);
+                },
+                AssignTarget::FieldAccess { target, .. } => {
+                    find_calls_in_expr(target, calls);
+                },
+                AssignTarget::Ident { .. } => {}
+            }
+            find_calls_in_expr(value, calls);
+        },
+        Stmt::Let { value, .. } | Stmt::Assert { cond: value, .. } => {
             find_calls_in_expr(value, calls);
         },
         Stmt::If { cond, body, elifs, else_body, .. } => {
@@ -475,6 +508,26 @@ fn find_calls_in_stmt(stmt: &Stmt, calls: &mut Vec<String>) {
                 find_calls_in_stmt(s, calls);
             }
         },
+        Stmt::ForIn { iterable, body, .. } => {
+            find_calls_in_expr(iterable, calls);
+            for s in body {
+                find_calls_in_stmt(s, calls);
+            }
+        },
+        Stmt::Switch { expr, cases, default, .. } => {
+            find_calls_in_expr(expr, calls);
+            for (case_expr, case_body) in cases {
+                find_calls_in_expr(case_expr, calls);
+                for s in case_body {
+                    find_calls_in_stmt(s, calls);
+                }
+            }
+            if let Some(default_body) = default {
+                for s in default_body {
+                    find_calls_in_stmt(s, calls);
+                }
+            }
+        },
         _ => {}
     }
 }
@@ -484,6 +537,13 @@ fn find_calls_in_expr(expr: &Expr, calls: &mut Vec<String>) {
     match expr {
         Expr::Call(call_info) => {
             calls.push(call_info.name.clone());
+            // CO_START/CO_RESUME(f) run (or set up) coroutine f from here
+            let upper = call_info.name.to_uppercase();
+            if upper == "CO_START" || upper == "CO_RESUME" {
+                if let Some(Expr::Ident(coroutine)) = call_info.args.first() {
+                    calls.push(coroutine.name.clone());
+                }
+            }
             for arg in &call_info.args {
                 find_calls_in_expr(arg, calls);
             }
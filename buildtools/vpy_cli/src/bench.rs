@@ -0,0 +1,225 @@
+//! `vpy_cli bench`: expression generator benchmarks
+//!
+//! Every `.vpy` file of the benchmark directory defines `def bench():`. Each
+//! snippet is compiled twice, with the legacy RESULT/TMPPTR expression code
+//! and with the register lowering (`vpy_codegen::m6809::lowering`), into a
+//! test ROM whose `main()` calls `bench()` once. Code size is the distance
+//! from `bench` to a marker function placed right after it; cycles are
+//! counted in `vectrex_emulator` from the `JSR` target to the `RTS` that
+//! leaves it. Snippets may `assert` their results: a failed assert in either
+//! build is an error, so the benchmark also checks both generators agree.
+
+use anyhow::{Context, Result};
+use colored::*;
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use vectrex_emulator::{Vectrex, CYCLES_PER_FRAME};
+use vpy_codegen::m6809::{context, test_harness};
+use vpy_parser::{Function, Item, Module};
+
+use crate::test_runner::{assemble_single_bank, load_bios, load_sources, module_name_of};
+
+/// Frames allowed to reach and finish `bench()`
+const MAX_FRAMES: u64 = 600;
+
+pub struct BenchOptions {
+    /// Fail when the lowering is larger or slower than the legacy code on any snippet
+    pub check: bool,
+    /// Write the results as JSON
+    pub json: Option<PathBuf>,
+    pub verbose: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Measurement {
+    bytes: u16,
+    cycles: u64,
+}
+
+struct BenchResult {
+    name: String,
+    legacy: Measurement,
+    lowered: Measurement,
+}
+
+pub fn cmd_bench(dir: &Path, options: &BenchOptions) -> Result<()> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read benchmark directory {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().and_then(|e| e.to_str()) == Some("vpy"))
+        .collect();
+    files.sort();
+    if files.is_empty() {
+        anyhow::bail!("No .vpy benchmarks in {}", dir.display());
+    }
+
+    let bios = load_bios(dir)?;
+    let include_dir = crate::resolve_include_dir();
+    let build_dir = std::env::temp_dir().join("vpy_bench");
+
+    let mut results = Vec::new();
+    for file in &files {
+        let name = module_name_of(file);
+        let legacy = measure(file, true, &bios, &include_dir, &build_dir, options.verbose)
+            .with_context(|| format!("{} (legacy expressions)", file.display()))?;
+        let lowered = measure(file, false, &bios, &include_dir, &build_dir, options.verbose)
+            .with_context(|| format!("{} (register lowering)", file.display()))?;
+        results.push(BenchResult { name, legacy, lowered });
+    }
+
+    print_table(&results);
+
+    if let Some(path) = &options.json {
+        std::fs::write(path, serde_json::to_string_pretty(&to_json(&results))?)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        println!("\n  JSON report: {}", path.display());
+    }
+
+    let regressions: Vec<&BenchResult> = results
+        .iter()
+        .filter(|r| r.lowered.bytes > r.legacy.bytes || r.lowered.cycles > r.legacy.cycles)
+        .collect();
+    if options.check && !regressions.is_empty() {
+        let names: Vec<&str> = regressions.iter().map(|r| r.name.as_str()).collect();
+        anyhow::bail!("register lowering is larger or slower than the legacy code on: {}", names.join(", "));
+    }
+    Ok(())
+}
+
+/// Build `file` with the selected expression generator and run its `bench()`
+fn measure(file: &Path, legacy: bool, bios: &[u8], include_dir: &Path, build_dir: &Path, verbose: bool) -> Result<Measurement> {
+    let mut sources = load_sources(file)?;
+    let module_name = module_name_of(&sources.entry_point);
+    // Unify the snippet as the entry module `main` so its names get no module prefix
+    let module = sources.modules.remove(&module_name).context("snippet not loaded")?;
+    let modules = std::iter::once(("main".to_string(), module)).collect();
+    let mut unified = vpy_unifier::unify_modules(modules, "main")
+        .map_err(|e| anyhow::anyhow!("Unification error: {}", e))?;
+    insert_end_marker(&mut unified, "BENCH", "BENCH_END")?;
+
+    let bank_config = vpy_codegen::BankConfig::single_bank();
+    let assets = vpy_codegen::m6809::assets::discover_assets(&sources.entry_point);
+    context::set_legacy_expressions(legacy);
+    let generated = vpy_codegen::generate_test_from_module(&unified, &bank_config, "BENCH", &assets, "BENCH");
+    context::set_legacy_expressions(false);
+    let generated = generated.map_err(|e| anyhow::anyhow!("Codegen error: {}", e))?;
+
+    if verbose {
+        let asm_path = build_dir.join(format!("{}.{}.asm", module_name, if legacy { "legacy" } else { "lowered" }));
+        std::fs::create_dir_all(build_dir)?;
+        std::fs::write(&asm_path, &generated.asm_source)?;
+        println!("    ASM written: {}", asm_path.display());
+    }
+
+    let (rom, symbols) = assemble_single_bank(&generated, include_dir)?;
+    let address = |name: &str| symbols.get(name).copied().with_context(|| format!("label {} not found", name));
+    let (start, stop) = (address("BENCH")?, address("BENCH_END")?);
+    let cycles = run_bench(bios, &rom, start)?;
+    Ok(Measurement { bytes: stop.wrapping_sub(start), cycles })
+}
+
+/// Put an empty `BENCH_END` function right after `bench` so its label marks the end of the code
+fn insert_end_marker(module: &mut Module, bench: &str, end: &str) -> Result<()> {
+    let position = module
+        .items
+        .iter()
+        .position(|item| matches!(item, Item::Function(f) if f.name == bench))
+        .context("no `def bench():` function")?;
+    module.items.insert(
+        position + 1,
+        Item::Function(Function { name: end.to_string(), line: 0, params: Vec::new(), body: Vec::new() }),
+    );
+    Ok(())
+}
+
+/// Cycles spent in the routine at `start`, from its first instruction to its RTS
+fn run_bench(bios: &[u8], rom: &[u8], start: u16) -> Result<u64> {
+    let mut vectrex = Vectrex::new(bios).map_err(|e| anyhow::anyhow!("{}", e))?;
+    vectrex.load_cartridge(rom);
+    vectrex.boot_cartridge().map_err(|e| anyhow::anyhow!("boot failed: {}", e))?;
+    let budget = vectrex.cpu.cycles + MAX_FRAMES * CYCLES_PER_FRAME;
+
+    let step = |vectrex: &mut Vectrex| -> Result<()> {
+        vectrex.step().map_err(|e| anyhow::anyhow!("emulator stopped: {}", e))?;
+        match vectrex.peek(test_harness::TEST_STATUS_ADDR) {
+            test_harness::TEST_RUNNING | test_harness::TEST_PASSED => {}
+            test_harness::TEST_FAILED => {
+                anyhow::bail!("assert failed at line {}", vectrex.peek16(test_harness::TEST_LINE_ADDR))
+            }
+            other => anyhow::bail!("invalid test status ${:02X}", other),
+        }
+        if vectrex.cpu.cycles > budget {
+            anyhow::bail!("bench() did not finish within {} frames", MAX_FRAMES);
+        }
+        Ok(())
+    };
+
+    while vectrex.cpu.pc != start {
+        step(&mut vectrex)?;
+    }
+    let (entry_cycles, entry_s) = (vectrex.cpu.cycles, vectrex.cpu.s);
+    // The RTS pops the return address pushed by the JSR
+    while vectrex.cpu.s <= entry_s {
+        step(&mut vectrex)?;
+    }
+    let cycles = vectrex.cpu.cycles - entry_cycles;
+
+    while vectrex.peek(test_harness::TEST_STATUS_ADDR) != test_harness::TEST_PASSED {
+        step(&mut vectrex)?;
+    }
+    Ok(cycles)
+}
+
+fn percent(legacy: u64, lowered: u64) -> String {
+    if legacy == 0 {
+        return "-".to_string();
+    }
+    format!("{:+.0}%", (lowered as f64 - legacy as f64) * 100.0 / legacy as f64)
+}
+
+fn print_table(results: &[BenchResult]) {
+    println!(
+        "\n  {:<20} {:>7} {:>7} {:>6}  {:>8} {:>8} {:>6}",
+        "benchmark", "bytes", "(old)", "", "cycles", "(old)", ""
+    );
+    let (mut legacy, mut lowered) = ((0u64, 0u64), (0u64, 0u64));
+    for r in results {
+        let bytes = percent(r.legacy.bytes as u64, r.lowered.bytes as u64);
+        let cycles = percent(r.legacy.cycles, r.lowered.cycles);
+        let line = format!(
+            "  {:<20} {:>7} {:>7} {:>6}  {:>8} {:>8} {:>6}",
+            r.name, r.lowered.bytes, r.legacy.bytes, bytes, r.lowered.cycles, r.legacy.cycles, cycles
+        );
+        if r.lowered.bytes > r.legacy.bytes || r.lowered.cycles > r.legacy.cycles {
+            println!("{}", line.yellow());
+        } else {
+            println!("{}", line);
+        }
+        legacy = (legacy.0 + r.legacy.bytes as u64, legacy.1 + r.legacy.cycles);
+        lowered = (lowered.0 + r.lowered.bytes as u64, lowered.1 + r.lowered.cycles);
+    }
+    println!(
+        "{}",
+        format!(
+            "  {:<20} {:>7} {:>7} {:>6}  {:>8} {:>8} {:>6}",
+            "total",
+            lowered.0,
+            legacy.0,
+            percent(legacy.0, lowered.0),
+            lowered.1,
+            legacy.1,
+            percent(legacy.1, lowered.1)
+        )
+        .bold()
+    );
+}
+
+fn to_json(results: &[BenchResult]) -> serde_json::Value {
+    json!({
+        "benchmarks": results.iter().map(|r| json!({
+            "name": r.name,
+            "legacy": { "bytes": r.legacy.bytes, "cycles": r.legacy.cycles },
+            "lowered": { "bytes": r.lowered.bytes, "cycles": r.lowered.cycles },
+        })).collect::<Vec<_>>(),
+    })
+}
@@ -3,6 +3,12 @@ use colored::*;
 use std::path::{Path, PathBuf};
 use anyhow::{Result, Context};
 
+mod bench;
+mod profiler;
+mod objects;
+mod snapshot;
+mod test_runner;
+
 // Use centralized asset discovery from vpy_codegen (ensures consistent sorting)
 fn discover_assets(source_path: &Path) -> Vec<vpy_codegen::AssetInfo> {
     vpy_codegen::m6809::assets::discover_assets(source_path)
@@ -13,7 +19,7 @@ fn discover_assets(source_path: &Path) -> Vec<vpy_codegen::AssetInfo> {
 ///   1. Same directory as the binary (packaged app — VECTREX.I bundled next to vpy_cli)
 ///   2. Walk up 3 levels from binary dir: target/ → buildtools/ → workspace/ide/frontend/public/include
 ///   3. Walk up 2 levels (fallback for shallower layouts)
-fn resolve_include_dir() -> PathBuf {
+pub(crate) fn resolve_include_dir() -> PathBuf {
     let cli_exe = std::env::current_exe().unwrap_or_default();
     let cli_dir = match cli_exe.parent() {
         Some(d) => d,
@@ -105,20 +111,22 @@ enum Commands {
         graph: bool,
     },
     
-    /// Assemble to object files
+    /// Assemble to relocatable object files (.vo)
     Assemble {
-        /// Entry point VPy file
-        input: PathBuf,
+        /// .asm or single-bank .vpy files
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
         
-        /// Output directory for .vo files
+        /// Output .vo file (one input) or directory
         #[arg(short, long)]
         output: Option<PathBuf>,
     },
     
     /// Link object files to ROM
     Link {
-        /// Entry point VPy file
-        input: PathBuf,
+        /// .vo files; the first one holds the cartridge header
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
         
         /// Output ROM file
         #[arg(short, long)]
@@ -150,6 +158,99 @@ enum Commands {
         #[arg(short, long)]
         verbose: bool,
     },
+    
+    /// Run `def test_*()` functions headless in the emulator
+    Test {
+        /// Entry point VPy file or .vpyproj
+        input: PathBuf,
+        
+        /// Only run tests whose name contains this string
+        #[arg(short, long)]
+        filter: Option<String>,
+        
+        /// Frame budget per test before it is reported as timed out
+        #[arg(long, default_value = "600")]
+        max_frames: u64,
+        
+        /// Show intermediate outputs
+        #[arg(short, long)]
+        verbose: bool,
+    },
+    
+    /// Compare drawn vectors against the golden-frame snapshot (snapshots/<name>.vsnap)
+    Snapshot {
+        /// Entry point VPy file or .vpyproj
+        input: PathBuf,
+        
+        /// Frames to record when creating or updating the snapshot
+        #[arg(long, value_delimiter = ',', default_value = "30,60")]
+        frames: Vec<u64>,
+        
+        /// Overwrite the snapshot with the current output
+        #[arg(long)]
+        update: bool,
+        
+        /// Allowed difference per coordinate (integrator units)
+        #[arg(long, default_value = "16")]
+        tolerance: i32,
+        
+        /// Allowed difference in intensity
+        #[arg(long, default_value = "0")]
+        intensity_tolerance: u8,
+        
+        /// Output directory for the ROM and diff SVGs (default: <project>/build/snapshots)
+        #[arg(long)]
+        out_dir: Option<PathBuf>,
+        
+        /// Show intermediate outputs
+        #[arg(short, long)]
+        verbose: bool,
+    },
+    
+    /// Compare code size and cycles of the expression generators on VPy snippets
+    Bench {
+        /// Directory of `.vpy` snippets, each defining `def bench():`
+        #[arg(default_value = "buildtools/vpy_cli/benchmarks/expressions")]
+        dir: PathBuf,
+        
+        /// Fail if the register lowering is larger or slower than the legacy code on any snippet
+        #[arg(long)]
+        check: bool,
+        
+        /// Write the results to this JSON file
+        #[arg(long)]
+        json: Option<PathBuf>,
+        
+        /// Write the generated ASM of every snippet to the temp directory
+        #[arg(short, long)]
+        verbose: bool,
+    },
+    
+    /// Profile a ROM in the emulator: cycles per routine and VPy line, call tree, folded stacks
+    Profile {
+        /// ROM image (.bin)
+        rom: PathBuf,
+        
+        /// Debug symbols (default: <rom>.pdb)
+        #[arg(long)]
+        pdb: Option<PathBuf>,
+        
+        /// Frames to profile after start-up
+        #[arg(long, default_value = "100")]
+        frames: u64,
+        
+        /// Busy cycles allowed per frame before it is flagged as an overrun
+        #[arg(long, default_value_t = vectrex_emulator::CYCLES_PER_FRAME)]
+        budget: u64,
+        
+        /// Output prefix for <prefix>.folded and <prefix>.profile.json (default: the ROM path)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        
+        /// Show every routine and line instead of the top 20
+        #[arg(short, long)]
+        verbose: bool,
+    },
 }
 
 fn main() -> Result<()> {
@@ -181,20 +282,48 @@ fn main() -> Result<()> {
             cmd_allocate(&input, graph)?;
         }
         
-        Commands::Assemble { input, output } => {
+        Commands::Assemble { inputs, output } => {
             println!("{}", "=== Phase 5: ASSEMBLE ===".bright_cyan().bold());
-            cmd_assemble(&input, output)?;
+            objects::cmd_assemble(&inputs, output)?;
         }
         
-        Commands::Link { input, output } => {
+        Commands::Link { inputs, output } => {
             println!("{}", "=== Phase 6: LINK ===".bright_cyan().bold());
-            cmd_link(&input, output)?;
+            objects::cmd_link(&inputs, output)?;
         }
         
         Commands::Build { input, output, rom_size, bank_size, debug, verbose } => {
             println!("{}", "=== FULL BUILD PIPELINE ===".bright_green().bold());
             cmd_build(&input, output, rom_size, bank_size, debug, verbose)?;
         }
+        
+        Commands::Test { input, filter, max_frames, verbose } => {
+            println!("{}", "=== VPY TESTS ===".bright_cyan().bold());
+            test_runner::cmd_test(&input, filter.as_deref(), max_frames, verbose)?;
+        }
+        
+        Commands::Snapshot { input, frames, update, tolerance, intensity_tolerance, out_dir, verbose } => {
+            println!("{}", "=== VECTOR SNAPSHOT ===".bright_cyan().bold());
+            let options = snapshot::SnapshotOptions {
+                frames,
+                update,
+                tolerance: vectrex_emulator::Tolerance { position: tolerance, intensity: intensity_tolerance },
+                out_dir,
+                verbose,
+            };
+            snapshot::cmd_snapshot(&input, &options)?;
+        }
+        
+        Commands::Bench { dir, check, json, verbose } => {
+            println!("{}", "=== EXPRESSION BENCHMARKS ===".bright_cyan().bold());
+            bench::cmd_bench(&dir, &bench::BenchOptions { check, json, verbose })?;
+        }
+        
+        Commands::Profile { rom, pdb, frames, budget, output, verbose } => {
+            println!("{}", "=== FRAME PROFILE ===".bright_cyan().bold());
+            let options = profiler::ProfileOptions { pdb, frames, budget, output, verbose };
+            profiler::cmd_profile(&rom, &options)?;
+        }
     }
     
     Ok(())
@@ -613,6 +742,7 @@ fn cmd_asm(input: &PathBuf, rom_size: usize, bank_size: usize, output: Option<Pa
     
     let module = vpy_parser::parser::parse(tokens, source_path.to_str().unwrap_or("unknown"))
         .map_err(|e| anyhow::anyhow!("Parse error: {}", e))?;
+    let module = resolve_single_module_enums(module, &source_path)?;
     
     // Extract function names
     let mut functions = Vec::new();
@@ -690,19 +820,222 @@ fn cmd_asm(input: &PathBuf, rom_size: usize, bank_size: usize, output: Option<Pa
     Ok(())
 }
 
-fn cmd_allocate(_input: &PathBuf, _graph: bool) -> Result<()> {
-    println!("{}", "TODO: Implement bank allocator integration".yellow());
+/// Run the bank allocator the multibank codegen uses and print where each
+/// function goes; with `--graph`, the call graph it clusters first
+fn cmd_allocate(input: &Path, graph: bool) -> Result<()> {
+    let module = load_entry_module(input)?;
+    let call_graph = vpy_bank_allocator::CallGraph::from_module(&module);
+
+    if graph {
+        println!("\n{}", "Call Graph:".bright_white().bold());
+        let mut edges: Vec<_> = call_graph.edges.iter().map(|e| (e.from.as_str(), e.to.as_str())).collect();
+        edges.sort();
+        edges.dedup();
+        for (from, to) in edges {
+            println!("  {} → {}", from.bright_yellow(), to);
+        }
+    }
+
+    // Same sizes as codegen: META ROM size, 16KB banks unless META says otherwise
+    let rom_size = module.meta.rom_total_size.map_or(32768, |size| size as usize);
+    let bank_size = module.meta.rom_bank_size.map_or(16384, |size| size as usize);
+    if rom_size <= 32768 {
+        println!(
+            "\n  {} Single-bank ROM ({} bytes): all {} functions stay in bank #0",
+            "✓".green(),
+            rom_size,
+            call_graph.nodes.len()
+        );
+        return Ok(());
+    }
+
+    let function_sizes: std::collections::HashMap<String, usize> =
+        call_graph.nodes.iter().map(|(name, node)| (name.clone(), node.size_bytes)).collect();
+    let assets = vpy_codegen::m6809::assets::filter_used_assets(&discover_assets(input), &module);
+    let asset_sizes = vpy_codegen::m6809::assets::prepare_assets_with_sizes(&assets)
+        .into_iter()
+        .map(|asset| (asset.info.name, asset.binary_size))
+        .collect();
+
+    let config = vpy_bank_allocator::BankConfig::new(rom_size, bank_size);
+    let mut allocator = vpy_bank_allocator::allocator::BankAllocator::new(config, call_graph);
+    allocator.set_asset_sizes(asset_sizes);
+    let assignments = match allocator.assign_banks() {
+        Ok(assignments) => assignments,
+        Err(e) => {
+            // As in codegen, which then generates every function into bank #0
+            println!("\n  {}", format!("⚠ Bank allocator failed: {}", e).yellow());
+            println!("    Codegen falls back to single-bank function generation");
+            return Ok(());
+        }
+    };
+    let stats = allocator.assignment_stats(&assignments);
+
+    println!("\n{}", "Bank Assignments:".bright_white().bold());
+    for bank in &stats.banks {
+        println!("  Bank #{}: {} bytes", bank.id.to_string().bright_yellow(), bank.used_bytes);
+        let mut functions = bank.functions.clone();
+        functions.sort();
+        for function in functions {
+            println!("    {} ({} bytes)", function, function_sizes.get(&function).copied().unwrap_or(0));
+        }
+    }
+    println!("\n{}", stats.summary());
+    println!("\n{}", "✓ Allocation SUCCESS".green().bold());
     Ok(())
 }
 
-fn cmd_assemble(_input: &PathBuf, _output: Option<PathBuf>) -> Result<()> {
-    println!("{}", "TODO: Implement assembler integration".yellow());
-    Ok(())
+/// The program a `.vpy` or `.vpyproj` compiles: the unified module of a
+/// project, or the single file with its enums resolved
+fn load_entry_module(input: &Path) -> Result<vpy_parser::Module> {
+    let parse = |path: &std::path::Path| -> Result<vpy_parser::Module> {
+        let source = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let tokens = vpy_parser::lex(&source).map_err(|e| anyhow::anyhow!("Lex error in {}: {}", path.display(), e))?;
+        vpy_parser::parser::parse(tokens, path.to_str().unwrap_or("unknown"))
+            .map_err(|e| anyhow::anyhow!("Parse error in {}: {}", path.display(), e))
+    };
+
+    if input.extension().and_then(|s| s.to_str()) != Some("vpyproj") {
+        return resolve_single_module_enums(parse(input)?, input);
+    }
+    let project_info = vpy_loader::load_project(input).context("Failed to load project")?;
+    let mut modules = std::collections::HashMap::new();
+    for source_file in &project_info.source_files {
+        let name = source_file.path.file_stem().and_then(|s| s.to_str()).unwrap_or("unknown").to_string();
+        modules.insert(name, parse(&source_file.path)?);
+    }
+    let entry = project_info.entry_point.file_stem().and_then(|s| s.to_str()).unwrap_or("main");
+    print_switch_warnings(&modules);
+    vpy_unifier::unify_modules(modules, entry).map_err(|e| anyhow::anyhow!("Unification error: {}", e))
 }
 
-fn cmd_link(_input: &PathBuf, _output: Option<PathBuf>) -> Result<()> {
-    println!("{}", "TODO: Implement linker integration".yellow());
-    Ok(())
+/// Print how many accesses the SETDP tracking turned into direct addressing
+fn print_direct_page_report(asm: &str) {
+    // Advisory only: a listing the first pass rejects is reported by the real build
+    let Ok(stats) = vpy_assembler::direct_page_stats(asm, 0) else {
+        return;
+    };
+    if stats.total() == 0 {
+        return;
+    }
+    for (page, count) in &stats.accesses {
+        println!("  {} Direct page ${:02X}: {} accesses", "✓".green(), page, count);
+    }
+    println!("    Saved {} bytes, {} cycles (one per access)", stats.bytes_saved(), stats.cycles_saved());
+}
+
+/// Print how many instructions the peephole rules rewrote, per rule with `--verbose`
+fn print_peephole_report(stats: &vpy_codegen::m6809::peephole::PeepholeStats, verbose: bool) {
+    if stats.total() == 0 {
+        return;
+    }
+    println!("  {} Peephole: {} rewrites", "✓".green(), stats.total());
+    if verbose {
+        for (rule, hits) in stats.hits.iter().filter(|(_, hits)| *hits > 0) {
+            println!("    {:<18} {}", rule, hits);
+        }
+    }
+}
+
+/// Print the `switch` statements over an enum that miss variants and have no `default`
+fn print_switch_warnings(modules: &std::collections::HashMap<String, vpy_parser::Module>) {
+    for warning in vpy_unifier::enums::check_switches(modules) {
+        println!("  {}", format!("⚠ {}", warning).yellow());
+    }
+}
+
+/// Single-file builds skip the unifier: check switches and resolve enum variants here
+fn resolve_single_module_enums(module: vpy_parser::Module, source_path: &std::path::Path) -> Result<vpy_parser::Module> {
+    let name = source_path.file_stem().and_then(|s| s.to_str()).unwrap_or("main").to_string();
+    let mut modules = std::collections::HashMap::from([(name.clone(), module)]);
+    print_switch_warnings(&modules);
+    vpy_unifier::enums::resolve_enums(&mut modules)
+        .map_err(|e| anyhow::anyhow!("Enum error: {}", e))?;
+    Ok(modules.remove(&name).expect("module inserted above"))
+}
+
+/// Print how much RAM the overlaid function locals take and the call chain that needs it
+fn print_locals_report(module: &vpy_parser::Module) {
+    let Ok(plan) = vpy_codegen::m6809::locals::LocalsPlan::from_module(module) else {
+        return;
+    };
+    if plan.size == 0 {
+        return;
+    }
+    println!("  {} Locals: {} bytes overlaid ({} without sharing)", "✓".green(), plan.size, plan.unshared);
+    println!("    Call chain with the most locals: {} ({} bytes)", plan.heaviest_chain.join(" -> "), plan.size);
+}
+
+/// Print the size of each music stream in the ROM and what the packed player costs
+fn print_music_report(asm: &str, assets: &[vpy_codegen::AssetInfo]) {
+    use vpy_codegen::musres::{MusicCompression, MusicResource};
+    for asset in assets.iter().filter(|a| a.asset_type == vpy_codegen::AssetType::Music) {
+        let symbol = asset.name.to_uppercase().replace(['-', ' '], "_");
+        if !asm.contains(&format!("_{}_MUSIC:", symbol)) {
+            continue;
+        }
+        let Ok(resource) = MusicResource::load(Path::new(&asset.path)) else {
+            continue;
+        };
+        let stats = resource.pack_stats();
+        if resource.compression == MusicCompression::Packed {
+            println!("  {} Music {}: {} bytes packed ({} plain, {:.0}%)", "✓".green(), asset.name,
+                stats.packed_bytes, stats.plain_bytes, stats.ratio() * 100.0);
+            println!("    Player: {} cycles/frame max, {} average", stats.max_cycles, stats.avg_cycles);
+        } else {
+            println!("  {} Music {}: {} bytes (\"compression\": \"packed\" would take {}, {} cycles/frame max)",
+                "✓".green(), asset.name, stats.plain_bytes, stats.packed_bytes, stats.max_cycles);
+        }
+    }
+}
+
+/// Source module of every function label (uppercase), prefixed the way the
+/// unifier names them; `MAIN` is the entry module's main program
+fn function_modules(modules: &std::collections::HashMap<String, vpy_parser::Module>, entry_module: &str) -> std::collections::HashMap<String, String> {
+    let mut resolver = vpy_unifier::SymbolResolver::new();
+    let mut owners = std::collections::HashMap::new();
+    for (module_name, module) in modules {
+        let prefix = resolver.register_module(module_name);
+        for item in &module.items {
+            let vpy_parser::Item::Function(func) = item else { continue };
+            let label = if prefix.is_empty() {
+                func.name.to_uppercase()
+            } else {
+                format!("{}_{}", prefix, func.name.to_uppercase())
+            };
+            owners.insert(label, module_name.clone());
+        }
+    }
+    owners.insert("MAIN".to_string(), entry_module.to_string());
+    owners
+}
+
+/// Link map of single-bank assembler output
+fn map_banks(binaries: &[vpy_assembler::BankBinary], capacity: usize) -> Vec<vpy_linker::MapBank> {
+    binaries.iter().map(|binary| vpy_linker::MapBank {
+        bank_id: binary.bank_id,
+        org: 0,
+        capacity,
+        used: binary.bytes.len(),
+        symbols: binary.symbols.iter().map(|(name, def)| (name.clone(), def.offset)).collect(),
+    }).collect()
+}
+
+/// Write `<name>.map` and `<name>.map.json` next to the ASM and print the ROM/RAM usage
+fn write_link_map(asm_path: &Path, map: &vpy_linker::LinkMap) {
+    let map_path = asm_path.with_extension("map");
+    if let Err(e) = map.write(&map_path) {
+        eprintln!("  ⚠ Failed to write link map {}: {}", map_path.display(), e);
+        return;
+    }
+    let rom_used: usize = map.banks.iter().map(|bank| bank.used).sum();
+    let rom_capacity: usize = map.banks.iter().map(|bank| bank.capacity).sum();
+    println!("  {} Link map: {}", "✓".green(), map_path.display());
+    println!("    ROM {} / {} bytes in {} bank(s), RAM {} bytes used ({} free)",
+        rom_used, rom_capacity, map.banks.len(), map.ram_used(), map.ram_free());
+    for bank in map.banks.iter().filter(|bank| bank.free < 0) {
+        println!("    {} Bank #{} overflows by {} bytes", "✗".red(), bank.bank, -bank.free);
+    }
 }
 
 fn cmd_build(input: &PathBuf, output: Option<PathBuf>, rom_size: usize, bank_size: usize, _debug: bool, verbose: bool) -> Result<()> {
@@ -758,6 +1091,8 @@ fn cmd_build(input: &PathBuf, output: Option<PathBuf>, rom_size: usize, bank_siz
             .and_then(|s| s.to_str())
             .unwrap_or("main");
         
+        print_switch_warnings(&modules);
+        let function_modules = function_modules(&modules, entry_module_name);
         let unified = vpy_unifier::unify_modules(modules, entry_module_name)
             .map_err(|e| anyhow::anyhow!("Unification error: {}", e))?;
         
@@ -787,6 +1122,10 @@ fn cmd_build(input: &PathBuf, output: Option<PathBuf>, rom_size: usize, bank_siz
             .map_err(|e| anyhow::anyhow!("Codegen error: {}", e))?;
         
         println!("  {} Generated {} bytes ASM", "✓".green(), generated.asm_source.len());
+        print_direct_page_report(&generated.asm_source);
+        print_peephole_report(&generated.peephole, verbose);
+        print_locals_report(&unified);
+        print_music_report(&generated.asm_source, &assets);
         
         // Determine output paths
         // CRITICAL FIX (2026-01-20): Detect project root directory properly
@@ -880,7 +1219,10 @@ fn cmd_build(input: &PathBuf, output: Option<PathBuf>, rom_size: usize, bank_siz
                 Some(include_dir.clone()) // include dir for VECTREX.I
             );
             
-            match linker.generate_multibank_rom(&asm_path, &output_path_mb) {
+            let mut map_banks = Vec::new();
+            let result = linker.generate_multibank_rom_with_map(&asm_path, &output_path_mb, &mut map_banks);
+            write_link_map(&asm_path, &vpy_linker::LinkMap::from_listing(&generated.asm_source, &function_modules, &assets, &map_banks));
+            match result {
                 Ok(_symbol_table) => {
                     println!("  {} Phase 6.7 SUCCESS: Multi-bank binary written to {}",
                         "✓".green(), output_path_mb.display());
@@ -956,6 +1298,8 @@ fn cmd_build(input: &PathBuf, output: Option<PathBuf>, rom_size: usize, bank_siz
             }
         }
         println!("  {} Assembled {} bank(s)", "✓".green(), binaries.len());
+        let map_banks = map_banks(&binaries, rom_size);
+        write_link_map(&asm_path, &vpy_linker::LinkMap::from_listing(&generated.asm_source, &function_modules, &assets, &map_banks));
         
         // Phase 7: Link ROM
         println!("\n{}", "Phase 7: Link ROM".bright_cyan().bold());
@@ -992,6 +1336,7 @@ fn cmd_build(input: &PathBuf, output: Option<PathBuf>, rom_size: usize, bank_siz
     
     let module = vpy_parser::parser::parse(tokens, source_path.to_str().unwrap_or("unknown"))
         .map_err(|e| anyhow::anyhow!("Parse error: {}", e))?;
+    let module = resolve_single_module_enums(module, &source_path)?;
     
     // Extract function names
     let mut functions = Vec::new();
@@ -1001,6 +1346,13 @@ fn cmd_build(input: &PathBuf, output: Option<PathBuf>, rom_size: usize, bank_siz
         }
     }
     
+    // Single file: no unifier prefixes, every function belongs to this module
+    let module_name = source_path.file_stem().and_then(|s| s.to_str()).unwrap_or("main");
+    let mut function_modules: std::collections::HashMap<String, String> = functions.iter()
+        .map(|name| (name.to_uppercase(), module_name.to_string()))
+        .collect();
+    function_modules.insert("MAIN".to_string(), module_name.to_string());
+    
     // Override rom_size and bank_size from META if specified
     let rom_size = module.meta.rom_total_size.map(|s| s as usize).unwrap_or(rom_size);
     let bank_size = module.meta.rom_bank_size.map(|s| s as usize).unwrap_or(bank_size);
@@ -1032,6 +1384,10 @@ fn cmd_build(input: &PathBuf, output: Option<PathBuf>, rom_size: usize, bank_siz
     if verbose {
         println!("  ASM size: {} bytes", generated.asm_source.len());
         println!("  Symbols: {}", generated.symbols.len());
+        print_direct_page_report(&generated.asm_source);
+        print_peephole_report(&generated.peephole, verbose);
+        print_locals_report(&module);
+        print_music_report(&generated.asm_source, &assets);
     }
     
     // Phase 2: Parse unified ASM into bank sections
@@ -1130,7 +1486,10 @@ fn cmd_build(input: &PathBuf, output: Option<PathBuf>, rom_size: usize, bank_siz
             Some(include_dir.clone()) // include dir for VECTREX.I
         );
         
-        match linker.generate_multibank_rom(&asm_path, &output_path_mb) {
+        let mut map_banks = Vec::new();
+        let result = linker.generate_multibank_rom_with_map(&asm_path, &output_path_mb, &mut map_banks);
+        write_link_map(&asm_path, &vpy_linker::LinkMap::from_listing(&generated.asm_source, &function_modules, &assets, &map_banks));
+        match result {
             Ok(_symbol_table) => {
                 println!("  {} Phase 6.7 SUCCESS: Multi-bank binary written to {}",
                     "✓".green(), output_path_mb.display());
@@ -1195,6 +1554,8 @@ fn cmd_build(input: &PathBuf, output: Option<PathBuf>, rom_size: usize, bank_siz
             println!("    Bank {}: {} bytes", binary.bank_id, binary.bytes.len());
         }
     }
+    let map_banks = map_banks(&binaries, rom_size);
+    write_link_map(&asm_path, &vpy_linker::LinkMap::from_listing(&generated.asm_source, &function_modules, &assets, &map_banks));
     
     // Phase 4: Link banks into ROM
     if verbose {
@@ -0,0 +1,155 @@
+//! `vpy_cli assemble` / `vpy_cli link`: separate compilation through `.vo` objects
+//!
+//! `assemble` turns each `.asm` or `.vpy` into a relocatable
+//! `vpy_linker::VectrexObject`: labels named by `EXPORT` are global,
+//! everything else stays local to its object, and undefined names become
+//! imports. A `.vpy` with `main()` is the single-bank program and exports
+//! its RAM variables and runtime helpers; any other `.vpy` is a module of
+//! functions and data that imports them (see `vpy_codegen::m6809::objects`)
+//! and keeps its locals in a BSS section. `link` places the objects one
+//! after another from $0000, so the first one must start with the cartridge
+//! header, puts BSS sections in RAM from $C880 and writes a link map
+//! (`.map` / `.map.json`) next to the ROM.
+
+use anyhow::{Context, Result};
+use colored::*;
+use std::path::{Path, PathBuf};
+use vpy_codegen::m6809::objects::ObjectAsm;
+use vpy_linker::object::{Section, SectionType, Symbol, SymbolScope, SymbolType};
+use vpy_linker::{LinkMap, VectrexObject};
+
+/// Largest ROM the object linker produces (no bank switching)
+const MAX_ROM_SIZE: usize = 32768;
+
+pub fn cmd_assemble(inputs: &[PathBuf], output: Option<PathBuf>) -> Result<()> {
+    let include_dir = crate::resolve_include_dir();
+    vpy_assembler::set_include_dir(Some(include_dir));
+
+    for input in inputs {
+        let out_path = object_path(input, inputs.len(), output.as_deref());
+        let unit = match input.extension().and_then(|s| s.to_str()) {
+            Some("vpy") => generate_object(input)?,
+            _ => ObjectAsm {
+                asm_source: std::fs::read_to_string(input)
+                    .with_context(|| format!("Failed to read {}", input.display()))?,
+                ram_size: 0,
+                ram: Vec::new(),
+            },
+        };
+
+        let mut obj = vpy_linker::object_from_asm(&unit.asm_source, &input.display().to_string())
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        add_ram_section(&mut obj, &unit);
+        obj.save(&out_path)
+            .with_context(|| format!("Failed to write {}", out_path.display()))?;
+
+        let exports = obj.symbols.exports.iter().filter(|s| s.scope == SymbolScope::Global).count();
+        println!(
+            "  {} {} → {} ({} bytes, {} exports, {} imports, {} relocations)",
+            "✓".green(),
+            input.display(),
+            out_path.display().to_string().bright_yellow(),
+            obj.total_size(),
+            exports,
+            obj.symbols.imports.len(),
+            obj.relocations.len()
+        );
+    }
+
+    println!("\n{}", "✓ Assemble SUCCESS".green().bold());
+    Ok(())
+}
+
+pub fn cmd_link(inputs: &[PathBuf], output: Option<PathBuf>) -> Result<()> {
+    let out_path = output.unwrap_or_else(|| inputs[0].with_extension("bin"));
+    let objects = inputs
+        .iter()
+        .map(|path| {
+            VectrexObject::load(path).map_err(|e| anyhow::anyhow!("Failed to load '{}': {}", path.display(), e))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let linked = vpy_linker::linker::link(objects.clone(), 0x0000).map_err(|e| anyhow::anyhow!("{}", e))?;
+    if let Some(parent) = out_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    // The map is written first so an oversized link can be inspected
+    let map = LinkMap::from_objects(&objects, &linked, MAX_ROM_SIZE);
+    let map_path = out_path.with_extension("map");
+    map.write(&map_path).with_context(|| format!("Failed to write {}", map_path.display()))?;
+
+    if linked.binary.len() > MAX_ROM_SIZE {
+        anyhow::bail!(
+            "Linked code is {} bytes, more than the {} bytes of a single-bank ROM",
+            linked.binary.len(),
+            MAX_ROM_SIZE
+        );
+    }
+
+    std::fs::write(&out_path, &linked.binary)
+        .with_context(|| format!("Failed to write {}", out_path.display()))?;
+
+    for input in inputs {
+        println!("  {} {}", "+".bright_black(), input.display());
+    }
+    println!("  Global symbols: {}", linked.symbols.len());
+    println!("  Link map: {}", map_path.display());
+    println!("\n{}", format!("✓ ROM written to {} ({} bytes)", out_path.display(), linked.binary.len()).green().bold());
+    Ok(())
+}
+
+/// `-o` names the object for a single input, or the directory for several
+fn object_path(input: &Path, count: usize, output: Option<&Path>) -> PathBuf {
+    match output {
+        Some(out) if count == 1 && !out.is_dir() => out.to_path_buf(),
+        Some(dir) => dir.join(input.file_name().unwrap_or_default()).with_extension("vo"),
+        None => input.with_extension("vo"),
+    }
+}
+
+/// The unit's RAM as a BSS section: the linker places these one after
+/// another from $C880, so the program (linked first) keeps the addresses its
+/// EQUs already use and each module's locals follow
+fn add_ram_section(obj: &mut VectrexObject, unit: &ObjectAsm) {
+    if unit.ram_size == 0 {
+        return;
+    }
+    let section = obj.sections.len();
+    obj.sections.push(Section {
+        name: ".bss".to_string(),
+        section_type: SectionType::Bss,
+        bank_hint: None,
+        alignment: unit.ram_size as u16, // BSS size
+        data: Vec::new(),
+    });
+    for slot in &unit.ram {
+        obj.symbols.imports.retain(|import| import.name != slot.name);
+        obj.symbols.exports.push(Symbol {
+            name: slot.name.clone(),
+            section: Some(section),
+            offset: slot.address,
+            scope: SymbolScope::Local,
+            symbol_type: SymbolType::Variable,
+        });
+    }
+}
+
+/// Generate a `.vpy` file as one relocatable unit: the program, or a module
+/// of functions and data using the program's runtime
+fn generate_object(input: &Path) -> Result<ObjectAsm> {
+    let source = std::fs::read_to_string(input)
+        .with_context(|| format!("Failed to read {}", input.display()))?;
+    let tokens = vpy_parser::lex(&source).map_err(|e| anyhow::anyhow!("Lex error: {}", e))?;
+    let module = vpy_parser::parser::parse(tokens, input.to_str().unwrap_or("unknown"))
+        .map_err(|e| anyhow::anyhow!("Parse error: {}", e))?;
+
+    if module.meta.rom_total_size.is_some_and(|size| size as usize > MAX_ROM_SIZE) {
+        anyhow::bail!("{}: multibank programs cannot be assembled to objects", input.display());
+    }
+
+    let title = module.meta.title_override.clone().unwrap_or_else(|| {
+        input.file_stem().and_then(|s| s.to_str()).unwrap_or("VPY GAME").to_string()
+    });
+    let assets = crate::discover_assets(input);
+    vpy_codegen::generate_object_from_module(&module, &title, &assets).context("Failed to generate ASM")
+}
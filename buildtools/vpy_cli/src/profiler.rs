@@ -0,0 +1,658 @@
+//! `vpy_cli profile`: runtime frame profiler
+//!
+//! Boots a ROM in `vectrex_emulator`, runs it for N frames and charges the
+//! cycles of every executed instruction to the routine and VPy line it
+//! belongs to, using the `.pdb` written next to the binary. Calls are
+//! followed on a shadow stack (JSR/BSR/LBSR push a frame, popping its return
+//! address ends it), which gives flat and call-tree reports plus a folded
+//! stacks file for flamegraph tools. Frames whose busy time (everything but
+//! the `Wait_Recal` wait) exceeds the refresh budget are flagged as overruns.
+
+use anyhow::{Context, Result};
+use colored::*;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use vectrex_emulator::cpu::BIOS_WAIT_RECAL;
+use vectrex_emulator::{Vectrex, CYCLES_PER_FRAME};
+
+use crate::test_runner::load_bios;
+
+/// First address of the BIOS ROM
+const BIOS_START: u16 = 0xE000;
+/// A frame this many refresh periods long means `Wait_Recal` is no longer called
+const STALL_FRAMES: u64 = 50;
+/// Rows printed per table unless `--verbose`
+const TOP_ROWS: usize = 20;
+
+pub struct ProfileOptions {
+    pub pdb: Option<PathBuf>,
+    /// Frames to profile after start-up
+    pub frames: u64,
+    /// Busy cycles allowed per frame
+    pub budget: u64,
+    /// Prefix for `<prefix>.folded` and `<prefix>.profile.json` (default: the ROM path)
+    pub output: Option<PathBuf>,
+    pub verbose: bool,
+}
+
+pub fn cmd_profile(rom_path: &Path, options: &ProfileOptions) -> Result<()> {
+    let rom = std::fs::read(rom_path).with_context(|| format!("Failed to read ROM {}", rom_path.display()))?;
+    let pdb_path = options.pdb.clone().unwrap_or_else(|| rom_path.with_extension("pdb"));
+    let text = std::fs::read_to_string(&pdb_path)
+        .with_context(|| format!("Failed to read debug symbols {} (build with --bin to get one)", pdb_path.display()))?;
+    let mut symbols = SymbolMap::from_pdb(&text).with_context(|| format!("Invalid .pdb {}", pdb_path.display()))?;
+    if symbols.bios.is_empty() {
+        let vectrex_i = crate::resolve_include_dir().join("VECTREX.I");
+        if let Ok(content) = std::fs::read_to_string(&vectrex_i) {
+            symbols.add_bios_symbols(&vpy_debug_gen::parse_bios_symbols(&content));
+        }
+    }
+    if options.verbose {
+        println!(
+            "  Symbols: {} routines, {} VPy functions, {} lines, {} BIOS entries",
+            symbols.routines.len(),
+            symbols.vpy_functions.len(),
+            symbols.lines.len(),
+            symbols.bios.len()
+        );
+    }
+
+    let profile = run_profile(&load_bios(rom_path)?, &rom, &symbols, options.frames)?;
+    let rows = if options.verbose { usize::MAX } else { TOP_ROWS };
+    let min_share = if options.verbose { 0.0 } else { 0.001 };
+
+    println!("\n{}", "Flat profile".bright_cyan().bold());
+    print!("{}", profile.format_flat(rows));
+    println!("\n{}", "Call tree".bright_cyan().bold());
+    print!("{}", profile.format_tree(min_share));
+    if !profile.lines.is_empty() {
+        println!("\n{}", "Hot lines".bright_cyan().bold());
+        print!("{}", profile.format_lines(rows));
+    }
+    println!("\n{}", "Frames".bright_cyan().bold());
+    print!("{}", profile.format_frames(options.budget));
+
+    let prefix = options.output.clone().unwrap_or_else(|| rom_path.to_path_buf());
+    let folded_path = prefix.with_extension("folded");
+    let json_path = prefix.with_extension("profile.json");
+    std::fs::write(&folded_path, profile.to_folded())
+        .with_context(|| format!("Failed to write {}", folded_path.display()))?;
+    std::fs::write(&json_path, serde_json::to_string_pretty(&profile.to_json(options.budget))?)
+        .with_context(|| format!("Failed to write {}", json_path.display()))?;
+    println!("\n  Folded stacks: {}", folded_path.display());
+    println!("  JSON report:   {}", json_path.display());
+
+    let overruns = profile.overruns(options.budget).count();
+    if overruns > 0 {
+        println!("\n{}", format!("⚠ {} of {} frames overran the {} cycle budget", overruns, options.frames, options.budget).bright_yellow().bold());
+    } else {
+        println!("\n{}", format!("✓ {} frames profiled, none over budget", options.frames).green().bold());
+    }
+    Ok(())
+}
+
+/// Boot `rom` and profile start-up plus `frames` frames.
+pub fn run_profile<'a>(bios: &[u8], rom: &[u8], symbols: &'a SymbolMap, frames: u64) -> Result<Profile<'a>> {
+    let mut vectrex = Vectrex::new(bios)?;
+    vectrex.load_cartridge(rom);
+    vectrex.boot_cartridge()?;
+    // Skip the header's jump so the tree is rooted at the entry point (main)
+    if let Some(entry) = symbols.entry {
+        let start = vectrex.cpu.cycles;
+        while vectrex.cpu.pc != entry && vectrex.cpu.cycles - start < CYCLES_PER_FRAME {
+            vectrex.step()?;
+        }
+    }
+
+    let mut profile = Profile::new(symbols, vectrex.cpu.pc);
+    let mut frame_count = vectrex.cpu.frame_count;
+    while (profile.frames.len() as u64) <= frames {
+        let sample_pc = vectrex.cpu.pc;
+        let opcode = vectrex.peek(sample_pc);
+        let sp_before = vectrex.cpu.s;
+        let cycles = vectrex.step()?;
+        profile.record(Sample { pc: sample_pc, opcode, cycles, sp_before, sp_after: vectrex.cpu.s, next_pc: vectrex.cpu.pc });
+        if vectrex.cpu.frame_count != frame_count {
+            frame_count = vectrex.cpu.frame_count;
+            profile.end_frame();
+        } else if profile.frame.cycles > STALL_FRAMES * CYCLES_PER_FRAME {
+            anyhow::bail!(
+                "no Wait_Recal for {} cycles in frame {} (PC=${:04X})",
+                profile.frame.cycles,
+                profile.frame.number,
+                vectrex.cpu.pc
+            );
+        }
+    }
+    Ok(profile)
+}
+
+/// Address → name lookup built from a `.pdb`
+#[derive(Debug, Default)]
+pub struct SymbolMap {
+    /// Routine entry points: VPy functions, runtime helpers and other labels
+    routines: HashMap<u16, String>,
+    /// Entry addresses of VPy functions (the frames lines are charged to)
+    vpy_functions: HashSet<u16>,
+    /// Address of the first instruction of each VPy line
+    lines: BTreeMap<u16, usize>,
+    /// BIOS routine entry points, for code reached by JMP from a wrapper
+    bios: BTreeMap<u16, String>,
+    /// Program entry (`START`), where the call tree is rooted
+    entry: Option<u16>,
+}
+
+impl SymbolMap {
+    /// Parse either PDB flavour: the compiler's `DebugInfo` (hex strings,
+    /// `vpyLineMap`) or `vpy_debug_gen::PdbFile` (numeric `labels`/`functions`).
+    pub fn from_pdb(text: &str) -> Result<Self> {
+        let pdb: Value = serde_json::from_str(text)?;
+        let mut map = SymbolMap::default();
+        for key in ["symbols", "labels"] {
+            for (name, value) in pdb.get(key).and_then(Value::as_object).into_iter().flatten() {
+                // Local labels (`J1B1_BUILTIN.J1B1_OFF`) are not routines
+                if let (Some(addr), false) = (parse_address(value), name.contains('.')) {
+                    map.routines.entry(addr).or_insert_with(|| name.clone());
+                }
+            }
+        }
+        for (name, value) in pdb.get("functions").and_then(Value::as_object).into_iter().flatten() {
+            // DebugInfo: {"address": "0x00F5", "type": "vpy", ...}; PdbFile: plain address
+            let (addr, vpy) = match value.get("address") {
+                Some(addr) => (parse_address(addr), value.get("type").and_then(Value::as_str) == Some("vpy")),
+                None => (parse_address(value), false),
+            };
+            if let Some(addr) = addr {
+                map.routines.insert(addr, name.clone());
+                if vpy {
+                    map.vpy_functions.insert(addr);
+                }
+            }
+        }
+        for entry in pdb.get("vpyLineMap").and_then(Value::as_object).into_iter().flatten().map(|(_, e)| e) {
+            let addr = entry.get("address").and_then(parse_address);
+            let line = entry.get("line").and_then(Value::as_u64);
+            if let (Some(addr), Some(line)) = (addr, line) {
+                map.lines.insert(addr, line as usize);
+            }
+        }
+        if let Some(bios) = pdb.get("bios_symbols").and_then(Value::as_object) {
+            let bios = bios.iter().filter_map(|(n, v)| Some((n.clone(), v.as_u64()? as u32))).collect();
+            map.add_bios_symbols(&bios);
+        }
+        map.entry = pdb.get("entryPoint").and_then(parse_address);
+        Ok(map)
+    }
+
+    /// Register BIOS entry points (VECTREX.I `EQU`s); RAM and I/O equates are ignored.
+    pub fn add_bios_symbols(&mut self, symbols: &HashMap<String, u32>) {
+        let mut sorted: Vec<_> = symbols.iter().collect();
+        sorted.sort();
+        for (name, &addr) in sorted {
+            if let Ok(addr) = u16::try_from(addr) {
+                if addr >= BIOS_START {
+                    self.bios.entry(addr).or_insert_with(|| name.clone());
+                }
+            }
+        }
+    }
+
+    /// Name of the routine starting at `addr`
+    fn routine_name(&self, addr: u16) -> String {
+        self.routines
+            .get(&addr)
+            .map(String::as_str)
+            .or_else(|| if addr >= BIOS_START { self.bios_routine(addr) } else { None })
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("${:04X}", addr))
+    }
+
+    /// BIOS routine containing `pc`
+    fn bios_routine(&self, pc: u16) -> Option<&str> {
+        self.bios.range(..=pc).next_back().map(|(_, name)| name.as_str())
+    }
+
+    /// VPy line of `pc` inside the function starting at `entry`
+    fn line_at(&self, entry: u16, pc: u16) -> Option<usize> {
+        if pc < entry {
+            return None;
+        }
+        self.lines.range(entry..=pc).next_back().map(|(_, line)| *line)
+    }
+}
+
+/// `"0x00F5"`, `"$00F5"` or a plain number; banked (> 16 bit) addresses are skipped
+fn parse_address(value: &Value) -> Option<u16> {
+    match value {
+        Value::String(s) => {
+            let hex = s.strip_prefix("0x").or_else(|| s.strip_prefix('$'))?;
+            u16::from_str_radix(hex, 16).ok()
+        }
+        Value::Number(n) => u16::try_from(n.as_u64()?).ok(),
+        _ => None,
+    }
+}
+
+/// One executed instruction as seen by the profiler
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    pub pc: u16,
+    /// First opcode byte at `pc`
+    pub opcode: u8,
+    pub cycles: u64,
+    pub sp_before: u16,
+    pub sp_after: u16,
+    pub next_pc: u16,
+}
+
+impl Sample {
+    /// JSR/BSR/LBSR that pushed its return address (not an interrupt entry)
+    fn is_call(&self) -> bool {
+        matches!(self.opcode, 0x8D | 0x9D | 0xAD | 0xBD | 0x17) && self.sp_after == self.sp_before.wrapping_sub(2)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct CallFrame {
+    name: String,
+    entry: u16,
+    /// S after the call: the frame ends once S moves above it (return address popped)
+    sp: u16,
+    /// Address of the calling instruction
+    call_site: u16,
+    /// `caller;...;name`, the folded-stacks key
+    path: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RoutineStats {
+    pub self_cycles: u64,
+    /// Cycles with the routine anywhere on the stack
+    pub total_cycles: u64,
+    pub calls: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct LineStats {
+    pub function: String,
+    /// Cycles of the line including its callees
+    pub cycles: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FrameStats {
+    /// 0 = start-up (before the first `Wait_Recal`)
+    pub number: u64,
+    pub cycles: u64,
+    /// Cycles outside `Wait_Recal`
+    pub busy: u64,
+}
+
+pub struct Profile<'a> {
+    symbols: &'a SymbolMap,
+    stack: Vec<CallFrame>,
+    pub routines: HashMap<String, RoutineStats>,
+    pub folded: HashMap<String, u64>,
+    pub lines: BTreeMap<usize, LineStats>,
+    pub frames: Vec<FrameStats>,
+    frame: FrameStats,
+    pub total: u64,
+}
+
+impl<'a> Profile<'a> {
+    /// Start profiling with the routine at `entry` as the root of the call tree.
+    pub fn new(symbols: &'a SymbolMap, entry: u16) -> Self {
+        let name = symbols.routine_name(entry);
+        let root = CallFrame { path: name.clone(), name, entry, sp: u16::MAX, call_site: entry };
+        Profile {
+            symbols,
+            stack: vec![root],
+            routines: HashMap::new(),
+            folded: HashMap::new(),
+            lines: BTreeMap::new(),
+            frames: Vec::new(),
+            frame: FrameStats::default(),
+            total: 0,
+        }
+    }
+
+    /// Charge one instruction to the current stack, then follow calls and returns.
+    pub fn record(&mut self, sample: Sample) {
+        self.charge(sample.pc, sample.cycles);
+        while self.stack.len() > 1 && self.stack.last().is_some_and(|f| sample.sp_after > f.sp) {
+            self.stack.pop();
+        }
+        if sample.is_call() {
+            let name = self.symbols.routine_name(sample.next_pc);
+            self.routines.entry(name.clone()).or_default().calls += 1;
+            let path = format!("{};{}", self.stack.last().map_or("", |f| f.path.as_str()), name);
+            self.stack.push(CallFrame { name, entry: sample.next_pc, sp: sample.sp_after, call_site: sample.pc, path });
+        }
+    }
+
+    /// Close the current frame (called when `Wait_Recal` is entered).
+    pub fn end_frame(&mut self) {
+        let number = self.frame.number;
+        self.frames.push(std::mem::take(&mut self.frame));
+        self.frame.number = number + 1;
+    }
+
+    fn charge(&mut self, pc: u16, cycles: u64) {
+        self.total += cycles;
+        self.frame.cycles += cycles;
+        if !self.stack.iter().any(|f| f.entry == BIOS_WAIT_RECAL) {
+            self.frame.busy += cycles;
+        }
+
+        let top = self.stack.last().expect("root frame");
+        // BIOS code reached by JMP from a wrapper (e.g. __Moveto_d → Moveto_d)
+        let bios_leaf = if pc >= BIOS_START && top.entry < BIOS_START { self.symbols.bios_routine(pc) } else { None };
+        let (key, leaf) = match bios_leaf {
+            Some(name) => (format!("{};{}", top.path, name), name),
+            None => (top.path.clone(), top.name.as_str()),
+        };
+        *self.folded.entry(key).or_default() += cycles;
+        self.routines.entry(leaf.to_string()).or_default().self_cycles += cycles;
+
+        let mut seen: Vec<&str> = Vec::with_capacity(self.stack.len() + 1);
+        for name in self.stack.iter().map(|f| f.name.as_str()).chain(bios_leaf) {
+            if !seen.contains(&name) {
+                seen.push(name);
+                self.routines.entry(name.to_string()).or_default().total_cycles += cycles;
+            }
+        }
+
+        // Lines are charged inclusively to the innermost VPy function on the stack
+        for (i, frame) in self.stack.iter().enumerate().rev() {
+            if self.symbols.vpy_functions.contains(&frame.entry) {
+                let at = self.stack.get(i + 1).map_or(pc, |callee| callee.call_site);
+                if let Some(line) = self.symbols.line_at(frame.entry, at) {
+                    self.lines
+                        .entry(line)
+                        .or_insert_with(|| LineStats { function: frame.name.clone(), cycles: 0 })
+                        .cycles += cycles;
+                }
+                break;
+            }
+        }
+    }
+
+    /// Profiled frames after start-up
+    fn game_frames(&self) -> impl Iterator<Item = &FrameStats> {
+        self.frames.iter().filter(|f| f.number > 0)
+    }
+
+    pub fn overruns(&self, budget: u64) -> impl Iterator<Item = &FrameStats> {
+        self.game_frames().filter(move |f| f.busy > budget)
+    }
+
+    fn percent(&self, cycles: u64) -> f64 {
+        if self.total == 0 { 0.0 } else { cycles as f64 * 100.0 / self.total as f64 }
+    }
+
+    /// Routines by self time
+    pub fn format_flat(&self, rows: usize) -> String {
+        let frames = self.game_frames().count().max(1) as u64;
+        let mut sorted: Vec<_> = self.routines.iter().filter(|(_, s)| s.total_cycles > 0).collect();
+        sorted.sort_by(|a, b| b.1.self_cycles.cmp(&a.1.self_cycles).then(a.0.cmp(b.0)));
+        let mut out = format!("  {:>6} {:>11} {:>6} {:>11} {:>8} {:>9}  routine\n", "self%", "self", "total%", "total", "calls", "cyc/frame");
+        for (name, stats) in sorted.into_iter().take(rows) {
+            out.push_str(&format!(
+                "  {:>5.1}% {:>11} {:>5.1}% {:>11} {:>8} {:>9}  {}\n",
+                self.percent(stats.self_cycles),
+                stats.self_cycles,
+                self.percent(stats.total_cycles),
+                stats.total_cycles,
+                stats.calls,
+                stats.total_cycles / frames,
+                name
+            ));
+        }
+        out
+    }
+
+    /// Inclusive call tree; nodes below `min_share` of all cycles are omitted
+    pub fn format_tree(&self, min_share: f64) -> String {
+        #[derive(Default)]
+        struct Node {
+            total: u64,
+            self_cycles: u64,
+            children: BTreeMap<String, Node>,
+        }
+        fn write(profile: &Profile, node: &Node, depth: usize, min: u64, out: &mut String) {
+            let mut children: Vec<_> = node.children.iter().filter(|(_, c)| c.total >= min).collect();
+            children.sort_by(|a, b| b.1.total.cmp(&a.1.total).then(a.0.cmp(b.0)));
+            for (name, child) in children {
+                out.push_str(&format!(
+                    "  {:>5.1}% {:>11}  {}{} (self {})\n",
+                    profile.percent(child.total),
+                    child.total,
+                    "  ".repeat(depth),
+                    name,
+                    child.self_cycles
+                ));
+                write(profile, child, depth + 1, min, out);
+            }
+        }
+
+        let mut root = Node::default();
+        for (path, &cycles) in &self.folded {
+            let mut node = &mut root;
+            for name in path.split(';') {
+                node = node.children.entry(name.to_string()).or_default();
+                node.total += cycles;
+            }
+            node.self_cycles += cycles;
+        }
+        let mut out = String::new();
+        write(self, &root, 0, ((self.total as f64 * min_share) as u64).max(1), &mut out);
+        out
+    }
+
+    /// VPy lines by inclusive cycles
+    pub fn format_lines(&self, rows: usize) -> String {
+        let frames = self.game_frames().count().max(1) as u64;
+        let mut sorted: Vec<_> = self.lines.iter().collect();
+        sorted.sort_by(|a, b| b.1.cycles.cmp(&a.1.cycles).then(a.0.cmp(b.0)));
+        let mut out = format!("  {:>6} {:>11} {:>9}  {:>5}  function\n", "total%", "cycles", "cyc/frame", "line");
+        for (line, stats) in sorted.into_iter().take(rows) {
+            out.push_str(&format!(
+                "  {:>5.1}% {:>11} {:>9}  {:>5}  {}\n",
+                self.percent(stats.cycles),
+                stats.cycles,
+                stats.cycles / frames,
+                line,
+                stats.function
+            ));
+        }
+        out
+    }
+
+    /// Busy time per frame and the frames over `budget`
+    pub fn format_frames(&self, budget: u64) -> String {
+        let busy: Vec<u64> = self.game_frames().map(|f| f.busy).collect();
+        let mut out = String::new();
+        if let Some(setup) = self.frames.first().filter(|f| f.number == 0) {
+            out.push_str(&format!("  start-up: {} cycles\n", setup.cycles));
+        }
+        if busy.is_empty() {
+            return out;
+        }
+        let avg = busy.iter().sum::<u64>() / busy.len() as u64;
+        let max = busy.iter().copied().max().unwrap_or(0);
+        out.push_str(&format!(
+            "  busy cycles per frame: avg {} ({:.0}% of {}), max {}\n",
+            avg,
+            avg as f64 * 100.0 / budget as f64,
+            budget,
+            max
+        ));
+        for frame in self.overruns(budget) {
+            out.push_str(&format!(
+                "  ⚠ frame {}: {} busy cycles ({:+} over budget)\n",
+                frame.number,
+                frame.busy,
+                frame.busy as i64 - budget as i64
+            ));
+        }
+        out
+    }
+
+    /// Folded stacks (`main;loop;work 1234`), one per line, for flamegraph.pl / inferno / speedscope
+    pub fn to_folded(&self) -> String {
+        let mut stacks: Vec<_> = self.folded.iter().collect();
+        stacks.sort();
+        stacks.into_iter().map(|(path, cycles)| format!("{} {}\n", path, cycles)).collect()
+    }
+
+    pub fn to_json(&self, budget: u64) -> Value {
+        let mut routines: Vec<_> = self.routines.iter().collect();
+        routines.sort_by(|a, b| b.1.self_cycles.cmp(&a.1.self_cycles).then(a.0.cmp(b.0)));
+        json!({
+            "frame_budget": budget,
+            "total_cycles": self.total,
+            "frames": self.frames.iter().map(|f| json!({
+                "number": f.number,
+                "cycles": f.cycles,
+                "busy": f.busy,
+                "overrun": f.number > 0 && f.busy > budget,
+            })).collect::<Vec<_>>(),
+            "routines": routines.into_iter().map(|(name, s)| json!({
+                "name": name,
+                "self_cycles": s.self_cycles,
+                "total_cycles": s.total_cycles,
+                "calls": s.calls,
+            })).collect::<Vec<_>>(),
+            "lines": self.lines.iter().map(|(line, s)| json!({
+                "line": line,
+                "function": s.function,
+                "cycles": s.cycles,
+            })).collect::<Vec<_>>(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CORE_PDB: &str = r#"{
+        "entryPoint": "0x00A6",
+        "symbols": {"START": "0x00A6", "WORK": "0x00F5", "LOOP_BODY": "0x01A7",
+                    "__Moveto_d": "0x0090", "J1B1_BUILTIN.J1B1_OFF": "0x0053"},
+        "functions": {
+            "main": {"name": "main", "address": "0x00A6", "startLine": 11, "endLine": 12, "type": "vpy"},
+            "work": {"name": "work", "address": "0x00F5", "startLine": 3, "endLine": 9, "type": "vpy"},
+            "loop": {"name": "loop", "address": "0x01A7", "startLine": 14, "endLine": 17, "type": "vpy"}
+        },
+        "vpyLineMap": {
+            "0x00F5": {"file": "g.vpy", "address": "0x00F5", "line": 3},
+            "0x0100": {"file": "g.vpy", "address": "0x0100", "line": 4},
+            "0x01B3": {"file": "g.vpy", "address": "0x01B3", "line": 15},
+            "0x01CD": {"file": "g.vpy", "address": "0x01CD", "line": 16}
+        }
+    }"#;
+
+    fn symbols() -> SymbolMap {
+        let mut map = SymbolMap::from_pdb(CORE_PDB).unwrap();
+        let bios = [("Wait_Recal", 0xF192), ("Moveto_d", 0xF312), ("Vec_Btn_State", 0xC80F)];
+        map.add_bios_symbols(&bios.iter().map(|(n, a)| (n.to_string(), *a)).collect());
+        map
+    }
+
+    fn step(pc: u16, cycles: u64, sp: u16) -> Sample {
+        Sample { pc, opcode: 0x12, cycles, sp_before: sp, sp_after: sp, next_pc: pc + 1 }
+    }
+
+    fn call(pc: u16, target: u16, sp: u16) -> Sample {
+        Sample { pc, opcode: 0xBD, cycles: 8, sp_before: sp, sp_after: sp - 2, next_pc: target }
+    }
+
+    fn ret(pc: u16, sp: u16) -> Sample {
+        Sample { pc, opcode: 0x39, cycles: 5, sp_before: sp, sp_after: sp + 2, next_pc: 0 }
+    }
+
+    #[test]
+    fn reads_both_pdb_flavours() {
+        let core = symbols();
+        assert_eq!(core.routine_name(0x00F5), "work");
+        assert_eq!(core.routine_name(0x0090), "__Moveto_d");
+        assert_eq!(core.routine_name(0xF192), "Wait_Recal");
+        assert_eq!(core.routine_name(0x1234), "$1234");
+        assert!(core.vpy_functions.contains(&0x01A7));
+        assert_eq!(core.line_at(0x00F5, 0x0105), Some(4));
+        assert_eq!(core.line_at(0x01A7, 0x01B0), None);
+        assert!(!core.bios.values().any(|n| n == "Vec_Btn_State"));
+
+        let buildtools = r#"{"version": "1.0", "labels": {"WORK": 245, "IF_END_3": 260},
+            "functions": {"WORK": 245}, "bios_symbols": {"Wait_Recal": 61842}, "symbols": {},
+            "source_lines": {}, "variables": {}, "vpy_line_map": {}, "asm_line_map": {}}"#;
+        let map = SymbolMap::from_pdb(buildtools).unwrap();
+        assert_eq!(map.routine_name(245), "WORK");
+        assert_eq!(map.routine_name(0xF192), "Wait_Recal");
+        assert!(map.vpy_functions.is_empty());
+    }
+
+    #[test]
+    fn builds_flat_tree_and_folded_stacks() {
+        let symbols = symbols();
+        let mut p = Profile::new(&symbols, 0x00A6);
+        p.record(step(0x00A8, 10, 0xCBEA));
+        p.record(call(0x00C0, 0x01A7, 0xCBEA)); // main → loop
+        p.record(step(0x01B3, 4, 0xCBE8)); // line 15
+        p.record(call(0x01B5, 0x00F5, 0xCBE8)); // loop → work (line 15)
+        p.record(step(0x0100, 100, 0xCBE6)); // work line 4
+        p.record(ret(0x0110, 0xCBE6));
+        p.record(call(0x01CD, 0x0090, 0xCBE8)); // loop → __Moveto_d (line 16)
+        p.record(step(0x0090, 3, 0xCBE6));
+        p.record(step(0xF312, 40, 0xCBE6)); // JMP'd into BIOS
+        p.record(ret(0xF320, 0xCBE6));
+        p.record(ret(0x01D0, 0xCBE8)); // back in main
+
+        assert_eq!(p.total, 10 + 8 + 4 + 8 + 100 + 5 + 8 + 3 + 40 + 5 + 5);
+        assert_eq!(p.routines["work"].self_cycles, 105);
+        assert_eq!(p.routines["work"].calls, 1);
+        assert_eq!(p.routines["loop"].total_cycles, p.total - 18);
+        assert_eq!(p.routines["Moveto_d"].self_cycles, 45);
+        assert_eq!(p.routines["__Moveto_d"].total_cycles, 48);
+        assert_eq!(p.stack.len(), 1);
+
+        // work is charged to its own line; the callers' lines include it
+        assert_eq!(p.lines[&4].cycles, 105);
+        assert_eq!(p.lines[&4].function, "work");
+        assert_eq!(p.lines[&15].cycles, 4 + 8);
+        assert_eq!(p.lines[&16].cycles, 8 + 48 + 5);
+
+        let folded = p.to_folded();
+        assert!(folded.contains("main;loop;work 105\n"));
+        assert!(folded.contains("main;loop;__Moveto_d;Moveto_d 45\n"));
+        let tree = p.format_tree(0.0);
+        assert!(tree.contains("      work (self 105)"), "{}", tree);
+        assert!(p.format_flat(1).lines().nth(1).unwrap().ends_with("work"));
+    }
+
+    #[test]
+    fn flags_frames_over_budget_ignoring_wait_recal() {
+        let symbols = symbols();
+        let mut p = Profile::new(&symbols, 0x00A6);
+        p.record(step(0x00A8, 500, 0xCBEA));
+        for busy in [1_000, 40_000, 2_000] {
+            p.record(call(0x01A7, 0xF192, 0xCBEA));
+            p.end_frame();
+            p.record(step(0xF19A, 29_000, 0xCBE8)); // waiting for the refresh timer
+            p.record(ret(0xF1A0, 0xCBE8));
+            p.record(step(0x01B3, busy, 0xCBEA));
+        }
+        p.end_frame();
+
+        assert_eq!(p.frames[0].number, 0);
+        assert_eq!(p.frames[0].busy, 508);
+        assert_eq!(p.frames[2].busy, 40_000 + 8);
+        let over: Vec<u64> = p.overruns(30_000).map(|f| f.number).collect();
+        assert_eq!(over, vec![2]);
+        let report = p.format_frames(30_000);
+        assert!(report.contains("frame 2: 40008 busy cycles (+10008 over budget)"), "{}", report);
+        assert_eq!(p.to_json(30_000)["frames"][2]["overrun"], true);
+    }
+}
@@ -0,0 +1,150 @@
+//! `vpy_cli snapshot`: golden-frame vector snapshot tests
+//!
+//! Builds the program, boots it in `vectrex_emulator` and records the
+//! segments drawn in selected frames (counted from the first frame after
+//! boot). The result is compared against `<project>/snapshots/<name>.vsnap`;
+//! on a mismatch the diff is printed and each failing frame is rendered to
+//! an SVG showing expected and actual side by side.
+
+use anyhow::{Context, Result};
+use colored::*;
+use std::path::{Path, PathBuf};
+use vectrex_emulator::snapshot::{self, FrameSnapshot, Tolerance};
+use vectrex_emulator::{Vectrex, CYCLES_PER_FRAME};
+
+use crate::test_runner::{assemble_rom, load_bios, load_sources, module_name_of, LoadedSources};
+
+/// Diff lines printed per frame before eliding the rest
+const MAX_DIFF_LINES: usize = 20;
+
+pub struct SnapshotOptions {
+    /// Frames to record when creating or updating the snapshot
+    pub frames: Vec<u64>,
+    pub update: bool,
+    pub tolerance: Tolerance,
+    /// Where the ROM and failure SVGs go (default `<project>/build/snapshots`)
+    pub out_dir: Option<PathBuf>,
+    pub verbose: bool,
+}
+
+pub fn cmd_snapshot(input: &Path, options: &SnapshotOptions) -> Result<()> {
+    let LoadedSources { modules, entry_point, project_dir, .. } = load_sources(input)?;
+    let name = input.file_stem().and_then(|s| s.to_str()).unwrap_or("main").to_string();
+    let snapshot_path = project_dir.join("snapshots").join(format!("{}.vsnap", name));
+    let out_dir = options.out_dir.clone().unwrap_or_else(|| project_dir.join("build").join("snapshots"));
+
+    let expected = if options.update || !snapshot_path.exists() {
+        None
+    } else {
+        let text = std::fs::read_to_string(&snapshot_path)
+            .with_context(|| format!("Failed to read {}", snapshot_path.display()))?;
+        Some(snapshot::parse(&text).with_context(|| format!("Invalid snapshot {}", snapshot_path.display()))?)
+    };
+    let frames: Vec<u64> = match &expected {
+        Some(expected) => expected.iter().map(|f| f.frame).collect(),
+        None => options.frames.clone(),
+    };
+    if frames.is_empty() || frames.contains(&0) {
+        anyhow::bail!("frame numbers must start at 1");
+    }
+
+    // Build the ROM exactly as `build` would
+    let unified = vpy_unifier::unify_modules(modules, &module_name_of(&entry_point))
+        .map_err(|e| anyhow::anyhow!("Unification error: {}", e))?;
+    let rom_size = unified.meta.rom_total_size.map(|s| s as usize).unwrap_or(32768);
+    let bank_size = unified.meta.rom_bank_size.map(|s| s as usize).unwrap_or(32768);
+    let bank_config = vpy_codegen::BankConfig::new(rom_size, bank_size);
+    let assets = vpy_codegen::m6809::assets::discover_assets(&entry_point);
+    let title = unified.meta.title_override.clone().unwrap_or_else(|| "VPY GAME".to_string());
+    let generated = vpy_codegen::generate_from_module(&unified, &bank_config, &title, &assets)
+        .map_err(|e| anyhow::anyhow!("Codegen error: {}", e))?;
+    let rom = assemble_rom(
+        &generated,
+        &bank_config,
+        &out_dir.join(format!("{}.asm", name)),
+        &crate::resolve_include_dir(),
+        options.verbose,
+    )?;
+
+    let actual = capture_frames(&load_bios(input)?, &rom, &frames)?;
+
+    let Some(expected) = expected else {
+        if let Some(dir) = snapshot_path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&snapshot_path, snapshot::to_text(&actual))
+            .with_context(|| format!("Failed to write {}", snapshot_path.display()))?;
+        let segments: usize = actual.iter().map(|f| f.segments.len()).sum();
+        println!(
+            "{} {} ({} frames, {} segments)",
+            "✓ Snapshot written:".green(),
+            snapshot_path.display(),
+            actual.len(),
+            segments
+        );
+        return Ok(());
+    };
+
+    let mut failed = 0;
+    for (exp, act) in expected.iter().zip(&actual) {
+        let diff = snapshot::diff_frame(exp, act, options.tolerance);
+        if diff.is_empty() {
+            println!("  {} frame {} ({} segments)", "✓".green(), exp.frame, act.segments.len());
+            continue;
+        }
+
+        failed += 1;
+        println!(
+            "  {} frame {}: {} missing, {} unexpected",
+            "✗".red(),
+            exp.frame,
+            diff.missing.len(),
+            diff.unexpected.len()
+        );
+        let text = diff.to_string();
+        let lines: Vec<&str> = text.lines().skip(1).collect();
+        for line in lines.iter().take(MAX_DIFF_LINES) {
+            let coloured = if line.starts_with('-') { line.red() } else { line.green() };
+            println!("      {}", coloured);
+        }
+        if lines.len() > MAX_DIFF_LINES {
+            println!("      ... {} more", lines.len() - MAX_DIFF_LINES);
+        }
+
+        std::fs::create_dir_all(&out_dir)?;
+        let svg_path = out_dir.join(format!("{}.frame{}.svg", name, exp.frame));
+        std::fs::write(&svg_path, snapshot::render_svg(exp, act, &diff))
+            .with_context(|| format!("Failed to write {}", svg_path.display()))?;
+        println!("      {} {}", "side-by-side:".bright_black(), svg_path.display());
+    }
+
+    if failed == 0 {
+        println!("\n{}", format!("snapshot ok. {} frames match", expected.len()).green().bold());
+        Ok(())
+    } else {
+        println!("\n{}", format!("snapshot FAILED. {} of {} frames differ", failed, expected.len()).red().bold());
+        println!("  (re-run with --update to accept the new output)");
+        Err(anyhow::anyhow!("{} frame(s) differ from {}", failed, snapshot_path.display()))
+    }
+}
+
+/// Boot the ROM and record the requested frames (1 = first frame after boot)
+fn capture_frames(bios: &[u8], rom: &[u8], frames: &[u64]) -> Result<Vec<FrameSnapshot>> {
+    let mut vectrex = Vectrex::new(bios)?;
+    vectrex.load_cartridge(rom);
+    vectrex.boot_cartridge()?;
+
+    let last = frames.iter().copied().max().unwrap_or(0);
+    let mut captured = Vec::with_capacity(frames.len());
+    for number in 1..=last {
+        let frame = vectrex
+            .run_frame(CYCLES_PER_FRAME * 4)
+            .with_context(|| format!("Emulation failed in frame {}", number))?;
+        if frames.contains(&number) {
+            captured.push(FrameSnapshot { frame: number, segments: frame.segments });
+        }
+    }
+    // Keep the snapshot's frame order
+    captured.sort_by_key(|f| frames.iter().position(|n| *n == f.frame));
+    Ok(captured)
+}
@@ -0,0 +1,376 @@
+//! `vpy_cli test`: run VPy unit tests headless in the emulator
+//!
+//! Every `def test_*()` function in the project becomes its own ROM whose
+//! `main()` calls the test once (see `vpy_codegen::m6809::test_harness`).
+//! Each ROM is booted in `vectrex_emulator` and the result is read back from
+//! the RAM mailbox: passed, failed (with the line of the `assert`), or
+//! timed out.
+
+use anyhow::{Context, Result};
+use colored::*;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use vectrex_emulator::{locate_bios, Vectrex, CYCLES_PER_FRAME};
+use vpy_codegen::m6809::test_harness::{
+    TEST_FAILED, TEST_LINE_ADDR, TEST_PASSED, TEST_RUNNING, TEST_STATUS_ADDR,
+};
+use vpy_parser::{Item, Module, Stmt};
+
+/// A `def test_*()` function found in a source file
+struct TestCase {
+    /// Name as written in the source
+    name: String,
+    /// Name after unification (module prefix, uppercase)
+    unified_name: String,
+    module_name: String,
+    path: PathBuf,
+    line: usize,
+}
+
+enum Outcome {
+    Passed { frames: u64 },
+    Failed { line: usize },
+    Timeout { frames: u64 },
+    Crashed(String),
+}
+
+/// Parsed sources of a `.vpyproj` project or a single `.vpy` file
+pub(crate) struct LoadedSources {
+    pub modules: HashMap<String, Module>,
+    pub paths: HashMap<String, PathBuf>,
+    pub entry_point: PathBuf,
+    /// Directory that holds `build/` (and the `.vpyproj`, if any)
+    pub project_dir: PathBuf,
+}
+
+/// Load and parse every module of the project without unifying them
+pub(crate) fn load_sources(input: &Path) -> Result<LoadedSources> {
+    let (source_files, entry_point, project_dir) = if input.extension().and_then(|s| s.to_str()) == Some("vpyproj") {
+        let project_info = vpy_loader::load_project(input).context("Failed to load project")?;
+        let project_dir = input.parent().unwrap_or_else(|| Path::new(".")).to_path_buf();
+        (project_info.source_files, project_info.entry_point, project_dir)
+    } else {
+        let file = vpy_loader::SourceFile { path: input.to_path_buf(), is_entry: true };
+        let parent = input.parent().unwrap_or_else(|| Path::new("."));
+        // Same layout as `build`: src/main.vpy builds into <project>/build
+        let project_dir = if parent.file_name().and_then(|n| n.to_str()) == Some("src") {
+            parent.parent().unwrap_or(parent)
+        } else {
+            parent
+        };
+        (vec![file], input.to_path_buf(), project_dir.to_path_buf())
+    };
+
+    let mut modules = HashMap::new();
+    let mut paths = HashMap::new();
+    for source_file in &source_files {
+        let source = std::fs::read_to_string(&source_file.path)
+            .with_context(|| format!("Failed to read {}", source_file.path.display()))?;
+        let tokens = vpy_parser::lex(&source)
+            .map_err(|e| anyhow::anyhow!("Lex error in {}: {}", source_file.path.display(), e))?;
+        let module = vpy_parser::parser::parse(tokens, source_file.path.to_str().unwrap_or("unknown"))
+            .map_err(|e| anyhow::anyhow!("Parse error in {}: {}", source_file.path.display(), e))?;
+        let module_name = module_name_of(&source_file.path);
+        paths.insert(module_name.clone(), source_file.path.clone());
+        modules.insert(module_name, module);
+    }
+
+    Ok(LoadedSources { modules, paths, entry_point, project_dir })
+}
+
+pub fn cmd_test(input: &Path, filter: Option<&str>, max_frames: u64, verbose: bool) -> Result<()> {
+    let LoadedSources { modules, paths, entry_point, project_dir } = load_sources(input)?;
+
+    let tests = collect_tests(&modules, &paths, filter);
+    if tests.is_empty() {
+        println!("{}", "No test_*() functions found".yellow());
+        return Ok(());
+    }
+
+    let entry_module_name = module_name_of(&entry_point);
+    let asserts = collect_asserts(&modules);
+    let unified = vpy_unifier::unify_modules(modules, &entry_module_name)
+        .map_err(|e| anyhow::anyhow!("Unification error: {}", e))?;
+
+    let rom_size = unified.meta.rom_total_size.map(|s| s as usize).unwrap_or(32768);
+    let bank_size = unified.meta.rom_bank_size.map(|s| s as usize).unwrap_or(32768);
+    let bank_config = vpy_codegen::BankConfig::new(rom_size, bank_size);
+    let assets = vpy_codegen::m6809::assets::discover_assets(&entry_point);
+
+    let bios = load_bios(input)?;
+    let include_dir = crate::resolve_include_dir();
+    let build_dir = project_dir.join("build").join("tests");
+
+    println!("running {} test{}", tests.len(), if tests.len() == 1 { "" } else { "s" });
+
+    let mut passed = 0;
+    let mut failures = Vec::new();
+    for test in &tests {
+        let outcome = match build_test_rom(&unified, &bank_config, &assets, test, &include_dir, &build_dir, verbose) {
+            Ok(rom) => run_test_rom(&bios, &rom, max_frames),
+            Err(e) => Outcome::Crashed(format!("build failed: {:#}", e)),
+        };
+
+        let location = format!("{}:{}", display_path(&test.path), test.line);
+        match &outcome {
+            Outcome::Passed { frames } => {
+                passed += 1;
+                println!("  {} {} ({}, {} frames)", "✓".green(), test.name.bright_white(), location.bright_black(), frames);
+            }
+            _ => {
+                println!("  {} {} ({})", "✗".red(), test.name.bright_white(), location.bright_black());
+                failures.push((test, outcome));
+            }
+        }
+    }
+
+    if !failures.is_empty() {
+        println!("\n{}", "failures:".bright_red().bold());
+        for (test, outcome) in &failures {
+            println!("\n  {} {}", "---".bright_black(), test.name.bright_yellow());
+            match outcome {
+                Outcome::Failed { line } => {
+                    let path = locate_assert(&asserts, &paths, &test.module_name, *line).unwrap_or(&test.path);
+                    println!("  assert failed at {}:{}", display_path(path), line);
+                    if let Some(text) = source_line(path, *line) {
+                        println!("      {}", text.trim().bright_white());
+                    }
+                }
+                Outcome::Timeout { frames } => {
+                    println!("  test did not finish within {} frames", frames);
+                }
+                Outcome::Crashed(msg) => println!("  {}", msg),
+                Outcome::Passed { .. } => {}
+            }
+        }
+    }
+
+    let summary = format!(
+        "test result: {}. {} passed; {} failed",
+        if failures.is_empty() { "ok" } else { "FAILED" },
+        passed,
+        failures.len()
+    );
+    if failures.is_empty() {
+        println!("\n{}", summary.green().bold());
+        Ok(())
+    } else {
+        println!("\n{}", summary.red().bold());
+        Err(anyhow::anyhow!("{} test(s) failed", failures.len()))
+    }
+}
+
+pub(crate) fn module_name_of(path: &Path) -> String {
+    path.file_stem().and_then(|s| s.to_str()).unwrap_or("main").to_string()
+}
+
+fn display_path(path: &Path) -> String {
+    std::env::current_dir()
+        .ok()
+        .and_then(|cwd| path.strip_prefix(cwd).ok().map(|p| p.display().to_string()))
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+/// Find `def test_*()` functions, sorted by file then line
+fn collect_tests(modules: &HashMap<String, Module>, paths: &HashMap<String, PathBuf>, filter: Option<&str>) -> Vec<TestCase> {
+    let mut resolver = vpy_unifier::SymbolResolver::new();
+    let mut tests = Vec::new();
+    for (module_name, module) in modules {
+        let prefix = resolver.register_module(module_name);
+        for item in &module.items {
+            let Item::Function(func) = item else { continue };
+            if !func.name.starts_with("test_") || !func.params.is_empty() {
+                continue;
+            }
+            if filter.is_some_and(|f| !func.name.contains(f)) {
+                continue;
+            }
+            let unified_name = if prefix.is_empty() {
+                func.name.to_uppercase()
+            } else {
+                format!("{}_{}", prefix, func.name.to_uppercase())
+            };
+            tests.push(TestCase {
+                name: func.name.clone(),
+                unified_name,
+                module_name: module_name.clone(),
+                path: paths[module_name].clone(),
+                line: func.line,
+            });
+        }
+    }
+    tests.sort_by(|a, b| (&a.path, a.line).cmp(&(&b.path, b.line)));
+    tests
+}
+
+/// Source lines of every `assert`, per module (the mailbox only carries the line)
+fn collect_asserts(modules: &HashMap<String, Module>) -> HashMap<String, Vec<usize>> {
+    fn walk(stmts: &[Stmt], lines: &mut Vec<usize>) {
+        for stmt in stmts {
+            match stmt {
+                Stmt::Assert { source_line, .. } => lines.push(*source_line),
+                Stmt::If { body, elifs, else_body, .. } => {
+                    walk(body, lines);
+                    for (_, b) in elifs {
+                        walk(b, lines);
+                    }
+                    if let Some(b) = else_body {
+                        walk(b, lines);
+                    }
+                }
+                Stmt::While { body, .. } | Stmt::For { body, .. } | Stmt::ForIn { body, .. } => walk(body, lines),
+                Stmt::Switch { cases, default, .. } => {
+                    for (_, b) in cases {
+                        walk(b, lines);
+                    }
+                    if let Some(b) = default {
+                        walk(b, lines);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    modules
+        .iter()
+        .map(|(name, module)| {
+            let mut lines = Vec::new();
+            for item in &module.items {
+                if let Item::Function(func) = item {
+                    walk(&func.body, &mut lines);
+                }
+            }
+            (name.clone(), lines)
+        })
+        .collect()
+}
+
+/// Which file holds the assert on `line`? Prefer the test's own module.
+fn locate_assert<'a>(
+    asserts: &HashMap<String, Vec<usize>>,
+    paths: &'a HashMap<String, PathBuf>,
+    test_module: &str,
+    line: usize,
+) -> Option<&'a PathBuf> {
+    let has_line = |module: &str| asserts.get(module).is_some_and(|lines| lines.contains(&line));
+    if has_line(test_module) {
+        return paths.get(test_module);
+    }
+    let mut modules: Vec<&String> = asserts.keys().filter(|m| has_line(m)).collect();
+    modules.sort();
+    modules.first().and_then(|m| paths.get(*m))
+}
+
+fn source_line(path: &Path, line: usize) -> Option<String> {
+    let source = std::fs::read_to_string(path).ok()?;
+    source.lines().nth(line.checked_sub(1)?).map(str::to_string)
+}
+
+pub(crate) fn load_bios(input: &Path) -> Result<Vec<u8>> {
+    let mut starts = vec![input.canonicalize().unwrap_or_else(|_| input.to_path_buf())];
+    starts.extend(std::env::current_dir().ok());
+    starts.extend(std::env::current_exe().ok());
+    let path = starts
+        .iter()
+        .find_map(|start| locate_bios(start))
+        .context("BIOS not found (expected ide/frontend/src/assets/bios.bin)")?;
+    std::fs::read(&path).with_context(|| format!("Failed to read BIOS {}", path.display()))
+}
+
+fn build_test_rom(
+    unified: &Module,
+    bank_config: &vpy_codegen::BankConfig,
+    assets: &[vpy_codegen::AssetInfo],
+    test: &TestCase,
+    include_dir: &Path,
+    build_dir: &Path,
+    verbose: bool,
+) -> Result<Vec<u8>> {
+    let generated = vpy_codegen::generate_test_from_module(unified, bank_config, "TEST", assets, &test.unified_name)
+        .map_err(|e| anyhow::anyhow!("Codegen error: {}", e))?;
+
+    let asm_path = build_dir.join(format!("{}.asm", test.name));
+    assemble_rom(&generated, bank_config, &asm_path, include_dir, verbose)
+}
+
+/// Write the generated ASM to `asm_path` and assemble/link it into a ROM image
+pub(crate) fn assemble_rom(
+    generated: &vpy_codegen::GeneratedASM,
+    bank_config: &vpy_codegen::BankConfig,
+    asm_path: &Path,
+    include_dir: &Path,
+    verbose: bool,
+) -> Result<Vec<u8>> {
+    if let Some(dir) = asm_path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(asm_path, &generated.asm_source)
+        .with_context(|| format!("Failed to write ASM to {}", asm_path.display()))?;
+    if verbose {
+        println!("    ASM written: {}", asm_path.display());
+    }
+
+    if bank_config.rom_total_size > 32768 {
+        let bin_path = asm_path.with_extension("bin");
+        let linker = vpy_linker::MultiBankLinker::new(
+            bank_config.rom_bank_size as u32,
+            bank_config.rom_bank_count as u8,
+            true,
+            Some(include_dir.to_path_buf()),
+        );
+        linker
+            .generate_multibank_rom(asm_path, &bin_path)
+            .map_err(|e| anyhow::anyhow!("Multibank link failed: {}", e))?;
+        return std::fs::read(&bin_path).with_context(|| format!("Failed to read {}", bin_path.display()));
+    }
+
+    assemble_single_bank(generated, include_dir).map(|(rom, _)| rom)
+}
+
+/// Assemble and link a single-bank ROM in memory; also returns the address of every label
+pub(crate) fn assemble_single_bank(
+    generated: &vpy_codegen::GeneratedASM,
+    include_dir: &Path,
+) -> Result<(Vec<u8>, HashMap<String, u16>)> {
+    let sections = vpy_assembler::parse_unified_asm(&generated.asm_source).context("Failed to parse unified ASM")?;
+    vpy_assembler::set_include_dir(Some(include_dir.to_path_buf()));
+    let binaries = vpy_assembler::assemble_banks(sections).context("Failed to assemble banks")?;
+    // Bank 0 is assembled at ORG $0000, so offsets are addresses
+    let symbols = binaries
+        .iter()
+        .filter(|binary| binary.bank_id == 0)
+        .flat_map(|binary| binary.symbols.iter().map(|(name, def)| (name.clone(), def.offset)))
+        .collect();
+    let rom = vpy_linker::link_unified_asm(generated, binaries).context("Failed to link ROM")?;
+    Ok((rom.rom_data, symbols))
+}
+
+fn run_test_rom(bios: &[u8], rom: &[u8], max_frames: u64) -> Outcome {
+    let mut vectrex = match Vectrex::new(bios) {
+        Ok(v) => v,
+        Err(e) => return Outcome::Crashed(e.to_string()),
+    };
+    vectrex.load_cartridge(rom);
+    if let Err(e) = vectrex.boot_cartridge() {
+        return Outcome::Crashed(format!("boot failed: {}", e));
+    }
+
+    let start_cycles = vectrex.cpu.cycles;
+    let budget = max_frames * CYCLES_PER_FRAME;
+    loop {
+        if let Err(e) = vectrex.step() {
+            return Outcome::Crashed(format!("emulator stopped: {}", e));
+        }
+        let frames = (vectrex.cpu.cycles - start_cycles) / CYCLES_PER_FRAME;
+        match vectrex.peek(TEST_STATUS_ADDR) {
+            TEST_RUNNING => {}
+            TEST_PASSED => return Outcome::Passed { frames },
+            TEST_FAILED => return Outcome::Failed { line: vectrex.peek16(TEST_LINE_ADDR) as usize },
+            other => return Outcome::Crashed(format!("invalid test status ${:02X}", other)),
+        }
+        if vectrex.cpu.cycles - start_cycles > budget {
+            return Outcome::Timeout { frames };
+        }
+    }
+}
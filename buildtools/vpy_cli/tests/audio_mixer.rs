@@ -0,0 +1,137 @@
+// SFX mixer regression tests
+//
+// Builds small programs that play a three-channel chord and fire sound
+// effects over it, runs them in the emulator and compares the PSG registers
+// frame by frame against the same program without the effects.
+// Skipped when the BIOS image is not available.
+
+mod common;
+
+use std::process::Command;
+
+const FRAMES: usize = 60;
+
+/// Registers of each channel: tone fine, tone coarse, volume
+const CHANNEL_A: [usize; 3] = [0, 1, 8];
+const CHANNEL_B: [usize; 3] = [2, 3, 9];
+const CHANNEL_C: [usize; 3] = [4, 5, 10];
+
+/// Three notes held on channels A, B and C
+const CHORD: &str = r#"{"version":"1.0","name":"chord","author":"","tempo":120,"ticksPerBeat":24,"totalTicks":192,
+ "notes":[{"id":"a","note":48,"start":0,"duration":192,"velocity":12,"channel":0},
+          {"id":"b","note":55,"start":0,"duration":192,"velocity":11,"channel":1},
+          {"id":"c","note":64,"start":0,"duration":192,"velocity":10,"channel":2}],
+ "noise":[],"loopStart":0,"loopEnd":192}"#;
+
+/// A square-wave .vsfx on channel A
+fn sfx(name: &str, duration_ms: u32, frequency: u32, priority: u8) -> String {
+    format!(
+        r#"{{"version":"1.0","name":"{name}","category":"custom","duration_ms":{duration_ms},"priority":{priority},
+ "oscillator":{{"frequency":{frequency},"channel":0,"duty":50}},
+ "envelope":{{"attack":0,"decay":0,"sustain":15,"release":0,"peak":15}},
+ "pitch":{{"enabled":false,"start_mult":1.0,"end_mult":1.0,"curve":0}},
+ "noise":{{"enabled":false,"period":0,"volume":0,"decay_ms":0}},
+ "modulation":{{"arpeggio":false,"arpeggio_notes":[],"arpeggio_speed":100,"vibrato":false,"vibrato_depth":0,"vibrato_speed":0}}}}"#
+    )
+}
+
+/// Build a project whose loop runs `events` (frame number, statement) and
+/// return the PSG registers after each frame, or None without a BIOS
+fn psg_states(tag: &str, slots: u8, events: &[(u32, &str)]) -> Option<Vec<[u8; 14]>> {
+    let bios_path = common::bios_or_skip(tag)?;
+
+    let mut src = format!(
+        "META TITLE = \"MIXER\"\nMETA SFX_SLOTS = {}\n\nframe = 0\n\ndef main():\n    PLAY_MUSIC(\"chord\")\n\ndef loop():\n    WAIT_RECAL()\n    frame = frame + 1\n",
+        slots
+    );
+    for (frame, statement) in events {
+        src.push_str(&format!("    if frame == {}:\n        {}\n", frame, statement));
+    }
+
+    let dir = common::write_project(
+        tag,
+        &[
+            ("assets/music/chord.vmus", CHORD),
+            ("assets/sfx/zap.vsfx", &sfx("zap", 400, 880, 3)),
+            ("assets/sfx/blip.vsfx", &sfx("blip", 200, 440, 1)),
+            ("src/main.vpy", &src),
+        ],
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_vpy_cli"))
+        .current_dir(common::repo_root())
+        .arg("build")
+        .arg(dir.join(format!("{}.vpyproj", tag)))
+        .output()
+        .expect("failed to run vpy_cli");
+    assert!(
+        output.status.success(),
+        "build failed for {}:\n{}{}",
+        tag,
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let rom = std::fs::read(dir.join(format!("build/{}.bin", tag))).unwrap();
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let bios = std::fs::read(bios_path).unwrap();
+    let mut machine = vectrex_emulator::Vectrex::new(&bios).unwrap();
+    machine.load_cartridge(&rom);
+    machine.boot_cartridge().unwrap();
+    let mut states = Vec::with_capacity(FRAMES);
+    for _ in 0..FRAMES {
+        machine.run_frame(vectrex_emulator::CYCLES_PER_FRAME * 4).unwrap();
+        let mut regs = [0u8; 14];
+        regs.copy_from_slice(&machine.cpu.bus.psg.regs[..14]);
+        states.push(regs);
+    }
+    Some(states)
+}
+
+fn channel_differs(a: &[[u8; 14]], b: &[[u8; 14]], regs: [usize; 3]) -> bool {
+    a.iter().zip(b).any(|(x, y)| regs.iter().any(|&r| x[r] != y[r]))
+}
+
+#[test]
+fn test_sfx_ducks_one_channel_and_restores_it() {
+    let Some(music) = psg_states("duck_music", 1, &[]) else { return };
+    let with_sfx = psg_states("duck_sfx", 1, &[(5, "PLAY_SFX(\"zap\")")]).unwrap();
+
+    assert!(channel_differs(&music, &with_sfx, CHANNEL_C), "zap never reached channel C");
+    assert!(!channel_differs(&music, &with_sfx, CHANNEL_A), "music lost channel A");
+    assert!(!channel_differs(&music, &with_sfx, CHANNEL_B), "music lost channel B");
+    // zap lasts 20 frames; afterwards every register is the music's again
+    assert_eq!(music[FRAMES - 1], with_sfx[FRAMES - 1]);
+}
+
+#[test]
+fn test_lower_priority_sfx_is_rejected() {
+    let Some(zap) = psg_states("reject_zap", 1, &[(5, "PLAY_SFX(\"zap\")")]) else { return };
+    let both = psg_states("reject_both", 1, &[(5, "PLAY_SFX(\"zap\")"), (8, "PLAY_SFX(\"blip\")")]).unwrap();
+    assert_eq!(zap, both, "blip (priority 1) interrupted zap (priority 3)");
+
+    let raised = psg_states("reject_raised", 1, &[(5, "PLAY_SFX(\"zap\")"), (8, "PLAY_SFX(\"blip\", 3)")]).unwrap();
+    assert_ne!(zap, raised, "blip at priority 3 did not replace zap");
+}
+
+#[test]
+fn test_second_slot_takes_channel_b() {
+    let Some(music) = psg_states("slots_music", 2, &[]) else { return };
+    let both = psg_states("slots_both", 2, &[(5, "PLAY_SFX(\"zap\")"), (6, "PLAY_SFX(\"blip\")")]).unwrap();
+
+    assert!(channel_differs(&music, &both, CHANNEL_C));
+    assert!(channel_differs(&music, &both, CHANNEL_B));
+    assert!(!channel_differs(&music, &both, CHANNEL_A));
+    assert_eq!(music[FRAMES - 1], both[FRAMES - 1]);
+}
+
+#[test]
+fn test_sfx_on_requested_channel() {
+    let Some(music) = psg_states("chan_music", 3, &[]) else { return };
+    let on_a = psg_states("chan_a", 3, &[(5, "PLAY_SFX(\"blip\", 1, 0)")]).unwrap();
+
+    assert!(channel_differs(&music, &on_a, CHANNEL_A));
+    assert!(!channel_differs(&music, &on_a, CHANNEL_B));
+    assert!(!channel_differs(&music, &on_a, CHANNEL_C));
+    assert_eq!(music[FRAMES - 1], on_a[FRAMES - 1]);
+}
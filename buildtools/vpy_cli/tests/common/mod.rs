@@ -0,0 +1,43 @@
+// Helpers shared by the vpy_cli integration tests
+//
+// Each test binary includes this module with `mod common;` and uses only
+// some of the helpers.
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+
+/// Repository root: examples, include files and the BIOS image
+pub fn repo_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../..")
+}
+
+/// Fresh project directory named `tag` holding `tag.vpyproj` (entry
+/// src/main.vpy) and `files`, given relative to it. vpy_cli names build
+/// outputs after the directory, so the ROM is build/`tag`.bin.
+pub fn write_project(tag: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("vpy_cli_tests_{}", std::process::id())).join(tag);
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    std::fs::write(
+        dir.join(format!("{}.vpyproj", tag)),
+        format!(
+            "[project]\nname = \"{tag}\"\nversion = \"0.1.0\"\nentry = \"src/main.vpy\"\n\n[build]\noutput = \"build/{tag}.bin\"\n"
+        ),
+    )
+    .unwrap();
+    for (name, contents) in files {
+        let path = dir.join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+    dir
+}
+
+/// The BIOS image, or None after reporting that `name` is skipped
+pub fn bios_or_skip(name: &str) -> Option<PathBuf> {
+    let bios = vectrex_emulator::locate_bios(&repo_root());
+    if bios.is_none() {
+        eprintln!("BIOS not found, skipping {}", name);
+    }
+    bios
+}
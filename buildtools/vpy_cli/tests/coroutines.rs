@@ -0,0 +1,136 @@
+// Coroutine tests
+//
+// Runs `vpy_cli test` on projects whose scripts `yield` between frames and
+// are driven with CO_START/CO_RESUME/CO_ALIVE/CO_STOP (skipped when the BIOS
+// image is not available), and checks the build rejects calling a coroutine
+// like a plain function.
+
+mod common;
+
+use std::process::Command;
+
+#[test]
+fn test_coroutines_at_runtime() {
+    if common::bios_or_skip("coroutine runtime test").is_none() {
+        return;
+    }
+
+    let dir = common::write_project(
+        "runtime",
+        &[(
+            "src/main.vpy",
+            concat!(
+                "x = 0\nlog = 0\n\n",
+                "def main():\n    pass\n\n",
+                "def loop():\n    pass\n\n",
+                // i survives the yield; tmp does not and shares its slot with helper's locals
+                "def walk(steps, speed):\n",
+                "    i = 0\n",
+                "    while i < steps:\n",
+                "        tmp = i * speed\n",
+                "        x = tmp\n",
+                "        yield\n",
+                "        i = i + 1\n",
+                "    log = 99\n\n",
+                "def helper(a):\n    b = a + 1\n    return b\n\n",
+                "def blink(n):\n",
+                "    while n > 0:\n",
+                "        n = n - 1\n",
+                "        if n == 0:\n            return\n",
+                "        yield\n\n",
+                "def test_walk():\n",
+                "    CO_START(walk, 3, 10)\n",
+                "    assert CO_ALIVE(walk) == 1\n",
+                "    assert CO_RESUME(walk) == 1\n    assert x == 0\n",
+                "    assert helper(5) == 6\n",
+                "    assert CO_RESUME(walk) == 1\n    assert x == 10\n",
+                "    assert CO_RESUME(walk) == 1\n    assert x == 20\n",
+                "    assert CO_RESUME(walk) == 0\n    assert log == 99\n",
+                "    assert CO_ALIVE(walk) == 0\n",
+                "    assert CO_RESUME(walk) == 0\n\n",
+                "def test_restart_stop_and_return():\n",
+                "    CO_START(blink, 5)\n",
+                "    assert CO_RESUME(blink) == 1\n",
+                "    CO_START(walk, 2, 7)\n",
+                "    assert CO_RESUME(walk) == 1\n",
+                "    assert CO_RESUME(blink) == 1\n",
+                "    CO_STOP(blink)\n    assert CO_ALIVE(blink) == 0\n",
+                "    assert CO_RESUME(walk) == 1\n    assert x == 7\n",
+                "    CO_START(blink, 2)\n",
+                "    assert CO_RESUME(blink) == 1\n",
+                "    assert CO_RESUME(blink) == 0\n",
+            ),
+        )],
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_vpy_cli"))
+        .current_dir(common::repo_root())
+        .arg("test")
+        .arg(dir.join("runtime.vpyproj"))
+        .output()
+        .expect("failed to run vpy_cli");
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "tests failed:\n{}{}", stdout, String::from_utf8_lossy(&output.stderr));
+    assert!(stdout.contains("2 passed"), "{}", stdout);
+}
+
+#[test]
+fn test_coroutine_with_many_yields() {
+    if common::bios_or_skip("coroutine runtime test").is_none() {
+        return;
+    }
+
+    // More than 63 yields: the resume table index no longer fits a signed byte offset
+    let source = format!(
+        concat!(
+            "steps = 0\n\n",
+            "def main():\n    pass\n\n",
+            "def loop():\n    pass\n\n",
+            "def long():\n{}\n",
+            "def test_long():\n",
+            "    assert CO_ALIVE(long) == 0\n",
+            "    CO_START(long)\n",
+            "    n = 0\n",
+            "    while CO_RESUME(long) == 1:\n",
+            "        n = n + 1\n",
+            "    assert n == 70\n",
+            "    assert steps == 70\n",
+        ),
+        "    steps = steps + 1\n    yield\n".repeat(70)
+    );
+    let dir = common::write_project("many_yields", &[("src/main.vpy", &source)]);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_vpy_cli"))
+        .current_dir(common::repo_root())
+        .arg("test")
+        .arg(dir.join("many_yields.vpyproj"))
+        .output()
+        .expect("failed to run vpy_cli");
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "tests failed:\n{}{}", stdout, String::from_utf8_lossy(&output.stderr));
+    assert!(stdout.contains("1 passed"), "{}", stdout);
+}
+
+#[test]
+fn test_calling_a_coroutine_directly_is_an_error() {
+    let dir = common::write_project(
+        "direct_call",
+        &[("src/main.vpy", "def main():\n    pass\n\ndef loop():\n    patrol(3)\n\ndef patrol(n):\n    yield\n")],
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_vpy_cli"))
+        .current_dir(common::repo_root())
+        .arg("build")
+        .arg(dir.join("direct_call.vpyproj"))
+        .output()
+        .expect("failed to run vpy_cli");
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success(), "build should fail");
+    assert!(stderr.contains("line 5: 'PATROL' contains yield"), "{}", stderr);
+}
@@ -0,0 +1,99 @@
+// Enum and switch tests
+//
+// Runs `vpy_cli test` on a project whose states are an enum imported from
+// another module and dispatched with `switch` (skipped when the BIOS image
+// is not available), and checks the build warns about a switch that misses
+// variants without a `default`.
+
+mod common;
+
+use std::process::Command;
+
+#[test]
+fn test_enum_switch_at_runtime() {
+    if common::bios_or_skip("enum runtime test").is_none() {
+        return;
+    }
+
+    let dir = common::write_project(
+        "runtime",
+        &[
+            ("src/states.vpy", "enum GameState: TITLE, MAP, GAME, OVER\n"),
+            (
+                "src/main.vpy",
+                concat!(
+                    "import states\n",
+                    "from states import GameState as S\n\n",
+                    "screen = 0\n\n",
+                    "def main():\n    pass\n\n",
+                    "def loop():\n    pass\n\n",
+                    "def next_screen(current):\n",
+                    "    switch current:\n",
+                    "        case S.TITLE:\n            return S.MAP\n",
+                    "        case S.MAP:\n            return states.GameState.GAME\n",
+                    "        case S.GAME:\n            return S.OVER\n",
+                    "        default:\n            return S.TITLE\n\n",
+                    "def bonus(level, current):\n",
+                    "    result = 0\n",
+                    "    switch current:\n",
+                    "        case level + 1:\n            result = 10\n",
+                    "        case level * 2:\n            result = 20\n",
+                    "    return result\n\n",
+                    "def test_variants_are_numbered():\n",
+                    "    assert S.TITLE == 0\n    assert S.OVER == 3\n\n",
+                    "def test_constant_cases():\n",
+                    "    screen = S.TITLE\n",
+                    "    screen = next_screen(screen)\n    assert screen == S.MAP\n",
+                    "    screen = next_screen(screen)\n    assert screen == S.GAME\n",
+                    "    screen = next_screen(screen)\n    assert screen == S.OVER\n",
+                    "    screen = next_screen(screen)\n    assert screen == S.TITLE\n\n",
+                    "def test_computed_cases():\n",
+                    "    assert bonus(3, 4) == 10\n    assert bonus(3, 6) == 20\n    assert bonus(3, 5) == 0\n",
+                ),
+            ),
+        ],
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_vpy_cli"))
+        .current_dir(common::repo_root())
+        .arg("test")
+        .arg(dir.join("runtime.vpyproj"))
+        .output()
+        .expect("failed to run vpy_cli");
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "tests failed:\n{}{}", stdout, String::from_utf8_lossy(&output.stderr));
+    assert!(stdout.contains("3 passed"), "{}", stdout);
+}
+
+#[test]
+fn test_non_exhaustive_switch_warns() {
+    let dir = common::write_project(
+        "warning",
+        &[(
+            "src/main.vpy",
+            concat!(
+                "enum Dir: LEFT, RIGHT, UP, DOWN\n\n",
+                "heading = 0\n\n",
+                "def main():\n    pass\n\n",
+                "def loop():\n",
+                "    switch heading:\n",
+                "        case Dir.LEFT:\n            heading = Dir.RIGHT\n",
+                "        case Dir.RIGHT:\n            heading = Dir.LEFT\n",
+            ),
+        )],
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_vpy_cli"))
+        .current_dir(common::repo_root())
+        .arg("build")
+        .arg(dir.join("warning.vpyproj"))
+        .output()
+        .expect("failed to run vpy_cli");
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "build failed:\n{}{}", stdout, String::from_utf8_lossy(&output.stderr));
+    assert!(stdout.contains("main:9: switch over Dir has no case for UP, DOWN and no default"), "{}", stdout);
+}
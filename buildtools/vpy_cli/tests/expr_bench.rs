@@ -0,0 +1,40 @@
+// Expression benchmark suite
+//
+// Runs `vpy_cli bench --check` on benchmarks/expressions (skipped when the
+// BIOS image is not available): every snippet's asserts must hold under both
+// expression generators, and the register lowering must not be larger or
+// slower than the legacy code on any of them.
+
+mod common;
+
+use std::path::Path;
+use std::process::Command;
+
+#[test]
+fn test_expression_benchmarks() {
+    if common::bios_or_skip("expression benchmarks").is_none() {
+        return;
+    }
+
+    let json = std::env::temp_dir().join(format!("vpy_expr_bench_{}.json", std::process::id()));
+    let output = Command::new(env!("CARGO_BIN_EXE_vpy_cli"))
+        .current_dir(common::repo_root())
+        .arg("bench")
+        .arg(Path::new(env!("CARGO_MANIFEST_DIR")).join("benchmarks/expressions"))
+        .arg("--check")
+        .arg("--json")
+        .arg(&json)
+        .output()
+        .expect("failed to run vpy_cli");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "benchmarks failed:\n{}{}", stdout, String::from_utf8_lossy(&output.stderr));
+
+    let report: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&json).unwrap()).unwrap();
+    let _ = std::fs::remove_file(&json);
+    let benchmarks = report["benchmarks"].as_array().unwrap();
+    assert!(benchmarks.len() >= 5, "{}", stdout);
+    for bench in benchmarks {
+        assert!(bench["lowered"]["cycles"].as_u64().unwrap() > 0, "{}", bench);
+    }
+}
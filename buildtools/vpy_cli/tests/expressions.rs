@@ -0,0 +1,56 @@
+// Expression lowering tests
+//
+// Runs `vpy_cli test` on a program whose asserts cover the cases the
+// register lowering treats specially (skipped when the BIOS image is not
+// available): constant operands on either side, spills, materialized
+// conditions, folded array offsets and compound assignments.
+
+mod common;
+
+use std::process::Command;
+
+#[test]
+fn test_lowered_expressions_at_runtime() {
+    if common::bios_or_skip("expression runtime test").is_none() {
+        return;
+    }
+
+    let dir = common::write_project(
+        "expressions",
+        &[(
+            "src/main.vpy",
+            concat!(
+                "a = 6\nb = -4\nc = 0\n",
+                "table = [10, 20, 30, 40]\n\n",
+                "def main():\n    pass\n\n",
+                "def loop():\n    pass\n\n",
+                "def twice(v):\n    return v + v\n\n",
+                "def test_constant_on_the_left():\n",
+                "    assert 10 - a == 4\n    assert 3 < a\n    assert not (7 < a)\n    assert 2 * a == 12\n\n",
+                "def test_spills():\n",
+                "    assert (a + 1) * (b + 1) == -21\n    assert (a - b) - (b - a) == 20\n    assert twice(a) - twice(b) == 20\n\n",
+                "def test_conditions_as_values():\n",
+                "    c = a > b\n    assert c == 1\n    c = (a < b) + (a == 6) + (b != 0 and a > 0) + (a < 0 or b < 0)\n    assert c == 3\n\n",
+                "def test_array_offsets():\n",
+                "    c = 2\n    assert table[c + 1] == 40\n    assert table[c - 2] == 10\n",
+                "    table[c + 1] = table[c - 1] + a\n    assert table[3] == 26\n    table[3] = 40\n\n",
+                "def test_negative_products():\n",
+                "    assert a * -3 == -18\n    assert -a == -6\n    assert b * 8 == -32\n\n",
+                "def test_compound_assignment():\n",
+                "    c = 5\n    c += a\n    c -= 1\n    c *= 3\n    assert c == 30\n",
+            ),
+        )],
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_vpy_cli"))
+        .current_dir(common::repo_root())
+        .arg("test")
+        .arg(dir.join("expressions.vpyproj"))
+        .output()
+        .expect("failed to run vpy_cli");
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "tests failed:\n{}{}", stdout, String::from_utf8_lossy(&output.stderr));
+    assert!(stdout.contains("6 passed"), "{}", stdout);
+}
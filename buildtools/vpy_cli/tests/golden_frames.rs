@@ -0,0 +1,44 @@
+// Golden-frame regression tests
+//
+// Builds the example projects, runs them in the emulator and compares the
+// drawn vectors against the checked-in snapshots (examples/*/snapshots).
+// Skipped when the BIOS image is not available.
+
+mod common;
+
+use std::process::Command;
+
+fn check_snapshot(project: &str) {
+    if common::bios_or_skip(project).is_none() {
+        return;
+    }
+    let root = common::repo_root();
+
+    let out_dir = std::env::temp_dir().join(format!("vpy_golden_{}_{}", project, std::process::id()));
+    let output = Command::new(env!("CARGO_BIN_EXE_vpy_cli"))
+        .arg("snapshot")
+        .arg(root.join("examples").join(project).join("pang.vpyproj"))
+        .arg("--out-dir")
+        .arg(&out_dir)
+        .output()
+        .expect("failed to run vpy_cli");
+    let _ = std::fs::remove_dir_all(&out_dir);
+
+    assert!(
+        output.status.success(),
+        "snapshot mismatch for {}:\n{}{}",
+        project,
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn test_pang_golden_frames() {
+    check_snapshot("pang");
+}
+
+#[test]
+fn test_pang_multi_golden_frames() {
+    check_snapshot("pang_multi");
+}
@@ -0,0 +1,74 @@
+// Function-local storage tests
+//
+// Parameters and locals live in overlaid RAM frames: runs `vpy_cli test` on
+// a program that relies on them, when a BIOS image is there to run it on, and
+// checks that recursion through a function with locals is rejected at build
+// time.
+
+mod common;
+
+use std::process::Command;
+
+#[test]
+fn test_params_and_locals_at_runtime() {
+    if common::bios_or_skip("locals runtime test").is_none() {
+        return;
+    }
+
+    let dir = common::write_project(
+        "runtime",
+        &[(
+            "src/main.vpy",
+            concat!(
+                "total = 0\n\n",
+                "def main():\n    pass\n\n",
+                "def loop():\n    pass\n\n",
+                "def add(a, b):\n    return a + b\n\n",
+                "def count_up(n):\n    i = 0\n    while i < n:\n        total = total + 1\n        i = i + 1\n    return i\n\n",
+                "def outer():\n    i = 10\n    count_up(3)\n    return i\n\n",
+                "def test_params():\n    assert add(2, 3) == 5\n\n",
+                "def test_nested_call_arguments():\n    assert add(add(1, 2), add(3, 4)) == 10\n\n",
+                "def test_locals_survive_calls():\n    total = 0\n    assert outer() == 10\n    assert total == 3\n",
+            ),
+        )],
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_vpy_cli"))
+        .current_dir(common::repo_root())
+        .arg("test")
+        .arg(dir.join("runtime.vpyproj"))
+        .output()
+        .expect("failed to run vpy_cli");
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "tests failed:\n{}{}", stdout, String::from_utf8_lossy(&output.stderr));
+    assert!(stdout.contains("3 passed"), "{}", stdout);
+}
+
+#[test]
+fn test_recursion_with_locals_is_rejected() {
+    let dir = common::write_project(
+        "recursion",
+        &[(
+            "src/main.vpy",
+            concat!(
+                "def main():\n    fact(3)\n\n",
+                "def loop():\n    pass\n\n",
+                "def fact(n):\n    if n > 1:\n        return n * fact(n - 1)\n    return 1\n",
+            ),
+        )],
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_vpy_cli"))
+        .current_dir(common::repo_root())
+        .arg("build")
+        .arg(dir.join("recursion.vpyproj"))
+        .output()
+        .expect("failed to run vpy_cli");
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(stderr.contains("recursive call chain FACT -> FACT"), "{}", stderr);
+}
@@ -0,0 +1,187 @@
+// Packed music player tests
+//
+// Builds one song plain and packed, plays both in the emulator and compares
+// the PSG register writes frame by frame, then checks the cycles the packed
+// decoder (AU_PACKED_FRAME) takes on every frame against the cost model the
+// build report prints. Skipped when the BIOS image is not available.
+
+mod common;
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+
+use vpy_codegen::music_pack::pack;
+use vpy_codegen::musres::{HardwareEnvelope, MusicCompression, MusicResource, NoiseEvent, NoteEvent};
+
+fn note(id: String, note: u8, start: u32, duration: u32, channel: u8) -> NoteEvent {
+    NoteEvent { id, note, start, duration, velocity: 12, channel, envelope: None, buzzer: false }
+}
+
+/// A bar played twice then a variation, looped: back-references, a buzzer
+/// bass restarting the envelope on every note and a noise hit per bar
+fn riff_song() -> MusicResource {
+    let mut music = MusicResource::new("riff");
+    music.tempo = 300;
+    music.ticks_per_beat = 24;
+    for bar in 0..3u32 {
+        let base = bar * 96;
+        let top = if bar == 2 { 74 } else { 72 };
+        for (i, pitch) in [60, 64, 67, top].into_iter().enumerate() {
+            music.notes.push(note(format!("m{}_{}", bar, i), pitch, base + i as u32 * 24, 20, 0));
+        }
+        for half in 0..2 {
+            let mut bass = note(format!("b{}_{}", bar, half), 36, base + half * 48, 40, 1);
+            bass.envelope = Some(HardwareEnvelope { shape: 0x0E, period: 0 });
+            bass.buzzer = true;
+            music.notes.push(bass);
+        }
+        music.noise.push(NoiseEvent { id: format!("n{}", bar), start: base + 72, duration: 8, period: 6, channels: 4, velocity: 10 });
+    }
+    music.total_ticks = 288;
+    music.loop_start = 0;
+    music.loop_end = 288;
+    music
+}
+
+/// Register writes made during each AUDIO_UPDATE call, and the cycles of
+/// each packed decoder call from its JSR to the return
+struct Playback {
+    writes: Vec<Vec<(u8, u8)>>,
+    player_cycles: Vec<u64>,
+}
+
+/// Build a project playing `music` and return the ROM and its link map symbols
+fn build(tag: &str, music: &MusicResource) -> (Vec<u8>, HashMap<String, u16>) {
+    let dir = common::write_project(
+        tag,
+        &[
+            ("assets/music/riff.vmus", &serde_json::to_string(music).unwrap()),
+            ("src/main.vpy", "def main():\n    PLAY_MUSIC(\"riff\")\n\ndef loop():\n    WAIT_RECAL()\n"),
+        ],
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_vpy_cli"))
+        .current_dir(common::repo_root())
+        .arg("build")
+        .arg(dir.join(format!("{}.vpyproj", tag)))
+        .output()
+        .expect("failed to run vpy_cli");
+    assert!(
+        output.status.success(),
+        "build failed for {}:\n{}{}",
+        tag,
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let rom = std::fs::read(dir.join(format!("build/{}.bin", tag))).unwrap();
+    let map: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(dir.join(format!("build/{}.map.json", tag))).unwrap()).unwrap();
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let symbols = map["symbols"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| (s["name"].as_str().unwrap().to_string(), s["address"].as_u64().unwrap() as u16))
+        .collect();
+    (rom, symbols)
+}
+
+/// Play `music` for `calls` AUDIO_UPDATE calls
+fn play(bios_path: &Path, tag: &str, music: &MusicResource, calls: usize) -> Playback {
+    let (rom, symbols) = build(tag, music);
+    let address = |name: &str| *symbols.get(name).unwrap_or_else(|| panic!("{} not in the link map", name));
+    let audio_update = address("AUDIO_UPDATE");
+    let (player_call, player_return) = (address("AU_MUSIC_PACKED"), address("AU_SKIP_MUSIC"));
+
+    let bios = std::fs::read(bios_path).unwrap();
+    let mut machine = vectrex_emulator::Vectrex::new(&bios).unwrap();
+    machine.load_cartridge(&rom);
+    machine.boot_cartridge().unwrap();
+    machine.cpu.bus.psg.log_writes = true;
+
+    let budget = machine.cpu.cycles + (calls as u64 + 4) * vectrex_emulator::CYCLES_PER_FRAME * 4;
+    let mut playback = Playback { writes: Vec::new(), player_cycles: Vec::new() };
+    let mut started = false;
+    let mut player_start = None;
+    while playback.writes.len() < calls {
+        let pc = machine.cpu.pc;
+        if pc == audio_update {
+            // Writes since the previous call belong to it
+            let writes = machine.cpu.bus.psg.take_writes();
+            if started {
+                playback.writes.push(writes.iter().map(|w| (w.reg, w.value)).collect());
+            }
+            started = true;
+        } else if pc == player_call {
+            player_start = Some(machine.cpu.cycles);
+        } else if pc == player_return {
+            if let Some(start) = player_start.take() {
+                playback.player_cycles.push(machine.cpu.cycles - start);
+            }
+        }
+        machine.step().unwrap();
+        assert!(machine.cpu.cycles < budget, "{}: AUDIO_UPDATE stopped being called", tag);
+    }
+    playback
+}
+
+/// Writes that change a register, plus every R13 write (it restarts the
+/// envelope), in register order
+fn effective(frames: &[Vec<(u8, u8)>]) -> Vec<Vec<(u8, u8)>> {
+    let mut state = [None; 16];
+    frames
+        .iter()
+        .map(|writes| {
+            let mut changed: Vec<(u8, u8)> = writes
+                .iter()
+                .filter(|&&(reg, value)| {
+                    let keep = reg == 13 || state[reg as usize] != Some(value);
+                    state[reg as usize] = Some(value);
+                    keep
+                })
+                .copied()
+                .collect();
+            changed.sort();
+            changed
+        })
+        .collect()
+}
+
+#[test]
+fn test_packed_stream_plays_like_plain() {
+    let plain = riff_song();
+    let mut packed = plain.clone();
+    packed.compression = MusicCompression::Packed;
+
+    let pass = plain.register_timeline().expand().len();
+    let calls = 2 * pass + 10;
+    let Some(bios) = common::bios_or_skip("packed music test") else { return };
+    let expected = play(&bios, "plain", &plain, calls);
+    let actual = play(&bios, "packed", &packed, calls);
+
+    let (expected, actual) = (effective(&expected.writes), effective(&actual.writes));
+    for (frame, (e, a)) in expected.iter().zip(&actual).enumerate() {
+        assert_eq!(e, a, "PSG writes differ on frame {} (pass is {} frames)", frame, pass);
+    }
+    assert!(expected.iter().filter(|w| w.iter().any(|&(reg, _)| reg == 13)).count() >= 6, "no envelope restarts");
+}
+
+#[test]
+fn test_packed_player_cycles_match_the_cost_model() {
+    let mut music = riff_song();
+    music.compression = MusicCompression::Packed;
+    let model = pack(&music.register_timeline(), "RIFF");
+    let pass = model.frame_cycles.len();
+    assert_eq!(pass, music.register_timeline().expand().len());
+
+    let Some(bios) = common::bios_or_skip("packed player cycle test") else { return };
+    let playback = play(&bios, "cycles", &music, 2 * pass + 1);
+    // The second pass starts with the loop jump, as the model assumes
+    let measured: Vec<u32> = playback.player_cycles[pass..2 * pass].iter().map(|&c| c as u32).collect();
+    for (frame, (m, e)) in measured.iter().zip(&model.frame_cycles).enumerate() {
+        assert_eq!(m, e, "frame {}: AU_PACKED_FRAME took {} cycles, the model says {}", frame, m, e);
+    }
+    assert_eq!(model.max_cycles, *measured.iter().max().unwrap());
+}
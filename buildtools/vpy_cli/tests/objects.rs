@@ -0,0 +1,148 @@
+// Separate compilation through `.vo` objects
+//
+// Assembles a hand-written program and a library, or a VPy program and a
+// VPy module, to objects with `vpy_cli assemble`, links them with
+// `vpy_cli link` and runs the ROM in the emulator (skipped when the BIOS
+// image is not available). Link errors must name the source file and line
+// of the offending symbol.
+
+mod common;
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+const MAIN: &str = r#"    INCLUDE "VECTREX.I"
+    ORG $0000
+    FCC "g GCE 2025"
+    FCB $80
+    FDB $0000
+    FCB $F8,$50,$20,$BB
+    FCC "OBJ"
+    FCB $80,$00
+start:
+    LDB #21
+    JSR TWICE
+    STB $C880
+forever:
+    JSR Wait_Recal
+    BRA forever
+"#;
+
+const LIB: &str = "; Utility library\n    EXPORT TWICE\nTWICE:\n    BSR add\n    RTS\nadd:\n    ASLB\n    RTS\n";
+
+fn vpy_cli(args: &[&Path]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_vpy_cli"))
+        .current_dir(common::repo_root())
+        .args(args)
+        .output()
+        .expect("failed to run vpy_cli")
+}
+
+/// Every global and the helpers the module needs live in the program
+const PROGRAM: &str = "offset = 0\nscore = 0\n\ndef main():\n    offset = 1\n    score = twice(20) + offset\n    score = score + twice(0)\n\ndef loop():\n    pass\n";
+
+/// Locals and the result go through RAM the linker gives the module
+const MODULE: &str = "def twice(x):\n    y = x + x\n    return y\n";
+
+/// Write `sources` to a fresh directory and assemble each one to a `.vo` there
+fn assemble(tag: &str, sources: &[(&str, &str)]) -> PathBuf {
+    let dir = common::write_project(tag, sources);
+    let mut args = vec![Path::new("assemble")];
+    let paths: Vec<PathBuf> = sources.iter().map(|(name, _)| dir.join(name)).collect();
+    args.extend(paths.iter().map(PathBuf::as_path));
+    let output = vpy_cli(&args);
+    assert!(output.status.success(), "assemble failed:\n{}", String::from_utf8_lossy(&output.stderr));
+    dir
+}
+
+#[test]
+fn test_assemble_and_link_library() {
+    // The library is assembled once; only its object takes part in the link
+    let dir = assemble("link", &[("main.asm", MAIN), ("lib.asm", LIB)]);
+    std::fs::remove_file(dir.join("lib.asm")).unwrap();
+
+    let rom_path = dir.join("game.bin");
+    let output = vpy_cli(&[
+        Path::new("link"),
+        &dir.join("main.vo"),
+        &dir.join("lib.vo"),
+        Path::new("-o"),
+        &rom_path,
+    ]);
+    assert!(output.status.success(), "link failed:\n{}", String::from_utf8_lossy(&output.stderr));
+    let rom = std::fs::read(&rom_path).unwrap();
+    let map = std::fs::read_to_string(dir.join("game.map")).unwrap();
+    let map_json = std::fs::read_to_string(dir.join("game.map.json")).unwrap();
+    let _ = std::fs::remove_dir_all(&dir);
+
+    // The map places TWICE (2 bytes of BSR, then add) right after main.asm
+    assert!(map.contains("$0023") && map.contains("TWICE"), "{}", map);
+    assert!(map_json.contains("lib.asm\""), "{}", map_json);
+
+    // LIB follows MAIN's 35 bytes: JSR TWICE points at it
+    assert_eq!(&rom[0x18..0x1B], &[0xBD, 0x00, 0x23]);
+
+    let Some(bios_path) = common::bios_or_skip("linked library run") else { return };
+    let bios = std::fs::read(bios_path).unwrap();
+    let mut machine = vectrex_emulator::Vectrex::new(&bios).unwrap();
+    machine.load_cartridge(&rom);
+    machine.boot_cartridge().unwrap();
+    for _ in 0..3 {
+        machine.run_frame(vectrex_emulator::CYCLES_PER_FRAME * 4).unwrap();
+    }
+    assert_eq!(machine.peek(0xC880), 42);
+}
+
+#[test]
+fn test_link_vpy_program_and_module() {
+    let dir = assemble("vpy", &[("game.vpy", PROGRAM), ("maths.vpy", MODULE)]);
+
+    let rom_path = dir.join("game.bin");
+    let output = vpy_cli(&[Path::new("link"), &dir.join("game.vo"), &dir.join("maths.vo"), Path::new("-o"), &rom_path]);
+    assert!(output.status.success(), "link failed:\n{}", String::from_utf8_lossy(&output.stderr));
+    let rom = std::fs::read(&rom_path).unwrap();
+    let map: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(dir.join("game.map.json")).unwrap()).unwrap();
+    let program = vpy_linker::VectrexObject::load(&dir.join("game.vo")).unwrap();
+    let module = vpy_linker::VectrexObject::load(&dir.join("maths.vo")).unwrap();
+    let _ = std::fs::remove_dir_all(&dir);
+
+    // The module brings no header or runtime of its own
+    assert!(module.find_symbol("START").is_none() && module.find_symbol("MAIN").is_none());
+    let imports: Vec<&str> = module.symbols.imports.iter().map(|s| s.name.as_str()).collect();
+    assert!(imports.contains(&"RESULT") && imports.contains(&"VAR_ARG0"), "{:?}", imports);
+    assert!(module.total_size() < 64, "module is {} bytes", module.total_size());
+
+    // Its locals follow the program's RAM
+    let ram = map["ram"].as_array().unwrap();
+    let address = |name: &str| ram.iter().find(|s| s["name"] == name).and_then(|s| s["address"].as_u64()).unwrap();
+    let program_ram = program.sections.iter().find(|s| s.name == ".bss").unwrap().size() as u64;
+    assert_eq!(address("LOC_TWICE__X"), 0xC880 + program_ram);
+    assert_eq!(address("LOC_TWICE__Y"), 0xC882 + program_ram);
+
+    let Some(bios_path) = common::bios_or_skip("linked VPy objects run") else { return };
+    let score = program.find_symbol("VAR_SCORE").unwrap().offset;
+    let bios = std::fs::read(bios_path).unwrap();
+    let mut machine = vectrex_emulator::Vectrex::new(&bios).unwrap();
+    machine.load_cartridge(&rom);
+    machine.boot_cartridge().unwrap();
+    for _ in 0..3 {
+        machine.run_frame(vectrex_emulator::CYCLES_PER_FRAME * 4).unwrap();
+    }
+    assert_eq!((machine.peek(score), machine.peek(score + 1)), (0, 41));
+}
+
+#[test]
+fn test_link_errors_name_file_and_line() {
+    let dir = assemble("errors", &[("main.asm", MAIN), ("lib.asm", LIB), ("copy.asm", LIB)]);
+
+    let output = vpy_cli(&[Path::new("link"), &dir.join("main.vo")]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(stderr.contains("'TWICE'") && stderr.contains("main.asm:11"), "{}", stderr);
+
+    let output = vpy_cli(&[Path::new("link"), &dir.join("main.vo"), &dir.join("lib.vo"), &dir.join("copy.vo")]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let _ = std::fs::remove_dir_all(&dir);
+    assert!(!output.status.success());
+    assert!(stderr.contains("lib.asm:3") && stderr.contains("copy.asm:3"), "{}", stderr);
+}
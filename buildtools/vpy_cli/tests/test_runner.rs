@@ -0,0 +1,45 @@
+// Test runner tests
+//
+// Runs `vpy_cli test` on a project with one passing and one failing
+// `test_*` function (skipped when the BIOS image is not available): the
+// failure must name the file and line of the assert and fail the command.
+
+mod common;
+
+use std::process::Command;
+
+#[test]
+fn test_failing_assert_reports_file_and_line() {
+    if common::bios_or_skip("failing assert test").is_none() {
+        return;
+    }
+
+    let dir = common::write_project(
+        "failing",
+        &[(
+            "src/main.vpy",
+            concat!(
+                "score = 0\n\n",
+                "def main():\n    pass\n\n",
+                "def loop():\n    pass\n\n",
+                "def test_passes():\n    score = 2\n    assert score == 2\n\n",
+                "def test_fails():\n    score = 3\n    assert score == 2\n",
+            ),
+        )],
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_vpy_cli"))
+        .current_dir(common::repo_root())
+        .arg("test")
+        .arg(dir.join("failing.vpyproj"))
+        .output()
+        .expect("failed to run vpy_cli");
+    let source = dir.join("src/main.vpy");
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success(), "a failing test must fail the run:\n{}", stdout);
+    let location = format!("assert failed at {}:15", source.display());
+    assert!(stdout.contains(&location), "expected '{}' in:\n{}", location, stdout);
+    assert!(stdout.contains("1 passed; 1 failed"), "{}", stdout);
+}
@@ -5,7 +5,9 @@
 
 pub mod m6809;
 pub mod vecres;
+pub mod path_ordering;
 pub mod musres;
+pub mod music_pack;
 pub mod levelres;
 pub mod sfxres;
 
@@ -132,6 +134,9 @@ pub struct GeneratedASM {
     
     /// External references that need linking
     pub external_refs: Vec<String>,
+
+    /// Peephole rule hits on this listing
+    pub peephole: m6809::peephole::PeepholeStats,
 }
 
 #[derive(Debug, Clone)]
@@ -156,15 +161,51 @@ pub fn generate_from_module(
         bank_config.rom_bank_size,
         assets,
     ).map_err(|e| CodegenError::Error(e))?;
+    let (asm_source, peephole) = m6809::peephole::optimize(&asm_source);
     
     Ok(GeneratedASM {
         asm_source,
         bank_config: bank_config.clone(),
         symbols: HashMap::new(), // TODO: Extract from generated ASM
         external_refs: Vec::new(),
+        peephole,
     })
 }
 
+/// Generate one unit of separate compilation: the program (a module with
+/// `main()`/`loop()`) exporting its RAM and runtime, or a module's functions
+/// and data with the runtime as imports (see `m6809::objects`)
+pub fn generate_object_from_module(
+    module: &Module,
+    title: &str,
+    assets: &[AssetInfo],
+) -> Result<m6809::objects::ObjectAsm, CodegenError> {
+    let mut object = if m6809::objects::is_program(module) {
+        m6809::objects::generate_program(module, title, assets)
+    } else {
+        m6809::objects::generate_module(module, assets)
+    }
+    .map_err(CodegenError::Error)?;
+    object.asm_source = m6809::peephole::optimize(&object.asm_source).0;
+    Ok(object)
+}
+
+/// Generate a unit-test ROM: `main()` calls `test_name` once, then reports
+/// through the test mailbox (see `m6809::test_harness`).
+pub fn generate_test_from_module(
+    module: &Module,
+    bank_config: &BankConfig,
+    title: &str,
+    assets: &[AssetInfo],
+    test_name: &str,
+) -> Result<GeneratedASM, CodegenError> {
+    let test_module = m6809::test_harness::build_test_module(module, test_name);
+    m6809::test_harness::set_test_harness(true);
+    let result = generate_from_module(&test_module, bank_config, title, assets);
+    m6809::test_harness::set_test_harness(false);
+    result
+}
+
 /// Generate unified ASM with bank markers (OLD - placeholder version)
 /// Kept for backward compatibility with existing code
 pub fn generate_unified_asm(
@@ -568,6 +609,7 @@ pub fn generate_unified_asm(
         bank_config: bank_config.clone(),
         symbols,
         external_refs: vec!["Wait_Recal".to_string()],
+        peephole: Default::default(),
     })
 }
 
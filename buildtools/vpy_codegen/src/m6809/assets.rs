@@ -78,6 +78,11 @@ fn collect_asset_names_from_stmt(stmt: &Stmt, used_names: &mut HashSet<String>)
                 collect_asset_names_from_stmt(s, used_names);
             }
         }
+        Stmt::Switch { cases, default, .. } => {
+            for s in cases.iter().flat_map(|(_, body)| body).chain(default.iter().flatten()) {
+                collect_asset_names_from_stmt(s, used_names);
+            }
+        }
         Stmt::Assign { value, .. } | Stmt::Let { value, .. } => {
             collect_asset_names_from_expr(value, used_names);
         }
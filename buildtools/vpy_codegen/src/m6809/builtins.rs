@@ -5,6 +5,7 @@
 //! - DRAW_LINE: Draw line from (x0,y0) to (x1,y1)
 //! - WAIT_RECAL: Wait for screen refresh
 //! - SET_INTENSITY: Set drawing intensity
+//! - CO_START/CO_RESUME/CO_ALIVE/CO_STOP: Drive coroutines (see coroutines.rs)
 
 use vpy_parser::{Expr, Module};
 use super::expressions;
@@ -14,7 +15,9 @@ use super::math_extended;
 use super::drawing;
 use super::level;
 use super::utilities;
+use super::coroutines;
 use crate::{AssetInfo, AssetType};
+use crate::sfxres::SfxResource;
 use crate::vecres::VecResource;
 use std::sync::atomic::{AtomicUsize, AtomicBool, Ordering};
 
@@ -66,7 +69,7 @@ static BUILTIN_ARITIES: &[(&str, usize)] = &[
     
     // Audio functions
     ("PLAY_MUSIC", 1),      // name
-    ("PLAY_SFX", 1),        // name
+    ("PLAY_SFX", 1),        // name [, priority [, channel]] (handled specially)
     ("STOP_MUSIC", 0),      // no args
     ("AUDIO_UPDATE", 0),    // no args
     ("MUSIC_UPDATE", 0),    // no args (deprecated)
@@ -85,6 +88,12 @@ static BUILTIN_ARITIES: &[(&str, usize)] = &[
     ("MIN", 2),             // a, b
     ("MAX", 2),             // a, b
     
+    // Coroutines
+    ("CO_START", 1),        // coroutine, args... (handled specially)
+    ("CO_RESUME", 1),       // coroutine
+    ("CO_ALIVE", 1),        // coroutine
+    ("CO_STOP", 1),         // coroutine
+    
     // Debug functions
     ("DEBUG_PRINT", 1),           // value
     ("DEBUG_PRINT_LABELED", 2),   // label, value
@@ -120,6 +129,18 @@ fn validate_builtin_arity(name: &str, arg_count: usize) -> Result<(), String> {
             }
             return Ok(());
         }
+        "PLAY_SFX" => {
+            if !(1..=3).contains(&arg_count) {
+                return Err(format!("PLAY_SFX requires 1 to 3 arguments (name, priority, channel), got {}", arg_count));
+            }
+            return Ok(());
+        }
+        "CO_START" => {
+            if arg_count == 0 {
+                return Err("CO_START requires a coroutine and its arguments, got 0 arguments".to_string());
+            }
+            return Ok(());
+        }
         _ => {}
     }
     
@@ -457,10 +478,7 @@ pub fn emit_builtin(
             true
         }
         "PLAY_SFX" => {
-            out.push_str("    ; PLAY_SFX: Play sound effect\n");
-            out.push_str("    JSR PLAY_SFX_RUNTIME\n");
-            out.push_str("    LDD #0\n");
-            out.push_str("    STD RESULT\n");
+            emit_play_sfx(args, out, assets);
             true
         }
         
@@ -493,6 +511,12 @@ pub fn emit_builtin(
             true
         }
         
+        // ===== Coroutines =====
+        "CO_START" | "CO_RESUME" | "CO_ALIVE" | "CO_STOP" => {
+            coroutines::emit_builtin(&up, args, out, assets);
+            true
+        }
+        
         // ===== Debug Tools =====
         "DEBUG_PRINT" => {
             debug::emit_debug_print(args, out, assets);
@@ -516,6 +540,14 @@ pub fn emit_builtin(
             math_extended::emit_cos(args, out, assets);
             true
         }
+        "SIN_FX" | "MATH_SIN_FX" => {
+            math_extended::emit_sin_fx(args, out, assets);
+            true
+        }
+        "COS_FX" | "MATH_COS_FX" => {
+            math_extended::emit_cos_fx(args, out, assets);
+            true
+        }
         "TAN" | "MATH_TAN" => {
             math_extended::emit_tan(args, out, assets);
             true
@@ -668,6 +700,59 @@ fn emit_set_intensity(args: &[Expr], out: &mut String, assets: &[AssetInfo]) {
     out.push_str("    STD RESULT\n");
 }
 
+/// PLAY_SFX("name" [, priority [, channel]]): priority defaults to the
+/// effect's own, channel to -1 (any slot); 0-2 = A-C
+fn emit_play_sfx(args: &[Expr], out: &mut String, assets: &[AssetInfo]) {
+    let Expr::StringLit(asset_name) = &args[0] else {
+        out.push_str("    ; ERROR: PLAY_SFX first argument must be string literal\n");
+        out.push_str("    LDD #0\n");
+        out.push_str("    STD RESULT\n");
+        return;
+    };
+    let sfx_assets: Vec<_> = assets.iter()
+        .filter(|a| matches!(a.asset_type, AssetType::Sfx))
+        .collect();
+    let resource = sfx_assets.iter()
+        .position(|a| a.name == *asset_name)
+        .and_then(|index| SfxResource::load(std::path::Path::new(&sfx_assets[index].path)).ok().map(|r| (index, r)));
+    let Some((asset_index, resource)) = resource else {
+        out.push_str(&format!("    ; ERROR: SFX asset '{}' not found\n", asset_name));
+        out.push_str(&format!("    ; Available SFX assets: {:?}\n",
+            sfx_assets.iter().map(|a| &a.name).collect::<Vec<_>>()));
+        out.push_str("    LDD #0\n");
+        out.push_str("    STD RESULT\n");
+        return;
+    };
+
+    out.push_str(&format!("    ; PLAY_SFX(\"{}\") - play sound effect (index={})\n", asset_name, asset_index));
+    match args.get(1) {
+        Some(priority) => {
+            expressions::emit_simple_expr(priority, out, assets);
+            out.push_str("    LDB RESULT+1\n");
+        }
+        None => out.push_str(&format!("    LDB #{}           ; Effect's own priority\n", resource.priority)),
+    }
+    out.push_str("    STB >SFX_REQ_PRIO\n");
+    match args.get(2) {
+        Some(channel) => {
+            expressions::emit_simple_expr(channel, out, assets);
+            out.push_str("    LDB RESULT+1\n");
+        }
+        None => out.push_str("    LDB #$FF          ; Any slot\n"),
+    }
+    out.push_str("    STB >SFX_REQ_CHAN\n");
+    if use_banked_assets() {
+        out.push_str(&format!("    LDX #{}        ; SFX asset index for lookup\n", asset_index));
+        out.push_str("    JSR PLAY_SFX_BANKED  ; Play with automatic bank switching\n");
+    } else {
+        let symbol = format!("_{}_SFX", resource.name.to_uppercase().replace([' ', '-'], "_"));
+        out.push_str(&format!("    LDX #{}  ; Load SFX data pointer\n", symbol));
+        out.push_str("    JSR PLAY_SFX_RUNTIME\n");
+    }
+    out.push_str("    LDD #0\n");
+    out.push_str("    STD RESULT\n");
+}
+
 /// Generate deterministic hash for string
 pub fn hash_string(s: &str) -> u64 {
     let mut hash: u64 = 0;
@@ -687,13 +772,11 @@ fn emit_print_text(args: &[Expr], out: &mut String, assets: &[AssetInfo]) {
     
     // Store all 3 arguments in VAR_ARG0, VAR_ARG1, VAR_ARG2 (like core implementation)
     // Arg 0: x coordinate
-    expressions::emit_simple_expr(&args[0], out, assets);
-    out.push_str("    LDD RESULT\n");
+    expressions::emit_expr_to_d(&args[0], out, assets);
     out.push_str("    STD VAR_ARG0\n");
     
     // Arg 1: y coordinate
-    expressions::emit_simple_expr(&args[1], out, assets);
-    out.push_str("    LDD RESULT\n");
+    expressions::emit_expr_to_d(&args[1], out, assets);
     out.push_str("    STD VAR_ARG1\n");
     
     // Arg 2: text string
@@ -706,8 +789,7 @@ fn emit_print_text(args: &[Expr], out: &mut String, assets: &[AssetInfo]) {
         }
         _ => {
             // Variable or expression - evaluate to pointer
-            expressions::emit_simple_expr(&args[2], out, assets);
-            out.push_str("    LDD RESULT\n");
+            expressions::emit_expr_to_d(&args[2], out, assets);
             out.push_str("    STD VAR_ARG2\n");
         }
     }
@@ -745,28 +827,23 @@ fn emit_draw_line(args: &[Expr], out: &mut String, assets: &[AssetInfo]) {
     
     // Store all arguments in DRAW_LINE_ARGS area (10 bytes: 5 words)
     // Arg 0: x0
-    expressions::emit_simple_expr(&args[0], out, assets);
-    out.push_str("    LDD RESULT\n");
+    expressions::emit_expr_to_d(&args[0], out, assets);
     out.push_str("    STD DRAW_LINE_ARGS+0    ; x0\n");
     
     // Arg 1: y0
-    expressions::emit_simple_expr(&args[1], out, assets);
-    out.push_str("    LDD RESULT\n");
+    expressions::emit_expr_to_d(&args[1], out, assets);
     out.push_str("    STD DRAW_LINE_ARGS+2    ; y0\n");
     
     // Arg 2: x1
-    expressions::emit_simple_expr(&args[2], out, assets);
-    out.push_str("    LDD RESULT\n");
+    expressions::emit_expr_to_d(&args[2], out, assets);
     out.push_str("    STD DRAW_LINE_ARGS+4    ; x1\n");
     
     // Arg 3: y1
-    expressions::emit_simple_expr(&args[3], out, assets);
-    out.push_str("    LDD RESULT\n");
+    expressions::emit_expr_to_d(&args[3], out, assets);
     out.push_str("    STD DRAW_LINE_ARGS+6    ; y1\n");
     
     // Arg 4: intensity
-    expressions::emit_simple_expr(&args[4], out, assets);
-    out.push_str("    LDD RESULT\n");
+    expressions::emit_expr_to_d(&args[4], out, assets);
     out.push_str("    STD DRAW_LINE_ARGS+8    ; intensity\n");
     
     // Call DRAW_LINE_WRAPPER which handles DP switching and segmentation
@@ -1015,6 +1092,11 @@ fn collect_strings_from_stmt(stmt: &vpy_parser::Stmt, strings: &mut std::collect
                 }
             }
         }
+        vpy_parser::Stmt::Switch { cases, default, .. } => {
+            for s in cases.iter().flat_map(|(_, body)| body).chain(default.iter().flatten()) {
+                collect_strings_from_stmt(s, strings);
+            }
+        }
         vpy_parser::Stmt::CompoundAssign { value, .. } => collect_strings_from_expr(value, strings),
         _ => {}
     }
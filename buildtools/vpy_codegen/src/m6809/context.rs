@@ -3,13 +3,26 @@
 //! Provides thread-local context for sharing information across expression compilation
 //! without needing to pass parameters through every function call.
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashSet;
+use super::locals::LocalsPlan;
 
 thread_local! {
     /// Set of array names that are mutable (GlobalLet, stored in RAM)
     /// Const arrays are not in this set (stored in ROM)
     static MUTABLE_ARRAYS: RefCell<HashSet<String>> = RefCell::new(HashSet::new());
+
+    /// Overlay layout of function locals (see locals.rs)
+    static LOCALS: RefCell<LocalsPlan> = RefCell::new(LocalsPlan::default());
+
+    /// Function whose body is being generated
+    static CURRENT_FUNCTION: RefCell<Option<String>> = const { RefCell::new(None) };
+
+    /// `yield`s generated so far in the current function
+    static YIELDS: Cell<usize> = const { Cell::new(0) };
+
+    /// Use the RESULT-based expression generator instead of lowering.rs
+    static LEGACY_EXPRESSIONS: Cell<bool> = const { Cell::new(false) };
 }
 
 /// Initialize the mutable arrays context
@@ -29,9 +42,81 @@ pub fn is_mutable_array(name: &str) -> bool {
     })
 }
 
+/// Set the locals layout used to resolve variable labels
+pub fn set_locals(plan: LocalsPlan) {
+    LOCALS.with(|l| {
+        *l.borrow_mut() = plan;
+    });
+}
+
+/// Run `f` with the current locals layout
+pub fn with_locals<R>(f: impl FnOnce(&LocalsPlan) -> R) -> R {
+    LOCALS.with(|l| f(&l.borrow()))
+}
+
+/// Set the function whose locals are in scope (None outside functions)
+pub fn set_current_function(name: Option<&str>) {
+    CURRENT_FUNCTION.with(|c| {
+        *c.borrow_mut() = name.map(str::to_string);
+    });
+    YIELDS.with(|y| y.set(0));
+}
+
+/// Function whose body is being generated
+pub fn current_function() -> Option<String> {
+    CURRENT_FUNCTION.with(|c| c.borrow().clone())
+}
+
+/// Number the next `yield` of the current function (1, 2, ...)
+pub fn next_yield() -> usize {
+    YIELDS.with(|y| {
+        y.set(y.get() + 1);
+        y.get()
+    })
+}
+
+/// `yield`s generated so far in the current function
+pub fn yields() -> usize {
+    YIELDS.with(Cell::get)
+}
+
+/// RAM label of a variable: the function's local slot if it has one,
+/// otherwise the global `VAR_{NAME}`
+pub fn var_label(name: &str) -> String {
+    match current_function() {
+        Some(function) => var_label_in(&function, name),
+        None => format!("VAR_{}", name.to_uppercase()),
+    }
+}
+
+/// RAM label of `name` as seen from inside `function`
+pub fn var_label_in(function: &str, name: &str) -> String {
+    with_locals(|plan| {
+        if let Some(co) = plan.coroutine(function).filter(|co| co.is_saved(name)) {
+            co.saved_label(name)
+        } else if plan.frame(function).is_some_and(|frame| frame.slot_offset(name).is_some()) {
+            LocalsPlan::label(function, name)
+        } else {
+            format!("VAR_{}", name.to_uppercase())
+        }
+    })
+}
+
+/// Select the expression generator: `true` for the previous RESULT/TMPPTR
+/// code, kept for benchmarking against lowering.rs
+pub fn set_legacy_expressions(legacy: bool) {
+    LEGACY_EXPRESSIONS.with(|l| l.set(legacy));
+}
+
+pub fn legacy_expressions() -> bool {
+    LEGACY_EXPRESSIONS.with(Cell::get)
+}
+
 /// Clear the mutable arrays context
 pub fn clear_context() {
     MUTABLE_ARRAYS.with(|ma| {
         ma.borrow_mut().clear();
     });
+    set_locals(LocalsPlan::default());
+    set_current_function(None);
 }
@@ -0,0 +1,578 @@
+//! Coroutines
+//!
+//! A function whose body contains `yield` is a coroutine: a script that runs
+//! a little every frame. It compiles to a state machine. `CO_<F>_STATE` holds
+//! 0 once the coroutine has finished, 1 before its first statement and k+1
+//! after its k-th `yield`; the function entry jumps through a table to the
+//! matching resume point.
+//!
+//! Between two resumes other functions reuse the overlaid frames (see
+//! locals.rs), so the locals that are live across a `yield` (including the
+//! parameters read after the start) get their own `CO_<F>__<NAME>` slots.
+//! The remaining locals stay in the frame.
+//!
+//! Builtins, usually called from `loop()`:
+//! - `CO_START(f, args...)`: stores the arguments and rewinds `f` to its first statement
+//! - `CO_RESUME(f)`: runs `f` until its next `yield` (returns 1) or its end (returns 0)
+//! - `CO_ALIVE(f)`: 1 until `f` finishes
+//! - `CO_STOP(f)`: finishes `f` without running it
+//!
+//! Each coroutine has a single instance: starting it again restarts it.
+
+use std::collections::{BTreeMap, HashSet};
+use vpy_parser::{AssignTarget, CallInfo, Expr, Function, Item, Module, Stmt};
+use super::context;
+use super::functions::fresh_label;
+use super::lowering;
+use crate::AssetInfo;
+
+/// Builtins taking a coroutine as first argument
+pub const BUILTINS: &[&str] = &["CO_START", "CO_RESUME", "CO_ALIVE", "CO_STOP"];
+
+/// `yield`s one coroutine can have: its state is a byte and 0 means finished
+pub const MAX_YIELDS: usize = 254;
+
+/// A function compiled as a resumable state machine
+#[derive(Debug, Clone, PartialEq)]
+pub struct Coroutine {
+    /// Function label (uppercase)
+    pub function: String,
+    /// Parameter names (uppercase)
+    pub params: Vec<String>,
+    /// Locals kept in `CO_` slots (uppercase), parameters first
+    pub saved: Vec<String>,
+}
+
+impl Coroutine {
+    /// Analyse `func`, whose locals (parameters first) are `locals`
+    pub fn from_function(func: &Function, locals: &[String]) -> Result<Self, String> {
+        if let Some(line) = first_return_value(&func.body) {
+            return Err(format!(
+                "coroutine '{}' returns a value at line {}; coroutines can only `return` without one",
+                func.name, line
+            ));
+        }
+        let mut liveness = Liveness::default();
+        let mut live = liveness.block(&func.body, HashSet::new());
+        live.extend(liveness.across);
+        Ok(Coroutine {
+            function: func.name.to_uppercase(),
+            params: func.params.iter().map(|p| p.to_uppercase()).collect(),
+            saved: locals.iter().filter(|name| live.contains(*name)).cloned().collect(),
+        })
+    }
+
+    pub fn is_saved(&self, name: &str) -> bool {
+        self.saved.contains(&name.to_uppercase())
+    }
+
+    /// RAM byte with the resume point (0 = finished)
+    pub fn state_label(&self) -> String {
+        format!("CO_{}_STATE", self.function)
+    }
+
+    /// RAM slot of a saved local: `CO_<FUNCTION>__<NAME>`
+    pub fn saved_label(&self, name: &str) -> String {
+        format!("CO_{}__{}", self.function, name.to_uppercase())
+    }
+
+    /// Code label of the k-th resume point (0 = first statement)
+    fn resume_label(&self, k: usize) -> String {
+        if k == 0 {
+            format!("CO_{}_BEGIN", self.function)
+        } else {
+            format!("CO_{}_Y{}", self.function, k)
+        }
+    }
+
+    fn done_label(&self) -> String {
+        format!("CO_{}_DONE", self.function)
+    }
+}
+
+pub fn contains_yield(stmts: &[Stmt]) -> bool {
+    first_yield(stmts).is_some()
+}
+
+/// Line of the first `yield` in `stmts`
+pub fn first_yield(stmts: &[Stmt]) -> Option<usize> {
+    stmts.iter().find_map(|stmt| match stmt {
+        Stmt::Yield { source_line } => Some(*source_line),
+        _ => nested_blocks(stmt).into_iter().find_map(first_yield),
+    })
+}
+
+fn first_return_value(stmts: &[Stmt]) -> Option<usize> {
+    stmts.iter().find_map(|stmt| match stmt {
+        Stmt::Return(Some(_), line) => Some(*line),
+        _ => nested_blocks(stmt).into_iter().find_map(first_return_value),
+    })
+}
+
+fn nested_blocks(stmt: &Stmt) -> Vec<&[Stmt]> {
+    match stmt {
+        Stmt::For { body, .. } | Stmt::ForIn { body, .. } | Stmt::While { body, .. } => vec![body],
+        Stmt::If { body, elifs, else_body, .. } => {
+            let mut blocks: Vec<&[Stmt]> = vec![body];
+            blocks.extend(elifs.iter().map(|(_, b)| b.as_slice()));
+            blocks.extend(else_body.as_deref());
+            blocks
+        }
+        Stmt::Switch { cases, default, .. } => {
+            let mut blocks: Vec<&[Stmt]> = cases.iter().map(|(_, b)| b.as_slice()).collect();
+            blocks.extend(default.as_deref());
+            blocks
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Backward liveness over the statement tree (names uppercase)
+#[derive(Default)]
+struct Liveness {
+    /// Names live right after some `yield`
+    across: HashSet<String>,
+    /// Live sets at `break` and `continue` of the enclosing loops
+    loops: Vec<(HashSet<String>, HashSet<String>)>,
+}
+
+impl Liveness {
+    /// Names live before `stmts` given the names live after them
+    fn block(&mut self, stmts: &[Stmt], live_out: HashSet<String>) -> HashSet<String> {
+        stmts.iter().rev().fold(live_out, |live, stmt| self.stmt(stmt, live))
+    }
+
+    fn stmt(&mut self, stmt: &Stmt, mut live: HashSet<String>) -> HashSet<String> {
+        match stmt {
+            Stmt::Assign { target: AssignTarget::Ident { name, .. }, value, .. } | Stmt::Let { name, value, .. } => {
+                live.remove(&name.to_uppercase());
+                uses(value, &mut live);
+            }
+            Stmt::Assign { target, value, .. } | Stmt::CompoundAssign { target, value, .. } => {
+                target_uses(target, &mut live);
+                uses(value, &mut live);
+            }
+            Stmt::Expr(expr, _) | Stmt::Assert { cond: expr, .. } => uses(expr, &mut live),
+            Stmt::Return(expr, _) => {
+                live.clear();
+                if let Some(expr) = expr {
+                    uses(expr, &mut live);
+                }
+            }
+            Stmt::Yield { .. } => self.across.extend(live.iter().cloned()),
+            Stmt::Break { .. } => return self.loops.last().map(|(b, _)| b.clone()).unwrap_or_default(),
+            Stmt::Continue { .. } => return self.loops.last().map(|(_, c)| c.clone()).unwrap_or_default(),
+            Stmt::Pass { .. } => {}
+            Stmt::If { cond, body, elifs, else_body, .. } => {
+                let mut rest = match else_body {
+                    Some(else_body) => self.block(else_body, live.clone()),
+                    None => live.clone(),
+                };
+                for (elif_cond, elif_body) in elifs.iter().rev() {
+                    rest.extend(self.block(elif_body, live.clone()));
+                    uses(elif_cond, &mut rest);
+                }
+                rest.extend(self.block(body, live));
+                uses(cond, &mut rest);
+                return rest;
+            }
+            Stmt::Switch { expr, cases, default, .. } => {
+                let mut before = match default {
+                    Some(default) => self.block(default, live.clone()),
+                    None => live.clone(),
+                };
+                for (value, body) in cases {
+                    before.extend(self.block(body, live.clone()));
+                    uses(value, &mut before);
+                }
+                uses(expr, &mut before);
+                return before;
+            }
+            Stmt::While { cond, body, .. } => {
+                // Iterate to a fixpoint: the loop head sees what the body needs next time round
+                let mut head = live.clone();
+                uses(cond, &mut head);
+                loop {
+                    self.loops.push((live.clone(), head.clone()));
+                    let body_in = self.block(body, head.clone());
+                    self.loops.pop();
+                    let mut next = head.clone();
+                    next.extend(body_in);
+                    if next == head {
+                        return head;
+                    }
+                    head = next;
+                }
+            }
+            // Not generated by this backend; keep every name they mention alive
+            Stmt::For { var, start, end, step, body, .. } => {
+                live.insert(var.to_uppercase());
+                uses(start, &mut live);
+                uses(end, &mut live);
+                if let Some(step) = step {
+                    uses(step, &mut live);
+                }
+                let body_in = self.block(body, live.clone());
+                live.extend(body_in);
+            }
+            Stmt::ForIn { var, iterable, body, .. } => {
+                live.insert(var.to_uppercase());
+                uses(iterable, &mut live);
+                let body_in = self.block(body, live.clone());
+                live.extend(body_in);
+            }
+        }
+        live
+    }
+}
+
+fn target_uses(target: &AssignTarget, live: &mut HashSet<String>) {
+    match target {
+        AssignTarget::Ident { name, .. } => {
+            live.insert(name.to_uppercase());
+        }
+        AssignTarget::Index { target, index, .. } => {
+            uses(target, live);
+            uses(index, live);
+        }
+        AssignTarget::FieldAccess { target, .. } => uses(target, live),
+    }
+}
+
+/// Add the names `expr` reads to `live`
+fn uses(expr: &Expr, live: &mut HashSet<String>) {
+    match expr {
+        Expr::Ident(id) => {
+            live.insert(id.name.to_uppercase());
+        }
+        Expr::Call(call) => call.args.iter().for_each(|a| uses(a, live)),
+        Expr::MethodCall(mc) => {
+            uses(&mc.target, live);
+            mc.args.iter().for_each(|a| uses(a, live));
+        }
+        Expr::Binary { left, right, .. } | Expr::Compare { left, right, .. } | Expr::Logic { left, right, .. } => {
+            uses(left, live);
+            uses(right, live);
+        }
+        Expr::Not(inner) | Expr::BitNot(inner) => uses(inner, live),
+        Expr::List(items) => items.iter().for_each(|i| uses(i, live)),
+        Expr::Index { target, index } => {
+            uses(target, live);
+            uses(index, live);
+        }
+        Expr::FieldAccess { target, .. } => uses(target, live),
+        Expr::Number(_) | Expr::StringLit(_) | Expr::StructInit { .. } => {}
+    }
+}
+
+/// Check how `module` uses its coroutines: `main`/`loop` cannot yield,
+/// coroutines are only driven through the CO_* builtins, and CO_START
+/// passes one argument per parameter
+pub fn check_module(module: &Module, coroutines: &BTreeMap<String, Coroutine>) -> Result<(), String> {
+    for item in &module.items {
+        let Item::Function(func) = item else { continue };
+        if func.name.eq_ignore_ascii_case("main") || func.name.eq_ignore_ascii_case("loop") {
+            if let Some(line) = first_yield(&func.body) {
+                return Err(format!(
+                    "'{}' cannot yield (line {}); move the script into a function and run it with CO_START/CO_RESUME",
+                    func.name.to_lowercase(), line
+                ));
+            }
+        }
+        let mut result = Ok(());
+        for_each_call(&func.body, &mut |call| {
+            if result.is_ok() {
+                result = check_call(call, coroutines);
+            }
+        });
+        result?;
+    }
+    Ok(())
+}
+
+fn check_call(call: &CallInfo, coroutines: &BTreeMap<String, Coroutine>) -> Result<(), String> {
+    let name = call.name.to_uppercase();
+    if coroutines.contains_key(&name) {
+        return Err(format!(
+            "line {}: '{}' contains yield; start it with CO_START({}) and run it with CO_RESUME({})",
+            call.source_line, call.name, call.name, call.name
+        ));
+    }
+    if !BUILTINS.contains(&name.as_str()) {
+        return Ok(());
+    }
+    let target = match call.args.first() {
+        Some(Expr::Ident(id)) => id.name.to_uppercase(),
+        _ => return Err(format!("line {}: {} expects the name of a coroutine first", call.source_line, name)),
+    };
+    let Some(co) = coroutines.get(&target) else {
+        return Err(format!(
+            "line {}: {} expects a coroutine, but '{}' is not a function containing yield",
+            call.source_line, name, target
+        ));
+    };
+    let expected = if name == "CO_START" { 1 + co.params.len() } else { 1 };
+    if call.args.len() != expected {
+        return Err(format!(
+            "line {}: {}({}) requires exactly {} argument{}, got {}",
+            call.source_line, name, target, expected, if expected == 1 { "" } else { "s" }, call.args.len()
+        ));
+    }
+    Ok(())
+}
+
+fn for_each_call(stmts: &[Stmt], f: &mut impl FnMut(&CallInfo)) {
+    fn expr_calls(expr: &Expr, f: &mut impl FnMut(&CallInfo)) {
+        match expr {
+            Expr::Call(call) => {
+                f(call);
+                call.args.iter().for_each(|a| expr_calls(a, f));
+            }
+            Expr::MethodCall(mc) => {
+                expr_calls(&mc.target, f);
+                mc.args.iter().for_each(|a| expr_calls(a, f));
+            }
+            Expr::Binary { left, right, .. } | Expr::Compare { left, right, .. } | Expr::Logic { left, right, .. } => {
+                expr_calls(left, f);
+                expr_calls(right, f);
+            }
+            Expr::Not(inner) | Expr::BitNot(inner) => expr_calls(inner, f),
+            Expr::List(items) => items.iter().for_each(|i| expr_calls(i, f)),
+            Expr::Index { target, index } => {
+                expr_calls(target, f);
+                expr_calls(index, f);
+            }
+            Expr::FieldAccess { target, .. } => expr_calls(target, f),
+            Expr::Number(_) | Expr::StringLit(_) | Expr::Ident(_) | Expr::StructInit { .. } => {}
+        }
+    }
+    fn target_calls(target: &AssignTarget, f: &mut impl FnMut(&CallInfo)) {
+        match target {
+            AssignTarget::Ident { .. } => {}
+            AssignTarget::Index { target, index, .. } => {
+                expr_calls(target, f);
+                expr_calls(index, f);
+            }
+            AssignTarget::FieldAccess { target, .. } => expr_calls(target, f),
+        }
+    }
+
+    for stmt in stmts {
+        match stmt {
+            Stmt::Assign { target, value, .. } | Stmt::CompoundAssign { target, value, .. } => {
+                target_calls(target, f);
+                expr_calls(value, f);
+            }
+            Stmt::Let { value, .. } | Stmt::Expr(value, _) | Stmt::Assert { cond: value, .. } => expr_calls(value, f),
+            Stmt::Return(Some(value), _) => expr_calls(value, f),
+            Stmt::For { start, end, step, .. } => {
+                expr_calls(start, f);
+                expr_calls(end, f);
+                if let Some(step) = step {
+                    expr_calls(step, f);
+                }
+            }
+            Stmt::ForIn { iterable, .. } => expr_calls(iterable, f),
+            Stmt::While { cond, .. } | Stmt::If { cond, .. } => expr_calls(cond, f),
+            Stmt::Switch { expr, cases, .. } => {
+                expr_calls(expr, f);
+                cases.iter().for_each(|(value, _)| expr_calls(value, f));
+            }
+            _ => {}
+        }
+        if let Stmt::If { elifs, .. } = stmt {
+            elifs.iter().for_each(|(cond, _)| expr_calls(cond, f));
+        }
+        for block in nested_blocks(stmt) {
+            for_each_call(block, f);
+        }
+    }
+}
+
+/// Function entry: jump to the resume point in the state byte
+pub fn emit_entry(co: &Coroutine, out: &mut String) {
+    out.push_str(&format!("    LDB {}\n", co.state_label()));
+    out.push_str(&format!("    LBEQ {}   ; Finished\n", co.done_label()));
+    out.push_str("    DECB\n");
+    out.push_str("    CLRA            ; Unsigned index (B,X would be signed)\n");
+    out.push_str("    ASLB            ; 2-byte table entries\n");
+    out.push_str("    ROLA\n");
+    out.push_str(&format!("    LDX #CO_{}_TABLE\n", co.function));
+    out.push_str("    JMP [D,X]\n");
+    out.push_str(&format!("{}:\n", co.resume_label(0)));
+}
+
+/// Start-up code: every coroutine starts finished (RAM is not cleared at power-on)
+pub fn emit_state_init(out: &mut String) {
+    context::with_locals(|plan| {
+        for co in plan.coroutines.values() {
+            out.push_str(&format!("    CLR {}   ; Coroutine not started\n", co.state_label()));
+        }
+    });
+}
+
+/// `yield`: save the next resume point and return 1
+pub fn emit_yield(co: &Coroutine, k: usize, out: &mut String) {
+    out.push_str(&format!("    LDB #{}\n", k + 1));
+    out.push_str(&format!("    STB {}   ; yield {}\n", co.state_label(), k));
+    out.push_str("    LDD #1\n");
+    out.push_str("    STD RESULT\n");
+    out.push_str("    RTS\n");
+    out.push_str(&format!("{}:\n", co.resume_label(k)));
+}
+
+/// `return` inside a coroutine
+pub fn emit_return(co: &Coroutine, out: &mut String) {
+    out.push_str(&format!("    LBRA {}\n", co.done_label()));
+}
+
+/// End of the body and the resume table for its `yields` resume points
+pub fn emit_exit(co: &Coroutine, yields: usize, out: &mut String) {
+    out.push_str(&format!("{}:\n", co.done_label()));
+    out.push_str(&format!("    CLR {}\n", co.state_label()));
+    out.push_str("    LDD #0\n");
+    out.push_str("    STD RESULT\n");
+    out.push_str("    RTS\n");
+    out.push_str(&format!("CO_{}_TABLE:\n", co.function));
+    for k in 0..=yields {
+        out.push_str(&format!("    FDB {}\n", co.resume_label(k)));
+    }
+}
+
+/// CO_START / CO_RESUME / CO_ALIVE / CO_STOP (arguments checked by `check_module`)
+pub fn emit_builtin(name: &str, args: &[Expr], out: &mut String, assets: &[AssetInfo]) {
+    let Some(Expr::Ident(id)) = args.first() else { return };
+    let Some(co) = context::with_locals(|plan| plan.coroutine(&id.name).cloned()) else { return };
+    match name {
+        "CO_START" => {
+            out.push_str(&format!("    ; CO_START: {}\n", co.function));
+            for (param, arg) in co.params.iter().zip(&args[1..]) {
+                lowering::to_d(arg, out, assets);
+                out.push_str(&format!("    STD {}   ; Parameter '{}'\n", context::var_label_in(&co.function, param), param.to_lowercase()));
+            }
+            out.push_str("    LDB #1\n");
+            out.push_str(&format!("    STB {}\n", co.state_label()));
+            out.push_str("    LDD #0\n");
+        }
+        "CO_RESUME" => {
+            out.push_str(&format!("    JSR {}   ; CO_RESUME\n", co.function));
+            return;
+        }
+        "CO_ALIVE" => {
+            let finished = fresh_label("CO_ALIVE");
+            out.push_str(&format!("    LDB {}   ; CO_ALIVE\n", co.state_label()));
+            out.push_str(&format!("    BEQ {}\n", finished));
+            out.push_str("    LDB #1\n");
+            out.push_str(&format!("{}:\n", finished));
+            out.push_str("    CLRA\n");
+        }
+        "CO_STOP" => {
+            out.push_str(&format!("    CLR {}   ; CO_STOP\n", co.state_label()));
+            out.push_str("    LDD #0\n");
+        }
+        _ => return,
+    }
+    out.push_str("    STD RESULT\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Module {
+        let tokens = vpy_parser::lex(source).unwrap();
+        vpy_parser::parser::parse(tokens, "test.vpy").unwrap()
+    }
+
+    fn function<'a>(module: &'a Module, name: &str) -> &'a Function {
+        module.items.iter().find_map(|item| match item {
+            Item::Function(f) if f.name == name => Some(f),
+            _ => None,
+        }).unwrap()
+    }
+
+    fn names(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_only_locals_live_across_yield_are_saved() {
+        let module = parse(concat!(
+            "def walk(steps, speed):\n",
+            "    i = 0\n",
+            "    while i < steps:\n",
+            "        tmp = i * speed\n",
+            "        x = tmp\n",
+            "        yield\n",
+            "        i = i + 1\n",
+            "    done = 1\n",
+        ));
+        let locals = names(&["STEPS", "SPEED", "I", "TMP", "X", "DONE"]);
+        let co = Coroutine::from_function(function(&module, "walk"), &locals).unwrap();
+        // tmp, x and done are dead at every yield
+        assert_eq!(co.saved, names(&["STEPS", "SPEED", "I"]));
+        assert_eq!(co.params, names(&["STEPS", "SPEED"]));
+        assert_eq!(co.saved_label("i"), "CO_WALK__I");
+    }
+
+    #[test]
+    fn test_value_read_in_next_iteration_is_saved() {
+        let module = parse(concat!(
+            "def blink():\n",
+            "    on = 0\n",
+            "    while 1:\n",
+            "        if on:\n",
+            "            on = 0\n",
+            "        else:\n",
+            "            on = 1\n",
+            "        yield\n",
+        ));
+        let co = Coroutine::from_function(function(&module, "blink"), &names(&["ON"])).unwrap();
+        assert_eq!(co.saved, names(&["ON"]));
+    }
+
+    #[test]
+    fn test_return_value_is_rejected() {
+        let module = parse("def f():\n    yield\n    return 3\n");
+        let err = Coroutine::from_function(function(&module, "f"), &[]).unwrap_err();
+        assert!(err.contains("line 3"), "{}", err);
+    }
+
+    #[test]
+    fn test_misuse_is_reported() {
+        let check = |source: &str| {
+            let module = parse(source);
+            let f = function(&module, "script");
+            let co = Coroutine::from_function(f, &names(&["N"])).unwrap();
+            check_module(&module, &BTreeMap::from([("SCRIPT".to_string(), co)]))
+        };
+        let script = "def script(n):\n    yield\n\n";
+
+        assert!(check(&format!("{}def loop():\n    CO_START(script, 3)\n    CO_RESUME(script)\n", script)).is_ok());
+        let err = check(&format!("{}def loop():\n    script(3)\n", script)).unwrap_err();
+        assert!(err.contains("CO_START(script)"), "{}", err);
+        let err = check(&format!("{}def loop():\n    CO_START(script)\n", script)).unwrap_err();
+        assert!(err.contains("requires exactly 2 arguments, got 1"), "{}", err);
+        let err = check(&format!("{}def loop():\n    CO_RESUME(loop)\n", script)).unwrap_err();
+        assert!(err.contains("'LOOP' is not a function containing yield"), "{}", err);
+        let err = check(&format!("{}def loop():\n    yield\n", script)).unwrap_err();
+        assert!(err.starts_with("'loop' cannot yield"), "{}", err);
+    }
+
+    #[test]
+    fn test_program_start_up_and_yield_limit() {
+        let program = |yields: usize| {
+            let source = format!(
+                "def main():\n    pass\n\ndef loop():\n    CO_RESUME(script)\n\ndef script():\n{}",
+                "    yield\n".repeat(yields)
+            );
+            super::super::generate_m6809_asm(&parse(&source), "T", 32768, 32768, &[])
+        };
+        let asm = program(70).unwrap();
+        let init = asm.find("    CLR CO_SCRIPT_STATE").expect("state cleared at start-up");
+        assert!(init < asm.find("Call main()").unwrap());
+        assert!(asm.contains("    JMP [D,X]\n"));
+        let err = program(MAX_YIELDS + 1).unwrap_err();
+        assert!(err.contains("more than 254 yields"), "{}", err);
+    }
+}
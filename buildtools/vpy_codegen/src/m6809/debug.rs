@@ -165,18 +165,15 @@ pub fn emit_print_number(args: &[Expr], out: &mut String, assets: &[AssetInfo])
     out.push_str("    ; PRINT_NUMBER(x, y, num)\n");
     
     // Evaluate x position
-    expressions::emit_simple_expr(&args[0], out, assets);
-    out.push_str("    LDD RESULT\n");
+    expressions::emit_expr_to_d(&args[0], out, assets);
     out.push_str("    STD VAR_ARG0    ; X position\n");
     
     // Evaluate y position
-    expressions::emit_simple_expr(&args[1], out, assets);
-    out.push_str("    LDD RESULT\n");
+    expressions::emit_expr_to_d(&args[1], out, assets);
     out.push_str("    STD VAR_ARG1    ; Y position\n");
     
     // Evaluate number
-    expressions::emit_simple_expr(&args[2], out, assets);
-    out.push_str("    LDD RESULT\n");
+    expressions::emit_expr_to_d(&args[2], out, assets);
     out.push_str("    STD VAR_ARG2    ; Number value\n");
     
     // Call helper
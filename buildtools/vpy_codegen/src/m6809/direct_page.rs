@@ -0,0 +1,580 @@
+//! Direct Page Tracking
+//!
+//! Generated code runs with DP=$C8 (RAM) and switches to DP=$D0 (VIA) around
+//! BIOS calls. This pass follows DP through the finished listing and inserts
+//! `SETDP` lines so the assembler picks direct addressing for any operand on
+//! the page DP holds: `LDA VAR_X` on $C8xx becomes the 2-byte, 4-cycle form
+//! instead of the 3-byte, 5-cycle extended one.
+//!
+//! DP is followed across branches and calls. Each routine gets a summary of
+//! the DP it returns with for every DP it can be called with, so helpers that
+//! save and restore DP keep the caller's page. BIOS routines in
+//! `BIOS_DP_SETTERS` leave DP on a fixed page and the ones in
+//! `BIOS_DP_KEEPERS` keep it; any other call outside the listing (another
+//! BIOS routine, a raw `JSR $Fxxx`, an external symbol) leaves DP unknown.
+//! Wherever DP cannot be proved, the pass emits a bare `SETDP` and the
+//! assembler keeps extended addressing.
+//!
+//! Only the RAM page is declared. DP=$D0 is tracked (so $C8xx accesses around
+//! BIOS calls stay extended) but VIA accesses keep their extended timing: the
+//! drawing helpers' beam positioning is tuned to it.
+
+use std::collections::HashMap;
+
+/// BIOS entry points that leave DP on a fixed page
+const BIOS_DP_SETTERS: &[(&str, u16, u8)] = &[
+    ("DP_TO_D0", 0xF1AA, 0xD0),
+    ("DP_TO_C8", 0xF1AF, 0xC8),
+    ("WAIT_RECAL", 0xF192, 0xD0),
+    ("INIT_VIA", 0xF14C, 0xD0),
+    ("INIT_OS", 0xF18B, 0xD0),
+    ("INIT_OS_RAM", 0xF164, 0xC8),
+    ("RESET0REF_D0", 0xF34A, 0xD0),
+    ("SELECT_GAME", 0xF7A9, 0xC8),
+];
+
+/// BIOS entry points that return with the DP they were called with (checked
+/// against the listing in docs/vec_prog_docs/html/BIOS.ASM)
+const BIOS_DP_KEEPERS: &[(&str, u16)] = &[
+    ("SET_REFRESH", 0xF1A2), ("READ_BTNS_MASK", 0xF1B4), ("READ_BTNS", 0xF1BA),
+    ("JOY_ANALOG", 0xF1F5), ("JOY_DIGITAL", 0xF1F8),
+    ("SOUND_BYTE", 0xF256), ("SOUND_BYTE_X", 0xF259), ("CLEAR_SOUND", 0xF272), ("SOUND_BYTES", 0xF27D),
+    ("DO_SOUND", 0xF289), ("INIT_MUSIC_CHK", 0xF687), ("INIT_MUSIC", 0xF68D), ("EXPLOSION_SND", 0xF92E),
+    ("INTENSITY_1F", 0xF29D), ("INTENSITY_3F", 0xF2A1), ("INTENSITY_5F", 0xF2A5), ("INTENSITY_7F", 0xF2A9),
+    ("INTENSITY_A", 0xF2AB),
+    ("DOT_IX", 0xF2C1), ("DOT_D", 0xF2C3), ("DOT_HERE", 0xF2C5), ("RECALIBRATE", 0xF2E6),
+    ("MOVETO_D_7F", 0xF2FC), ("MOVETO_IX_FF", 0xF308), ("MOVETO_IX_7F", 0xF30C), ("MOVETO_IX_A", 0xF30E),
+    ("MOVETO_IX", 0xF310), ("MOVETO_D", 0xF312),
+    ("CHECK0REF", 0xF34F), ("RESET0REF", 0xF354), ("RESET_PEN", 0xF35B),
+    ("PRINT_STR_HWYX", 0xF373), ("PRINT_STR_YX", 0xF378), ("PRINT_STR_D", 0xF37A), ("PRINT_LIST", 0xF38A),
+    ("PRINT_SHIPS", 0xF393), ("PRINT_STR", 0xF495),
+    ("MOV_DRAW_VLC_A", 0xF3AD), ("MOV_DRAW_VL", 0xF3BC), ("DRAW_VLC", 0xF3CE), ("DRAW_VL_B", 0xF3D2),
+    ("DRAW_VLCS", 0xF3D6), ("DRAW_VL_A", 0xF3DA), ("DRAW_VL", 0xF3DD), ("DRAW_LINE_D", 0xF3DF),
+    ("DRAW_VLP", 0xF410), ("DRAW_PAT_VL", 0xF437),
+    ("RANDOM_3", 0xF511), ("RANDOM", 0xF517), ("CLEAR_X_B", 0xF53F), ("CLEAR_X_256", 0xF545),
+    ("DEC_3_COUNTERS", 0xF55A), ("DEC_6_COUNTERS", 0xF55E),
+    ("DELAY_3", 0xF56D), ("DELAY_2", 0xF571), ("DELAY_1", 0xF575), ("DELAY_0", 0xF579), ("DELAY_B", 0xF57A),
+    ("BITMASK_A", 0xF57E), ("ABS_A_B", 0xF584), ("ABS_B", 0xF58B),
+    ("ROT_VL_AB", 0xF610), ("ROT_VL", 0xF616), ("XFORM_RUN", 0xF65D), ("XFORM_RISE", 0xF663),
+    ("CLEAR_SCORE", 0xF84F), ("ADD_SCORE_A", 0xF85E), ("ADD_SCORE_D", 0xF87C), ("OBJ_WILL_HIT", 0xF8F3),
+];
+
+/// Pages declared to the assembler with `SETDP`
+const DIRECT_PAGES: &[u8] = &[0xC8];
+
+/// DP value at a point of the listing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Dp {
+    /// Not reached (yet)
+    Unreached,
+    Page(u8),
+    Unknown,
+}
+
+impl Dp {
+    fn meet(self, other: Dp) -> Dp {
+        match (self, other) {
+            (Dp::Unreached, x) | (x, Dp::Unreached) => x,
+            (a, b) if a == b => a,
+            _ => Dp::Unknown,
+        }
+    }
+}
+
+/// DP plus the value saved by the last `PSHS DP`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct State {
+    dp: Dp,
+    saved: Dp,
+}
+
+impl State {
+    const UNREACHED: State = State { dp: Dp::Unreached, saved: Dp::Unreached };
+    const UNKNOWN: State = State { dp: Dp::Unknown, saved: Dp::Unknown };
+
+    fn meet(self, other: State) -> State {
+        State { dp: self.dp.meet(other.dp), saved: self.saved.meet(other.saved) }
+    }
+
+    fn with_dp(self, dp: Dp) -> State {
+        State { dp, ..self }
+    }
+}
+
+/// Where a branch, jump or call goes
+#[derive(Debug, Clone, Copy)]
+enum Target {
+    /// Line of a label defined once in the listing
+    Line(usize),
+    /// Outside the listing (BIOS): leaves DP on this page, or keeps it (None)
+    External(Option<u8>),
+    /// Computed, ambiguous or unknown destination
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Effect {
+    None,
+    /// DP loaded from a known or unknown value (`TFR A,DP`, `EXG`)
+    Set(Dp),
+    /// `PSHS DP`
+    Push,
+    /// `PULS DP` (`returns` when PC is pulled too)
+    Pull { returns: bool },
+    /// `RTS`, `PULS PC`
+    Return,
+    /// `RTI` and anything else that leaves with an unknown DP
+    Exit,
+    Call(Target),
+    Jump(Target),
+    Branch(Target),
+}
+
+#[derive(Default)]
+struct Line {
+    label: Option<String>,
+    /// Instruction (not data or directive) and its DP effect
+    instruction: Option<Effect>,
+    /// FCB/FDB/FCC...: execution does not fall through into what follows
+    data: bool,
+    org: bool,
+    /// Label named in an operand (LDX #TABLE, FDB HANDLER): may be entered
+    /// through a computed jump
+    address_taken: bool,
+}
+
+/// Inserts `SETDP` lines that follow DP through generated assembly
+pub fn track_direct_page(asm: &str) -> String {
+    let source: Vec<&str> = asm.lines().collect();
+    let lines = parse(&source);
+    let states = analyse(&lines);
+
+    let mut out = String::with_capacity(asm.len() + asm.len() / 16);
+    // Page the assembler currently assumes (None: not known here, resync)
+    let mut assumed: Option<Option<u8>> = Some(Some(0));
+    for (index, text) in source.iter().enumerate() {
+        let line = &lines[index];
+        if line.org || is_setdp(text) {
+            assumed = None;
+        }
+        if line.instruction.is_some() {
+            let page = match states[index].dp {
+                Dp::Page(page) if DIRECT_PAGES.contains(&page) => Some(page),
+                _ => None,
+            };
+            if assumed != Some(page) {
+                match page {
+                    Some(page) => out.push_str(&format!("    SETDP ${:02X}\n", page)),
+                    None => out.push_str("    SETDP\n"),
+                }
+                assumed = Some(page);
+            }
+        }
+        out.push_str(text);
+        out.push('\n');
+    }
+    out
+}
+
+fn is_setdp(text: &str) -> bool {
+    code_of(text).split_whitespace().next().is_some_and(|word| word.eq_ignore_ascii_case("SETDP"))
+}
+
+/// Code part of a line (the assembler cuts comments at the first ';')
+fn code_of(text: &str) -> &str {
+    let code = match text.find(';') {
+        Some(index) => &text[..index],
+        None => text,
+    };
+    let code = code.trim();
+    if code.starts_with('*') { "" } else { code }
+}
+
+fn parse(source: &[&str]) -> Vec<Line> {
+    // Labels with their scoped names (".X" belongs to the last global label)
+    let mut definitions: HashMap<String, Vec<usize>> = HashMap::new();
+    let mut scopes = Vec::with_capacity(source.len());
+    let mut scope = String::new();
+    for (index, text) in source.iter().enumerate() {
+        let code = code_of(text);
+        if let Some((label, _)) = code.split_once(':') {
+            let label = label.trim();
+            if !label.is_empty() {
+                let name = scoped(label, &scope);
+                if !label.starts_with('.') {
+                    scope = label.to_string();
+                }
+                definitions.entry(name).or_default().push(index);
+            }
+        }
+        scopes.push(scope.clone());
+    }
+    let resolve = |name: &str, scope: &str| -> Option<Target> {
+        definitions.get(&scoped(name, scope)).map(|lines| match lines.as_slice() {
+            [line] => Target::Line(*line),
+            _ => Target::Unknown,
+        })
+    };
+
+    let mut lines = Vec::with_capacity(source.len());
+    let mut mentions: Vec<(&str, usize)> = Vec::new();
+    let mut last_load: Option<(char, u8)> = None;
+    for (index, text) in source.iter().enumerate() {
+        let code = code_of(text);
+        let mut line = Line::default();
+        if let Some((label, _)) = code.split_once(':') {
+            // The assembler ignores anything after a label on the same line
+            if !label.trim().is_empty() {
+                line.label = Some(scoped(label.trim(), &scopes[index]));
+                last_load = None;
+            }
+            lines.push(line);
+            continue;
+        }
+        let mut words = code.split_whitespace();
+        let Some(mnemonic) = words.next() else {
+            lines.push(line);
+            continue;
+        };
+        let mnemonic = mnemonic.to_ascii_uppercase();
+        let operand = words.next().unwrap_or("");
+        if mnemonic == "ORG" {
+            line.org = true;
+            last_load = None;
+            lines.push(line);
+            continue;
+        }
+        if is_data(&mnemonic) || is_directive(&mnemonic) || operand.eq_ignore_ascii_case("EQU") {
+            line.data = is_data(&mnemonic);
+            mentions.extend(identifiers(code).map(|word| (word, index)));
+            lines.push(line);
+            continue;
+        }
+        let target = |operand: &str| -> Target {
+            let operand = operand.trim_start_matches(['<', '>']);
+            if operand.contains([',', '[']) {
+                return Target::Unknown;
+            }
+            if let Some(address) = parse_number(operand) {
+                return bios_target(None, Some(address));
+            }
+            resolve(operand, &scopes[index]).unwrap_or_else(|| bios_target(Some(operand), None))
+        };
+        let effect = match mnemonic.as_str() {
+            "JSR" | "BSR" | "LBSR" => Effect::Call(target(operand)),
+            "JMP" | "BRA" | "LBRA" => Effect::Jump(target(operand)),
+            "BRN" | "LBRN" => Effect::None,
+            "RTS" => Effect::Return,
+            "RTI" => Effect::Exit,
+            "SWI" | "SWI2" | "SWI3" => Effect::Set(Dp::Unknown),
+            "PSHS" | "PSHU" if has_register(operand, "DP") => Effect::Push,
+            "PULS" | "PULU" if has_register(operand, "DP") => Effect::Pull { returns: has_register(operand, "PC") },
+            "PULS" | "PULU" if has_register(operand, "PC") => Effect::Return,
+            "TFR" => match operand.to_ascii_uppercase().split_once(',') {
+                Some((source, "DP")) => {
+                    let value = match (source, last_load) {
+                        ("A", Some(('A', value))) | ("B", Some(('B', value))) => Dp::Page(value),
+                        _ => Dp::Unknown,
+                    };
+                    Effect::Set(value)
+                }
+                _ => Effect::None,
+            },
+            "EXG" if has_register(operand, "DP") => Effect::Set(Dp::Unknown),
+            m if is_conditional_branch(m) => Effect::Branch(target(operand)),
+            _ => Effect::None,
+        };
+        let direct = matches!(effect, Effect::Call(Target::Line(_)) | Effect::Jump(Target::Line(_)) | Effect::Branch(Target::Line(_)));
+        if !direct {
+            mentions.extend(identifiers(operand).map(|word| (word, index)));
+        }
+        last_load = match (mnemonic.as_str(), operand.strip_prefix('#').and_then(parse_number)) {
+            ("LDA", Some(value)) if value <= 0xFF => Some(('A', value as u8)),
+            ("LDB", Some(value)) if value <= 0xFF => Some(('B', value as u8)),
+            _ => None,
+        };
+        line.instruction = Some(effect);
+        lines.push(line);
+    }
+
+    for (word, index) in mentions {
+        for &line in definitions.get(&scoped(word, &scopes[index])).into_iter().flatten() {
+            lines[line].address_taken = true;
+        }
+    }
+    lines
+}
+
+/// Names mentioned in an operand or expression
+fn identifiers(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '@'))
+        .filter(|word| word.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_' || c == '.'))
+}
+
+fn scoped(label: &str, scope: &str) -> String {
+    if label.starts_with('.') {
+        format!("{}{}", scope, label)
+    } else {
+        label.to_string()
+    }
+}
+
+/// A call outside the listing: only the BIOS routines listed above are known
+fn bios_target(name: Option<&str>, address: Option<u16>) -> Target {
+    let is = |bios: &str, bios_address: u16| {
+        name.is_some_and(|name| name.eq_ignore_ascii_case(bios)) || address == Some(bios_address)
+    };
+    if let Some((_, _, page)) = BIOS_DP_SETTERS.iter().find(|(bios, bios_address, _)| is(bios, *bios_address)) {
+        return Target::External(Some(*page));
+    }
+    if BIOS_DP_KEEPERS.iter().any(|(bios, bios_address)| is(bios, *bios_address)) {
+        return Target::External(None);
+    }
+    Target::Unknown
+}
+
+fn has_register(operand: &str, register: &str) -> bool {
+    operand.split(',').any(|r| r.trim().eq_ignore_ascii_case(register))
+}
+
+fn is_conditional_branch(mnemonic: &str) -> bool {
+    const CONDITIONS: &[&str] = &[
+        "EQ", "NE", "CC", "CS", "HS", "LO", "HI", "LS", "GT", "GE", "LT", "LE", "MI", "PL", "VC", "VS",
+    ];
+    let short = mnemonic.strip_prefix("LB").or_else(|| mnemonic.strip_prefix('B'));
+    short.is_some_and(|condition| CONDITIONS.contains(&condition))
+}
+
+fn is_data(mnemonic: &str) -> bool {
+    matches!(mnemonic, "FCB" | "FDB" | "FCC" | "FDW" | "DB" | "DW" | "RMB" | "ZMB" | "EXCH" | "ALIGN" | "INCBIN")
+}
+
+fn is_directive(mnemonic: &str) -> bool {
+    matches!(mnemonic, "SETDP" | "INCLUDE" | "EQU" | "END")
+}
+
+fn parse_number(text: &str) -> Option<u16> {
+    if let Some(hex) = text.strip_prefix('$').or_else(|| text.strip_prefix("0x")) {
+        u16::from_str_radix(hex, 16).ok()
+    } else {
+        text.parse().ok()
+    }
+}
+
+/// Exit DP of a routine (its label line) called with a given DP
+type Summaries = HashMap<(usize, Dp), Dp>;
+
+/// DP at the start of every line
+fn analyse(lines: &[Line]) -> Vec<State> {
+    // Routines are the labels reached by a call
+    let mut routines: Vec<usize> = lines
+        .iter()
+        .filter_map(|line| match line.instruction {
+            Some(Effect::Call(Target::Line(entry))) => Some(entry),
+            _ => None,
+        })
+        .collect();
+    routines.sort_unstable();
+    routines.dedup();
+
+    // Every page the listing can put in DP, plus "unknown"
+    let mut pages = vec![Dp::Unknown];
+    for line in lines {
+        let page = match line.instruction {
+            Some(Effect::Set(Dp::Page(page))) => page,
+            Some(Effect::Call(Target::External(Some(page)))) => page,
+            _ => continue,
+        };
+        if !pages.contains(&Dp::Page(page)) {
+            pages.push(Dp::Page(page));
+        }
+    }
+
+    // DP each routine returns with, for each DP it can be called with.
+    // Summaries start optimistic ("never returns") and only move down.
+    let mut summaries: Summaries = HashMap::new();
+    for &entry in &routines {
+        for &page in &pages {
+            summaries.insert((entry, page), Dp::Unreached);
+        }
+    }
+    loop {
+        let mut changed = false;
+        for &entry in &routines {
+            for &page in &pages {
+                let seed = State { dp: page, saved: Dp::Unknown };
+                let (_, exit) = flow(lines, &[(entry, seed)], &summaries, false);
+                if summaries[&(entry, page)] != exit {
+                    summaries.insert((entry, page), exit);
+                    changed = true;
+                }
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    let seeds: Vec<(usize, State)> = roots(lines).into_iter().map(|line| (line, State::UNKNOWN)).collect();
+    flow(lines, &seeds, &summaries, true).0
+}
+
+/// Lines where execution can start without a visible predecessor: the top of
+/// the listing, labels whose address is taken (tables, pointers) and labels
+/// nothing branches to that are not reached by falling through
+fn roots(lines: &[Line]) -> Vec<usize> {
+    let mut targeted = vec![false; lines.len()];
+    for line in lines {
+        if let Some(Effect::Call(Target::Line(t)) | Effect::Jump(Target::Line(t)) | Effect::Branch(Target::Line(t))) =
+            line.instruction
+        {
+            targeted[t] = true;
+        }
+    }
+    let mut roots = vec![0];
+    let mut falls_through = true;
+    for (index, line) in lines.iter().enumerate() {
+        if line.label.is_some() && (line.address_taken || (!targeted[index] && !falls_through)) {
+            roots.push(index);
+        }
+        if line.org || line.data {
+            falls_through = false;
+        }
+        if let Some(effect) = line.instruction {
+            falls_through = !matches!(
+                effect,
+                Effect::Return | Effect::Exit | Effect::Jump(_) | Effect::Pull { returns: true }
+            );
+        }
+    }
+    roots
+}
+
+/// Propagates states from `seeds`. With `follow_calls`, calls also flow into
+/// the called routine; otherwise the walk stays in one routine and the
+/// returned `Dp` is the meet of the DP values it returns with.
+fn flow(
+    lines: &[Line],
+    seeds: &[(usize, State)],
+    summaries: &Summaries,
+    follow_calls: bool,
+) -> (Vec<State>, Dp) {
+    let mut states = vec![State::UNREACHED; lines.len()];
+    let mut exit = Dp::Unreached;
+    let mut work: Vec<usize> = Vec::new();
+    let push = |states: &mut Vec<State>, work: &mut Vec<usize>, line: usize, state: State| {
+        if line >= states.len() {
+            return;
+        }
+        let merged = states[line].meet(state);
+        if merged != states[line] {
+            states[line] = merged;
+            work.push(line);
+        }
+    };
+    for &(line, state) in seeds {
+        push(&mut states, &mut work, line, state);
+    }
+
+    while let Some(index) = work.pop() {
+        let state = states[index];
+        let line = &lines[index];
+        if line.org || line.data {
+            continue;
+        }
+        let next = index + 1;
+        let Some(effect) = line.instruction else {
+            push(&mut states, &mut work, next, state);
+            continue;
+        };
+        match effect {
+            Effect::None => push(&mut states, &mut work, next, state),
+            Effect::Set(dp) => push(&mut states, &mut work, next, state.with_dp(dp)),
+            Effect::Push => push(&mut states, &mut work, next, State { saved: state.dp, ..state }),
+            Effect::Pull { returns: false } => push(&mut states, &mut work, next, state.with_dp(state.saved)),
+            Effect::Pull { returns: true } => exit = exit.meet(state.saved),
+            Effect::Return => exit = exit.meet(state.dp),
+            Effect::Exit => exit = exit.meet(Dp::Unknown),
+            Effect::Call(target) => {
+                let after = match target {
+                    Target::Line(entry) => {
+                        if follow_calls {
+                            push(&mut states, &mut work, entry, State { dp: state.dp, saved: Dp::Unknown });
+                        }
+                        match state.dp {
+                            Dp::Unreached => Dp::Unreached,
+                            dp => summaries.get(&(entry, dp)).copied().unwrap_or(Dp::Unknown),
+                        }
+                    }
+                    Target::External(Some(page)) => Dp::Page(page),
+                    Target::External(None) => state.dp,
+                    Target::Unknown => Dp::Unknown,
+                };
+                if after != Dp::Unreached {
+                    push(&mut states, &mut work, next, state.with_dp(after));
+                }
+            }
+            Effect::Jump(target) | Effect::Branch(target) => {
+                match target {
+                    Target::Line(line) => push(&mut states, &mut work, line, state),
+                    // Tail call into the BIOS
+                    Target::External(Some(page)) => exit = exit.meet(Dp::Page(page)),
+                    Target::External(None) => exit = exit.meet(state.dp),
+                    Target::Unknown => exit = exit.meet(Dp::Unknown),
+                }
+                if matches!(effect, Effect::Branch(_)) {
+                    push(&mut states, &mut work, next, state);
+                }
+            }
+        }
+    }
+    (states, exit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setdp_before(out: &str, instruction: &str) -> Option<String> {
+        let lines: Vec<&str> = out.lines().collect();
+        let at = lines.iter().position(|l| l.trim() == instruction)?;
+        lines[..at]
+            .iter()
+            .rev()
+            .map(|l| l.trim())
+            .find(|l| l.starts_with("SETDP"))
+            .map(str::to_string)
+    }
+
+    #[test]
+    fn test_bios_calls_switch_page() {
+        let asm = "    ORG $0000\nSTART:\n    JSR $F1AF\n    LDA VAR_X\n    JSR DP_to_D0\n    LDB VAR_X\n    JSR DP_to_C8\n    STB VAR_Y\n    RTS\n";
+        let out = track_direct_page(asm);
+        assert_eq!(setdp_before(&out, "LDA VAR_X").as_deref(), Some("SETDP $C8"));
+        assert_eq!(setdp_before(&out, "LDB VAR_X").as_deref(), Some("SETDP"));
+        assert_eq!(setdp_before(&out, "STB VAR_Y").as_deref(), Some("SETDP $C8"));
+    }
+
+    #[test]
+    fn test_helper_restoring_dp_keeps_caller_page() {
+        let asm = "    ORG $0000\nSTART:\n    JSR DP_to_C8\n    JSR HELPER\n    LDA VAR_X\n    RTS\nHELPER:\n    PSHS DP\n    JSR DP_to_D0\n    PULS DP,PC\n";
+        let out = track_direct_page(asm);
+        assert_eq!(setdp_before(&out, "LDA VAR_X").as_deref(), Some("SETDP $C8"));
+    }
+
+    #[test]
+    fn test_unknown_external_calls_forget_page() {
+        let asm = "    ORG $0000\nSTART:\n    JSR DP_to_C8\n    JSR Moveto_d\n    LDA VAR_X\n    JSR $F000\n    LDB VAR_X\n    JSR DP_to_C8\n    JSR Not_A_Bios_Routine\n    STB VAR_Y\n    RTS\n";
+        let out = track_direct_page(asm);
+        assert_eq!(setdp_before(&out, "LDA VAR_X").as_deref(), Some("SETDP $C8"));
+        assert_eq!(setdp_before(&out, "LDB VAR_X").as_deref(), Some("SETDP"));
+        assert_eq!(setdp_before(&out, "STB VAR_Y").as_deref(), Some("SETDP"));
+    }
+
+    #[test]
+    fn test_indirect_call_forgets_page() {
+        let asm = "    ORG $0000\nSTART:\n    JSR DP_to_C8\n    JSR [VECTOR]\n    LDA VAR_X\n    RTS\n";
+        let out = track_direct_page(asm);
+        assert_eq!(setdp_before(&out, "LDA VAR_X").as_deref(), Some("SETDP"));
+    }
+}
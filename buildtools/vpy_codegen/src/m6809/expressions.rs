@@ -2,17 +2,74 @@
 //!
 //! Compiles VPy expressions to M6809 assembly
 //! Result stored in RESULT (2-byte RAM variable)
+//!
+//! Expressions are lowered by lowering.rs into D. The functions ending in
+//! `_legacy` are the previous generator, which stores every intermediate
+//! value to RESULT; `context::set_legacy_expressions(true)` selects it
+//! (`vpy_cli bench` compares the two).
 
 use vpy_parser::{Expr, BinOp, CmpOp, LogicOp};
 use super::builtins;
 use super::context;  // For checking mutable arrays
+use super::lowering;
 use crate::AssetInfo;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
 static LABEL_COUNTER: AtomicUsize = AtomicUsize::new(0);
 
 /// Emit code for simple expression (numbers, vars, strings, calls)
+/// Result stored in RESULT
 pub fn emit_simple_expr(expr: &Expr, out: &mut String, assets: &[AssetInfo]) {
+    if context::legacy_expressions() {
+        emit_simple_expr_legacy(expr, out, assets);
+    } else {
+        lowering::to_d(expr, out, assets);
+        out.push_str("    STD RESULT\n");
+    }
+}
+
+/// Emit code leaving the value of `expr` in D
+pub fn emit_expr_to_d(expr: &Expr, out: &mut String, assets: &[AssetInfo]) {
+    if context::legacy_expressions() {
+        emit_simple_expr_legacy(expr, out, assets);
+        out.push_str("    LDD RESULT\n");
+    } else {
+        lowering::to_d(expr, out, assets);
+    }
+}
+
+/// Emit code jumping to `target` when `cond` is false (0)
+pub fn emit_branch_if_false(cond: &Expr, target: &str, out: &mut String, assets: &[AssetInfo]) {
+    if context::legacy_expressions() {
+        emit_simple_expr_legacy(cond, out, assets);
+        out.push_str("    LDD RESULT\n");
+        out.push_str(&format!("    LBEQ {}\n", target));
+    } else {
+        lowering::branch(cond, false, target, true, out, assets);
+    }
+}
+
+/// Emit code jumping to `target` when `cond` is true (non-zero)
+pub fn emit_branch_if_true(cond: &Expr, target: &str, out: &mut String, assets: &[AssetInfo]) {
+    if context::legacy_expressions() {
+        emit_simple_expr_legacy(cond, out, assets);
+        out.push_str("    LDD RESULT\n");
+        out.push_str(&format!("    LBNE {}\n", target));
+    } else {
+        lowering::branch(cond, true, target, true, out, assets);
+    }
+}
+
+/// Emit code for an expression statement (value unused)
+pub fn emit_discard(expr: &Expr, out: &mut String, assets: &[AssetInfo]) {
+    if context::legacy_expressions() {
+        emit_simple_expr_legacy(expr, out, assets);
+    } else {
+        lowering::effect(expr, out, assets);
+    }
+}
+
+fn emit_simple_expr_legacy(expr: &Expr, out: &mut String, assets: &[AssetInfo]) {
     match expr {
         Expr::Number(n) => {
             out.push_str(&format!("    LDD #{}\n", n));
@@ -27,8 +84,8 @@ pub fn emit_simple_expr(expr: &Expr, out: &mut String, assets: &[AssetInfo]) {
         }
         
         Expr::Ident(id) => {
-            // Variable references use uppercase labels
-            out.push_str(&format!("    LDD VAR_{}\n", id.name.to_uppercase()));
+            // Local slot of the current function, or the global VAR_{NAME}
+            out.push_str(&format!("    LDD {}\n", context::var_label(&id.name)));
             out.push_str("    STD RESULT\n");
         }
         
@@ -40,10 +97,25 @@ pub fn emit_simple_expr(expr: &Expr, out: &mut String, assets: &[AssetInfo]) {
             
             // User function call (name already uppercase from unifier)
             // Evaluate arguments and store in VAR_ARG0-4
-            for (i, arg) in call.args.iter().enumerate().take(5) {
-                emit_simple_expr(arg, out, assets);
-                out.push_str("    LDD RESULT\n");
-                out.push_str(&format!("    STD VAR_ARG{}\n", i));
+            let args = &call.args[..call.args.len().min(5)];
+            if args.iter().skip(1).any(lowering::contains_call) {
+                // A later argument makes a call that may overwrite VAR_ARGn:
+                // keep the values on the stack until all of them are known
+                for arg in args {
+                    emit_simple_expr_legacy(arg, out, assets);
+                    out.push_str("    LDD RESULT\n");
+                    out.push_str("    PSHS D\n");
+                }
+                for i in (0..args.len()).rev() {
+                    out.push_str("    PULS D\n");
+                    out.push_str(&format!("    STD VAR_ARG{}\n", i));
+                }
+            } else {
+                for (i, arg) in args.iter().enumerate() {
+                    emit_simple_expr_legacy(arg, out, assets);
+                    out.push_str("    LDD RESULT\n");
+                    out.push_str(&format!("    STD VAR_ARG{}\n", i));
+                }
             }
             
             // Call function
@@ -59,12 +131,12 @@ pub fn emit_simple_expr(expr: &Expr, out: &mut String, assets: &[AssetInfo]) {
             match op {
                 LogicOp::And => {
                     // Short-circuit AND: if left is false, result is 0 (false)
-                    emit_simple_expr(left, out, assets);
+                    emit_simple_expr_legacy(left, out, assets);
                     out.push_str("    LDD RESULT\n");
                     out.push_str(&format!("    LBEQ .LOGIC_{}_FALSE\n", id));
                     
                     // Left is true, check right
-                    emit_simple_expr(right, out, assets);
+                    emit_simple_expr_legacy(right, out, assets);
                     out.push_str("    LDD RESULT\n");
                     out.push_str(&format!("    LBEQ .LOGIC_{}_FALSE\n", id));
                     
@@ -79,12 +151,12 @@ pub fn emit_simple_expr(expr: &Expr, out: &mut String, assets: &[AssetInfo]) {
                 }
                 LogicOp::Or => {
                     // Short-circuit OR: if left is true, result is 1 (true)
-                    emit_simple_expr(left, out, assets);
+                    emit_simple_expr_legacy(left, out, assets);
                     out.push_str("    LDD RESULT\n");
                     out.push_str(&format!("    LBNE .LOGIC_{}_TRUE\n", id));
                     
                     // Left is false, check right
-                    emit_simple_expr(right, out, assets);
+                    emit_simple_expr_legacy(right, out, assets);
                     out.push_str("    LDD RESULT\n");
                     out.push_str(&format!("    LBNE .LOGIC_{}_TRUE\n", id));
                     
@@ -102,7 +174,7 @@ pub fn emit_simple_expr(expr: &Expr, out: &mut String, assets: &[AssetInfo]) {
         
         Expr::Not(expr) => {
             let id = LABEL_COUNTER.fetch_add(1, Ordering::SeqCst);
-            emit_simple_expr(expr, out, assets);
+            emit_simple_expr_legacy(expr, out, assets);
             out.push_str("    LDD RESULT\n");
             out.push_str(&format!("    LBNE .NOT_{}_ZERO\n", id));
             out.push_str("    LDD #1\n");
@@ -114,7 +186,7 @@ pub fn emit_simple_expr(expr: &Expr, out: &mut String, assets: &[AssetInfo]) {
         }
         
         Expr::BitNot(expr) => {
-            emit_simple_expr(expr, out, assets);
+            emit_simple_expr_legacy(expr, out, assets);
             out.push_str("    LDD RESULT\n");
             out.push_str("    COMA\n");
             out.push_str("    COMB\n");
@@ -141,12 +213,12 @@ pub fn emit_simple_expr(expr: &Expr, out: &mut String, assets: &[AssetInfo]) {
 
 fn emit_binop(left: &Expr, op: BinOp, right: &Expr, out: &mut String, assets: &[AssetInfo]) {
     // Evaluate left
-    emit_simple_expr(left, out, assets);
+    emit_simple_expr_legacy(left, out, assets);
     out.push_str("    LDD RESULT\n");
     out.push_str("    PSHS D\n");
     
     // Evaluate right
-    emit_simple_expr(right, out, assets);
+    emit_simple_expr_legacy(right, out, assets);
     out.push_str("    LDD RESULT\n");
     
     // Perform operation
@@ -238,11 +310,11 @@ fn emit_compare(left: &Expr, op: CmpOp, right: &Expr, out: &mut String, assets:
     // CRITICAL FIX: Evaluate RIGHT first, push to stack
     // Then evaluate LEFT, compare D (LEFT) with stack (RIGHT)
     // CMPD does: D - [S], so we want LEFT - RIGHT
-    emit_simple_expr(right, out, assets);
+    emit_simple_expr_legacy(right, out, assets);
     out.push_str("    LDD RESULT\n");
     out.push_str("    PSHS D\n");          // Push RIGHT to stack
     
-    emit_simple_expr(left, out, assets);
+    emit_simple_expr_legacy(left, out, assets);
     out.push_str("    LDD RESULT\n");      // D = LEFT
     out.push_str("    CMPD ,S++\n");       // Compare LEFT - RIGHT (sets flags correctly)
     
@@ -280,13 +352,13 @@ fn emit_index(array: &Expr, index: &Expr, out: &mut String, assets: &[AssetInfo]
         out.push_str("    PSHS X\n");  // CRITICAL: Save X before evaluating index
     } else {
         // Complex array expression - evaluate it
-        emit_simple_expr(array, out, assets);
+        emit_simple_expr_legacy(array, out, assets);
         out.push_str("    LDX RESULT  ; Array base address\n");
         out.push_str("    PSHS X\n");
     }
     
     // Evaluate index
-    emit_simple_expr(index, out, assets);
+    emit_simple_expr_legacy(index, out, assets);
     out.push_str("    LDD RESULT  ; Index\n");
     out.push_str("    ASLB        ; Multiply by 2 (16-bit elements)\n");
     out.push_str("    ROLA\n");
@@ -7,8 +7,12 @@
 //! - Multi-bank: Functions distributed across banks (generate_functions_by_bank)
 
 use vpy_parser::{Module, Function, Stmt, Expr};
+use super::context;
+use super::coroutines;
 use super::expressions;
+use super::lowering;
 use super::joystick;
+use super::test_harness;
 use crate::AssetInfo;
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicUsize, Ordering};
@@ -44,6 +48,10 @@ pub fn has_audio_calls(module: &Module) -> bool {
             },
             Stmt::While { cond, body, .. } => check_expr(cond) || body.iter().any(check_stmt),
             Stmt::For { body, .. } => body.iter().any(check_stmt),
+            Stmt::Switch { cases, default, .. } => {
+                cases.iter().any(|(_, b)| b.iter().any(check_stmt)) ||
+                default.as_ref().is_some_and(|body| body.iter().any(check_stmt))
+            },
             _ => false,
         }
     }
@@ -124,12 +132,16 @@ pub fn generate_functions(module: &Module, assets: &[AssetInfo]) -> Result<Strin
     // CRITICAL: Initialize joystick mux ONCE before any J1_X/J1_Y calls
     // (copied from core/src/backend/m6809/mod.rs lines 834-849)
     joystick::emit_joystick_init(&mut asm);
+    coroutines::emit_state_init(&mut asm);
     
     // Call main() if exists
     if let Some(main) = main_fn {
         asm.push_str("    ; Call main() for initialization\n");
         generate_function_body(main, &mut asm, assets)?;
     }
+    if test_harness::is_test_harness() {
+        test_harness::emit_test_passed(&mut asm);
+    }
     
     // Infinite loop calling loop()
     asm.push_str("\n.MAIN_LOOP:\n");
@@ -176,44 +188,76 @@ pub fn generate_functions(module: &Module, assets: &[AssetInfo]) -> Result<Strin
             continue;  // Already handled above
         }
         
-        // IMPORTANT: Function name already comes uppercase from unifier
-        asm.push_str(&format!("; Function: {}\n", func.name));
-        asm.push_str(&format!("{}:\n", func.name));
-        generate_function_body(func, &mut asm, assets)?;
-        
-        // Only add RTS if function doesn't end with explicit return
-        let has_explicit_return = func.body.last()
-            .map(|stmt| matches!(stmt, Stmt::Return(..)))
-            .unwrap_or(false);
-        if !has_explicit_return {
-            asm.push_str("    RTS\n");
-        }
-        asm.push_str("\n");
+        generate_function(func, &mut asm, assets)?;
     }
     
     Ok(asm)
 }
 
+/// Label, body and return of a user function (not MAIN or LOOP)
+pub fn generate_function(func: &Function, asm: &mut String, assets: &[AssetInfo]) -> Result<(), String> {
+    // IMPORTANT: Function name already comes uppercase from unifier
+    asm.push_str(&format!("; Function: {}\n", func.name));
+    asm.push_str(&format!("{}:\n", func.name));
+    generate_function_body(func, asm, assets)?;
+    
+    // Only add RTS if function doesn't end with explicit return
+    if !ends_with_return(func) {
+        asm.push_str("    RTS\n");
+    }
+    asm.push('\n');
+    Ok(())
+}
+
 fn generate_function_body(func: &Function, asm: &mut String, assets: &[AssetInfo]) -> Result<(), String> {
+    context::set_current_function(Some(&func.name));
+    let coroutine = context::with_locals(|plan| plan.coroutine(&func.name).cloned());
+    
+    if let Some(co) = &coroutine {
+        // CO_START already stored the arguments; resume where the last yield left off
+        coroutines::emit_entry(co, asm);
+    } else {
+        // Copy arguments from VAR_ARG0-4 into the parameter slots
+        for (i, param) in func.params.iter().enumerate() {
+            asm.push_str(&format!("    LDD VAR_ARG{}\n", i));
+            asm.push_str(&format!("    STD {}   ; Parameter '{}'\n", context::var_label(param), param));
+        }
+    }
+    
     // Generate code for each statement
-    for stmt in &func.body {
-        generate_statement(stmt, asm, assets)?;
+    let result = func.body.iter().try_for_each(|stmt| generate_statement(stmt, asm, assets));
+    if let Some(co) = &coroutine {
+        coroutines::emit_exit(co, context::yields(), asm);
     }
-    Ok(())
+    context::set_current_function(None);
+    result
+}
+
+/// Coroutine whose body is being generated, if any
+fn current_coroutine() -> Option<coroutines::Coroutine> {
+    let function = context::current_function()?;
+    context::with_locals(|plan| plan.coroutine(&function).cloned())
+}
+
+/// Whether the generated body already returns (explicit `return` or coroutine exit)
+fn ends_with_return(func: &Function) -> bool {
+    matches!(func.body.last(), Some(Stmt::Return(..)))
+        || context::with_locals(|plan| plan.coroutine(&func.name).is_some())
 }
 
 fn generate_statement(stmt: &Stmt, asm: &mut String, assets: &[AssetInfo]) -> Result<(), String> {
+    // Source line marker for the debug line maps (kept by the peephole pass)
+    asm.push_str(&format!("    ; VPy_LINE:{}\n", stmt.source_line()));
     match stmt {
         Stmt::Assign { target, value, .. } => {
             match target {
                 vpy_parser::AssignTarget::Ident { name, .. } => {
                     // Simple variable assignment: var = value
                     // 1. Evaluate expression
-                    expressions::emit_simple_expr(value, asm, assets);
+                    expressions::emit_expr_to_d(value, asm, assets);
                     
-                    // 2. Store to variable (uppercase for consistency)
-                    asm.push_str("    LDD RESULT\n");
-                    asm.push_str(&format!("    STD VAR_{}\n", name.to_uppercase()));
+                    // 2. Store to variable (local slot or global VAR_)
+                    asm.push_str(&format!("    STD {}\n", context::var_label(name)));
                 }
                 
                 vpy_parser::AssignTarget::Index { target: array_expr, index, .. } => {
@@ -224,10 +268,14 @@ fn generate_statement(stmt: &Stmt, asm: &mut String, assets: &[AssetInfo]) -> Re
                     } else {
                         return Err("Complex array expressions not yet supported in assignment".to_string());
                     };
+
+                    if !context::legacy_expressions() {
+                        lowering::store_index(array_name, index, value, asm, assets);
+                        return Ok(());
+                    }
                     
                     // 1. Evaluate index first
-                    expressions::emit_simple_expr(index, asm, assets);
-                    asm.push_str("    LDD RESULT\n");
+                    expressions::emit_expr_to_d(index, asm, assets);
                     asm.push_str("    ASLB            ; Multiply index by 2 (16-bit elements)\n");
                     asm.push_str("    ROLA\n");
                     asm.push_str("    STD TMPPTR      ; Save offset temporarily\n");
@@ -263,17 +311,26 @@ fn generate_statement(stmt: &Stmt, asm: &mut String, assets: &[AssetInfo]) -> Re
             }
         }
         
+        Stmt::CompoundAssign { target, op, value, .. } if !context::legacy_expressions() => {
+            // x op= v is lowered as x = x op v (every operator is supported)
+            let vpy_parser::AssignTarget::Ident { name, source_line, col } = target else {
+                return Err("Complex assignment targets not yet supported".to_string());
+            };
+            let current = Expr::Ident(vpy_parser::IdentInfo { name: name.clone(), source_line: *source_line, col: *col });
+            let combined = Expr::Binary { op: *op, left: Box::new(current), right: Box::new(value.clone()) };
+            expressions::emit_expr_to_d(&combined, asm, assets);
+            asm.push_str(&format!("    STD {}\n", context::var_label(name)));
+        }
+
         Stmt::CompoundAssign { target, op, value, .. } => {
             // Load current value
             match target {
                 vpy_parser::AssignTarget::Ident { name, .. } => {
-                    // IMPORTANT: Name already comes uppercase from unifier
-                    asm.push_str(&format!("    LDD VAR_{}\n", name));
+                    asm.push_str(&format!("    LDD {}\n", context::var_label(name)));
                     asm.push_str("    PSHS D\n");
                     
                     // Evaluate right side
-                    expressions::emit_simple_expr(value, asm, assets);
-                    asm.push_str("    LDD RESULT\n");
+                    expressions::emit_expr_to_d(value, asm, assets);
                     
                     // Perform operation
                     match op {
@@ -282,15 +339,21 @@ fn generate_statement(stmt: &Stmt, asm: &mut String, assets: &[AssetInfo]) -> Re
                         _ => return Err(format!("Aug-assign {:?} not yet supported", op)),
                     }
                     
-                    // Store back (uppercase for consistency)
-                    asm.push_str(&format!("    STD VAR_{}\n", name.to_uppercase()));
+                    // Store back
+                    asm.push_str(&format!("    STD {}\n", context::var_label(name)));
                 }
                 _ => return Err("Complex assignment targets not yet supported".to_string()),
             }
         }
         
+        Stmt::Let { name, value, .. } => {
+            // Function-local variable: always gets a frame slot (see locals.rs)
+            expressions::emit_expr_to_d(value, asm, assets);
+            asm.push_str(&format!("    STD {}\n", context::var_label(name)));
+        }
+        
         Stmt::Expr(expr, ..) => {
-            expressions::emit_simple_expr(expr, asm, assets);
+            expressions::emit_discard(expr, asm, assets);
         }
         
         Stmt::If { cond, body, elifs, else_body, .. } => {
@@ -298,15 +361,13 @@ fn generate_statement(stmt: &Stmt, asm: &mut String, assets: &[AssetInfo]) -> Re
             let end = fresh_label("IF_END");
             let mut next = fresh_label("IF_NEXT");
             let simple_if = elifs.is_empty() && else_body.is_none();
-            expressions::emit_simple_expr(cond, asm, assets);
-            asm.push_str(&format!("    LDD RESULT\n    LBEQ {}\n", next));
+            expressions::emit_branch_if_false(cond, &next, asm, assets);
             for s in body { generate_statement(s, asm, assets)?; }
             asm.push_str(&format!("    LBRA {}\n", end));
             for (i, (c, b)) in elifs.iter().enumerate() {
                 asm.push_str(&format!("{}:\n", next));
                 let new_next = if i == elifs.len() - 1 && else_body.is_none() { end.clone() } else { fresh_label("IF_NEXT") };
-                expressions::emit_simple_expr(c, asm, assets);
-                asm.push_str(&format!("    LDD RESULT\n    LBEQ {}\n", new_next));
+                expressions::emit_branch_if_false(c, &new_next, asm, assets);
                 for s in b { generate_statement(s, asm, assets)?; }
                 asm.push_str(&format!("    LBRA {}\n", end));
                 next = new_next;
@@ -327,12 +388,72 @@ fn generate_statement(stmt: &Stmt, asm: &mut String, assets: &[AssetInfo]) -> Re
             let ls = fresh_label("WH");
             let le = fresh_label("WH_END");
             asm.push_str(&format!("{}: ; while start\n", ls));
-            expressions::emit_simple_expr(cond, asm, assets);
-            asm.push_str(&format!("    LDD RESULT\n    LBEQ {}\n", le));
+            expressions::emit_branch_if_false(cond, &le, asm, assets);
             for s in body { generate_statement(s, asm, assets)?; }
             asm.push_str(&format!("    LBRA {}\n{}: ; while end\n", ls, le));
         }
         
+        Stmt::Switch { expr, cases, default, .. } => {
+            // Cases do not fall through: each body ends jumping to SW_END
+            let end = fresh_label("SW_END");
+            let labels: Vec<String> = cases.iter().map(|_| fresh_label("SW_CASE")).collect();
+            let default_label = default.as_ref().map(|_| fresh_label("SW_DEFAULT"));
+            let values: Option<Vec<i32>> = cases.iter().map(|(value, _)| lowering::constant(value)).collect();
+            expressions::emit_expr_to_d(expr, asm, assets);
+            match &values {
+                // Enum variants and numbers: compare D against immediates
+                Some(values) => {
+                    for (value, label) in values.iter().zip(&labels) {
+                        asm.push_str(&format!("    CMPD #{}\n    LBEQ {}\n", value, label));
+                    }
+                }
+                // Computed cases: keep the switch value on the stack while they are evaluated
+                None => {
+                    asm.push_str("    PSHS D\n");
+                    for ((value, _), label) in cases.iter().zip(&labels) {
+                        expressions::emit_expr_to_d(value, asm, assets);
+                        asm.push_str(&format!("    CMPD ,S\n    LBEQ {}\n", label));
+                    }
+                    asm.push_str("    LEAS 2,S\n");
+                }
+            }
+            asm.push_str(&format!("    LBRA {}\n", default_label.as_ref().unwrap_or(&end)));
+            for ((_, body), label) in cases.iter().zip(&labels) {
+                asm.push_str(&format!("{}:\n", label));
+                if values.is_none() {
+                    asm.push_str("    LEAS 2,S\n");
+                }
+                for s in body { generate_statement(s, asm, assets)?; }
+                asm.push_str(&format!("    LBRA {}\n", end));
+            }
+            if let (Some(label), Some(body)) = (&default_label, default) {
+                asm.push_str(&format!("{}:\n", label));
+                for s in body { generate_statement(s, asm, assets)?; }
+            }
+            asm.push_str(&format!("{}:\n", end));
+        }
+
+        Stmt::Assert { cond, source_line } => {
+            test_harness::emit_assert(cond, *source_line, asm, assets);
+        }
+        
+        Stmt::Yield { source_line } => {
+            let co = current_coroutine().ok_or("yield outside a coroutine")?;
+            let k = context::next_yield();
+            if k > coroutines::MAX_YIELDS {
+                return Err(format!(
+                    "line {}: coroutine '{}' has more than {} yields; split it into several coroutines",
+                    source_line, co.function.to_lowercase(), coroutines::MAX_YIELDS
+                ));
+            }
+            coroutines::emit_yield(&co, k, asm);
+        }
+        
+        // A coroutine that returns is finished
+        Stmt::Return(None, ..) if current_coroutine().is_some() => {
+            coroutines::emit_return(&current_coroutine().unwrap(), asm);
+        }
+        
         Stmt::Return(expr, ..) => {
             if let Some(e) = expr {
                 expressions::emit_simple_expr(e, asm, assets);
@@ -418,11 +539,15 @@ pub fn generate_functions_by_bank(
     }
     
     joystick::emit_joystick_init(&mut bank0_asm);
+    coroutines::emit_state_init(&mut bank0_asm);
     
     if let Some(main) = main_fn {
         bank0_asm.push_str("    ; Call main() for initialization\n");
         generate_function_body(main, &mut bank0_asm, assets)?;
     }
+    if test_harness::is_test_harness() {
+        test_harness::emit_test_passed(&mut bank0_asm);
+    }
     
     bank0_asm.push_str("\n.MAIN_LOOP:\n");
     bank0_asm.push_str("    JSR LOOP_BODY\n");
@@ -475,10 +600,7 @@ pub fn generate_functions_by_bank(
         asm.push_str(&format!("{}:\n", func.name));
         generate_function_body(func, asm, assets)?;
         
-        let has_explicit_return = func.body.last()
-            .map(|stmt| matches!(stmt, Stmt::Return(..)))
-            .unwrap_or(false);
-        if !has_explicit_return {
+        if !ends_with_return(func) {
             asm.push_str("    RTS\n");
         }
         asm.push_str("\n");
@@ -34,6 +34,12 @@ pub fn generate_ram_and_arrays(module: &Module) -> Result<String, String> {
     // Create RamLayout for RAM variable allocation
     let mut ram = RamLayout::new(0xC880); // Start at $C880 (Vectrex RAM: $C800-$CBFF)
     
+    // Test mailbox comes first so its address is fixed (see test_harness.rs)
+    if super::test_harness::needs_mailbox(module) {
+        ram.allocate("TEST_STATUS", 1, "Test mailbox: 0=running 1=passed 2=failed");
+        ram.allocate("TEST_LINE", 2, "Test mailbox: source line of failed assert");
+    }
+    
     // Core scratch variables (always needed)
     ram.allocate("RESULT", 2, "Main result temporary");
     ram.allocate("TMPPTR", 2, "Temporary pointer");
@@ -115,8 +121,19 @@ pub fn generate_ram_and_arrays(module: &Module) -> Result<String, String> {
         ram.allocate("PSG_IS_PLAYING", 1, "PSG playing flag");
         ram.allocate("PSG_DELAY_FRAMES", 1, "PSG frame delay counter");
         ram.allocate("PSG_MUSIC_BANK", 1, "PSG music bank ID (for multibank)");
-        ram.allocate("SFX_PTR", 2, "SFX data pointer");
-        ram.allocate("SFX_ACTIVE", 1, "SFX active flag");
+        ram.allocate("PSG_MUSIC_PACKED", 1, "PSG music stream is packed");
+        ram.allocate("PSG_MUSIC_REF_LEFT", 1, "Packed music: tokens left in a back-reference");
+        ram.allocate("PSG_MUSIC_REF_RET", 2, "Packed music: return address of a back-reference");
+        ram.allocate("SFX_ACTIVE", 1, "Channels held by sound effects (bit n = channel n)");
+        ram.allocate("SFX_ENV_MODE", 1, "$10 while a sound effect drives the envelope");
+        ram.allocate("SFX_MIX_MASK", 1, "Mixer bits of the channels held by sound effects");
+        ram.allocate("SFX_NOISE_USED", 1, "Non-zero once a playing sound effect set the noise period");
+        ram.allocate("SFX_REQ_PRIO", 1, "PLAY_SFX priority argument");
+        ram.allocate("SFX_REQ_CHAN", 1, "PLAY_SFX channel argument ($FF = any slot)");
+        ram.allocate("SFX_CUR", 2, "SFX slot being played");
+        ram.allocate("SFX_CUR_PARAMS", 2, "PSG registers of the SFX slot being played");
+        ram.allocate("SFX_SLOTS", 4 * sfx_slot_count(module), "Per SFX slot: data pointer, priority, volume mode");
+        ram.allocate("PSG_MUSIC_SHADOW", 14, "Last music value per PSG register (restored after SFX)");
     }
     
     // Function argument slots (used by PRINT_TEXT, etc.) - at fixed address $CFE0
@@ -146,6 +163,11 @@ pub fn generate_ram_and_arrays(module: &Module) -> Result<String, String> {
     asm.push_str("; === RAM VARIABLE DEFINITIONS ===\n");
     asm.push_str(";***************************************************************************\n");
     asm.push_str(&ram.emit_equ_definitions());
+    super::context::with_locals(|plan| {
+        if plan.size > 0 {
+            asm.push_str(&plan.report());
+        }
+    });
     asm.push_str("\n");
     
     // CRITICAL FIX (2026-01-18): Emit array data BEFORE code
@@ -167,7 +189,14 @@ pub fn generate_ram_and_arrays(module: &Module) -> Result<String, String> {
 fn analyze_stmt_for_helpers(stmt: &Stmt, needed: &mut HashSet<String>) {
     match stmt {
         Stmt::Expr(expr, _) => analyze_expr_for_helpers(expr, needed),
-        Stmt::Assign { value, .. } => analyze_expr_for_helpers(value, needed),
+        Stmt::Assign { value, .. } | Stmt::Let { value, .. } => analyze_expr_for_helpers(value, needed),
+        Stmt::CompoundAssign { op, value, .. } => {
+            // Lowered as `x = x op value`
+            let target = Expr::Number(0);
+            let combined = Expr::Binary { op: *op, left: Box::new(target), right: Box::new(value.clone()) };
+            analyze_expr_for_helpers(&combined, needed);
+        }
+        Stmt::Assert { cond, .. } => analyze_expr_for_helpers(cond, needed),
         Stmt::If { cond, body, elifs, else_body, .. } => {
             analyze_expr_for_helpers(cond, needed);
             for s in body {
@@ -191,6 +220,20 @@ fn analyze_stmt_for_helpers(stmt: &Stmt, needed: &mut HashSet<String>) {
                 analyze_stmt_for_helpers(s, needed);
             }
         }
+        Stmt::Switch { expr, cases, default, .. } => {
+            analyze_expr_for_helpers(expr, needed);
+            for (case_expr, case_body) in cases {
+                analyze_expr_for_helpers(case_expr, needed);
+                for s in case_body {
+                    analyze_stmt_for_helpers(s, needed);
+                }
+            }
+            if let Some(default_body) = default {
+                for s in default_body {
+                    analyze_stmt_for_helpers(s, needed);
+                }
+            }
+        }
         Stmt::Return(Some(expr), _) => analyze_expr_for_helpers(expr, needed),
         _ => {}
     }
@@ -286,17 +329,12 @@ fn analyze_expr_for_helpers(expr: &Expr, needed: &mut HashSet<String>) {
         
         // Binary operations that may need math helpers
         Expr::Binary { left, op, right } => {
-            // Check if operands are variables (not constants)
-            let left_is_const = matches!(**left, Expr::Number(_));
-            let right_is_const = matches!(**right, Expr::Number(_));
-            
-            if !left_is_const || !right_is_const {
-                match op {
-                    BinOp::Mul => { needed.insert("MUL16".to_string()); }
-                    BinOp::Div | BinOp::FloorDiv => { needed.insert("DIV16".to_string()); }
-                    BinOp::Mod => { needed.insert("MOD16".to_string()); }
-                    _ => {}
-                }
+            // Conservative: the lowering replaces some constant products with shifts
+            match op {
+                BinOp::Mul => { needed.insert("MUL16".to_string()); }
+                BinOp::Div | BinOp::FloorDiv => { needed.insert("DIV16".to_string()); }
+                BinOp::Mod => { needed.insert("MOD16".to_string()); }
+                _ => {}
             }
             
             analyze_expr_for_helpers(left, needed);
@@ -473,12 +511,17 @@ pub fn generate_helpers(module: &Module) -> Result<String, String> {
     // AUDIO_UPDATE: Auto-inject if PLAY_MUSIC or PLAY_SFX detected
     if has_audio_calls(module) {
         emit_audio_update_helper(&mut asm);
-        emit_play_sfx_runtime(&mut asm);
+        emit_play_sfx_runtime(&mut asm, sfx_slot_count(module));
     }
 
     Ok(asm)
 }
 
+/// Channels the SFX mixer may take from the music (`META SFX_SLOTS`)
+fn sfx_slot_count(module: &Module) -> usize {
+    module.meta.sfx_slots.map_or(1, |n| n.clamp(1, 3) as usize)
+}
+
 /// Emit PLAY_MUSIC_RUNTIME and STOP_MUSIC_RUNTIME helpers
 /// Called when PLAY_MUSIC() builtin is used in code
 fn emit_play_music_runtime(asm: &mut String) {
@@ -507,18 +550,27 @@ fn emit_play_music_runtime(asm: &mut String) {
         \n\
         ; RAM variables (defined in SYSTEM RAM VARIABLES section):\n\
         ; PSG_MUSIC_PTR, PSG_MUSIC_START, PSG_IS_PLAYING,\n\
-        ; PSG_MUSIC_ACTIVE, PSG_DELAY_FRAMES\n\
+        ; PSG_MUSIC_ACTIVE, PSG_DELAY_FRAMES, PSG_MUSIC_PACKED,\n\
+        ; PSG_MUSIC_REF_LEFT, PSG_MUSIC_REF_RET\n\
         \n\
         ; PLAY_MUSIC_RUNTIME - Start PSG music playback\n\
-        ; Input: X = pointer to PSG music data\n\
+        ; Input: X = pointer to PSG music data ($FE first = packed stream)\n\
         PLAY_MUSIC_RUNTIME:\n\
         CMPX >PSG_MUSIC_START   ; Check if already playing this music\n\
         BNE PMr_start_new       ; If different, start fresh\n\
         LDA >PSG_IS_PLAYING     ; Check if currently playing\n\
         BNE PMr_done            ; If playing same song, ignore\n\
 PMr_start_new:\n\
-        STX >PSG_MUSIC_PTR      ; Store current music pointer (force extended)\n\
         STX >PSG_MUSIC_START    ; Store start pointer for loops (force extended)\n\
+        CLR >PSG_MUSIC_PACKED\n\
+        CLR >PSG_MUSIC_REF_LEFT\n\
+        LDA ,X\n\
+        CMPA #$FE               ; Packed stream header?\n\
+        BNE PMr_plain\n\
+        INC >PSG_MUSIC_PACKED\n\
+        LEAX 1,X                ; First token\n\
+PMr_plain:\n\
+        STX >PSG_MUSIC_PTR      ; Store current music pointer (force extended)\n\
         CLR >PSG_DELAY_FRAMES   ; Clear delay counter\n\
         LDA #$01\n\
         STA >PSG_IS_PLAYING     ; Mark as playing (extended - var at 0xC8A0)\n\
@@ -626,7 +678,11 @@ fn emit_audio_update_helper(asm: &mut String) {
         ; Sets DP=$D0 once at entry, restores at exit\n\
         ; RAM variables: PSG_MUSIC_PTR, PSG_IS_PLAYING, PSG_DELAY_FRAMES\n\
         ;                PSG_MUSIC_BANK (for multibank: bank ID where music data lives)\n\
-        ;                SFX_PTR, SFX_ACTIVE (defined in SYSTEM RAM VARIABLES)\n\
+        ;                SFX_ACTIVE, SFX_ENV_MODE, SFX_MIX_MASK, SFX_NOISE_USED, PSG_MUSIC_SHADOW\n\
+        ;                (defined in SYSTEM RAM VARIABLES)\n\
+        ; A sound effect owns its channel's tone, volume and mixer bits, the noise\n\
+        ; period, and R11-R13 if it uses the envelope: the music only records\n\
+        ; those writes in PSG_MUSIC_SHADOW, and sfx_endofeffect restores them\n\
         \n\
         AUDIO_UPDATE:\n\
         PSHS DP                 ; Save current DP\n\
@@ -646,6 +702,8 @@ fn emit_audio_update_helper(asm: &mut String) {
         ; UPDATE MUSIC (channel B: registers 9, 11-14)\n\
         LDA >PSG_IS_PLAYING     ; Check if music is playing\n\
         BEQ AU_SKIP_MUSIC       ; Skip if not\n\
+        TST >PSG_MUSIC_PACKED\n\
+        BNE AU_MUSIC_PACKED     ; Packed stream has its own decoder\n\
         \n\
         ; Check delay counter first\n\
         LDA >PSG_DELAY_FRAMES   ; Load delay counter\n\
@@ -698,7 +756,7 @@ fn emit_audio_update_helper(asm: &mut String) {
         LDA ,X+                 ; Load register number\n\
         LDB ,X+                 ; Load register value\n\
         PSHS X                  ; Save pointer\n\
-        JSR Sound_Byte          ; Write to PSG using BIOS (DP=$D0)\n\
+        JSR AU_MUSIC_WRITE_REG  ; Write unless the SFX owns the register\n\
         PULS X                  ; Restore pointer\n\
         PULS B                  ; Get counter\n\
         DECB                    ; Decrement\n\
@@ -720,6 +778,8 @@ fn emit_audio_update_helper(asm: &mut String) {
         CLR >PSG_DELAY_FRAMES   ; Clear delay on loop\n\
         BRA AU_UPDATE_SFX       ; Continue to SFX\n\
         \n\
+        AU_MUSIC_PACKED:\n\
+        JSR AU_PACKED_FRAME\n\
         AU_SKIP_MUSIC:\n\
         BRA AU_UPDATE_SFX       ; Skip music, go to SFX\n\
         \n\
@@ -728,7 +788,7 @@ fn emit_audio_update_helper(asm: &mut String) {
         LDA >SFX_ACTIVE         ; Check if SFX is active\n\
         BEQ AU_DONE             ; Skip if not active\n\
         \n\
-        JSR sfx_doframe         ; Process one SFX frame (uses Sound_Byte internally)\n\
+        JSR SFX_UPDATE          ; One frame of every playing slot (uses Sound_Byte)\n\
         \n\
         AU_DONE:\n\
         ; MULTIBANK: Restore original bank\n\
@@ -737,8 +797,58 @@ fn emit_audio_update_helper(asm: &mut String) {
         STA $DF00               ; Restore bank hardware register\n\
         PULS DP                 ; Restore original DP\n\
         RTS\n\
+        \n\
+        ; AU_MUSIC_WRITE_REG - A=register, B=value. Records the music's value;\n\
+        ; leaves the channels held by sound effects (and their envelope) alone\n\
+        AU_MUSIC_WRITE_REG:\n\
+        LDX #PSG_MUSIC_SHADOW\n\
+        STB A,X                 ; Remember the music's value\n\
+        TST >SFX_ACTIVE\n\
+        BEQ AU_MUSIC_WRITE      ; No SFX: the music owns every channel\n\
+        CMPA #$07\n\
+        BNE AU_MUSIC_CHECK_CH\n\
+        PSHS B                  ; Mixer: the music's bits for its channels,\n\
+        LDB >SFX_MIX_MASK       ; the effects' bits as they are\n\
+        COMB\n\
+        ANDB ,S\n\
+        STB ,S\n\
+        LDB >SFX_MIX_MASK\n\
+        ANDB $C807              ; Mixer shadow\n\
+        ORB ,S+\n\
+        BRA AU_MUSIC_WRITE\n\
+        AU_MUSIC_CHECK_CH:\n\
+        CMPA #$06\n\
+        BEQ AU_MUSIC_CHECK_NOISE\n\
+        BHI AU_MUSIC_CHECK_VOL\n\
+        PSHS A\n\
+        LSRA                    ; Tone: channel = register / 2\n\
+        BRA AU_MUSIC_OWNED\n\
+        AU_MUSIC_CHECK_VOL:\n\
+        CMPA #$0A\n\
+        BHI AU_MUSIC_CHECK_ENV\n\
+        PSHS A\n\
+        SUBA #$08               ; Volume: channel = register - 8\n\
+        AU_MUSIC_OWNED:\n\
+        LDX #sfx_chan_bits\n\
+        LDA A,X\n\
+        ANDA >SFX_ACTIVE\n\
+        PULS A                  ; (flags kept)\n\
+        BNE AU_MUSIC_SKIP       ; Channel held by an effect\n\
+        BRA AU_MUSIC_WRITE\n\
+        AU_MUSIC_CHECK_NOISE:\n\
+        TST >SFX_NOISE_USED\n\
+        BNE AU_MUSIC_SKIP       ; Noise period, while an effect sets it\n\
+        BRA AU_MUSIC_WRITE\n\
+        AU_MUSIC_CHECK_ENV:\n\
+        TST >SFX_ENV_MODE\n\
+        BNE AU_MUSIC_SKIP       ; Envelope, while an effect uses it\n\
+        AU_MUSIC_WRITE:\n\
+        JMP Sound_Byte          ; Write to PSG using BIOS (DP=$D0)\n\
+        AU_MUSIC_SKIP:\n\
+        RTS\n\
         \n"
     );
+    asm.push_str(crate::music_pack::PLAYER_ASM);
 }
 
 // emit_draw_sync_list_at_with_mirrors - Vector drawing with mirror support
@@ -915,57 +1025,177 @@ DSWM_NEXT_NO_NEGATE_X:\n\
     );
 }
 
-/// Emit PLAY_SFX_RUNTIME and sfx_doframe helpers
-/// AYFX sound effects player (Richard Chadd system - 1 channel, channel C)
-fn emit_play_sfx_runtime(asm: &mut String) {
-    asm.push_str(
+/// Emit PLAY_SFX_RUNTIME, SFX_UPDATE and sfx_doframe helpers
+/// AYFX sound effects player (Richard Chadd system), one effect per slot:
+/// slot 0 plays on channel C, slot 1 on B, slot 2 on A
+fn emit_play_sfx_runtime(asm: &mut String, slots: usize) {
+    let env_owner: String = (0..slots)
+        .map(|i| format!("            ORA >SFX_SLOTS+{}\n", 4 * i + 3))
+        .collect();
+    asm.push_str(&format!(
         "; ============================================================================\n\
         ; AYFX SOUND EFFECTS PLAYER (Richard Chadd original system)\n\
         ; ============================================================================\n\
-        ; Uses channel C (registers 4/5=tone, 6=noise, 10=volume, 7=mixer bit2/bit5)\n\
-        ; RAM variables: SFX_PTR (16-bit), SFX_ACTIVE (8-bit)\n\
+        ; {slots} slot(s): slot 0 plays on channel C, slot 1 on B, slot 2 on A. While\n\
+        ; it plays a slot owns its channel (tone, volume, mixer bits) and the noise\n\
+        ; period; the music keeps the other channels and gets it back at the end\n\
+        ; RAM variables: SFX_SLOTS (4 bytes per slot: data pointer, priority,\n\
+        ;            volume mode), SFX_ACTIVE, SFX_MIX_MASK, SFX_ENV_MODE, SFX_NOISE_USED,\n\
+        ;            SFX_REQ_PRIO, SFX_REQ_CHAN, SFX_CUR, SFX_CUR_PARAMS\n\
         ; AYFX format: flag byte + optional data per frame, end marker $D0 $20\n\
         ; Flag bits: 0-3=volume, 4=disable tone, 5=tone data present,\n\
         ;            6=noise data present, 7=disable noise\n\
+        ; $D0 $21 shape, period (FDB): hardware envelope; from then on the\n\
+        ;            slot's volume follows it. Takes no frame of its own\n\
         ; ============================================================================\n\
         \n\
+        SFX_SLOT_COUNT EQU {slots}\n\
+        \n\
         ; PLAY_SFX_RUNTIME - Start SFX playback\n\
-        ; Input: X = pointer to AYFX data\n\
+        ; Input: X = pointer to AYFX data, SFX_REQ_PRIO = priority,\n\
+        ;        SFX_REQ_CHAN = channel 0-2 ($FF or a channel without a slot: any)\n\
+        ; A playing effect is only replaced by one of equal or higher priority;\n\
+        ; with no slot free, the lowest-priority effect loses its slot\n\
         PLAY_SFX_RUNTIME:\n\
-            STX >SFX_PTR           ; Store pointer (force extended addressing)\n\
-            LDA #$01\n\
-            STA >SFX_ACTIVE        ; Mark as active\n\
+            PSHS X\n\
+            LDB >SFX_REQ_CHAN\n\
+            CMPB #$02\n\
+            BHI PSR_AUTO           ; Any slot\n\
+            NEGB\n\
+            ADDB #$02              ; Slot = 2 - channel\n\
+            CMPB #SFX_SLOT_COUNT\n\
+            BHS PSR_AUTO           ; No slot plays on that channel\n\
+            BSR sfx_slot_select\n\
+            LDA 2,Y\n\
+            ANDA >SFX_ACTIVE\n\
+            BEQ PSR_START          ; Free\n\
+            LDA 2,X\n\
+            CMPA >SFX_REQ_PRIO\n\
+            BHI PSR_REJECT         ; Playing something more important\n\
+            BRA PSR_START\n\
+        PSR_AUTO:\n\
+            CLRB\n\
+        PSR_FREE:\n\
+            BSR sfx_slot_select\n\
+            LDA 2,Y\n\
+            ANDA >SFX_ACTIVE\n\
+            BEQ PSR_START          ; First free slot\n\
+            INCB\n\
+            CMPB #SFX_SLOT_COUNT\n\
+            BLO PSR_FREE\n\
+            CLRB                   ; All busy: find the lowest priority\n\
+            BSR sfx_slot_select\n\
+            LDA 2,X\n\
+            PSHS A,B               ; ,S = lowest priority, 1,S = its slot\n\
+        PSR_LOWEST:\n\
+            INCB\n\
+            CMPB #SFX_SLOT_COUNT\n\
+            BHS PSR_STEAL\n\
+            BSR sfx_slot_select\n\
+            LDA 2,X\n\
+            CMPA ,S\n\
+            BHS PSR_LOWEST         ; Ties keep the earlier slot\n\
+            STA ,S\n\
+            STB 1,S\n\
+            BRA PSR_LOWEST\n\
+        PSR_STEAL:\n\
+            PULS A,B\n\
+            CMPA >SFX_REQ_PRIO\n\
+            BHI PSR_REJECT\n\
+            BSR sfx_slot_select\n\
+        PSR_START:\n\
+            PULS D\n\
+            STD ,X                 ; Data pointer\n\
+            LDA >SFX_REQ_PRIO\n\
+            STA 2,X\n\
+            CLR 3,X                ; Software volume unless the effect selects the envelope\n\
+            LDA >SFX_ACTIVE\n\
+            ORA 2,Y                ; Channel now held by the effect\n\
+            STA >SFX_ACTIVE\n\
+            LDA 2,Y\n\
+            ORA 3,Y\n\
+            ORA >SFX_MIX_MASK\n\
+            STA >SFX_MIX_MASK\n\
+            BRA sfx_env_owner      ; The replaced effect may have held the envelope\n\
+        PSR_REJECT:\n\
+            PULS X,PC\n\
+        \n\
+        ; sfx_slot_select - B = slot: X = its SFX_SLOTS entry, Y = its registers\n\
+        ; (sfx_slot_params: tone register, volume register, tone bit, noise bit)\n\
+        sfx_slot_select:\n\
+            PSHS B\n\
+            ASLB\n\
+            ASLB\n\
+            LDX #SFX_SLOTS\n\
+            ABX\n\
+            LDY #sfx_slot_params\n\
+            LEAY B,Y\n\
+            PULS B,PC\n\
+        \n\
+        ; sfx_env_owner - SFX_ENV_MODE = $10 while any slot uses the envelope\n\
+        sfx_env_owner:\n\
+            CLRA\n\
+        {env_owner}\
+            STA >SFX_ENV_MODE\n\
             RTS\n\
         \n\
-        ; SFX_UPDATE - Process one AYFX frame (call once per frame in loop)\n\
+        sfx_slot_params:\n\
+            FCB $04,$0A,$04,$20    ; Slot 0: channel C\n\
+            FCB $02,$09,$02,$10    ; Slot 1: channel B\n\
+            FCB $00,$08,$01,$08    ; Slot 2: channel A\n\
+        sfx_chan_bits:\n\
+            FCB $01,$02,$04\n\
+        \n\
+        ; SFX_UPDATE - Process one AYFX frame of every playing slot (call once per frame)\n\
         SFX_UPDATE:\n\
-            LDA >SFX_ACTIVE        ; Check if active\n\
-            BEQ noay               ; Not active, skip\n\
+            LDA >SFX_ACTIVE        ; Check if any slot plays\n\
+            BEQ noay               ; No, skip\n\
+            LDX #SFX_SLOTS\n\
+            LDY #sfx_slot_params\n\
+        SFX_UPDATE_SLOT:\n\
+            LDA 2,Y\n\
+            ANDA >SFX_ACTIVE\n\
+            BEQ SFX_UPDATE_NEXT\n\
+            STX >SFX_CUR\n\
+            STY >SFX_CUR_PARAMS\n\
             JSR sfx_doframe        ; Process one frame\n\
+            LDX >SFX_CUR\n\
+            LDY >SFX_CUR_PARAMS\n\
+        SFX_UPDATE_NEXT:\n\
+            LEAX 4,X\n\
+            LEAY 4,Y\n\
+            CMPY #sfx_slot_params+{params_end}\n\
+            BNE SFX_UPDATE_SLOT\n\
         noay:\n\
             RTS\n\
         \n\
-        ; sfx_doframe - AYFX frame parser (Richard Chadd original)\n\
+        ; sfx_doframe - AYFX frame parser (Richard Chadd original) for the slot\n\
+        ; at SFX_CUR, playing on the registers at SFX_CUR_PARAMS\n\
         sfx_doframe:\n\
-            LDU >SFX_PTR           ; Get current frame pointer\n\
+            LDX >SFX_CUR\n\
+            LDU ,X                 ; Get current frame pointer\n\
             LDB ,U                 ; Read flag byte (NO auto-increment)\n\
             CMPB #$D0              ; Check end marker (first byte)\n\
             BNE sfx_checktonefreq  ; Not end, continue\n\
             LDB 1,U                ; Check second byte at offset 1\n\
             CMPB #$20              ; End marker $D0 $20?\n\
-            BEQ sfx_endofeffect    ; Yes, stop\n\
+            LBEQ sfx_endofeffect   ; Yes, stop\n\
+            CMPB #$21              ; Hardware envelope $D0 $21?\n\
+            LBEQ sfx_envelope\n\
         \n\
         sfx_checktonefreq:\n\
             LEAY 1,U               ; Y = pointer to tone/noise data\n\
             LDB ,U                 ; Reload flag byte (Sound_Byte corrupts B)\n\
             BITB #$20              ; Bit 5: tone data present?\n\
             BEQ sfx_checknoisefreq ; No, skip tone\n\
-            ; Set tone frequency (channel C = reg 4/5)\n\
+            LDX >SFX_CUR_PARAMS\n\
+            LDA ,X                 ; Tone register (fine tune)\n\
             LDB 2,U                ; Get LOW byte (fine tune)\n\
-            LDA #$04               ; Register 4\n\
             JSR Sound_Byte         ; Write to PSG\n\
+            LDX >SFX_CUR_PARAMS\n\
+            LDA ,X\n\
+            INCA                   ; Coarse tune register\n\
             LDB 1,U                ; Get HIGH byte (coarse tune)\n\
-            LDA #$05               ; Register 5\n\
             JSR Sound_Byte         ; Write to PSG\n\
             LEAY 2,Y               ; Skip 2 tone bytes\n\
         \n\
@@ -975,63 +1205,145 @@ fn emit_play_sfx_runtime(asm: &mut String) {
             BEQ sfx_checkvolume    ; No, skip noise\n\
             LDB ,Y                 ; Get noise period\n\
             LDA #$06               ; Register 6\n\
+            STA >SFX_NOISE_USED    ; The music's noise period waits for the effects\n\
             JSR Sound_Byte         ; Write to PSG\n\
             LEAY 1,Y               ; Skip 1 noise byte\n\
         \n\
         sfx_checkvolume:\n\
             LDB ,U                 ; Reload flag byte\n\
             ANDB #$0F              ; Get volume from bits 0-3\n\
-            LDA #$0A               ; Register 10 (volume C)\n\
+            LDX >SFX_CUR\n\
+            ORB 3,X                ; $10: volume follows the envelope\n\
+            LDX >SFX_CUR_PARAMS\n\
+            LDA 1,X                ; Volume register\n\
             JSR Sound_Byte         ; Write to PSG\n\
         \n\
-        sfx_checktonedisable:\n\
-            LDB ,U                 ; Reload flag byte\n\
-            BITB #$10              ; Bit 4: disable tone?\n\
-            BEQ sfx_enabletone\n\
-        sfx_disabletone:\n\
+        sfx_checkmixer:\n\
+            LDX >SFX_CUR_PARAMS\n\
             LDB $C807              ; Read mixer shadow (MUST be B register)\n\
-            ORB #$04               ; Set bit 2 (disable tone C)\n\
-            LDA #$07               ; Register 7 (mixer)\n\
-            JSR Sound_Byte         ; Write to PSG\n\
-            BRA sfx_checknoisedisable  ; Continue to noise check\n\
-        \n\
-        sfx_enabletone:\n\
-            LDB $C807              ; Read mixer shadow (MUST be B register)\n\
-            ANDB #$FB              ; Clear bit 2 (enable tone C)\n\
-            LDA #$07               ; Register 7 (mixer)\n\
-            JSR Sound_Byte         ; Write to PSG\n\
-        \n\
+            ORB 2,X                ; Tone off...\n\
+            LDA ,U\n\
+            BITA #$10              ; Bit 4: disable tone?\n\
+            BNE sfx_checknoisedisable\n\
+            EORB 2,X               ; ...unless the frame wants it\n\
         sfx_checknoisedisable:\n\
-            LDB ,U                 ; Reload flag byte\n\
-            BITB #$80              ; Bit 7: disable noise?\n\
-            BEQ sfx_enablenoise\n\
-        sfx_disablenoise:\n\
-            LDB $C807              ; Read mixer shadow (MUST be B register)\n\
-            ORB #$20               ; Set bit 5 (disable noise C)\n\
-            LDA #$07               ; Register 7 (mixer)\n\
-            JSR Sound_Byte         ; Write to PSG\n\
-            BRA sfx_nextframe      ; Done, update pointer\n\
-        \n\
-        sfx_enablenoise:\n\
-            LDB $C807              ; Read mixer shadow (MUST be B register)\n\
-            ANDB #$DF              ; Clear bit 5 (enable noise C)\n\
+            ORB 3,X                ; Noise off...\n\
+            BITA #$80              ; Bit 7: disable noise?\n\
+            BNE sfx_writemixer\n\
+            EORB 3,X               ; ...unless the frame wants it\n\
+        sfx_writemixer:\n\
             LDA #$07               ; Register 7 (mixer)\n\
             JSR Sound_Byte         ; Write to PSG\n\
         \n\
         sfx_nextframe:\n\
-            STY >SFX_PTR            ; Update pointer for next frame\n\
+            LDX >SFX_CUR\n\
+            STY ,X                 ; Update pointer for next frame\n\
             RTS\n\
         \n\
+        sfx_envelope:\n\
+            LDB 4,U                ; Period low\n\
+            LDA #$0B               ; Register 11\n\
+            JSR Sound_Byte\n\
+            LDB 3,U                ; Period high\n\
+            LDA #$0C               ; Register 12\n\
+            JSR Sound_Byte\n\
+            LDB 2,U                ; Shape (writing it restarts the envelope)\n\
+            LDA #$0D               ; Register 13\n\
+            JSR Sound_Byte\n\
+            LDA #$10\n\
+            STA >SFX_ENV_MODE      ; The slot's volume follows the envelope\n\
+            LDX >SFX_CUR\n\
+            STA 3,X\n\
+            LEAU 5,U\n\
+            STU ,X\n\
+            LBRA sfx_doframe       ; Play this frame's data\n\
+        \n\
         sfx_endofeffect:\n\
+            LDX >SFX_CUR_PARAMS\n\
+            LDA 2,X\n\
+            COMA\n\
+            ANDA >SFX_ACTIVE\n\
+            STA >SFX_ACTIVE        ; Channel back to the music\n\
+            LDA 2,X\n\
+            ORA 3,X\n\
+            COMA\n\
+            ANDA >SFX_MIX_MASK\n\
+            STA >SFX_MIX_MASK\n\
+            LDX >SFX_CUR\n\
+            LDB 3,X                ; Did this effect use the envelope?\n\
+            CLR 3,X\n\
+            PSHS B\n\
+            JSR sfx_env_owner\n\
+            LDA >PSG_IS_PLAYING    ; Music running: give the channel back to it\n\
+            BNE sfx_restore_music\n\
             ; Stop SFX - set volume to 0\n\
-            CLR >SFX_ACTIVE         ; Mark as inactive\n\
-            LDA #$0A                ; Register 10 (volume C)\n\
-            LDB #$00                ; Volume = 0\n\
+            LDX >SFX_CUR_PARAMS\n\
+            LDA 1,X                ; Volume register\n\
+            CLRB                   ; Volume = 0\n\
             JSR Sound_Byte\n\
-            LDD #$0000\n\
-            STD >SFX_PTR            ; Clear pointer\n\
-            RTS\n\
-        \n"
-    );
+            TST >SFX_ACTIVE\n\
+            BNE sfx_stop_done\n\
+            CLR >SFX_NOISE_USED    ; Last effect gone\n\
+        sfx_stop_done:\n\
+            PULS B,PC\n\
+        \n\
+        sfx_restore_music:\n\
+            LDU #PSG_MUSIC_SHADOW\n\
+            LDX >SFX_CUR_PARAMS\n\
+            LDA ,X                 ; Tone, fine\n\
+            LDB A,U                ; Music's last value\n\
+            JSR Sound_Byte\n\
+            LDX >SFX_CUR_PARAMS\n\
+            LDA ,X\n\
+            INCA                   ; Tone, coarse\n\
+            LDB A,U\n\
+            JSR Sound_Byte\n\
+            LDX >SFX_CUR_PARAMS\n\
+            LDA 1,X                ; Volume\n\
+            LDB A,U\n\
+            JSR Sound_Byte\n\
+            TST >SFX_ACTIVE        ; Noise, once no effect is left\n\
+            BNE sfx_restore_envelope\n\
+            TST >SFX_NOISE_USED    ; and only if one of them changed it\n\
+            BEQ sfx_restore_envelope\n\
+            CLR >SFX_NOISE_USED\n\
+            LDA #$06\n\
+            LDB 6,U\n\
+            JSR Sound_Byte\n\
+        sfx_restore_envelope:\n\
+            PULS B\n\
+            TSTB                   ; Envelope, if this effect took it\n\
+            BEQ sfx_restore_mixer\n\
+            TST >SFX_ENV_MODE      ; and no other effect still uses it\n\
+            BNE sfx_restore_mixer\n\
+            LDA #$0B\n\
+        sfx_restore_env_loop:\n\
+            LDB A,U\n\
+            PSHS A\n\
+            JSR Sound_Byte\n\
+            PULS A\n\
+            INCA\n\
+            CMPA #$0E\n\
+            BLO sfx_restore_env_loop\n\
+        sfx_restore_mixer:\n\
+            LDX >SFX_CUR_PARAMS\n\
+            LDA 2,X\n\
+            ORA 3,X                ; This channel's mixer bits\n\
+            PSHS A\n\
+            LDB 7,U\n\
+            ANDB ,S                ; Take the music's\n\
+            COMA\n\
+            ANDA $C807             ; Keep the rest\n\
+            PSHS B\n\
+            ORA ,S+\n\
+            LEAS 1,S\n\
+            TFR A,B\n\
+            LDA #$07               ; Register 7 (mixer)\n\
+            JMP Sound_Byte\n\
+        \n",
+        slots = slots,
+        env_owner = env_owner,
+        params_end = 4 * slots,
+    ));
 }
 
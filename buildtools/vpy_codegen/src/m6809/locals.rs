@@ -0,0 +1,386 @@
+//! Function-Local Storage
+//!
+//! Parameters and locals live in static RAM slots, one 16-bit slot each.
+//! Frames are overlaid along the call graph: a function's frame starts where
+//! the frames of every chain of callers that can reach it end, so functions
+//! that are never active at the same time share the same bytes.
+//!
+//! A local is a parameter, a `let`, or a name the function assigns that is
+//! not a module-level `let`/`const`. Everything else stays a global
+//! `VAR_<NAME>`.
+//!
+//! Static frames cannot hold two activations of the same function, so a
+//! call cycle through a function with locals is rejected. Cycles whose
+//! functions only touch globals are allowed.
+//!
+//! Coroutines (see coroutines.rs) keep the locals they need across a
+//! `yield` outside the frames; only the rest is overlaid.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use vpy_bank_allocator::CallGraph;
+use vpy_parser::{AssignTarget, Item, Module, Stmt};
+use super::coroutines::{self, Coroutine};
+
+/// Bytes per local (all VPy values are 16-bit)
+pub const SLOT_SIZE: usize = 2;
+
+/// Parameters are passed in VAR_ARG0-4
+pub const MAX_PARAMS: usize = 5;
+
+/// RAM label of the overlay block
+pub const BLOCK_LABEL: &str = "LOCAL_FRAMES";
+
+/// Locals of one function and where they sit in the overlay block
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    /// Function label (uppercase)
+    pub function: String,
+    /// Offset of the frame in the overlay block
+    pub offset: usize,
+    /// Local names (uppercase) in slot order, parameters first
+    pub slots: Vec<String>,
+    /// Leading slots that are parameters, copied from VAR_ARG0-4 on entry
+    /// (coroutine parameters kept across `yield` live outside the frame)
+    pub params: usize,
+}
+
+impl Frame {
+    pub fn size(&self) -> usize {
+        self.slots.len() * SLOT_SIZE
+    }
+
+    /// Offset of a local in the overlay block
+    pub fn slot_offset(&self, name: &str) -> Option<usize> {
+        let name = name.to_uppercase();
+        self.slots.iter().position(|s| *s == name).map(|i| self.offset + i * SLOT_SIZE)
+    }
+}
+
+/// Overlay layout of every function frame
+#[derive(Debug, Clone, Default)]
+pub struct LocalsPlan {
+    /// Frames by function label, only functions with locals
+    pub frames: BTreeMap<String, Frame>,
+    /// Bytes of the overlay block
+    pub size: usize,
+    /// Bytes the frames would take without overlaying
+    pub unshared: usize,
+    /// Call chain whose frames end highest, setting `size` (outermost first);
+    /// not necessarily the chain with the most calls
+    pub heaviest_chain: Vec<String>,
+    /// Functions containing `yield`, by function label
+    pub coroutines: BTreeMap<String, Coroutine>,
+}
+
+impl LocalsPlan {
+    /// Lay out the frames of every function of `module`
+    pub fn from_module(module: &Module) -> Result<Self, String> {
+        let mut globals = HashSet::new();
+        for item in &module.items {
+            if let Item::GlobalLet { name, .. } | Item::Const { name, .. } = item {
+                globals.insert(name.to_uppercase());
+            }
+        }
+
+        let mut frames: BTreeMap<String, Frame> = BTreeMap::new();
+        let mut coroutines: BTreeMap<String, Coroutine> = BTreeMap::new();
+        for item in &module.items {
+            let Item::Function(func) = item else { continue };
+            if func.params.len() > MAX_PARAMS {
+                return Err(format!(
+                    "function '{}' has {} parameters; at most {} are supported (VAR_ARG0-{})",
+                    func.name, func.params.len(), MAX_PARAMS, MAX_PARAMS - 1
+                ));
+            }
+            let mut slots: Vec<String> = Vec::new();
+            for param in &func.params {
+                push_unique(&mut slots, param);
+            }
+            collect_locals(&func.body, &globals, &mut slots);
+            let function = func.name.to_uppercase();
+            let mut params = func.params.len();
+            if coroutines::contains_yield(&func.body) && function != "MAIN" && function != "LOOP" {
+                let co = Coroutine::from_function(func, &slots)?;
+                params -= func.params.iter().filter(|p| co.is_saved(p)).count();
+                slots.retain(|name| !co.is_saved(name));
+                coroutines.insert(function.clone(), co);
+            }
+            if !slots.is_empty() {
+                frames.insert(function.clone(), Frame { function, offset: 0, slots, params });
+            }
+        }
+        coroutines::check_module(module, &coroutines)?;
+
+        let graph = CallGraph::from_module(module);
+        let names: HashSet<String> = graph.nodes.keys().map(|n| n.to_uppercase()).collect();
+        let mut callees: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for edge in &graph.edges {
+            let (from, to) = (edge.from.to_uppercase(), edge.to.to_uppercase());
+            if names.contains(&to) {
+                let list = callees.entry(from).or_default();
+                if !list.contains(&to) {
+                    list.push(to);
+                }
+            }
+        }
+
+        // A coroutine has one state: it cannot be resumed while it runs
+        for function in coroutines.keys() {
+            if let Some(cycle) = find_cycle(function, &callees) {
+                return Err(format!(
+                    "coroutine '{}' can resume itself ({}); each coroutine has a single instance",
+                    function,
+                    cycle.join(" -> ")
+                ));
+            }
+        }
+
+        for function in frames.keys() {
+            if let Some(cycle) = find_cycle(function, &callees) {
+                return Err(format!(
+                    "recursive call chain {}: '{}' has locals, which are statically overlaid and cannot hold two activations",
+                    cycle.join(" -> "),
+                    function
+                ));
+            }
+        }
+
+        let size_of = |f: &str| frames.get(f).map_or(0, Frame::size);
+
+        // Longest path from the roots: a callee frame starts past every caller frame.
+        // Only zero-sized frames can sit on a cycle, so this settles.
+        let mut offsets: HashMap<String, usize> = names.iter().map(|n| (n.clone(), 0)).collect();
+        let mut caller_of: HashMap<String, String> = HashMap::new();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (from, tos) in &callees {
+                let end = offsets.get(from).copied().unwrap_or(0) + size_of(from);
+                for to in tos {
+                    if end > offsets[to] {
+                        offsets.insert(to.clone(), end);
+                        caller_of.insert(to.clone(), from.clone());
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        for frame in frames.values_mut() {
+            frame.offset = offsets.get(&frame.function).copied().unwrap_or(0);
+        }
+        let size = frames.values().map(|f| f.offset + f.size()).max().unwrap_or(0);
+        let unshared = frames.values().map(Frame::size).sum();
+
+        let mut heaviest_chain = Vec::new();
+        if let Some(last) = frames.values().filter(|f| f.offset + f.size() == size).map(|f| &f.function).next() {
+            let mut current = Some(last.clone());
+            while let Some(function) = current {
+                if heaviest_chain.contains(&function) {
+                    break;
+                }
+                current = caller_of.get(&function).cloned();
+                heaviest_chain.push(function);
+            }
+            heaviest_chain.reverse();
+        }
+
+        Ok(LocalsPlan { frames, size, unshared, heaviest_chain, coroutines })
+    }
+
+    pub fn frame(&self, function: &str) -> Option<&Frame> {
+        self.frames.get(&function.to_uppercase())
+    }
+
+    pub fn coroutine(&self, function: &str) -> Option<&Coroutine> {
+        self.coroutines.get(&function.to_uppercase())
+    }
+
+    /// Whether `name` is a local of `function`, in its frame or in a coroutine slot
+    pub fn is_local(&self, function: &str, name: &str) -> bool {
+        self.frame(function).is_some_and(|f| f.slot_offset(name).is_some())
+            || self.coroutine(function).is_some_and(|co| co.is_saved(name))
+    }
+
+    /// Label of a local: `LOC_<FUNCTION>__<NAME>`
+    pub fn label(function: &str, name: &str) -> String {
+        format!("LOC_{}__{}", function.to_uppercase(), name.to_uppercase())
+    }
+
+    /// Comment block for the listing: block size and the chain that sets it
+    pub fn report(&self) -> String {
+        let mut out = String::new();
+        out.push_str(&format!(
+            "; Function locals: {} bytes overlaid ({} bytes without sharing)\n",
+            self.size, self.unshared
+        ));
+        if !self.heaviest_chain.is_empty() {
+            out.push_str(&format!(
+                "; Call chain with the most locals: {} ({} bytes)\n",
+                self.heaviest_chain.join(" -> "),
+                self.size
+            ));
+        }
+        out
+    }
+}
+
+fn push_unique(slots: &mut Vec<String>, name: &str) {
+    let name = name.to_uppercase();
+    if !slots.contains(&name) {
+        slots.push(name);
+    }
+}
+
+/// Names a function body declares or assigns that are not module globals
+fn collect_locals(stmts: &[Stmt], globals: &HashSet<String>, slots: &mut Vec<String>) {
+    let local = |name: &str, slots: &mut Vec<String>| {
+        if !globals.contains(&name.to_uppercase()) {
+            push_unique(slots, name);
+        }
+    };
+    for stmt in stmts {
+        match stmt {
+            Stmt::Let { name, .. } => local(name, slots),
+            Stmt::Assign { target: AssignTarget::Ident { name, .. }, .. }
+            | Stmt::CompoundAssign { target: AssignTarget::Ident { name, .. }, .. } => local(name, slots),
+            Stmt::For { var, body, .. } | Stmt::ForIn { var, body, .. } => {
+                local(var, slots);
+                collect_locals(body, globals, slots);
+            }
+            Stmt::While { body, .. } => collect_locals(body, globals, slots),
+            Stmt::If { body, elifs, else_body, .. } => {
+                collect_locals(body, globals, slots);
+                for (_, elif_body) in elifs {
+                    collect_locals(elif_body, globals, slots);
+                }
+                if let Some(else_body) = else_body {
+                    collect_locals(else_body, globals, slots);
+                }
+            }
+            Stmt::Switch { cases, default, .. } => {
+                for (_, case_body) in cases {
+                    collect_locals(case_body, globals, slots);
+                }
+                if let Some(default) = default {
+                    collect_locals(default, globals, slots);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A call path from `start` back to itself, if any
+fn find_cycle(start: &str, callees: &BTreeMap<String, Vec<String>>) -> Option<Vec<String>> {
+    fn visit(
+        current: &str,
+        start: &str,
+        callees: &BTreeMap<String, Vec<String>>,
+        path: &mut Vec<String>,
+        seen: &mut HashSet<String>,
+    ) -> bool {
+        for next in callees.get(current).into_iter().flatten() {
+            if next == start {
+                path.push(next.clone());
+                return true;
+            }
+            if seen.insert(next.clone()) {
+                path.push(next.clone());
+                if visit(next, start, callees, path, seen) {
+                    return true;
+                }
+                path.pop();
+            }
+        }
+        false
+    }
+
+    let mut path = vec![start.to_string()];
+    let mut seen = HashSet::new();
+    visit(start, start, callees, &mut path, &mut seen).then_some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Module {
+        let tokens = vpy_parser::lex(source).unwrap();
+        vpy_parser::parser::parse(tokens, "test.vpy").unwrap()
+    }
+
+    #[test]
+    fn test_siblings_share_and_callees_stack() {
+        let module = parse(concat!(
+            "score = 0\n\n",
+            "def main():\n    a()\n    b()\n\n",
+            "def a():\n    i = 1\n    score = i\n\n",
+            "def b():\n    i = 2\n    c(i)\n\n",
+            "def c(n):\n    k = n\n",
+        ));
+        let plan = LocalsPlan::from_module(&module).unwrap();
+
+        // score is a global; a and b never run together so both start at 0
+        assert_eq!(plan.frame("a").unwrap().slots, vec!["I"]);
+        assert_eq!(plan.frame("a").unwrap().offset, 0);
+        assert_eq!(plan.frame("b").unwrap().offset, 0);
+        // c is live while b is, so it goes past b's frame
+        let c = plan.frame("c").unwrap();
+        assert_eq!((c.offset, c.slots.clone(), c.params), (2, vec!["N".to_string(), "K".to_string()], 1));
+        assert_eq!(c.slot_offset("k"), Some(4));
+        assert_eq!(plan.size, 6);
+        assert_eq!(plan.unshared, 8);
+        assert_eq!(plan.heaviest_chain, vec!["B", "C"]);
+    }
+
+    #[test]
+    fn test_recursion_with_locals_is_rejected() {
+        let module = parse(concat!(
+            "def main():\n    f(3)\n\n",
+            "def f(n):\n    g(n)\n\n",
+            "def g(n):\n    if n > 0:\n        f(n - 1)\n",
+        ));
+        let err = LocalsPlan::from_module(&module).unwrap_err();
+        assert!(err.contains("F -> G -> F"), "{}", err);
+    }
+
+    #[test]
+    fn test_coroutine_keeps_live_locals_out_of_the_frame() {
+        let module = parse(concat!(
+            "def loop():\n    CO_RESUME(script)\n\n",
+            "def script(n):\n    while n > 0:\n        t = n * 2\n        show(t)\n        yield\n        n = n - 1\n\n",
+            "def show(v):\n    k = v\n",
+        ));
+        let plan = LocalsPlan::from_module(&module).unwrap();
+        assert_eq!(plan.coroutine("script").unwrap().saved, vec!["N"]);
+        // t is dead at the yield, so it stays overlaid; show still goes past it
+        let script = plan.frame("script").unwrap();
+        assert_eq!((script.slots.clone(), script.params), (vec!["T".to_string()], 0));
+        assert_eq!(plan.frame("show").unwrap().offset, 2);
+        assert!(plan.is_local("script", "n") && plan.is_local("script", "t"));
+    }
+
+    #[test]
+    fn test_coroutine_resuming_itself_is_rejected() {
+        let module = parse(concat!(
+            "def loop():\n    CO_RESUME(a)\n\n",
+            "def a():\n    yield\n    b()\n\n",
+            "def b():\n    CO_RESUME(a)\n",
+        ));
+        let err = LocalsPlan::from_module(&module).unwrap_err();
+        assert!(err.contains("A -> B -> A"), "{}", err);
+    }
+
+    #[test]
+    fn test_recursion_on_globals_is_allowed() {
+        let module = parse(concat!(
+            "n = 3\n\n",
+            "def main():\n    tick()\n\n",
+            "def tick():\n    n = n - 1\n    if n > 0:\n        tick()\n",
+        ));
+        let plan = LocalsPlan::from_module(&module).unwrap();
+        assert!(plan.frames.is_empty());
+        assert_eq!(plan.size, 0);
+    }
+}
@@ -0,0 +1,678 @@
+//! Register Expression Lowering
+//!
+//! Evaluates expressions into D instead of storing every intermediate value
+//! to `RESULT`:
+//!
+//! - Constants, variables, string addresses and array elements at a constant
+//!   index are folded into the instruction that uses them (`ADDD #4`,
+//!   `CMPD VAR_X`, `LDD VAR_TABLE_DATA+6`)
+//! - When both operands need code, the right one is spilled to the S stack
+//!   and consumed with `,S++` / `,S+`
+//! - X holds array addresses and the `MUL16`/`DIV16`/`MOD16` left operand;
+//!   Y and U are left alone because BIOS routines and helpers clobber them
+//! - Conditions compile to `CMPD` + conditional branch, with `and`/`or`
+//!   short-circuiting to the branch target instead of building 0/1 values
+//! - Multiplication by a constant uses shifts, shifts by a constant are
+//!   unrolled
+//!
+//! `RESULT` stays the call interface: user functions and builtins return in
+//! it, so a call is followed by `LDD RESULT`. The previous generator is kept
+//! in expressions.rs behind `context::legacy_expressions()` for comparison
+//! (`vpy_cli bench`).
+
+use vpy_parser::{BinOp, CallInfo, CmpOp, Expr, LogicOp};
+use super::builtins;
+use super::context;
+use crate::AssetInfo;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static LABEL_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// What the condition codes say about D after evaluating an expression
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flags {
+    /// N and Z follow D and V is clear (load): signed tests against 0 are valid
+    Load,
+    /// Z and N follow D but V may be set (ADDD/SUBD)
+    Arith,
+    /// Nothing known
+    None,
+}
+
+/// An instruction operand that needs no code of its own
+enum Operand {
+    /// `#value`
+    Imm(String),
+    /// Extended/direct memory operand
+    Mem(String),
+}
+
+impl Operand {
+    fn text(&self) -> String {
+        match self {
+            Operand::Imm(value) => format!("#{}", value),
+            Operand::Mem(label) => label.clone(),
+        }
+    }
+}
+
+fn local_label(prefix: &str) -> String {
+    format!(".{}_{}", prefix, LABEL_COUNTER.fetch_add(1, Ordering::Relaxed))
+}
+
+/// Keep a folded value in 16 bits, as the CPU would
+fn wrap16(value: i64) -> i32 {
+    value as i16 as i32
+}
+
+/// Value of an expression made only of numbers
+pub fn constant(expr: &Expr) -> Option<i32> {
+    match expr {
+        Expr::Number(n) => Some(*n),
+        Expr::BitNot(inner) => constant(inner).map(|v| wrap16(!(v as i64))),
+        Expr::Binary { op, left, right } => {
+            let (l, r) = (constant(left)? as i64, constant(right)? as i64);
+            let value = match op {
+                BinOp::Add => l + r,
+                BinOp::Sub => l - r,
+                BinOp::Mul => l.wrapping_mul(r),
+                BinOp::BitAnd => l & r,
+                BinOp::BitOr => l | r,
+                BinOp::BitXor => l ^ r,
+                BinOp::Shl if (0..16).contains(&r) => l << r,
+                BinOp::Shr if (0..16).contains(&r) => (l as i16 as i64) >> r,
+                // Division follows DIV16/MOD16, which only handle positive operands
+                _ => return None,
+            };
+            Some(wrap16(value))
+        }
+        _ => None,
+    }
+}
+
+/// RAM or ROM data label of an array (see context::is_mutable_array)
+fn array_label(name: &str) -> String {
+    if context::is_mutable_array(name) {
+        format!("VAR_{}_DATA", name.to_uppercase())
+    } else {
+        format!("ARRAY_{}_DATA", name.to_uppercase())
+    }
+}
+
+fn offset_label(label: &str, offset: i32) -> String {
+    match offset {
+        0 => label.to_string(),
+        o if o > 0 => format!("{}+{}", label, o),
+        o => format!("{}{}", label, o),
+    }
+}
+
+fn operand(expr: &Expr) -> Option<Operand> {
+    if let Some(value) = constant(expr) {
+        return Some(Operand::Imm(value.to_string()));
+    }
+    match expr {
+        Expr::StringLit(s) => Some(Operand::Imm(format!("PRINT_TEXT_STR_{}", builtins::hash_string(s)))),
+        Expr::Ident(id) => Some(Operand::Mem(context::var_label(&id.name))),
+        Expr::Index { target, index } => match (&**target, constant(index)) {
+            (Expr::Ident(id), Some(i)) => Some(Operand::Mem(offset_label(&array_label(&id.name), i * 2))),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Whether evaluating `expr` calls a builtin or user function
+pub fn contains_call(expr: &Expr) -> bool {
+    match expr {
+        Expr::Call(_) | Expr::MethodCall(_) => true,
+        Expr::Binary { left, right, .. } | Expr::Compare { left, right, .. } | Expr::Logic { left, right, .. } => {
+            contains_call(left) || contains_call(right)
+        }
+        Expr::Not(operand) | Expr::BitNot(operand) => contains_call(operand),
+        Expr::Index { target, index } => contains_call(target) || contains_call(index),
+        Expr::List(elements) => elements.iter().any(contains_call),
+        Expr::FieldAccess { target, .. } => contains_call(target),
+        _ => false,
+    }
+}
+
+/// Evaluate `expr` into D
+pub fn to_d(expr: &Expr, out: &mut String, assets: &[AssetInfo]) -> Flags {
+    if let Some(op) = operand(expr) {
+        out.push_str(&format!("    LDD {}\n", op.text()));
+        return Flags::Load;
+    }
+    match expr {
+        Expr::Binary { left, op, right } => binary(left, *op, right, out, assets),
+
+        Expr::Compare { .. } | Expr::Logic { .. } | Expr::Not(_) => {
+            // 0/1 value of a condition; calls can put the operands out of short branch range
+            let is_false = local_label("BOOL_FALSE");
+            let end = local_label("BOOL_END");
+            branch(expr, false, &is_false, contains_call(expr), out, assets);
+            out.push_str("    LDD #1\n");
+            out.push_str(&format!("    BRA {}\n", end));
+            out.push_str(&format!("{}:\n", is_false));
+            out.push_str("    LDD #0\n");
+            out.push_str(&format!("{}:\n", end));
+            Flags::Load
+        }
+
+        Expr::BitNot(inner) => {
+            to_d(inner, out, assets);
+            out.push_str("    COMA\n");
+            out.push_str("    COMB\n");
+            Flags::None
+        }
+
+        Expr::Index { target, index } => {
+            if let Expr::Ident(id) = &**target {
+                let (index, offset) = split_offset(index);
+                to_d(index, out, assets);
+                out.push_str("    ASLB            ; 16-bit elements\n");
+                out.push_str("    ROLA\n");
+                out.push_str(&format!("    LDX #{}\n", offset_label(&array_label(&id.name), offset * 2)));
+                out.push_str("    LDD D,X\n");
+            } else {
+                element_address(target, index, out, assets);
+                out.push_str("    LDD ,X\n");
+            }
+            Flags::Load
+        }
+
+        Expr::Call(call) => {
+            call_expr(call, out, assets);
+            out.push_str("    LDD RESULT\n");
+            Flags::Load
+        }
+
+        _ => {
+            // Unimplemented expression types (List, StructInit, FieldAccess, MethodCall)
+            out.push_str(&format!("    ; Unimplemented Expr {:?}\n", expr));
+            out.push_str("    LDD #0\n");
+            Flags::Load
+        }
+    }
+}
+
+/// Evaluate `expr` for its side effects only (statement position)
+pub fn effect(expr: &Expr, out: &mut String, assets: &[AssetInfo]) {
+    match expr {
+        Expr::Call(call) => call_expr(call, out, assets),
+        _ => {
+            to_d(expr, out, assets);
+        }
+    }
+}
+
+/// Call a builtin or user function; the return value is left in RESULT
+fn call_expr(call: &CallInfo, out: &mut String, assets: &[AssetInfo]) {
+    if builtins::emit_builtin(&call.name, &call.args, out, assets) {
+        return;
+    }
+
+    // User function call (name already uppercase from unifier)
+    // Arguments go to VAR_ARG0-4
+    let args = &call.args[..call.args.len().min(5)];
+    if args.iter().skip(1).any(contains_call) {
+        // A later argument makes a call that may overwrite VAR_ARGn
+        for arg in args {
+            to_d(arg, out, assets);
+            out.push_str("    PSHS D\n");
+        }
+        for i in (0..args.len()).rev() {
+            out.push_str("    PULS D\n");
+            out.push_str(&format!("    STD VAR_ARG{}\n", i));
+        }
+    } else {
+        for (i, arg) in args.iter().enumerate() {
+            to_d(arg, out, assets);
+            out.push_str(&format!("    STD VAR_ARG{}\n", i));
+        }
+    }
+    out.push_str(&format!("    JSR {}\n", call.name));
+}
+
+/// `i + k` / `i - k` as (`i`, ±k), so the constant moves into the array address
+fn split_offset(index: &Expr) -> (&Expr, i32) {
+    match index {
+        Expr::Binary { op: BinOp::Add, left, right } => match constant(right) {
+            Some(k) => (left, k),
+            None => (index, 0),
+        },
+        Expr::Binary { op: BinOp::Sub, left, right } => match constant(right) {
+            Some(k) => (left, -k),
+            None => (index, 0),
+        },
+        _ => (index, 0),
+    }
+}
+
+/// Leave the address of `target[index]` in X
+fn element_address(target: &Expr, index: &Expr, out: &mut String, assets: &[AssetInfo]) {
+    if let Expr::Ident(id) = target {
+        let (index, offset) = split_offset(index);
+        to_d(index, out, assets);
+        out.push_str("    ASLB            ; 16-bit elements\n");
+        out.push_str("    ROLA\n");
+        out.push_str(&format!("    LDX #{}\n", offset_label(&array_label(&id.name), offset * 2)));
+        out.push_str("    LEAX D,X\n");
+    } else {
+        // Computed base address
+        to_d(target, out, assets);
+        out.push_str("    PSHS D\n");
+        to_d(index, out, assets);
+        out.push_str("    ASLB            ; 16-bit elements\n");
+        out.push_str("    ROLA\n");
+        out.push_str("    PULS X\n");
+        out.push_str("    LEAX D,X\n");
+    }
+}
+
+/// `array[index] = value`
+pub fn store_index(array: &str, index: &Expr, value: &Expr, out: &mut String, assets: &[AssetInfo]) {
+    let label = array_label(array);
+    if let Some(i) = constant(index) {
+        to_d(value, out, assets);
+        out.push_str(&format!("    STD {}\n", offset_label(&label, i * 2)));
+        return;
+    }
+    let target = Expr::Ident(vpy_parser::IdentInfo { name: array.to_string(), source_line: 0, col: 0 });
+    if let Some(op) = operand(value) {
+        // Loading the value does not touch X
+        element_address(&target, index, out, assets);
+        out.push_str(&format!("    LDD {}\n", op.text()));
+    } else {
+        to_d(value, out, assets);
+        out.push_str("    PSHS D\n");
+        element_address(&target, index, out, assets);
+        out.push_str("    PULS D\n");
+    }
+    out.push_str("    STD ,X\n");
+}
+
+fn binary(left: &Expr, op: BinOp, right: &Expr, out: &mut String, assets: &[AssetInfo]) -> Flags {
+    match op {
+        BinOp::Add => {
+            if let Some(r) = operand(right) {
+                to_d(left, out, assets);
+                out.push_str(&format!("    ADDD {}\n", r.text()));
+            } else if let Some(l) = operand(left) {
+                to_d(right, out, assets);
+                out.push_str(&format!("    ADDD {}\n", l.text()));
+            } else {
+                to_d(right, out, assets);
+                out.push_str("    PSHS D\n");
+                to_d(left, out, assets);
+                out.push_str("    ADDD ,S++\n");
+            }
+            Flags::Arith
+        }
+        BinOp::Sub => {
+            if let Some(r) = operand(right) {
+                to_d(left, out, assets);
+                out.push_str(&format!("    SUBD {}\n", r.text()));
+            } else {
+                to_d(right, out, assets);
+                out.push_str("    PSHS D\n");
+                to_d(left, out, assets);
+                out.push_str("    SUBD ,S++\n");
+            }
+            Flags::Arith
+        }
+        BinOp::BitAnd | BinOp::BitOr | BinOp::BitXor => bitwise(left, op, right, out, assets),
+        BinOp::Mul => multiply(left, right, out, assets),
+        BinOp::Div | BinOp::FloorDiv | BinOp::Mod => {
+            let helper = if op == BinOp::Mod { "MOD16" } else { "DIV16" };
+            // Helpers take the left operand in X and the right one in D
+            if let Some(r) = operand(right) {
+                to_d(left, out, assets);
+                out.push_str("    TFR D,X\n");
+                out.push_str(&format!("    LDD {}\n", r.text()));
+            } else if let Some(l) = operand(left) {
+                to_d(right, out, assets);
+                out.push_str(&format!("    LDX {}\n", l.text()));
+            } else {
+                to_d(right, out, assets);
+                out.push_str("    PSHS D\n");
+                to_d(left, out, assets);
+                out.push_str("    TFR D,X\n");
+                out.push_str("    PULS D\n");
+            }
+            out.push_str(&format!("    JSR {}\n", helper));
+            Flags::None
+        }
+        BinOp::Shl | BinOp::Shr => {
+            match constant(right) {
+                Some(count) if count >= 0 => {
+                    to_d(left, out, assets);
+                    if op == BinOp::Shl {
+                        shift_left(count, out);
+                    } else {
+                        shift_right(count, out);
+                    }
+                }
+                _ => {
+                    if let Some(r) = operand(right) {
+                        to_d(left, out, assets);
+                        out.push_str(&format!("    LDX {}\n", r.text()));
+                    } else {
+                        to_d(right, out, assets);
+                        out.push_str("    PSHS D\n");
+                        to_d(left, out, assets);
+                        out.push_str("    PULS X\n");
+                        out.push_str("    LEAX ,X          ; Test shift count\n");
+                    }
+                    shift_loop(op, out);
+                }
+            }
+            Flags::None
+        }
+    }
+}
+
+/// Shift D by the count in X (clamped to 16), as the legacy generator does
+fn shift_loop(op: BinOp, out: &mut String) {
+    let prefix = if op == BinOp::Shl { "SHL" } else { "SHR" };
+    let body = local_label(&format!("{}_LOOP", prefix));
+    let end = local_label(&format!("{}_END", prefix));
+    out.push_str(&format!("    BEQ {}\n", end));
+    out.push_str("    CMPX #16\n");
+    out.push_str(&format!("    BLE {}\n", body));
+    out.push_str("    LDX #16\n");
+    out.push_str(&format!("{}:\n", body));
+    if op == BinOp::Shl {
+        out.push_str("    ASLB\n");
+        out.push_str("    ROLA\n");
+    } else {
+        out.push_str("    ASRA\n");
+        out.push_str("    RORB\n");
+    }
+    out.push_str("    LEAX -1,X\n");
+    out.push_str(&format!("    BNE {}\n", body));
+    out.push_str(&format!("{}:\n", end));
+}
+
+fn shift_left(count: i32, out: &mut String) {
+    if count >= 16 {
+        out.push_str("    LDD #0\n");
+        return;
+    }
+    let mut count = count;
+    if count >= 8 {
+        out.push_str("    TFR B,A\n");
+        out.push_str("    CLRB\n");
+        for _ in 8..count {
+            out.push_str("    ASLA\n");
+        }
+        return;
+    }
+    while count > 0 {
+        out.push_str("    ASLB\n");
+        out.push_str("    ROLA\n");
+        count -= 1;
+    }
+}
+
+fn shift_right(count: i32, out: &mut String) {
+    // 15 and more leave only sign bits
+    let count = count.min(15);
+    if count >= 8 {
+        out.push_str("    TFR A,B\n");
+        out.push_str("    SEX\n");
+        for _ in 8..count {
+            out.push_str("    ASRB\n");
+        }
+        return;
+    }
+    for _ in 0..count {
+        out.push_str("    ASRA\n");
+        out.push_str("    RORB\n");
+    }
+}
+
+fn multiply(left: &Expr, right: &Expr, out: &mut String, assets: &[AssetInfo]) -> Flags {
+    let (value, factor) = match (constant(left), constant(right)) {
+        (_, Some(k)) => (left, Some(k)),
+        (Some(k), _) => (right, Some(k)),
+        _ => (left, None),
+    };
+    if let Some(k) = factor {
+        let magnitude = k.unsigned_abs();
+        to_d(value, out, assets);
+        if magnitude == 0 {
+            out.push_str("    LDD #0\n");
+            return Flags::Load;
+        }
+        if !magnitude.is_power_of_two() {
+            out.push_str(&format!("    LDX #{}\n", k));
+            out.push_str("    JSR MUL16\n");
+            return Flags::None;
+        }
+        shift_left(magnitude.trailing_zeros() as i32, out);
+        if k < 0 {
+            out.push_str("    COMA\n");
+            out.push_str("    COMB\n");
+            out.push_str("    ADDD #1\n");
+        }
+        return Flags::None;
+    }
+    // MUL16: D = X * D
+    if let Some(r) = operand(right) {
+        to_d(left, out, assets);
+        out.push_str("    TFR D,X\n");
+        out.push_str(&format!("    LDD {}\n", r.text()));
+    } else if let Some(l) = operand(left) {
+        to_d(right, out, assets);
+        out.push_str(&format!("    LDX {}\n", l.text()));
+    } else {
+        to_d(right, out, assets);
+        out.push_str("    PSHS D\n");
+        to_d(left, out, assets);
+        out.push_str("    TFR D,X\n");
+        out.push_str("    PULS D\n");
+    }
+    out.push_str("    JSR MUL16\n");
+    Flags::None
+}
+
+fn bitwise(left: &Expr, op: BinOp, right: &Expr, out: &mut String, assets: &[AssetInfo]) -> Flags {
+    let mnemonic = match op {
+        BinOp::BitAnd => "AND",
+        BinOp::BitOr => "OR",
+        _ => "EOR",
+    };
+    // All three are commutative: fold whichever side is an operand
+    let (value, other) = if operand(right).is_some() || operand(left).is_none() { (left, right) } else { (right, left) };
+
+    if let Some(k) = constant(other) {
+        to_d(value, out, assets);
+        let bytes = [("A", (k >> 8) & 0xFF), ("B", k & 0xFF)];
+        for (reg, byte) in bytes {
+            match (op, byte) {
+                // Identity bytes need no instruction
+                (BinOp::BitAnd, 0xFF) | (BinOp::BitOr, 0) | (BinOp::BitXor, 0) => {}
+                (BinOp::BitAnd, 0) => out.push_str(&format!("    CLR{}\n", reg)),
+                _ => out.push_str(&format!("    {}{} #${:02X}\n", mnemonic, reg, byte)),
+            }
+        }
+        return Flags::None;
+    }
+    if let Some(Operand::Mem(label)) = operand(other) {
+        to_d(value, out, assets);
+        out.push_str(&format!("    {}A {}\n", mnemonic, label));
+        out.push_str(&format!("    {}B {}+1\n", mnemonic, label));
+        return Flags::None;
+    }
+    to_d(other, out, assets);
+    out.push_str("    PSHS D\n");
+    to_d(value, out, assets);
+    out.push_str(&format!("    {}A ,S+\n", mnemonic));
+    out.push_str(&format!("    {}B ,S+\n", mnemonic));
+    Flags::None
+}
+
+fn branch_mnemonic(op: CmpOp, long: bool) -> String {
+    let base = match op {
+        CmpOp::Eq => "BEQ",
+        CmpOp::Ne => "BNE",
+        CmpOp::Lt => "BLT",
+        CmpOp::Le => "BLE",
+        CmpOp::Gt => "BGT",
+        CmpOp::Ge => "BGE",
+    };
+    if long { format!("L{}", base) } else { base.to_string() }
+}
+
+fn negate(op: CmpOp) -> CmpOp {
+    match op {
+        CmpOp::Eq => CmpOp::Ne,
+        CmpOp::Ne => CmpOp::Eq,
+        CmpOp::Lt => CmpOp::Ge,
+        CmpOp::Ge => CmpOp::Lt,
+        CmpOp::Le => CmpOp::Gt,
+        CmpOp::Gt => CmpOp::Le,
+    }
+}
+
+/// The comparison with its operands swapped (`a < b` is `b > a`)
+fn swap(op: CmpOp) -> CmpOp {
+    match op {
+        CmpOp::Lt => CmpOp::Gt,
+        CmpOp::Gt => CmpOp::Lt,
+        CmpOp::Le => CmpOp::Ge,
+        CmpOp::Ge => CmpOp::Le,
+        other => other,
+    }
+}
+
+/// Set the flags for `left op right`; returns the comparison to branch on
+fn compare(left: &Expr, op: CmpOp, right: &Expr, out: &mut String, assets: &[AssetInfo]) -> CmpOp {
+    if let Some(r) = operand(right) {
+        let flags = to_d(left, out, assets);
+        // A load already compared D with 0
+        if !(constant(right) == Some(0) && flags == Flags::Load) {
+            out.push_str(&format!("    CMPD {}\n", r.text()));
+        }
+        op
+    } else if let Some(l) = operand(left) {
+        to_d(right, out, assets);
+        out.push_str(&format!("    CMPD {}\n", l.text()));
+        swap(op)
+    } else {
+        to_d(right, out, assets);
+        out.push_str("    PSHS D\n");
+        to_d(left, out, assets);
+        out.push_str("    CMPD ,S++\n");
+        op
+    }
+}
+
+/// Jump to `target` when `cond` is `when` (true/false); falls through otherwise.
+/// `long` selects LBxx branches for targets outside the expression.
+pub fn branch(cond: &Expr, when: bool, target: &str, long: bool, out: &mut String, assets: &[AssetInfo]) {
+    match cond {
+        Expr::Compare { left, op, right } => {
+            let op = compare(left, *op, right, out, assets);
+            let op = if when { op } else { negate(op) };
+            out.push_str(&format!("    {} {}\n", branch_mnemonic(op, long), target));
+        }
+        Expr::Logic { op, left, right } => {
+            // `a and b` jumps on true only if both hold; `a or b` on false only if both fail
+            let both = matches!((op, when), (LogicOp::And, false) | (LogicOp::Or, true));
+            if both {
+                branch(left, when, target, long, out, assets);
+                branch(right, when, target, long, out, assets);
+            } else {
+                let skip = local_label("COND_SKIP");
+                branch(left, !when, &skip, long, out, assets);
+                branch(right, when, target, long, out, assets);
+                out.push_str(&format!("{}:\n", skip));
+            }
+        }
+        Expr::Not(inner) => branch(inner, !when, target, long, out, assets),
+        _ => {
+            if let Some(value) = constant(cond) {
+                if (value != 0) == when {
+                    out.push_str(&format!("    {} {}\n", if long { "LBRA" } else { "BRA" }, target));
+                }
+                return;
+            }
+            if to_d(cond, out, assets) == Flags::None {
+                out.push_str("    CMPD #0\n");
+            }
+            let op = if when { CmpOp::Ne } else { CmpOp::Eq };
+            out.push_str(&format!("    {} {}\n", branch_mnemonic(op, long), target));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vpy_parser::IdentInfo;
+
+    fn ident(name: &str) -> Expr {
+        Expr::Ident(IdentInfo { name: name.to_string(), source_line: 0, col: 0 })
+    }
+
+    fn bin(op: BinOp, left: Expr, right: Expr) -> Expr {
+        Expr::Binary { op, left: Box::new(left), right: Box::new(right) }
+    }
+
+    fn lower(expr: &Expr) -> String {
+        let mut out = String::new();
+        to_d(expr, &mut out, &[]);
+        out
+    }
+
+    #[test]
+    fn test_operands_fold_into_instructions() {
+        let asm = lower(&bin(BinOp::Add, ident("x"), Expr::Number(4)));
+        assert_eq!(asm, "    LDD VAR_X\n    ADDD #4\n");
+        assert!(!asm.contains("RESULT"));
+
+        // Constant subexpressions are folded at compile time
+        let asm = lower(&bin(BinOp::Sub, ident("x"), bin(BinOp::Mul, Expr::Number(3), Expr::Number(5))));
+        assert_eq!(asm, "    LDD VAR_X\n    SUBD #15\n");
+
+        // Constant index addresses the element directly
+        let element = Expr::Index { target: Box::new(ident("table")), index: Box::new(Expr::Number(3)) };
+        assert_eq!(lower(&element), "    LDD ARRAY_TABLE_DATA+6\n");
+    }
+
+    #[test]
+    fn test_spills_go_to_the_stack() {
+        let product = |a, b| bin(BinOp::Mul, ident(a), ident(b));
+        let asm = lower(&bin(BinOp::Add, product("a", "b"), product("c", "d")));
+        assert!(asm.contains("    PSHS D\n"));
+        assert!(asm.ends_with("    ADDD ,S++\n"));
+    }
+
+    #[test]
+    fn test_constant_multiply_and_shift() {
+        assert_eq!(lower(&bin(BinOp::Mul, ident("x"), Expr::Number(4))), "    LDD VAR_X\n    ASLB\n    ROLA\n    ASLB\n    ROLA\n");
+        assert_eq!(lower(&bin(BinOp::Shr, ident("x"), Expr::Number(8))), "    LDD VAR_X\n    TFR A,B\n    SEX\n");
+        assert_eq!(lower(&bin(BinOp::BitAnd, ident("x"), Expr::Number(0x0F))), "    LDD VAR_X\n    CLRA\n    ANDB #$0F\n");
+    }
+
+    #[test]
+    fn test_conditions_branch_directly() {
+        let cond = Expr::Logic {
+            op: LogicOp::And,
+            left: Box::new(Expr::Compare { op: CmpOp::Lt, left: Box::new(ident("x")), right: Box::new(Expr::Number(10)) }),
+            right: Box::new(Expr::Compare { op: CmpOp::Ne, left: Box::new(ident("y")), right: Box::new(Expr::Number(0)) }),
+        };
+        let mut out = String::new();
+        branch(&cond, false, "ELSE", true, &mut out, &[]);
+        assert_eq!(out, "    LDD VAR_X\n    CMPD #10\n    LBGE ELSE\n    LDD VAR_Y\n    LBEQ ELSE\n");
+    }
+
+    #[test]
+    fn test_constant_folding_wraps_to_16_bits() {
+        assert_eq!(constant(&bin(BinOp::Add, Expr::Number(32767), Expr::Number(1))), Some(-32768));
+        assert_eq!(constant(&bin(BinOp::Shr, Expr::Number(-256), Expr::Number(4))), Some(-16));
+        assert_eq!(constant(&bin(BinOp::Div, Expr::Number(8), Expr::Number(2))), None);
+    }
+}
@@ -50,8 +50,7 @@ pub fn emit_min(args: &[Expr], out: &mut String, assets: &[AssetInfo]) {
     out.push_str("    ; MIN: Return minimum of two values\n");
     
     // Evaluate first argument -> store in TMPPTR
-    expressions::emit_simple_expr(&args[0], out, assets);
-    out.push_str("    LDD RESULT\n");
+    expressions::emit_expr_to_d(&args[0], out, assets);
     out.push_str("    STD TMPPTR     ; Save first value\n");
     
     // Evaluate second argument -> RESULT
@@ -82,8 +81,7 @@ pub fn emit_max(args: &[Expr], out: &mut String, assets: &[AssetInfo]) {
     out.push_str("    ; MAX: Return maximum of two values\n");
     
     // Evaluate first argument -> store in TMPPTR
-    expressions::emit_simple_expr(&args[0], out, assets);
-    out.push_str("    LDD RESULT\n");
+    expressions::emit_expr_to_d(&args[0], out, assets);
     out.push_str("    STD TMPPTR     ; Save first value\n");
     
     // Evaluate second argument -> RESULT
@@ -114,18 +112,15 @@ pub fn emit_clamp(args: &[Expr], out: &mut String, assets: &[AssetInfo]) {
     out.push_str("    ; CLAMP: Clamp value to range [min, max]\n");
     
     // Evaluate value (arg 0)
-    expressions::emit_simple_expr(&args[0], out, assets);
-    out.push_str("    LDD RESULT\n");
+    expressions::emit_expr_to_d(&args[0], out, assets);
     out.push_str("    STD TMPPTR     ; Save value\n");
     
     // Evaluate min (arg 1)
-    expressions::emit_simple_expr(&args[1], out, assets);
-    out.push_str("    LDD RESULT\n");
+    expressions::emit_expr_to_d(&args[1], out, assets);
     out.push_str("    STD TMPPTR+2   ; Save min\n");
     
     // Evaluate max (arg 2)
-    expressions::emit_simple_expr(&args[2], out, assets);
-    out.push_str("    LDD RESULT\n");
+    expressions::emit_expr_to_d(&args[2], out, assets);
     out.push_str("    STD TMPPTR+4   ; Save max\n");
     
     // Compare value with min
@@ -163,17 +158,24 @@ pub fn emit_runtime_helpers(out: &mut String, needed: &HashSet<String>) {
     // MUL16: Multiply X * D -> D
     if needed.contains("MUL16") {
         out.push_str("MUL16:\n");
-        out.push_str("    ; Multiply 16-bit X * D -> D\n");
-        out.push_str("    ; Simple implementation (can be optimized)\n");
-        out.push_str("    PSHS X,B,A\n");
-        out.push_str("    LDD #0         ; Result accumulator\n");
-        out.push_str("    LDX 2,S        ; Multiplier\n");
-        out.push_str(".MUL16_LOOP:\n");
-        out.push_str("    BEQ .MUL16_END\n");
-        out.push_str("    ADDD ,S        ; Add multiplicand\n");
-        out.push_str("    LEAX -1,X\n");
-        out.push_str("    BRA .MUL16_LOOP\n");
-        out.push_str(".MUL16_END:\n");
+        out.push_str("    ; Multiply 16-bit X * D -> D (low 16 bits, signed or unsigned)\n");
+        out.push_str("    ; XL*DL + (XH*DL + XL*DH) << 8 with the 8-bit MUL\n");
+        out.push_str("    PSHS X,B,A     ; 0,S=DH 1,S=DL 2,S=XH 3,S=XL\n");
+        out.push_str("    LDA 3,S\n");
+        out.push_str("    LDB 1,S\n");
+        out.push_str("    MUL            ; XL*DL\n");
+        out.push_str("    PSHS B,A       ; Product at 0,S (operands now at 2,S)\n");
+        out.push_str("    LDA 5,S\n");
+        out.push_str("    LDB 2,S\n");
+        out.push_str("    MUL            ; XL*DH\n");
+        out.push_str("    ADDB ,S\n");
+        out.push_str("    STB ,S\n");
+        out.push_str("    LDA 4,S\n");
+        out.push_str("    LDB 3,S\n");
+        out.push_str("    MUL            ; XH*DL\n");
+        out.push_str("    ADDB ,S\n");
+        out.push_str("    STB ,S\n");
+        out.push_str("    PULS A,B\n");
         out.push_str("    LEAS 4,S\n");
         out.push_str("    RTS\n\n");
     }
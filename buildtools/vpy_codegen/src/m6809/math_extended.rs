@@ -2,6 +2,7 @@
 //!
 //! Advanced math operations:
 //! - SIN, COS, TAN: Trigonometry (lookup tables)
+//! - SIN_FX, COS_FX: Same, returning fixed-point 8.8 (256 = 1.0)
 //! - SQRT: Square root (Newton-Raphson approximation)
 //! - POW: Power (repeated multiplication)
 //! - ATAN2: Arctangent (CORDIC-style approximation)
@@ -30,7 +31,7 @@ pub fn generate_trig_tables() -> String {
         let value = (angle.sin() * 127.0).round() as i16;
         asm.push_str(&format!("    FDB {}    ; angle {}\n", value, i));
     }
-    asm.push_str("\n");
+    asm.push('\n');
     
     // Generate COS table
     asm.push_str("COS_TABLE:\n");
@@ -39,7 +40,7 @@ pub fn generate_trig_tables() -> String {
         let value = (angle.cos() * 127.0).round() as i16;
         asm.push_str(&format!("    FDB {}    ; angle {}\n", value, i));
     }
-    asm.push_str("\n");
+    asm.push('\n');
     
     // Generate TAN table (clamped to ±120 to avoid overflow)
     asm.push_str("TAN_TABLE:\n");
@@ -53,7 +54,30 @@ pub fn generate_trig_tables() -> String {
         };
         asm.push_str(&format!("    FDB {}    ; angle {}\n", value, i));
     }
-    asm.push_str("\n");
+    asm.push('\n');
+    
+    asm
+}
+
+/// Generates the fixed-point trig tables used by SIN_FX/COS_FX
+///
+/// Same 128-step angle as SIN/COS, values in 8.8 (-256 to +256)
+pub fn generate_fixed_trig_tables() -> String {
+    let mut asm = String::new();
+    
+    asm.push_str(";***************************************************************************\n");
+    asm.push_str("; FIXED-POINT 8.8 TRIGONOMETRY TABLES (128 entries each)\n");
+    asm.push_str(";***************************************************************************\n");
+    
+    for (label, f) in [("SIN_FX_TABLE", f32::sin as fn(f32) -> f32), ("COS_FX_TABLE", f32::cos)] {
+        asm.push_str(&format!("{}:\n", label));
+        for i in 0..128 {
+            let angle = (i as f32) * std::f32::consts::TAU / 128.0;
+            let value = (f(angle) * 256.0).round() as i16;
+            asm.push_str(&format!("    FDB {}    ; angle {}\n", value, i));
+        }
+        asm.push('\n');
+    }
     
     asm
 }
@@ -110,6 +134,38 @@ pub fn emit_cos(args: &[Expr], out: &mut String, assets: &[AssetInfo]) {
     out.push_str("    STD RESULT\n");
 }
 
+/// SIN_FX(angle) - Sine as fixed-point 8.8 (angle 0-127 represents 0-360°)
+pub fn emit_sin_fx(args: &[Expr], out: &mut String, assets: &[AssetInfo]) {
+    emit_fixed_trig("SIN_FX", "SIN_FX_TABLE", args, out, assets);
+}
+
+/// COS_FX(angle) - Cosine as fixed-point 8.8
+pub fn emit_cos_fx(args: &[Expr], out: &mut String, assets: &[AssetInfo]) {
+    emit_fixed_trig("COS_FX", "COS_FX_TABLE", args, out, assets);
+}
+
+fn emit_fixed_trig(name: &str, table: &str, args: &[Expr], out: &mut String, assets: &[AssetInfo]) {
+    if args.is_empty() {
+        out.push_str(&format!("    ; {}: no argument\n", name));
+        out.push_str("    LDD #0\n    STD RESULT\n");
+        return;
+    }
+    
+    out.push_str(&format!("    ; {}: 8.8 lookup\n", name));
+    
+    expressions::emit_simple_expr(&args[0], out, assets);
+    
+    out.push_str("    LDD RESULT\n");
+    out.push_str("    ANDB #$7F\n");
+    out.push_str("    CLRA\n");
+    out.push_str("    ASLB\n");
+    out.push_str("    ROLA\n");
+    out.push_str(&format!("    LDX #{}\n", table));
+    out.push_str("    ABX\n");
+    out.push_str("    LDD ,X\n");
+    out.push_str("    STD RESULT\n");
+}
+
 /// TAN(angle) - Tangent lookup
 pub fn emit_tan(args: &[Expr], out: &mut String, assets: &[AssetInfo]) {
     if args.is_empty() {
@@ -167,13 +223,11 @@ pub fn emit_pow(args: &[Expr], out: &mut String, assets: &[AssetInfo]) {
     out.push_str("    ; POW: Power (base ^ exp)\n");
     
     // Evaluate base
-    expressions::emit_simple_expr(&args[0], out, assets);
-    out.push_str("    LDD RESULT\n");
+    expressions::emit_expr_to_d(&args[0], out, assets);
     out.push_str("    STD TMPPTR     ; Save base\n");
     
     // Evaluate exponent
-    expressions::emit_simple_expr(&args[1], out, assets);
-    out.push_str("    LDD RESULT\n");
+    expressions::emit_expr_to_d(&args[1], out, assets);
     out.push_str("    STD TMPPTR2    ; Save exponent\n");
     
     // Call helper
@@ -192,13 +246,11 @@ pub fn emit_atan2(args: &[Expr], out: &mut String, assets: &[AssetInfo]) {
     out.push_str("    ; ATAN2: Arctangent (y, x)\n");
     
     // Evaluate y
-    expressions::emit_simple_expr(&args[0], out, assets);
-    out.push_str("    LDD RESULT\n");
+    expressions::emit_expr_to_d(&args[0], out, assets);
     out.push_str("    STD TMPPTR     ; Save y\n");
     
     // Evaluate x
-    expressions::emit_simple_expr(&args[1], out, assets);
-    out.push_str("    LDD RESULT\n");
+    expressions::emit_expr_to_d(&args[1], out, assets);
     out.push_str("    STD TMPPTR2    ; Save x\n");
     
     // Call helper
@@ -227,16 +279,49 @@ pub fn emit_rand_range(args: &[Expr], out: &mut String, assets: &[AssetInfo]) {
     out.push_str("    ; RAND_RANGE: Random in range [min, max]\n");
     
     // Evaluate min
-    expressions::emit_simple_expr(&args[0], out, assets);
-    out.push_str("    LDD RESULT\n");
+    expressions::emit_expr_to_d(&args[0], out, assets);
     out.push_str("    STD TMPPTR     ; Save min\n");
     
     // Evaluate max
-    expressions::emit_simple_expr(&args[1], out, assets);
-    out.push_str("    LDD RESULT\n");
+    expressions::emit_expr_to_d(&args[1], out, assets);
     out.push_str("    STD TMPPTR2    ; Save max\n");
     
     // Call helper
     out.push_str("    JSR RAND_RANGE_HELPER\n");
     out.push_str("    STD RESULT\n");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixed_trig_tables_are_8_8() {
+        let tables = generate_fixed_trig_tables();
+        let values = |label: &str| -> Vec<i32> {
+            tables
+                .split(label)
+                .nth(1)
+                .unwrap()
+                .lines()
+                .filter_map(|l| l.trim().strip_prefix("FDB "))
+                .take(128)
+                .map(|v| v.split_whitespace().next().unwrap().parse().unwrap())
+                .collect()
+        };
+        let sin = values("SIN_FX_TABLE:");
+        let cos = values("COS_FX_TABLE:");
+        assert_eq!(sin.len(), 128);
+        assert_eq!((sin[0], sin[32], sin[64], sin[96]), (0, 256, 0, -256));
+        assert_eq!((cos[0], cos[32], cos[64]), (256, 0, -256));
+        assert_eq!(sin[16], 181); // sin(45°) * 256
+    }
+
+    #[test]
+    fn test_sin_fx_reads_fixed_table() {
+        let mut out = String::new();
+        emit_sin_fx(&[Expr::Number(8)], &mut out, &[]);
+        assert!(out.contains("LDX #SIN_FX_TABLE"));
+        assert!(!out.contains("#SIN_TABLE"));
+    }
+}
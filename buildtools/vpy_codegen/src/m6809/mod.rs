@@ -5,14 +5,21 @@
 //! - variables: RAM variable allocation
 //! - functions: Function code generation
 //! - expressions: Expression compilation
+//! - lowering: expressions evaluated in registers (D/X, spills on S)
 //! - builtins: Builtin function code
 //! - helpers: Runtime helpers (MUL16, DIV16, etc.)
 //! - assets: Asset discovery and generation
+//! - test_harness: assert statements and test ROM mailbox
+//! - direct_page: SETDP tracking so RAM accesses use direct addressing
+//! - locals: parameters and locals in call-graph-overlaid RAM frames
+//! - peephole: rule-table rewrites over the finished listing
+//! - objects: program and module listings for separate compilation
 
 pub mod header;
 pub mod variables;
 pub mod functions;
 pub mod expressions;
+pub mod lowering;
 pub mod builtins;
 pub mod helpers;
 pub mod math;
@@ -25,6 +32,12 @@ pub mod utilities;
 pub mod ram_layout;
 pub mod assets;
 pub mod context;  // Thread-local context for mutable array tracking
+pub mod test_harness;
+pub mod direct_page;
+pub mod locals;
+pub mod coroutines;
+pub mod peephole;
+pub mod objects;
 
 use vpy_parser::{Item, Expr, Stmt, CallInfo};
 
@@ -86,7 +99,7 @@ fn analyze_used_assets(module: &Module, assets: &[crate::AssetInfo]) -> std::col
                 if (name_upper == "DRAW_VECTOR" && call_info.args.len() == 3) || 
                    (name_upper == "DRAW_VECTOR_EX" && call_info.args.len() == 5) ||
                    (name_upper == "PLAY_MUSIC" && call_info.args.len() == 1) ||
-                   (name_upper == "PLAY_SFX" && call_info.args.len() == 1) ||
+                   (name_upper == "PLAY_SFX" && !call_info.args.is_empty()) ||
                    (name_upper == "LOAD_LEVEL" && call_info.args.len() == 1) {
                     if let Expr::StringLit(asset_name) = &call_info.args[0] {
                         used.insert(asset_name.clone());
@@ -203,42 +216,52 @@ fn analyze_used_assets(module: &Module, assets: &[crate::AssetInfo]) -> std::col
     used
 }
 
-/// Check if trigonometric functions (SIN, COS, TAN) are used in statements
-fn check_trig_usage(stmts: &[Stmt]) -> bool {
+/// Builtins backed by SIN_TABLE/COS_TABLE/TAN_TABLE
+const TRIG_BUILTINS: &[&str] = &["SIN", "COS", "TAN", "MATH_SIN", "MATH_COS", "MATH_TAN"];
+/// Builtins backed by the 8.8 tables (SIN_FX_TABLE/COS_FX_TABLE)
+const FIXED_TRIG_BUILTINS: &[&str] = &["SIN_FX", "COS_FX", "MATH_SIN_FX", "MATH_COS_FX"];
+
+/// Check if any of the given trig builtins is used in statements
+fn check_trig_usage(stmts: &[Stmt], names: &[&str]) -> bool {
     for stmt in stmts {
-        if check_stmt_trig(stmt) {
+        if check_stmt_trig(stmt, names) {
             return true;
         }
     }
     false
 }
 
-fn check_stmt_trig(stmt: &Stmt) -> bool {
+fn check_stmt_trig(stmt: &Stmt, names: &[&str]) -> bool {
     match stmt {
-        Stmt::Expr(expr, _) => check_expr_trig(expr),
-        Stmt::Assign { value, .. } => check_expr_trig(value),
+        Stmt::Expr(expr, _) => check_expr_trig(expr, names),
+        Stmt::Assign { value, .. } => check_expr_trig(value, names),
         Stmt::If { cond, body, elifs, else_body, .. } => {
-            check_expr_trig(cond)
-                || check_trig_usage(body)
-                || elifs.iter().any(|(c, b)| check_expr_trig(c) || check_trig_usage(b))
-                || else_body.as_ref().map_or(false, |b| check_trig_usage(b))
+            check_expr_trig(cond, names)
+                || check_trig_usage(body, names)
+                || elifs.iter().any(|(c, b)| check_expr_trig(c, names) || check_trig_usage(b, names))
+                || else_body.as_ref().map_or(false, |b| check_trig_usage(b, names))
+        }
+        Stmt::While { cond, body, .. } => check_expr_trig(cond, names) || check_trig_usage(body, names),
+        Stmt::Switch { expr, cases, default, .. } => {
+            check_expr_trig(expr, names)
+                || cases.iter().any(|(c, b)| check_expr_trig(c, names) || check_trig_usage(b, names))
+                || default.as_ref().is_some_and(|b| check_trig_usage(b, names))
         }
-        Stmt::While { cond, body, .. } => check_expr_trig(cond) || check_trig_usage(body),
         _ => false,
     }
 }
 
-fn check_expr_trig(expr: &Expr) -> bool {
+fn check_expr_trig(expr: &Expr, names: &[&str]) -> bool {
     match expr {
         Expr::Call(CallInfo { name, args, .. }) => {
             let upper = name.to_uppercase();
-            upper == "SIN" || upper == "COS" || upper == "TAN"
-                || args.iter().any(check_expr_trig)
+            names.contains(&upper.as_str())
+                || args.iter().any(|a| check_expr_trig(a, names))
         }
-        Expr::Binary { left, right, .. } => check_expr_trig(left) || check_expr_trig(right),
-        Expr::Not(operand) | Expr::BitNot(operand) => check_expr_trig(operand),
-        Expr::Index { target, index, .. } => check_expr_trig(target) || check_expr_trig(index),
-        Expr::List(elements) => elements.iter().any(check_expr_trig),
+        Expr::Binary { left, right, .. } => check_expr_trig(left, names) || check_expr_trig(right, names),
+        Expr::Not(operand) | Expr::BitNot(operand) => check_expr_trig(operand, names),
+        Expr::Index { target, index, .. } => check_expr_trig(target, names) || check_expr_trig(index, names),
+        Expr::List(elements) => elements.iter().any(|e| check_expr_trig(e, names)),
         _ => false,
     }
 }
@@ -269,6 +292,7 @@ pub fn generate_m6809_asm(
         }
     }
     context::set_mutable_arrays(mutable_arrays);
+    context::set_locals(locals::LocalsPlan::from_module(module)?);
     
     // Calculate bank configuration dynamically
     let bank_size = 16384; // Standard Vectrex bank size (16KB)
@@ -333,13 +357,18 @@ pub fn generate_m6809_asm(
         asm.push_str("    STA >CURRENT_ROM_BANK   ; Initialize bank tracker (Bank 0 is visible at boot)\n");
     }
     
+    if test_harness::needs_mailbox(module) {
+        test_harness::emit_mailbox_init(&mut asm);
+    }
+    
     // CRITICAL: Initialize SFX system variables to prevent garbage data interference
     use crate::m6809::functions::has_audio_calls;
     if has_audio_calls(module) {
         asm.push_str("    ; Initialize SFX variables to prevent random noise on startup\n");
-        asm.push_str("    CLR >SFX_ACTIVE         ; Mark SFX as inactive (0=off)\n");
-        asm.push_str("    LDD #$0000\n");
-        asm.push_str("    STD >SFX_PTR            ; Clear SFX pointer\n");
+        asm.push_str("    CLR >SFX_ACTIVE         ; No channel held by an effect (slots free)\n");
+        asm.push_str("    CLR >SFX_MIX_MASK       ; Mixer bits all belong to the music\n");
+        asm.push_str("    CLR >SFX_ENV_MODE       ; Envelope belongs to the music\n");
+        asm.push_str("    CLR >SFX_NOISE_USED     ; Noise period belongs to the music\n");
         
         // CRITICAL (2026-01-20): Initialize PSG_MUSIC_BANK for multibank
         if is_multibank {
@@ -547,15 +576,19 @@ pub fn generate_m6809_asm(
     
     // Emit trigonometry lookup tables (SIN, COS, TAN) - CONDITIONAL
     // Only emit if SIN, COS, or TAN functions are actually used
-    if module.items.iter().any(|item| {
+    let uses_trig = |names: &[&str]| module.items.iter().any(|item| {
         if let Item::Function(f) = item {
-            check_trig_usage(&f.body)
+            check_trig_usage(&f.body, names)
         } else {
             false
         }
-    }) {
+    });
+    if uses_trig(TRIG_BUILTINS) {
         asm.push_str(&math_extended::generate_trig_tables());
     }
+    if uses_trig(FIXED_TRIG_BUILTINS) {
+        asm.push_str(&math_extended::generate_fixed_trig_tables());
+    }
     
     // CRITICAL FIX (2026-01-17): Emit PRINT_TEXT strings AFTER all code/helpers
     // This ensures labels have stable final addresses that assembler can resolve
@@ -572,5 +605,6 @@ pub fn generate_m6809_asm(
     // BIOS vectors point to RAM vectors ($CBF2-$CBFB) as defined in VECTREX.I
     // Cartridge starts at $0000 and BIOS jumps there after verification
     
-    Ok(asm)
+    // Follow DP through the listing so $C8xx RAM accesses assemble as direct
+    Ok(direct_page::track_direct_page(&asm))
 }
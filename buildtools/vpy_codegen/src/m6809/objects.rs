@@ -0,0 +1,178 @@
+//! Separately Compiled Units
+//!
+//! Listings for `vpy_cli assemble`, which turns each `.vpy` into a `.vo`.
+//!
+//! A program (a file with `main()` or `loop()`) is the whole cartridge as
+//! usual, plus an `EXPORT` of its RAM variables and runtime helpers so that
+//! module objects can use them.
+//!
+//! A module (any other file) compiles to its functions and data only: no
+//! header, no start-up code, no runtime. Its functions are exported; the
+//! runtime helpers, the system RAM (RESULT, VAR_ARG0-4...) and the globals
+//! it uses are left undefined, so they become imports the program's object
+//! resolves at link time. Its parameters and locals are returned as RAM
+//! slots for a BSS section, which the linker places after the program's RAM.
+//!
+//! Limits of module objects: they cannot own globals or coroutines
+//! (both are initialised by the program's start-up code), and the runtime
+//! helpers they need must be ones the program uses too. A module may not
+//! call back into program functions: the program exports none, since each
+//! object's locals are overlaid along its own call graph only.
+
+use std::collections::HashSet;
+use vpy_parser::{Item, Module};
+use super::locals::{self, LocalsPlan};
+use super::ram_layout::{parse_equ_definitions, RamSlot};
+use super::{assets, builtins, context, functions, helpers, variables};
+use crate::AssetInfo;
+
+/// Start of the program's compact RAM region (see helpers.rs)
+const RAM_START: usize = 0xC880;
+
+/// Listing of one unit and the RAM it owns
+#[derive(Debug, Clone)]
+pub struct ObjectAsm {
+    pub asm_source: String,
+    /// Bytes of the unit's compact RAM region (from `RAM_START` for a program)
+    pub ram_size: usize,
+    /// Variables the linker places (a module's locals, by offset in its region)
+    pub ram: Vec<RamSlot>,
+}
+
+/// Whether `module` is a program rather than a module of one
+pub fn is_program(module: &Module) -> bool {
+    module.items.iter().any(|item| {
+        matches!(item, Item::Function(f) if f.name.eq_ignore_ascii_case("main") || f.name.eq_ignore_ascii_case("loop"))
+    })
+}
+
+/// The single-bank program with its RAM variables and runtime helpers exported
+pub fn generate_program(module: &Module, title: &str, assets: &[AssetInfo]) -> Result<ObjectAsm, String> {
+    let mut asm = super::generate_m6809_asm(module, title, 32768, 32768, assets)?;
+    let slots = parse_equ_definitions(&asm);
+    let ram_size = slots
+        .iter()
+        .filter(|slot| !slot.fixed)
+        .map(|slot| slot.address as usize + slot.size)
+        .max()
+        .map_or(0, |end| end - RAM_START);
+
+    let mut exports: Vec<String> = slots.into_iter().map(|slot| slot.name).collect();
+    exports.extend(labels(&helpers::generate_helpers(module)?));
+    asm.push_str("\n; Used by module objects\n");
+    for name in exports {
+        asm.push_str(&format!("    EXPORT {}\n", name));
+    }
+    Ok(ObjectAsm { asm_source: asm, ram_size, ram: Vec::new() })
+}
+
+/// Functions and data of a module, runtime and shared RAM left as imports
+pub fn generate_module(module: &Module, assets: &[AssetInfo]) -> Result<ObjectAsm, String> {
+    if let Some(name) = module.items.iter().find_map(|item| match item {
+        Item::GlobalLet { name, .. } => Some(name),
+        _ => None,
+    }) {
+        return Err(format!(
+            "module objects cannot own global variables yet ('{}'): declare it in the program, which exports it",
+            name
+        ));
+    }
+    let plan = LocalsPlan::from_module(module)?;
+    if let Some(name) = plan.coroutines.keys().next() {
+        return Err(format!("coroutine {} must be compiled with the program (its state is reset at start-up)", name));
+    }
+    let ram = plan
+        .frames
+        .values()
+        .flat_map(|frame| {
+            frame.slots.iter().map(|name| RamSlot {
+                name: LocalsPlan::label(&frame.function, name),
+                address: frame.slot_offset(name).unwrap_or(frame.offset) as u16,
+                size: locals::SLOT_SIZE,
+                comment: format!("Local '{}' of {}", name.to_lowercase(), frame.function),
+                fixed: false,
+            })
+        })
+        .collect();
+    let ram_size = plan.size;
+
+    let assets = assets::filter_used_assets(assets, module);
+    context::set_mutable_arrays(HashSet::new());
+    context::set_locals(plan);
+    builtins::set_multibank_mode(false);
+    builtins::set_banked_assets_mode(false);
+
+    let mut asm = String::new();
+    asm.push_str("; VPy M6809 module object (functions and data, no runtime)\n\n");
+    asm.push_str("    INCLUDE \"VECTREX.I\"\n\n");
+    for item in &module.items {
+        if let Item::Function(func) = item {
+            asm.push_str(&format!("    EXPORT {}\n", func.name));
+        }
+    }
+    asm.push('\n');
+    asm.push_str(&variables::emit_array_data(module));
+    for item in &module.items {
+        if let Item::Function(func) = item {
+            functions::generate_function(func, &mut asm, &assets)?;
+        }
+    }
+    let print_text_strings = builtins::collect_print_text_strings(module);
+    if !print_text_strings.is_empty() {
+        builtins::emit_print_text_strings(&print_text_strings, &mut asm);
+    }
+    if !assets.is_empty() {
+        asm.push_str(&assets::generate_assets_asm(&assets).map_err(|e| format!("Asset generation failed: {}", e))?);
+    }
+    Ok(ObjectAsm { asm_source: asm, ram_size, ram })
+}
+
+/// Labels defined at column 0 (not `.local` ones)
+fn labels(asm: &str) -> Vec<String> {
+    asm.lines()
+        .filter(|line| !line.starts_with(char::is_whitespace) && !line.starts_with(';') && !line.starts_with('.'))
+        .filter_map(|line| line.split_whitespace().next()?.strip_suffix(':'))
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Module {
+        let tokens = vpy_parser::lex(source).unwrap();
+        vpy_parser::parser::parse(tokens, "test.vpy").unwrap()
+    }
+
+    #[test]
+    fn test_program_exports_ram_and_helpers() {
+        let module = parse("score = 0\n\ndef main():\n    score = score * 3\n\ndef loop():\n    pass\n");
+        assert!(is_program(&module));
+        let object = generate_program(&module, "T", &[]).unwrap();
+        for name in ["RESULT", "VAR_ARG0", "VAR_SCORE", "MUL16"] {
+            assert!(object.asm_source.contains(&format!("    EXPORT {}\n", name)), "{} not exported", name);
+        }
+        assert!(object.ram_size >= 8);
+    }
+
+    #[test]
+    fn test_module_has_functions_only() {
+        let module = parse("def twice(x):\n    y = x + x\n    return y\n\ndef show():\n    PRINT_TEXT(0, 0, \"HI\")\n");
+        assert!(!is_program(&module));
+        let object = generate_module(&module, &[]).unwrap();
+        let asm = &object.asm_source;
+        assert!(asm.contains("    EXPORT twice\n") && asm.contains("    EXPORT show\n"));
+        assert!(!asm.contains("START:") && !asm.contains("VECTREX_PRINT_TEXT:") && !asm.contains(" EQU "));
+        assert!(asm.contains("JSR VECTREX_PRINT_TEXT"));
+        let names: Vec<_> = object.ram.iter().map(|s| (s.name.as_str(), s.address)).collect();
+        assert_eq!(names, vec![("LOC_TWICE__X", 0), ("LOC_TWICE__Y", 2)]);
+        assert_eq!(object.ram_size, 4);
+    }
+
+    #[test]
+    fn test_module_limits() {
+        let err = generate_module(&parse("speed = 3\n\ndef go():\n    pass\n"), &[]).unwrap_err();
+        assert!(err.contains("'speed'"), "{}", err);
+    }
+}
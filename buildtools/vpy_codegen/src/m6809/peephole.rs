@@ -0,0 +1,668 @@
+//! Peephole Optimiser
+//!
+//! Rewrites the finished listing with the declarative rules in `RULES`.
+//! A rule is a window of consecutive instructions written as templates
+//! (`ST{r} {m}`: `{name}` matches any text, the same name the same text),
+//! the instructions that replace it, and conditions on the bindings and on
+//! the code around the window. Rules are applied until none matches.
+//!
+//! Every rule leaves registers, memory and the condition codes the code after
+//! it can observe unchanged. Windows never span a label, so code reached by a
+//! branch is not affected; comment lines (including `; VPy_LINE:N` markers)
+//! between the instructions stay where they are, and the comment of a deleted
+//! instruction that carries a `VPy_LINE` marker is kept on its own line, so
+//! the debug line maps built from the listing stay correct.
+
+use std::collections::{HashMap, HashSet};
+
+/// One declarative rewrite
+pub struct Rule {
+    pub name: &'static str,
+    /// Consecutive instructions, `MNEMONIC OPERAND` with `{name}` placeholders
+    pub pattern: &'static [&'static str],
+    /// Instructions written over the first lines of the match (never more than the pattern)
+    pub replacement: &'static [&'static str],
+    pub when: &'static [Cond],
+}
+
+/// Condition on the bindings of a match
+#[derive(Debug, Clone, Copy)]
+pub enum Cond {
+    /// `{var}` is one of these texts
+    OneOf(&'static str, &'static [&'static str]),
+    /// `{var}` is a plain RAM address: not immediate, indexed or an I/O register
+    Memory(&'static str),
+    /// `{var}` names a label defined once, in the same bank as the match
+    Label(&'static str),
+    /// `{var}` is a PSHS/PULS register list without S or PC
+    Registers(&'static str),
+    /// The next instruction loads this register without reading it
+    Dead(&'static str),
+    /// Label `{var}` is defined right after the match
+    LabelFollows(&'static str),
+    /// Label `{from}` starts with an unconditional jump; binds `{to}` to the end of the jump chain
+    JumpsTo(&'static str, &'static str),
+    /// Label `{var}` starts with RTS
+    Returns(&'static str),
+    /// `{cc}` is a branch condition; binds `{inverse}` to the opposite one
+    Inverse(&'static str, &'static str),
+}
+
+const REGISTERS: &[&str] = &["A", "B", "D", "X", "Y", "U"];
+const JUMPS: &[&str] = &["BRA", "LBRA", "JMP"];
+const LONG_BRANCHES: &[&str] = &[
+    "LBRA", "JMP", "LBEQ", "LBNE", "LBMI", "LBPL", "LBVS", "LBVC", "LBCS", "LBCC", "LBLO", "LBHS",
+    "LBHI", "LBLS", "LBGT", "LBGE", "LBLT", "LBLE",
+];
+const BRANCHES: &[&str] = &[
+    "BRA", "LBRA", "JMP", "BEQ", "BNE", "BMI", "BPL", "BVS", "BVC", "BCS", "BCC", "BLO", "BHS",
+    "BHI", "BLS", "BGT", "BGE", "BLT", "BLE", "LBEQ", "LBNE", "LBMI", "LBPL", "LBVS", "LBVC",
+    "LBCS", "LBCC", "LBLO", "LBHS", "LBHI", "LBLS", "LBGT", "LBGE", "LBLT", "LBLE", "BRN", "LBRN",
+];
+const INVERSE_CONDITIONS: &[(&str, &str)] = &[
+    ("EQ", "NE"), ("MI", "PL"), ("VS", "VC"), ("CS", "CC"), ("LO", "HS"), ("HI", "LS"), ("GT", "LE"), ("GE", "LT"),
+];
+
+pub const RULES: &[Rule] = &[
+    // ST sets N/Z/V from the register exactly like LD does
+    Rule {
+        name: "store-reload",
+        pattern: &["ST{r} {m}", "LD{r} {m}"],
+        replacement: &["ST{r} {m}"],
+        when: &[Cond::OneOf("r", REGISTERS), Cond::Memory("m")],
+    },
+    Rule {
+        name: "load-store-same",
+        pattern: &["LD{r} {m}", "ST{r} {m}"],
+        replacement: &["LD{r} {m}"],
+        when: &[Cond::OneOf("r", REGISTERS), Cond::Memory("m")],
+    },
+    // LDX sets the same flags as LDD for the same value
+    Rule {
+        name: "load-transfer",
+        pattern: &["LDD {v}", "TFR D,X"],
+        replacement: &["LDX {v}"],
+        when: &[Cond::Dead("D")],
+    },
+    Rule {
+        name: "push-pull",
+        pattern: &["PSHS {r}", "PULS {r}"],
+        replacement: &[],
+        when: &[Cond::Registers("r")],
+    },
+    Rule {
+        name: "branch-to-next",
+        pattern: &["{b} {l}"],
+        replacement: &[],
+        when: &[Cond::OneOf("b", BRANCHES), Cond::Label("l"), Cond::LabelFollows("l")],
+    },
+    Rule {
+        name: "branch-over-jump",
+        pattern: &["B{cc} {skip}", "{j} {l}"],
+        replacement: &["LB{inverse} {l}"],
+        when: &[
+            Cond::Inverse("cc", "inverse"),
+            Cond::OneOf("j", &["BRA", "LBRA"]),
+            Cond::Label("l"),
+            Cond::LabelFollows("skip"),
+        ],
+    },
+    Rule {
+        name: "branch-over-jump",
+        pattern: &["LB{cc} {skip}", "{j} {l}"],
+        replacement: &["LB{inverse} {l}"],
+        when: &[
+            Cond::Inverse("cc", "inverse"),
+            Cond::OneOf("j", &["BRA", "LBRA"]),
+            Cond::Label("l"),
+            Cond::LabelFollows("skip"),
+        ],
+    },
+    // Short branches keep their target: a farther one may be out of range
+    Rule {
+        name: "jump-to-jump",
+        pattern: &["{b} {l}"],
+        replacement: &["{b} {to}"],
+        when: &[Cond::OneOf("b", LONG_BRANCHES), Cond::Label("l"), Cond::JumpsTo("l", "to")],
+    },
+    Rule {
+        name: "jump-to-return",
+        pattern: &["{b} {l}"],
+        replacement: &["RTS"],
+        when: &[Cond::OneOf("b", JUMPS), Cond::Label("l"), Cond::Returns("l")],
+    },
+];
+
+/// Hits per rule, in `RULES` order (rules sharing a name are counted together)
+#[derive(Debug, Clone, Default)]
+pub struct PeepholeStats {
+    pub hits: Vec<(&'static str, usize)>,
+}
+
+impl PeepholeStats {
+    pub fn total(&self) -> usize {
+        self.hits.iter().map(|(_, n)| n).sum()
+    }
+
+    fn record(&mut self, name: &'static str) {
+        match self.hits.iter_mut().find(|(rule, _)| *rule == name) {
+            Some((_, n)) => *n += 1,
+            None => self.hits.push((name, 1)),
+        }
+    }
+}
+
+/// Passes over the listing before giving up on reaching a fixpoint
+const MAX_PASSES: usize = 16;
+
+/// Apply `RULES` to a listing
+pub fn optimize(asm: &str) -> (String, PeepholeStats) {
+    let mut listing = Listing::parse(asm);
+    let mut stats = PeepholeStats::default();
+    for rule in RULES {
+        if !stats.hits.iter().any(|(name, _)| *name == rule.name) {
+            stats.hits.push((rule.name, 0));
+        }
+    }
+
+    for _ in 0..MAX_PASSES {
+        let mut changed = false;
+        listing.index_labels();
+        for index in 0..listing.lines.len() {
+            if listing.lines[index].removed || listing.lines[index].mnemonic.is_none() {
+                continue;
+            }
+            // Rewritten lines may match again (jump chains, new neighbours)
+            while let Some(rule) = RULES.iter().find(|rule| listing.apply(rule, index)) {
+                stats.record(rule.name);
+                changed = true;
+                if listing.lines[index].removed || listing.lines[index].mnemonic.is_none() {
+                    break;
+                }
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    (listing.render(), stats)
+}
+
+#[derive(Debug, Clone)]
+struct Line {
+    text: String,
+    label: Option<String>,
+    /// Uppercase mnemonic of an instruction or directive
+    mnemonic: Option<String>,
+    operand: String,
+    comment: Option<String>,
+    /// Global label the line's `.local` labels belong to
+    scope: String,
+    /// Bank section (`; === BANK n ===` markers)
+    section: usize,
+    removed: bool,
+}
+
+impl Line {
+    fn is_setdp(&self) -> bool {
+        self.mnemonic.as_deref() == Some("SETDP")
+    }
+
+    /// Code the assembler executes (SETDP only changes its addressing assumptions)
+    fn is_code(&self) -> bool {
+        !self.removed && self.mnemonic.is_some() && !self.is_setdp()
+    }
+
+    fn render(&self) -> String {
+        let label = self.label.as_ref().map(|l| format!("{}:", l)).unwrap_or_default();
+        let mut text = match &self.mnemonic {
+            Some(mnemonic) if self.operand.is_empty() => format!("{}    {}", label, mnemonic),
+            Some(mnemonic) => format!("{}    {} {}", label, mnemonic, self.operand),
+            None => label,
+        };
+        if let Some(comment) = &self.comment {
+            if !text.is_empty() {
+                text.push(' ');
+            }
+            text.push(';');
+            text.push_str(comment);
+        }
+        text
+    }
+}
+
+struct Listing {
+    lines: Vec<Line>,
+    /// Scoped label name -> defining line (None when defined more than once)
+    labels: HashMap<String, Option<usize>>,
+}
+
+type Bindings = Vec<(String, String)>;
+
+impl Listing {
+    fn parse(asm: &str) -> Self {
+        let mut lines = Vec::new();
+        let mut scope = String::new();
+        let mut section = 0;
+        for text in asm.lines() {
+            if text.trim_start().starts_with("; === BANK ") {
+                section += 1;
+            }
+            let (code, comment) = split_comment(text);
+            let mut line = Line {
+                text: text.to_string(),
+                label: None,
+                mnemonic: None,
+                operand: String::new(),
+                comment: comment.map(str::to_string),
+                scope: scope.clone(),
+                section,
+                removed: false,
+            };
+            let mut rest = code;
+            if !code.starts_with([' ', '\t']) && !code.trim().is_empty() && !code.starts_with('*') {
+                let end = code.find([' ', '\t']).unwrap_or(code.len());
+                let label = code[..end].trim_end_matches(':');
+                if !label.starts_with('.') {
+                    scope = label.to_string();
+                }
+                line.label = Some(label.to_string());
+                line.scope = scope.clone();
+                rest = &code[end..];
+            }
+            if code.starts_with('*') {
+                rest = "";
+            }
+            let rest = rest.trim();
+            if !rest.is_empty() {
+                let (mnemonic, operand) = rest.split_once([' ', '\t']).unwrap_or((rest, ""));
+                line.mnemonic = Some(mnemonic.to_ascii_uppercase());
+                line.operand = operand.trim().to_string();
+            }
+            lines.push(line);
+        }
+        Listing { lines, labels: HashMap::new() }
+    }
+
+    fn index_labels(&mut self) {
+        self.labels.clear();
+        for (index, line) in self.lines.iter().enumerate() {
+            let Some(label) = &line.label else { continue };
+            if matches!(line.mnemonic.as_deref(), Some("EQU" | "SET" | "=")) {
+                continue;
+            }
+            let name = scoped(label, &line.scope);
+            self.labels.entry(name).and_modify(|e| *e = None).or_insert(Some(index));
+        }
+    }
+
+    fn render(&self) -> String {
+        let mut out = String::with_capacity(self.lines.iter().map(|l| l.text.len() + 1).sum());
+        for line in self.lines.iter().filter(|l| !l.removed) {
+            out.push_str(&line.text);
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Next line from `from` that is code or defines a label
+    fn next_significant(&self, from: usize) -> Option<usize> {
+        (from..self.lines.len()).find(|&i| {
+            let line = &self.lines[i];
+            !line.removed && (line.label.is_some() || line.is_code())
+        })
+    }
+
+    /// First instruction executed when jumping to `label`
+    fn code_at(&self, label: usize) -> Option<usize> {
+        let line = &self.lines[label];
+        if line.is_code() {
+            return Some(label);
+        }
+        (label + 1..self.lines.len()).find(|&i| self.lines[i].is_code())
+    }
+
+    /// Line defining `name` as written in an operand on line `at`
+    fn resolve(&self, name: &str, at: usize) -> Option<usize> {
+        let line = &self.lines[at];
+        let target = (*self.labels.get(&scoped(name, &line.scope))?)?;
+        (self.lines[target].section == line.section).then_some(target)
+    }
+
+    fn apply(&mut self, rule: &Rule, start: usize) -> bool {
+        let mut matched = Vec::with_capacity(rule.pattern.len());
+        let mut bindings: Bindings = Vec::new();
+        let mut index = start;
+        for (k, template) in rule.pattern.iter().enumerate() {
+            if k > 0 {
+                match self.next_significant(index + 1) {
+                    Some(next) if self.lines[next].label.is_none() => index = next,
+                    _ => return false,
+                }
+            }
+            let line = &self.lines[index];
+            if !line.is_code() {
+                return false;
+            }
+            let (mnemonic, operand) = template.split_once(' ').unwrap_or((template, ""));
+            if !glob(mnemonic, line.mnemonic.as_deref().unwrap_or(""), &mut bindings)
+                || !glob(operand, &line.operand, &mut bindings)
+            {
+                return false;
+            }
+            matched.push(index);
+        }
+        let last = *matched.last().expect("rules have a pattern");
+        if !rule.when.iter().all(|cond| self.check(*cond, start, last, &mut bindings)) {
+            return false;
+        }
+
+        for (k, &index) in matched.iter().enumerate() {
+            let line = &mut self.lines[index];
+            match rule.replacement.get(k) {
+                Some(template) if *template == rule.pattern[k] => {}
+                Some(template) => {
+                    let text = substitute(template, &bindings);
+                    let (mnemonic, operand) = text.split_once(' ').unwrap_or((&text, ""));
+                    line.mnemonic = Some(mnemonic.to_string());
+                    line.operand = operand.to_string();
+                    line.text = line.render();
+                }
+                None => {
+                    let marker = line.comment.as_deref().is_some_and(|c| c.to_ascii_uppercase().contains("VPY_LINE"));
+                    line.mnemonic = None;
+                    line.operand.clear();
+                    if !marker {
+                        line.comment = None;
+                    }
+                    if line.label.is_none() && line.comment.is_none() {
+                        line.removed = true;
+                    } else {
+                        line.text = line.render();
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    fn check(&self, cond: Cond, start: usize, last: usize, bindings: &mut Bindings) -> bool {
+        let get = |name: &str, bindings: &Bindings| {
+            bindings.iter().find(|(n, _)| n == name).map(|(_, v)| v.clone()).unwrap_or_default()
+        };
+        match cond {
+            Cond::OneOf(var, values) => values.contains(&get(var, bindings).as_str()),
+            Cond::Memory(var) => is_memory(&get(var, bindings)),
+            Cond::Label(var) => {
+                let name = get(var, bindings);
+                is_label_name(&name) && self.resolve(&name, start).is_some()
+            }
+            Cond::Registers(var) => get(var, bindings)
+                .split(',')
+                .all(|r| matches!(r.trim().to_ascii_uppercase().as_str(), "A" | "B" | "D" | "X" | "Y" | "U" | "CC" | "DP")),
+            Cond::Dead(register) => {
+                let Some(next) = self.next_significant(last + 1) else { return false };
+                let line = &self.lines[next];
+                line.label.is_none()
+                    && line.mnemonic.as_deref() == Some(&format!("LD{}", register))
+                    && !reads_register(&line.operand, register)
+            }
+            Cond::LabelFollows(var) => {
+                let target = scoped(&get(var, bindings), &self.lines[start].scope);
+                let mut index = last + 1;
+                while let Some(next) = self.next_significant(index) {
+                    let line = &self.lines[next];
+                    match &line.label {
+                        Some(label) if scoped(label, &line.scope) == target => return true,
+                        Some(_) if !line.is_code() => index = next + 1,
+                        _ => return false,
+                    }
+                }
+                false
+            }
+            Cond::JumpsTo(from, to) => {
+                let Some(mut label) = self.resolve(&get(from, bindings), start) else { return false };
+                let mut seen = HashSet::from([label]);
+                while let Some(code) = self.code_at(label) {
+                    let line = &self.lines[code];
+                    if !JUMPS.contains(&line.mnemonic.as_deref().unwrap_or("")) || !is_label_name(&line.operand) {
+                        break;
+                    }
+                    match self.resolve(&line.operand, code) {
+                        Some(next) if seen.insert(next) => label = next,
+                        _ => break,
+                    }
+                }
+                if seen.len() < 2 {
+                    return false;
+                }
+                // Name the final label as the branch has to write it
+                let target = &self.lines[label];
+                let name = target.label.clone().unwrap_or_default();
+                if name.starts_with('.') && target.scope != self.lines[start].scope {
+                    return false;
+                }
+                bindings.push((to.to_string(), name));
+                true
+            }
+            Cond::Returns(var) => self
+                .resolve(&get(var, bindings), start)
+                .and_then(|label| self.code_at(label))
+                .is_some_and(|code| self.lines[code].mnemonic.as_deref() == Some("RTS")),
+            Cond::Inverse(var, inverse) => {
+                let cc = get(var, bindings);
+                let found = INVERSE_CONDITIONS.iter().find_map(|&(a, b)| {
+                    if a == cc { Some(b) } else if b == cc { Some(a) } else { None }
+                });
+                match found {
+                    Some(opposite) => {
+                        bindings.push((inverse.to_string(), opposite.to_string()));
+                        true
+                    }
+                    None => false,
+                }
+            }
+        }
+    }
+}
+
+/// Code and comment of a line (the assembler cuts comments at the first ';' outside quotes)
+fn split_comment(text: &str) -> (&str, Option<&str>) {
+    let mut quote = None;
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, ';') => return (&text[..i], Some(&text[i + 1..])),
+            _ => {}
+        }
+    }
+    (text, None)
+}
+
+fn scoped(label: &str, scope: &str) -> String {
+    if label.starts_with('.') {
+        format!("{}{}", scope, label)
+    } else {
+        label.to_string()
+    }
+}
+
+fn is_label_name(text: &str) -> bool {
+    let name = text.strip_prefix('.').unwrap_or(text);
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// A RAM operand: address expression without immediate, indexing or I/O registers
+fn is_memory(operand: &str) -> bool {
+    let address = operand.trim_start_matches(['<', '>']);
+    if address.is_empty() || address.contains(['#', ',', '[']) {
+        return false;
+    }
+    if address.to_ascii_uppercase().starts_with("VIA_") {
+        return false;
+    }
+    let numeric = match address.strip_prefix('$') {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => address.parse::<u16>().ok(),
+    };
+    // $D000-$DFFF is the VIA
+    !numeric.is_some_and(|a| (0xD000..0xE000).contains(&a))
+}
+
+/// Whether an indexed operand reads `register` (`D,X` / `,X+`)
+fn reads_register(operand: &str, register: &str) -> bool {
+    let operand = operand.trim_start_matches('[').trim_end_matches(']');
+    let Some((offset, base)) = operand.split_once(',') else { return false };
+    let uses = |part: &str| part.trim_matches(['+', '-', ' ']).eq_ignore_ascii_case(register);
+    match register {
+        // D is read through an accumulator offset, which A and B are part of
+        "D" => ["A", "B", "D"].iter().any(|r| offset.trim().eq_ignore_ascii_case(r)),
+        _ => uses(offset) || uses(base),
+    }
+}
+
+/// Match `text` against a template with `{name}` placeholders
+fn glob(template: &str, text: &str, bindings: &mut Bindings) -> bool {
+    let Some(open) = template.find('{') else { return template == text };
+    if !text.starts_with(&template[..open]) {
+        return false;
+    }
+    let close = open + template[open..].find('}').expect("unclosed placeholder");
+    let name = &template[open + 1..close];
+    let (template_rest, text_rest) = (&template[close + 1..], &text[open..]);
+
+    if let Some(value) = bindings.iter().find(|(n, _)| n == name).map(|(_, v)| v.clone()) {
+        return text_rest.starts_with(&value) && glob(template_rest, &text_rest[value.len()..], bindings);
+    }
+    for end in (1..=text_rest.len()).filter(|&end| text_rest.is_char_boundary(end)) {
+        let saved = bindings.len();
+        bindings.push((name.to_string(), text_rest[..end].to_string()));
+        if glob(template_rest, &text_rest[end..], bindings) {
+            return true;
+        }
+        bindings.truncate(saved);
+    }
+    false
+}
+
+fn substitute(template: &str, bindings: &Bindings) -> String {
+    let mut out = template.to_string();
+    for (name, value) in bindings {
+        out = out.replace(&format!("{{{}}}", name), value);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hits(stats: &PeepholeStats, rule: &str) -> usize {
+        stats.hits.iter().find(|(n, _)| *n == rule).map_or(0, |(_, n)| *n)
+    }
+
+    #[test]
+    fn test_store_reload_keeps_markers() {
+        let asm = "    STD RESULT\n    ; VPy_LINE:12\n    LDD RESULT\n    STD VAR_X\n";
+        let (out, stats) = optimize(asm);
+        assert_eq!(out, "    STD RESULT\n    ; VPy_LINE:12\n    STD VAR_X\n");
+        assert_eq!(hits(&stats, "store-reload"), 1);
+
+        // I/O registers and indexed operands are left alone
+        let asm = "    STA VIA_port_a\n    LDA VIA_port_a\n    STD ,X\n    LDD ,X\n";
+        assert_eq!(optimize(asm).0, asm);
+    }
+
+    #[test]
+    fn test_program_keeps_line_markers() {
+        let source = "x = 0\ny = 0\n\ndef main():\n    x = 5\n    y = x + 1\n    x = y\n\ndef loop():\n    pass\n";
+        let tokens = vpy_parser::lex(source).unwrap();
+        let module = vpy_parser::parser::parse(tokens, "test.vpy").unwrap();
+        let asm = super::super::generate_m6809_asm(&module, "T", 32768, 32768, &[]).unwrap();
+        let (out, stats) = optimize(&asm);
+        assert!(hits(&stats, "store-reload") > 0, "{}", out);
+        let markers = |text: &str| text.lines().filter(|l| l.trim_start().starts_with("; VPy_LINE:")).map(str::to_string).collect::<Vec<_>>();
+        assert_eq!(markers(&out), markers(&asm));
+        for line in 5..=7 {
+            assert!(out.contains(&format!("    ; VPy_LINE:{}\n", line)), "line {} missing:\n{}", line, out);
+        }
+    }
+
+    #[test]
+    fn test_labels_block_windows() {
+        let asm = "    STD VAR_X\nLOOP:\n    LDD VAR_X\n";
+        assert_eq!(optimize(asm).0, asm);
+    }
+
+    #[test]
+    fn test_load_transfer_needs_dead_d() {
+        let asm = "    LDD #VAR_ARR_DATA\n    TFR D,X\n    LDD TMPPTR\n    LEAX D,X\n";
+        assert_eq!(optimize(asm).0, "    LDX #VAR_ARR_DATA\n    LDD TMPPTR\n    LEAX D,X\n");
+
+        // D is read by the next instruction
+        let asm = "    LDD #VAR_ARR_DATA\n    TFR D,X\n    LDD D,X\n";
+        assert_eq!(optimize(asm).0, asm);
+        let asm = "    LDD #4\n    TFR D,X\n    STD VAR_Y\n";
+        assert_eq!(optimize(asm).0, asm);
+    }
+
+    #[test]
+    fn test_branches() {
+        // Branches over jumps are inverted, then follow the jump chain
+        let asm = concat!(
+            "F:\n",
+            "    LDD VAR_X\n",
+            "    LBNE .SKIP\n",
+            "    LBRA IF_END\n",
+            ".SKIP:\n",
+            "    JSR G\n",
+            "IF_END:\n",
+            "    LBRA DONE\n",
+            "DONE:\n",
+            "    SETDP $C8\n",
+            "    RTS\n",
+        );
+        let (out, stats) = optimize(asm);
+        assert_eq!(
+            out,
+            concat!(
+                "F:\n",
+                "    LDD VAR_X\n",
+                "    LBEQ DONE\n",
+                ".SKIP:\n",
+                "    JSR G\n",
+                "IF_END:\n",
+                "DONE:\n",
+                "    SETDP $C8\n",
+                "    RTS\n",
+            )
+        );
+        assert_eq!(hits(&stats, "branch-over-jump"), 1);
+        assert_eq!(hits(&stats, "jump-to-jump"), 1);
+        assert_eq!(hits(&stats, "branch-to-next"), 1);
+
+        let asm = "    LBEQ A1\n    NOP\nA1:\n    LBRA A2\nA2B:\n    NOP\nA2:\n    RTS\n    BRA A1\n";
+        let (out, stats) = optimize(asm);
+        assert!(out.starts_with("    LBEQ A2\n"), "{}", out);
+        assert!(out.ends_with("    RTS\n"), "{}", out);
+        assert_eq!(hits(&stats, "jump-to-jump"), 1);
+        assert!(hits(&stats, "jump-to-return") >= 1);
+    }
+
+    #[test]
+    fn test_push_pull_pair() {
+        let asm = "    PSHS D\n    PULS D\n    PSHS PC\n    PULS PC\n";
+        assert_eq!(optimize(asm).0, "    PSHS PC\n    PULS PC\n");
+    }
+
+    #[test]
+    fn test_rules_never_grow_code() {
+        for rule in RULES {
+            assert!(rule.replacement.len() <= rule.pattern.len(), "{}", rule.name);
+        }
+    }
+}
@@ -2,6 +2,7 @@
 // Ensures no collisions and compact memory usage
 
 use std::collections::HashMap;
+use serde::Serialize;
 
 #[derive(Debug, Clone)]
 pub struct RamVar {
@@ -10,6 +11,17 @@ pub struct RamVar {
     pub comment: String,
 }
 
+/// A RAM variable read back from the generated EQU block (for link maps)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RamSlot {
+    pub name: String,
+    pub address: u16,
+    pub size: usize,
+    pub comment: String,
+    /// Placed with `allocate_fixed` rather than in the compact region
+    pub fixed: bool,
+}
+
 pub struct RamLayout {
     base_address: u16,
     current_offset: usize,
@@ -58,6 +70,20 @@ impl RamLayout {
         offset
     }
     
+    /// Place a variable `offset` bytes into a block allocated earlier.
+    /// Overlaid variables may share bytes (locals of functions that never run together)
+    pub fn allocate_overlay(&mut self, block: &str, offset: usize, name: impl Into<String>, size: usize, comment: impl Into<String>) -> Option<usize> {
+        let block_offset = self.get_offset(block)?;
+        let name = name.into();
+        self.vars.push(RamVar {
+            name: name.clone(),
+            size,
+            comment: comment.into(),
+        });
+        self.offsets.insert(name, block_offset + offset);
+        Some(block_offset + offset)
+    }
+    
     /// Allocate a variable at a fixed absolute address (outside the compact region)
     /// Emits an EQU with the absolute address but does NOT reserve storage in ORG block.
     pub fn allocate_fixed(&mut self, name: impl Into<String>, address: u16, size: usize, comment: impl Into<String>) {
@@ -144,6 +170,50 @@ impl RamLayout {
     }
 }
 
+/// Parse the variables written by `emit_equ_definitions` back out of a listing
+pub fn parse_equ_definitions(asm: &str) -> Vec<RamSlot> {
+    let mut slots: Vec<RamSlot> = Vec::new();
+    for line in asm.lines() {
+        let Some((code, comment)) = line.split_once(';') else { continue };
+        let mut words = code.split_whitespace();
+        let (Some(name), Some("EQU"), Some(value), None) = (words.next(), words.next(), words.next(), words.next()) else {
+            continue;
+        };
+        let (address, fixed) = match value.split_once('+') {
+            Some((base, offset)) => match (parse_hex(base), parse_hex(offset)) {
+                (Some(base), Some(offset)) => (base.wrapping_add(offset), false),
+                _ => continue,
+            },
+            None => match parse_hex(value) {
+                Some(address) => (address, true),
+                None => continue,
+            },
+        };
+        let Some(size) = size_in_comment(comment) else { continue };
+        if !(0xC800..=0xCFFF).contains(&address) || slots.iter().any(|s| s.name == name) {
+            continue;
+        }
+        let comment = comment.trim();
+        let comment = comment.strip_suffix(&format!("({} bytes)", size)).unwrap_or(comment).trim_end();
+        slots.push(RamSlot { name: name.to_string(), address, size, comment: comment.to_string(), fixed });
+    }
+    slots
+}
+
+fn parse_hex(text: &str) -> Option<u16> {
+    u16::from_str_radix(text.strip_prefix('$')?, 16).ok()
+}
+
+/// `(N bytes)` at the end of an EQU comment
+fn size_in_comment(comment: &str) -> Option<usize> {
+    let start = comment.rfind('(')?;
+    let words: Vec<&str> = comment[start + 1..].split_whitespace().collect();
+    match words.as_slice() {
+        [count, unit, ..] if unit.starts_with("byte") => count.parse().ok(),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -193,4 +263,32 @@ mod tests {
             layout.get_address("NUM_STR")
         );
     }
+    
+    #[test]
+    fn test_overlay_inside_block() {
+        let mut layout = RamLayout::new(0xC880);
+        layout.allocate("RESULT", 2, "Main temporary");
+        layout.allocate("FRAMES", 4, "Overlaid locals");
+        assert_eq!(layout.allocate_overlay("FRAMES", 2, "LOC_A__I", 2, "a: i"), Some(4));
+        assert_eq!(layout.allocate_overlay("FRAMES", 2, "LOC_B__J", 2, "b: j"), Some(4));
+        assert_eq!(layout.allocate_overlay("MISSING", 0, "LOC_C__K", 2, "c: k"), None);
+        assert_eq!(layout.get_address("LOC_B__J"), Some(0xC884));
+        // Overlays do not grow the region
+        assert_eq!(layout.allocate("NEXT", 1, "After the block"), 6);
+    }
+    
+    #[test]
+    fn test_parse_equ_definitions() {
+        let mut layout = RamLayout::new(0xC880);
+        layout.allocate("RESULT", 2, "Main temporary");
+        layout.allocate("VAR_TABLE_DATA", 16, "Mutable array 'table' (8 elements x 2 bytes)");
+        layout.allocate_fixed("VAR_ARG0", 0xCFE0, 2, "Function argument 0 (16-bit)");
+        let asm = format!("SFX_SLOT_COUNT EQU 1\n{}", layout.emit_equ_definitions());
+
+        let slots = parse_equ_definitions(&asm);
+        assert_eq!(slots.len(), 3);
+        assert_eq!((slots[1].address, slots[1].size, slots[1].fixed), (0xC882, 16, false));
+        assert_eq!(slots[1].comment, "Mutable array 'table' (8 elements x 2 bytes)");
+        assert_eq!((slots[2].name.as_str(), slots[2].address, slots[2].fixed), ("VAR_ARG0", 0xCFE0, true));
+    }
 }
@@ -0,0 +1,176 @@
+//! Unit Test Harness Support
+//!
+//! `assert cond` statements and `def test_*()` functions are run headless by
+//! `vpy_cli test`. Results are reported through a small RAM mailbox:
+//!
+//! - `TEST_STATUS` (1 byte): 0 = running, 1 = passed, 2 = failed
+//! - `TEST_LINE` (2 bytes): VPy source line of the failed assert
+//!
+//! The mailbox is the first allocation in the RAM layout so its address is
+//! stable across programs. A failed assert stores the line, sets the status
+//! and spins forever so the test runner can read the result.
+
+use std::cell::Cell;
+use vpy_parser::{Expr, Item, Module, Stmt};
+use super::expressions;
+use super::functions::fresh_label;
+use crate::AssetInfo;
+
+/// Address of `TEST_STATUS` (first byte of the VPy RAM region)
+pub const TEST_STATUS_ADDR: u16 = 0xC880;
+/// Address of `TEST_LINE` (16-bit, big-endian)
+pub const TEST_LINE_ADDR: u16 = 0xC881;
+
+pub const TEST_RUNNING: u8 = 0;
+pub const TEST_PASSED: u8 = 1;
+pub const TEST_FAILED: u8 = 2;
+
+thread_local! {
+    /// Set while generating a test ROM (main() calls a single test function)
+    static TEST_HARNESS: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Enable/disable test-ROM generation for the current thread
+pub fn set_test_harness(enabled: bool) {
+    TEST_HARNESS.with(|h| h.set(enabled));
+}
+
+pub fn is_test_harness() -> bool {
+    TEST_HARNESS.with(|h| h.get())
+}
+
+/// True if the mailbox must be allocated (test ROM or any `assert` in the program)
+pub fn needs_mailbox(module: &Module) -> bool {
+    is_test_harness() || has_asserts(module)
+}
+
+/// Check if any function in the module contains an `assert` statement
+pub fn has_asserts(module: &Module) -> bool {
+    fn check(stmts: &[Stmt]) -> bool {
+        stmts.iter().any(|stmt| match stmt {
+            Stmt::Assert { .. } => true,
+            Stmt::If { body, elifs, else_body, .. } => {
+                check(body)
+                    || elifs.iter().any(|(_, b)| check(b))
+                    || else_body.as_ref().is_some_and(|b| check(b))
+            }
+            Stmt::While { body, .. } | Stmt::For { body, .. } | Stmt::ForIn { body, .. } => check(body),
+            Stmt::Switch { cases, default, .. } => {
+                cases.iter().any(|(_, b)| check(b)) || default.as_ref().is_some_and(|b| check(b))
+            }
+            _ => false,
+        })
+    }
+
+    module.items.iter().any(|item| match item {
+        Item::Function(func) => check(&func.body),
+        Item::StructDef(def) => def.methods.iter().any(|m| check(&m.body)),
+        _ => false,
+    })
+}
+
+/// Build the module for one test ROM: `main()` calls `test_name` and nothing else,
+/// `loop()` is dropped so the program idles on Wait_Recal once the test returns.
+pub fn build_test_module(module: &Module, test_name: &str) -> Module {
+    let mut items: Vec<Item> = module
+        .items
+        .iter()
+        .filter(|item| {
+            !matches!(item, Item::Function(f) if matches!(f.name.to_uppercase().as_str(), "MAIN" | "LOOP"))
+        })
+        .cloned()
+        .collect();
+
+    let line = module
+        .items
+        .iter()
+        .find_map(|item| match item {
+            Item::Function(f) if f.name == test_name => Some(f.line),
+            _ => None,
+        })
+        .unwrap_or(0);
+
+    items.push(Item::Function(vpy_parser::Function {
+        name: "MAIN".to_string(),
+        line,
+        params: Vec::new(),
+        body: vec![Stmt::Expr(
+            Expr::Call(vpy_parser::CallInfo {
+                name: test_name.to_string(),
+                source_line: line,
+                col: 0,
+                args: Vec::new(),
+            }),
+            line,
+        )],
+    }));
+
+    Module {
+        items,
+        meta: module.meta.clone(),
+        imports: module.imports.clone(),
+    }
+}
+
+/// `TEST_STATUS = running` (emitted at START)
+pub fn emit_mailbox_init(asm: &mut String) {
+    asm.push_str("    CLR >TEST_STATUS        ; Test mailbox: running\n");
+}
+
+/// `TEST_STATUS = passed` (emitted after main() in test ROMs)
+pub fn emit_test_passed(asm: &mut String) {
+    asm.push_str(&format!("    LDA #{}\n", TEST_PASSED));
+    asm.push_str("    STA >TEST_STATUS        ; Test mailbox: passed\n");
+}
+
+/// `assert cond`: on failure store the source line, mark failed and halt
+pub fn emit_assert(cond: &Expr, source_line: usize, asm: &mut String, assets: &[AssetInfo]) {
+    let ok = fresh_label("ASSERT_OK");
+    let halt = fresh_label("ASSERT_HALT");
+    expressions::emit_branch_if_true(cond, &ok, asm, assets);
+    asm.push_str(&format!("    LDD #{}\n", source_line));
+    asm.push_str("    STD >TEST_LINE\n");
+    asm.push_str(&format!("    LDA #{}\n", TEST_FAILED));
+    asm.push_str(&format!("    STA >TEST_STATUS        ; assert failed (line {})\n", source_line));
+    asm.push_str(&format!("{}:\n    BRA {}\n", halt, halt));
+    asm.push_str(&format!("{}:\n", ok));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Module {
+        let tokens = vpy_parser::lex(source).unwrap();
+        vpy_parser::parser::parse(tokens, "test.vpy").unwrap()
+    }
+
+    #[test]
+    fn test_build_test_module_replaces_main_and_loop() {
+        let module = parse(
+            "def main():\n    pass\n\ndef loop():\n    pass\n\ndef test_one():\n    assert 1 == 1\n",
+        );
+        assert!(has_asserts(&module));
+
+        let test_module = build_test_module(&module, "test_one");
+        let names: Vec<&str> = test_module
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                Item::Function(f) => Some(f.name.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(names, vec!["test_one", "MAIN"]);
+    }
+
+    #[test]
+    fn test_mailbox_only_allocated_when_needed() {
+        let plain = parse("def main():\n    pass\n");
+        assert!(!needs_mailbox(&plain));
+
+        set_test_harness(true);
+        assert!(needs_mailbox(&plain));
+        set_test_harness(false);
+    }
+}
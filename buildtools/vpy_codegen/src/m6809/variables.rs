@@ -1,18 +1,22 @@
 use vpy_parser::{Module, Item, Expr, Stmt, AssignTarget};
-use std::collections::HashSet;
+use std::collections::HashMap;
 use super::ram_layout::RamLayout;
+use super::locals::{self, LocalsPlan};
+
+/// Weight of a use inside a loop relative to one outside it
+const LOOP_WEIGHT: u32 = 8;
 
 /// Generate user variables using a RamLayout that already has system variables allocated
 /// Returns ASM string for array data sections (not EQU definitions - those come from RamLayout)
 pub fn generate_user_variables(module: &Module, ram: &mut RamLayout) -> Result<String, String> {
     let asm = String::new();
-    let mut vars = HashSet::new();
+    let mut vars = HashMap::new();
     let mut mutable_arrays: Vec<(String, usize)> = Vec::new();  // (name, element_count) for arrays that need RAM
     
     // Collect all variable names from module (GlobalLet items)
     for item in &module.items {
         if let Item::GlobalLet { name, value, .. } = item {
-            vars.insert(name.clone());
+            vars.entry(name.clone()).or_insert(0);
             
             // Check if this is an array initialization (mutable array, needs RAM)
             if let Expr::List(elements) = value {
@@ -21,24 +25,65 @@ pub fn generate_user_variables(module: &Module, ram: &mut RamLayout) -> Result<S
         }
     }
     
+    // Parameters and locals get overlaid frame slots (see locals.rs)
+    super::context::with_locals(|plan| {
+        if plan.size == 0 {
+            return;
+        }
+        ram.allocate(locals::BLOCK_LABEL, plan.size, "Function locals, overlaid along the call graph");
+        for frame in plan.frames.values() {
+            for name in &frame.slots {
+                let offset = frame.slot_offset(name).unwrap_or(frame.offset);
+                ram.allocate_overlay(
+                    locals::BLOCK_LABEL,
+                    offset,
+                    LocalsPlan::label(&frame.function, name),
+                    locals::SLOT_SIZE,
+                    format!("Local '{}' of {}", name.to_lowercase(), frame.function),
+                );
+            }
+        }
+    });
+
+    // Coroutines keep their resume point and the locals live across `yield` (see coroutines.rs)
+    super::context::with_locals(|plan| {
+        for co in plan.coroutines.values() {
+            ram.allocate(co.state_label(), 1, format!("Resume point of coroutine {}", co.function));
+            for name in &co.saved {
+                ram.allocate(
+                    co.saved_label(name),
+                    locals::SLOT_SIZE,
+                    format!("Local '{}' of coroutine {}", name.to_lowercase(), co.function),
+                );
+            }
+        }
+    });
+    
     // CRITICAL FIX: Also collect all identifiers used in functions
-    // This includes function parameters and local variables
-    // Treat them all as globals for now (simple solution)
+    // Names a function keeps in its frame are not globals
     for item in &module.items {
         if let Item::Function(func) = item {
-            // Collect parameters - they are Vec<String> not Vec<Param>
-            for param in &func.params {
-                vars.insert(param.clone());
-            }
-            
-            // Collect local variables from function body
-            collect_identifiers_from_stmts(&func.body, &mut vars);
+            // Collect identifiers from function body
+            // (loop() runs every frame, so its body counts as a loop)
+            let depth = if func.name.eq_ignore_ascii_case("loop") { 1 } else { 0 };
+            let mut used = HashMap::new();
+            collect_identifiers_from_stmts(&func.body, depth, &mut used);
+            super::context::with_locals(|plan| {
+                for (name, weight) in used {
+                    // CO_START(f, ...) names a coroutine, not a variable
+                    if !plan.is_local(&func.name, &name) && plan.coroutine(&name).is_none() {
+                        *vars.entry(name).or_insert(0) += weight;
+                    }
+                }
+            });
         }
     }
     
     // Allocate all user variables using RamLayout
-    // This ensures no collisions with system variables
-    for var in vars.iter() {
+    // This ensures no collisions with system variables.
+    // Most used first (uses in loops weigh more) so the hot ones land on the
+    // $C8xx page and assemble as direct accesses (see direct_page.rs)
+    for var in hot_first(&vars) {
         // Variables use uppercase labels for consistency with array/const naming
         ram.allocate(&format!("VAR_{}", var.to_uppercase()), 2, &format!("User variable: {}", var));
     }
@@ -68,6 +113,19 @@ pub fn generate_user_variables(module: &Module, ram: &mut RamLayout) -> Result<S
     Ok(asm)
 }
 
+/// Variable names by descending use weight (ties by name), one per label
+fn hot_first(vars: &HashMap<String, u32>) -> Vec<&String> {
+    let mut by_label: HashMap<String, (&String, u32)> = HashMap::new();
+    for (name, weight) in vars {
+        let entry = by_label.entry(name.to_uppercase()).or_insert((name, 0));
+        entry.0 = entry.0.min(name);
+        entry.1 += weight;
+    }
+    let mut ordered: Vec<(&String, u32)> = by_label.into_values().collect();
+    ordered.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    ordered.into_iter().map(|(name, _)| name).collect()
+}
+
 /// Emit array data sections (must be called AFTER EQU definitions, BEFORE code)
 /// Arrays stored in ROM with ARRAY_{name}_DATA labels
 /// At runtime, main() initializes VAR_{name} (RAM pointer) to point to this ROM data
@@ -209,13 +267,13 @@ pub fn emit_array_aliases(module: &Module) -> String {
 /// Use generate_user_variables() instead with RamLayout parameter
 pub fn generate_variables(module: &Module) -> Result<String, String> {
     let mut asm = String::new();
-    let mut vars = HashSet::new();
+    let mut vars = HashMap::new();
     let mut arrays = Vec::new();  // Track arrays for data generation
     
     // Collect all variable names from module (GlobalLet items)
     for item in &module.items {
         if let Item::GlobalLet { name, value, .. } = item {
-            vars.insert(name.clone());
+            vars.entry(name.clone()).or_insert(0);
             
             // Check if this is an array initialization
             if matches!(value, Expr::List(_)) {
@@ -231,11 +289,11 @@ pub fn generate_variables(module: &Module) -> Result<String, String> {
         if let Item::Function(func) = item {
             // Collect parameters - they are Vec<String> not Vec<Param>
             for param in &func.params {
-                vars.insert(param.clone());
+                vars.entry(param.clone()).or_insert(0);
             }
             
             // Collect local variables from function body
-            collect_identifiers_from_stmts(&func.body, &mut vars);
+            collect_identifiers_from_stmts(&func.body, 0, &mut vars);
         }
     }
     
@@ -246,7 +304,7 @@ pub fn generate_variables(module: &Module) -> Result<String, String> {
         asm.push_str(";***************************************************************************\n");
         
         let mut offset = 0;
-        for var in vars.iter() {
+        for var in vars.keys() {
             // Variables use uppercase labels for consistency with array/const naming
             asm.push_str(&format!("VAR_{} EQU $CF10+{}\n", var.to_uppercase(), offset));
             offset += 2;  // 16-bit variables
@@ -297,81 +355,93 @@ pub fn generate_variables(module: &Module) -> Result<String, String> {
 }
 
 /// Recursively collect all identifiers from statements
-/// This captures local variables and any identifiers used in expressions
-fn collect_identifiers_from_stmts(stmts: &[Stmt], vars: &mut HashSet<String>) {
+/// This captures local variables and any identifiers used in expressions,
+/// counting each use (weighted by loop nesting `depth`)
+fn collect_identifiers_from_stmts(stmts: &[Stmt], depth: u32, vars: &mut HashMap<String, u32>) {
+    let weight = LOOP_WEIGHT.saturating_pow(depth);
     for stmt in stmts {
         match stmt {
             Stmt::Assign { target, value, .. } => {
                 // Collect from assignment target
                 match target {
                     AssignTarget::Ident { name, .. } => {
-                        vars.insert(name.clone());
+                        *vars.entry(name.clone()).or_insert(0) += weight;
                     }
                     AssignTarget::Index { target, .. } => {
-                        collect_identifiers_from_expr(target, vars);
+                        collect_identifiers_from_expr(target, weight, vars);
                     }
                     AssignTarget::FieldAccess { target, .. } => {
-                        collect_identifiers_from_expr(target, vars);
+                        collect_identifiers_from_expr(target, weight, vars);
                     }
                 }
                 
                 // Collect from value expression
-                collect_identifiers_from_expr(value, vars);
+                collect_identifiers_from_expr(value, weight, vars);
             }
             Stmt::Let { name, value, .. } => {
-                vars.insert(name.clone());
-                collect_identifiers_from_expr(value, vars);
+                *vars.entry(name.clone()).or_insert(0) += weight;
+                collect_identifiers_from_expr(value, weight, vars);
             }
             Stmt::If { cond, body, elifs, else_body, .. } => {
-                collect_identifiers_from_expr(cond, vars);
-                collect_identifiers_from_stmts(body, vars);
+                collect_identifiers_from_expr(cond, weight, vars);
+                collect_identifiers_from_stmts(body, depth, vars);
                 for (elif_cond, elif_body) in elifs {
-                    collect_identifiers_from_expr(elif_cond, vars);
-                    collect_identifiers_from_stmts(elif_body, vars);
+                    collect_identifiers_from_expr(elif_cond, weight, vars);
+                    collect_identifiers_from_stmts(elif_body, depth, vars);
                 }
                 if let Some(else_stmts) = else_body {
-                    collect_identifiers_from_stmts(else_stmts, vars);
+                    collect_identifiers_from_stmts(else_stmts, depth, vars);
                 }
             }
             Stmt::While { cond, body, .. } => {
-                collect_identifiers_from_expr(cond, vars);
-                collect_identifiers_from_stmts(body, vars);
+                collect_identifiers_from_expr(cond, weight, vars);
+                collect_identifiers_from_stmts(body, depth + 1, vars);
             }
             Stmt::For { var, start, end, step, body, .. } => {
-                vars.insert(var.clone());
-                collect_identifiers_from_expr(start, vars);
-                collect_identifiers_from_expr(end, vars);
+                *vars.entry(var.clone()).or_insert(0) += weight;
+                collect_identifiers_from_expr(start, weight, vars);
+                collect_identifiers_from_expr(end, weight, vars);
                 if let Some(step_expr) = step {
-                    collect_identifiers_from_expr(step_expr, vars);
+                    collect_identifiers_from_expr(step_expr, weight, vars);
                 }
-                collect_identifiers_from_stmts(body, vars);
+                collect_identifiers_from_stmts(body, depth + 1, vars);
             }
             Stmt::ForIn { var, iterable, body, .. } => {
-                vars.insert(var.clone());
-                collect_identifiers_from_expr(iterable, vars);
-                collect_identifiers_from_stmts(body, vars);
+                *vars.entry(var.clone()).or_insert(0) += weight;
+                collect_identifiers_from_expr(iterable, weight, vars);
+                collect_identifiers_from_stmts(body, depth + 1, vars);
+            }
+            Stmt::Switch { expr, cases, default, .. } => {
+                collect_identifiers_from_expr(expr, weight, vars);
+                for (case_expr, case_body) in cases {
+                    collect_identifiers_from_expr(case_expr, weight, vars);
+                    collect_identifiers_from_stmts(case_body, depth, vars);
+                }
+                if let Some(default_body) = default {
+                    collect_identifiers_from_stmts(default_body, depth, vars);
+                }
             }
             Stmt::Return(value, _) => {
                 if let Some(expr) = value {
-                    collect_identifiers_from_expr(expr, vars);
+                    collect_identifiers_from_expr(expr, weight, vars);
                 }
             }
-            Stmt::Expr(expr, _) => {
-                collect_identifiers_from_expr(expr, vars);
+            Stmt::Expr(expr, _) | Stmt::Assert { cond: expr, .. } => {
+                collect_identifiers_from_expr(expr, weight, vars);
             }
             Stmt::CompoundAssign { target, value, .. } => {
                 match target {
                     AssignTarget::Ident { name, .. } => {
-                        vars.insert(name.clone());
+                        *vars.entry(name.clone()).or_insert(0) += weight;
                     }
                     AssignTarget::Index { target, .. } => {
-                        collect_identifiers_from_expr(target, vars);
+                        collect_identifiers_from_expr(target, weight, vars);
                     }
                     AssignTarget::FieldAccess { target, .. } => {
-                        collect_identifiers_from_expr(target, vars);
+                        collect_identifiers_from_expr(target, weight, vars);
                     }
                 }
-                collect_identifiers_from_expr(value, vars);
+                collect_identifiers_from_expr(value, weight, vars);
             }
             _ => {}
         }
@@ -379,53 +449,72 @@ fn collect_identifiers_from_stmts(stmts: &[Stmt], vars: &mut HashSet<String>) {
 }
 
 /// Recursively collect identifiers from an expression
-fn collect_identifiers_from_expr(expr: &Expr, vars: &mut HashSet<String>) {
+fn collect_identifiers_from_expr(expr: &Expr, weight: u32, vars: &mut HashMap<String, u32>) {
     match expr {
         Expr::Ident(id) => {
-            vars.insert(id.name.clone());
+            *vars.entry(id.name.clone()).or_insert(0) += weight;
         }
         Expr::Binary { left, right, .. } => {
-            collect_identifiers_from_expr(left, vars);
-            collect_identifiers_from_expr(right, vars);
+            collect_identifiers_from_expr(left, weight, vars);
+            collect_identifiers_from_expr(right, weight, vars);
         }
         Expr::Compare { left, right, .. } => {
-            collect_identifiers_from_expr(left, vars);
-            collect_identifiers_from_expr(right, vars);
+            collect_identifiers_from_expr(left, weight, vars);
+            collect_identifiers_from_expr(right, weight, vars);
         }
         Expr::Logic { left, right, .. } => {
-            collect_identifiers_from_expr(left, vars);
-            collect_identifiers_from_expr(right, vars);
+            collect_identifiers_from_expr(left, weight, vars);
+            collect_identifiers_from_expr(right, weight, vars);
         }
         Expr::Not(operand) => {
-            collect_identifiers_from_expr(operand, vars);
+            collect_identifiers_from_expr(operand, weight, vars);
         }
         Expr::BitNot(operand) => {
-            collect_identifiers_from_expr(operand, vars);
+            collect_identifiers_from_expr(operand, weight, vars);
         }
         Expr::Call(call_info) => {
             // CallInfo has name field, not func
             for arg in &call_info.args {
-                collect_identifiers_from_expr(arg, vars);
+                collect_identifiers_from_expr(arg, weight, vars);
             }
         }
         Expr::MethodCall(method_info) => {
-            collect_identifiers_from_expr(&method_info.target, vars);
+            collect_identifiers_from_expr(&method_info.target, weight, vars);
             for arg in &method_info.args {
-                collect_identifiers_from_expr(arg, vars);
+                collect_identifiers_from_expr(arg, weight, vars);
             }
         }
         Expr::Index { target, index, .. } => {
-            collect_identifiers_from_expr(target, vars);
-            collect_identifiers_from_expr(index, vars);
+            collect_identifiers_from_expr(target, weight, vars);
+            collect_identifiers_from_expr(index, weight, vars);
         }
         Expr::FieldAccess { target, .. } => {
-            collect_identifiers_from_expr(target, vars);
+            collect_identifiers_from_expr(target, weight, vars);
         }
         Expr::List(elements) => {
             for elem in elements {
-                collect_identifiers_from_expr(elem, vars);
+                collect_identifiers_from_expr(elem, weight, vars);
             }
         }
         _ => {}
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hot_variables_first() {
+        let mut vars = HashMap::new();
+        vars.insert("cold".to_string(), 0);
+        vars.insert("warm".to_string(), 3);
+        vars.insert("hot".to_string(), 8);
+        vars.insert("alpha".to_string(), 3);
+        // Same label after uppercasing: merged into one variable
+        vars.insert("HOT".to_string(), 1);
+
+        let order: Vec<&str> = hot_first(&vars).into_iter().map(|s| s.as_str()).collect();
+        assert_eq!(order, vec!["HOT", "alpha", "warm", "cold"]);
+    }
+}
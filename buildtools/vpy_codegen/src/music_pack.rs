@@ -0,0 +1,510 @@
+//! Packed music streams (`"compression": "packed"` in a .vmus)
+//!
+//! Same register timeline as the plain `FCB delay, count, (reg, val)*` list,
+//! stored as tokens that AUDIO_UPDATE decodes in place (`AU_PACKED_FRAME`):
+//!
+//! - `$00`: end of song
+//! - `$01-$7F`: n frames without register changes
+//! - `%10hhhhhh llllllll`: one frame. 14-bit mask of the registers written
+//!   (bit 0 = R0), then their values in register order. Registers that keep
+//!   their value are left out, except R13: writing it restarts the envelope
+//! - `%11nnnnnn` + `FDB addr`: play the n (1-63) tokens at `addr`, then go on
+//!   after the reference. The tokens played never contain another reference
+//! - `$C0` + `FDB addr`: continue at `addr` (the loop); takes no frame
+//!
+//! The stream starts with `$FE`, a first delay the plain format never uses,
+//! so PLAY_MUSIC tells the two apart.
+
+use std::collections::HashMap;
+
+/// First byte of a packed stream
+pub const PACKED_MAGIC: u8 = 0xFE;
+
+const MAX_WAIT: u32 = 0x7F;
+const MAX_REF_TOKENS: usize = 63;
+/// Reference token + FDB
+const REF_BYTES: usize = 3;
+
+/// Register writes of one frame, in the order the player issues them
+pub type FrameWrites = Vec<(u8, u8)>;
+
+/// What happens after the last frame of a timeline
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimelineEnd {
+    /// Jump back to the first frame at this frame
+    Loop(u32),
+    /// Silence channels A-C at this frame, then stop
+    Stop(u32),
+}
+
+/// Frame-by-frame register writes of a song, as the compiler lays them out
+#[derive(Debug, Clone)]
+pub struct RegisterTimeline {
+    /// (frame, writes), ascending frames
+    pub frames: Vec<(u32, FrameWrites)>,
+    pub end: TimelineEnd,
+}
+
+impl RegisterTimeline {
+    /// Writes per frame (empty for unchanged frames) up to the loop jump or
+    /// through the release frame
+    pub fn expand(&self) -> Vec<FrameWrites> {
+        let last = match self.end {
+            TimelineEnd::Loop(frame) => frame,
+            TimelineEnd::Stop(frame) => frame + 1,
+        };
+        let mut frames = vec![Vec::new(); last as usize];
+        for (frame, writes) in self.frames.iter().chain(&self.release()) {
+            if let Some(slot) = frames.get_mut(*frame as usize) {
+                slot.extend(writes);
+            }
+        }
+        frames
+    }
+
+    fn release(&self) -> Option<(u32, FrameWrites)> {
+        match self.end {
+            TimelineEnd::Stop(frame) => Some((frame, vec![(8, 0), (9, 0), (10, 0)])),
+            TimelineEnd::Loop(_) => None,
+        }
+    }
+}
+
+/// Packed stream ready to embed, with its size and player cost
+#[derive(Debug, Clone)]
+pub struct PackedMusic {
+    pub asm: String,
+    /// Packed size in bytes
+    pub bytes: usize,
+    /// Cycles AU_PACKED_FRAME spends on the busiest frame
+    pub max_cycles: u32,
+    /// Average cycles per frame over one pass
+    pub avg_cycles: u32,
+    /// Cycles of every frame of one pass, the loop jump counted on the first
+    pub frame_cycles: Vec<u32>,
+}
+
+// Cycles of PLAYER_ASM per frame, from the JSR to the return. Measured on
+// the emulator by the vpy_cli music_pack test, which fails when the decoder
+// and these figures drift apart. Assumes no sound effect is playing.
+const CYCLES_CALL: u32 = 8; // JSR AU_PACKED_FRAME
+const CYCLES_IDLE: u32 = 20; // delay counter still running
+const CYCLES_READ: u32 = 14 + 19; // load pointer, token done, store pointer
+const CYCLES_WAIT: u32 = 22;
+const CYCLES_FRAME: u32 = 45;
+const CYCLES_REGISTER: u32 = 30; // one mask bit
+const CYCLES_WRITE: u32 = 101; // fetch, AU_MUSIC_WRITE_REG, Sound_Byte
+const CYCLES_REF: u32 = 51;
+const CYCLES_JUMP: u32 = 30;
+const CYCLES_REF_TOKEN: u32 = 10; // reference countdown per token
+const CYCLES_REF_RETURN: u32 = 16; // countdown of the last token, back after the FDB
+
+/// 6809 decoder for packed streams, shared by both code generators. Called
+/// from AUDIO_UPDATE with DP=$D0 and the stream's bank selected; writes go
+/// through AU_MUSIC_WRITE_REG
+pub const PLAYER_ASM: &str = "\
+; AU_PACKED_FRAME - One frame of a packed stream (DP=$D0, bank selected)
+; Tokens: $00 end, $01-$7F wait n frames, %10hhhhhh llllllll register
+; mask + values (one frame), %11nnnnnn FDB replay n tokens, $C0 FDB loop
+AU_PACKED_FRAME:
+LDA >PSG_DELAY_FRAMES
+BEQ AUP_NEXT
+DECA                    ; Still waiting
+STA >PSG_DELAY_FRAMES
+RTS
+AUP_NEXT:
+LDX >PSG_MUSIC_PTR
+AUP_READ:
+LDB ,X+
+BEQ AUP_END             ; $00: end of music
+BMI AUP_TOKEN
+DECB                    ; Wait n: this frame and n-1 more
+STB >PSG_DELAY_FRAMES
+BRA AUP_DONE
+AUP_TOKEN:
+BITB #$40
+BNE AUP_REF
+ANDB #$3F               ; Mask, registers 13-8
+TFR B,A
+LDB ,X+                 ; Mask, registers 7-0
+PSHS D                  ; ,S = high, 1,S = low
+CLRA                    ; Register number
+AUP_REG:
+LSR ,S
+ROR 1,S                 ; Next mask bit into carry
+BCC AUP_SKIP
+LDB ,X+                 ; Value
+PSHS A,X
+JSR AU_MUSIC_WRITE_REG
+PULS A,X
+AUP_SKIP:
+INCA
+LDB ,S
+ORB 1,S                 ; Any registers left?
+BNE AUP_REG
+LEAS 2,S
+AUP_DONE:
+LDA >PSG_MUSIC_REF_LEFT ; Inside a back-reference?
+BEQ AUP_SAVE
+DECA
+STA >PSG_MUSIC_REF_LEFT
+BNE AUP_SAVE
+LDX >PSG_MUSIC_REF_RET  ; Last token replayed: go back
+AUP_SAVE:
+STX >PSG_MUSIC_PTR
+RTS
+AUP_REF:
+ANDB #$3F
+BEQ AUP_JUMP            ; $C0: loop
+STB >PSG_MUSIC_REF_LEFT
+TFR X,D
+ADDD #2
+STD >PSG_MUSIC_REF_RET  ; Continue after the FDB
+AUP_JUMP:
+LDX ,X
+BRA AUP_READ            ; Takes no frame
+AUP_END:
+CLR >PSG_IS_PLAYING
+RTS
+
+";
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Token {
+    Wait(u8),
+    /// Registers (ascending) and values
+    Frame(Vec<(u8, u8)>),
+}
+
+impl Token {
+    fn bytes(&self) -> Vec<u8> {
+        match self {
+            Token::Wait(n) => vec![*n],
+            Token::Frame(writes) => {
+                let mask = writes.iter().fold(0u16, |m, (r, _)| m | 1 << r);
+                let mut bytes = vec![0x80 | (mask >> 8) as u8, mask as u8];
+                bytes.extend(writes.iter().map(|(_, v)| v));
+                bytes
+            }
+        }
+    }
+
+    fn cycles(&self) -> u32 {
+        match self {
+            Token::Wait(_) => CYCLES_WAIT,
+            Token::Frame(writes) => {
+                let highest = writes.last().map_or(0, |(r, _)| *r as u32 + 1);
+                CYCLES_FRAME + highest * CYCLES_REGISTER + writes.len() as u32 * CYCLES_WRITE
+            }
+        }
+    }
+}
+
+/// Literal token, or the `len` tokens starting at token `start` replayed
+#[derive(Debug, Clone, Copy)]
+enum Item {
+    Literal(usize),
+    Ref { start: usize, len: usize },
+}
+
+/// Tokens of a timeline: only registers whose value changes are written
+fn tokenize(timeline: &RegisterTimeline) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    // Unknown at the start and after the loop jump: the first frame writes all
+    let mut state: [Option<u8>; 14] = [None; 14];
+    let mut next_frame = 0u32;
+    let release = timeline.release();
+    for (frame, writes) in timeline.frames.iter().chain(&release) {
+        let mut values: [Option<u8>; 14] = [None; 14];
+        for &(reg, val) in writes {
+            if let Some(slot) = values.get_mut(reg as usize) {
+                *slot = Some(val);
+            }
+        }
+        let changed: Vec<(u8, u8)> = (0..14u8)
+            .filter_map(|r| values[r as usize].map(|v| (r, v)))
+            .filter(|&(r, v)| r == 13 || state[r as usize] != Some(v))
+            .collect();
+        if changed.is_empty() {
+            continue;
+        }
+        push_wait(&mut tokens, frame.saturating_sub(next_frame));
+        for &(r, v) in &changed {
+            state[r as usize] = Some(v);
+        }
+        tokens.push(Token::Frame(changed));
+        next_frame = frame + 1;
+    }
+    if let TimelineEnd::Loop(frame) = timeline.end {
+        push_wait(&mut tokens, frame.saturating_sub(next_frame));
+    }
+    tokens
+}
+
+fn push_wait(tokens: &mut Vec<Token>, mut frames: u32) {
+    while frames > 0 {
+        let n = frames.min(MAX_WAIT);
+        tokens.push(Token::Wait(n as u8));
+        frames -= n;
+    }
+}
+
+/// Greedy LZ over tokens: replace a run by a reference to an earlier run that
+/// was emitted literally, when that saves bytes
+fn compress(tokens: &[Token]) -> Vec<Item> {
+    let mut ids = HashMap::new();
+    let ids: Vec<usize> = tokens.iter().map(|t| {
+        let next = ids.len();
+        *ids.entry(t.clone()).or_insert(next)
+    }).collect();
+    let sizes: Vec<usize> = tokens.iter().map(|t| t.bytes().len()).collect();
+
+    let mut items = Vec::new();
+    let mut literal = vec![false; tokens.len()];
+    let mut i = 0;
+    while i < tokens.len() {
+        let mut best: Option<(usize, usize, usize)> = None; // (start, len, bytes)
+        for j in 0..i {
+            let mut len = 0;
+            let mut bytes = 0;
+            while len < MAX_REF_TOKENS && j + len < i && i + len < tokens.len()
+                && literal[j + len] && ids[j + len] == ids[i + len]
+            {
+                bytes += sizes[i + len];
+                len += 1;
+            }
+            if bytes > REF_BYTES && best.is_none_or(|(_, _, b)| bytes > b) {
+                best = Some((j, len, bytes));
+            }
+        }
+        match best {
+            Some((start, len, _)) => {
+                items.push(Item::Ref { start, len });
+                i += len;
+            }
+            None => {
+                items.push(Item::Literal(i));
+                literal[i] = true;
+                i += 1;
+            }
+        }
+    }
+    items
+}
+
+/// Player cycles of each frame over one pass
+fn cost(tokens: &[Token], items: &[Item], end: TimelineEnd) -> Vec<u32> {
+    let mut frames: Vec<u32> = Vec::new();
+    let mut current = CYCLES_CALL + CYCLES_READ;
+    let mut play = |token: &Token, extra: u32, current: &mut u32| {
+        *current += token.cycles() + extra;
+        frames.push(*current);
+        if let Token::Wait(n) = token {
+            frames.extend(std::iter::repeat_n(CYCLES_CALL + CYCLES_IDLE, *n as usize - 1));
+        }
+        *current = CYCLES_CALL + CYCLES_READ;
+    };
+    for item in items {
+        match *item {
+            Item::Literal(i) => play(&tokens[i], 0, &mut current),
+            Item::Ref { start, len } => {
+                current += CYCLES_REF;
+                for (k, token) in tokens[start..start + len].iter().enumerate() {
+                    let countdown = if k + 1 == len { CYCLES_REF_RETURN } else { CYCLES_REF_TOKEN };
+                    play(token, countdown, &mut current);
+                }
+            }
+        }
+    }
+    // The loop jump runs at the start of the next pass's first frame
+    if let TimelineEnd::Loop(_) = end {
+        if let Some(first) = frames.first_mut() {
+            *first += CYCLES_JUMP;
+        }
+    }
+    frames
+}
+
+/// Encode a timeline as a packed stream labelled `_<symbol>_MUSIC`
+pub fn pack(timeline: &RegisterTimeline, symbol: &str) -> PackedMusic {
+    let tokens = tokenize(timeline);
+    let items = compress(&tokens);
+    let frame_cycles = cost(&tokens, &items, timeline.end);
+    let max_cycles = frame_cycles.iter().copied().max().unwrap_or(0);
+    let avg_cycles = (frame_cycles.iter().map(|&c| c as u64).sum::<u64>() / frame_cycles.len().max(1) as u64) as u32;
+
+    let label = |i: usize| format!("_{}_MUSIC_T{}", symbol, i);
+    let mut targets: Vec<usize> = items.iter()
+        .filter_map(|item| match item {
+            Item::Ref { start, .. } => Some(*start),
+            Item::Literal(_) => None,
+        })
+        .collect();
+    if matches!(timeline.end, TimelineEnd::Loop(_)) && !tokens.is_empty() {
+        targets.push(0);
+    }
+
+    let mut asm = String::new();
+    let mut bytes = 1;
+    asm.push_str(&format!("_{}_MUSIC:\n", symbol));
+    asm.push_str(&format!("    FCB     ${:02X}             ; Packed stream\n", PACKED_MAGIC));
+    for item in &items {
+        match *item {
+            Item::Literal(i) => {
+                if targets.contains(&i) {
+                    asm.push_str(&format!("{}:\n", label(i)));
+                }
+                let data = tokens[i].bytes();
+                let comment = match &tokens[i] {
+                    Token::Wait(n) => format!("Wait {} frames", n),
+                    Token::Frame(writes) => format!("Frame - {} register writes", writes.len()),
+                };
+                let list: Vec<String> = data.iter().map(|b| format!("${:02X}", b)).collect();
+                asm.push_str(&format!("    FCB     {}  ; {}\n", list.join(","), comment));
+                bytes += data.len();
+            }
+            Item::Ref { start, len } => {
+                asm.push_str(&format!("    FCB     ${:02X}             ; Replay {} tokens\n", 0xC0 | len, len));
+                asm.push_str(&format!("    FDB     {}\n", label(start)));
+                bytes += REF_BYTES;
+            }
+        }
+    }
+    match timeline.end {
+        TimelineEnd::Loop(_) if !tokens.is_empty() => {
+            asm.push_str("    FCB     $C0             ; Loop\n");
+            asm.push_str(&format!("    FDB     {}\n\n", label(0)));
+            bytes += REF_BYTES;
+        }
+        _ => {
+            asm.push_str("    FCB     $00             ; End of music\n\n");
+            bytes += 1;
+        }
+    }
+    PackedMusic { asm, bytes, max_cycles, avg_cycles, frame_cycles }
+}
+
+/// Size of the plain encoding of a timeline, in bytes
+pub fn plain_size(timeline: &RegisterTimeline) -> usize {
+    let frames: usize = timeline.frames.iter().map(|(_, w)| 2 + 2 * w.len()).sum();
+    frames + match timeline.end {
+        // Delay before the loop, $FF, FDB
+        TimelineEnd::Loop(_) => 4,
+        // Release frame, end frame
+        TimelineEnd::Stop(_) => 2 + 6 + 2,
+    }
+}
+
+/// Token-at-a-time reader with the runtime's state, for tools and tests
+pub struct PackedReader<'a> {
+    data: &'a [u8],
+    origin: u16,
+    pos: usize,
+    wait: u8,
+    ref_left: u8,
+    ref_return: usize,
+    /// Loop jumps taken
+    pub loops: usize,
+    pub ended: bool,
+}
+
+impl<'a> PackedReader<'a> {
+    /// `data` assembled at `origin`, starting with the magic byte
+    pub fn new(data: &'a [u8], origin: u16) -> Result<Self, String> {
+        if data.first() != Some(&PACKED_MAGIC) {
+            return Err("not a packed music stream".to_string());
+        }
+        Ok(Self { data, origin, pos: 1, wait: 0, ref_left: 0, ref_return: 0, loops: 0, ended: false })
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        let b = *self.data.get(self.pos)
+            .ok_or_else(|| format!("packed stream overruns its data at offset {}", self.pos))?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    fn address(&mut self) -> Result<usize, String> {
+        let hi = self.byte()?;
+        let lo = self.byte()?;
+        u16::from_be_bytes([hi, lo]).checked_sub(self.origin)
+            .map(|a| a as usize)
+            .ok_or_else(|| format!("packed stream points below its origin at offset {}", self.pos - 2))
+    }
+
+    fn token_done(&mut self) {
+        if self.ref_left > 0 {
+            self.ref_left -= 1;
+            if self.ref_left == 0 {
+                self.pos = self.ref_return;
+            }
+        }
+    }
+
+    /// Writes of the next frame, `None` once the song has ended
+    pub fn next_frame(&mut self) -> Result<Option<FrameWrites>, String> {
+        if self.ended {
+            return Ok(None);
+        }
+        if self.wait > 0 {
+            self.wait -= 1;
+            return Ok(Some(Vec::new()));
+        }
+        let mut jumps = 0;
+        loop {
+            let token = self.byte()?;
+            match token {
+                0x00 => {
+                    self.ended = true;
+                    return Ok(None);
+                }
+                0x01..=0x7F => {
+                    self.wait = token - 1;
+                    self.token_done();
+                    return Ok(Some(Vec::new()));
+                }
+                0x80..=0xBF => {
+                    let mask = ((token as u16 & 0x3F) << 8) | self.byte()? as u16;
+                    let mut writes = Vec::new();
+                    for reg in 0..14u8 {
+                        if mask & (1 << reg) != 0 {
+                            writes.push((reg, self.byte()?));
+                        }
+                    }
+                    self.token_done();
+                    return Ok(Some(writes));
+                }
+                0xC0 => {
+                    jumps += 1;
+                    if jumps > 1 {
+                        return Err("packed stream loops without playing a frame".to_string());
+                    }
+                    self.pos = self.address()?;
+                    self.loops += 1;
+                }
+                _ => {
+                    if self.ref_left > 0 {
+                        return Err(format!("nested reference at offset {}", self.pos - 1));
+                    }
+                    let target = self.address()?;
+                    self.ref_left = token & 0x3F;
+                    self.ref_return = self.pos;
+                    self.pos = target;
+                }
+            }
+        }
+    }
+}
+
+/// Decode one pass of a packed stream: writes per frame, and whether it loops
+pub fn decode_packed(data: &[u8], origin: u16) -> Result<(Vec<FrameWrites>, bool), String> {
+    let mut reader = PackedReader::new(data, origin)?;
+    let mut frames = Vec::new();
+    while let Some(writes) = reader.next_frame()? {
+        if reader.loops > 0 {
+            return Ok((frames, true));
+        }
+        frames.push(writes);
+    }
+    Ok((frames, false))
+}
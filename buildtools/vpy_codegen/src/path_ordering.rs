@@ -110,6 +110,10 @@ impl GreedyThenTwoOpt {
     }
 
     /// 2-opt local search refinement
+    ///
+    /// Candidate moves are applied in place and undone when they don't
+    /// shorten the tour, so large traced resources don't clone the whole
+    /// path list for every pair.
     fn two_opt_improve(&self, mut paths: Vec<VecPath>) -> Vec<VecPath> {
         let mut improved = true;
         let mut iterations = 0;
@@ -121,79 +125,41 @@ impl GreedyThenTwoOpt {
             let current_distance = Self::tour_distance(&paths);
 
             // Try all pairs of paths
-            for i in 0..paths.len() {
+            'pairs: for i in 0..paths.len() {
                 for j in (i + 1)..paths.len() {
-                    // Try reversing path i
-                    {
-                        let mut test_paths = paths.clone();
-                        test_paths[i].points.reverse();
-                        let new_distance = Self::tour_distance(&test_paths);
-                        if new_distance < current_distance {
-                            paths = test_paths;
+                    // Reverse i, reverse j, swap, swap + reverse i, swap + reverse j
+                    for (swap, reverse) in [(false, Some(i)), (false, Some(j)), (true, None), (true, Some(i)), (true, Some(j))] {
+                        Self::apply_move(&mut paths, i, j, swap, reverse);
+                        if Self::tour_distance(&paths) < current_distance {
                             improved = true;
-                            break;
+                            break 'pairs;
                         }
+                        Self::undo_move(&mut paths, i, j, swap, reverse);
                     }
-
-                    // Try reversing path j
-                    {
-                        let mut test_paths = paths.clone();
-                        test_paths[j].points.reverse();
-                        let new_distance = Self::tour_distance(&test_paths);
-                        if new_distance < current_distance {
-                            paths = test_paths;
-                            improved = true;
-                            break;
-                        }
-                    }
-
-                    // Try swapping paths i and j
-                    {
-                        let mut test_paths = paths.clone();
-                        test_paths.swap(i, j);
-                        let new_distance = Self::tour_distance(&test_paths);
-                        if new_distance < current_distance {
-                            paths = test_paths;
-                            improved = true;
-                            break;
-                        }
-                    }
-
-                    // Try swapping + reversing i
-                    {
-                        let mut test_paths = paths.clone();
-                        test_paths.swap(i, j);
-                        test_paths[i].points.reverse();
-                        let new_distance = Self::tour_distance(&test_paths);
-                        if new_distance < current_distance {
-                            paths = test_paths;
-                            improved = true;
-                            break;
-                        }
-                    }
-
-                    // Try swapping + reversing j
-                    {
-                        let mut test_paths = paths.clone();
-                        test_paths.swap(i, j);
-                        test_paths[j].points.reverse();
-                        let new_distance = Self::tour_distance(&test_paths);
-                        if new_distance < current_distance {
-                            paths = test_paths;
-                            improved = true;
-                            break;
-                        }
-                    }
-                }
-
-                if improved {
-                    break;
                 }
             }
         }
 
         paths
     }
+
+    fn apply_move(paths: &mut [VecPath], i: usize, j: usize, swap: bool, reverse: Option<usize>) {
+        if swap {
+            paths.swap(i, j);
+        }
+        if let Some(k) = reverse {
+            paths[k].points.reverse();
+        }
+    }
+
+    fn undo_move(paths: &mut [VecPath], i: usize, j: usize, swap: bool, reverse: Option<usize>) {
+        if let Some(k) = reverse {
+            paths[k].points.reverse();
+        }
+        if swap {
+            paths.swap(i, j);
+        }
+    }
 }
 
 impl PathOrderer for GreedyThenTwoOpt {
@@ -214,48 +180,6 @@ impl PathOrderer for GreedyThenTwoOpt {
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_distance() {
-        let p1 = Point { x: 0, y: 0, intensity: None };
-        let p2 = Point { x: 3, y: 4, intensity: None };
-        let dist = GreedyThenTwoOpt::distance(p1, p2);
-        assert!((dist - 5.0).abs() < 0.001);
-    }
-
-    #[test]
-    fn test_greedy_ordering() {
-        let paths = vec![
-            VecPath {
-                name: "path1".to_string(),
-                intensity: 127,
-                closed: false,
-                points: vec![
-                    Point { x: 0, y: 0, intensity: None },
-                    Point { x: 10, y: 0, intensity: None },
-                ],
-            },
-            VecPath {
-                name: "path2".to_string(),
-                intensity: 127,
-                closed: false,
-                points: vec![
-                    Point { x: 5, y: 5, intensity: None },
-                    Point { x: 15, y: 5, intensity: None },
-                ],
-            },
-        ];
-
-        let orderer = GreedyThenTwoOpt::default();
-        let ordered = orderer.order(paths);
-
-        assert_eq!(ordered.len(), 2);
-    }
-}
-
 /// Christofides Algorithm - Near-optimal TSP solution
 /// 
 /// Algorithm (5 phases):
@@ -539,3 +463,45 @@ impl PathOrderer for ChristofidesOrderer {
         ordered
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distance() {
+        let p1 = Point { x: 0, y: 0, intensity: None };
+        let p2 = Point { x: 3, y: 4, intensity: None };
+        let dist = GreedyThenTwoOpt::distance(p1, p2);
+        assert!((dist - 5.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_greedy_ordering() {
+        let paths = vec![
+            VecPath {
+                name: "path1".to_string(),
+                intensity: 127,
+                closed: false,
+                points: vec![
+                    Point { x: 0, y: 0, intensity: None },
+                    Point { x: 10, y: 0, intensity: None },
+                ],
+            },
+            VecPath {
+                name: "path2".to_string(),
+                intensity: 127,
+                closed: false,
+                points: vec![
+                    Point { x: 5, y: 5, intensity: None },
+                    Point { x: 15, y: 5, intensity: None },
+                ],
+            },
+        ];
+
+        let orderer = GreedyThenTwoOpt::default();
+        let ordered = orderer.order(paths);
+
+        assert_eq!(ordered.len(), 2);
+    }
+}
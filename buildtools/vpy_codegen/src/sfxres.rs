@@ -8,6 +8,8 @@ use std::path::Path;
 use serde::{Deserialize, Serialize};
 use anyhow::Result;
 
+use crate::musres::HardwareEnvelope;
+
 /// Sound effects resource file extension
 pub const VSFX_EXTENSION: &str = "vsfx";
 
@@ -48,6 +50,15 @@ pub struct SfxResource {
     /// Arpeggio/vibrato effects
     #[serde(default)]
     pub modulation: Modulation,
+    
+    /// PSG hardware envelope: volume follows it instead of `envelope`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hardware_envelope: Option<HardwareEnvelope>,
+    
+    /// Default PLAY_SFX priority: a playing effect only gives its channel to
+    /// one of equal or higher priority
+    #[serde(default)]
+    pub priority: u8,
 }
 
 fn default_version() -> String { "1.0".to_string() }
@@ -265,6 +276,8 @@ impl SfxResource {
             pitch: PitchEnvelope::default(),
             noise: NoiseSettings::default(),
             modulation: Modulation::default(),
+            hardware_envelope: None,
+            priority: 0,
         }
     }
     
@@ -484,6 +497,17 @@ impl SfxResource {
 ",
             self.duration_ms, total_frames, self.oscillator.frequency, self.oscillator.channel));
         
+        // Hardware envelope: $D0 $21 shape, period (big-endian) before the
+        // first frame. Cannot be mistaken for a frame: $D0 would carry a
+        // noise byte and periods stop at 31
+        if let Some(env) = self.hardware_envelope {
+            asm.push_str(&format!("    FCB $D0, $21    ; Hardware envelope
+    FCB ${:02X}         ; Shape
+    FCB ${:02X}, ${:02X}  ; Period = {} (big-endian)
+",
+                env.shape & 0x0F, env.period >> 8, env.period & 0xFF, env.period));
+        }
+        
         // Generate AYFX frame-by-frame
         let mut last_period: Option<u16> = None;
         let mut last_noise: Option<u8> = None;
@@ -549,7 +573,8 @@ impl SfxResource {
                 current_period = current_period.max(1).min(4095);
             }
             
-            // Build flag byte
+            // Build flag byte (ignored with a hardware envelope: the player
+            // ORs $10 into the volume, which selects envelope mode)
             let mut flag: u8 = volume & 0x0F; // Bits 0-3: volume
             
             // VRelease optimization: only include data when it changes
@@ -642,6 +667,18 @@ mod tests {
         assert!(asm.contains("FCB")); // Has byte data
     }
 
+    #[test]
+    fn test_hardware_envelope_escape() {
+        let mut sfx = SfxResource::preset_hit();
+        sfx.hardware_envelope = Some(HardwareEnvelope { shape: 9, period: 0x0234 });
+        let asm = sfx.compile_to_asm();
+        let escape = asm.find("FCB $D0, $21").expect("envelope command");
+        assert!(escape < asm.find("; Frame 0").unwrap());
+        assert!(asm.contains("FCB $09         ; Shape"));
+        assert!(asm.contains("FCB $02, $34"));
+        assert!(!SfxResource::preset_hit().compile_to_asm().contains("$D0, $21"));
+    }
+
     #[test]
     fn test_json_roundtrip() {
         let original = SfxResource::preset_powerup();
@@ -650,4 +687,16 @@ mod tests {
         assert_eq!(parsed.name, original.name);
         assert_eq!(parsed.duration_ms, original.duration_ms);
     }
+
+    #[test]
+    fn test_priority_is_optional() {
+        let mut json = serde_json::to_value(SfxResource::preset_hit()).unwrap();
+        json.as_object_mut().unwrap().remove("priority");
+        let parsed: SfxResource = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(parsed.priority, 0);
+
+        json["priority"] = 7.into();
+        let parsed: SfxResource = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.priority, 7);
+    }
 }
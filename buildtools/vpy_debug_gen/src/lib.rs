@@ -65,14 +65,21 @@ pub fn generate_pdb(
 
     // ── Parse BIOS symbols from VECTREX.I ───────────────────────────────────
     if let Some(content) = vectrex_i {
-        for line in content.lines() {
-            parse_equ_line(line, &mut pdb.bios_symbols);
-        }
+        pdb.bios_symbols = parse_bios_symbols(content);
     }
 
     Ok(pdb)
 }
 
+/// Parse the `EQU`/`SET` definitions of a VECTREX.I include into name → address.
+pub fn parse_bios_symbols(vectrex_i: &str) -> HashMap<String, u32> {
+    let mut symbols = HashMap::new();
+    for line in vectrex_i.lines() {
+        parse_equ_line(line, &mut symbols);
+    }
+    symbols
+}
+
 /// Phase 9, step 2 – update all PDB symbol addresses from the linker's
 /// resolved symbol table.
 ///
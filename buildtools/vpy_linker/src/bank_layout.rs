@@ -175,18 +175,13 @@ impl MultibankLayout {
                 base_address,
             );
             
-            // Update symbols in this section
-            for symbol in &obj.symbols.exports {
-                if let Some(sym_section_idx) = symbol.section {
-                    if sym_section_idx == assignment.section_index {
-                        let symbol_address = base_address.wrapping_add(symbol.offset);
-                        
-                        if let Some(global_sym) = global.symbols.get_mut(&symbol.name) {
-                            global_sym.address = symbol_address;
-                        }
-                    }
-                }
-            }
+            SymbolResolver::place_symbols(
+                assignment.object_index,
+                obj,
+                assignment.section_index,
+                base_address,
+                global,
+            );
         }
         
         Ok(section_bases)
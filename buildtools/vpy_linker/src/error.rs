@@ -33,6 +33,13 @@ pub enum LinkerError {
         size: usize,
     },
 
+    #[error("Branch to '{symbol}' at {location} out of range ({distance} bytes, need a long branch)")]
+    BranchOutOfRange {
+        symbol: String,
+        location: String,
+        distance: i32,
+    },
+
     #[error("CrossBank relocation not yet implemented for symbol '{symbol}'")]
     CrossBankNotImplemented {
         symbol: String,
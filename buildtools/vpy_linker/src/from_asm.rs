@@ -0,0 +1,158 @@
+// Object files from assembly source
+//
+// Assembles one source (hand-written or generated from a VPy module) into a
+// single-section `.vo`: labels become symbols (global if named by EXPORT,
+// local otherwise), exported equates become absolute symbols, undefined
+// symbols become imports and every absolute reference to a label becomes a
+// relocation, so the code can be placed at any address.
+
+use crate::error::{LinkerError, LinkerResult};
+use crate::object::{
+    Relocation, RelocationType, Section, SectionType, Symbol, SymbolScope, SymbolType, VectrexObject,
+};
+use vpy_assembler::m6809::asm_to_binary::RefType;
+
+/// Assemble `asm_source` into an object; errors carry `source_file`
+pub fn object_from_asm(asm_source: &str, source_file: &str) -> LinkerResult<VectrexObject> {
+    let assembled = vpy_assembler::assemble_object(asm_source)
+        .map_err(|e| LinkerError::Error(format!("{}: {}", source_file, e)))?;
+
+    let mut obj = VectrexObject::new(source_file.to_string());
+    obj.sections.push(Section {
+        name: ".text".to_string(),
+        section_type: SectionType::Text,
+        bank_hint: None,
+        alignment: 1,
+        data: assembled.code,
+    });
+
+    for label in &assembled.labels {
+        obj.symbols.exports.push(Symbol {
+            name: label.name.clone(),
+            section: Some(0),
+            offset: label.offset,
+            scope: if label.exported { SymbolScope::Global } else { SymbolScope::Local },
+            symbol_type: SymbolType::Function,
+        });
+        obj.debug_info.symbol_lines.insert(label.name.clone(), label.line);
+    }
+
+    // Exported equates keep their value wherever the code goes
+    for constant in &assembled.constants {
+        obj.symbols.exports.push(Symbol {
+            name: constant.name.clone(),
+            section: None,
+            offset: constant.value,
+            scope: SymbolScope::Global,
+            symbol_type: SymbolType::Constant,
+        });
+        obj.debug_info.symbol_lines.insert(constant.name.clone(), constant.line);
+    }
+
+    for import in &assembled.imports {
+        if !obj.debug_info.symbol_lines.contains_key(&import.symbol) {
+            obj.debug_info.symbol_lines.insert(import.symbol.clone(), import.line);
+            obj.symbols.imports.push(Symbol {
+                name: import.symbol.clone(),
+                section: None,
+                offset: 0,
+                scope: SymbolScope::Global,
+                symbol_type: SymbolType::Function,
+            });
+        }
+    }
+
+    for reference in assembled.imports.iter().chain(&assembled.label_refs) {
+        obj.relocations.push(Relocation {
+            section: 0,
+            offset: reference.offset as u16,
+            reloc_type: match reference.ref_type {
+                RefType::Absolute16 => RelocationType::Absolute16,
+                RefType::Relative8 => RelocationType::Relative8,
+                RefType::Relative16 => RelocationType::Relative16,
+            },
+            symbol: reference.symbol.clone(),
+            addend: reference.addend as i32,
+        });
+    }
+
+    Ok(obj)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::linker::link;
+
+    const LIB: &str = "    EXPORT DOUBLE\nDOUBLE:\n    ASLB\n    BSR done\ndone:\n    RTS\n";
+
+    #[test]
+    fn test_labels_and_imports() {
+        let obj = object_from_asm("START:\n    JSR DOUBLE\n    LBRA START\n", "main.asm").unwrap();
+        let start = obj.find_symbol("START").unwrap();
+        assert_eq!(start.scope, SymbolScope::Local);
+        assert_eq!(obj.symbols.imports.len(), 1);
+        assert_eq!(obj.symbol_location("DOUBLE"), "main.asm:2");
+        // JSR DOUBLE (import) and nothing for LBRA: relative to its own section
+        assert_eq!(obj.relocations.len(), 1);
+
+        let lib = object_from_asm(LIB, "lib.asm").unwrap();
+        assert_eq!(lib.find_symbol("DOUBLE").unwrap().scope, SymbolScope::Global);
+        assert_eq!(lib.find_symbol("DONE").unwrap().scope, SymbolScope::Local);
+    }
+
+    #[test]
+    fn test_relocated_code() {
+        // The library goes first so the program's own addresses move too
+        let lib = object_from_asm(LIB, "lib.asm").unwrap();
+        let main = object_from_asm("START:\n    LDX #TABLE+1\n    JSR DOUBLE\n    BRA START\nTABLE:\n    FDB START\n", "main.asm").unwrap();
+        let linked = link(vec![lib, main], 0x0000).unwrap();
+
+        assert_eq!(linked.symbols["DOUBLE"], 0x0000);
+        let start = 4; // ASLB, BSR, RTS
+        let code = &linked.binary[start..];
+        assert_eq!(&code[0..3], &[0x8E, 0x00, 0x0D]); // LDX #TABLE+1 (TABLE at $000C)
+        assert_eq!(&code[3..6], &[0xBD, 0x00, 0x00]); // JSR DOUBLE
+        assert_eq!(&code[6..8], &[0x20, 0xF8]); // BRA START
+        assert_eq!(&code[8..10], &[0x00, 0x04]); // FDB START
+    }
+
+    #[test]
+    fn test_undefined_and_duplicate_symbols() {
+        let main = object_from_asm("    NOP\n    JSR MISSING\n", "main.asm").unwrap();
+        let err = link(vec![main], 0).unwrap_err().to_string();
+        assert!(err.contains("'MISSING' in main.asm:2"), "{}", err);
+
+        let a = object_from_asm("    EXPORT F\nF:\n    RTS\n", "a.asm").unwrap();
+        let b = object_from_asm("    NOP\n    EXPORT F\nF:\n    RTS\n", "b.asm").unwrap();
+        let err = link(vec![a, b], 0).unwrap_err().to_string();
+        assert!(err.contains("a.asm:2") && err.contains("b.asm:3"), "{}", err);
+    }
+
+    #[test]
+    fn test_exported_equates_and_ram() {
+        // SCORE is the program's, BUFFER lives in the library's BSS section
+        let main = object_from_asm("SCORE EQU $C880\n    EXPORT SCORE\nSTART:\n    JSR CLEAR\n    RTS\n", "main.asm").unwrap();
+        let score = main.find_symbol("SCORE").unwrap();
+        assert_eq!((score.section, score.offset), (None, 0xC880));
+
+        let mut lib = object_from_asm("    EXPORT CLEAR\nCLEAR:\n    CLR BUFFER\n    STD SCORE\n    RTS\n", "lib.asm").unwrap();
+        lib.sections.push(Section { name: ".bss".to_string(), section_type: SectionType::Bss, bank_hint: None, alignment: 4, data: vec![] });
+        lib.symbols.imports.retain(|s| s.name != "BUFFER");
+        lib.symbols.exports.push(Symbol { name: "BUFFER".to_string(), section: Some(1), offset: 2, scope: SymbolScope::Local, symbol_type: SymbolType::Variable });
+        let mut main_ram = main.clone();
+        main_ram.sections.push(Section { name: ".bss".to_string(), section_type: SectionType::Bss, bank_hint: None, alignment: 3, data: vec![] });
+
+        let linked = link(vec![main_ram, lib], 0x0000).unwrap();
+        let clear = &linked.binary[4..];
+        assert_eq!(&clear[0..3], &[0x7F, 0xC8, 0x85]); // CLR BUFFER: after the program's 3 bytes, +2
+        assert_eq!(&clear[3..6], &[0xFD, 0xC8, 0x80]); // STD SCORE
+        assert_eq!(linked.binary.len(), 4 + 7);
+    }
+
+    #[test]
+    fn test_object_errors() {
+        assert!(object_from_asm("    ORG $4000\n    RTS\n", "x.asm").is_err());
+        assert!(object_from_asm("    EXPORT NOWHERE\n    RTS\n", "x.asm").is_err());
+    }
+}
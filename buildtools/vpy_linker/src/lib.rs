@@ -9,10 +9,12 @@
 //! # Module Structure
 //!
 //! - `object.rs`: Object file format (.vo files)
+//! - `from_asm.rs`: Assemble a source into a `.vo`
 //! - `error.rs`: Error types
 //! - `relocation.rs`: Apply relocation records
 //! - `linker.rs`: Main linking algorithm
 //! - `layout.rs`: Memory layout decisions
+//! - `link_map.rs`: `.map` / `.map.json` reports (symbols, sections, ROM/RAM usage)
 //!
 //! # Input
 //! `Vec<VectrexObject>` (object files with relocation tables from Phase 6)
@@ -21,8 +23,10 @@
 //! `LinkedBinary` (final binary with symbol table and relocation info for PDB)
 
 pub mod error;
+pub mod from_asm;    // Relocatable objects from assembly source
 pub mod layout;
 pub mod linker;
+pub mod link_map;    // Link map and ROM/RAM usage report
 pub mod object;      // Object file format (.vo)
 pub mod relocation;
 pub mod resolver;    // Symbol resolution (4-step algorithm)
@@ -37,6 +41,8 @@ pub use object::{
     TargetArch, ObjectFlags,
     OBJECT_MAGIC, OBJECT_FORMAT_VERSION,
 };
+pub use from_asm::object_from_asm;
+pub use link_map::{LinkMap, MapBank};
 pub use resolver::{SymbolResolver, GlobalSymbolTable, ResolvedSymbol};
 pub use bank_layout::{BankConfig, MultibankLayout, BankData, SectionAssignment};
 pub use multi_bank_linker::MultiBankLinker;
@@ -0,0 +1,579 @@
+// Link map: where every section and symbol ended up and what fills the ROM
+//
+// Built after assembly, before the ROM is written, so a bank that overflows
+// can still be inspected. Rendered as a text `.map` and as JSON for the IDE.
+//
+// For generated programs the owner of each label comes from the listing:
+// `; Function:` and `; MAIN PROGRAM` comments start a source module's code,
+// `_NAME_...` labels belong to the asset NAME and `;****` banners start
+// runtime sections (helpers, arrays, lookup tables). For `.vo` objects the
+// owner is the object's source file.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::path::Path;
+
+use serde::Serialize;
+use vpy_codegen::m6809::ram_layout::{parse_equ_definitions, RamSlot};
+use vpy_codegen::AssetInfo;
+
+use crate::object::{SectionType, VectrexObject};
+use crate::LinkedBinary;
+
+/// The stack starts here and grows down into the free RAM
+const STACK_TOP: u16 = 0xCBFF;
+
+/// What a range of ROM belongs to
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+#[serde(tag = "kind", content = "name", rename_all = "lowercase")]
+pub enum Owner {
+    /// Code of a source module (or object file)
+    Module(String),
+    /// Data generated from an asset file
+    Asset(String),
+    /// Runtime or compiler-generated section, named by its banner
+    Section(String),
+}
+
+impl std::fmt::Display for Owner {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Owner::Module(name) => write!(f, "module {}", name),
+            Owner::Asset(name) => write!(f, "asset {}", name),
+            Owner::Section(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+/// One assembled bank as the linker saw it
+#[derive(Debug, Clone)]
+pub struct MapBank {
+    pub bank_id: usize,
+    /// CPU address the bank is assembled at
+    pub org: u16,
+    pub capacity: usize,
+    /// Bytes emitted (may exceed `capacity` when the bank overflows)
+    pub used: usize,
+    /// Labels defined in the bank (name → address)
+    pub symbols: HashMap<String, u16>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BankUsage {
+    pub bank: usize,
+    pub org: u16,
+    pub capacity: usize,
+    pub used: usize,
+    /// Negative when the bank overflows
+    pub free: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MapSection {
+    pub name: String,
+    pub bank: usize,
+    pub address: u16,
+    pub size: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MapSymbol {
+    pub name: String,
+    pub bank: usize,
+    pub address: u16,
+    pub size: usize,
+    pub owner: Owner,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageTotal {
+    pub name: String,
+    pub bytes: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LinkMap {
+    pub banks: Vec<BankUsage>,
+    pub sections: Vec<MapSection>,
+    pub symbols: Vec<MapSymbol>,
+    /// Bytes per source module, largest first
+    pub modules: Vec<UsageTotal>,
+    /// Bytes per asset, largest first
+    pub assets: Vec<UsageTotal>,
+    /// Bytes per runtime section, largest first
+    pub runtime: Vec<UsageTotal>,
+    pub ram: Vec<RamSlot>,
+}
+
+impl LinkMap {
+    /// Map of a generated program. `function_modules` gives the module of each
+    /// function label (uppercase), including MAIN for the entry module.
+    pub fn from_listing(
+        asm_source: &str,
+        function_modules: &HashMap<String, String>,
+        assets: &[AssetInfo],
+        banks: &[MapBank],
+    ) -> Self {
+        let owners = label_owners(asm_source, function_modules, assets);
+        let mut map = LinkMap { ram: parse_equ_definitions(asm_source), ..Default::default() };
+
+        let mut banks: Vec<&MapBank> = banks.iter().collect();
+        banks.sort_by_key(|bank| bank.bank_id);
+        for bank in banks {
+            map.banks.push(BankUsage {
+                bank: bank.bank_id,
+                org: bank.org,
+                capacity: bank.capacity,
+                used: bank.used,
+                free: bank.capacity as i64 - bank.used as i64,
+            });
+            let labels = unique_labels(&bank.symbols);
+            let end = bank.org as usize + bank.used;
+            let mut placed: Vec<(String, u16, Owner)> = labels
+                .into_iter()
+                .filter(|(_, address)| (bank.org as usize..=end).contains(&(*address as usize)))
+                .map(|(name, address)| {
+                    // Local labels are scoped as `PARENT.LOCAL` and belong with their parent
+                    let upper = name.to_uppercase();
+                    let parent = upper.split('.').next().unwrap_or(&upper);
+                    let owner = owners
+                        .get(&upper)
+                        .or_else(|| owners.get(parent))
+                        .cloned()
+                        .unwrap_or_else(|| Owner::Section("(unlisted)".to_string()));
+                    (name, address, owner)
+                })
+                .collect();
+            placed.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+            if placed.first().is_none_or(|first| first.1 > bank.org) && bank.used > 0 {
+                let owner = Owner::Section("(start of bank)".to_string());
+                placed.insert(0, ("(start of bank)".to_string(), bank.org, owner));
+            }
+            map.add_symbols(bank.bank_id, placed, end);
+        }
+        map.sum_totals();
+        map
+    }
+
+    /// Map of `.vo` objects linked by `linker::link`; each object's source
+    /// file is a module
+    pub fn from_objects(objects: &[VectrexObject], linked: &LinkedBinary, capacity: usize) -> Self {
+        let mut map = LinkMap::default();
+        let base = linked.sections.first().map_or(0, |s| s.start as usize);
+        map.banks.push(BankUsage {
+            bank: 0,
+            org: base as u16,
+            capacity,
+            used: linked.binary.len(),
+            free: capacity as i64 - linked.binary.len() as i64,
+        });
+
+        let mut placed = Vec::new();
+        let mut linked_sections = linked.sections.iter();
+        for obj in objects {
+            let owner = Owner::Module(obj.header.source_file.clone());
+            for (section_idx, section) in obj.sections.iter().enumerate() {
+                let Some(linked_section) = linked_sections.next() else { break };
+                let start = linked_section.start as u16;
+                if section.section_type == SectionType::Bss {
+                    map.ram.extend(bss_slots(obj, section_idx, start));
+                    continue;
+                }
+                map.sections.push(MapSection {
+                    name: format!("{} {}", obj.header.source_file, section.name),
+                    bank: 0,
+                    address: start,
+                    size: section.size(),
+                });
+                let symbols: Vec<_> = obj.symbols.exports.iter().filter(|s| s.section == Some(section_idx)).collect();
+                if section.size() > 0 && !symbols.iter().any(|s| s.offset == 0) {
+                    placed.push((format!("(start of {})", section.name), start, owner.clone()));
+                }
+                for symbol in symbols {
+                    placed.push((symbol.name.clone(), start.wrapping_add(symbol.offset), owner.clone()));
+                }
+            }
+        }
+        placed.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+        let sections = std::mem::take(&mut map.sections);
+        map.add_symbols(0, placed, base + linked.binary.len());
+        map.sections = sections;
+        map.sum_totals();
+        map
+    }
+
+    /// Symbols of one bank sorted by address; each one runs to the next
+    /// (or to `end`) and consecutive symbols of one owner form a section
+    fn add_symbols(&mut self, bank: usize, placed: Vec<(String, u16, Owner)>, end: usize) {
+        for (i, (name, address, owner)) in placed.iter().enumerate() {
+            let next = placed.get(i + 1).map_or(end, |n| n.1 as usize);
+            let size = next.saturating_sub(*address as usize);
+            self.symbols.push(MapSymbol { name: name.clone(), bank, address: *address, size, owner: owner.clone() });
+
+            let section_name = owner.to_string();
+            match self.sections.last_mut() {
+                Some(last) if last.bank == bank && last.name == section_name => last.size += size,
+                _ => self.sections.push(MapSection { name: section_name, bank, address: *address, size }),
+            }
+        }
+    }
+
+    fn sum_totals(&mut self) {
+        let mut totals: HashMap<&Owner, usize> = HashMap::new();
+        for symbol in &self.symbols {
+            *totals.entry(&symbol.owner).or_default() += symbol.size;
+        }
+        let (mut modules, mut assets, mut runtime) = (Vec::new(), Vec::new(), Vec::new());
+        for (owner, bytes) in totals {
+            match owner {
+                Owner::Module(name) => modules.push(UsageTotal { name: name.clone(), bytes }),
+                Owner::Asset(name) => assets.push(UsageTotal { name: name.clone(), bytes }),
+                Owner::Section(name) => runtime.push(UsageTotal { name: name.clone(), bytes }),
+            }
+        }
+        for list in [&mut modules, &mut assets, &mut runtime] {
+            list.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.name.cmp(&b.name)));
+        }
+        self.modules = modules;
+        self.assets = assets;
+        self.runtime = runtime;
+    }
+
+    /// Bytes used by the variables of the compact region and the fixed ones
+    /// (overlaid variables such as function locals are counted once)
+    pub fn ram_used(&self) -> usize {
+        let bytes: std::collections::BTreeSet<usize> = self.ram.iter()
+            .flat_map(|slot| slot.address as usize..slot.address as usize + slot.size)
+            .collect();
+        bytes.len()
+    }
+
+    /// Free RAM between the compact region and the top of the stack
+    pub fn ram_free(&self) -> usize {
+        let compact = self.ram.iter().filter(|slot| !slot.fixed);
+        match (compact.clone().map(|s| s.address).min(), compact.map(|s| s.address as usize + s.size).max()) {
+            (Some(_), Some(end)) => (STACK_TOP as usize + 1).saturating_sub(end),
+            _ => 0,
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "VPy link map");
+        let _ = writeln!(out, "\nBANKS");
+        let _ = writeln!(out, "  {:<6} {:<7} {:>8} {:>8} {:>8}", "Bank", "Org", "Size", "Used", "Free");
+        for bank in &self.banks {
+            let flag = if bank.free < 0 { "  OVERFLOW" } else { "" };
+            let _ = writeln!(
+                out,
+                "  #{:<5} ${:04X}   {:>8} {:>8} {:>8}{}",
+                bank.bank, bank.org, bank.capacity, bank.used, bank.free, flag
+            );
+        }
+
+        for (title, totals) in [("MODULES", &self.modules), ("ASSETS", &self.assets), ("RUNTIME", &self.runtime)] {
+            if totals.is_empty() {
+                continue;
+            }
+            let _ = writeln!(out, "\n{}", title);
+            for total in totals {
+                let _ = writeln!(out, "  {:<40} {:>8}", total.name, total.bytes);
+            }
+        }
+
+        if !self.ram.is_empty() {
+            let _ = writeln!(out, "\nRAM ({} bytes used, {} free below the stack)", self.ram_used(), self.ram_free());
+            let _ = writeln!(out, "  {:<7} {:>5}  {:<24} Comment", "Address", "Size", "Name");
+            let mut ram: Vec<&RamSlot> = self.ram.iter().collect();
+            ram.sort_by_key(|slot| slot.address);
+            for slot in ram {
+                let fixed = if slot.fixed { " [fixed]" } else { "" };
+                let _ = writeln!(out, "  ${:04X}   {:>5}  {:<24} {}{}", slot.address, slot.size, slot.name, slot.comment, fixed);
+            }
+        }
+
+        let _ = writeln!(out, "\nSECTIONS");
+        let _ = writeln!(out, "  {:<6} {:<7} {:>8}  Section", "Bank", "Address", "Size");
+        for section in &self.sections {
+            let _ = writeln!(out, "  #{:<5} ${:04X}   {:>8}  {}", section.bank, section.address, section.size, section.name);
+        }
+
+        let _ = writeln!(out, "\nSYMBOLS");
+        let _ = writeln!(out, "  {:<6} {:<7} {:>8}  {:<40} Owner", "Bank", "Address", "Size", "Symbol");
+        for symbol in &self.symbols {
+            let _ = writeln!(
+                out,
+                "  #{:<5} ${:04X}   {:>8}  {:<40} {}",
+                symbol.bank, symbol.address, symbol.size, symbol.name, symbol.owner
+            );
+        }
+        out
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    /// Write the text map to `path` and the JSON one next to it (`.map.json`)
+    pub fn write(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, self.to_text())?;
+        std::fs::write(path.with_extension("map.json"), self.to_json())
+    }
+}
+
+/// RAM variables of an object's BSS section placed at `start`: each symbol
+/// runs to the next higher offset, a section without symbols is one slot
+fn bss_slots(obj: &VectrexObject, section_idx: usize, start: u16) -> Vec<RamSlot> {
+    let size = obj.sections[section_idx].size();
+    let symbols: Vec<_> = obj.symbols.exports.iter().filter(|s| s.section == Some(section_idx)).collect();
+    if symbols.is_empty() {
+        let name = format!("{} {}", obj.header.source_file, obj.sections[section_idx].name);
+        let comment = format!("RAM of {}", obj.header.source_file);
+        return vec![RamSlot { name, address: start, size, comment, fixed: false }];
+    }
+    symbols
+        .iter()
+        .map(|symbol| {
+            let end = symbols.iter().map(|s| s.offset as usize).filter(|&o| o > symbol.offset as usize).min().unwrap_or(size);
+            RamSlot {
+                name: symbol.name.clone(),
+                address: start.wrapping_add(symbol.offset),
+                size: end - symbol.offset as usize,
+                comment: format!("RAM of {}", obj.header.source_file),
+                fixed: false,
+            }
+        })
+        .collect()
+}
+
+/// The assembler records each label under its own spelling and in
+/// uppercase; keep one entry per label, preferring the spelling used
+fn unique_labels(symbols: &HashMap<String, u16>) -> Vec<(String, u16)> {
+    let mut labels: BTreeMap<String, (String, u16)> = BTreeMap::new();
+    for (name, &address) in symbols {
+        let key = name.to_uppercase();
+        match labels.get(&key) {
+            Some((existing, _)) if *existing != key => {}
+            _ => {
+                labels.insert(key, (name.clone(), address));
+            }
+        }
+    }
+    labels.into_values().collect()
+}
+
+/// Owner of every label defined in the listing (by uppercase name)
+fn label_owners(
+    asm_source: &str,
+    function_modules: &HashMap<String, String>,
+    assets: &[AssetInfo],
+) -> HashMap<String, Owner> {
+    let module_of = |function: &str| {
+        Owner::Module(function_modules.get(&function.to_uppercase()).cloned().unwrap_or_else(|| "(unknown)".to_string()))
+    };
+    // Longest names first, so `_BUBBLE_LARGE_` is not taken for `_BUBBLE_`
+    let mut prefixes: Vec<(String, &str)> = assets.iter().map(|a| (format!("_{}_", label_name(&a.name)), a.name.as_str())).collect();
+    prefixes.sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+
+    let mut owners = HashMap::new();
+    let mut current = Owner::Section("(header)".to_string());
+    // A bare rule opens a banner, its first comment is the title, the next rule closes it
+    let mut banner = Banner::None;
+    for line in asm_source.lines() {
+        let text = line.trim_end();
+        if text.starts_with(";****") {
+            // `;**** NAME ****` names a section by itself
+            let name = text.trim_matches(|c| c == ';' || c == '*' || c == ' ');
+            if !name.is_empty() {
+                current = Owner::Section(name.to_string());
+            }
+            banner = match banner {
+                Banner::None if name.is_empty() => Banner::Open,
+                _ => Banner::None,
+            };
+            continue;
+        }
+        if let Some(comment) = text.strip_prefix("; ") {
+            if let Some(function) = comment.strip_prefix("Function: ") {
+                current = module_of(function.split(" (Bank").next().unwrap_or(function));
+            } else if comment.starts_with("MAIN PROGRAM") {
+                current = module_of("MAIN");
+            } else if banner == Banner::Open && !comment.starts_with("===") {
+                // `PLAY_SFX_BANKED - Play SFX asset with...`: the name is enough
+                current = Owner::Section(comment.split(" - ").next().unwrap_or(comment).to_string());
+            }
+            if banner == Banner::Open {
+                banner = Banner::Titled;
+            }
+            continue;
+        }
+        banner = Banner::None;
+
+        let Some(label) = text.split_whitespace().next().and_then(|w| w.strip_suffix(':')) else { continue };
+        if line.starts_with(char::is_whitespace) || label.is_empty() {
+            continue;
+        }
+        let upper = label.to_uppercase();
+        if let Some((_, asset)) = prefixes.iter().find(|(prefix, _)| upper.starts_with(prefix.as_str())) {
+            current = Owner::Asset(asset.to_string());
+        }
+        owners.insert(upper, current.clone());
+    }
+    owners
+}
+
+#[derive(PartialEq)]
+enum Banner {
+    None,
+    Open,
+    Titled,
+}
+
+/// Asset names as they appear in labels
+fn label_name(name: &str) -> String {
+    name.chars().map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vpy_codegen::AssetType;
+
+    const LISTING: &str = "\
+;***************************************************************************
+; CARTRIDGE HEADER
+;***************************************************************************
+    FCC \"g GCE 2025\"
+START:
+    JMP MAIN
+RESULT               EQU $C880+$00   ; Main result temporary (2 bytes)
+VAR_ARG0             EQU $CFE0   ; Function argument 0 (16-bit) (2 bytes)
+;***************************************************************************
+; MAIN PROGRAM
+;***************************************************************************
+MAIN:
+    RTS
+LOOP_BODY:
+    JSR PLAYER_MOVE
+    RTS
+; Function: PLAYER_MOVE
+PLAYER_MOVE:
+IF_NEXT_1:
+    RTS
+;***************************************************************************
+; EMBEDDED ASSETS (vectors, music, levels, SFX)
+;***************************************************************************
+_SHIP_VECTORS:
+    FCB 1
+_SHIP_PATH0:
+    FCB 2
+;***************************************************************************
+; RUNTIME HELPERS
+;***************************************************************************
+; Helpers are emitted only when used
+MUL16:
+.LOOP:
+    RTS
+;**** PRINT_TEXT String Data ****
+PRINT_TEXT_STR_1:
+    FCC \"HI\"
+";
+
+    fn bank(symbols: &[(&str, u16)], used: usize, capacity: usize) -> MapBank {
+        MapBank {
+            bank_id: 0,
+            org: 0,
+            capacity,
+            used,
+            symbols: symbols.iter().map(|(n, a)| (n.to_string(), *a)).collect(),
+        }
+    }
+
+    fn function_modules() -> HashMap<String, String> {
+        [("MAIN", "main"), ("PLAYER_MOVE", "player")].iter().map(|(f, m)| (f.to_string(), m.to_string())).collect()
+    }
+
+    #[test]
+    fn test_owners_and_totals() {
+        let assets = vec![AssetInfo { name: "ship".to_string(), path: String::new(), asset_type: AssetType::Vector }];
+        let symbols = [
+            ("START", 10), ("MAIN", 13), ("main", 13), ("LOOP_BODY", 14), ("PLAYER_MOVE", 18), ("IF_NEXT_1", 18),
+            ("_SHIP_VECTORS", 19), ("_SHIP_PATH0", 20), ("MUL16", 21), ("MUL16.LOOP", 21), ("PRINT_TEXT_STR_1", 22),
+        ];
+        let map = LinkMap::from_listing(LISTING, &function_modules(), &assets, &[bank(&symbols, 25, 32768)]);
+
+        let owner = |name: &str| map.symbols.iter().find(|s| s.name == name).unwrap().owner.to_string();
+        assert_eq!(owner("START"), "CARTRIDGE HEADER");
+        assert_eq!(owner("LOOP_BODY"), "module main");
+        assert_eq!(owner("IF_NEXT_1"), "module player");
+        assert_eq!(owner("_SHIP_PATH0"), "asset ship");
+        assert_eq!(owner("MUL16"), "RUNTIME HELPERS");
+        assert_eq!(owner("MUL16.LOOP"), "RUNTIME HELPERS");
+        assert_eq!(owner("PRINT_TEXT_STR_1"), "PRINT_TEXT String Data");
+        // `main` and `MAIN` are one label; the header bytes before START are listed too
+        assert_eq!(map.symbols.iter().filter(|s| s.name.eq_ignore_ascii_case("main")).count(), 1);
+        assert_eq!((map.symbols[0].name.as_str(), map.symbols[0].size), ("(start of bank)", 10));
+
+        let total = |list: &[UsageTotal], name: &str| list.iter().find(|t| t.name == name).unwrap().bytes;
+        assert_eq!(total(&map.modules, "main"), 5);
+        assert_eq!(total(&map.modules, "player"), 1);
+        assert_eq!(total(&map.assets, "ship"), 2);
+        assert_eq!(total(&map.runtime, "PRINT_TEXT String Data"), 3);
+        assert_eq!(map.sections.iter().map(|s| s.size).sum::<usize>(), 25);
+        assert_eq!(map.banks[0].free, 32768 - 25);
+
+        assert_eq!(map.ram.len(), 2);
+        assert_eq!(map.ram_used(), 4);
+        assert_eq!(map.ram_free(), 0xCC00 - 0xC882);
+    }
+
+    #[test]
+    fn test_overflow_and_json() {
+        let map = LinkMap::from_listing(LISTING, &function_modules(), &[], &[bank(&[("START", 0)], 300, 256)]);
+        assert_eq!(map.banks[0].free, -44);
+        assert!(map.to_text().contains("OVERFLOW"));
+
+        let json: serde_json::Value = serde_json::from_str(&map.to_json()).unwrap();
+        assert_eq!(json["banks"][0]["used"], 300);
+        assert_eq!(json["symbols"][0]["owner"]["kind"], "section");
+        assert_eq!(json["ram"][1]["fixed"], true);
+    }
+
+    #[test]
+    fn test_objects() {
+        let main = crate::object_from_asm("START:\n    JSR TWICE\nloop:\n    BRA loop\n", "main.asm").unwrap();
+        let mut lib = crate::object_from_asm("    EXPORT TWICE\nTWICE:\n    ASLB\n    RTS\n", "lib.asm").unwrap();
+        lib.sections.push(crate::object::Section {
+            name: ".bss".to_string(),
+            section_type: SectionType::Bss,
+            bank_hint: None,
+            alignment: 6,
+            data: vec![],
+        });
+        for (name, offset) in [("TWICE_X", 0), ("TWICE_Y", 2), ("OTHER_X", 0)] {
+            lib.symbols.exports.push(crate::object::Symbol {
+                name: name.to_string(),
+                section: Some(1),
+                offset,
+                scope: crate::object::SymbolScope::Local,
+                symbol_type: crate::object::SymbolType::Variable,
+            });
+        }
+        let objects = vec![main, lib];
+        let linked = crate::linker::link(objects.clone(), 0).unwrap();
+        let map = LinkMap::from_objects(&objects, &linked, 32768);
+
+        assert_eq!(map.modules.len(), 2);
+        assert_eq!(map.modules[0].name, "main.asm");
+        assert_eq!(map.modules[0].bytes, 5);
+        let twice = map.symbols.iter().find(|s| s.name == "TWICE").unwrap();
+        assert_eq!((twice.address, twice.size), (5, 2));
+        assert_eq!(map.sections[1].name, "lib.asm .text");
+
+        // Overlaid locals share bytes; the BSS section is RAM, not ROM
+        let ram: Vec<_> = map.ram.iter().map(|s| (s.name.as_str(), s.address, s.size)).collect();
+        assert_eq!(ram, vec![("TWICE_X", 0xC880, 2), ("TWICE_Y", 0xC882, 4), ("OTHER_X", 0xC880, 2)]);
+        assert_eq!(map.ram_used(), 6);
+        assert_eq!(map.banks[0].used, 7);
+    }
+}
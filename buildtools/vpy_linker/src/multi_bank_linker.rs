@@ -11,6 +11,8 @@ use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 
+use crate::link_map::MapBank;
+
 /// Simple label extractor - extracts labels from ASM without full assembly
 /// Returns map of label_name -> offset_in_bytes (approximation)
 #[allow(dead_code)]
@@ -618,6 +620,31 @@ impl MultiBankLinker {
         temp_dir: &Path,
         helper_symbols: &HashMap<String, u16>,
     ) -> Result<Vec<u8>, String> {
+        let (binary, _map_bank) = self.assemble_bank_unpadded(bank_section, temp_dir, helper_symbols)?;
+        self.pad_bank(bank_section.bank_id, binary)
+    }
+
+    /// Fail if a bank does not fit, otherwise pad it to the bank size with 0xFF
+    fn pad_bank(&self, bank_id: u8, mut binary_data: Vec<u8>) -> Result<Vec<u8>, String> {
+        let bank_size = self.rom_bank_size as usize;
+        if binary_data.len() > bank_size {
+            return Err(format!("Bank {} overflow: {} bytes (max: {} bytes)", 
+                bank_id, binary_data.len(), bank_size));
+        }
+        
+        // Pad with 0xFF (standard for unused ROM)
+        binary_data.resize(bank_size, 0xFF);
+        
+        Ok(binary_data)
+    }
+
+    /// Assemble a bank without padding; also returns its labels for the link map
+    fn assemble_bank_unpadded(
+        &self,
+        bank_section: &BankSection,
+        temp_dir: &Path,
+        helper_symbols: &HashMap<String, u16>,
+    ) -> Result<(Vec<u8>, MapBank), String> {
         // Bank ASM already contains everything
         let mut full_asm = bank_section.asm_code.clone();
         
@@ -815,28 +842,21 @@ impl MultiBankLinker {
         
         let _ = org_count; // suppress unused variable warning
         
-        let (binary, _line_map, _symbol_table, _unresolved) = vpy_assembler::m6809::asm_to_binary::assemble_m6809(
+        let (binary, _line_map, symbol_table, _unresolved) = vpy_assembler::m6809::asm_to_binary::assemble_m6809(
             &full_asm_longbranch,
             bank_org,  // Use correct ORG based on bank type
             false,   // Not object mode
             false    // Don't auto-convert (we already did it above)
         ).map_err(|e| format!("Failed to assemble bank {}: {}", bank_section.bank_id, e))?;
 
-        
-        // Get binary data (already Vec<u8>, no .0 field)
-        let mut binary_data = binary;
-        
-        // Pad to bank size (16KB)
-        let bank_size = self.rom_bank_size as usize;
-        if binary_data.len() > bank_size {
-            return Err(format!("Bank {} overflow: {} bytes (max: {} bytes)", 
-                bank_section.bank_id, binary_data.len(), bank_size));
-        }
-        
-        // Pad with 0xFF (standard for unused ROM)
-        binary_data.resize(bank_size, 0xFF);
-        
-        Ok(binary_data)
+        let map_bank = MapBank {
+            bank_id: bank_section.bank_id as usize,
+            org: bank_org,
+            capacity: self.rom_bank_size as usize,
+            used: binary.len(),
+            symbols: symbol_table,
+        };
+        Ok((binary, map_bank))
     }
     
     /// Generate multi-bank ROM from sectioned ASM
@@ -854,6 +874,18 @@ impl MultiBankLinker {
         &self,
         asm_path: &Path,
         output_rom_path: &Path,
+    ) -> Result<HashMap<String, u16>, String> {
+        self.generate_multibank_rom_with_map(asm_path, output_rom_path, &mut Vec::new())
+    }
+
+    /// Same as `generate_multibank_rom`, collecting every assembled bank into
+    /// `map_banks` for the link map. Banks are collected before the overflow
+    /// check, so the map can explain an overflow error.
+    pub fn generate_multibank_rom_with_map(
+        &self,
+        asm_path: &Path,
+        output_rom_path: &Path,
+        map_banks: &mut Vec<MapBank>,
     ) -> Result<HashMap<String, u16>, String> {
         // Read ASM
         let asm_content = fs::read_to_string(asm_path)
@@ -1035,12 +1067,19 @@ impl MultiBankLinker {
         } else {
             0x0000u16
         };
-        let (mut helper_binary, _helper_line_map, _helper_symbol_table, _helper_unresolved) = vpy_assembler::m6809::asm_to_binary::assemble_m6809(
+        let (mut helper_binary, _helper_line_map, helper_symbol_table, _helper_unresolved) = vpy_assembler::m6809::asm_to_binary::assemble_m6809(
             &helper_full_asm_longbranch,
             helper_org,
             false,
             false,
         ).map_err(|e| format!("Failed to assemble helper bank {}: {}", helper_bank_id, e))?;
+        map_banks.push(MapBank {
+            bank_id: helper_bank_id as usize,
+            org: helper_org,
+            capacity: self.rom_bank_size as usize,
+            used: helper_binary.len(),
+            symbols: helper_symbol_table,
+        });
 
         // Pad helper bank to full size
         let bank_size = self.rom_bank_size as usize;
@@ -1263,7 +1302,9 @@ impl MultiBankLinker {
                     .collect();
                 
                 
-                let binary = self.assemble_bank(section, &temp_dir, &external_symbols)?;
+                let (binary, map_bank) = self.assemble_bank_unpadded(section, &temp_dir, &external_symbols)?;
+                map_banks.push(map_bank);
+                let binary = self.pad_bank(section.bank_id, binary)?;
                 rom_data.extend_from_slice(&binary);
             } else {
                 // Empty bank - fill with 0xFF
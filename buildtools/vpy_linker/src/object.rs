@@ -10,7 +10,7 @@ use std::collections::HashMap;
 use std::io::{self, Read, Write};
 
 /// Version of the object file format
-pub const OBJECT_FORMAT_VERSION: u16 = 1;
+pub const OBJECT_FORMAT_VERSION: u16 = 2;
 
 /// Magic number for .vo files ("VObj")
 pub const OBJECT_MAGIC: [u8; 4] = [0x56, 0x4F, 0x62, 0x6A];
@@ -137,6 +137,7 @@ pub enum RelocationType {
 pub struct DebugInfo {
     pub line_map: HashMap<u16, usize>,  // Address → source line
     pub source_lines: Vec<String>,       // Original source code
+    pub symbol_lines: HashMap<String, usize>, // Symbol → line defining it (exports) or first using it (imports)
 }
 
 impl VectrexObject {
@@ -218,6 +219,14 @@ impl VectrexObject {
     pub fn find_symbol(&self, name: &str) -> Option<&Symbol> {
         self.symbols.exports.iter().find(|s| s.name == name)
     }
+
+    /// "file:line" where this object defines or first uses `name` ("file" without line info)
+    pub fn symbol_location(&self, name: &str) -> String {
+        match self.debug_info.symbol_lines.get(name) {
+            Some(line) => format!("{}:{}", self.header.source_file, line),
+            None => self.header.source_file.clone(),
+        }
+    }
 }
 
 #[cfg(test)]
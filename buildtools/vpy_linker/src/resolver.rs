@@ -5,7 +5,7 @@
 //
 // Ported from core/src/linker/resolver.rs
 
-use crate::object::{VectrexObject, RelocationType};
+use crate::object::{VectrexObject, RelocationType, SectionType, SymbolScope};
 use crate::error::{LinkerError, LinkerResult};
 use std::collections::HashMap;
 
@@ -13,6 +13,8 @@ use std::collections::HashMap;
 #[derive(Debug, Clone)]
 pub struct GlobalSymbolTable {
     pub symbols: HashMap<String, ResolvedSymbol>,
+    /// Local symbols by (object index, name): only that object's relocations see them
+    pub locals: HashMap<(usize, String), u16>,
 }
 
 /// A resolved symbol with final address assignment
@@ -25,6 +27,10 @@ pub struct ResolvedSymbol {
     pub object_index: usize,       // Index in the objects array
 }
 
+/// First RAM byte after the BIOS work area: uninitialised (BSS) sections
+/// are placed from here, in the order of the objects
+pub const RAM_BASE: u16 = 0xC880;
+
 pub struct SymbolResolver;
 
 impl SymbolResolver {
@@ -34,26 +40,32 @@ impl SymbolResolver {
     /// Detects duplicate definitions (error if same symbol in multiple objects).
     pub fn collect_symbols(objects: &[VectrexObject]) -> LinkerResult<GlobalSymbolTable> {
         let mut global = GlobalSymbolTable { 
-            symbols: HashMap::new() 
+            symbols: HashMap::new(),
+            locals: HashMap::new(),
         };
         
         for (obj_idx, obj) in objects.iter().enumerate() {
             for symbol in &obj.symbols.exports {
+                if symbol.scope == SymbolScope::Local {
+                    continue;
+                }
+
                 // Check for duplicate definitions
                 if global.symbols.contains_key(&symbol.name) {
                     let existing = &global.symbols[&symbol.name];
                     return Err(LinkerError::DuplicateSymbol {
                         symbol: symbol.name.clone(),
-                        first: existing.source_file.clone(),
-                        second: obj.header.source_file.clone(),
+                        first: objects[existing.object_index].symbol_location(&symbol.name),
+                        second: obj.symbol_location(&symbol.name),
                     });
                 }
                 
-                // Add to global table (address not yet assigned)
+                // Add to global table; symbols outside any section are absolute
+                // (exported equates), the rest are placed in assign_addresses()
                 global.symbols.insert(symbol.name.clone(), ResolvedSymbol {
                     name: symbol.name.clone(),
-                    address: 0,  // Will be assigned in assign_addresses()
-                    section: format!("section_{}", symbol.section.unwrap_or(0)),
+                    address: if symbol.section.is_none() { symbol.offset } else { 0 },
+                    section: symbol.section.map_or_else(|| "absolute".to_string(), |s| format!("section_{}", s)),
                     source_file: obj.header.source_file.clone(),
                     object_index: obj_idx,
                 });
@@ -78,7 +90,7 @@ impl SymbolResolver {
                 if !global.symbols.contains_key(&import.name) {
                     undefined.push((
                         import.name.clone(),
-                        obj.header.source_file.clone(),
+                        obj.symbol_location(&import.name),
                     ));
                 }
             }
@@ -93,7 +105,8 @@ impl SymbolResolver {
     
     /// Step 3: Assign addresses to all sections and update symbol table
     ///
-    /// Walks through all sections sequentially, assigns base addresses.
+    /// Walks through all sections sequentially, assigns base addresses:
+    /// BSS sections to RAM from `RAM_BASE`, the others from `base_address`.
     /// Updates symbol table with final addresses (section_base + symbol.offset).
     /// Returns map of (object_index, section_index) -> base_address for relocation phase.
     pub fn assign_addresses(
@@ -103,26 +116,20 @@ impl SymbolResolver {
     ) -> LinkerResult<HashMap<(usize, usize), u16>> {
         let mut section_bases: HashMap<(usize, usize), u16> = HashMap::new();
         let mut current_address = base_address;
+        let mut ram_address = RAM_BASE;
         
         for (obj_idx, obj) in objects.iter().enumerate() {
             for (section_idx, section) in obj.sections.iter().enumerate() {
+                if section.section_type == SectionType::Bss {
+                    section_bases.insert((obj_idx, section_idx), ram_address);
+                    Self::place_symbols(obj_idx, obj, section_idx, ram_address, global);
+                    ram_address = ram_address.wrapping_add(section.size() as u16);
+                    continue;
+                }
+
                 // Assign section base address
                 section_bases.insert((obj_idx, section_idx), current_address);
-                
-                // Update symbols in this section
-                for symbol in &obj.symbols.exports {
-                    // Check if this symbol belongs to this section
-                    if let Some(sym_section_idx) = symbol.section {
-                        if sym_section_idx == section_idx {
-                            let symbol_address = current_address.wrapping_add(symbol.offset);
-                            
-                            // Update global table
-                            if let Some(global_sym) = global.symbols.get_mut(&symbol.name) {
-                                global_sym.address = symbol_address;
-                            }
-                        }
-                    }
-                }
+                Self::place_symbols(obj_idx, obj, section_idx, current_address, global);
                 
                 // Advance address for next section
                 current_address = current_address.wrapping_add(section.size() as u16);
@@ -138,6 +145,24 @@ impl SymbolResolver {
         Ok(section_bases)
     }
     
+    /// Give the symbols of one section their final address (`base` + offset)
+    pub fn place_symbols(
+        obj_idx: usize,
+        obj: &VectrexObject,
+        section_idx: usize,
+        base: u16,
+        global: &mut GlobalSymbolTable,
+    ) {
+        for symbol in obj.symbols.exports.iter().filter(|s| s.section == Some(section_idx)) {
+            let address = base.wrapping_add(symbol.offset);
+            if symbol.scope == SymbolScope::Local {
+                global.locals.insert((obj_idx, symbol.name.clone()), address);
+            } else if let Some(global_sym) = global.symbols.get_mut(&symbol.name) {
+                global_sym.address = address;
+            }
+        }
+    }
+
     /// Step 4: Apply relocations using resolved symbols
     ///
     /// Patches code/data sections with actual addresses from symbol table.
@@ -153,11 +178,16 @@ impl SymbolResolver {
             let relocations = obj.relocations.clone();
             
             for reloc in relocations {
-                // Lookup symbol address
-                let symbol = global.symbols.get(&reloc.symbol)
-                    .ok_or_else(|| LinkerError::SymbolNotFound { 
-                        symbol: reloc.symbol.clone() 
-                    })?;
+                // Lookup symbol address: the object's own locals first
+                let symbol_address = match global.locals.get(&(obj_idx, reloc.symbol.clone())) {
+                    Some(&address) => address,
+                    None => global.symbols.get(&reloc.symbol)
+                        .ok_or_else(|| LinkerError::SymbolNotFound { 
+                            symbol: reloc.symbol.clone() 
+                        })?
+                        .address,
+                };
+                let location = obj.symbol_location(&reloc.symbol);
                 
                 // Get target section and its base address
                 let section_idx = reloc.section;
@@ -172,7 +202,7 @@ impl SymbolResolver {
                     })?;
                 
                 // Calculate target address with addend
-                let target_address = (symbol.address as i32 + reloc.addend) as u16;
+                let target_address = (symbol_address as i32 + reloc.addend) as u16;
                 let offset = reloc.offset as usize;
                 
                 // Apply relocation based on type
@@ -190,9 +220,17 @@ impl SymbolResolver {
                         }
                     }
                     RelocationType::Relative8 => {
-                        // Calculate PC-relative offset (signed 8-bit)
-                        let pc_address = section_base + reloc.offset + 2;  // PC after instruction
-                        let relative_offset = (target_address as i32 - pc_address as i32) as i8;
+                        // Calculate PC-relative offset (signed 8-bit); reloc.offset is
+                        // the offset byte, the last of the instruction
+                        let pc_address = section_base + reloc.offset + 1;  // PC after instruction
+                        let relative_offset = target_address as i32 - pc_address as i32;
+                        if !(-128..=127).contains(&relative_offset) {
+                            return Err(LinkerError::BranchOutOfRange {
+                                symbol: reloc.symbol.clone(),
+                                location,
+                                distance: relative_offset,
+                            });
+                        }
                         
                         if offset < section.data.len() {
                             section.data[offset] = relative_offset as u8;
@@ -205,7 +243,7 @@ impl SymbolResolver {
                     }
                     RelocationType::Relative16 => {
                         // Calculate PC-relative offset (signed 16-bit)
-                        let pc_address = section_base + reloc.offset + 3;  // PC after LBRA
+                        let pc_address = section_base + reloc.offset + 2;  // PC after the offset word
                         let relative_offset = (target_address as i32 - pc_address as i32) as i16;
                         
                         if offset + 1 < section.data.len() {
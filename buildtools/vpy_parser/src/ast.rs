@@ -19,6 +19,8 @@ pub struct ModuleMeta {
     pub rom_total_size: Option<u32>,
     /// Bank size in bytes (e.g., 16384 for 16KB)
     pub rom_bank_size: Option<u32>,
+    /// Channels sound effects may take from the music (1-3, from C down)
+    pub sfx_slots: Option<u8>,
 }
 
 /// Top-level items in a module
@@ -42,6 +44,7 @@ pub enum Item {
     ExprStatement(Expr),
     Export(ExportDecl),
     StructDef(StructDef),
+    Enum(EnumDef),
 }
 
 /// Vector list entries (for VECTORLIST blocks)
@@ -98,6 +101,24 @@ pub struct FieldDef {
     pub source_line: usize,
 }
 
+/// Enum declaration: `enum GameState: TITLE, MAP, GAME`
+///
+/// Variants are numbered from 0 in declaration order and referenced as
+/// `GameState.MAP`; the unifier replaces each reference with its number.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnumDef {
+    pub name: String,
+    pub variants: Vec<String>,
+    pub source_line: usize,
+}
+
+impl EnumDef {
+    /// Value of a variant (case-insensitive, like every VPy name)
+    pub fn value_of(&self, variant: &str) -> Option<i32> {
+        self.variants.iter().position(|v| v.eq_ignore_ascii_case(variant)).map(|i| i as i32)
+    }
+}
+
 /// Import declaration
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ImportDecl {
@@ -174,6 +195,15 @@ pub enum Stmt {
     Pass {
         source_line: usize,
     },
+    /// `yield` - suspends the enclosing coroutine until the next `CO_RESUME`
+    Yield {
+        source_line: usize,
+    },
+    /// `assert cond` - halts with the line number in the test mailbox when `cond` is false
+    Assert {
+        cond: Expr,
+        source_line: usize,
+    },
     Expr(Expr, usize),
     If {
         cond: Expr,
@@ -208,6 +238,8 @@ impl Stmt {
             Stmt::Break { source_line } => *source_line,
             Stmt::Continue { source_line } => *source_line,
             Stmt::Pass { source_line } => *source_line,
+            Stmt::Yield { source_line } => *source_line,
+            Stmt::Assert { source_line, .. } => *source_line,
             Stmt::Expr(_, source_line) => *source_line,
             Stmt::If { source_line, .. } => *source_line,
             Stmt::Switch { source_line, .. } => *source_line,
@@ -64,7 +64,7 @@ pub fn builtin_arity(name: &str) -> Option<usize> {
         | "UPDATE_LEVEL" | "GET_LEVEL_BOUNDS" => Some(0),
 
         // 1-argument builtins
-        "DRAW_VECTOR" | "PLAY_MUSIC" | "PLAY_SFX" | "ABS" | "LEN" | "ASM" => Some(1),
+        "DRAW_VECTOR" | "PLAY_MUSIC" | "ABS" | "LEN" | "ASM" => Some(1),
 
         // 2-argument builtins
         "MOVE" | "DRAW_TO" => Some(2),
@@ -76,6 +76,7 @@ pub fn builtin_arity(name: &str) -> Option<usize> {
         "DEBUG_PRINT" | "DEBUG_PRINT_LABELED" | "DEBUG_PRINT_STR" => None,
         "DRAW_VECTOR_LIST" | "DRAW_VL" => None,
         "FRAME_BEGIN" => None,
+        "PLAY_SFX" => None, // name [, priority [, channel]]
         "SET_INTENSITY" => Some(1),
         "DRAW_VECTOR_EX" => Some(4),
 
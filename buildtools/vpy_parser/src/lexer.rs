@@ -20,6 +20,8 @@ pub enum TokenKind {
     Break,
     Continue,
     Pass,
+    Yield,
+    Assert,
     Return,
     Const,
     VectorList,
@@ -37,6 +39,7 @@ pub enum TokenKind {
     As,
     Export,
     Struct,
+    Enum,
     Self_,
 
     // Literals
@@ -489,6 +492,8 @@ fn lex_line(line: &str, line_no: usize, out: &mut Vec<Token>) -> ParseResult<()>
                     "break" => TokenKind::Break,
                     "continue" => TokenKind::Continue,
                     "pass" => TokenKind::Pass,
+                    "yield" => TokenKind::Yield,
+                    "assert" => TokenKind::Assert,
                     "const" => TokenKind::Const,
                     "vectorlist" => TokenKind::VectorList,
                     "switch" => TokenKind::Switch,
@@ -508,6 +513,7 @@ fn lex_line(line: &str, line_no: usize, out: &mut Vec<Token>) -> ParseResult<()>
                     "as" => TokenKind::As,
                     "export" => TokenKind::Export,
                     "struct" => TokenKind::Struct,
+                    "enum" => TokenKind::Enum,
                     "self" => TokenKind::Self_,
                     _ => TokenKind::Identifier(ident.to_string()),
                 };
@@ -22,7 +22,7 @@ pub mod lexer;
 pub mod parser;
 
 pub use ast::{
-    AssignTarget, BinOp, CallInfo, CmpOp, Expr, ExportDecl, FieldDef, Function, IdentInfo,
+    AssignTarget, BinOp, CallInfo, CmpOp, EnumDef, Expr, ExportDecl, FieldDef, Function, IdentInfo,
     ImportDecl, ImportedSymbol, ImportSymbols, Item, LogicOp, MethodCallInfo, Module, ModuleMeta,
     Stmt, StructDef, VlEntry,
 };
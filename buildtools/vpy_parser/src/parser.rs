@@ -258,6 +258,11 @@ impl<'a> Parser<'a> {
                         if let Expr::Number(n) = value {
                             meta.rom_bank_size = Some(n as u32);
                         }
+                    } else if key.eq_ignore_ascii_case("SFX_SLOTS") {
+                        match value {
+                            Expr::Number(n @ 1..=3) => meta.sfx_slots = Some(n as u8),
+                            _ => return self.err_here("META SFX_SLOTS must be 1, 2 or 3"),
+                        }
                     }
                     
                     if let Expr::StringLit(s) = &value {
@@ -287,6 +292,12 @@ impl<'a> Parser<'a> {
                     items.push(Item::StructDef(struct_def));
                     continue;
                 }
+                TokenKind::Enum => {
+                    self.advance();
+                    let enum_def = self.parse_enum_def()?;
+                    items.push(Item::Enum(enum_def));
+                    continue;
+                }
                 TokenKind::Def => {
                     self.advance();
                     let func = self.parse_function_def()?;
@@ -346,7 +357,7 @@ impl<'a> Parser<'a> {
             let mut symbols = vec![];
             loop {
                 let name = self.identifier()?;
-                let alias = if self.match_ident_case("AS") {
+                let alias = if self.match_kind(&TokenKind::As) || self.match_ident_case("AS") {
                     Some(self.identifier()?)
                 } else {
                     None
@@ -369,7 +380,7 @@ impl<'a> Parser<'a> {
             self.advance();
             // import module [as alias]
             let module = self.identifier()?;
-            let alias = if self.match_ident_case("AS") {
+            let alias = if self.match_kind(&TokenKind::As) || self.match_ident_case("AS") {
                 Some(self.identifier()?)
             } else {
                 None
@@ -506,6 +517,32 @@ impl<'a> Parser<'a> {
         })
     }
 
+    /// Parse enum definition: enum Name: VARIANT, VARIANT, ...
+    fn parse_enum_def(&mut self) -> ParseResult<EnumDef> {
+        let line = self.current_line();
+        let name = self.identifier()?;
+        self.consume(TokenKind::Colon)?;
+
+        let mut variants: Vec<String> = vec![];
+        loop {
+            let variant = self.identifier()?;
+            if variants.iter().any(|v| v.eq_ignore_ascii_case(&variant)) {
+                return self.err_here(&format!("duplicate variant '{}' in enum '{}'", variant, name));
+            }
+            variants.push(variant);
+            if !self.match_kind(&TokenKind::Comma) || self.check(TokenKind::Newline) {
+                break;
+            }
+        }
+        self.consume(TokenKind::Newline)?;
+
+        Ok(EnumDef {
+            name,
+            variants,
+            source_line: line,
+        })
+    }
+
     /// Parse vectorlist definition
     fn parse_vectorlist(&mut self) -> ParseResult<Item> {
         let name = self.identifier()?;
@@ -835,6 +872,17 @@ impl<'a> Parser<'a> {
                 self.consume(TokenKind::Newline)?;
                 return Ok(Stmt::Pass { source_line: start_line });
             }
+            TokenKind::Yield => {
+                self.advance();
+                self.consume(TokenKind::Newline)?;
+                return Ok(Stmt::Yield { source_line: start_line });
+            }
+            TokenKind::Assert => {
+                self.advance();
+                let cond = self.expression()?;
+                self.consume(TokenKind::Newline)?;
+                return Ok(Stmt::Assert { cond, source_line: start_line });
+            }
             _ => {}
         }
 
@@ -1129,7 +1177,7 @@ impl<'a> Parser<'a> {
         let mut default_block = None;
 
         while !self.check(TokenKind::Dedent) {
-            if self.match_ident_case("CASE") {
+            if self.match_kind(&TokenKind::Case) || self.match_ident_case("CASE") {
                 let case_expr = self.expression()?;
                 self.consume(TokenKind::Colon)?;
                 self.consume(TokenKind::Newline)?;
@@ -1142,7 +1190,7 @@ impl<'a> Parser<'a> {
                 self.consume(TokenKind::Dedent)?;
 
                 cases.push((case_expr, case_body));
-            } else if self.match_ident_case("DEFAULT") {
+            } else if self.match_kind(&TokenKind::Default) || self.match_ident_case("DEFAULT") {
                 self.consume(TokenKind::Colon)?;
                 self.consume(TokenKind::Newline)?;
                 self.consume(TokenKind::Indent)?;
@@ -1290,6 +1338,13 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_meta_sfx_slots() {
+        let module = lex_and_parse("META SFX_SLOTS = 2\n").unwrap();
+        assert_eq!(module.meta.sfx_slots, Some(2));
+        assert!(lex_and_parse("META SFX_SLOTS = 4\n").is_err());
+    }
+
     #[test]
     fn test_parse_function_definition() {
         let code = "def main():\n    x = 42\n";
@@ -1499,4 +1554,60 @@ def loop():
             assert_eq!(module.items.len(), 1, "Expected 1 function");
         }
     }
+
+    #[test]
+    fn test_parse_assert_statement() {
+        let code = r#"def test_add():
+    x = 1 + 2
+    assert x == 3
+"#;
+        let module = lex_and_parse(code).expect("assert should parse");
+        let Item::Function(func) = &module.items[0] else {
+            panic!("Expected function");
+        };
+        match &func.body[1] {
+            Stmt::Assert { cond, source_line } => {
+                assert_eq!(*source_line, 3);
+                assert!(matches!(cond, Expr::Compare { op: CmpOp::Eq, .. }));
+            }
+            other => panic!("Expected assert, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_enum_definition() {
+        let module = lex_and_parse("enum GameState: TITLE, MAP, GAME\n").expect("enum should parse");
+        let Item::Enum(def) = &module.items[0] else {
+            panic!("Expected enum");
+        };
+        assert_eq!(def.name, "GameState");
+        assert_eq!(def.variants, vec!["TITLE", "MAP", "GAME"]);
+        assert_eq!(def.value_of("game"), Some(2));
+        assert!(lex_and_parse("enum E: A, B, A\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_switch_over_enum() {
+        let code = r#"def tick():
+    switch screen:
+        case GameState.TITLE:
+            x = 1
+        case GameState.MAP:
+            x = 2
+        default:
+            x = 3
+"#;
+        let module = lex_and_parse(code).expect("switch should parse");
+        let Item::Function(func) = &module.items[0] else {
+            panic!("Expected function");
+        };
+        match &func.body[0] {
+            Stmt::Switch { cases, default, .. } => {
+                assert_eq!(cases.len(), 2);
+                assert!(matches!(&cases[1].0, Expr::FieldAccess { field, .. } if field == "MAP"));
+                assert!(default.is_some());
+            }
+            other => panic!("Expected switch, got {:?}", other),
+        }
+    }
 }
@@ -0,0 +1,507 @@
+//! Enum resolution across modules
+//!
+//! `enum GameState: TITLE, MAP, GAME` numbers its variants from 0. A module
+//! sees its own enums, the ones it names in `from m import GameState [as S]`,
+//! and `m.GameState.MAP` through `import m [as alias]`. When the defining
+//! module has `export` declarations, only the enums listed there can be
+//! imported. Variant references are replaced with their number before the
+//! modules are merged, so code generation only sees constants.
+//!
+//! `check_switches` runs on the modules before that: a `switch` over a
+//! variable holding an enum, or whose cases name variants of one, must cover
+//! every variant or have a `default`.
+
+use crate::error::{UnifierError, UnifierResult};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use vpy_parser::ast::{AssignTarget, EnumDef, Expr, ImportSymbols, Item, Module, Stmt};
+
+/// Enums visible from one module
+#[derive(Debug, Clone, Default)]
+pub struct EnumScope {
+    /// Enums by the (uppercase) name the module uses for them
+    enums: HashMap<String, EnumDef>,
+    /// Exported enums of the modules imported with `import m [as alias]`, by alias
+    modules: HashMap<String, HashMap<String, EnumDef>>,
+}
+
+impl EnumScope {
+    /// Enums `name` declares or imports
+    pub fn for_module(name: &str, modules: &HashMap<String, Module>) -> UnifierResult<Self> {
+        let Some(module) = modules.get(name) else {
+            return Ok(Self::default());
+        };
+        let mut scope = EnumScope { enums: declared(module), modules: HashMap::new() };
+
+        for import in &module.imports {
+            let Some(source_name) = import.module_path.last() else { continue };
+            // Imports of modules outside the project are reported elsewhere
+            let Some(source) = find_module(modules, source_name) else { continue };
+            let available = declared(source);
+            match &import.symbols {
+                ImportSymbols::Module { alias } => {
+                    let visible = available.into_iter().filter(|(_, def)| is_exported(source, &def.name)).collect();
+                    scope.modules.insert(alias.as_ref().unwrap_or(source_name).to_uppercase(), visible);
+                }
+                ImportSymbols::All => {
+                    scope.enums.extend(available.into_iter().filter(|(_, def)| is_exported(source, &def.name)));
+                }
+                ImportSymbols::Named(symbols) => {
+                    for symbol in symbols {
+                        // Names that are not enums are functions or variables
+                        let Some(def) = available.get(&symbol.name.to_uppercase()) else { continue };
+                        if !is_exported(source, &def.name) {
+                            return Err(UnifierError::Generic(format!(
+                                "{}:{}: enum '{}' is not exported by module '{}'",
+                                name, import.source_line, def.name, source_name
+                            )));
+                        }
+                        scope.enums.insert(symbol.alias.as_ref().unwrap_or(&symbol.name).to_uppercase(), def.clone());
+                    }
+                }
+            }
+        }
+        Ok(scope)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.enums.is_empty() && self.modules.values().all(HashMap::is_empty)
+    }
+
+    /// Enum named by `expr`: `GameState` or `module.GameState`
+    pub fn lookup(&self, expr: &Expr) -> Option<&EnumDef> {
+        match expr {
+            Expr::Ident(ident) => self.enums.get(&ident.name.to_uppercase()),
+            Expr::FieldAccess { target, field, .. } => match target.as_ref() {
+                Expr::Ident(module) => self.modules.get(&module.name.to_uppercase())?.get(&field.to_uppercase()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Enum and value of a variant reference (`GameState.MAP`)
+    fn variant(&self, expr: &Expr) -> Option<(&EnumDef, Option<i32>)> {
+        let Expr::FieldAccess { target, field, .. } = expr else { return None };
+        let def = self.lookup(target)?;
+        Some((def, def.value_of(field)))
+    }
+}
+
+/// Enums declared by a module, by uppercase name
+fn declared(module: &Module) -> HashMap<String, EnumDef> {
+    module
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Enum(def) => Some((def.name.to_uppercase(), def.clone())),
+            _ => None,
+        })
+        .collect()
+}
+
+fn find_module<'a>(modules: &'a HashMap<String, Module>, name: &str) -> Option<&'a Module> {
+    modules
+        .get(name)
+        .or_else(|| modules.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, m)| m))
+}
+
+/// Without `export` declarations everything a module declares is public
+fn is_exported(module: &Module, name: &str) -> bool {
+    let mut exports = module.items.iter().filter_map(|item| match item {
+        Item::Export(export) => Some(export),
+        _ => None,
+    });
+    let mut any = false;
+    let listed = exports.any(|export| {
+        any = true;
+        export.symbols.iter().any(|s| s.eq_ignore_ascii_case(name))
+    });
+    listed || !any
+}
+
+/// Replace every enum variant reference with its value
+pub fn resolve_enums(modules: &mut HashMap<String, Module>) -> UnifierResult<()> {
+    let mut scopes = Vec::new();
+    for name in modules.keys() {
+        let scope = EnumScope::for_module(name, modules)?;
+        if !scope.is_empty() {
+            scopes.push((name.clone(), scope));
+        }
+    }
+    for (name, scope) in scopes {
+        let module = modules.get_mut(&name).expect("scope built from this module");
+        let resolver = Resolver { module: &name, scope: &scope };
+        for item in &mut module.items {
+            resolver.item(item)?;
+        }
+    }
+    Ok(())
+}
+
+struct Resolver<'a> {
+    module: &'a str,
+    scope: &'a EnumScope,
+}
+
+impl Resolver<'_> {
+    fn error(&self, line: usize, message: String) -> UnifierError {
+        UnifierError::Generic(format!("{}:{}: {}", self.module, line, message))
+    }
+
+    fn item(&self, item: &mut Item) -> UnifierResult<()> {
+        match item {
+            Item::Function(func) => self.block(&mut func.body),
+            Item::Const { value, .. } | Item::GlobalLet { value, .. } | Item::ExprStatement(value) => self.expr(value),
+            Item::StructDef(def) => {
+                for method in def.methods.iter_mut().chain(def.constructor.as_mut()) {
+                    self.block(&mut method.body)?;
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn block(&self, stmts: &mut [Stmt]) -> UnifierResult<()> {
+        stmts.iter_mut().try_for_each(|stmt| self.stmt(stmt))
+    }
+
+    fn stmt(&self, stmt: &mut Stmt) -> UnifierResult<()> {
+        match stmt {
+            Stmt::Assign { target, value, .. } | Stmt::CompoundAssign { target, value, .. } => {
+                self.target(target)?;
+                self.expr(value)
+            }
+            Stmt::Let { value, .. } => self.expr(value),
+            Stmt::For { start, end, step, body, .. } => {
+                self.expr(start)?;
+                self.expr(end)?;
+                if let Some(step) = step {
+                    self.expr(step)?;
+                }
+                self.block(body)
+            }
+            Stmt::ForIn { iterable, body, .. } => {
+                self.expr(iterable)?;
+                self.block(body)
+            }
+            Stmt::While { cond, body, .. } => {
+                self.expr(cond)?;
+                self.block(body)
+            }
+            Stmt::Assert { cond, .. } => self.expr(cond),
+            Stmt::Expr(expr, _) | Stmt::Return(Some(expr), _) => self.expr(expr),
+            Stmt::If { cond, body, elifs, else_body, .. } => {
+                self.expr(cond)?;
+                self.block(body)?;
+                for (cond, body) in elifs {
+                    self.expr(cond)?;
+                    self.block(body)?;
+                }
+                else_body.as_mut().map_or(Ok(()), |body| self.block(body))
+            }
+            Stmt::Switch { expr, cases, default, .. } => {
+                self.expr(expr)?;
+                for (value, body) in cases {
+                    self.expr(value)?;
+                    self.block(body)?;
+                }
+                default.as_mut().map_or(Ok(()), |body| self.block(body))
+            }
+            Stmt::Break { .. } | Stmt::Continue { .. } | Stmt::Pass { .. } | Stmt::Yield { .. } | Stmt::Return(None, _) => Ok(()),
+        }
+    }
+
+    fn target(&self, target: &mut AssignTarget) -> UnifierResult<()> {
+        match target {
+            AssignTarget::Ident { .. } => Ok(()),
+            AssignTarget::Index { target, index, .. } => {
+                self.expr(target)?;
+                self.expr(index)
+            }
+            AssignTarget::FieldAccess { target, field, source_line, .. } => match self.scope.lookup(target) {
+                Some(def) => Err(self.error(*source_line, format!("cannot assign to enum variant {}.{}", def.name, field))),
+                None => self.expr(target),
+            },
+        }
+    }
+
+    fn expr(&self, expr: &mut Expr) -> UnifierResult<()> {
+        if let Some((def, value)) = self.scope.variant(expr) {
+            let Expr::FieldAccess { field, source_line, .. } = &*expr else { unreachable!() };
+            let value = value.ok_or_else(|| {
+                self.error(*source_line, format!("enum '{}' has no variant '{}'", def.name, field))
+            })?;
+            *expr = Expr::Number(value);
+            return Ok(());
+        }
+        match expr {
+            Expr::Call(call) => call.args.iter_mut().try_for_each(|arg| self.expr(arg)),
+            Expr::MethodCall(call) => {
+                self.expr(&mut call.target)?;
+                call.args.iter_mut().try_for_each(|arg| self.expr(arg))
+            }
+            Expr::Binary { left, right, .. } | Expr::Compare { left, right, .. } | Expr::Logic { left, right, .. } => {
+                self.expr(left)?;
+                self.expr(right)
+            }
+            Expr::Not(inner) | Expr::BitNot(inner) => self.expr(inner),
+            Expr::List(items) => items.iter_mut().try_for_each(|item| self.expr(item)),
+            Expr::Index { target, index } => {
+                self.expr(target)?;
+                self.expr(index)
+            }
+            Expr::FieldAccess { target, .. } => self.expr(target),
+            Expr::Number(_) | Expr::StringLit(_) | Expr::Ident(_) | Expr::StructInit { .. } => Ok(()),
+        }
+    }
+}
+
+/// A `switch` over enum variants that misses some and has no `default`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwitchWarning {
+    pub module: String,
+    pub line: usize,
+    pub enum_name: String,
+    pub missing: Vec<String>,
+}
+
+impl fmt::Display for SwitchWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: switch over {} has no case for {} and no default",
+            self.module,
+            self.line,
+            self.enum_name,
+            self.missing.join(", ")
+        )
+    }
+}
+
+/// Non-exhaustive `switch` statements over enums, by module and line
+///
+/// The enum of a switch over a variable is the one the variable is
+/// initialised or assigned from (`screen = GameState.TITLE`) anywhere in the
+/// module; other switches use the enum their first variant case names. A
+/// module whose imports cannot be resolved is skipped (`resolve_enums`
+/// reports it).
+pub fn check_switches(modules: &HashMap<String, Module>) -> Vec<SwitchWarning> {
+    let mut names: Vec<&String> = modules.keys().collect();
+    names.sort();
+
+    let mut warnings = Vec::new();
+    for name in names {
+        let Ok(scope) = EnumScope::for_module(name, modules) else { continue };
+        if scope.is_empty() {
+            continue;
+        }
+        let mut stmts = Vec::new();
+        for item in &modules[name].items {
+            match item {
+                Item::Function(func) => collect_stmts(&func.body, &mut stmts),
+                Item::StructDef(def) => {
+                    for method in def.methods.iter().chain(def.constructor.as_ref()) {
+                        collect_stmts(&method.body, &mut stmts);
+                    }
+                }
+                _ => {}
+            }
+        }
+        let typed = variable_enums(&modules[name], &stmts, &scope);
+
+        for switch in &stmts {
+            let Stmt::Switch { expr, cases, default: None, source_line } = switch else { continue };
+            let cased = cases.iter().filter_map(|(value, _)| scope.variant(value));
+            let def = match expr {
+                Expr::Ident(ident) => typed.get(&ident.name.to_uppercase()).cloned().flatten(),
+                _ => None,
+            };
+            let Some(def) = def.or_else(|| cased.clone().next().map(|(def, _)| def)) else { continue };
+            let numbers = cases.iter().filter_map(|(value, _)| match value {
+                Expr::Number(n) => Some(*n),
+                _ => None,
+            });
+            let covered: HashSet<i32> = cased
+                .filter(|(other, _)| other.name == def.name)
+                .filter_map(|(_, value)| value)
+                .chain(numbers)
+                .collect();
+            let missing: Vec<String> = def
+                .variants
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| !covered.contains(&(*i as i32)))
+                .map(|(_, variant)| variant.clone())
+                .collect();
+            if !missing.is_empty() {
+                warnings.push(SwitchWarning {
+                    module: name.clone(),
+                    line: *source_line,
+                    enum_name: def.name.clone(),
+                    missing,
+                });
+            }
+        }
+    }
+    warnings
+}
+
+/// Enum of each variable (uppercase) initialised or assigned from a variant;
+/// `None` when the variants come from different enums
+fn variable_enums<'a>(module: &Module, stmts: &[&Stmt], scope: &'a EnumScope) -> HashMap<String, Option<&'a EnumDef>> {
+    let globals = module.items.iter().filter_map(|item| match item {
+        Item::GlobalLet { name, value, .. } => Some((name, value)),
+        _ => None,
+    });
+    let assignments = stmts.iter().filter_map(|stmt| match stmt {
+        Stmt::Assign { target: AssignTarget::Ident { name, .. }, value, .. } | Stmt::Let { name, value, .. } => Some((name, value)),
+        _ => None,
+    });
+
+    let mut typed: HashMap<String, Option<&EnumDef>> = HashMap::new();
+    for (name, value) in globals.chain(assignments) {
+        let Some((def, _)) = scope.variant(value) else { continue };
+        typed
+            .entry(name.to_uppercase())
+            .and_modify(|known| {
+                if known.is_some_and(|known| known.name != def.name) {
+                    *known = None;
+                }
+            })
+            .or_insert(Some(def));
+    }
+    typed
+}
+
+/// Every statement of a block, nested ones included
+fn collect_stmts<'a>(stmts: &'a [Stmt], out: &mut Vec<&'a Stmt>) {
+    for stmt in stmts {
+        out.push(stmt);
+        match stmt {
+            Stmt::Switch { cases, default, .. } => {
+                for (_, body) in cases {
+                    collect_stmts(body, out);
+                }
+                if let Some(body) = default {
+                    collect_stmts(body, out);
+                }
+            }
+            Stmt::If { body, elifs, else_body, .. } => {
+                collect_stmts(body, out);
+                for (_, body) in elifs {
+                    collect_stmts(body, out);
+                }
+                if let Some(body) = else_body {
+                    collect_stmts(body, out);
+                }
+            }
+            Stmt::For { body, .. } | Stmt::ForIn { body, .. } | Stmt::While { body, .. } => collect_stmts(body, out),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Module {
+        let tokens = vpy_parser::lex(source).unwrap();
+        vpy_parser::parser::parse(tokens, "test.vpy").unwrap()
+    }
+
+    fn project(files: &[(&str, &str)]) -> HashMap<String, Module> {
+        files.iter().map(|(name, source)| (name.to_string(), parse(source))).collect()
+    }
+
+    fn body(modules: &HashMap<String, Module>, module: &str, function: &str) -> Vec<Stmt> {
+        modules[module]
+            .items
+            .iter()
+            .find_map(|item| match item {
+                Item::Function(f) if f.name == function => Some(f.body.clone()),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_variants_become_numbers_across_modules() {
+        let mut modules = project(&[
+            ("states", "enum GameState: TITLE, MAP, GAME\n"),
+            (
+                "main",
+                concat!(
+                    "import states as st\n",
+                    "from states import GameState as S\n",
+                    "def main():\n    x = S.GAME\n    y = st.GameState.map\n",
+                ),
+            ),
+        ]);
+        resolve_enums(&mut modules).unwrap();
+        let body = body(&modules, "main", "main");
+        assert!(matches!(&body[0], Stmt::Assign { value: Expr::Number(2), .. }));
+        assert!(matches!(&body[1], Stmt::Assign { value: Expr::Number(1), .. }));
+    }
+
+    #[test]
+    fn test_unknown_variant_and_unexported_enum() {
+        let mut modules = project(&[("main", "enum Dir: LEFT, RIGHT\ndef main():\n    x = Dir.UP\n")]);
+        let err = resolve_enums(&mut modules).unwrap_err().to_string();
+        assert_eq!(err, "main:3: enum 'Dir' has no variant 'UP'");
+
+        let mut modules = project(&[
+            ("states", "enum Hidden: A, B\nexport tick\ndef tick():\n    pass\n"),
+            ("main", "from states import Hidden\ndef main():\n    pass\n"),
+        ]);
+        let err = resolve_enums(&mut modules).unwrap_err().to_string();
+        assert!(err.contains("enum 'Hidden' is not exported by module 'states'"), "{}", err);
+    }
+
+    #[test]
+    fn test_switch_exhaustiveness() {
+        let modules = project(&[(
+            "main",
+            concat!(
+                "enum GameState: TITLE, MAP, GAME\n",
+                "screen = 0\n",
+                "def loop():\n",
+                "    switch screen:\n",
+                "        case GameState.TITLE:\n",
+                "            screen = 1\n",
+                "        case GameState.MAP:\n",
+                "            switch screen:\n",
+                "                case GameState.GAME:\n",
+                "                    pass\n",
+                "                default:\n",
+                "                    pass\n",
+            ),
+        )]);
+        let warnings = check_switches(&modules);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].to_string(), "main:4: switch over GameState has no case for GAME and no default");
+
+        // The variable's enum wins over the first case's
+        let modules = project(&[(
+            "main",
+            concat!(
+                "enum GameState: TITLE, MAP, GAME\n",
+                "enum Dir: LEFT, RIGHT\n",
+                "screen = GameState.TITLE\n",
+                "def loop():\n",
+                "    switch screen:\n",
+                "        case Dir.LEFT:\n",
+                "            pass\n",
+                "        case GameState.MAP:\n",
+                "            pass\n",
+                "        case 2:\n",
+                "            pass\n",
+            ),
+        )]);
+        let warnings = check_switches(&modules);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].to_string(), "main:5: switch over GameState has no case for TITLE and no default");
+    }
+}
@@ -5,6 +5,7 @@
 //! # Module Structure
 //!
 //! - `error.rs`: Error types (UnifierError, UnifierResult)
+//! - `enums.rs`: Enum visibility, variant resolution and `switch` exhaustiveness
 //! - `graph.rs`: Module dependency graph with cycle detection
 //! - `resolver.rs`: Symbol resolver for unified naming
 //! - `visitor.rs`: AST visitor pattern for custom passes
@@ -18,6 +19,7 @@
 //!
 //! # Phases
 //!
+//! 0. **Enums**: Replace `Enum.VARIANT` with its value (imports/exports honoured)
 //! 1. **Load & Graph**: Load all .vpy files, build dependency graph
 //! 2. **Cycle Detection**: Reject circular imports
 //! 3. **Topological Sort**: Determine merge order (dependencies first)
@@ -26,6 +28,7 @@
 //! 6. **Reference Fixing**: Update all calls/accesses to use renamed symbols
 //! 7. **Validation**: Ensure all symbols are defined
 
+pub mod enums;
 pub mod error;
 pub mod graph;
 pub mod resolver;
@@ -55,9 +58,12 @@ pub use vpy_parser::ast::{Module, Item, ImportDecl};
 /// 5. Merge into single module
 /// 6. Validate all references
 pub fn unify_modules(
-    modules: std::collections::HashMap<String, Module>,
+    mut modules: std::collections::HashMap<String, Module>,
     entry_module: &str,
 ) -> UnifierResult<Module> {
+    // Phase 3.0: Enum variants become constants while each module's imports are still known
+    enums::resolve_enums(&mut modules)?;
+
     // Phase 3.1: Build graph
     let mut graph = ModuleGraph::new();
     for (name, module) in modules {
@@ -133,6 +139,12 @@ fn rename_item_symbols(item: &Item, prefix: &str, _resolver: &SymbolResolver) ->
                 source_line: *source_line,
             }
         }
+        Item::Enum(def) => {
+            Item::Enum(vpy_parser::ast::EnumDef {
+                name: apply_prefix(prefix, &def.name),
+                ..def.clone()
+            })
+        }
         _ => item.clone(),  // Other items unchanged
     }
 }
@@ -150,6 +162,12 @@ fn rewrite_stmt_with_prefix(stmt: vpy_parser::ast::Stmt, prefix: &str) -> vpy_pa
                 source_line,
             }
         }
+        Stmt::Assert { cond, source_line } => {
+            Stmt::Assert {
+                cond: rewrite_expr_with_prefix(cond, prefix),
+                source_line,
+            }
+        }
         // Add other statement types as needed...
         _ => stmt,  // TODO: Handle other statement types
     }
@@ -197,6 +215,29 @@ fn rewrite_expr_with_prefix(expr: vpy_parser::ast::Expr, prefix: &str) -> vpy_pa
                 index: Box::new(rewrite_expr_with_prefix(*index, prefix)),
             }
         }
+        Expr::Binary { left, op, right } => {
+            Expr::Binary {
+                left: Box::new(rewrite_expr_with_prefix(*left, prefix)),
+                op,
+                right: Box::new(rewrite_expr_with_prefix(*right, prefix)),
+            }
+        }
+        Expr::Compare { left, op, right } => {
+            Expr::Compare {
+                left: Box::new(rewrite_expr_with_prefix(*left, prefix)),
+                op,
+                right: Box::new(rewrite_expr_with_prefix(*right, prefix)),
+            }
+        }
+        Expr::Logic { op, left, right } => {
+            Expr::Logic {
+                op,
+                left: Box::new(rewrite_expr_with_prefix(*left, prefix)),
+                right: Box::new(rewrite_expr_with_prefix(*right, prefix)),
+            }
+        }
+        Expr::Not(operand) => Expr::Not(Box::new(rewrite_expr_with_prefix(*operand, prefix))),
+        Expr::BitNot(operand) => Expr::BitNot(Box::new(rewrite_expr_with_prefix(*operand, prefix))),
         // Literals don't need rewriting
         _ => expr,
     }
@@ -299,6 +340,12 @@ fn rewrite_stmt(stmt: vpy_parser::ast::Stmt, resolver: &SymbolResolver) -> vpy_p
         Stmt::Return(value, source_line) => {
             Stmt::Return(value.map(|v| rewrite_expr(v, resolver)), source_line)
         }
+        Stmt::Assert { cond, source_line } => {
+            Stmt::Assert {
+                cond: rewrite_expr(cond, resolver),
+                source_line,
+            }
+        }
         Stmt::Expr(expr, source_line) => {
             Stmt::Expr(rewrite_expr(expr, resolver), source_line)
         }
@@ -489,7 +536,7 @@ fn rewrite_expr(expr: vpy_parser::ast::Expr, resolver: &SymbolResolver) -> vpy_p
 mod tests {
     use super::*;
     use std::collections::HashMap;
-    use vpy_parser::ast::ModuleMeta;
+    use vpy_parser::ast::{Expr, ModuleMeta, Stmt};
 
     #[test]
     fn test_unify_single_module() {
@@ -591,4 +638,32 @@ mod tests {
         assert!(util_idx < input_idx);
         assert!(input_idx < main_idx);
     }
+
+    #[test]
+    fn test_module_prefix_applies_inside_assert() {
+        let source = "gravity = 2\n\ndef test_gravity():\n    assert gravity == 2\n";
+        let tokens = vpy_parser::lex(source).unwrap();
+        let physics = vpy_parser::parser::parse(tokens, "physics.vpy").unwrap();
+        let main = Module {
+            items: vec![],
+            meta: ModuleMeta::default(),
+            imports: vec![],
+        };
+
+        let mut modules = HashMap::new();
+        modules.insert("main".to_string(), main);
+        modules.insert("physics".to_string(), physics);
+        let unified = unify_modules(modules, "main").unwrap();
+
+        let func = unified.items.iter().find_map(|item| match item {
+            Item::Function(f) if f.name == "PHYSICS_TEST_GRAVITY" => Some(f),
+            _ => None,
+        }).expect("prefixed test function");
+        match &func.body[0] {
+            Stmt::Assert { cond: Expr::Compare { left, .. }, .. } => {
+                assert!(matches!(&**left, Expr::Ident(ident) if ident.name == "PHYSICS_GRAVITY"));
+            }
+            other => panic!("expected assert, got {:?}", other),
+        }
+    }
 }
@@ -58,13 +58,15 @@ pub struct ExportDecl {
 pub enum Item { 
     Function(Function), 
     Const { name: String, value: Expr, source_line: usize }, 
-    GlobalLet { name: String, value: Expr, source_line: usize }, 
+    GlobalLet { name: String, type_annotation: Option<String>, value: Expr, source_line: usize }, 
     VectorList { name: String, entries: Vec<VlEntry> },
     ExprStatement(Expr),  // Para permitir expresiones ejecutables en top-level
     /// Declaración de export explícita
     Export(ExportDecl),
     /// Definición de struct
     StructDef(StructDef),
+    /// Definición de enum
+    Enum(EnumDef),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -84,6 +86,8 @@ pub struct Function {
 	pub name: String, 
 	pub line: usize,  // Starting line number of function definition
 	#[allow(dead_code)] pub params: Vec<String>, 
+	/// Anotaciones de tipo de los parámetros (`def f(x: u8)`); vacío o None = sin anotar
+	pub param_types: Vec<Option<String>>,
 	pub body: Vec<Stmt> 
 }
 
@@ -98,18 +102,35 @@ pub struct StructDef {
 	pub source_line: usize,
 }
 
+/// Definición de enum: `enum GameState: TITLE, MAP, GAME`
+/// Las variantes valen 0, 1, 2... y cada `GameState.MAP` se sustituye por su número
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnumDef {
+	pub name: String,
+	pub variants: Vec<String>,
+	pub source_line: usize,
+}
+
+impl EnumDef {
+	/// Valor de una variante (sin distinguir mayúsculas)
+	pub fn value_of(&self, variant: &str) -> Option<i32> {
+		self.variants.iter().position(|v| v.eq_ignore_ascii_case(variant)).map(|i| i as i32)
+	}
+}
+
 /// Campo de un struct
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct FieldDef {
 	pub name: String,
-	pub type_annotation: Option<String>,  // "int", nombre de otro struct, etc.
+	pub type_annotation: Option<String>,  // "int", "u8", "bool", nombre de otro struct, etc.
 	pub source_line: usize,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Stmt {
 	Assign { target: AssignTarget, value: Expr, source_line: usize },
-	Let { name: String, value: Expr, source_line: usize },
+	/// Declaración local anotada: `x: u8 = 0`
+	Let { name: String, type_annotation: Option<String>, value: Expr, source_line: usize },
 	For { var: String, start: Expr, end: Expr, step: Option<Expr>, body: Vec<Stmt>, source_line: usize },
 	ForIn { var: String, iterable: Expr, body: Vec<Stmt>, source_line: usize },
 	While { cond: Expr, body: Vec<Stmt>, source_line: usize },
@@ -176,6 +197,8 @@ pub struct MethodCallInfo {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Expr {
 	Number(i32),
+	/// Fixed-point literal, raw 8.8 value (1.5 -> 384)
+	Fixed(i32),
 	StringLit(String),
 	Ident(IdentInfo),
 	Call(CallInfo),
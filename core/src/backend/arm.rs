@@ -1,574 +1,1160 @@
-use crate::ast::{BinOp, CmpOp, Expr, Function, Item, Module, Stmt};
+// ARM backend shared by the Pitrex (ARM11, A32) and the Cortex-M carts
+// (VecFever / Vextreme, Thumb-2, see cortexm.rs).
+//
+// Output is GNU `as` unified syntax, linked against the platform's vector SDK
+// glue named in `TargetInfo` (init, line routine, wait_recal, ...). The emitted
+// `main` is C-callable: SDK init, global initialisers, the user's main(), then
+// the SDK wait_recal + the user's loop() forever.
+//
+// Conventions:
+// - values are 16-bit like on the 6809, kept sign-extended in 32-bit registers;
+//   every variable, array element and struct field takes one word
+// - user functions are `vpy_<name>`, methods `vpy_<Struct>_<method>` with the
+//   instance address as first argument, constructors `vpy_<Struct>_init`
+// - calls follow AAPCS (r0-r3, then stack); unknown functions are called by
+//   their plain name so SDK routines can be used directly
+// - locals live in a frame addressed through r7; expression temporaries are
+//   pushed in 8-byte steps so sp stays AAPCS-aligned at every call
+// - struct and array variables evaluate to their address; arrays keep their
+//   length in the word before the first element (for LEN and for-in)
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::ast::{AssignTarget, BinOp, CallInfo, CmpOp, Expr, Function, IdentInfo, Item, LogicOp, MethodCallInfo, Module, Stmt};
 use super::string_literals::{collect_string_literals, escape_ascii};
-use crate::codegen::CodegenOptions;
 use crate::backend::trig::emit_trig_tables;
+use crate::codegen::{is_builtin, CodegenOptions, Diagnostic, DiagnosticCode, DiagnosticSeverity};
 use crate::target::{Target, TargetInfo};
 
-// emit: entry point for ARM backend assembly generation.
-pub fn emit(module: &Module, _t: Target, ti: &TargetInfo, opts: &CodegenOptions) -> String {
-    let mut out = String::new();
-    let syms = collect_symbols(module);
-    let string_map = collect_string_literals(module); // literal text -> label
-    out.push_str(&format!("; --- ARM backend (PiTrex) --- title='{}' origin={} ---\n", opts.title, ti.origin));
-    out.push_str(";***************************************************************************\n; DEFINE SECTION (ARM)\n;***************************************************************************\n");
-    out.push_str("; (No BIOS header required for PiTrex)\n\n");
-    out.push_str(";***************************************************************************\n; CODE SECTION\n;***************************************************************************\n");
-    out.push_str("; Entry point\n.global _start\n_start:\n    BL pitrex_init ; engine init placeholder\n    BL main\n1:  B 1b ; loop\n\n");
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Isa { Arm, Thumb2 }
+
+// emit: entry point for the Pitrex
+pub fn emit(module: &Module, t: Target, ti: &TargetInfo, opts: &CodegenOptions) -> (String, Vec<Diagnostic>) {
+    emit_program(module, t, ti, opts, Isa::Arm)
+}
+
+// emit_program: lower the whole module for the given instruction set.
+// Builtins the target cannot run are errors; everything else it had to skip is a warning.
+pub(crate) fn emit_program(module: &Module, _t: Target, ti: &TargetInfo, opts: &CodegenOptions, isa: Isa) -> (String, Vec<Diagnostic>) {
+    let mut cg = ArmCodegen::new(module, ti, isa);
+    let mut code = String::new();
+
+    let functions: Vec<&Function> = module.items.iter().filter_map(|i| if let Item::Function(f) = i { Some(f) } else { None }).collect();
+    let has = |name: &str| functions.iter().any(|f| f.name == name);
+    let needs_init = cg.emit_global_init(module);
+    code.push_str(&cg.entry_point(opts, needs_init, has("main"), has("loop")));
+    code.push_str(&std::mem::take(&mut cg.out));
+
     for item in &module.items {
         match item {
-            Item::Function(f) => emit_function(f, &mut out, &string_map),
-            Item::Const { name, value } => if let Expr::Number(n) = value { out.push_str(&format!(".equ {} , {}\n", name, n & 0xFFFF)); },
-            Item::GlobalLet { name, value } => if let Expr::Number(n) = value { out.push_str(&format!("GVAR_{}: .word {}\n", name.to_uppercase(), n & 0xFFFF)); } else { out.push_str(&format!("GVAR_{}: .word 0\n", name.to_uppercase())); },
-            Item::VectorList { name, .. } => { out.push_str(&format!("; vectorlist {} ignored on ARM backend (NYI)\n", name)); },
-            Item::ExprStatement(_) => { out.push_str("; ExprStatement ignored on ARM backend (TODO: implement top-level execution)\n"); },
-            Item::Export(_) => { /* Export declarations are metadata, no code needed */ },
-            Item::StructDef(_) => { out.push_str("; struct definition ignored on ARM backend (Phase 3)\n"); },
-        }
-    }
-    out.push_str(";***************************************************************************\n; RUNTIME SECTION\n;***************************************************************************\n; Runtime helpers\n");
-    out.push_str(
-    "__mul32:\n    PUSH {r2,r3,lr}\n    MOV r2,#0\n    CMP r1,#0\n    BEQ __mul32_done\n__mul32_loop:\n    AND r3,r1,#1\n    CMP r3,#0\n    BEQ __mul32_skip\n    ADD r2,r2,r0\n__mul32_skip:\n    LSR r1,r1,#1\n    LSL r0,r0,#1\n    CMP r1,#0\n    BNE __mul32_loop\n__mul32_done:\n    MOV r0,r2\n    POP {r2,r3,lr}\n    BX lr\n\n",
-    );
-    out.push_str(
-        "__div32:\n    PUSH {r2,r3,lr}\n    MOV r2,#0\n    CMP r1,#0\n    BEQ __div32_done\n    MOV r3,r0\n__div32_loop:\n    CMP r3,r1\n    BLT __div32_done\n    SUB r3,r3,r1\n    ADD r2,r2,#1\n    B __div32_loop\n__div32_done:\n    MOV r0,r2\n    POP {r2,r3,lr}\n    BX lr\n\n",
-    );
-    out.push_str(";***************************************************************************\n; DATA SECTION\n;***************************************************************************\n; Data segment (prototype)\n.data\n");
-    // Shared trig tables
-    out.push_str("; Trig tables (shared)\n");
-    emit_trig_tables(&mut out, ".hword");
-    for v in syms { out.push_str(&format!("VAR_{}: .word 0\n", v.to_uppercase())); }
-    if !string_map.is_empty() { out.push_str("; String literals (null-terminated)\n"); }
-    for (lit, label) in &string_map {
-        out.push_str(&format!("{}: .ascii \"{}\"\n    .byte 0\n", label, escape_ascii(lit)));
-    }
-    out.push_str("; Call arg scratch (if needed by future ABI changes)\nVAR_ARG0: .word 0\nVAR_ARG1: .word 0\nVAR_ARG2: .word 0\nVAR_ARG3: .word 0\n");
-    out
+            Item::Function(f) => cg.emit_function(&function_symbol(&f.name), &f.params, &f.body, None),
+            Item::StructDef(sd) => {
+                for m in &sd.methods {
+                    let params: Vec<String> = std::iter::once("self".to_string()).chain(m.params.iter().cloned()).collect();
+                    cg.emit_function(&method_symbol(&sd.name, &m.name), &params, &m.body, Some(&sd.name));
+                }
+                if let Some(ctor) = &sd.constructor {
+                    let params: Vec<String> = std::iter::once("self".to_string()).chain(ctor.params.iter().cloned()).collect();
+                    cg.emit_function(&method_symbol(&sd.name, "init"), &params, &ctor.body, Some(&sd.name));
+                }
+            }
+            Item::VectorList { name, .. } => cg.warn(format!("vectorlist {} is not supported on {}", name, ti.name)),
+            Item::Const { .. } | Item::GlobalLet { .. } | Item::ExprStatement(_) | Item::Export(_) | Item::Enum(_) => {}
+        }
+        code.push_str(&std::mem::take(&mut cg.out));
+    }
+    cg.emit_runtime();
+    code.push_str(&std::mem::take(&mut cg.out));
+
+    let mut out = String::new();
+    out.push_str(&format!("@ --- {} backend ({}) --- title='{}' origin={} ---\n", if isa == Isa::Thumb2 { "Cortex-M" } else { "ARM" }, ti.name, opts.title, ti.origin));
+    out.push_str(&format!(
+        "@ SDK: {}, {}, {}, {}, {}, {}, {}\n",
+        ti.init_label, ti.line_routine, ti.sdk.wait_recal, ti.sdk.intensity, ti.sdk.text, ti.sdk.buttons, ti.sdk.joystick
+    ));
+    out.push_str("    .syntax unified\n");
+    out.push_str(if isa == Isa::Thumb2 { "    .thumb\n" } else { "    .arm\n" });
+    out.push_str("    .text\n\n");
+    out.push_str(&code);
+    out.push_str(&cg.emit_data(module));
+    let mut diagnostics = std::mem::take(&mut cg.errors);
+    if ti.experimental {
+        diagnostics.push(warning(format!(
+            "{} is experimental: the SDK glue ({}, {}, ...) is not part of this repo, link against the platform SDK",
+            ti.name, ti.init_label, ti.line_routine
+        )));
+    }
+    diagnostics.extend(cg.warnings.iter().cloned().map(warning));
+    (out, diagnostics)
 }
 
-// emit_function: outputs assembly for a single function including label and tail return.
-fn emit_function(f: &Function, out: &mut String, string_map: &std::collections::BTreeMap<String,String>) {
-    out.push_str(&format!(".global {0}\n{0}:\n", f.name));
-    // Collect local variables declared via 'let'
-    let locals = collect_locals(&f.body);
-    let frame_size = (locals.len() as i32) * 2; // 2 bytes per 16-bit local
-    if frame_size > 0 { out.push_str(&format!("    SUB sp, sp, #{}\n", frame_size)); }
-    // Parameter prologue (still stored in globals for now)
-    for (i, p) in f.params.iter().enumerate().take(4) {
-        out.push_str(&format!("    LDR r4, =VAR_{}\n    STR r{} , [r4]\n", p.to_uppercase(), i));
-    }
-    let fctx = FuncCtx { locals: locals.clone(), frame_size };
-    for stmt in &f.body { emit_stmt(stmt, out, &LoopCtx::default(), &fctx, string_map); }
-    if !matches!(f.body.last(), Some(Stmt::Return(_, _))) {
-        if frame_size > 0 { out.push_str(&format!("    ADD sp, sp, #{}\n", frame_size)); }
-        out.push_str("    BX LR\n");
-    }
-    out.push('\n');
+fn warning(message: String) -> Diagnostic {
+    Diagnostic { severity: DiagnosticSeverity::Warning, code: DiagnosticCode::UnsupportedBuiltin, message, line: None, col: None }
 }
 
-#[derive(Default, Clone)]
-struct LoopCtx { start: Option<String>, end: Option<String>, }
+fn function_symbol(name: &str) -> String { format!("vpy_{}", sanitize(name)) }
+fn method_symbol(struct_name: &str, method: &str) -> String { format!("vpy_{}_{}", sanitize(struct_name), sanitize(method)) }
+fn global_label(name: &str) -> String { format!("VAR_{}", sanitize(name).to_uppercase()) }
+fn sanitize(name: &str) -> String { name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' }).collect() }
+
+// wrap16: literals follow the 6809 backend's 16-bit semantics
+fn wrap16(n: i32) -> i32 { n as i16 as i32 }
 
-struct FuncCtx { locals: Vec<String>, frame_size: i32 }
-impl FuncCtx {
-    fn offset_of(&self, name: &str) -> Option<i32> { self.locals.iter().position(|n| n == name).map(|i| (i as i32)*2) }
+// Storage shape of a variable. Arrays and structs evaluate to their address.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Storage { Scalar, Array(usize), Struct(String) }
+
+impl Storage {
+    fn words(&self, structs: &BTreeMap<String, StructInfo>) -> usize {
+        match self {
+            Storage::Scalar => 1,
+            Storage::Array(n) => n + 1,
+            Storage::Struct(s) => structs.get(s).map(|si| si.fields.len()).unwrap_or(0).max(1),
+        }
+    }
 }
 
-// emit_stmt: lowers high-level statements into ARM instructions with structured labels.
-fn emit_stmt(stmt: &Stmt, out: &mut String, loop_ctx: &LoopCtx, fctx: &FuncCtx, string_map: &std::collections::BTreeMap<String,String>) {
-    match stmt {
-        Stmt::Assign { target, value, .. } => {
-            match target {
-                crate::ast::AssignTarget::Ident { name, .. } => {
-                    emit_expr(value, out, fctx, string_map);
-                    if let Some(off) = fctx.offset_of(name) {
-                        out.push_str(&format!("    STRH r0, [sp, #{}]\n", off));
-                    } else {
-                        out.push_str(&format!("    LDR r1, =VAR_{0}\n    STR r0, [r1]\n", name.to_uppercase()));
-                    }
+struct StructInfo { fields: Vec<String>, methods: BTreeSet<String>, has_init: bool }
+
+struct Global { label: String, storage: Storage }
+
+#[derive(Default)]
+struct Frame {
+    slots: HashMap<String, (i32, Storage)>,
+    size: i32,
+    ret: String,
+    self_struct: Option<String>,
+    // (continue target, break target) of the enclosing loops
+    loops: Vec<(String, String)>,
+}
+
+struct ArmCodegen<'a> {
+    isa: Isa,
+    ti: &'a TargetInfo,
+    out: String,
+    labels: usize,
+    strings: BTreeMap<String, String>,
+    consts: HashMap<String, i32>,
+    // string constants, inlined as the literal's address
+    const_strings: HashMap<String, String>,
+    globals: BTreeMap<String, Global>,
+    functions: BTreeSet<String>,
+    structs: BTreeMap<String, StructInfo>,
+    helpers: BTreeSet<&'static str>,
+    uses_trig: bool,
+    warnings: BTreeSet<String>,
+    errors: Vec<Diagnostic>,
+}
+
+impl<'a> ArmCodegen<'a> {
+    fn new(module: &Module, ti: &'a TargetInfo, isa: Isa) -> Self {
+        let mut structs = BTreeMap::new();
+        let mut consts = HashMap::new();
+        let mut const_strings = HashMap::new();
+        let mut globals = BTreeMap::new();
+        let mut functions = BTreeSet::new();
+        for item in &module.items {
+            if let Item::StructDef(sd) = item {
+                structs.insert(sd.name.clone(), StructInfo {
+                    fields: sd.fields.iter().map(|f| f.name.clone()).collect(),
+                    methods: sd.methods.iter().map(|m| m.name.clone()).collect(),
+                    has_init: sd.constructor.is_some(),
+                });
+            }
+        }
+        for item in &module.items {
+            match item {
+                Item::Function(f) => { functions.insert(f.name.clone()); }
+                Item::Const { name, value: Expr::Number(n), .. } => { consts.insert(name.clone(), wrap16(*n)); }
+                Item::Const { name, value: Expr::StringLit(lit), .. } => { const_strings.insert(name.clone(), lit.clone()); }
+                Item::Const { name, value, .. } | Item::GlobalLet { name, value, .. } => {
+                    let storage = match value {
+                        Expr::List(elems) => Storage::Array(elems.len()),
+                        Expr::StructInit { struct_name, .. } if structs.contains_key(struct_name) => Storage::Struct(struct_name.clone()),
+                        Expr::Call(ci) if structs.contains_key(&ci.name) => Storage::Struct(ci.name.clone()),
+                        _ => Storage::Scalar,
+                    };
+                    globals.insert(name.clone(), Global { label: global_label(name), storage });
                 }
-                crate::ast::AssignTarget::Index { .. } => panic!("Array assignment not implemented for ARM backend"),
-                crate::ast::AssignTarget::FieldAccess { .. } => panic!("Field access assignment not implemented for ARM backend (Phase 3)"),
-            }
-        },
-        Stmt::Let { name, value, .. } => {
-            emit_expr(value, out, fctx, string_map);
-            if let Some(off) = fctx.offset_of(name) { out.push_str(&format!("    STRH r0, [sp, #{}]\n", off)); }
-        },
-        Stmt::Expr(e, _) => emit_expr(e, out, fctx, string_map),
-        Stmt::Return(expr_opt, _) => {
-            if let Some(e) = expr_opt { emit_expr(e, out, fctx, string_map); }
-            if fctx.frame_size > 0 { out.push_str(&format!("    ADD sp, sp, #{}\n", fctx.frame_size)); }
-            out.push_str("    BX LR\n");
-        },
-        Stmt::Break { .. } => {
-            if let Some(end) = &loop_ctx.end { out.push_str(&format!("    B {}\n", end)); }
-            else { out.push_str("    ; break outside loop\n"); }
-        },
-        Stmt::Continue { .. } => {
-            if let Some(start) = &loop_ctx.start { out.push_str(&format!("    B {}\n", start)); }
-            else { out.push_str("    ; continue outside loop\n"); }
-        },
-        Stmt::Pass { .. } => {
-            out.push_str("    ; pass (no-op)\n");
-        },
-        Stmt::While { cond, body, .. } => {
-            let lbl_start = fresh_label("WH");
-            let lbl_end = fresh_label("WH_END");
-            out.push_str(&format!("{}:\n", lbl_start));
-            emit_expr(cond, out, fctx, string_map);
-            out.push_str(&format!("    CMP r0, #0\n    BEQ {}\n", lbl_end));
-            let inner = LoopCtx { start: Some(lbl_start.clone()), end: Some(lbl_end.clone()) };
-            for s in body { emit_stmt(s, out, &inner, fctx, string_map); }
-            out.push_str(&format!("    B {}\n{}:\n", lbl_start, lbl_end));
-        },
-        Stmt::For { var, start, end, step, body, .. } => {
-            let loop_label = fresh_label("FOR");
-            let end_label = fresh_label("FOR_END");
-            emit_expr(start, out, fctx, string_map);
-            if let Some(off) = fctx.offset_of(var) { out.push_str(&format!("    STRH r0, [sp, #{}]\n", off)); }
-            else { out.push_str(&format!("    LDR r1, =VAR_{0}\n    STR r0, [r1]\n", var.to_uppercase())); }
-            out.push_str(&format!("{}:\n", loop_label));
-            if let Some(off) = fctx.offset_of(var) { out.push_str(&format!("    LDRH r1, [sp, #{}]\n", off)); }
-            else { out.push_str(&format!("    LDR r1, =VAR_{}\n    LDR r1, [r1]\n", var.to_uppercase())); }
-            emit_expr(end, out, fctx, string_map);
-            out.push_str(&format!("    CMP r1, r0\n    BGE {}\n", end_label));
-            let inner = LoopCtx { start: Some(loop_label.clone()), end: Some(end_label.clone()) };
-            for s in body { emit_stmt(s, out, &inner, fctx, string_map); }
-            if let Some(se) = step { emit_expr(se, out, fctx, string_map); } else { out.push_str("    MOV r0, #1\n"); }
-            if let Some(off) = fctx.offset_of(var) {
-                out.push_str(&format!("    LDRH r3, [sp, #{}]\n    ADD r3, r3, r0\n    AND r3, r3, #0xFFFF\n    STRH r3, [sp, #{}]\n", off, off));
-            } else {
-                out.push_str(&format!("    LDR r2, =VAR_{}\n    LDR r3, [r2]\n    ADD r3, r3, r0\n    STR r3, [r2]\n", var.to_uppercase()));
-            }
-            out.push_str(&format!("    B {}\n{}:\n", loop_label, end_label));
-        },
-        Stmt::If { cond, body, elifs, else_body, .. } => {
-            let end_label = fresh_label("IF_END");
-            let mut next_label = fresh_label("IF_NEXT");
-            emit_expr(cond, out, fctx, string_map);
-            out.push_str(&format!("    CMP r0, #0\n    BEQ {}\n", next_label));
-            for s in body { emit_stmt(s, out, loop_ctx, fctx, string_map); }
-            out.push_str(&format!("    B {}\n", end_label));
-            for (i, (econd, ebody)) in elifs.iter().enumerate() {
-                out.push_str(&format!("{}:\n", next_label));
-                let new_next = if i == elifs.len() - 1 && else_body.is_none() { end_label.clone() } else { fresh_label("IF_NEXT") };
-                emit_expr(econd, out, fctx, string_map);
-                out.push_str(&format!("    CMP r0, #0\n    BEQ {}\n", new_next));
-                for s in ebody { emit_stmt(s, out, loop_ctx, fctx, string_map); }
-                out.push_str(&format!("    B {}\n", end_label));
-                next_label = new_next;
-            }
-            if let Some(eb) = else_body {
-                out.push_str(&format!("{}:\n", next_label));
-                for s in eb { emit_stmt(s, out, loop_ctx, fctx, string_map); }
-            } else if !elifs.is_empty() {
-                out.push_str(&format!("{}:\n", next_label));
-            }
-            out.push_str(&format!("{}:\n", end_label));
-        },
-        Stmt::Switch { expr, cases, default, .. } => {
-            emit_expr(expr, out, fctx, string_map);
-            out.push_str("    MOV r4, r0\n");
-            let end_label = fresh_label("SW_END");
-            let default_label = if default.is_some() { Some(fresh_label("SW_DEF")) } else { None };
-            let mut numeric_cases: Vec<(i32,&Vec<Stmt>)> = Vec::new();
-            let mut all_numeric = true;
-            for (ce, body) in cases { if let Expr::Number(n) = ce { numeric_cases.push((*n, body)); } else { all_numeric = false; break; } }
-            if all_numeric && numeric_cases.len() >= 3 {
-                numeric_cases.sort_by_key(|(v,_)| *v);
-                let min = numeric_cases.first().unwrap().0 & 0xFFFF;
-                let max = numeric_cases.last().unwrap().0 & 0xFFFF;
-                let span = (max - min) as usize + 1;
-                if span <= numeric_cases.len()*2 { // density heuristic
-                    let table_label = fresh_label("SW_JT");
-                    out.push_str(&format!("    MOV r5,#{}\n    CMP r4,r5\n    BLT {}\n", min, default_label.as_ref().unwrap_or(&end_label)));
-                    out.push_str(&format!("    MOV r5,#{}\n    CMP r4,r5\n    BGT {}\n", max, default_label.as_ref().unwrap_or(&end_label)));
-                    if min != 0 { out.push_str(&format!("    SUB r4,r4,#{}\n", min)); }
-                    out.push_str(&format!("    LDR r5, ={}\n    LSL r4,r4,#2\n    ADD r5,r5,r4\n    LDR r5, [r5]\n    MOV r15,r5 ; jump indirect\n", table_label));
-                    let mut label_map: std::collections::BTreeMap<i32,String> = std::collections::BTreeMap::new();
-                    for (val, _b) in &numeric_cases { label_map.insert(*val, fresh_label("SW_CASE")); }
-                    for (val, body) in &numeric_cases {
-                        let lbl = label_map.get(val).unwrap();
-                        out.push_str(&format!("{}:\n", lbl));
-                        for s in *body { emit_stmt(s, out, loop_ctx, fctx, string_map); }
-                        out.push_str(&format!("    B {}\n", end_label));
-                    }
-                    if let Some(dl) = &default_label {
-                        out.push_str(&format!("{}:\n", dl));
-                        for s in default.as_ref().unwrap() { emit_stmt(s, out, loop_ctx, fctx, string_map); }
-                    }
-                    out.push_str(&format!("{}:\n", end_label));
-                    out.push_str(&format!("{}:\n", table_label));
-                    for offset in 0..span as i32 {
-                        let actual = (min + offset) & 0xFFFF;
-                        if let Some(lbl) = label_map.get(&actual) { out.push_str(&format!("    .word {}\n", lbl)); }
-                        else if let Some(dl) = &default_label { out.push_str(&format!("    .word {}\n", dl)); }
-                        else { out.push_str(&format!("    .word {}\n", end_label)); }
+                _ => {}
+            }
+        }
+        ArmCodegen {
+            isa, ti, out: String::new(), labels: 0,
+            strings: collect_string_literals(module),
+            consts, const_strings, globals, functions, structs,
+            helpers: BTreeSet::new(), uses_trig: false, warnings: BTreeSet::new(), errors: Vec::new(),
+        }
+    }
+
+    fn line(&mut self, s: &str) { self.out.push_str("    "); self.out.push_str(s); self.out.push('\n'); }
+    fn label(&mut self, l: &str) { self.out.push_str(l); self.out.push_str(":\n"); }
+    fn fresh(&mut self, prefix: &str) -> String { self.labels += 1; format!(".L{}{}", prefix, self.labels) }
+    fn warn(&mut self, msg: String) {
+        self.out.push_str(&format!("    @ {}\n", msg));
+        self.warnings.insert(msg);
+    }
+
+    fn function_header(&mut self, sym: &str, global: bool) {
+        self.out.push_str("    .balign 4\n");
+        if global { self.out.push_str(&format!("    .global {}\n", sym)); }
+        self.out.push_str(&format!("    .type {}, %function\n", sym));
+        if self.isa == Isa::Thumb2 { self.out.push_str("    .thumb_func\n"); }
+        self.label(sym);
+    }
+
+    // entry_point: C-callable main driving the per-frame loop
+    fn entry_point(&mut self, opts: &CodegenOptions, needs_init: bool, has_main: bool, has_loop: bool) -> String {
+        let saved = std::mem::take(&mut self.out);
+        self.function_header("main", true);
+        self.line("PUSH {r7, lr}");
+        self.line("MOV r7, sp");
+        self.line(&format!("BL {}", self.ti.init_label));
+        if needs_init { self.line("BL __vpy_init"); }
+        if has_main { self.line("BL vpy_main"); }
+        if opts.auto_loop {
+            self.label(".Lframe");
+            self.line(&format!("BL {}", self.ti.sdk.wait_recal));
+            if has_loop { self.line("BL vpy_loop"); }
+            self.line("B .Lframe");
+        } else {
+            if has_loop { self.line("BL vpy_loop"); }
+            self.label(".Lhang");
+            self.line("B .Lhang");
+        }
+        self.out.push('\n');
+        std::mem::replace(&mut self.out, saved)
+    }
+
+    // emit_global_init: non-constant global initialisers, struct construction and
+    // top-level expression statements, run once before main(). Left in self.out.
+    fn emit_global_init(&mut self, module: &Module) -> bool {
+        let mut body: Vec<Stmt> = Vec::new();
+        for item in &module.items {
+            match item {
+                Item::GlobalLet { name, value, source_line, .. } => {
+                    let static_value = matches!(value, Expr::Number(_) | Expr::StringLit(_))
+                        || matches!(value, Expr::List(elems) if elems.iter().all(|e| self.static_word(e).is_some()));
+                    if !static_value {
+                        body.push(Stmt::Assign { target: AssignTarget::Ident { name: name.clone(), source_line: *source_line, col: 0 }, value: value.clone(), source_line: *source_line });
                     }
-                    return;
                 }
+                Item::ExprStatement(e) => body.push(Stmt::Expr(e.clone(), 0)),
+                _ => {}
             }
-            let mut case_labels = Vec::new();
-            for _ in cases { case_labels.push(fresh_label("SW_CASE")); }
-            for ((case_expr, _), lbl) in cases.iter().zip(case_labels.iter()) {
-                emit_expr(case_expr, out, fctx, string_map);
-                out.push_str("    MOV r5, r0\n    CMP r4, r5\n");
-                out.push_str(&format!("    BEQ {}\n", lbl));
+        }
+        if body.is_empty() { return false; }
+        self.emit_function("__vpy_init", &[], &body, None);
+        true
+    }
+
+    // ---------------------------------------------------------------- functions
+
+    fn emit_function(&mut self, sym: &str, params: &[String], body: &[Stmt], self_struct: Option<&str>) {
+        let mut f = Frame { ret: format!(".L{}_ret", sym), self_struct: self_struct.map(str::to_string), ..Frame::default() };
+        let mut offset = 0;
+        for p in params {
+            f.slots.insert(p.clone(), (offset, Storage::Scalar));
+            offset += 4;
+        }
+        for (name, storage) in self.collect_locals(body, params) {
+            let words = storage.words(&self.structs) as i32;
+            // arrays are addressed past their length word
+            f.slots.insert(name, (offset, storage));
+            offset += words * 4;
+        }
+        f.size = (offset + 7) & !7;
+
+        self.function_header(sym, false);
+        self.line("PUSH {r7, lr}");
+        if f.size > 0 { self.add_imm("sp", "sp", -f.size); }
+        self.line("MOV r7, sp");
+        for (i, _) in params.iter().enumerate() {
+            if i < 4 {
+                self.line(&format!("STR r{}, [r7, #{}]", i, i * 4));
+            } else {
+                self.line(&format!("LDR r0, [r7, #{}]", f.size + 8 + (i as i32 - 4) * 4));
+                self.line(&format!("STR r0, [r7, #{}]", i * 4));
             }
-            if let Some(dl) = &default_label { out.push_str(&format!("    B {}\n", dl)); } else { out.push_str(&format!("    B {}\n", end_label)); }
-            for ((_, body), lbl) in cases.iter().zip(case_labels.iter()) {
-                out.push_str(&format!("{}:\n", lbl));
-                for s in body { emit_stmt(s, out, loop_ctx, fctx, string_map); }
-                out.push_str(&format!("    B {}\n", end_label));
+        }
+        let mut arrays: Vec<(i32, usize)> = f.slots.values().filter_map(|(off, s)| if let Storage::Array(n) = s { Some((*off, *n)) } else { None }).collect();
+        arrays.sort();
+        for (off, n) in arrays {
+            self.load_imm("r0", n as i32);
+            self.line(&format!("STR r0, [r7, #{}]", off));
+        }
+        for (i, s) in body.iter().enumerate() {
+            match s {
+                // falls through to the epilogue
+                Stmt::Return(value, _) if i + 1 == body.len() => { if let Some(e) = value { self.expr(&mut f, e); } }
+                _ => self.stmt(&mut f, s),
             }
-            if let Some(dl) = default_label {
-                out.push_str(&format!("{}:\n", dl));
-                for s in default.as_ref().unwrap() { emit_stmt(s, out, loop_ctx, fctx, string_map); }
+        }
+        let ret = f.ret.clone();
+        self.label(&ret);
+        self.line("MOV sp, r7");
+        if f.size > 0 { self.add_imm("sp", "sp", f.size); }
+        self.line("POP {r7, pc}");
+        self.line(".ltorg");
+        self.out.push('\n');
+    }
+
+    // collect_locals: names assigned in the body that are not globals, in order of
+    // first appearance, with the storage implied by their first assignment
+    fn collect_locals(&self, body: &[Stmt], params: &[String]) -> Vec<(String, Storage)> {
+        fn walk(cg: &ArmCodegen, s: &Stmt, params: &[String], out: &mut Vec<(String, Storage)>) {
+            let mut add = |name: &str, value: Option<&Expr>| {
+                if params.iter().any(|p| p == name) || cg.globals.contains_key(name) || out.iter().any(|(n, _)| n == name) { return; }
+                let storage = match value {
+                    Some(Expr::List(elems)) => Storage::Array(elems.len()),
+                    Some(v) => cg.constructed_struct(v).map(|(s, _)| Storage::Struct(s.to_string())).unwrap_or(Storage::Scalar),
+                    None => Storage::Scalar,
+                };
+                out.push((name.to_string(), storage));
+            };
+            match s {
+                Stmt::Let { name, value, .. } => add(name, Some(value)),
+                Stmt::Assign { target: AssignTarget::Ident { name, .. }, value, .. } => add(name, Some(value)),
+                Stmt::For { var, .. } | Stmt::ForIn { var, .. } => add(var, None),
+                _ => {}
             }
-            out.push_str(&format!("{}:\n", end_label));
-        },
-        Stmt::CompoundAssign { .. } => panic!("CompoundAssign should be transformed away before emit_stmt"),
-        _ => panic!("Unsupported statement type in ARM backend"),
+            match s {
+                Stmt::If { body, elifs, else_body, .. } => {
+                    for b in body { walk(cg, b, params, out); }
+                    for (_, eb) in elifs { for b in eb { walk(cg, b, params, out); } }
+                    if let Some(eb) = else_body { for b in eb { walk(cg, b, params, out); } }
+                }
+                Stmt::While { body, .. } | Stmt::For { body, .. } | Stmt::ForIn { body, .. } => { for b in body { walk(cg, b, params, out); } }
+                Stmt::Switch { cases, default, .. } => {
+                    for (_, cb) in cases { for b in cb { walk(cg, b, params, out); } }
+                    if let Some(db) = default { for b in db { walk(cg, b, params, out); } }
+                }
+                _ => {}
+            }
+        }
+        let mut out = Vec::new();
+        for s in body { walk(self, s, params, &mut out); }
+        out
     }
-}
 
-// emit_expr: produces expression value in r0 with 16-bit masking semantics.
-fn emit_expr(expr: &Expr, out: &mut String, fctx: &FuncCtx, string_map: &std::collections::BTreeMap<String,String>) {
-    match expr {
-        Expr::Number(n) => out.push_str(&format!("    MOV r0, #{}\n", *n)),
-        Expr::StringLit(s) => {
-            if let Some(label) = string_map.get(s) {
-                out.push_str(&format!("    LDR r0, ={}\n", label));
-            } else {
-                out.push_str("    MOV r0,#0 ; missing string label (should not happen)\n");
+    // --------------------------------------------------------------- statements
+
+    fn stmt(&mut self, f: &mut Frame, s: &Stmt) {
+        match s {
+            Stmt::Assign { target, value, .. } => self.assign(f, target, value),
+            Stmt::Let { name, value, source_line, .. } => {
+                let target = AssignTarget::Ident { name: name.clone(), source_line: *source_line, col: 0 };
+                self.assign(f, &target, value);
+            }
+            Stmt::Expr(e, _) => self.expr(f, e),
+            Stmt::Pass { .. } => {}
+            Stmt::Return(value, _) => {
+                if let Some(e) = value { self.expr(f, e); }
+                self.line(&format!("B {}", f.ret));
+            }
+            Stmt::Break { .. } => match f.loops.last() {
+                Some((_, brk)) => { let brk = brk.clone(); self.line(&format!("B {}", brk)); }
+                None => self.warn("break outside loop".to_string()),
+            },
+            Stmt::Continue { .. } => match f.loops.last() {
+                Some((cont, _)) => { let cont = cont.clone(); self.line(&format!("B {}", cont)); }
+                None => self.warn("continue outside loop".to_string()),
+            },
+            Stmt::While { cond, body, .. } => {
+                let head = self.fresh("while");
+                let end = self.fresh("wend");
+                self.label(&head);
+                self.branch_if_false(f, cond, &end);
+                self.loop_body(f, body, &head, &end);
+                self.line(&format!("B {}", head));
+                self.label(&end);
+            }
+            Stmt::For { var, start, end, step, body, source_line } => {
+                let head = self.fresh("for");
+                let next = self.fresh("fnext");
+                let done = self.fresh("fend");
+                let var_expr = Expr::Ident(IdentInfo { name: var.clone(), source_line: *source_line, col: 0 });
+                self.expr(f, start);
+                self.store_var(f, var, "r0");
+                self.label(&head);
+                let descending = matches!(step, Some(Expr::Number(n)) if *n < 0);
+                let op = if descending { CmpOp::Gt } else { CmpOp::Lt };
+                self.branch_if_false(f, &Expr::Compare { op, left: Box::new(var_expr.clone()), right: Box::new(end.clone()) }, &done);
+                self.loop_body(f, body, &next, &done);
+                self.label(&next);
+                let one = Expr::Number(1);
+                let inc = Expr::Binary { op: BinOp::Add, left: Box::new(var_expr), right: Box::new(step.clone().unwrap_or(one)) };
+                self.expr(f, &inc);
+                self.store_var(f, var, "r0");
+                self.line(&format!("B {}", head));
+                self.label(&done);
+            }
+            Stmt::ForIn { var, iterable, body, .. } => {
+                // [sp] = array address, [sp, #4] = index
+                let head = self.fresh("forin");
+                let next = self.fresh("finext");
+                let done = self.fresh("finend");
+                self.expr(f, iterable);
+                self.line("SUB sp, sp, #8");
+                self.line("STR r0, [sp]");
+                self.line("MOV r0, #0");
+                self.line("STR r0, [sp, #4]");
+                self.label(&head);
+                self.line("LDR r1, [sp]");
+                self.line("LDR r0, [sp, #4]");
+                self.line("LDR r2, [r1, #-4]");
+                self.line("CMP r0, r2");
+                self.line(&format!("BGE {}", done));
+                self.line("LDR r0, [r1, r0, LSL #2]");
+                self.store_var(f, var, "r0");
+                self.loop_body(f, body, &next, &done);
+                self.label(&next);
+                self.line("LDR r0, [sp, #4]");
+                self.line("ADD r0, r0, #1");
+                self.line("STR r0, [sp, #4]");
+                self.line(&format!("B {}", head));
+                self.label(&done);
+                self.line("ADD sp, sp, #8");
+            }
+            Stmt::If { cond, body, elifs, else_body, .. } => {
+                let end = self.fresh("endif");
+                let mut next = self.fresh("else");
+                self.branch_if_false(f, cond, &next);
+                for s in body { self.stmt(f, s); }
+                for (econd, ebody) in elifs {
+                    self.line(&format!("B {}", end));
+                    self.label(&next);
+                    next = self.fresh("else");
+                    self.branch_if_false(f, econd, &next);
+                    for s in ebody { self.stmt(f, s); }
+                }
+                if let Some(eb) = else_body {
+                    self.line(&format!("B {}", end));
+                    self.label(&next);
+                    for s in eb { self.stmt(f, s); }
+                } else {
+                    self.label(&next);
+                }
+                self.label(&end);
+            }
+            Stmt::Switch { expr, cases, default, .. } => {
+                // The switch value stays at [sp] until a case is selected
+                let end = self.fresh("swend");
+                self.expr(f, expr);
+                self.push_r0();
+                let case_labels: Vec<String> = cases.iter().map(|_| self.fresh("case")).collect();
+                for ((value, _), lbl) in cases.iter().zip(&case_labels) {
+                    self.expr(f, value);
+                    self.line("LDR r1, [sp]");
+                    self.line("CMP r1, r0");
+                    self.line(&format!("BEQ {}", lbl));
+                }
+                self.line("ADD sp, sp, #8");
+                if let Some(db) = default { for s in db { self.stmt(f, s); } }
+                self.line(&format!("B {}", end));
+                for ((_, body), lbl) in cases.iter().zip(&case_labels) {
+                    self.label(lbl);
+                    self.line("ADD sp, sp, #8");
+                    for s in body { self.stmt(f, s); }
+                    self.line(&format!("B {}", end));
+                }
+                self.label(&end);
+            }
+            Stmt::CompoundAssign { target, op, value, source_line } => {
+                // Normally rewritten by optimize_module; lower it here for completeness
+                let current = match target {
+                    AssignTarget::Ident { name, source_line, col } => Expr::Ident(IdentInfo { name: name.clone(), source_line: *source_line, col: *col }),
+                    AssignTarget::Index { target, index, .. } => Expr::Index { target: target.clone(), index: index.clone() },
+                    AssignTarget::FieldAccess { target, field, source_line, col } => Expr::FieldAccess { target: target.clone(), field: field.clone(), source_line: *source_line, col: *col },
+                };
+                let value = Expr::Binary { op: *op, left: Box::new(current), right: Box::new(value.clone()) };
+                self.stmt(f, &Stmt::Assign { target: target.clone(), value, source_line: *source_line });
             }
         }
-        Expr::Ident(name) => {
-            if let Some(off) = fctx.offset_of(&name.name) {
-                out.push_str(&format!("    LDRH r0, [sp, #{}]\n", off));
-            } else {
-                out.push_str(&format!("    LDR r0, =VAR_{}\n    LDR r0, [r0]\n", name.name.to_uppercase()));
-            }
-        }
-        Expr::List(_) => panic!("Array literals not implemented for ARM backend"),
-        Expr::Index { .. } => panic!("Array indexing not implemented for ARM backend"),
-        Expr::Call(ci) => {
-            if emit_builtin_call_arm(&ci.name, &ci.args, out, fctx, string_map) { return; }
-            let limit = ci.args.len().min(4);
-            for idx in (0..limit).rev() { emit_expr(&ci.args[idx], out, fctx, string_map); if idx!=0 { out.push_str(&format!("    MOV r{} , r0\n", idx)); } }
-            out.push_str(&format!("    BL {}\n", ci.name));
-        },
-        Expr::Binary { op, left, right } => {
-            if let BinOp::Mul = op {
-                if let Expr::Number(n) = &**right {
-                    if *n > 0 && (*n & (*n - 1)) == 0 {
-                        if let Some(shift) = (0..=16).find(|s| (1 << s) == *n) {
-                            emit_expr(left, out, fctx, string_map);
-                            out.push_str(&format!("    LSL r0, r0, #{}\n    AND r0, r0, #0xFFFF\n", shift));
-                            return;
-                        }
+    }
+
+    fn loop_body(&mut self, f: &mut Frame, body: &[Stmt], cont: &str, brk: &str) {
+        f.loops.push((cont.to_string(), brk.to_string()));
+        for s in body { self.stmt(f, s); }
+        f.loops.pop();
+    }
+
+    fn assign(&mut self, f: &mut Frame, target: &AssignTarget, value: &Expr) {
+        match target {
+            AssignTarget::Ident { name, source_line, col } => {
+                let storage = self.storage_of(f, name);
+                if let Some((struct_name, args)) = self.constructed_struct(value) {
+                    if storage == Some(Storage::Struct(struct_name.to_string())) {
+                        let this = Expr::Ident(IdentInfo { name: name.clone(), source_line: *source_line, col: *col });
+                        self.construct(f, struct_name, &this, args);
+                        return;
                     }
+                    self.warn(format!("{} must be constructed into a plain variable", struct_name));
+                    return;
                 }
-                if let Expr::Number(n) = &**left {
-                    if *n > 0 && (*n & (*n - 1)) == 0 {
-                        if let Some(shift) = (0..=16).find(|s| (1 << s) == *n) {
-                            emit_expr(right, out, fctx, string_map);
-                            out.push_str(&format!("    LSL r0, r0, #{}\n    AND r0, r0, #0xFFFF\n", shift));
-                            return;
-                        }
+                if let (Some(Storage::Array(len)), Expr::List(elems)) = (&storage, value) {
+                    if elems.len() != *len { self.warn(format!("array {} resized from {} to {} elements", name, len, elems.len())); }
+                    for (i, e) in elems.iter().enumerate().take(*len) {
+                        self.expr(f, e);
+                        self.load_ident(f, name, "r1");
+                        self.line(&format!("STR r0, [r1, #{}]", i * 4));
                     }
+                    return;
+                }
+                self.expr(f, value);
+                self.store_var(f, name, "r0");
+            }
+            AssignTarget::Index { target, index, .. } => {
+                self.expr(f, value);
+                self.push_r0();
+                self.element_address(f, target, index);
+                self.line("MOV r1, r0");
+                self.pop("r0");
+                self.line("STR r0, [r1]");
+            }
+            AssignTarget::FieldAccess { target, field, .. } => {
+                let offset = self.field_offset(f, target, field);
+                self.expr(f, value);
+                if let Some(base) = self.local_struct_slot(f, target) {
+                    self.line(&format!("STR r0, [r7, #{}]", base + offset));
+                    return;
+                }
+                if self.is_simple(f, target) {
+                    self.load_simple(f, target, "r1");
+                } else {
+                    self.push_r0();
+                    self.expr(f, target);
+                    self.line("MOV r1, r0");
+                    self.pop("r0");
+                }
+                self.line(&format!("STR r0, [r1, #{}]", offset));
+            }
+        }
+    }
+
+    // construct: zero the struct at `this` and run its constructor
+    fn construct(&mut self, f: &mut Frame, struct_name: &str, this: &Expr, args: &[Expr]) {
+        let (fields, has_init) = match self.structs.get(struct_name) { Some(si) => (si.fields.len(), si.has_init), None => (0, false) };
+        self.load_simple(f, this, "r0");
+        self.line("MOV r1, #0");
+        for i in 0..fields { self.line(&format!("STR r1, [r0, #{}]", i * 4)); }
+        if has_init {
+            let all: Vec<Expr> = std::iter::once(this.clone()).chain(args.iter().cloned()).collect();
+            self.call(f, &method_symbol(struct_name, "init"), &all);
+        } else if !args.is_empty() {
+            self.warn(format!("{} has no __init__, constructor arguments ignored", struct_name));
+        }
+    }
+
+    // constructed_struct: `Name()` / `Name(args)` where Name is a struct
+    fn constructed_struct<'e>(&self, e: &'e Expr) -> Option<(&'e str, &'e [Expr])> {
+        match e {
+            Expr::StructInit { struct_name, .. } if self.structs.contains_key(struct_name) => Some((struct_name.as_str(), &[])),
+            Expr::Call(ci) if self.structs.contains_key(&ci.name) && !self.functions.contains(&ci.name) => Some((ci.name.as_str(), ci.args.as_slice())),
+            _ => None,
+        }
+    }
+
+    // -------------------------------------------------------------- expressions
+
+    // expr: evaluate into r0
+    fn expr(&mut self, f: &mut Frame, e: &Expr) {
+        match e {
+            Expr::Number(_) | Expr::Fixed(_) | Expr::StringLit(_) | Expr::Ident(_) => self.load_simple(f, e, "r0"),
+            Expr::Call(ci) => self.call_expr(f, ci),
+            Expr::MethodCall(mc) => self.method_call(f, mc),
+            Expr::Binary { op, left, right } => self.binary(f, *op, left, right),
+            Expr::Compare { .. } => {
+                let done = self.fresh("cmp");
+                self.emit_compare(f, e, &done, true);
+                self.line("MOV r0, #1");
+                self.label(&done);
+            }
+            Expr::Logic { op, left, right } => {
+                let short = self.fresh("sc");
+                let done = self.fresh("scend");
+                let (branch, short_value, long_value) = match op { LogicOp::And => ("BEQ", 0, 1), LogicOp::Or => ("BNE", 1, 0) };
+                for side in [left, right] {
+                    self.expr(f, side);
+                    self.line("CMP r0, #0");
+                    self.line(&format!("{} {}", branch, short));
+                }
+                self.line(&format!("MOV r0, #{}", long_value));
+                self.line(&format!("B {}", done));
+                self.label(&short);
+                self.line(&format!("MOV r0, #{}", short_value));
+                self.label(&done);
+            }
+            Expr::Not(inner) => {
+                let done = self.fresh("not");
+                self.expr(f, inner);
+                self.line("CMP r0, #0");
+                self.line("MOV r0, #0");
+                self.line(&format!("BNE {}", done));
+                self.line("MOV r0, #1");
+                self.label(&done);
+            }
+            Expr::BitNot(inner) => {
+                self.expr(f, inner);
+                self.line("MVN r0, r0");
+            }
+            Expr::Index { target, index } => {
+                if let (Expr::Number(i), true) = (&**index, self.is_simple(f, target)) {
+                    self.load_simple(f, target, "r0");
+                    self.line(&format!("LDR r0, [r0, #{}]", i * 4));
+                } else {
+                    self.element_address(f, target, index);
+                    self.line("LDR r0, [r0]");
                 }
-            } else if let BinOp::Div = op {
-                if let Expr::Number(n) = &**right {
-                    if *n > 0 && (*n & (*n - 1)) == 0 {
-                        if let Some(shift) = (0..=16).find(|s| (1 << s) == *n) {
-                            emit_expr(left, out, fctx, string_map);
-                            out.push_str(&format!("    LSR r0, r0, #{}\n    AND r0, r0, #0xFFFF\n", shift));
-                            return;
-                        }
+            }
+            Expr::FieldAccess { target, field, .. } => {
+                let offset = self.field_offset(f, target, field);
+                match self.local_struct_slot(f, target) {
+                    Some(base) => self.line(&format!("LDR r0, [r7, #{}]", base + offset)),
+                    None => {
+                        self.expr(f, target);
+                        self.line(&format!("LDR r0, [r0, #{}]", offset));
                     }
                 }
             }
-            emit_expr(left, out, fctx, string_map);
-            out.push_str("    MOV r4, r0\n");
-            emit_expr(right, out, fctx, string_map);
-            out.push_str("    MOV r5, r0\n");
+            Expr::List(_) => {
+                self.warn("array literal outside an assignment is not supported".to_string());
+                self.line("MOV r0, #0");
+            }
+            Expr::StructInit { struct_name, .. } => {
+                self.warn(format!("{} must be constructed into a variable", struct_name));
+                self.line("MOV r0, #0");
+            }
+        }
+    }
+
+    // is_simple: loads into any register without side effects or temporaries
+    fn is_simple(&self, f: &Frame, e: &Expr) -> bool {
+        match e {
+            Expr::Number(_) | Expr::Fixed(_) | Expr::StringLit(_) => true,
+            Expr::Ident(id) => f.slots.contains_key(&id.name) || self.consts.contains_key(&id.name)
+                || self.const_strings.contains_key(&id.name) || self.globals.contains_key(&id.name),
+            _ => false,
+        }
+    }
+
+    fn load_simple(&mut self, f: &Frame, e: &Expr, reg: &str) {
+        match e {
+            Expr::Number(n) | Expr::Fixed(n) => self.load_imm(reg, wrap16(*n)),
+            Expr::StringLit(s) => {
+                let label = self.strings.get(s).cloned().unwrap_or_else(|| "STR_MISSING".to_string());
+                self.line(&format!("LDR {}, ={}", reg, label));
+            }
+            Expr::Ident(id) => self.load_ident(f, &id.name, reg),
+            _ => unreachable!("load_simple on a complex expression"),
+        }
+    }
+
+    fn load_ident(&mut self, f: &Frame, name: &str, reg: &str) {
+        if let Some((off, storage)) = f.slots.get(name) {
+            match storage {
+                Storage::Scalar => self.line(&format!("LDR {}, [r7, #{}]", reg, off)),
+                Storage::Array(_) => self.add_imm(reg, "r7", off + 4),
+                Storage::Struct(_) => self.add_imm(reg, "r7", *off),
+            }
+        } else if let Some(n) = self.consts.get(name) {
+            self.load_imm(reg, *n);
+        } else if let Some(lit) = self.const_strings.get(name) {
+            let lit = lit.clone();
+            self.load_simple(f, &Expr::StringLit(lit), reg);
+        } else if let Some(g) = self.globals.get(name) {
+            let (label, scalar) = (g.label.clone(), g.storage == Storage::Scalar);
+            self.line(&format!("LDR {}, ={}", reg, label));
+            if scalar { self.line(&format!("LDR {0}, [{0}]", reg)); }
+        } else {
+            self.warn(format!("unknown identifier {}", name));
+            self.line(&format!("MOV {}, #0", reg));
+        }
+    }
+
+    // store_var: write `reg` (not r1) to a scalar variable
+    fn store_var(&mut self, f: &Frame, name: &str, reg: &str) {
+        if let Some((off, _)) = f.slots.get(name) {
+            self.line(&format!("STR {}, [r7, #{}]", reg, off));
+        } else if let Some(g) = self.globals.get(name) {
+            let label = g.label.clone();
+            self.line(&format!("LDR r1, ={}", label));
+            self.line(&format!("STR {}, [r1]", reg));
+        } else {
+            self.warn(format!("cannot assign to {}", name));
+        }
+    }
+
+    fn storage_of(&self, f: &Frame, name: &str) -> Option<Storage> {
+        f.slots.get(name).map(|(_, s)| s.clone()).or_else(|| self.globals.get(name).map(|g| g.storage.clone()))
+    }
+
+    // element_address: r0 = &target[index]
+    fn element_address(&mut self, f: &mut Frame, target: &Expr, index: &Expr) {
+        self.expr(f, index);
+        if self.is_simple(f, target) {
+            self.load_simple(f, target, "r1");
+        } else {
+            self.push_r0();
+            self.expr(f, target);
+            self.line("MOV r1, r0");
+            self.pop("r0");
+        }
+        self.line("ADD r0, r1, r0, LSL #2");
+    }
+
+    // local_struct_slot: frame offset of a struct stored in this frame
+    fn local_struct_slot(&self, f: &Frame, e: &Expr) -> Option<i32> {
+        match e {
+            Expr::Ident(id) => match f.slots.get(&id.name) { Some((off, Storage::Struct(_))) => Some(*off), _ => None },
+            _ => None,
+        }
+    }
+
+    // struct_of: static struct type of an expression, when known
+    fn struct_of(&self, f: &Frame, e: &Expr) -> Option<String> {
+        match e {
+            Expr::Ident(id) if id.name == "self" => f.self_struct.clone(),
+            Expr::Ident(id) => match self.storage_of(f, &id.name) { Some(Storage::Struct(s)) => Some(s), _ => None },
+            _ => None,
+        }
+    }
+
+    fn field_offset(&mut self, f: &Frame, target: &Expr, field: &str) -> i32 {
+        let index = match self.struct_of(f, target) {
+            Some(s) => self.structs.get(&s).and_then(|si| si.fields.iter().position(|x| x == field)),
+            // Untyped (e.g. a struct passed as parameter): first struct declaring the field
+            None => self.structs.values().find_map(|si| si.fields.iter().position(|x| x == field)),
+        };
+        match index {
+            Some(i) => i as i32 * 4,
+            None => { self.warn(format!("unknown field {}", field)); 0 }
+        }
+    }
+
+    fn binary(&mut self, f: &mut Frame, op: BinOp, left: &Expr, right: &Expr) {
+        let wrap = matches!(op, BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Shl | BinOp::Div | BinOp::FloorDiv);
+        let imm_op = match op {
+            BinOp::Add => Some("ADD"), BinOp::Sub => Some("SUB"), BinOp::Shl => Some("LSL"), BinOp::Shr => Some("ASR"),
+            BinOp::BitAnd => Some("AND"), BinOp::BitOr => Some("ORR"), BinOp::BitXor => Some("EOR"),
+            _ => None,
+        };
+        let imm = match (op, right) {
+            (BinOp::Shl | BinOp::Shr, Expr::Number(n)) if (1..=31).contains(n) => Some(*n),
+            (BinOp::Shl | BinOp::Shr, _) => None,
+            (_, Expr::Number(n)) if (0..=255).contains(n) => Some(*n),
+            _ => None,
+        };
+        let shift = match (op, imm) { (BinOp::Mul, Some(n)) if n > 0 && (n as u32).is_power_of_two() => Some(n.trailing_zeros()), _ => None };
+        if let (Some(mnemonic), Some(n)) = (imm_op, imm) {
+            self.expr(f, left);
+            self.line(&format!("{} r0, r0, #{}", mnemonic, n));
+        } else if let Some(s) = shift {
+            self.expr(f, left);
+            self.line(&format!("LSL r0, r0, #{}", s));
+        } else {
+            self.operands(f, left, right);
             match op {
-                BinOp::Add => out.push_str("    ADD r0, r4, r5\n    AND r0, r0, #0xFFFF\n"),
-                BinOp::Sub => out.push_str("    SUB r0, r4, r5\n    AND r0, r0, #0xFFFF\n"),
-                BinOp::Mul => out.push_str("    MOV r0,r4\n    MOV r1,r5\n    BL __mul32\n    AND r0, r0, #0xFFFF\n"),
-                BinOp::Div => out.push_str("    MOV r0,r4\n    MOV r1,r5\n    BL __div32\n    AND r0, r0, #0xFFFF\n"),
-                BinOp::FloorDiv => out.push_str("    MOV r0,r4\n    MOV r1,r5\n    BL __div32\n    AND r0, r0, #0xFFFF\n"), // División entera igual que Div
-                BinOp::Mod => out.push_str("    MOV r0,r4\n    MOV r1,r5\n    BL __div32\n    ; quotient now in r0 -> compute remainder r4 - r0*r5\n    MOV r2,r0\n    MUL r2,r2,r5\n    RSBS r0,r2,r4\n    AND r0,r0,#0xFFFF\n"),
-                BinOp::Shl => out.push_str("    MOV r0,r4,LSL r5\n    AND r0,r0,#0xFFFF\n"),
-                BinOp::Shr => out.push_str("    MOV r0,r4,LSR r5\n    AND r0,r0,#0xFFFF\n"),
-                BinOp::BitAnd => out.push_str("    AND r0, r4, r5\n"),
-                BinOp::BitOr  => out.push_str("    ORR r0, r4, r5\n"),
-                BinOp::BitXor => out.push_str("    EOR r0, r4, r5\n"),
-            }
-        }
-        Expr::BitNot(inner) => {
-            emit_expr(inner, out, fctx, string_map);
-            out.push_str("    MVN r0,r0\n    AND r0,r0,#0xFFFF\n");
-        }
-        Expr::Compare { op, left, right } => {
-            emit_expr(left, out, fctx, string_map);
-            out.push_str("    MOV r4, r0\n");
-            emit_expr(right, out, fctx, string_map);
-            out.push_str("    MOV r5, r0\n    CMP r4, r5\n    MOV r0, #0\n");
-            let lbl_true = fresh_label("CMP_T");
-            let lbl_end = fresh_label("CMP_E");
-            let branch = match op {
-                CmpOp::Eq => "BEQ",
-                CmpOp::Ne => "BNE",
-                CmpOp::Lt => "BLT",
-                CmpOp::Le => "BLE",
-                CmpOp::Gt => "BGT",
-                CmpOp::Ge => "BGE",
-            };
-            out.push_str(&format!(
-                "    {} {}\n    B {}\n{}:\n    MOV r0, #1\n{}:\n",
-                branch, lbl_true, lbl_end, lbl_true, lbl_end
-            ));
-        }
-        Expr::Logic { op, left, right } => match op {
-            crate::ast::LogicOp::And => {
-                emit_expr(left, out, fctx, string_map);
-                let false_lbl = fresh_label("AND_FALSE");
-                let end_lbl = fresh_label("AND_END");
-                out.push_str(&format!("    CMP r0,#0\n    BEQ {}\n", false_lbl));
-                emit_expr(right, out, fctx, string_map);
-                out.push_str(&format!(
-                    "    CMP r0,#0\n    BEQ {}\n    MOV r0,#1\n    B {}\n{}:\n    MOV r0,#0\n{}:\n",
-                    false_lbl, end_lbl, false_lbl, end_lbl
-                ));
-            }
-            crate::ast::LogicOp::Or => {
-                let true_lbl = fresh_label("OR_TRUE");
-                let end_lbl = fresh_label("OR_END");
-                emit_expr(left, out, fctx, string_map);
-                out.push_str(&format!("    CMP r0,#0\n    BNE {}\n", true_lbl));
-                emit_expr(right, out, fctx, string_map);
-                out.push_str(&format!(
-                    "    CMP r0,#0\n    BNE {}\n    MOV r0,#0\n    B {}\n{}:\n    MOV r0,#1\n{}:\n",
-                    true_lbl, end_lbl, true_lbl, end_lbl
-                ));
-            }
-        },
-        Expr::Not(inner) => {
-            emit_expr(inner, out, fctx, string_map);
-            out.push_str("    CMP r0,#0\n    MOVEQ r0,#1\n    MOVNE r0,#0\n");
-        }
-        Expr::StructInit { .. } => panic!("Struct initialization not implemented for ARM backend (Phase 3)"),
-        Expr::FieldAccess { .. } => panic!("Field access not implemented for ARM backend (Phase 3)"),
+                BinOp::Add => self.line("ADD r0, r0, r1"),
+                BinOp::Sub => self.line("SUB r0, r0, r1"),
+                BinOp::Mul => self.line("MUL r0, r0, r1"),
+                BinOp::Div | BinOp::FloorDiv => self.line("BL __aeabi_idiv"),
+                BinOp::Mod => { self.line("BL __aeabi_idivmod"); self.line("MOV r0, r1"); }
+                BinOp::Shl => self.line("LSL r0, r0, r1"),
+                BinOp::Shr => self.line("ASR r0, r0, r1"),
+                BinOp::BitAnd => self.line("AND r0, r0, r1"),
+                BinOp::BitOr => self.line("ORR r0, r0, r1"),
+                BinOp::BitXor => self.line("EOR r0, r0, r1"),
+            }
+        }
+        if wrap { self.line("SXTH r0, r0"); }
     }
-}
 
-// emit_builtin_call_arm: inline lowering for math intrinsics on ARM
-fn emit_builtin_call_arm(name: &str, args: &[Expr], out: &mut String, fctx: &FuncCtx, string_map: &std::collections::BTreeMap<String,String>) -> bool {
-    let up = name.to_ascii_uppercase();
-    let is = matches!(up.as_str(),
-        "SIN"|"COS"|"TAN"|"MATH_SIN"|"MATH_COS"|"MATH_TAN"|
-        "ABS"|"MATH_ABS"|"MIN"|"MATH_MIN"|"MAX"|"MATH_MAX"|"CLAMP"|"MATH_CLAMP"|
-        "VECTREX_PRINT_TEXT"|"VECTREX_MOVE_TO"|"VECTREX_DRAW_TO"|"VECTREX_DRAW_LINE"|"VECTREX_SET_ORIGIN"|"VECTREX_SET_INTENSITY"
-    );
-    if !is {
-        // Backward or namespace translation
-        let translated = match up.as_str() {
-            "PRINT_TEXT" => Some("VECTREX_PRINT_TEXT"),
-            "MOVE" => Some("VECTREX_MOVE_TO"),        // Unificado: MOVE -> VECTREX_MOVE_TO
-            "MOVE_TO" => Some("VECTREX_MOVE_TO"),     // Compatibilidad hacia atrás
-            "DRAW_TO" => Some("VECTREX_DRAW_TO"),
-            "DRAW_LINE" => Some("VECTREX_DRAW_LINE"),
-            "SET_ORIGIN" => Some("VECTREX_SET_ORIGIN"),
-            "SET_INTENSITY" => Some("VECTREX_SET_INTENSITY"),
-            _ => None
+    // operands: left in r0, right in r1
+    fn operands(&mut self, f: &mut Frame, left: &Expr, right: &Expr) {
+        self.expr(f, left);
+        if self.is_simple(f, right) {
+            self.load_simple(f, right, "r1");
+        } else {
+            self.push_r0();
+            self.expr(f, right);
+            self.line("MOV r1, r0");
+            self.pop("r0");
+        }
+    }
+
+    // emit_compare: CMP the operands and branch to `target` when the comparison
+    // is false. With `materialize` r0 is cleared before branching, so falling
+    // through only needs r0 = 1.
+    fn emit_compare(&mut self, f: &mut Frame, e: &Expr, target: &str, materialize: bool) {
+        let Expr::Compare { op, left, right } = e else { unreachable!() };
+        match &**right {
+            Expr::Number(n) if (0..=255).contains(n) => { self.expr(f, left); self.line(&format!("CMP r0, #{}", n)); }
+            Expr::Number(n) if (-255..0).contains(n) => { self.expr(f, left); self.line(&format!("CMN r0, #{}", -n)); }
+            _ => { self.operands(f, left, right); self.line("CMP r0, r1"); }
+        }
+        let cond = match op {
+            CmpOp::Eq => "NE",
+            CmpOp::Ne => "EQ",
+            CmpOp::Lt => "GE",
+            CmpOp::Ge => "LT",
+            CmpOp::Gt => "LE",
+            CmpOp::Le => "GT",
         };
-        if let Some(new_up) = translated { return emit_builtin_call_arm(new_up, args, out, fctx, string_map); }
-        return false;
-    }
-    // For non-6809 targets treat Vectrex-specific functions as inert no-ops (return 0) for portability
-    if matches!(up.as_str(),"VECTREX_PRINT_TEXT"|"VECTREX_MOVE_TO"|"VECTREX_DRAW_TO"|"VECTREX_DRAW_LINE"|"VECTREX_SET_ORIGIN"|"VECTREX_SET_INTENSITY") {
-        // Evaluate arguments for side-effects
-        for a in args { emit_expr(a, out, fctx, string_map); }
-        out.push_str("    MOV r0,#0\n");
-        return true;
-    }
-    // ABS
-    if matches!(up.as_str(), "ABS"|"MATH_ABS") {
-        if let Some(arg)=args.first() { emit_expr(arg,out,fctx,string_map); } else { out.push_str("    MOV r0,#0\n"); return true; }
-        let done = fresh_label("ABS_DONE");
-        out.push_str(&format!("    CMP r0,#0\n    BGE {}\n    RSBS r0,r0,#0\n{}:\n", done, done));
-        return true;
-    }
-    // MIN(a,b)
-    if matches!(up.as_str(), "MIN"|"MATH_MIN") {
-        if args.len()<2 { out.push_str("    MOV r0,#0\n"); return true; }
-        emit_expr(&args[0], out, fctx, string_map); out.push_str("    MOV r4,r0\n");
-        emit_expr(&args[1], out, fctx, string_map); out.push_str("    MOV r5,r0\n");
-        out.push_str("    CMP r4,r5\n    BLE 1f\n    MOV r0,r5\n    B 2f\n1:  MOV r0,r4\n2:\n");
-        return true;
-    }
-    // MAX(a,b)
-    if matches!(up.as_str(), "MAX"|"MATH_MAX") {
-        if args.len()<2 { out.push_str("    MOV r0,#0\n"); return true; }
-        emit_expr(&args[0], out, fctx, string_map); out.push_str("    MOV r4,r0\n");
-        emit_expr(&args[1], out, fctx, string_map); out.push_str("    MOV r5,r0\n");
-        out.push_str("    CMP r4,r5\n    BGE 1f\n    MOV r0,r5\n    B 2f\n1:  MOV r0,r4\n2:\n");
-        return true;
-    }
-    // CLAMP(v,lo,hi)
-    if matches!(up.as_str(), "CLAMP"|"MATH_CLAMP") {
-        if args.len()<3 { out.push_str("    MOV r0,#0\n"); return true; }
-        emit_expr(&args[0], out, fctx, string_map); out.push_str("    MOV r4,r0\n"); // v
-        emit_expr(&args[1], out, fctx, string_map); out.push_str("    MOV r5,r0\n"); // lo
-        emit_expr(&args[2], out, fctx, string_map); out.push_str("    MOV r6,r0\n"); // hi
-        out.push_str("    CMP r4,r5\n    BLT 1f\n    CMP r4,r6\n    BGT 2f\n    MOV r0,r4\n    B 3f\n1:  MOV r0,r5\n    B 3f\n2:  MOV r0,r6\n3:\n");
-        return true;
-    }
-    // Trig via tables (index masked 7 bits, table of .hword)
-    if matches!(up.as_str(), "SIN"|"COS"|"TAN"|"MATH_SIN"|"MATH_COS"|"MATH_TAN") {
-        if let Some(arg)=args.first() { emit_expr(arg,out,fctx,string_map); } else { out.push_str("    MOV r0,#0\n"); return true; }
-        out.push_str("    AND r0,r0,#0x7F\n    LSL r0,r0,#1\n");
-        out.push_str("    LDR r1, =SIN_TABLE\n");
-        if up.ends_with("COS") { out.push_str("    LDR r1, =COS_TABLE\n"); }
-        if up.ends_with("TAN") { out.push_str("    LDR r1, =TAN_TABLE\n"); }
-        out.push_str("    ADD r1,r1,r0\n    LDRH r0,[r1]\n");
-        return true;
-    }
-    false
-}
+        if materialize { self.line("MOV r0, #0"); }
+        self.line(&format!("B{} {}", cond, target));
+    }
 
-// (string literal collection & escaping now delegated to backend::string_literals)
+    fn branch_if_false(&mut self, f: &mut Frame, cond: &Expr, target: &str) {
+        match cond {
+            Expr::Compare { .. } => self.emit_compare(f, cond, target, false),
+            Expr::Logic { op: LogicOp::And, left, right } => {
+                self.branch_if_false(f, left, target);
+                self.branch_if_false(f, right, target);
+            }
+            Expr::Number(n) if *n != 0 => {}
+            _ => {
+                self.expr(f, cond);
+                self.line("CMP r0, #0");
+                self.line(&format!("BEQ {}", target));
+            }
+        }
+    }
 
-// collect_symbols: gather all variable identifiers across module for data section.
-fn collect_symbols(module: &Module) -> Vec<String> {
-    use std::collections::BTreeSet;
-    let mut globals = BTreeSet::new();
-    let mut locals = BTreeSet::new();
-    for item in &module.items {
-        if let Item::Function(f) = item {
-            for stmt in &f.body { collect_stmt_syms(stmt, &mut globals); }
-            for l in collect_locals(&f.body) { locals.insert(l); }
+    // ------------------------------------------------------------------- calls
+
+    // load_args: args into r0-r3 (rest on the stack). Returns the stack bytes to
+    // release after the call.
+    fn load_args(&mut self, f: &mut Frame, args: &[Expr]) -> i32 {
+        let n = args.len();
+        if n > 4 {
+            let stack = ((n as i32 - 4) * 4 + 7) & !7;
+            self.line(&format!("SUB sp, sp, #{}", 16 + stack));
+            for (i, a) in args.iter().enumerate() {
+                self.expr(f, a);
+                self.line(&format!("STR r0, [sp, #{}]", i * 4));
+            }
+            self.line("POP {r0-r3}");
+            return stack;
+        }
+        let complex: Vec<usize> = (0..n).filter(|i| !self.is_simple(f, &args[*i])).collect();
+        match complex.len() {
+            0 => {}
+            1 => {
+                let i = complex[0];
+                self.expr(f, &args[i]);
+                if i != 0 { self.line(&format!("MOV r{}, r0", i)); }
+            }
+            _ => {
+                let regs = if n <= 2 { 2 } else { 4 };
+                self.line(&format!("SUB sp, sp, #{}", regs * 4));
+                for &i in &complex {
+                    self.expr(f, &args[i]);
+                    self.line(&format!("STR r0, [sp, #{}]", i * 4));
+                }
+                self.line(if regs == 2 { "POP {r0, r1}" } else { "POP {r0-r3}" });
+            }
+        }
+        for (i, a) in args.iter().enumerate() {
+            if !complex.contains(&i) { self.load_simple(f, a, &format!("r{}", i)); }
+        }
+        0
+    }
+
+    fn call(&mut self, f: &mut Frame, sym: &str, args: &[Expr]) {
+        let stack = self.load_args(f, args);
+        self.line(&format!("BL {}", sym));
+        if stack > 0 { self.line(&format!("ADD sp, sp, #{}", stack)); }
+    }
+
+    fn call_expr(&mut self, f: &mut Frame, ci: &CallInfo) {
+        if self.functions.contains(&ci.name) {
+            self.call(f, &function_symbol(&ci.name), &ci.args);
+        } else if let Some((struct_name, _)) = self.constructed_struct(&Expr::Call(ci.clone())) {
+            self.warn(format!("{} must be constructed into a variable", struct_name));
+            self.line("MOV r0, #0");
+        } else if !self.builtin(f, &ci.name, &ci.args, ci.source_line) {
+            // Not ours: an SDK or C function
+            self.call(f, &ci.name, &ci.args);
         }
     }
-    // remove locals from globals
-    for l in &locals { globals.remove(l); }
-    globals.into_iter().collect()
-}
 
-// collect_stmt_syms: scan a statement for variable references.
-fn collect_stmt_syms(stmt: &Stmt, set: &mut std::collections::BTreeSet<String>) {
-    match stmt {
-        Stmt::Assign { target, value, .. } => {
-            if let crate::ast::AssignTarget::Ident { name, .. } = target {
-                set.insert(name.clone());
-            }
-            collect_expr_syms(value, set);
-        }
-        Stmt::Let { .. } => { /* locals excluded */ }
-        Stmt::Expr(e, _) => collect_expr_syms(e, set),
-    Stmt::For { var: _var, start, end, step, body, .. } => {
-            // Do not insert loop var here; it may be a local 'let' elsewhere. We'll rely on absence of Let to keep it global.
-            collect_expr_syms(start, set);
-            collect_expr_syms(end, set);
-            if let Some(se) = step { collect_expr_syms(se, set); }
-            for s in body { collect_stmt_syms(s, set); }
-        }
-        Stmt::While { cond, body, .. } => {
-            collect_expr_syms(cond, set);
-            for s in body {
-                collect_stmt_syms(s, set);
-            }
-        }
-        Stmt::If { cond, body, elifs, else_body, .. } => {
-            collect_expr_syms(cond, set);
-            for s in body {
-                collect_stmt_syms(s, set);
-            }
-            for (c, b) in elifs {
-                collect_expr_syms(c, set);
-                for s in b {
-                    collect_stmt_syms(s, set);
+    fn method_call(&mut self, f: &mut Frame, mc: &MethodCallInfo) {
+        let owner = self.struct_of(f, &mc.target)
+            .filter(|s| self.structs.get(s).is_some_and(|si| si.methods.contains(&mc.method_name)))
+            .or_else(|| self.structs.iter().find(|(_, si)| si.methods.contains(&mc.method_name)).map(|(n, _)| n.clone()));
+        let Some(owner) = owner else {
+            self.warn(format!("unknown method {}", mc.method_name));
+            self.line("MOV r0, #0");
+            return;
+        };
+        let args: Vec<Expr> = std::iter::once((*mc.target).clone()).chain(mc.args.iter().cloned()).collect();
+        self.call(f, &method_symbol(&owner, &mc.method_name), &args);
+    }
+
+    // builtin: SDK-backed and inline builtins; false when `name` is not one
+    fn builtin(&mut self, f: &mut Frame, name: &str, args: &[Expr], line: usize) -> bool {
+        let up = name.to_ascii_uppercase();
+        let up = up.strip_prefix("VECTREX_").unwrap_or(&up);
+        let up = up.strip_prefix("MATH_").unwrap_or(up);
+        let sdk = &self.ti.sdk;
+        let (wait_recal, text, buttons, joystick) = (sdk.wait_recal, sdk.text, sdk.buttons, sdk.joystick);
+        match (up, args.len()) {
+            ("WAIT_RECAL", _) => self.line(&format!("BL {}", wait_recal)),
+            ("SET_INTENSITY", 1) | ("INTENSITY", 1) => { self.helpers.insert("__vpy_set_intensity"); self.call(f, "__vpy_set_intensity", args); }
+            ("MOVE", 2) | ("MOVE_TO", 2) => { self.helpers.insert("__vpy_move"); self.call(f, "__vpy_move", args); }
+            ("SET_ORIGIN", 0) => { self.helpers.insert("__vpy_move"); self.call(f, "__vpy_move", &[Expr::Number(0), Expr::Number(0)]); }
+            ("DRAW_TO", 2) => { self.helpers.insert("__vpy_draw_to"); self.call(f, "__vpy_draw_to", args); }
+            ("DRAW_LINE", 5) => { let line = self.ti.line_routine; self.call(f, line, args); }
+            ("PRINT_TEXT", 3) | ("PRINT_TEXT", 5) => self.call(f, text, &args[..3]),
+            ("UPDATE_BUTTONS", 0) => {}
+            ("J1_X" | "J1_Y" | "J2_X" | "J2_Y" | "J1_X_DIGITAL" | "J1_Y_DIGITAL" | "J2_X_DIGITAL" | "J2_Y_DIGITAL"
+                | "J1_X_ANALOG" | "J1_Y_ANALOG" | "J2_X_ANALOG" | "J2_Y_ANALOG", 0) => {
+                let axis = match &up[..4] { "J1_X" => 0, "J1_Y" => 1, "J2_X" => 2, _ => 3 };
+                self.line(&format!("MOV r0, #{}", axis));
+                self.line(&format!("BL {}", joystick));
+                if !up.ends_with("_ANALOG") {
+                    self.helpers.insert("__vpy_digital");
+                    self.line("BL __vpy_digital");
                 }
             }
-            if let Some(eb) = else_body {
-                for s in eb {
-                    collect_stmt_syms(s, set);
+            ("J1_BUTTON_1" | "J1_BUTTON_2" | "J1_BUTTON_3" | "J1_BUTTON_4"
+                | "J2_BUTTON_1" | "J2_BUTTON_2" | "J2_BUTTON_3" | "J2_BUTTON_4", 0) => {
+                let pad = if up.starts_with("J1") { 0 } else { 4 };
+                let bit = pad + up[10..].parse::<i32>().unwrap_or(1) - 1;
+                self.line(&format!("BL {}", buttons));
+                if bit > 0 { self.line(&format!("LSR r0, r0, #{}", bit)); }
+                self.line("AND r0, r0, #1");
+            }
+            ("U8", 1) => {
+                self.expr(f, &args[0]);
+                self.line("AND r0, r0, #255");
+            }
+            ("I8", 1) => {
+                self.expr(f, &args[0]);
+                self.line("LSL r0, r0, #24");
+                self.line("ASR r0, r0, #24");
+            }
+            ("U16", 1) | ("I16", 1) => self.expr(f, &args[0]),
+            ("BOOL", 1) => {
+                let done = self.fresh("bool");
+                self.expr(f, &args[0]);
+                self.line("CMP r0, #0");
+                self.line(&format!("BEQ {}", done));
+                self.line("MOV r0, #1");
+                self.label(&done);
+            }
+            ("ABS", 1) => {
+                let done = self.fresh("abs");
+                self.expr(f, &args[0]);
+                self.line("CMP r0, #0");
+                self.line(&format!("BGE {}", done));
+                self.line("RSB r0, r0, #0");
+                self.label(&done);
+            }
+            ("MIN", 2) | ("MAX", 2) => {
+                let done = self.fresh("minmax");
+                self.load_args(f, args);
+                self.line("CMP r0, r1");
+                self.line(&format!("{} {}", if up == "MIN" { "BLE" } else { "BGE" }, done));
+                self.line("MOV r0, r1");
+                self.label(&done);
+            }
+            ("CLAMP", 3) => {
+                let above_lo = self.fresh("clamp");
+                let done = self.fresh("clampend");
+                self.load_args(f, args);
+                self.line("CMP r0, r1");
+                self.line(&format!("BGE {}", above_lo));
+                self.line("MOV r0, r1");
+                self.label(&above_lo);
+                self.line("CMP r0, r2");
+                self.line(&format!("BLE {}", done));
+                self.line("MOV r0, r2");
+                self.label(&done);
+            }
+            ("SIN" | "COS" | "TAN", 1) => {
+                self.uses_trig = true;
+                self.expr(f, &args[0]);
+                self.line("AND r0, r0, #127");
+                self.line("LSL r0, r0, #1");
+                self.line(&format!("LDR r1, ={}_TABLE", up));
+                self.line("LDRSH r0, [r1, r0]");
+            }
+            ("FIXED_MUL", 2) => {
+                self.operands(f, &args[0], &args[1]);
+                self.line("MUL r0, r0, r1");
+                self.line("ASR r0, r0, #8");
+                self.line("SXTH r0, r0");
+            }
+            ("FIXED_DIV", 2) => {
+                self.operands(f, &args[0], &args[1]);
+                self.line("LSL r0, r0, #8");
+                self.line("BL __aeabi_idiv");
+                self.line("SXTH r0, r0");
+            }
+            ("FIXED_TO_INT", 1) => {
+                self.expr(f, &args[0]);
+                self.line("ASR r0, r0, #8");
+            }
+            ("LEN", 1) => {
+                let known = match &args[0] { Expr::Ident(id) => self.storage_of(f, &id.name), _ => None };
+                if let Some(Storage::Array(n)) = known {
+                    self.load_imm("r0", n as i32);
+                } else {
+                    self.expr(f, &args[0]);
+                    self.line("LDR r0, [r0, #-4]");
                 }
             }
-        }
-        Stmt::Return(o, _) => {
-            if let Some(e) = o {
-                collect_expr_syms(e, set);
+            ("ASM", 1) => match &args[0] {
+                Expr::StringLit(s) => self.line(s.trim()),
+                _ => self.warn("ASM expects a string literal".to_string()),
+            },
+            _ if is_builtin(name) => {
+                let message = format!("{} is not supported on {}", up, self.ti.name);
+                self.out.push_str(&format!("    @ {}\n", message));
+                self.errors.push(Diagnostic {
+                    severity: DiagnosticSeverity::Error,
+                    code: DiagnosticCode::UnsupportedBuiltin,
+                    message,
+                    line: Some(line),
+                    col: None,
+                });
+                self.line("MOV r0, #0");
             }
+            _ => return false,
         }
-        Stmt::Switch { expr, cases, default, .. } => {
-            collect_expr_syms(expr, set);
-            for (ce, cb) in cases { collect_expr_syms(ce, set); for s in cb { collect_stmt_syms(s, set); } }
-            if let Some(db) = default { for s in db { collect_stmt_syms(s, set); } }
+        true
+    }
+
+    // --------------------------------------------------------------- utilities
+
+    fn load_imm(&mut self, reg: &str, n: i32) {
+        if (0..=255).contains(&n) { self.line(&format!("MOV {}, #{}", reg, n)); }
+        else if (-256..0).contains(&n) { self.line(&format!("MVN {}, #{}", reg, -n - 1)); }
+        else { self.line(&format!("LDR {}, ={}", reg, n)); }
+    }
+
+    // add_imm: rd = rn + n for offsets that may not fit a modified immediate
+    fn add_imm(&mut self, rd: &str, rn: &str, n: i32) {
+        let (op, abs) = if n < 0 { ("SUB", -n) } else { ("ADD", n) };
+        if abs <= 255 {
+            self.line(&format!("{} {}, {}, #{}", op, rd, rn, abs));
+        } else {
+            self.line(&format!("LDR r12, ={}", abs));
+            self.line(&format!("{} {}, {}, r12", op, rd, rn));
         }
-        Stmt::Break { .. } | Stmt::Continue { .. } | Stmt::Pass { .. } => {},
-        Stmt::CompoundAssign { .. } => panic!("CompoundAssign should be transformed away before collect_stmt_syms"),
-        _ => {}, // Catch-all for unsupported statements (e.g., ForIn)
     }
-}
 
-// collect_expr_syms: scan an expression for identifiers.
-fn collect_expr_syms(expr: &Expr, set: &mut std::collections::BTreeSet<String>) {
-    match expr {
-    Expr::Ident(n) => { set.insert(n.name.clone()); }
-    Expr::StringLit(_) => {}
-    Expr::Call(ci) => { for a in &ci.args { collect_expr_syms(a, set); } }
-        Expr::Binary { left, right, .. }
-        | Expr::Compare { left, right, .. }
-        | Expr::Logic { left, right, .. } => {
-            collect_expr_syms(left, set);
-            collect_expr_syms(right, set);
+    fn push_r0(&mut self) { self.line("STR r0, [sp, #-8]!"); }
+    fn pop(&mut self, reg: &str) { self.line(&format!("LDR {}, [sp], #8", reg)); }
+
+    // static_word: initial value usable in a .word directive
+    fn static_word(&self, e: &Expr) -> Option<String> {
+        match e {
+            Expr::Number(n) | Expr::Fixed(n) => Some(wrap16(*n).to_string()),
+            Expr::StringLit(s) => self.strings.get(s).cloned(),
+            Expr::Ident(id) => self.consts.get(&id.name).map(|n| n.to_string())
+                .or_else(|| self.const_strings.get(&id.name).and_then(|lit| self.strings.get(lit).cloned())),
+            _ => None,
         }
-        Expr::Not(inner) | Expr::BitNot(inner) => collect_expr_syms(inner, set),
-        Expr::List(elements) => {
-            for elem in elements {
-                collect_expr_syms(elem, set);
+    }
+
+    // emit_runtime: helpers used by the builtins
+    fn emit_runtime(&mut self) {
+        let helpers = std::mem::take(&mut self.helpers);
+        let (line, intensity) = (self.ti.line_routine, self.ti.sdk.intensity);
+        for h in &helpers {
+            self.function_header(h, false);
+            match *h {
+                "__vpy_move" => {
+                    // (x, y): move the pen without drawing
+                    self.line("LDR r2, =__vpy_pen");
+                    self.line("STR r0, [r2]");
+                    self.line("STR r1, [r2, #4]");
+                    self.line("BX lr");
+                }
+                "__vpy_draw_to" => {
+                    // (x, y): line from the pen to (x, y) at the current intensity
+                    self.line("PUSH {r4, lr}");
+                    self.line("LDR r12, =__vpy_pen");
+                    self.line("MOV r2, r0");
+                    self.line("MOV r3, r1");
+                    self.line("LDR r0, [r12]");
+                    self.line("LDR r1, [r12, #4]");
+                    self.line("STR r2, [r12]");
+                    self.line("STR r3, [r12, #4]");
+                    self.line("LDR r4, [r12, #8]");
+                    self.line("SUB sp, sp, #8");
+                    self.line("STR r4, [sp]");
+                    self.line(&format!("BL {}", line));
+                    self.line("ADD sp, sp, #8");
+                    self.line("POP {r4, pc}");
+                }
+                "__vpy_set_intensity" => {
+                    self.line("LDR r1, =__vpy_pen");
+                    self.line("STR r0, [r1, #8]");
+                    self.line(&format!("B {}", intensity));
+                }
+                "__vpy_digital" => {
+                    // analog axis -> -1 / 0 / 1 with a small dead zone
+                    self.line("CMP r0, #32");
+                    self.line("BGE 1f");
+                    self.line("CMN r0, #32");
+                    self.line("BLE 2f");
+                    self.line("MOV r0, #0");
+                    self.line("BX lr");
+                    self.out.push_str("1:  MOV r0, #1\n");
+                    self.line("BX lr");
+                    self.out.push_str("2:  MVN r0, #0\n");
+                    self.line("BX lr");
+                }
+                _ => unreachable!(),
             }
+            self.line(".ltorg");
+            self.out.push('\n');
         }
-        Expr::Index { target, index } => {
-            collect_expr_syms(target, set);
-            collect_expr_syms(index, set);
-        }
-        Expr::Number(_) => {}
-        Expr::StructInit { .. } => {} // Phase 3
-        Expr::FieldAccess { target, .. } => collect_expr_syms(target, set),
+        self.helpers = helpers;
     }
-}
 
-fn collect_locals(stmts: &[Stmt]) -> Vec<String> {
-    use std::collections::BTreeSet;
-    let mut set = BTreeSet::new();
-    fn walk(s: &Stmt, set: &mut std::collections::BTreeSet<String>) {
-        match s {
-            Stmt::Let { name, .. } => { set.insert(name.clone()); }
-            Stmt::If { body, elifs, else_body, .. } => {
-                for b in body { walk(b, set); }
-                for (_, eb) in elifs { for b in eb { walk(b, set); } }
-                if let Some(eb) = else_body { for b in eb { walk(b, set); } }
+    fn emit_data(&mut self, module: &Module) -> String {
+        let mut out = String::new();
+        if !self.strings.is_empty() || self.uses_trig {
+            out.push_str("    .section .rodata\n");
+            for (lit, label) in &self.strings {
+                out.push_str(&format!("{}: .asciz \"{}\"\n", label, escape_ascii(lit)));
             }
-            Stmt::While { body, .. } => { for b in body { walk(b, set); } }
-            Stmt::For { var, body, .. } => {
-                set.insert(var.clone());
-                for b in body { walk(b, set); }
+            if self.uses_trig {
+                out.push_str("    .balign 2\n");
+                emit_trig_tables(&mut out, ".hword");
             }
-            _ => {}
+            out.push('\n');
         }
+        let mut data = String::new();
+        let mut bss = String::new();
+        for item in &module.items {
+            let (Item::GlobalLet { name, value, .. } | Item::Const { name, value, .. }) = item else { continue };
+            let Some(g) = self.globals.get(name) else { continue };
+            match (&g.storage, value) {
+                (Storage::Array(n), Expr::List(elems)) => {
+                    let words: Vec<String> = elems.iter().map(|e| self.static_word(e).unwrap_or_else(|| "0".to_string())).collect();
+                    data.push_str(&format!("    .word {}\n{}:\n", n, g.label));
+                    if !words.is_empty() { data.push_str(&format!("    .word {}\n", words.join(", "))); }
+                }
+                (Storage::Struct(_), _) => {
+                    bss.push_str(&format!("{}: .space {}\n", g.label, g.storage.words(&self.structs) * 4));
+                }
+                _ => {
+                    let word = self.static_word(value).unwrap_or_else(|| "0".to_string());
+                    data.push_str(&format!("{}: .word {}\n", g.label, word));
+                }
+            }
+        }
+        if self.helpers.contains("__vpy_move") || self.helpers.contains("__vpy_draw_to") || self.helpers.contains("__vpy_set_intensity") {
+            data.push_str("__vpy_pen: .word 0, 0, 127\n");
+        }
+        if !data.is_empty() { out.push_str(&format!("    .data\n    .balign 4\n{}\n", data)); }
+        if !bss.is_empty() { out.push_str(&format!("    .bss\n    .balign 4\n{}\n", bss)); }
+        out
     }
-    for s in stmts { walk(s, &mut set); }
-    set.into_iter().collect()
-}
-
-// fresh_label: produce unique assembler labels with a given prefix.
-fn fresh_label(prefix: &str) -> String {
-    use std::sync::atomic::{AtomicUsize, Ordering};
-    static C: AtomicUsize = AtomicUsize::new(0);
-    let id = C.fetch_add(1, Ordering::Relaxed);
-    format!("{}_{}", prefix, id)
 }
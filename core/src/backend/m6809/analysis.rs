@@ -22,6 +22,7 @@ pub struct RuntimeUsage {
     pub uses_show_level: bool,       // NEW: tracks SHOW_LEVEL usage
     pub needs_mul_helper: bool,
     pub needs_div_helper: bool,
+    pub needs_fixed_helpers: bool,   // FIXMUL/FIXDIV (fixed-point 8.8)
     pub needs_tmp_left: bool,
     pub needs_tmp_right: bool,
     pub needs_tmp_ptr: bool,
@@ -259,6 +260,9 @@ pub fn scan_expr_runtime(e: &Expr, usage: &mut RuntimeUsage) {
             if up == "VECTREX_DRAW_VECTORLIST" || up == "DRAW_VECTORLIST" {
                 usage.needs_vectorlist_runtime = true;
             }
+            if up == "FIXED_MUL" || up == "FIXED_DIV" {
+                usage.needs_fixed_helpers = true;
+            }
             // DRAW_LINE: mark wrapper as needed if:
             // 1. Not all args are constants (can't optimize inline), OR
             // 2. Constants have deltas > ±127 (requires segmentation)
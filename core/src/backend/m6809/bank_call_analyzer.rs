@@ -168,6 +168,7 @@ mod tests {
         Function {
             name: name.to_string(),
             params: vec![],
+            param_types: vec![],
             body,
             line: 1,
         }
@@ -36,7 +36,7 @@ pub fn emit_builtin_call(name: &str, args: &Vec<Expr>, out: &mut String, fctx: &
         "LOAD_LEVEL"|"SHOW_LEVEL"|"UPDATE_LEVEL"|
         "SIN"|"COS"|"TAN"|"MATH_SIN"|"MATH_COS"|"MATH_TAN"|
     "ABS"|"MATH_ABS"|"MIN"|"MATH_MIN"|"MAX"|"MATH_MAX"|"CLAMP"|"MATH_CLAMP"|"LEN"|
-    "MUL_A"|"DIV_A"|"MOD_A"|
+    "MUL_A"|"DIV_A"|"MOD_A"|"FIXED_TO_INT"|"U8"|"I8"|"U16"|"I16"|"BOOL"|
     "DRAW_CIRCLE"|"DRAW_CIRCLE_SEG"|"DRAW_ARC"|"DRAW_SPIRAL"|"DRAW_VECTORLIST"|"DRAW_POLYGON"|
     "DEBUG_PRINT"|"DEBUG_PRINT_LABELED"|"DEBUG_PRINT_STR"
     );
@@ -292,17 +292,28 @@ pub fn emit_builtin_call(name: &str, args: &Vec<Expr>, out: &mut String, fctx: &
     }
     
     // PLAY_SFX: Play sound effect asset by name (one-shot, non-looping)
-    // Usage: PLAY_SFX("explosion") -> plays SFX once
-    if up == "PLAY_SFX" && args.len() == 1 {
+    // Usage: PLAY_SFX("explosion" [, priority]) -> plays SFX once
+    // Un solo canal de SFX (C): el efecto que suena solo cede ante uno de
+    // prioridad igual o mayor (por defecto, la del .vsfx leída al recoger los assets)
+    if up == "PLAY_SFX" && (1..=2).contains(&args.len()) {
         if let Expr::StringLit(asset_name) = &args[0] {
             // Check if asset exists in opts.assets
-            let asset_exists = opts.assets.iter().any(|a| {
+            let asset = opts.assets.iter().find(|a| {
                 a.name == *asset_name && matches!(a.asset_type, crate::codegen::AssetType::Sfx)
             });
             
-            if asset_exists {
+            if let Some(asset) = asset {
                 let symbol = format!("_{}_SFX", asset_name.to_uppercase().replace("-", "_").replace(" ", "_"));
                 out.push_str(&format!("; PLAY_SFX(\"{}\") - play sound effect (one-shot)\n", asset_name));
+                match args.get(1) {
+                    Some(priority) => {
+                        emit_expr(priority, out, fctx, string_map, opts);
+                        out.push_str("    LDA RESULT+1\n");
+                    }
+                    None => {
+                        out.push_str(&format!("    LDA #{}           ; Prioridad del propio efecto\n", asset.priority));
+                    }
+                }
                 out.push_str(&format!("    LDX #{}\n", symbol));
                 out.push_str("    JSR PLAY_SFX_RUNTIME\n");
                 out.push_str("    LDD #0\n    STD RESULT\n");
@@ -1137,6 +1148,28 @@ pub fn emit_builtin_call(name: &str, args: &Vec<Expr>, out: &mut String, fctx: &
         return true;
     }
     
+    // FIXED_TO_INT(x): integer part of an 8.8 value (floor) = sign-extended high byte
+    if up == "FIXED_TO_INT" {
+        if let Some(arg) = args.first() { emit_expr(arg, out, fctx, string_map, opts); } else { out.push_str("    LDD #0\n    STD RESULT\n"); return true; }
+        out.push_str("    LDB RESULT\n    SEX\n    STD RESULT\n");
+        return true;
+    }
+
+    // U8/I8/U16/I16/BOOL(x): conversions for annotated variables (see types.rs)
+    if matches!(up.as_str(), "U8"|"I8"|"U16"|"I16"|"BOOL") {
+        if let Some(arg) = args.first() { emit_expr(arg, out, fctx, string_map, opts); } else { out.push_str("    LDD #0\n    STD RESULT\n"); return true; }
+        match up.as_str() {
+            "U8" => out.push_str("    LDD RESULT\n    CLRA\n    STD RESULT\n"),
+            "I8" => out.push_str("    LDB RESULT+1\n    SEX\n    STD RESULT\n"),
+            "BOOL" => {
+                let done = fresh_label("BOOL_DONE");
+                out.push_str(&format!("    LDD RESULT\n    BEQ {}\n    LDD #1\n{}: STD RESULT\n", done, done));
+            }
+            _ => {} // U16/I16: same 16-bit bits, only the static type changes
+        }
+        return true;
+    }
+
     // MUL_A(a, b): Multiply a * b (8-bit result)
     // Usage: result = MUL_A(x, y)
     if up == "MUL_A" {
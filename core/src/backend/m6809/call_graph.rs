@@ -324,7 +324,9 @@ fn is_builtin(name: &str) -> bool {
         "FRAME_BEGIN" | "ABS" | "LEN" | "ASM" | "SET_INTENSITY" |
         "J1_X" | "J1_Y" | "J1_BUTTON_1" | "J1_BUTTON_2" | "J1_BUTTON_3" | "J1_BUTTON_4" |
         "LOAD_LEVEL" | "SHOW_LEVEL" | "UPDATE_LEVEL" | "GET_LEVEL_BOUNDS" |
-        "DRAW_VECTOR_EX" | "SFX_UPDATE"
+        "DRAW_VECTOR_EX" | "SFX_UPDATE" |
+        "FIXED_MUL" | "FIXED_DIV" | "FIXED_TO_INT" |
+        "U8" | "I8" | "U16" | "I16" | "BOOL"
     )
 }
 
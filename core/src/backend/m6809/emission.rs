@@ -1,7 +1,7 @@
 // Emission - High-level code emission functions for M6809 backend
 use crate::ast::{Function, Stmt, Module, Expr};
 use crate::codegen::CodegenOptions;
-use super::{LoopCtx, FuncCtx, emit_stmt, collect_locals, collect_locals_with_params, RuntimeUsage, LineTracker, DebugInfo};
+use super::{LoopCtx, FuncCtx, emit_stmt, collect_locals, collect_locals_with_params, RuntimeUsage, LineTracker, DebugInfo, store_scalar};
 use super::analyze_var_types; // Import the new function
 use std::sync::atomic::{AtomicBool, Ordering};
 
@@ -31,25 +31,9 @@ pub fn emit_function(f: &Function, out: &mut String, string_map: &std::collectio
     // Analyze variable types to determine struct instances and their sizes
     let var_info = analyze_var_types(&f.body, &locals, &opts.structs);
     
-    // Calculate frame size based on actual variable sizes
-    let mut frame_size = 0;
-    for var_name in &locals {
-        let size = var_info.get(var_name)
-            .map(|(_, s)| *s as i32)
-            .unwrap_or(2); // Default to 2 bytes for simple variables
-        frame_size += size;
-    }
-    
-    if frame_size > 0 { out.push_str(&format!("    LEAS -{},S ; allocate locals\n", frame_size)); }
-    // Copy parameters from VAR_ARG to stack locals (parameters are first N locals)
-    // Parameters go at: 0,S (param 0), 2,S (param 1), 4,S (param 2), 6,S (param 3)
-    for (i, _p) in f.params.iter().enumerate().take(4) {
-        let offset = i as i32 * 2; // Each parameter is 2 bytes, sequential
-        out.push_str(&format!("    LDD VAR_ARG{}\n    STD {},S ; param {}\n", i, offset, i));
-    }
-    let fctx = FuncCtx { 
+    let mut fctx = FuncCtx { 
         locals: locals.clone(), 
-        frame_size, 
+        frame_size: 0, 
         var_info,
         // Detect if this is a struct method by checking if name contains underscore
         // Format: STRUCTNAME_methodname (e.g., POINT_MOVE, ENTITY_GET_NEW_X)
@@ -61,7 +45,22 @@ pub fn emit_function(f: &Function, out: &mut String, string_map: &std::collectio
         },
         // Add function parameters for correct stack offset calculation
         params: f.params.clone(),
+        // Annotated params/locals get byte or word slots
+        scalar_types: crate::types::local_types(f),
     };
+    // Calculate frame size based on actual variable sizes
+    fctx.frame_size = fctx.locals_size();
+    let frame_size = fctx.frame_size;
+    
+    if frame_size > 0 { out.push_str(&format!("    LEAS -{},S ; allocate locals\n", frame_size)); }
+    // Copy parameters from VAR_ARG to stack locals (parameters are first N locals)
+    // Struct methods receive self in VAR_ARG0, so their params start at VAR_ARG1
+    let arg_base = match &fctx.struct_type { Some(s) if opts.structs.contains_key(s) => 1, _ => 0 };
+    for (i, p) in f.params.iter().enumerate().take(4 - arg_base) {
+        let offset = fctx.offset_of(p).unwrap_or(0);
+        out.push_str(&format!("    LDD VAR_ARG{}\n", i + arg_base));
+        out.push_str(&store_scalar(&format!("{},S ; param {}", offset, i), fctx.scalar_type(p)));
+    }
     for stmt in &f.body { emit_stmt(stmt, out, &LoopCtx::default(), &fctx, string_map, opts, tracker, 0); }
     if !matches!(f.body.last(), Some(Stmt::Return(_, _))) {
     if frame_size > 0 { out.push_str(&format!("    LEAS {},S ; free locals\n", frame_size)); }
@@ -443,13 +442,22 @@ pub fn emit_builtin_helpers(out: &mut String, usage: &RuntimeUsage, opts: &Codeg
             \n\
             ; RAM variables (defined via ram.allocate in mod.rs):\n\
             ; PSG_MUSIC_PTR, PSG_MUSIC_START, PSG_IS_PLAYING,\n\
-            ; PSG_MUSIC_ACTIVE, PSG_DELAY_FRAMES\n\
+            ; PSG_MUSIC_ACTIVE, PSG_DELAY_FRAMES, PSG_MUSIC_PACKED,\n\
+            ; PSG_MUSIC_REF_LEFT, PSG_MUSIC_REF_RET\n\
             \n\
             ; PLAY_MUSIC_RUNTIME - Start PSG music playback\n\
-            ; Input: X = pointer to PSG music data\n\
+            ; Input: X = pointer to PSG music data ($FE first = packed stream)\n\
             PLAY_MUSIC_RUNTIME:\n\
-            STX >PSG_MUSIC_PTR     ; Store current music pointer (force extended)\n\
             STX >PSG_MUSIC_START   ; Store start pointer for loops (force extended)\n\
+            CLR >PSG_MUSIC_PACKED\n\
+            CLR >PSG_MUSIC_REF_LEFT\n\
+            LDA ,X\n\
+            CMPA #$FE              ; Packed stream header?\n\
+            BNE PMr_plain\n\
+            INC >PSG_MUSIC_PACKED\n\
+            LEAX 1,X               ; First token\n\
+PMr_plain:\n\
+            STX >PSG_MUSIC_PTR     ; Store current music pointer (force extended)\n\
             CLR >PSG_DELAY_FRAMES  ; Clear delay counter\n\
             LDA #$01\n\
             STA >PSG_IS_PLAYING ; Mark as playing (extended - var at 0xC8A0)\n\
@@ -556,6 +564,8 @@ PSG_update_done:\n\
             ; UPDATE MUSIC (channel B: registers 9, 11-14)\n\
             LDA >PSG_IS_PLAYING     ; Check if music is playing\n\
             BEQ AU_SKIP_MUSIC       ; Skip if not\n\
+            TST >PSG_MUSIC_PACKED\n\
+            BNE AU_MUSIC_PACKED     ; Packed stream has its own decoder\n\
             \n\
             ; Check delay counter first\n\
             LDA >PSG_DELAY_FRAMES   ; Load delay counter\n\
@@ -608,7 +618,7 @@ PSG_update_done:\n\
             LDA ,X+                 ; Load register number\n\
             LDB ,X+                 ; Load register value\n\
             PSHS X                  ; Save pointer\n\
-            JSR Sound_Byte          ; Write to PSG using BIOS (DP=$D0)\n\
+            JSR AU_MUSIC_WRITE_REG  ; Write unless the SFX owns the register\n\
             PULS X                  ; Restore pointer\n\
             PULS B                  ; Get counter\n\
             DECB                    ; Decrement\n\
@@ -630,6 +640,8 @@ PSG_update_done:\n\
             CLR >PSG_DELAY_FRAMES   ; Clear delay on loop\n\
             BRA AU_UPDATE_SFX       ; Continue to SFX\n\
             \n\
+            AU_MUSIC_PACKED:\n\
+            JSR AU_PACKED_FRAME\n\
             AU_SKIP_MUSIC:\n\
             BRA AU_UPDATE_SFX       ; Skip music, go to SFX\n\
             \n\
@@ -643,8 +655,40 @@ PSG_update_done:\n\
             AU_DONE:\n\
             PULS DP                 ; Restore original DP\n\
             RTS\n\
+            \n\
+            ; AU_MUSIC_WRITE_REG - A=registro, B=valor. Guarda el valor de la\n\
+            ; musica; con un SFX activo no toca el canal C (ni su envolvente)\n\
+            AU_MUSIC_WRITE_REG:\n\
+            LDX #PSG_MUSIC_SHADOW\n\
+            STB A,X                 ; Valor de la musica (se restaura tras un SFX)\n\
+            TST >SFX_ACTIVE\n\
+            BEQ AU_MUSIC_WRITE      ; Sin SFX: la musica usa todos los canales\n\
+            CMPA #$07\n\
+            BNE AU_MUSIC_CHECK_C\n\
+            ANDB #$DB               ; Mixer: conservar los bits tono/ruido C del SFX\n\
+            PSHS B\n\
+            LDB $C807               ; Mixer shadow\n\
+            ANDB #$24\n\
+            ORB ,S+\n\
+            BRA AU_MUSIC_WRITE\n\
+            AU_MUSIC_CHECK_C:\n\
+            CMPA #$04\n\
+            BLO AU_MUSIC_WRITE      ; Tono A/B\n\
+            CMPA #$06\n\
+            BLS AU_MUSIC_SKIP       ; Tono C y ruido son del SFX\n\
+            CMPA #$0A\n\
+            BEQ AU_MUSIC_SKIP       ; Volumen C\n\
+            CMPA #$0B\n\
+            BLO AU_MUSIC_WRITE      ; Volumen A/B\n\
+            TST >SFX_ENV_MODE\n\
+            BNE AU_MUSIC_SKIP       ; Envolvente, si el SFX la usa\n\
+            AU_MUSIC_WRITE:\n\
+            JMP Sound_Byte          ; Write to PSG using BIOS (DP=$D0)\n\
+            AU_MUSIC_SKIP:\n\
+            RTS\n\
             \n"
         );
+        out.push_str(crate::music_pack::PLAYER_ASM);
     }
     
     // PLAY_SFX_RUNTIME: Sound effects player for .vsfx assets (parametric sounds)
@@ -657,19 +701,31 @@ PSG_update_done:\n\
             ; AYFX SOUND EFFECTS PLAYER (Richard Chadd original system)\n\
             ; ============================================================================\n\
             ; Uses channel C (registers 4/5=tone, 6=noise, 10=volume, 7=mixer bit2/bit5)\n\
-            ; RAM variables: SFX_PTR (16-bit), SFX_ACTIVE (8-bit)\n\
+            ; RAM variables: SFX_PTR (16-bit), SFX_ACTIVE, SFX_PRIORITY, SFX_ENV_MODE (8-bit)\n\
             ; AYFX format: flag byte + optional data per frame, end marker $D0 $20\n\
             ; Flag bits: 0-3=volume, 4=disable tone, 5=tone data present,\n\
             ;            6=noise data present, 7=disable noise\n\
+            ; $D0 $21 shape, period (FDB): envolvente hardware; desde ahi el volumen C\n\
+            ;            la sigue. No ocupa frame propio\n\
+            ; Al terminar, si hay musica, se restaura el canal C desde PSG_MUSIC_SHADOW\n\
             ; ============================================================================\n\
             ; (RAM variables defined in AUDIO_UPDATE section above)\n\
             \n\
             ; PLAY_SFX_RUNTIME - Start SFX playback\n\
-            ; Input: X = pointer to AYFX data\n\
+            ; Input: X = pointer to AYFX data, A = priority. Un efecto en curso\n\
+            ; de prioridad mayor se queda con el canal\n\
             PLAY_SFX_RUNTIME:\n\
+                TST SFX_ACTIVE\n\
+                BEQ sfx_start\n\
+                CMPA SFX_PRIORITY      ; Menor que la del efecto que suena?\n\
+                BLO sfx_rejected\n\
+            sfx_start:\n\
+                STA SFX_PRIORITY\n\
                 STX SFX_PTR            ; Store pointer\n\
                 LDA #$01\n\
                 STA SFX_ACTIVE         ; Mark as active\n\
+                CLR SFX_ENV_MODE       ; Volumen por software salvo $D0 $21\n\
+            sfx_rejected:\n\
                 RTS\n\
             \n\
             ; SFX_UPDATE - Process one AYFX frame (call once per frame in loop)\n\
@@ -688,7 +744,9 @@ PSG_update_done:\n\
                 BNE sfx_checktonefreq  ; Not end, continue\n\
                 LDB 1,U                ; Check second byte at offset 1\n\
                 CMPB #$20              ; End marker $D0 $20?\n\
-                BEQ sfx_endofeffect    ; Yes, stop\n\
+                LBEQ sfx_endofeffect   ; Yes, stop\n\
+                CMPB #$21              ; Envolvente $D0 $21?\n\
+                BEQ sfx_envelope\n\
             \n\
             sfx_checktonefreq:\n\
                 LEAY 1,U               ; Y = pointer to tone/noise data\n\
@@ -716,6 +774,7 @@ PSG_update_done:\n\
             sfx_checkvolume:\n\
                 LDB ,U                 ; Reload flag byte\n\
                 ANDB #$0F              ; Get volume from bits 0-3\n\
+                ORB SFX_ENV_MODE       ; $10: el volumen sigue a la envolvente\n\
                 LDA #$0A               ; Register 10 (volume C)\n\
                 JSR Sound_Byte         ; Write to PSG\n\
             \n\
@@ -757,15 +816,63 @@ PSG_update_done:\n\
                 STY SFX_PTR            ; Update pointer for next frame\n\
                 RTS\n\
             \n\
+            sfx_envelope:\n\
+                LDB 4,U                ; Periodo (byte bajo)\n\
+                LDA #$0B               ; Register 11\n\
+                JSR Sound_Byte\n\
+                LDB 3,U                ; Periodo (byte alto)\n\
+                LDA #$0C               ; Register 12\n\
+                JSR Sound_Byte\n\
+                LDB 2,U                ; Forma (escribirla reinicia la envolvente)\n\
+                LDA #$0D               ; Register 13\n\
+                JSR Sound_Byte\n\
+                LDA #$10\n\
+                STA SFX_ENV_MODE\n\
+                LEAU 5,U\n\
+                STU SFX_PTR\n\
+                LBRA sfx_doframe       ; Datos del frame\n\
+            \n\
             sfx_endofeffect:\n\
-                ; Stop SFX - set volume to 0\n\
                 CLR SFX_ACTIVE         ; Mark as inactive\n\
+                LDD #$0000\n\
+                STD SFX_PTR            ; Clear pointer\n\
+                LDA >PSG_IS_PLAYING    ; Con musica, devolverle el canal C\n\
+                BNE sfx_restore_music\n\
+                ; Stop SFX - set volume to 0\n\
                 LDA #$0A               ; Register 10 (volume C)\n\
                 LDB #$00               ; Volume = 0\n\
                 JSR Sound_Byte\n\
-                LDD #$0000\n\
-                STD SFX_PTR            ; Clear pointer\n\
+                CLR SFX_ENV_MODE\n\
                 RTS\n\
+            \n\
+            sfx_restore_music:\n\
+                LDU #PSG_MUSIC_SHADOW\n\
+                LDY #sfx_restore_regs\n\
+            sfx_restore_loop:\n\
+                LDA ,Y+                ; Siguiente registro ($FF = fin)\n\
+                BMI sfx_restore_mixer\n\
+                CMPA #$0B\n\
+                BLO sfx_restore_write\n\
+                TST SFX_ENV_MODE       ; Envolvente solo si el SFX la uso\n\
+                BEQ sfx_restore_mixer\n\
+            sfx_restore_write:\n\
+                LDB A,U                ; Ultimo valor de la musica\n\
+                JSR Sound_Byte\n\
+                BRA sfx_restore_loop\n\
+            sfx_restore_mixer:\n\
+                LDB $C807              ; Mixer shadow\n\
+                ANDB #$DB              ; Quitar los bits tono/ruido C del SFX\n\
+                PSHS B\n\
+                LDB 7,U\n\
+                ANDB #$24              ; Poner los de la musica\n\
+                ORB ,S+\n\
+                LDA #$07               ; Register 7 (mixer)\n\
+                JSR Sound_Byte\n\
+                CLR SFX_ENV_MODE\n\
+                RTS\n\
+            \n\
+            sfx_restore_regs:\n\
+                FCB $04,$05,$06,$0A,$0B,$0C,$0D,$FF\n\
             \n"
         );
     }
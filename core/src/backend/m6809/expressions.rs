@@ -1,7 +1,7 @@
 // Expressions - Expression code generation for M6809 backend
 use crate::ast::{BinOp, CmpOp, Expr, LogicOp};
 use crate::codegen::CodegenOptions;
-use super::{FuncCtx, emit_builtin_call, fresh_label, power_of_two_const, format_expr_ref};
+use super::{FuncCtx, emit_builtin_call, fresh_label, power_of_two_const, format_expr_ref, load_scalar};
 
 pub fn emit_expr(expr: &Expr, out: &mut String, fctx: &FuncCtx, string_map: &std::collections::BTreeMap<String,String>, opts: &CodegenOptions) {
     emit_expr_depth(expr, out, fctx, string_map, opts, 0, 0);
@@ -18,7 +18,7 @@ pub fn emit_expr_depth(expr: &Expr, out: &mut String, fctx: &FuncCtx, string_map
     }
     
     match expr {
-        Expr::Number(n) => {
+        Expr::Number(n) | Expr::Fixed(n) => {
             // Emit numbers as-is in decimal format (assembler interprets negatives as signed)
             out.push_str(&format!("    LDD #{}\n    STD RESULT\n", *n));
         }
@@ -34,7 +34,12 @@ pub fn emit_expr_depth(expr: &Expr, out: &mut String, fctx: &FuncCtx, string_map
             // Check if it's a local variable first
             if let Some(off) = fctx.offset_of(&name.name) { 
                 let adjusted_offset = off + (stack_depth * 2) as i32;
-                out.push_str(&format!("    LDD {} ,S\n    STD RESULT\n", adjusted_offset)); 
+                if let Some(ty) = fctx.scalar_type(&name.name) {
+                    out.push_str(&load_scalar(&format!("{},S", adjusted_offset), Some(ty)));
+                    out.push_str("    STD RESULT\n");
+                } else {
+                    out.push_str(&format!("    LDD {} ,S\n    STD RESULT\n", adjusted_offset)); 
+                }
             } 
             // Check if it's a compile-time constant
             else if let Some(value) = opts.const_values.get(&upper_name) {
@@ -47,9 +52,18 @@ pub fn emit_expr_depth(expr: &Expr, out: &mut String, fctx: &FuncCtx, string_map
             }
             // Otherwise it's a regular global variable
             else { 
-                out.push_str(&format!("    LDD VAR_{}\n    STD RESULT\n", upper_name)); 
+                out.push_str(&load_scalar(&format!("VAR_{}", upper_name), opts.global_types.get(&name.name).copied()));
+                out.push_str("    STD RESULT\n");
             }
         }
+        Expr::Call(ci) if matches!(ci.name.as_str(), "FIXED_MUL" | "FIXED_DIV") && ci.args.len() == 2 => {
+            // Fixed-point 8.8 product/quotient: operands kept on the stack like Binary
+            emit_expr_depth(&ci.args[0], out, fctx, string_map, opts, depth + 1, stack_depth);
+            out.push_str("    LDD RESULT\n    PSHS D\n");
+            emit_expr_depth(&ci.args[1], out, fctx, string_map, opts, depth + 1, stack_depth + 1);
+            out.push_str("    LDD RESULT\n    STD FIX_B\n    PULS D\n    STD FIX_A\n");
+            out.push_str(if ci.name == "FIXED_MUL" { "    JSR FIXMUL\n" } else { "    JSR FIXDIV\n" });
+        }
         Expr::Call(ci) => {
             if emit_builtin_call(&ci.name, &ci.args, out, fctx, string_map, opts, Some(ci.source_line)) { 
                 return; 
@@ -332,7 +346,11 @@ pub fn emit_expr_depth(expr: &Expr, out: &mut String, fctx: &FuncCtx, string_map
                                 // Load struct pointer and read field
                                 out.push_str(&format!("    ; FieldAccess self.{} (struct {} field offset {})\n", field, method_struct_type, field_offset));
                                 out.push_str("    LDX VAR_ARG0    ; Load struct pointer\n");
-                                out.push_str(&format!("    LDD {},X        ; Read field value\n", field_offset));
+                                if field_layout.ty.is_some_and(|t| t.is_byte()) {
+                                    out.push_str(&load_scalar(&format!("{},X", field_offset), field_layout.ty));
+                                } else {
+                                    out.push_str(&format!("    LDD {},X        ; Read field value\n", field_offset));
+                                }
                                 out.push_str("    STD RESULT\n");
                             } else {
                                 eprintln!("WARNING at line {}: Field '{}' not found in struct '{}'", source_line, field, method_struct_type);
@@ -362,7 +380,8 @@ pub fn emit_expr_depth(expr: &Expr, out: &mut String, fctx: &FuncCtx, string_map
                                     let field_offset_bytes = field_layout.offset as i32; // offset is already in bytes
                                     let total_offset = base_offset + field_offset_bytes + (stack_depth * 2) as i32;
                                     out.push_str(&format!("    ; FieldAccess {}.{} (struct {} offset {})\n", var_name, field, type_name, total_offset));
-                                    out.push_str(&format!("    LDD {},S\n    STD RESULT\n", total_offset));
+                                    out.push_str(&load_scalar(&format!("{},S", total_offset), field_layout.ty));
+                                    out.push_str("    STD RESULT\n");
                                 } else {
                                     eprintln!("WARNING at line {}: Field '{}' not found in struct '{}'", source_line, field, type_name);
                                     out.push_str("    LDD #0\n    STD RESULT\n");
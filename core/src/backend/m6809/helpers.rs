@@ -13,3 +13,16 @@ pub fn emit_div_helper(out: &mut String) {
         "DIV16:\n    LDD #0\n    STD DIV_Q\n    LDD DIV_A\n    STD DIV_R\n    LDD DIV_B\n    BEQ DIV16_DONE\nDIV16_LOOP:\n    LDD DIV_R\n    SUBD DIV_B\n    BLO DIV16_DONE\n    STD DIV_R\n    LDD DIV_Q\n    ADDD #1\n    STD DIV_Q\n    BRA DIV16_LOOP\nDIV16_DONE:\n    LDD DIV_Q\n    STD RESULT\n    RTS\n\n",
     );
 }
+
+/// Emit fixed-point 8.8 helpers: FIXMUL (FIX_A * FIX_B) and FIXDIV (FIX_A / FIX_B),
+/// signed, result in RESULT. FIXMUL sums the four 8x8 MUL partial products of the
+/// magnitudes; FIXDIV is a 24/16-bit shift-subtract division of |A| << 8 by |B|.
+/// Division by zero yields +/-$7FFF.
+pub fn emit_fixed_helpers(out: &mut String) {
+    out.push_str(
+        "FIXMUL:\n    CLR FIX_SIGN\n    LDD FIX_A\n    BPL FIXMUL_APOS\n    INC FIX_SIGN\n    COMA\n    COMB\n    ADDD #1\n    STD FIX_A\nFIXMUL_APOS:\n    LDD FIX_B\n    BPL FIXMUL_BPOS\n    INC FIX_SIGN\n    COMA\n    COMB\n    ADDD #1\n    STD FIX_B\nFIXMUL_BPOS:\n    ; lo*lo: only the high byte survives the >> 8\n    LDA FIX_A+1\n    LDB FIX_B+1\n    MUL\n    TFR A,B\n    CLRA\n    STD FIX_RES\n    ; hi*lo + lo*hi\n    LDA FIX_A\n    LDB FIX_B+1\n    MUL\n    ADDD FIX_RES\n    STD FIX_RES\n    LDA FIX_A+1\n    LDB FIX_B\n    MUL\n    ADDD FIX_RES\n    STD FIX_RES\n    ; hi*hi << 8\n    LDA FIX_A\n    LDB FIX_B\n    MUL\n    TFR B,A\n    CLRB\n    ADDD FIX_RES\n    BRA FIX_APPLY_SIGN\n\n",
+    );
+    out.push_str(
+        "FIXDIV:\n    CLR FIX_SIGN\n    LDD FIX_A\n    BPL FIXDIV_APOS\n    INC FIX_SIGN\n    COMA\n    COMB\n    ADDD #1\nFIXDIV_APOS:\n    STD FIX_N\n    CLR FIX_N+2\n    LDD FIX_B\n    BNE FIXDIV_NONZERO\n    LDD #$7FFF\n    BRA FIX_APPLY_SIGN\nFIXDIV_NONZERO:\n    BPL FIXDIV_BPOS\n    INC FIX_SIGN\n    COMA\n    COMB\n    ADDD #1\n    STD FIX_B\nFIXDIV_BPOS:\n    LDD #0\n    STD FIX_R\n    LDA #24\n    STA FIX_CNT\nFIXDIV_LOOP:\n    ; N <<= 1, top bit into R\n    LDB FIX_N+2\n    ASLB\n    STB FIX_N+2\n    LDD FIX_N\n    ROLB\n    ROLA\n    STD FIX_N\n    LDD FIX_R\n    ROLB\n    ROLA\n    STD FIX_R\n    SUBD FIX_B\n    BLO FIXDIV_NEXT\n    STD FIX_R\n    INC FIX_N+2\nFIXDIV_NEXT:\n    DEC FIX_CNT\n    BNE FIXDIV_LOOP\n    LDD FIX_N+1\nFIX_APPLY_SIGN:\n    STD RESULT\n    LDA FIX_SIGN\n    ANDA #1\n    BEQ FIX_SIGN_DONE\n    LDD RESULT\n    COMA\n    COMB\n    ADDD #1\n    STD RESULT\nFIX_SIGN_DONE:\n    RTS\n\n",
+    );
+}
@@ -117,7 +117,7 @@ fn analyze_used_assets(module: &Module, assets: &[crate::codegen::AssetInfo]) ->
                 if (name_upper == "DRAW_VECTOR" && call_info.args.len() == 3) || 
                    (name_upper == "DRAW_VECTOR_EX" && call_info.args.len() == 5) ||
                    (name_upper == "PLAY_MUSIC" && call_info.args.len() == 1) ||
-                   (name_upper == "PLAY_SFX" && call_info.args.len() == 1) ||
+                   (name_upper == "PLAY_SFX" && (1..=3).contains(&call_info.args.len())) ||
                    (name_upper == "LOAD_LEVEL" && call_info.args.len() == 1) {
                     if let Expr::StringLit(asset_name) = &call_info.args[0] {
                         eprintln!("[DEBUG] Found asset usage: {} ({})", asset_name, name_upper);
@@ -473,6 +473,17 @@ pub fn emit_with_debug(module: &Module, _t: Target, ti: &TargetInfo, opts: &Code
         ram.allocate("MUL_CNT", 2, "Multiply counter");
     }
     
+    // 3b. Fixed-point helpers (if needed)
+    if rt_usage.needs_fixed_helpers {
+        ram.allocate("FIX_A", 2, "Fixed-point operand A");
+        ram.allocate("FIX_B", 2, "Fixed-point operand B");
+        ram.allocate("FIX_RES", 2, "FIXMUL partial sum");
+        ram.allocate("FIX_N", 3, "FIXDIV dividend/quotient (24-bit)");
+        ram.allocate("FIX_R", 2, "FIXDIV remainder");
+        ram.allocate("FIX_SIGN", 1, "Result sign (bit 0)");
+        ram.allocate("FIX_CNT", 1, "FIXDIV bit counter");
+    }
+    
     // 4. Division helper (if needed)
     if rt_usage.needs_div_helper {
         ram.allocate("DIV_A", 2, "Dividend");
@@ -494,6 +505,9 @@ pub fn emit_with_debug(module: &Module, _t: Target, ti: &TargetInfo, opts: &Code
         ram.allocate("PSG_MUSIC_ACTIVE", 1, "Set during UPDATE_MUSIC_PSG");
         ram.allocate("PSG_FRAME_COUNT", 1, "Frame register write count");
         ram.allocate("PSG_DELAY_FRAMES", 1, "Frames to wait before next read");
+        ram.allocate("PSG_MUSIC_PACKED", 1, "Stream is packed ($FE header)");
+        ram.allocate("PSG_MUSIC_REF_LEFT", 1, "Packed: tokens left in a back-reference");
+        ram.allocate("PSG_MUSIC_REF_RET", 2, "Packed: return address of a back-reference");
     }
     
     // 7. SFX variables (if SFX assets exist)
@@ -503,6 +517,13 @@ pub fn emit_with_debug(module: &Module, _t: Target, ti: &TargetInfo, opts: &Code
         ram.allocate("SFX_ACTIVE", 1, "Playback state ($00=stopped, $01=playing)");
         ram.allocate("SFX_PHASE", 1, "Envelope phase (0=A,1=D,2=S,3=R)");
         ram.allocate("SFX_VOL", 1, "Current volume level (0-15)");
+        ram.allocate("SFX_PRIORITY", 1, "Priority of the playing effect");
+    }
+    
+    // Compartidas musica/SFX: el SFX toma el canal C y lo devuelve al terminar
+    if has_music_assets || has_sfx_assets {
+        ram.allocate("SFX_ENV_MODE", 1, "SFX volume mode ($10=hardware envelope)");
+        ram.allocate("PSG_MUSIC_SHADOW", 14, "Last music value per PSG register");
     }
     
     // 8. PRINT_NUMBER buffer (always allocate if not suppressed)
@@ -625,6 +646,10 @@ pub fn emit_with_debug(module: &Module, _t: Target, ti: &TargetInfo, opts: &Code
             // Arrays: allocate space for N elements * 2 bytes each
             let var_name = format!("VAR_{}_DATA", v.to_uppercase());
             ram.allocate(&var_name, array_len * 2, &format!("Array data ({} elements)", array_len));
+        } else if let Some(ty) = opts.global_types.get(&v) {
+            // Annotated scalar: u8/i8/bool take a single byte
+            let var_name = format!("VAR_{}", v.to_uppercase());
+            ram.allocate(&var_name, ty.size(), format!("User variable ({})", ty.name()));
         } else {
             // Scalar variables: 2 bytes each
             let var_name = format!("VAR_{}", v.to_uppercase());
@@ -797,7 +822,7 @@ pub fn emit_with_debug(module: &Module, _t: Target, ti: &TargetInfo, opts: &Code
                 } else if let Expr::Number(n) = value {
                     // Emit numbers as decimal (assembler interprets negatives as signed)
                     out.push_str(&format!("    LDD #{}\n", n));
-                    out.push_str(&format!("    STD VAR_{}\n", name.to_uppercase()));
+                    out.push_str(&store_scalar(&format!("VAR_{}", name.to_uppercase()), opts.global_types.get(name).copied()));
                 } else if let Expr::StringLit(s) = value {
                     // String literal: load address of string from string_map
                     if let Some(label) = string_map.get(s) {
@@ -810,13 +835,13 @@ pub fn emit_with_debug(module: &Module, _t: Target, ti: &TargetInfo, opts: &Code
                     }
                 } else {
                     // For non-constant initial values, evaluate the expression
-                    emit_expr(value, &mut out, &FuncCtx { locals: Vec::new(), frame_size: 0, var_info: std::collections::HashMap::new(), struct_type: None, params: Vec::new() }, &string_map, opts);
-                    out.push_str(&format!("    STD VAR_{}\n", name.to_uppercase()));
+                    emit_expr(value, &mut out, &FuncCtx::top_level(), &string_map, opts);
+                    out.push_str(&store_scalar(&format!("VAR_{}", name.to_uppercase()), opts.global_types.get(name).copied()));
                 }
             }
             
             if let Some(main_func) = user_main {
-                let fctx = FuncCtx { locals: Vec::new(), frame_size: 0, var_info: std::collections::HashMap::new(), struct_type: None, params: Vec::new() };
+                let fctx = FuncCtx::top_level();
                 for stmt in &main_func.body {
                     emit_stmt(stmt, &mut out, &LoopCtx::default(), &fctx, &string_map, opts, &mut tracker, 0);
                 }
@@ -878,11 +903,11 @@ pub fn emit_with_debug(module: &Module, _t: Target, ti: &TargetInfo, opts: &Code
                     array_counter += 1;
                 } else if let Expr::Number(n) = value {
                     out.push_str(&format!("    LDD #{}\n", n));
-                    out.push_str(&format!("    STD VAR_{}\n", name.to_uppercase()));
+                    out.push_str(&store_scalar(&format!("VAR_{}", name.to_uppercase()), opts.global_types.get(name).copied()));
                 } else {
                     // For non-constant initial values, evaluate the expression
-                    emit_expr(value, &mut out, &FuncCtx { locals: Vec::new(), frame_size: 0, var_info: std::collections::HashMap::new(), struct_type: None, params: Vec::new() }, &string_map, opts);
-                    out.push_str(&format!("    STD VAR_{}\n", name.to_uppercase()));
+                    emit_expr(value, &mut out, &FuncCtx::top_level(), &string_map, opts);
+                    out.push_str(&store_scalar(&format!("VAR_{}", name.to_uppercase()), opts.global_types.get(name).copied()));
                 }
             }
         }
@@ -923,14 +948,10 @@ pub fn emit_with_debug(module: &Module, _t: Target, ti: &TargetInfo, opts: &Code
                     // Analyze variable types for struct instances
                     let var_info = analyze_var_types(&f.body, &locals, &opts.structs);
                     
-                    // Calculate frame size based on actual variable sizes
-                    let mut frame_size = 0;
-                    for var_name in &locals {
-                        let size = var_info.get(var_name)
-                            .map(|(_, s)| *s as i32)
-                            .unwrap_or(2);
-                        frame_size += size;
-                    }
+                    // Calculate frame size based on actual variable sizes (struct instances, byte-typed locals)
+                    let mut fctx = FuncCtx { locals: locals.clone(), frame_size: 0, var_info, struct_type: None, params: f.params.clone(), scalar_types: crate::types::local_types(f) };
+                    fctx.frame_size = fctx.locals_size();
+                    let frame_size = fctx.frame_size;
                     
                     if frame_size > 0 {
                         out.push_str(&format!("    LEAS -{},S ; allocate locals\n", frame_size));
@@ -946,7 +967,6 @@ pub fn emit_with_debug(module: &Module, _t: Target, ti: &TargetInfo, opts: &Code
                     out.push_str("    JSR $F1BA  ; Read_Btns: read PSG register 14, update $C80F (Vec_Btn_State)\n");
                     out.push_str("    JSR $F1AF  ; DP_to_C8: restore direct page to $C8 for normal RAM access\n");
 
-                    let fctx = FuncCtx { locals: locals.clone(), frame_size, var_info, struct_type: None, params: f.params.clone() };
                     for (i, stmt) in f.body.iter().enumerate() {
                         out.push_str(&format!("    ; DEBUG: Statement {} - {:?}\n", i, std::mem::discriminant(stmt)));
                         emit_stmt(stmt, &mut out, &LoopCtx::default(), &fctx, &string_map, opts, &mut tracker, 0);
@@ -1125,6 +1145,9 @@ pub fn emit_with_debug(module: &Module, _t: Target, ti: &TargetInfo, opts: &Code
                 // Export declarations are metadata for multi-file compilation.
                 // No code generation needed at this stage.
             }
+            Item::Enum(_) => {
+                // Variants were replaced by their numbers in enums::resolve_module
+            }
             Item::StructDef(struct_def) => {
                 // Phase 3 - struct definitions: emit methods as regular functions with mangled names
                 // Method naming convention: StructName_method_name
@@ -1165,6 +1188,7 @@ pub fn emit_with_debug(module: &Module, _t: Target, ti: &TargetInfo, opts: &Code
     if !suppress_runtime {
         if rt_usage.needs_mul_helper { emit_mul_helper(&mut out); }
         if rt_usage.needs_div_helper { emit_div_helper(&mut out); }
+        if rt_usage.needs_fixed_helpers { emit_fixed_helpers(&mut out); }
         // NOTE: emit_builtin_helpers moved BEFORE program code (line ~268) to fix forward references
     }
     out.push_str(";***************************************************************************\n; DATA SECTION\n;***************************************************************************\n");
@@ -1172,7 +1196,7 @@ pub fn emit_with_debug(module: &Module, _t: Target, ti: &TargetInfo, opts: &Code
     // Re-evaluate suppress_runtime now that we know max_args (calculated earlier)
     let no_runtime_vars_needed = !rt_usage.needs_tmp_left && !rt_usage.needs_tmp_right && 
                                  !rt_usage.needs_tmp_ptr && 
-                                 !rt_usage.needs_mul_helper && !rt_usage.needs_div_helper && 
+                                 !rt_usage.needs_mul_helper && !rt_usage.needs_div_helper && !rt_usage.needs_fixed_helpers && 
                                  !rt_usage.needs_line_vars && !rt_usage.needs_vcur_vars &&
                                  string_map.is_empty() && max_args == 0;
     suppress_runtime = main_inlined || no_runtime_vars_needed;
@@ -1,7 +1,8 @@
 // Statements - Statement code generation for M6809 backend
 use crate::ast::{AssignTarget, Expr, Stmt};
 use crate::codegen::CodegenOptions;
-use super::{LoopCtx, FuncCtx, emit_expr, emit_builtin_call, fresh_label, LineTracker};
+use super::{LoopCtx, FuncCtx, emit_expr, emit_builtin_call, fresh_label, LineTracker, var_scalar_type, load_scalar, store_scalar};
+use crate::types::ScalarType;
 
 pub fn emit_stmt(stmt: &Stmt, out: &mut String, loop_ctx: &LoopCtx, fctx: &FuncCtx, string_map: &std::collections::BTreeMap<String,String>, opts: &CodegenOptions, tracker: &mut LineTracker, depth: usize) {
     // Safety: Prevent stack overflow with deep recursion
@@ -22,7 +23,10 @@ pub fn emit_stmt(stmt: &Stmt, out: &mut String, loop_ctx: &LoopCtx, fctx: &FuncC
             match target {
                 crate::ast::AssignTarget::Ident { name, .. } => {
                     emit_expr(value, out, fctx, string_map, opts);
-                    if let Some(off) = fctx.offset_of(name) {
+                    let ty = var_scalar_type(name, fctx, opts);
+                    if ty.is_some_and(|t| t.is_byte()) {
+                        emit_store_var(name, ty, out, fctx);
+                    } else if let Some(off) = fctx.offset_of(name) {
                         out.push_str(&format!("    LDX RESULT\n    STX {} ,S\n", off));
                     } else {
                         out.push_str(&format!("    LDX RESULT\n    LDU #VAR_{}\n    STU TMPPTR\n    STX ,U\n", name.to_uppercase()));
@@ -92,7 +96,11 @@ pub fn emit_stmt(stmt: &Stmt, out: &mut String, loop_ctx: &LoopCtx, fctx: &FuncC
                                         out.push_str(&format!("    ; Assign self.{} (struct {} field offset {})\n", field, method_struct_type, field_offset));
                                         out.push_str("    LDX VAR_ARG0    ; Load struct pointer\n");
                                         out.push_str("    LDD RESULT      ; Load value to assign\n");
-                                        out.push_str(&format!("    STD {},X        ; Store at field offset\n", field_offset));
+                                        if field_layout.ty.is_some_and(|t| t.is_byte()) {
+                                            out.push_str(&store_scalar(&format!("{},X", field_offset), field_layout.ty));
+                                        } else {
+                                            out.push_str(&format!("    STD {},X        ; Store at field offset\n", field_offset));
+                                        }
                                     } else {
                                         eprintln!("WARNING: Field '{}' not found in struct '{}'", field, method_struct_type);
                                         out.push_str(&format!("    ; ERROR: Field '{}' not found in struct '{}'\n", field, method_struct_type));
@@ -126,7 +134,8 @@ pub fn emit_stmt(stmt: &Stmt, out: &mut String, loop_ctx: &LoopCtx, fctx: &FuncC
                                             
                                             // 2. Store value at field location
                                             out.push_str(&format!("    ; Assign {}.{} (struct {} offset {})\n", var_name, field, type_name, total_offset));
-                                            out.push_str(&format!("    LDD RESULT\n    STD {},S\n", total_offset));
+                                            out.push_str("    LDD RESULT\n");
+                                            out.push_str(&store_scalar(&format!("{},S", total_offset), field_layout.ty));
                                         } else {
                                             eprintln!("WARNING: Field '{}' not found in struct '{}'", field, type_name);
                                         }
@@ -168,7 +177,11 @@ pub fn emit_stmt(stmt: &Stmt, out: &mut String, loop_ctx: &LoopCtx, fctx: &FuncC
             } else {
                 // Normal expression evaluation
                 emit_expr(value, out, fctx, string_map, opts);
-                if let Some(off) = fctx.offset_of(name) {
+                let ty = var_scalar_type(name, fctx, opts);
+                if ty.is_some() || fctx.offset_of(name).is_none() {
+                    // Annotated declaration (`x: u8 = ...`); outside a frame it names a global
+                    emit_store_var(name, ty, out, fctx);
+                } else if let Some(off) = fctx.offset_of(name) {
                     out.push_str(&format!("    LDX RESULT\n    STX {} ,S\n", off));
                 }
             }
@@ -207,12 +220,15 @@ pub fn emit_stmt(stmt: &Stmt, out: &mut String, loop_ctx: &LoopCtx, fctx: &FuncC
         Stmt::For { var, start, end, step, body, .. } => {
             let ls = fresh_label("FOR");
             let le = fresh_label("FOR_END");
+            let var_ty = var_scalar_type(var, fctx, opts);
             emit_expr(start, out, fctx, string_map, opts);
             out.push_str("    LDD RESULT\n");
-            if let Some(off) = fctx.offset_of(var) { out.push_str(&format!("    STD {} ,S\n", off)); }
+            if var_ty.is_some_and(|t| t.is_byte()) { emit_store_var(var, var_ty, out, fctx); }
+            else if let Some(off) = fctx.offset_of(var) { out.push_str(&format!("    STD {} ,S\n", off)); }
             else { out.push_str(&format!("    STD VAR_{}\n", var.to_uppercase())); }
             out.push_str(&format!("{}: ; for loop\n", ls));
-            if let Some(off) = fctx.offset_of(var) { out.push_str(&format!("    LDD {} ,S\n", off)); }
+            if var_ty.is_some_and(|t| t.is_byte()) { emit_load_var(var, var_ty, out, fctx); }
+            else if let Some(off) = fctx.offset_of(var) { out.push_str(&format!("    LDD {} ,S\n", off)); }
             else { out.push_str(&format!("    LDD VAR_{}\n", var.to_uppercase())); }
             emit_expr(end, out, fctx, string_map, opts);
             out.push_str("    LDX RESULT\n    CPD RESULT\n");
@@ -225,7 +241,12 @@ pub fn emit_stmt(stmt: &Stmt, out: &mut String, loop_ctx: &LoopCtx, fctx: &FuncC
             } else {
                 out.push_str("    LDX #1\n");
             }
-            if let Some(off) = fctx.offset_of(var) { out.push_str(&format!("    LDD {} ,S\n    ADDD ,X\n    STD {} ,S\n", off, off)); }
+            if var_ty.is_some_and(|t| t.is_byte()) {
+                emit_load_var(var, var_ty, out, fctx);
+                out.push_str("    ADDD ,X\n");
+                emit_store_var(var, var_ty, out, fctx);
+            }
+            else if let Some(off) = fctx.offset_of(var) { out.push_str(&format!("    LDD {} ,S\n    ADDD ,X\n    STD {} ,S\n", off, off)); }
             else { out.push_str(&format!("    LDD VAR_{}\n    ADDD ,X\n    STD VAR_{}\n", var.to_uppercase(), var.to_uppercase())); }
             out.push_str(&format!("    LBRA {}\n{}: ; for end\n", ls, le));
         }
@@ -273,7 +294,10 @@ pub fn emit_stmt(stmt: &Stmt, out: &mut String, loop_ctx: &LoopCtx, fctx: &FuncC
             out.push_str("    STX TMPPTR      ; Save updated pointer\n");
             
             // Store element in loop variable
-            if let Some(off) = fctx.offset_of(var) {
+            let var_ty = var_scalar_type(var, fctx, opts);
+            if var_ty.is_some_and(|t| t.is_byte()) {
+                emit_store_var(var, var_ty, out, fctx);
+            } else if let Some(off) = fctx.offset_of(var) {
                 out.push_str(&format!("    STD {},S        ; Store in local var\n", off));
             } else {
                 out.push_str(&format!("    STD VAR_{}     ; Store in global var\n", var.to_uppercase()));
@@ -392,3 +416,19 @@ pub fn emit_stmt(stmt: &Stmt, out: &mut String, loop_ctx: &LoopCtx, fctx: &FuncC
 
 // emit_expr: lower expressions; result placed in RESULT.
 // Nota: En 6809 las operaciones sobre D ya limitan a 16 bits; no hace falta 'mask' explícito.
+
+// emit_load_var / emit_store_var: D <-> variable (stack slot or VAR_ global), byte- or word-sized per `ty`
+fn emit_load_var(name: &str, ty: Option<ScalarType>, out: &mut String, fctx: &FuncCtx) {
+    match fctx.offset_of(name) {
+        Some(off) => out.push_str(&load_scalar(&format!("{},S", off), ty)),
+        None => out.push_str(&load_scalar(&format!("VAR_{}", name.to_uppercase()), ty)),
+    }
+}
+
+fn emit_store_var(name: &str, ty: Option<ScalarType>, out: &mut String, fctx: &FuncCtx) {
+    out.push_str("    LDD RESULT\n");
+    match fctx.offset_of(name) {
+        Some(off) => out.push_str(&store_scalar(&format!("{},S", off), ty)),
+        None => out.push_str(&store_scalar(&format!("VAR_{}", name.to_uppercase()), ty)),
+    }
+}
@@ -2,6 +2,8 @@
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::collections::BTreeSet;
 use crate::ast::{Expr, Stmt};
+use crate::codegen::CodegenOptions;
+use crate::types::ScalarType;
 
 /// Generate a unique label with the given prefix
 pub fn fresh_label(prefix: &str) -> String {
@@ -26,6 +28,7 @@ pub fn format_expr_ref(e: &Expr) -> String {
     match e {
         Expr::Ident(n) => format!("I:{}", n.name),
         Expr::Number(v) => format!("N:{}", v),
+        Expr::Fixed(v) => format!("F:{}", v),
         Expr::StringLit(s) => format!("S:{}", s),
         Expr::Call(ci) => format!("C:{}", ci.name),
         Expr::MethodCall(mc) => format!("M:{}.{}", format_expr_ref(&mc.target), mc.method_name),
@@ -54,7 +57,7 @@ pub fn collect_expr_syms(expr: &Expr, set: &mut BTreeSet<String>) {
             collect_expr_syms(right, set);
         }
         Expr::Not(inner) | Expr::BitNot(inner) => collect_expr_syms(inner, set),
-        Expr::Number(_) | Expr::StringLit(_) => {}
+        Expr::Number(_) | Expr::Fixed(_) | Expr::StringLit(_) => {}
         Expr::List(elements) => {
             for elem in elements {
                 collect_expr_syms(elem, set);
@@ -292,36 +295,53 @@ pub struct FuncCtx {
     pub struct_type: Option<String>, // Some("Point") if in method, None if regular function
     // NEW: Track function parameters (in order) for correct stack offset calculation
     pub params: Vec<String>, // Parameter names in order (for correct stack positioning)
+    // Annotated locals/params (u8/i8/bool take a 1-byte slot), see types.rs
+    pub scalar_types: std::collections::HashMap<String, ScalarType>,
 }
 
 impl FuncCtx {
+    /// Empty context for code outside any function (global initialisers, inlined main)
+    pub fn top_level() -> Self {
+        FuncCtx { locals: Vec::new(), frame_size: 0, var_info: std::collections::HashMap::new(), struct_type: None, params: Vec::new(), scalar_types: std::collections::HashMap::new() }
+    }
+
+    // slot_size: bytes taken on the stack by one local or parameter
+    fn slot_size(&self, name: &str) -> i32 {
+        if let Some((_, size)) = self.var_info.get(name) { return *size as i32; }
+        self.scalar_types.get(name).map_or(2, |t| t.size() as i32) // Default to 2 bytes for simple variables
+    }
+
+    /// Total bytes of the stack frame (parameters + locals)
+    pub fn locals_size(&self) -> i32 {
+        self.locals.iter().map(|n| self.slot_size(n)).sum()
+    }
+
     pub fn offset_of(&self, name: &str) -> Option<i32> {
-        // FIRST: Check if this is a parameter (they're always at fixed positions: 0,S, 2,S, 4,S, 6,S)
-        for (i, param) in self.params.iter().enumerate() {
+        // FIRST: parameters, in declaration order (0,S is param 0)
+        let mut offset = 0;
+        for param in &self.params {
             if param.eq_ignore_ascii_case(name) {
-                // Parameter found - return its fixed stack position
-                return Some((i as i32) * 2); // Param 0 at 0,S, Param 1 at 2,S, etc.
+                return Some(offset);
             }
+            offset += self.slot_size(param);
         }
         
-        // If not a parameter, calculate offset for local variables
         // Local variables come AFTER parameters in the stack
-        let param_space = (self.params.len() as i32) * 2; // Space taken by parameters
-        let mut local_offset = param_space; // Start after parameters
-        
         for var_name in &self.locals {
+            if self.params.contains(var_name) { continue; } // already placed above
             if var_name.eq_ignore_ascii_case(name) {
-                return Some(local_offset);
+                return Some(offset);
             }
-            // Get size of this variable
-            let size = self.var_info.get(var_name)
-                .map(|(_, s)| *s as i32)
-                .unwrap_or(2); // Default to 2 bytes for simple variables
-            local_offset += size;
+            offset += self.slot_size(var_name);
         }
         None
     }
     
+    /// Annotated type of a local or parameter
+    pub fn scalar_type(&self, name: &str) -> Option<ScalarType> {
+        self.scalar_types.get(name).copied()
+    }
+    
     pub fn var_type(&self, name: &str) -> Option<&str> {
         self.var_info.get(name).map(|(t, _)| t.as_str())
     }
@@ -330,3 +350,25 @@ impl FuncCtx {
         self.struct_type.clone()
     }
 }
+
+/// Annotated type of a variable as seen from `fctx`: locals shadow globals
+pub fn var_scalar_type(name: &str, fctx: &FuncCtx, opts: &CodegenOptions) -> Option<ScalarType> {
+    if fctx.offset_of(name).is_some() { fctx.scalar_type(name) } else { opts.global_types.get(name).copied() }
+}
+
+/// Load a variable into D: byte types are widened (u8/bool zero-extend, i8 sign-extend)
+pub fn load_scalar(operand: &str, ty: Option<ScalarType>) -> String {
+    match ty {
+        Some(ScalarType::I8) => format!("    LDB {}\n    SEX\n", operand),
+        Some(t) if t.is_byte() => format!("    LDB {}\n    CLRA\n", operand),
+        _ => format!("    LDD {}\n", operand),
+    }
+}
+
+/// Store D into a variable: byte types keep the low byte (wraps like the hardware)
+pub fn store_scalar(operand: &str, ty: Option<ScalarType>) -> String {
+    match ty {
+        Some(t) if t.is_byte() => format!("    STB {}\n", operand),
+        _ => format!("    STD {}\n", operand),
+    }
+}
@@ -22,6 +22,7 @@ pub struct BinaryEmitter {
     line_to_offset: HashMap<usize, usize>,  // Línea VPy -> offset en binario
     offset_to_line: HashMap<usize, usize>,  // Offset en binario -> línea VPy
     current_line: usize,                    // Línea actual del código fuente VPy
+    direct_page: Option<u8>,                // Página de SETDP para modo directo automático (None: siempre extendido)
 }
 
 impl BinaryEmitter {
@@ -35,9 +36,28 @@ impl BinaryEmitter {
             line_to_offset: HashMap::new(),
             offset_to_line: HashMap::new(),
             current_line: 0,
+            direct_page: Some(0),
         }
     }
 
+    /// Establece la página asumida en DP (SETDP); sus direcciones usan modo directo
+    pub fn set_direct_page(&mut self, page: Option<u8>) {
+        self.direct_page = page;
+    }
+
+    /// Página asumida en DP, si hay
+    pub fn direct_page(&self) -> Option<u8> {
+        self.direct_page
+    }
+
+    /// Saltos largos en lugar de cortos: el core ensambla un solo banco y no los fuerza
+    pub fn long_branches(&self) -> bool {
+        false
+    }
+
+    /// Acceso que SETDP volvió directo: el core no informa del ahorro (ver vpy_assembler)
+    pub(crate) fn record_direct_page_hit(&mut self, _page: u8) {}
+
     /// Establece la línea actual del código fuente (para debug mapping)
     pub fn set_source_line(&mut self, line: usize) {
         self.current_line = line;
@@ -49,7 +69,7 @@ impl BinaryEmitter {
     }
 
     /// Registra mapeo bidireccional línea ↔ offset
-    fn record_line_mapping(&mut self) {
+    pub(crate) fn record_line_mapping(&mut self) {
         let offset = self.current_offset();
         self.line_to_offset.insert(self.current_line, offset);
         self.offset_to_line.insert(offset, self.current_line);
@@ -731,6 +751,12 @@ impl BinaryEmitter {
         self.emit(0x3A);
     }
 
+    /// MUL (opcode 0x3D) - Unsigned multiply A * B -> D
+    pub fn mul(&mut self) {
+        self.record_line_mapping();
+        self.emit(0x3D);
+    }
+
     /// SEX (opcode 0x1D) - Sign EXtend B into A
     /// Extends the sign bit of register B into register A
     /// If B is negative (bit 7 = 1), sets A to 0xFF
@@ -78,3 +78,160 @@ fn get_page3_size(opcode: u8) -> u16 {
         _ => 2,
     }
 }
+
+// ---------------------------------------------------------------------------
+// Cycle counts (E-clock) per mnemonic and addressing mode, for static
+// estimation over emitted assembly text (see cycles.rs).
+// Reference: MC6809 datasheet, Table 9 (indexed) and the instruction tables.
+// ---------------------------------------------------------------------------
+
+/// Addressing mode as written in the assembly operand
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddrMode {
+    Inherent,
+    Immediate,
+    Direct,
+    Indexed,
+    Extended,
+}
+
+/// Classify an operand the way the native assembler encodes it:
+/// `#` immediate, `<` direct, anything with a comma or `[` indexed, else extended.
+pub fn addressing_mode(operand: &str) -> AddrMode {
+    let op = operand.trim();
+    if op.is_empty() {
+        AddrMode::Inherent
+    } else if op.starts_with('#') {
+        AddrMode::Immediate
+    } else if op.starts_with('[') || op.contains(',') {
+        AddrMode::Indexed
+    } else if op.starts_with('<') {
+        AddrMode::Direct
+    } else {
+        AddrMode::Extended
+    }
+}
+
+/// Extra cycles added by an indexed postbyte (Table 9), including indirection
+pub fn indexed_extra_cycles(operand: &str) -> u32 {
+    let op: String = operand.chars().filter(|c| !c.is_whitespace()).collect();
+    let (inner, indirect) = match op.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+        Some(inner) => (inner.to_string(), true),
+        None => (op.clone(), false),
+    };
+    let Some((offset, reg)) = inner.rsplit_once(',') else {
+        // [n16] extended indirect
+        return 5;
+    };
+    let base = if reg.ends_with("++") || reg.starts_with("--") {
+        3
+    } else if reg.ends_with('+') || reg.starts_with('-') {
+        2
+    } else if reg.eq_ignore_ascii_case("PCR") || reg.eq_ignore_ascii_case("PC") {
+        match parse_offset(offset) {
+            Some(n) if (-128..=127).contains(&n) => 1,
+            _ => 5,
+        }
+    } else {
+        match offset.to_ascii_uppercase().as_str() {
+            "" => 0,
+            "A" | "B" => 1,
+            "D" => 4,
+            // An explicit 0 offset is encoded like ,R
+            off => match parse_offset(off) {
+                Some(0) => 0,
+                Some(n) if (-16..=15).contains(&n) && !indirect => 1,
+                Some(n) if (-128..=127).contains(&n) => 1,
+                // 16-bit or symbolic offsets
+                _ => 4,
+            },
+        }
+    };
+    if indirect { base + 3 } else { base }
+}
+
+fn parse_offset(s: &str) -> Option<i32> {
+    let (neg, digits) = match s.strip_prefix('-') { Some(d) => (true, d), None => (false, s) };
+    let v = if let Some(h) = digits.strip_prefix('$') {
+        i32::from_str_radix(h, 16).ok()?
+    } else if let Some(h) = digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        i32::from_str_radix(h, 16).ok()?
+    } else {
+        digits.parse::<i32>().ok()?
+    };
+    Some(if neg { -v } else { v })
+}
+
+/// Cycles for one instruction written as `mnemonic operand`, or None for
+/// directives and unknown mnemonics. Conditional long branches are counted
+/// taken (worst case); short branches always take 3.
+pub fn instruction_cycles(mnemonic: &str, operand: &str) -> Option<u32> {
+    let m = mnemonic.to_ascii_uppercase();
+    let mode = addressing_mode(operand);
+    // imm / dir / idx / ext base cycles; indexed adds the postbyte extra
+    let by_mode = |imm: u32, dir: u32, idx: u32, ext: u32| -> u32 {
+        match mode {
+            AddrMode::Immediate => imm,
+            AddrMode::Direct => dir,
+            AddrMode::Indexed => idx + indexed_extra_cycles(operand),
+            AddrMode::Extended | AddrMode::Inherent => ext,
+        }
+    };
+    let cycles = match m.as_str() {
+        // Inherent
+        "NOP" | "SEX" | "DAA" => 2,
+        "ABX" => 3,
+        "MUL" => 11,
+        "RTS" => 5,
+        "RTI" => 15,
+        "SWI" => 19,
+        "SWI2" | "SWI3" => 20,
+        "SYNC" => 4,
+        "CWAI" => 20,
+        "ANDCC" | "ORCC" => 3,
+        "TFR" => 6,
+        "EXG" => 8,
+        "PSHS" | "PULS" | "PSHU" | "PULU" => 5 + stack_bytes(operand),
+        "NEGA" | "COMA" | "LSRA" | "RORA" | "ASRA" | "ASLA" | "LSLA" | "ROLA" | "DECA" | "INCA" | "TSTA" | "CLRA" |
+        "NEGB" | "COMB" | "LSRB" | "RORB" | "ASRB" | "ASLB" | "LSLB" | "ROLB" | "DECB" | "INCB" | "TSTB" | "CLRB" => 2,
+        // Read-modify-write memory
+        "NEG" | "COM" | "LSR" | "ROR" | "ASR" | "ASL" | "LSL" | "ROL" | "DEC" | "INC" | "TST" | "CLR" => by_mode(6, 6, 6, 7),
+        // 8-bit accumulator ops
+        "ADCA" | "ADDA" | "ANDA" | "BITA" | "CMPA" | "EORA" | "LDA" | "ORA" | "SBCA" | "SUBA" |
+        "ADCB" | "ADDB" | "ANDB" | "BITB" | "CMPB" | "EORB" | "LDB" | "ORB" | "SBCB" | "SUBB" => by_mode(2, 4, 4, 5),
+        "STA" | "STB" => by_mode(4, 4, 4, 5),
+        // 16-bit
+        "ADDD" | "SUBD" | "CMPX" | "CPX" => by_mode(4, 6, 6, 7),
+        "LDD" | "LDX" | "LDU" => by_mode(3, 5, 5, 6),
+        "STD" | "STX" | "STU" => by_mode(5, 5, 5, 6),
+        "LDY" | "LDS" => by_mode(4, 6, 6, 7),
+        "STY" | "STS" => by_mode(6, 6, 6, 7),
+        "CMPD" | "CPD" | "CMPY" | "CPY" | "CMPU" | "CMPS" => by_mode(5, 7, 7, 8),
+        "LEAX" | "LEAY" | "LEAS" | "LEAU" => 4 + indexed_extra_cycles(operand),
+        // Flow control
+        "JMP" => by_mode(3, 3, 3, 4),
+        "JSR" => by_mode(7, 7, 7, 8),
+        "BSR" => 7,
+        "LBRA" => 5,
+        "LBSR" => 9,
+        "BRA" | "BRN" | "BHI" | "BLS" | "BCC" | "BHS" | "BCS" | "BLO" | "BNE" | "BEQ" |
+        "BVC" | "BVS" | "BPL" | "BMI" | "BGE" | "BLT" | "BGT" | "BLE" => 3,
+        "LBRN" => 5,
+        "LBHI" | "LBLS" | "LBCC" | "LBHS" | "LBCS" | "LBLO" | "LBNE" | "LBEQ" |
+        "LBVC" | "LBVS" | "LBPL" | "LBMI" | "LBGE" | "LBLT" | "LBGT" | "LBLE" => 6,
+        _ => return None,
+    };
+    Some(cycles)
+}
+
+// stack_bytes: bytes moved by a PSHS/PULS register list
+fn stack_bytes(operand: &str) -> u32 {
+    operand
+        .split(',')
+        .map(|r| match r.trim().to_ascii_uppercase().as_str() {
+            "PC" | "X" | "Y" | "U" | "S" | "D" => 2,
+            "" => 0,
+            _ => 1,
+        })
+        .sum()
+}
@@ -2,8 +2,13 @@ pub mod m6809;
 pub mod m6809_binary_emitter;
 pub mod m6809_opcodes;  // Tabla completa de opcodes M6809 para address mapping
 pub mod asm_to_binary;
-// pub mod arm;  // Desactivado temporalmente - no se está usando
-// pub mod cortexm;  // Desactivado temporalmente - no se está usando
+#[path = "../../../buildtools/vpy_assembler/src/m6809/directives.rs"]
+pub mod m6809_directives;  // Macros, IF/ELSE, REPT y STRUCT (preprocesado lwasm, compartido con vpy_assembler)
+#[path = "../../../buildtools/vpy_assembler/src/m6809/instruction_set.rs"]
+pub mod m6809_instruction_set;  // Codificador de instrucciones M6809 (compartido con vpy_assembler)
+use m6809_binary_emitter as binary_emitter;  // Nombre con el que lo importa m6809_instruction_set
+pub mod arm;      // Pitrex (ARM)
+pub mod cortexm;  // VecFever / Vextreme (Thumb-2)
 pub mod string_literals;
 pub mod trig;
 pub mod debug_info;
@@ -14,6 +14,10 @@ pub fn collect_string_literals(module: &Module) -> std::collections::BTreeMap<St
             gather_expr_strings(expr, &mut set);
         } else if let Item::GlobalLet { value, .. } = item {
             gather_expr_strings(value, &mut set);
+        } else if let Item::StructDef(sd) = item {
+            for m in sd.methods.iter().chain(sd.constructor.iter()) {
+                for s in &m.body { gather_stmt_strings(s, &mut set); }
+            }
         }
     }
     let mut map = BTreeMap::new();
@@ -57,7 +61,7 @@ fn gather_expr_strings(expr: &Expr, set: &mut std::collections::BTreeSet<String>
             gather_expr_strings(target, set);
             gather_expr_strings(index, set);
         }
-        Expr::Ident(_) | Expr::Number(_) => {}
+        Expr::Ident(_) | Expr::Number(_) | Expr::Fixed(_) => {}
         Expr::StructInit { .. } => {} // Phase 3 - no string literals
         Expr::FieldAccess { target, .. } => gather_expr_strings(target, set),
     }
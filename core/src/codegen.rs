@@ -4,6 +4,7 @@ use std::collections::{HashSet, HashMap};
 use std::cell::RefCell;
 
 use crate::struct_layout::{StructRegistry, build_struct_registry, StructLayout};
+use crate::types::ScalarType;
 
 // ---------------- Diagnostics (S8) ----------------
 // Canal estructurado para warnings (y pronto errores S9).
@@ -23,6 +24,11 @@ pub enum DiagnosticCode {
     StructRegistryError, // Phase 2: Error building struct registry
     UnusedVariable,      // Variable declared but never used (IDE)
     SuggestConst,        // Variable never changes - suggest const (IDE)
+    TypeMismatch,        // value doesn't fit the annotated type / fixed-int mixed without conversion
+    UnknownType,         // annotation names no known type
+    UnknownVariant,      // Enum.X where X is not a variant (or assignment to a variant)
+    NonExhaustiveSwitch, // switch over enum variants without all of them nor default
+    UnsupportedBuiltin,  // builtin the ARM targets cannot run (and other backend limitations)
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -43,6 +49,7 @@ struct VariableUsage {
     write_count: usize,
     declaration_line: Option<usize>,
     is_const: bool,
+    ty: Option<ScalarType>,  // Tipo anotado (None = word de 2 bytes)
 }
 
 #[derive(Debug, Default)]
@@ -74,7 +81,7 @@ static BUILTIN_ARITIES: &[(&str, usize)] = &[
     ("DRAW_VECTOR", 3),     // Draw vector asset at position: name, x, y
     ("DRAW_VECTOR_EX", 5),  // Draw vector with transformations: name, x, y, mirror, intensity
     ("PLAY_MUSIC", 1),      // Play background music in loop: name
-    ("PLAY_SFX", 1),        // Play sound effect (one-shot): name
+    ("PLAY_SFX", 1),        // Play sound effect (one-shot): name [, priority] (see is_valid_builtin_arity)
     ("AUDIO_UPDATE", 0),    // Update music + SFX (auto-injected after WAIT_RECAL)
     ("MUSIC_UPDATE", 0),    // Process music events per frame (deprecated - use AUDIO_UPDATE)
     ("SFX_UPDATE", 0),      // Process SFX envelope/pitch per frame (deprecated - use AUDIO_UPDATE)
@@ -100,6 +107,20 @@ static BUILTIN_ARITIES: &[(&str, usize)] = &[
     
     // Math functions
     ("ABS", 1),             // Absolute value
+
+    // Fixed-point 8.8 (see fixed_point.rs)
+    ("FIXED", 1),           // int -> fixed
+    ("INT", 1),             // fixed -> int (floor)
+    ("FIXED_MUL", 2),       // fixed * fixed (lowered form)
+    ("FIXED_DIV", 2),       // fixed / fixed (lowered form)
+    ("FIXED_TO_INT", 1),    // lowered form of int()
+
+    // Annotated-type conversions (see types.rs)
+    ("U8", 1),              // low byte, zero-extended
+    ("I8", 1),              // low byte, sign-extended
+    ("U16", 1),             // reinterpret word
+    ("I16", 1),             // reinterpret word
+    ("BOOL", 1),            // x != 0
     
     // Array functions
     ("LEN", 1),             // Get array length
@@ -145,6 +166,12 @@ static BUILTIN_ARITIES: &[(&str, usize)] = &[
     ("MOVE_TO", 2),         // deprecated: use MOVE
 ];
 
+// Builtin (any arity), used by backends to tell builtins from user/SDK calls
+pub(crate) fn is_builtin(name: &str) -> bool {
+    let upper = name.to_ascii_uppercase();
+    matches!(upper.as_str(), "PRINT_TEXT" | "VECTREX_PRINT_TEXT") || expected_builtin_arity(name).is_some()
+}
+
 fn expected_builtin_arity(name: &str) -> Option<usize> {
     let upper = name.to_ascii_uppercase();
     let core = if let Some(stripped) = upper.strip_prefix("VECTREX_") { stripped } else { upper.as_str() };
@@ -164,6 +191,7 @@ fn is_valid_builtin_arity(name: &str, arg_count: usize) -> bool {
         "DRAW_CIRCLE_SEG" => arg_count >= 4,     // nseg, xc, yc, diam, intensity?
         "DRAW_ARC" => arg_count >= 4,            // Arc drawing variadic
         "DRAW_SPIRAL" => arg_count >= 3,         // Spiral drawing variadic
+        "PLAY_SFX" => (1..=2).contains(&arg_count), // name, priority?
         _ => {
             // Fixed-arity functions
             if let Some(exp) = expected_builtin_arity(name) {
@@ -177,8 +205,8 @@ fn is_valid_builtin_arity(name: &str, arg_count: usize) -> bool {
 
 // Re-export backend emitters under stable names.
 mod backends_ref {
-    // pub use crate::backend::arm::emit as emit_arm;  // Desactivado
-    // pub use crate::backend::cortexm::emit as emit_cortexm;  // Desactivado
+    pub use crate::backend::arm::emit as emit_arm;
+    pub use crate::backend::cortexm::emit as emit_cortexm;
     pub use crate::backend::m6809::emit as emit_6809;
     pub use crate::backend::m6809::emit_with_debug as emit_6809_with_debug;
 }
@@ -189,6 +217,7 @@ pub struct AssetInfo {
     pub name: String,      // Asset name without extension (e.g., "player", "theme")
     pub path: String,      // Full path to asset file
     pub asset_type: AssetType,
+    pub priority: u8,      // Prioridad por defecto leída del .vsfx al recoger los assets (0 si no es SFX)
 }
 
 #[allow(dead_code)]
@@ -222,7 +251,7 @@ impl BufferRequirements {
 }
 
 // CodegenOptions: options affecting generation (title, etc.).
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct CodegenOptions {
     pub title: String,
     pub auto_loop: bool, // if false, backend must not emit implicit frame loop
@@ -244,6 +273,7 @@ pub struct CodegenOptions {
     pub mutable_arrays: std::collections::BTreeSet<String>, // Set of mutable (non-const) array names that need RAM allocation
     pub structs: StructRegistry, // Struct layout information (Phase 2)
     pub type_context: HashMap<String, String>, // Maps variable names to struct types (e.g., "p" -> "Point")
+    pub global_types: HashMap<String, ScalarType>, // Annotated global scalars (e.g., "lives" -> u8), see types.rs
     pub buffer_requirements: Option<BufferRequirements>, // Dynamic buffer sizing from .vplay analysis
     // future: fast_wait_counter could toggle increment of a frame counter
 }
@@ -308,13 +338,14 @@ fn analyze_variable_usage(module: &Module) -> UsageAnalysis {
     // Phase 1: Collect declarations from top-level items
     for item in &module.items {
         match item {
-            Item::GlobalLet { name, value, source_line } => {
+            Item::GlobalLet { name, value, source_line, type_annotation, .. } => {
                 let usage = VariableUsage {
                     declared: true,
                     initialized: !matches!(value, Expr::Number(0)),
                     read_count: 0,
                     write_count: 1, // Declaration counts as write
                     declaration_line: Some(*source_line),
+                    ty: crate::types::annotation_type(type_annotation.as_deref()),
                     ..Default::default()
                 };
                 analysis.variables.insert(name.clone(), usage);
@@ -328,6 +359,7 @@ fn analyze_variable_usage(module: &Module) -> UsageAnalysis {
                     write_count: 1,
                     declaration_line: Some(*source_line),
                     is_const: true,
+                    ty: None,
                 };
                 analysis.variables.insert(name.clone(), usage);
                 analyze_expr(value, &mut analysis);
@@ -524,10 +556,11 @@ fn generate_usage_diagnostics(analysis: &UsageAnalysis, diagnostics: &mut Vec<Di
            usage.write_count == 1 &&  // Only initialization, never modified
            usage.read_count > 0 &&    // Actually used
            !usage.is_const {
+            let saved = match usage.ty.map_or(2, ScalarType::size) { 1 => "1 byte", _ => "2 bytes" };
             diagnostics.push(Diagnostic {
                 severity: DiagnosticSeverity::Warning, // Using Warning for hints in IDE
                 code: DiagnosticCode::SuggestConst,
-                message: format!("Variable '{}' never changes - consider 'const' to save RAM (2 bytes)", name),
+                message: format!("Variable '{}' never changes - consider 'const' to save RAM ({})", name, saved),
                 line: usage.declaration_line,
                 col: None,
             });
@@ -588,25 +621,35 @@ pub fn emit_asm_with_debug(module: &Module, target: Target, opts: &CodegenOption
     
     // Paso 1: validación semántica básica (variables / aridad) recolectando warnings.
     let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    // Enums: las variantes pasan a ser números antes de validar
+    let resolved = crate::enums::resolve_module(module, &mut diagnostics);
+    let module = &resolved;
     let type_context = validate_semantics_with_structs(module, &struct_registry, &mut diagnostics);
     
     // NEW: Variable usage analysis for IDE (unused variables, const suggestions)
     let usage_analysis = analyze_variable_usage(module);
     generate_usage_diagnostics(&usage_analysis, &mut diagnostics);
     
+    // Optional type annotations (u8/i16/bool...): mismatches become errors
+    crate::types::check_module(module, &mut diagnostics);
+    
+    // Fixed-point: type check + lowering to integer ops (FIXED_MUL/FIXED_DIV helpers)
+    let lowered = crate::fixed_point::lower_module(module, &mut diagnostics);
+    
     let has_errors = diagnostics.iter().any(|d| matches!(d.severity, DiagnosticSeverity::Error));
     if has_errors {
         return (String::new(), None, diagnostics);
     }
     
     // Paso 2: pipeline de optimización (dead_store_elim preserva asignaciones con literales string).
-    let optimized = optimize_module(module);
+    let optimized = optimize_module(&lowered);
     let ti = info(target);
     
     // If source defines CONST TITLE = "..." let it override CLI title.
     let mut effective = CodegenOptions { 
         structs: struct_registry, // Add struct registry to options
         type_context, // Add type context for method resolution
+        global_types: crate::types::global_types(&optimized), // Byte/word storage for annotated globals
         const_string_arrays: std::collections::BTreeSet::new(), // Initialize empty (will be populated in backend)
         mutable_arrays: std::collections::BTreeSet::new(), // Initialize empty (will be populated in backend)
         output_name: opts.output_name.clone(), // Propagate project name for PDB
@@ -620,8 +663,11 @@ pub fn emit_asm_with_debug(module: &Module, target: Target, opts: &CodegenOption
             let (asm, dbg) = backends_ref::emit_6809_with_debug(&optimized, target, &ti, &effective);
             (asm, Some(dbg))
         },
-        CpuArch::Arm => panic!("ARM backend desactivado temporalmente"),
-        CpuArch::CortexM => panic!("Cortex-M backend desactivado temporalmente"),
+        CpuArch::Arm | CpuArch::CortexM => {
+            let (asm, backend_diags) = emit_arm_family(&optimized, target, &ti, &effective);
+            diagnostics.extend(backend_diags);
+            (asm, None)
+        }
     };
     
     (asm, debug_info, diagnostics)
@@ -631,21 +677,31 @@ pub fn emit_asm_with_debug(module: &Module, target: Target, opts: &CodegenOption
 pub fn emit_asm_with_diagnostics(module: &Module, target: Target, opts: &CodegenOptions) -> (String, Vec<Diagnostic>) {
     // Paso 1: validación semántica básica (variables / aridad) recolectando warnings.
     let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    // Enums: las variantes pasan a ser números antes de validar
+    let resolved = crate::enums::resolve_module(module, &mut diagnostics);
+    let module = &resolved;
     validate_semantics(module, &mut diagnostics);
     
     // NEW: Variable usage analysis for IDE (unused variables, const suggestions)
     let usage_analysis = analyze_variable_usage(module);
     generate_usage_diagnostics(&usage_analysis, &mut diagnostics);
     
+    // Optional type annotations (u8/i16/bool...): mismatches become errors
+    crate::types::check_module(module, &mut diagnostics);
+    
+    // Fixed-point: type check + lowering to integer ops (FIXED_MUL/FIXED_DIV helpers)
+    let lowered = crate::fixed_point::lower_module(module, &mut diagnostics);
+    
     let has_errors = diagnostics.iter().any(|d| matches!(d.severity, DiagnosticSeverity::Error));
     if has_errors {
         return (String::new(), diagnostics);
     }
     // Paso 2: pipeline de optimización (dead_store_elim preserva asignaciones con literales string).
-    let optimized = optimize_module(module);
+    let optimized = optimize_module(&lowered);
     let ti = info(target);
     // If source defines CONST TITLE = "..." let it override CLI title.
     let mut effective = CodegenOptions { 
+        global_types: crate::types::global_types(&optimized), // Byte/word storage for annotated globals
         output_name: opts.output_name.clone(), // Propagate project name for PDB
         ..opts.clone() 
     };
@@ -654,12 +710,25 @@ pub fn emit_asm_with_diagnostics(module: &Module, target: Target, opts: &Codegen
     if optimized.meta.music_override.is_some() { /* backend reads module.meta.music_override */ }
     let asm = match ti.arch {
         CpuArch::M6809 => backends_ref::emit_6809(&optimized, target, &ti, &effective),
-        CpuArch::Arm => panic!("ARM backend desactivado temporalmente"),
-        CpuArch::CortexM => panic!("Cortex-M backend desactivado temporalmente"),
+        CpuArch::Arm | CpuArch::CortexM => {
+            let (asm, backend_diags) = emit_arm_family(&optimized, target, &ti, &effective);
+            diagnostics.extend(backend_diags);
+            asm
+        }
     };
     (asm, diagnostics)
 }
 
+// emit_arm_family: backends ARM/Cortex-M; sin salida si algún builtin no está soportado
+fn emit_arm_family(module: &Module, target: Target, ti: &crate::target::TargetInfo, opts: &CodegenOptions) -> (String, Vec<Diagnostic>) {
+    let (asm, diags) = match ti.arch {
+        CpuArch::CortexM => backends_ref::emit_cortexm(module, target, ti, opts),
+        _ => backends_ref::emit_arm(module, target, ti, opts),
+    };
+    let failed = diags.iter().any(|d| d.severity == DiagnosticSeverity::Error);
+    (if failed { String::new() } else { asm }, diags)
+}
+
 // optimize_module: iterative fixpoint optimization pipeline (max 5 iterations).
 // Pass order per iteration:
 // 1. opt_item / opt_expr: constant folding, algebraic simplifications (16-bit truncation)
@@ -707,6 +776,7 @@ pub fn validate_semantics(module: &Module, diagnostics: &mut Vec<Diagnostic>) {
             Item::ExprStatement(_) => {}, // Expression statements no definen globals
             Item::Export(_) => {}, // Export declarations don't define globals
             Item::StructDef(_) => {}, // Struct definitions don't define globals
+            Item::Enum(_) => {}, // Enum variants are resolved to numbers before validation
         }
     }
     
@@ -1234,6 +1304,8 @@ fn validate_expr_collect(
                         "DRAW_CIRCLE_SEG" => (4, "DRAW_CIRCLE_SEG(n_segments, xc, yc, diameter, intensity?)"),
                         "DRAW_ARC" => (4, "DRAW_ARC(x, y, radius, angle_start, angle_end, ...)"),
                         "DRAW_SPIRAL" => (3, "DRAW_SPIRAL(x, y, scale, ...)"),
+                        // Un solo slot de SFX (canal C): elegir canal es cosa del mezclador de vpy_cli
+                        "PLAY_SFX" => (1, "PLAY_SFX(name, priority?); el canal del efecto lo elige el mezclador de vpy_cli"),
                         _ => {
                             if let Some(exp) = expected_builtin_arity(&ci.name) {
                                 (exp, "")
@@ -1302,7 +1374,7 @@ fn validate_expr_collect(
             validate_expr_collect(target, scope, reads, current_func, function_locals, defined_functions);
             // Field names are not variables, so no additional validation needed here
         }
-        Expr::Number(_) | Expr::StringLit(_) => {}
+        Expr::Number(_) | Expr::Fixed(_) | Expr::StringLit(_) => {}
     }
 }
 
@@ -1310,11 +1382,12 @@ fn opt_item(it: &Item) -> Item {
     match it { 
         Item::Function(f) => Item::Function(opt_function(f)), 
         Item::Const { name, value, source_line } => Item::Const { name: name.clone(), value: opt_expr(value), source_line: *source_line }, 
-        Item::GlobalLet { name, value, source_line } => Item::GlobalLet { name: name.clone(), value: opt_expr(value), source_line: *source_line }, 
+        Item::GlobalLet { name, type_annotation, value, source_line } => Item::GlobalLet { name: name.clone(), type_annotation: type_annotation.clone(), value: opt_expr(value), source_line: *source_line }, 
         Item::VectorList { name, entries } => Item::VectorList { name: name.clone(), entries: entries.clone() },
         Item::ExprStatement(expr) => Item::ExprStatement(opt_expr(expr)),
         Item::Export(e) => Item::Export(e.clone()),
         Item::StructDef(s) => Item::StructDef(s.clone()), // Structs don't need optimization
+        Item::Enum(e) => Item::Enum(e.clone()),
     } 
 }
 
@@ -1323,6 +1396,7 @@ fn opt_function(f: &Function) -> Function {
         name: f.name.clone(),
         line: f.line,
         params: f.params.clone(),
+        param_types: f.param_types.clone(),
         body: f.body.iter().map(opt_stmt).collect(),
     }
 }
@@ -1331,7 +1405,7 @@ fn opt_stmt(s: &Stmt) -> Stmt {
     let source_line = s.source_line(); // Preserve original line number
     match s {
     Stmt::Assign { target, value, .. } => Stmt::Assign { target: target.clone(), value: opt_expr(value), source_line },
-    Stmt::Let { name, value, .. } => Stmt::Let { name: name.clone(), value: opt_expr(value), source_line },
+    Stmt::Let { name, type_annotation, value, .. } => Stmt::Let { name: name.clone(), type_annotation: type_annotation.clone(), value: opt_expr(value), source_line },
         Stmt::CompoundAssign { target, op, value, .. } => {
             // Transformar x += expr en x = x + expr
             let var_expr = match target {
@@ -1537,6 +1611,7 @@ fn opt_expr(e: &Expr) -> Expr {
     }),
     Expr::Ident(i) => Expr::Ident(i.clone()),
     Expr::Number(n) => Expr::Number(trunc16(*n)),
+    Expr::Fixed(n) => Expr::Fixed(*n),
     Expr::StringLit(s) => Expr::StringLit(s.clone()),
     }
 }
@@ -1547,11 +1622,12 @@ fn dead_code_elim(m: &Module) -> Module {
         items: m.items.iter().map(|it| match it { 
             Item::Function(f) => Item::Function(dce_function(f)), 
             Item::Const { name, value, source_line } => Item::Const { name: name.clone(), value: value.clone(), source_line: *source_line }, 
-            Item::GlobalLet { name, value, source_line } => Item::GlobalLet { name: name.clone(), value: value.clone(), source_line: *source_line }, 
+            Item::GlobalLet { name, type_annotation, value, source_line } => Item::GlobalLet { name: name.clone(), type_annotation: type_annotation.clone(), value: value.clone(), source_line: *source_line }, 
             Item::VectorList { name, entries } => Item::VectorList { name: name.clone(), entries: entries.clone() },
             Item::ExprStatement(expr) => Item::ExprStatement(expr.clone()),
             Item::Export(e) => Item::Export(e.clone()),
             Item::StructDef(s) => Item::StructDef(s.clone()),
+            Item::Enum(e) => Item::Enum(e.clone()),
         }).collect(), 
         meta: m.meta.clone(),
         imports: m.imports.clone()
@@ -1565,7 +1641,7 @@ fn dce_function(f: &Function) -> Function {
         if terminated { break; }
         dce_stmt(stmt, &mut new_body, &mut terminated);
     }
-    Function { name: f.name.clone(), line: f.line, params: f.params.clone(), body: new_body }
+    Function { name: f.name.clone(), line: f.line, params: f.params.clone(), param_types: f.param_types.clone(), body: new_body }
 }
 
 fn dce_stmt(stmt: &Stmt, out: &mut Vec<Stmt>, terminated: &mut bool) {
@@ -1644,7 +1720,7 @@ fn dce_stmt(stmt: &Stmt, out: &mut Vec<Stmt>, terminated: &mut bool) {
         }
         Stmt::Return(e, _) => { out.push(Stmt::Return(e.clone(), source_line)); *terminated = true; }
         Stmt::Assign { target, value, .. } => out.push(Stmt::Assign { target: target.clone(), value: value.clone() , source_line: source_line }),
-        Stmt::Let { name, value, .. } => out.push(Stmt::Let { name: name.clone(), value: value.clone() , source_line: source_line }),
+        Stmt::Let { name, type_annotation, value, .. } => out.push(Stmt::Let { name: name.clone(), type_annotation: type_annotation.clone(), value: value.clone() , source_line: source_line }),
         Stmt::CompoundAssign { .. } => panic!("CompoundAssign should have been transformed to Assign by opt_stmt"),
         Stmt::Expr(e, _) => out.push(Stmt::Expr(e.clone(), source_line)),
         Stmt::Break { .. } | Stmt::Continue { .. } | Stmt::Pass { .. } => out.push(stmt.clone()),
@@ -1733,7 +1809,7 @@ fn dse_function(f: &Function) -> Function {
         }
     }
     new_body.reverse();
-    Function { name: f.name.clone(), line: f.line, params: f.params.clone(), body: new_body }
+    Function { name: f.name.clone(), line: f.line, params: f.params.clone(), param_types: f.param_types.clone(), body: new_body }
 }
 
 fn expr_has_call(e: &Expr) -> bool {
@@ -1828,7 +1904,7 @@ fn collect_reads_expr(e: &Expr, used: &mut std::collections::HashSet<String>) {
             // Field access reads the target object
             collect_reads_expr(target, used);
         }
-        Expr::Number(_) => {}
+        Expr::Number(_) | Expr::Fixed(_) => {}
     Expr::StringLit(_) => {}
     }
 }
@@ -1848,11 +1924,12 @@ fn propagate_constants(m: &Module) -> Module {
         items: m.items.iter().map(|it| match it { 
             Item::Function(f) => Item::Function(cp_function_with_globals(f, &globals)), 
             Item::Const { name, value, source_line } => Item::Const { name: name.clone(), value: value.clone(), source_line: *source_line }, 
-            Item::GlobalLet { name, value, source_line } => Item::GlobalLet { name: name.clone(), value: value.clone(), source_line: *source_line }, 
+            Item::GlobalLet { name, type_annotation, value, source_line } => Item::GlobalLet { name: name.clone(), type_annotation: type_annotation.clone(), value: value.clone(), source_line: *source_line }, 
             Item::VectorList { name, entries } => Item::VectorList { name: name.clone(), entries: entries.clone() },
             Item::ExprStatement(expr) => Item::ExprStatement(expr.clone()),
             Item::Export(e) => Item::Export(e.clone()),
             Item::StructDef(s) => Item::StructDef(s.clone()),
+            Item::Enum(e) => Item::Enum(e.clone()),
         }).collect(), 
         meta: m.meta.clone(),
         imports: m.imports.clone()
@@ -1866,7 +1943,7 @@ fn cp_function_with_globals(f: &Function, globals: &std::collections::HashMap<St
     for (k,v) in globals { env.insert(k.clone(), *v); }
     let mut new_body = Vec::new();
     for stmt in &f.body { new_body.push(cp_stmt(stmt, &mut env)); }
-    Function { name: f.name.clone(), line: f.line, params: f.params.clone(), body: new_body }
+    Function { name: f.name.clone(), line: f.line, params: f.params.clone(), param_types: f.param_types.clone(), body: new_body }
 }
 
 #[allow(dead_code)]
@@ -1895,14 +1972,14 @@ fn cp_stmt(stmt: &Stmt, env: &mut HashMap<String, i32>) -> Stmt {
                 }
             }
         }
-        Stmt::Let { name, value, .. } => {
+        Stmt::Let { name, type_annotation, value, .. } => {
             let v2 = cp_expr(value, env);
             if let Expr::Number(n) = v2 {
                 env.insert(name.clone(), n);
-                Stmt::Let { name: name.clone(), value: Expr::Number(n), source_line }
+                Stmt::Let { name: name.clone(), type_annotation: type_annotation.clone(), value: Expr::Number(n), source_line }
             } else {
                 env.remove(name);
-                Stmt::Let { name: name.clone(), value: v2, source_line }
+                Stmt::Let { name: name.clone(), type_annotation: type_annotation.clone(), value: v2, source_line }
             }
         }
         Stmt::Expr(e, _) => Stmt::Expr(cp_expr(e, env), source_line),
@@ -2023,6 +2100,7 @@ fn cp_expr(e: &Expr, env: &HashMap<String, i32>) -> Expr {
         col: mc.col,
     }),
         Expr::Number(n) => Expr::Number(*n),
+        Expr::Fixed(n) => Expr::Fixed(*n),
     Expr::StringLit(s) => Expr::StringLit(s.clone()),
     Expr::StructInit { .. } => e.clone(), // Phase 3 - no constant propagation
     Expr::FieldAccess { target, field, source_line, col } => Expr::FieldAccess { 
@@ -2041,11 +2119,12 @@ fn fold_const_switches(m: &Module) -> Module {
         items: m.items.iter().map(|it| match it { 
             Item::Function(f) => Item::Function(fold_const_switches_function(f)), 
             Item::Const { name, value, source_line } => Item::Const { name: name.clone(), value: value.clone(), source_line: *source_line }, 
-            Item::GlobalLet { name, value, source_line } => Item::GlobalLet { name: name.clone(), value: value.clone(), source_line: *source_line }, 
+            Item::GlobalLet { name, type_annotation, value, source_line } => Item::GlobalLet { name: name.clone(), type_annotation: type_annotation.clone(), value: value.clone(), source_line: *source_line }, 
             Item::VectorList { name, entries } => Item::VectorList { name: name.clone(), entries: entries.clone() },
             Item::ExprStatement(expr) => Item::ExprStatement(expr.clone()),
             Item::Export(e) => Item::Export(e.clone()),
             Item::StructDef(s) => Item::StructDef(s.clone()),
+            Item::Enum(e) => Item::Enum(e.clone()),
         }).collect(), 
         meta: m.meta.clone(),
         imports: m.imports.clone()
@@ -2055,7 +2134,7 @@ fn fold_const_switches(m: &Module) -> Module {
 fn fold_const_switches_function(f: &Function) -> Function {
     let mut out = Vec::new();
     for s in &f.body { fold_const_switch_stmt(s, &mut out); }
-    Function { name: f.name.clone(), line: f.line, params: f.params.clone(), body: out }
+    Function { name: f.name.clone(), line: f.line, params: f.params.clone(), param_types: f.param_types.clone(), body: out }
 }
 
 fn fold_const_switch_stmt(s: &Stmt, out: &mut Vec<Stmt>) {
@@ -2099,7 +2178,7 @@ fn fold_const_switch_stmt(s: &Stmt, out: &mut Vec<Stmt>) {
         Stmt::For { var, start, end, step, body, .. } => { let mut nb = Vec::new(); for cs in body { fold_const_switch_stmt(cs, &mut nb); } out.push(Stmt::For { var: var.clone(), start: start.clone(), end: end.clone(), step: step.clone(), body: nb , source_line: source_line }); }
         Stmt::ForIn { var, iterable, body, .. } => { let mut nb = Vec::new(); for cs in body { fold_const_switch_stmt(cs, &mut nb); } out.push(Stmt::ForIn { var: var.clone(), iterable: iterable.clone(), body: nb , source_line: source_line }); }
     Stmt::Assign { target, value, .. } => out.push(Stmt::Assign { target: target.clone(), value: value.clone() , source_line: source_line }),
-        Stmt::Let { name, value, .. } => out.push(Stmt::Let { name: name.clone(), value: value.clone() , source_line: source_line }),
+        Stmt::Let { name, type_annotation, value, .. } => out.push(Stmt::Let { name: name.clone(), type_annotation: type_annotation.clone(), value: value.clone() , source_line: source_line }),
         Stmt::Expr(e, _) => out.push(Stmt::Expr(e.clone(), source_line)),
         Stmt::Return(o, _) => out.push(Stmt::Return(o.clone(), source_line)),
         Stmt::Break { .. } => out.push(Stmt::Break { source_line }),
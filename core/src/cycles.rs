@@ -0,0 +1,554 @@
+//! Static CPU cycle estimates for the 6809 targets (`vectrexc build --cycles`)
+//!
+//! The emitted assembly is split into per-function regions and per-VPy-line
+//! chunks using the `; VPy_LINE:N` markers; every instruction is priced with
+//! the MC6809 tables in `backend/m6809_opcodes.rs`. The AST then supplies the
+//! control flow:
+//!
+//! - `for i in range(a, b[, s])` runs its body `(b - a) / s` times when the
+//!   bounds are literals or number constants; `for x in ARRAY` uses the array
+//!   length.
+//! - `while` loops and ranges with runtime bounds are counted once and listed
+//!   as unbounded so the number is clearly a lower bound for that function.
+//! - `if`/`elif`/`else` and `switch` take the most expensive branch.
+//! - `JSR` to a user function adds that function's worst case; `JSR` to a
+//!   helper routine in the same file adds its straight-line cost; BIOS calls
+//!   are reported as external and cost only the `JSR` itself.
+//!
+//! The result serialises to JSON (`<out>.cycles.json`) for the IDE/LSP code lenses.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::ast::{Expr, Function, Item, Module, Stmt};
+use crate::backend::m6809_opcodes::instruction_cycles;
+
+/// Cycles available per 50 Hz frame at 1.5 MHz
+pub const FRAME_CYCLES: u64 = 30_000;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CycleReport {
+    /// Entry source file name, so tools can match a report to its document
+    #[serde(default)]
+    pub source: String,
+    pub frame_budget: u64,
+    pub functions: Vec<FunctionCycles>,
+    pub lines: Vec<LineCycles>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FunctionCycles {
+    pub name: String,
+    /// Line of the `def`
+    pub line: usize,
+    /// Worst-case cycles for one call, callees included
+    pub worst_case: u64,
+    /// Lines of `while` loops / ranges with runtime bounds (counted once)
+    pub unbounded_loops: Vec<usize>,
+    /// BIOS or unresolved routines called (only the JSR is counted)
+    pub external_calls: Vec<String>,
+    /// Calls back into itself (directly or indirectly); the cycle is cut
+    pub recursive: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LineCycles {
+    pub line: usize,
+    pub function: String,
+    /// One execution of the line's code, callees included
+    pub cycles: u64,
+    /// Worst-case executions per call of the enclosing function
+    pub executions: u64,
+    pub total: u64,
+}
+
+impl FunctionCycles {
+    /// Short summary used by the CLI report and LSP code lenses
+    pub fn summary(&self, budget: u64) -> String {
+        let mut s = format!("{} cycles worst case ({:.1}% of frame)", thousands(self.worst_case), percent(self.worst_case, budget));
+        if !self.unbounded_loops.is_empty() { s.push_str(", unbounded loops counted once"); }
+        if self.recursive { s.push_str(", recursive"); }
+        s
+    }
+}
+
+// ------------------------------------------------------------------ asm scan
+
+#[derive(Debug, Default, Clone)]
+struct LineAsm {
+    cycles: u64,
+    calls: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+struct AsmIndex {
+    /// VPy function name -> (VPy line -> code attributed to it)
+    regions: HashMap<String, BTreeMap<usize, LineAsm>>,
+    /// asm label -> VPy function name
+    functions: HashMap<String, String>,
+    /// asm label -> straight-line code up to the first RTS/JMP/BRA
+    routines: HashMap<String, LineAsm>,
+}
+
+// split_instruction: "    LDD 4 ,S ; comment" -> ("LDD", "4,S")
+fn split_instruction(code: &str) -> Option<(String, String)> {
+    let code = code.split(';').next().unwrap_or("").trim();
+    if code.is_empty() { return None; }
+    let mut parts = code.splitn(2, char::is_whitespace);
+    let mnemonic = parts.next()?.to_string();
+    let operand: String = parts.next().unwrap_or("").chars().filter(|c| !c.is_whitespace()).collect();
+    Some((mnemonic, operand))
+}
+
+fn call_target(mnemonic: &str, operand: &str) -> Option<String> {
+    match mnemonic.to_ascii_uppercase().as_str() {
+        "JSR" | "BSR" | "LBSR" => Some(operand.trim_start_matches(['<', '>']).to_string()),
+        _ => None,
+    }
+}
+
+fn ends_routine(mnemonic: &str) -> bool {
+    matches!(mnemonic.to_ascii_uppercase().as_str(), "RTS" | "RTI" | "JMP" | "BRA" | "LBRA")
+}
+
+fn scan_asm(asm: &str) -> AsmIndex {
+    let mut index = AsmIndex::default();
+    let mut region: Option<String> = None;
+    let mut pending_label: Option<String> = None;
+    let mut line = 0usize;
+    // Routines currently collecting straight-line code
+    let mut open_routines: Vec<String> = Vec::new();
+
+    for raw in asm.lines() {
+        let trimmed = raw.trim();
+        if let Some(n) = trimmed.strip_prefix("; VPy_LINE:") {
+            line = n.trim().parse().unwrap_or(line);
+            continue;
+        }
+        if trimmed.contains("main() function code inline") {
+            region = Some("main".to_string());
+            continue;
+        }
+        if trimmed.starts_with(";***") {
+            region = None;
+            open_routines.clear();
+            continue;
+        }
+        if let Some(rest) = trimmed.strip_prefix("; --- function ") {
+            let name = rest.trim_end_matches('-').trim().to_string();
+            if let Some(label) = pending_label.take() { index.functions.insert(label, name.clone()); }
+            region = Some(name);
+            continue;
+        }
+
+        // Label at column 0, optionally followed by an instruction; some
+        // hand-written wrappers also put bare instructions at column 0
+        let mut code = raw;
+        let bare_instruction = !raw.split(';').next().unwrap_or("").contains(':')
+            && split_instruction(raw).is_some_and(|(m, o)| instruction_cycles(&m, &o).is_some());
+        if !raw.starts_with(char::is_whitespace) && !trimmed.starts_with(';') && !trimmed.is_empty() && !bare_instruction {
+            let (label, rest) = match raw.split_once(':') {
+                Some((l, r)) if !l.contains(char::is_whitespace) => (l.trim(), r),
+                _ => match raw.split_once(char::is_whitespace) {
+                    Some((l, r)) => (l.trim(), r),
+                    None => (raw.trim(), ""),
+                },
+            };
+            // EQU/FCB/... lines define data, not code labels
+            if let Some((m, o)) = split_instruction(rest) {
+                if instruction_cycles(&m, &o).is_none() { continue; }
+            }
+            match label {
+                "MAIN" | "START" => region = None,
+                "LOOP_BODY" => {
+                    index.functions.insert(label.to_string(), "loop".to_string());
+                    region = Some("loop".to_string());
+                }
+                _ => {}
+            }
+            if rest.contains("; function") { pending_label = Some(label.to_string()); }
+            index.routines.entry(label.to_string()).or_default();
+            open_routines.push(label.to_string());
+            code = rest;
+        }
+
+        let Some((mnemonic, operand)) = split_instruction(code) else { continue };
+        let Some(cycles) = instruction_cycles(&mnemonic, &operand) else { continue };
+        let call = call_target(&mnemonic, &operand);
+
+        if let Some(r) = &region {
+            let entry = index.regions.entry(r.clone()).or_default().entry(line).or_default();
+            entry.cycles += cycles as u64;
+            if let Some(c) = &call { entry.calls.push(c.clone()); }
+        }
+        for label in &open_routines {
+            let entry = index.routines.get_mut(label).expect("routine registered");
+            entry.cycles += cycles as u64;
+            if let Some(c) = &call { entry.calls.push(c.clone()); }
+        }
+        if ends_routine(&mnemonic) { open_routines.clear(); }
+    }
+    index
+}
+
+// ------------------------------------------------------------------ estimation
+
+struct Estimator<'a> {
+    index: AsmIndex,
+    functions: HashMap<String, &'a Function>,
+    consts: HashMap<String, &'a Expr>,
+    costs: HashMap<String, u64>,
+    routine_costs: HashMap<String, u64>,
+    in_progress: HashSet<String>,
+    results: BTreeMap<String, FunctionCycles>,
+    lines: Vec<LineCycles>,
+}
+
+// Per-function state while walking the body
+#[derive(Default)]
+struct Walk {
+    unbounded: Vec<usize>,
+    external: Vec<String>,
+    recursive: bool,
+    executions: BTreeMap<usize, u64>,
+    line_costs: BTreeMap<usize, u64>,
+}
+
+impl<'a> Estimator<'a> {
+    fn new(module: &'a Module, asm: &str) -> Self {
+        let mut functions = HashMap::new();
+        let mut consts = HashMap::new();
+        for item in &module.items {
+            match item {
+                Item::Function(f) => { functions.insert(f.name.clone(), f); }
+                Item::StructDef(sd) => {
+                    for m in sd.methods.iter().chain(sd.constructor.iter()) {
+                        functions.insert(format!("{}_{}", sd.name, m.name), m);
+                    }
+                }
+                Item::Const { name, value, .. } | Item::GlobalLet { name, value, .. } => { consts.insert(name.clone(), value); }
+                _ => {}
+            }
+        }
+        Estimator {
+            index: scan_asm(asm),
+            functions,
+            consts,
+            costs: HashMap::new(),
+            routine_costs: HashMap::new(),
+            in_progress: HashSet::new(),
+            results: BTreeMap::new(),
+            lines: Vec::new(),
+        }
+    }
+
+    fn number(&self, e: &Expr) -> Option<i64> {
+        match e {
+            Expr::Number(n) => Some(*n as i64),
+            Expr::Ident(id) => match self.consts.get(&id.name) {
+                Some(Expr::Number(n)) => Some(*n as i64),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn iterations(&self, s: &Stmt) -> Option<u64> {
+        match s {
+            Stmt::For { start, end, step, .. } => {
+                let (a, b) = (self.number(start)?, self.number(end)?);
+                let st = match step { Some(e) => self.number(e)?, None => 1 };
+                if st == 0 { return None; }
+                let n = (b - a + st - st.signum()) / st;
+                Some(n.max(0) as u64)
+            }
+            Stmt::ForIn { iterable, .. } => match iterable {
+                Expr::List(items) => Some(items.len() as u64),
+                Expr::Ident(id) => match self.consts.get(&id.name) {
+                    Some(Expr::List(items)) => Some(items.len() as u64),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn call_cost(&mut self, target: &str, walk: &mut Walk) -> u64 {
+        if let Some(name) = self.index.functions.get(target).cloned() {
+            if self.in_progress.contains(&name) {
+                walk.recursive = true;
+                return 0;
+            }
+            return self.function_cost(&name);
+        }
+        if self.index.routines.contains_key(target) {
+            return self.routine_cost(target, walk);
+        }
+        if !walk.external.iter().any(|e| e == target) { walk.external.push(target.to_string()); }
+        0
+    }
+
+    fn routine_cost(&mut self, label: &str, walk: &mut Walk) -> u64 {
+        if let Some(c) = self.routine_costs.get(label) { return *c; }
+        // Guard against helper cycles before recursing into its calls
+        self.routine_costs.insert(label.to_string(), 0);
+        let body = self.index.routines[label].clone();
+        let mut total = body.cycles;
+        for c in &body.calls { total += self.call_cost(c, walk); }
+        self.routine_costs.insert(label.to_string(), total);
+        total
+    }
+
+    fn line_cost(&mut self, name: &str, line: usize, walk: &mut Walk) -> u64 {
+        let Some(asm) = self.index.regions.get(name).and_then(|r| r.get(&line)).cloned() else { return 0 };
+        let mut total = asm.cycles;
+        for c in &asm.calls { total += self.call_cost(c, walk); }
+        walk.line_costs.insert(line, total);
+        total
+    }
+
+    fn function_cost(&mut self, name: &str) -> u64 {
+        if let Some(c) = self.costs.get(name) { return *c; }
+        let Some(f) = self.functions.get(name).copied() else { return 0 };
+        if !self.index.regions.contains_key(name) { return 0; }
+        self.in_progress.insert(name.to_string());
+
+        let mut walk = Walk::default();
+        let mut stmt_lines = HashSet::new();
+        collect_lines(&f.body, &mut stmt_lines);
+        // Prologue/epilogue and anything not attributed to a statement runs once
+        let other: Vec<usize> = self.index.regions[name].keys().copied().filter(|l| !stmt_lines.contains(l)).collect();
+        let mut total = 0;
+        for l in other {
+            total += self.line_cost(name, l, &mut walk);
+            walk.executions.insert(l, 1);
+        }
+        total += self.block_cost(name, &f.body, 1, &mut walk);
+
+        self.in_progress.remove(name);
+        self.costs.insert(name.to_string(), total);
+        for (line, executions) in &walk.executions {
+            let cycles = walk.line_costs.get(line).copied().unwrap_or(0);
+            self.lines.push(LineCycles { line: *line, function: name.to_string(), cycles, executions: *executions, total: cycles * executions });
+        }
+        self.results.insert(name.to_string(), FunctionCycles {
+            name: name.to_string(),
+            line: f.line,
+            worst_case: total,
+            unbounded_loops: walk.unbounded,
+            external_calls: walk.external,
+            recursive: walk.recursive,
+        });
+        total
+    }
+
+    fn block_cost(&mut self, name: &str, body: &[Stmt], runs: u64, walk: &mut Walk) -> u64 {
+        body.iter().map(|s| self.stmt_cost(name, s, runs, walk)).sum()
+    }
+
+    fn stmt_cost(&mut self, name: &str, s: &Stmt, runs: u64, walk: &mut Walk) -> u64 {
+        let line = s.source_line();
+        let own = self.line_cost(name, line, walk);
+        let mark = |walk: &mut Walk, n: u64| { *walk.executions.entry(line).or_insert(0) += n; };
+        match s {
+            Stmt::For { body, .. } | Stmt::ForIn { body, .. } => {
+                let n = self.iterations(s).unwrap_or_else(|| { walk.unbounded.push(line); 1 });
+                // Header code (init + test + step) runs once more than the body
+                mark(walk, runs * (n + 1));
+                own * (n + 1) + n * self.block_cost(name, body, runs * n, walk)
+            }
+            Stmt::While { body, .. } => {
+                walk.unbounded.push(line);
+                mark(walk, runs * 2);
+                own * 2 + self.block_cost(name, body, runs, walk)
+            }
+            Stmt::If { body, elifs, else_body, .. } => {
+                mark(walk, runs);
+                let mut worst = self.block_cost(name, body, runs, walk);
+                for (_, b) in elifs { worst = worst.max(self.block_cost(name, b, runs, walk)); }
+                if let Some(b) = else_body { worst = worst.max(self.block_cost(name, b, runs, walk)); }
+                own + worst
+            }
+            Stmt::Switch { cases, default, .. } => {
+                mark(walk, runs);
+                let mut worst = 0;
+                for (_, b) in cases { worst = worst.max(self.block_cost(name, b, runs, walk)); }
+                if let Some(b) = default { worst = worst.max(self.block_cost(name, b, runs, walk)); }
+                own + worst
+            }
+            _ => {
+                mark(walk, runs);
+                own
+            }
+        }
+    }
+}
+
+fn collect_lines(body: &[Stmt], out: &mut HashSet<usize>) {
+    for s in body {
+        out.insert(s.source_line());
+        match s {
+            Stmt::For { body, .. } | Stmt::ForIn { body, .. } | Stmt::While { body, .. } => collect_lines(body, out),
+            Stmt::If { body, elifs, else_body, .. } => {
+                collect_lines(body, out);
+                for (_, b) in elifs { collect_lines(b, out); }
+                if let Some(b) = else_body { collect_lines(b, out); }
+            }
+            Stmt::Switch { cases, default, .. } => {
+                for (_, b) in cases { collect_lines(b, out); }
+                if let Some(b) = default { collect_lines(b, out); }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Estimate worst-case cycles for every function of `module` compiled to `asm`
+pub fn estimate(module: &Module, asm: &str) -> CycleReport {
+    let mut est = Estimator::new(module, asm);
+    let mut names: Vec<String> = est.index.regions.keys().cloned().collect();
+    names.sort();
+    for name in &names { est.function_cost(name); }
+
+    let mut functions: Vec<FunctionCycles> = est.results.into_values().collect();
+    functions.sort_by_key(|f| (f.line, f.name.clone()));
+    let mut lines = est.lines;
+    lines.sort_by_key(|l| (l.line, l.function.clone()));
+    CycleReport { source: String::new(), frame_budget: FRAME_CYCLES, functions, lines }
+}
+
+// ------------------------------------------------------------------ report text
+
+fn thousands(n: u64) -> String {
+    let digits = n.to_string();
+    let mut out = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) { out.push(','); }
+        out.push(c);
+    }
+    out
+}
+
+fn percent(n: u64, budget: u64) -> f64 {
+    if budget == 0 { 0.0 } else { n as f64 * 100.0 / budget as f64 }
+}
+
+/// Human-readable per-function and per-line report for the CLI
+pub fn format_report(report: &CycleReport) -> String {
+    let mut out = format!("Cycle estimate (worst case, frame budget {} cycles)\n", thousands(report.frame_budget));
+    out.push_str(&format!("  {:<24} {:>6}  estimate\n", "function", "line"));
+    for f in &report.functions {
+        out.push_str(&format!("  {:<24} {:>6}  {}\n", f.name, f.line, f.summary(report.frame_budget)));
+    }
+    for f in &report.functions {
+        for l in &f.unbounded_loops {
+            out.push_str(&format!("  note: {}: loop at line {} has no static bound, counted once\n", f.name, l));
+        }
+        if f.recursive {
+            out.push_str(&format!("  note: {}: recursive call not included\n", f.name));
+        }
+        if !f.external_calls.is_empty() {
+            out.push_str(&format!("  note: {}: external calls not included: {}\n", f.name, f.external_calls.join(", ")));
+        }
+    }
+    out.push_str(&format!("\n  {:>6} {:<24} {:>8} {:>6} {:>10}\n", "line", "function", "cycles", "runs", "total"));
+    for l in report.lines.iter().filter(|l| l.total > 0) {
+        out.push_str(&format!("  {:>6} {:<24} {:>8} {:>6} {:>10}\n", l.line, l.function, thousands(l.cycles), l.executions, thousands(l.total)));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::{emit_asm_with_debug, CodegenOptions};
+    use crate::lexer::lex;
+    use crate::parser::parse_with_filename;
+    use crate::target::Target;
+
+    fn report(src: &str) -> CycleReport {
+        let module = parse_with_filename(&lex(src).unwrap(), "cyc.vpy").unwrap();
+        let opts = CodegenOptions {
+            title: "CYC".into(),
+            auto_loop: true,
+            diag_freeze: false,
+            force_extended_jsr: false,
+            _bank_size: 0,
+            per_frame_silence: false,
+            debug_init_draw: false,
+            blink_intensity: false,
+            exclude_ram_org: false,
+            fast_wait: false,
+            source_path: None,
+            output_name: None,
+            assets: vec![],
+            const_values: Default::default(),
+            const_arrays: Default::default(),
+            const_string_arrays: Default::default(),
+            mutable_arrays: Default::default(),
+            structs: Default::default(),
+            type_context: Default::default(),
+            global_types: Default::default(),
+            buffer_requirements: None,
+        };
+        let (asm, _, diags) = emit_asm_with_debug(&module, Target::Vectrex, &opts);
+        assert!(!asm.is_empty(), "{:?}", diags);
+        estimate(&module, &asm)
+    }
+
+    fn func<'r>(r: &'r CycleReport, name: &str) -> &'r FunctionCycles {
+        r.functions.iter().find(|f| f.name == name).unwrap_or_else(|| panic!("no {} in {:?}", name, r.functions))
+    }
+
+    #[test]
+    fn scan_attributes_instructions_to_lines() {
+        let asm = "    ; VPy_LINE:3\nF: ; function\n; --- function f ---\n    LDD #1\n    ; VPy_LINE:4\n    STD VAR_X\nDONE: RTS\n";
+        let index = scan_asm(asm);
+        assert_eq!(index.functions["F"], "f");
+        let region = &index.regions["f"];
+        assert_eq!(region[&3].cycles, 3);
+        assert_eq!(region[&4].cycles, 6 + 5);
+    }
+
+    #[test]
+    fn range_loops_multiply_their_body() {
+        let src = "def main():\n    pass\n\ndef loop():\n    step()\n\ndef step():\n    for i in range(0, 10):\n        x = i\n";
+        let r = report(src);
+        let body = r.lines.iter().find(|l| l.line == 9 && l.function == "step").unwrap();
+        assert_eq!(body.executions, 10);
+        assert_eq!(body.total, body.cycles * 10);
+        assert!(func(&r, "step").unbounded_loops.is_empty());
+        // loop() pays for step()
+        assert!(func(&r, "loop").worst_case > func(&r, "step").worst_case);
+    }
+
+    #[test]
+    fn const_bounds_and_unbounded_loops() {
+        let src = "const N = 4\nx = 0\n\ndef main():\n    pass\n\ndef loop():\n    for i in range(0, N):\n        x = i\n    while x > 0:\n        x = x - 1\n";
+        let r = report(src);
+        let f = func(&r, "loop");
+        assert_eq!(f.unbounded_loops, vec![10]);
+        assert_eq!(r.lines.iter().find(|l| l.line == 9).unwrap().executions, 4);
+        assert!(f.external_calls.iter().any(|c| c == "Wait_Recal"));
+    }
+
+    #[test]
+    fn branches_take_the_worst_case() {
+        let src = "def main():\n    pass\n\ndef loop():\n    if J1_X() > 0:\n        a = 1\n    else:\n        for i in range(0, 8):\n            a = a + i\n";
+        let r = report(src);
+        let lines = |n: usize| r.lines.iter().find(|l| l.line == n).unwrap().clone();
+        let f = func(&r, "loop");
+        assert!(f.worst_case >= lines(8).total + lines(9).total);
+        assert!(f.worst_case < lines(6).total + lines(8).total + lines(9).total + 2000);
+    }
+
+    #[test]
+    fn json_round_trips() {
+        let r = report("def main():\n    pass\n\ndef loop():\n    x = 1\n");
+        let json = serde_json::to_string(&r).unwrap();
+        assert_eq!(serde_json::from_str::<CycleReport>(&json).unwrap(), r);
+        assert!(format_report(&r).contains("loop"));
+    }
+}
@@ -0,0 +1,320 @@
+//! Enums
+//!
+//! `enum GameState: TITLE, MAP, GAME` numera sus variantes desde 0. Antes de
+//! la validación semántica `resolve_module` sustituye cada `GameState.MAP`
+//! (o `modulo.GameState.MAP`) por su número, así el resto del pipeline solo
+//! ve constantes. Un `switch` sobre una variable que guarda un enum, o cuyos
+//! `case` nombran variantes de uno, debe cubrirlas todas o tener `default`;
+//! si no, se emite un warning.
+
+use crate::ast::*;
+use crate::codegen::{Diagnostic, DiagnosticCode, DiagnosticSeverity};
+use std::collections::{HashMap, HashSet};
+
+pub fn resolve_module(module: &Module, diagnostics: &mut Vec<Diagnostic>) -> Module {
+    let enums: HashMap<String, EnumDef> = module.items.iter().filter_map(|it| match it {
+        Item::Enum(def) => Some((def.name.to_uppercase(), def.clone())),
+        _ => None,
+    }).collect();
+    if enums.is_empty() {
+        return module.clone();
+    }
+    let mut rs = Resolver { enums, typed: HashMap::new(), diagnostics: Vec::new() };
+    rs.typed = rs.variable_enums(module);
+    let items = module.items.iter().map(|it| rs.item(it)).collect();
+    diagnostics.extend(rs.diagnostics);
+    Module { items, meta: module.meta.clone(), imports: module.imports.clone() }
+}
+
+struct Resolver {
+    enums: HashMap<String, EnumDef>,
+    /// Enum (en mayúsculas) de cada variable asignada desde una variante; `None` si de varios
+    typed: HashMap<String, Option<String>>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Resolver {
+    fn push(&mut self, severity: DiagnosticSeverity, code: DiagnosticCode, message: String, line: usize) {
+        self.diagnostics.push(Diagnostic { severity, code, message, line: Some(line), col: None });
+    }
+
+    /// Enum nombrado por `expr`: `GameState` o `modulo.GameState`
+    fn lookup(&self, expr: &Expr) -> Option<&EnumDef> {
+        match expr {
+            Expr::Ident(id) => self.enums.get(&id.name.to_uppercase()),
+            Expr::FieldAccess { target, field, .. } if matches!(**target, Expr::Ident(_)) => {
+                self.enums.get(&field.to_uppercase())
+            }
+            _ => None,
+        }
+    }
+
+    /// Enum y valor de una referencia a variante (`GameState.MAP`)
+    fn variant(&self, expr: &Expr) -> Option<(&EnumDef, Option<i32>)> {
+        let Expr::FieldAccess { target, field, .. } = expr else { return None };
+        let def = self.lookup(target)?;
+        Some((def, def.value_of(field)))
+    }
+
+    fn item(&mut self, it: &Item) -> Item {
+        match it {
+            Item::Function(f) => Item::Function(self.function(f)),
+            Item::Const { name, value, source_line } => {
+                Item::Const { name: name.clone(), value: self.expr(value), source_line: *source_line }
+            }
+            Item::GlobalLet { name, type_annotation, value, source_line } => Item::GlobalLet {
+                name: name.clone(),
+                type_annotation: type_annotation.clone(),
+                value: self.expr(value),
+                source_line: *source_line,
+            },
+            Item::ExprStatement(e) => Item::ExprStatement(self.expr(e)),
+            Item::StructDef(sd) => {
+                let mut sd = sd.clone();
+                sd.methods = sd.methods.iter().map(|m| self.function(m)).collect();
+                sd.constructor = sd.constructor.as_ref().map(|c| self.function(c));
+                Item::StructDef(sd)
+            }
+            Item::VectorList { .. } | Item::Export(_) | Item::Enum(_) => it.clone(),
+        }
+    }
+
+    fn function(&mut self, f: &Function) -> Function {
+        Function { body: self.block(&f.body), ..f.clone() }
+    }
+
+    fn block(&mut self, stmts: &[Stmt]) -> Vec<Stmt> {
+        stmts.iter().map(|s| self.stmt(s)).collect()
+    }
+
+    fn stmt(&mut self, s: &Stmt) -> Stmt {
+        match s {
+            Stmt::Assign { target, value, source_line } => {
+                Stmt::Assign { target: self.target(target, *source_line), value: self.expr(value), source_line: *source_line }
+            }
+            Stmt::CompoundAssign { target, op, value, source_line } => Stmt::CompoundAssign {
+                target: self.target(target, *source_line),
+                op: *op,
+                value: self.expr(value),
+                source_line: *source_line,
+            },
+            Stmt::Let { name, type_annotation, value, source_line } => Stmt::Let {
+                name: name.clone(),
+                type_annotation: type_annotation.clone(),
+                value: self.expr(value),
+                source_line: *source_line,
+            },
+            Stmt::For { var, start, end, step, body, source_line } => Stmt::For {
+                var: var.clone(),
+                start: self.expr(start),
+                end: self.expr(end),
+                step: step.as_ref().map(|e| self.expr(e)),
+                body: self.block(body),
+                source_line: *source_line,
+            },
+            Stmt::ForIn { var, iterable, body, source_line } => Stmt::ForIn {
+                var: var.clone(),
+                iterable: self.expr(iterable),
+                body: self.block(body),
+                source_line: *source_line,
+            },
+            Stmt::While { cond, body, source_line } => {
+                Stmt::While { cond: self.expr(cond), body: self.block(body), source_line: *source_line }
+            }
+            Stmt::Expr(e, line) => Stmt::Expr(self.expr(e), *line),
+            Stmt::Return(e, line) => Stmt::Return(e.as_ref().map(|e| self.expr(e)), *line),
+            Stmt::If { cond, body, elifs, else_body, source_line } => Stmt::If {
+                cond: self.expr(cond),
+                body: self.block(body),
+                elifs: elifs.iter().map(|(c, b)| (self.expr(c), self.block(b))).collect(),
+                else_body: else_body.as_ref().map(|b| self.block(b)),
+                source_line: *source_line,
+            },
+            Stmt::Switch { expr, cases, default, source_line } => {
+                if default.is_none() {
+                    self.check_switch(expr, cases, *source_line);
+                }
+                Stmt::Switch {
+                    expr: self.expr(expr),
+                    cases: cases.iter().map(|(c, b)| (self.expr(c), self.block(b))).collect(),
+                    default: default.as_ref().map(|b| self.block(b)),
+                    source_line: *source_line,
+                }
+            }
+            Stmt::Break { .. } | Stmt::Continue { .. } | Stmt::Pass { .. } => s.clone(),
+        }
+    }
+
+    /// Enum de cada variable inicializada o asignada con una variante (`screen = GameState.TITLE`)
+    fn variable_enums(&self, module: &Module) -> HashMap<String, Option<String>> {
+        fn walk<'a>(stmts: &'a [Stmt], out: &mut Vec<(&'a String, &'a Expr)>) {
+            for s in stmts {
+                match s {
+                    Stmt::Assign { target: AssignTarget::Ident { name, .. }, value, .. } | Stmt::Let { name, value, .. } => {
+                        out.push((name, value))
+                    }
+                    Stmt::If { body, elifs, else_body, .. } => {
+                        walk(body, out);
+                        elifs.iter().for_each(|(_, b)| walk(b, out));
+                        if let Some(b) = else_body { walk(b, out) }
+                    }
+                    Stmt::Switch { cases, default, .. } => {
+                        cases.iter().for_each(|(_, b)| walk(b, out));
+                        if let Some(b) = default { walk(b, out) }
+                    }
+                    Stmt::For { body, .. } | Stmt::ForIn { body, .. } | Stmt::While { body, .. } => walk(body, out),
+                    _ => {}
+                }
+            }
+        }
+        let mut assigned = Vec::new();
+        for it in &module.items {
+            match it {
+                Item::GlobalLet { name, value, .. } => assigned.push((name, value)),
+                Item::Function(f) => walk(&f.body, &mut assigned),
+                Item::StructDef(sd) => sd.methods.iter().chain(sd.constructor.as_ref()).for_each(|m| walk(&m.body, &mut assigned)),
+                _ => {}
+            }
+        }
+        let mut typed: HashMap<String, Option<String>> = HashMap::new();
+        for (name, value) in assigned {
+            let Some((def, _)) = self.variant(value) else { continue };
+            let enum_name = def.name.to_uppercase();
+            typed.entry(name.to_uppercase())
+                .and_modify(|known| if known.as_ref() != Some(&enum_name) { *known = None })
+                .or_insert(Some(enum_name));
+        }
+        typed
+    }
+
+    /// Warning si faltan variantes del enum de la variable (o, si no se conoce,
+    /// del que nombra el primer `case`)
+    fn check_switch(&mut self, expr: &Expr, cases: &[(Expr, Vec<Stmt>)], line: usize) {
+        let mut cased = cases.iter().filter_map(|(c, _)| self.variant(c)).peekable();
+        let typed = match expr {
+            Expr::Ident(id) => self.typed.get(&id.name.to_uppercase()).cloned().flatten().and_then(|n| self.enums.get(&n)),
+            _ => None,
+        };
+        let Some(def) = typed.or_else(|| cased.peek().map(|(def, _)| *def)) else { return };
+        let numbers = cases.iter().filter_map(|(c, _)| match c {
+            Expr::Number(n) => Some(*n),
+            _ => None,
+        });
+        let covered: HashSet<i32> = cased
+            .filter(|(other, _)| other.name == def.name)
+            .filter_map(|(_, v)| v)
+            .chain(numbers)
+            .collect();
+        let missing: Vec<&str> = def.variants.iter().enumerate()
+            .filter(|(i, _)| !covered.contains(&(*i as i32)))
+            .map(|(_, v)| v.as_str())
+            .collect();
+        if !missing.is_empty() {
+            let message = format!("switch over {} has no case for {} and no default", def.name, missing.join(", "));
+            self.push(DiagnosticSeverity::Warning, DiagnosticCode::NonExhaustiveSwitch, message, line);
+        }
+    }
+
+    fn target(&mut self, t: &AssignTarget, line: usize) -> AssignTarget {
+        match t {
+            AssignTarget::Ident { .. } => t.clone(),
+            AssignTarget::Index { target, index, source_line, col } => AssignTarget::Index {
+                target: Box::new(self.expr(target)),
+                index: Box::new(self.expr(index)),
+                source_line: *source_line,
+                col: *col,
+            },
+            AssignTarget::FieldAccess { target, field, .. } => {
+                if let Some(def) = self.lookup(target) {
+                    let message = format!("cannot assign to enum variant {}.{}", def.name, field);
+                    self.push(DiagnosticSeverity::Error, DiagnosticCode::UnknownVariant, message, line);
+                }
+                t.clone()
+            }
+        }
+    }
+
+    fn expr(&mut self, e: &Expr) -> Expr {
+        if let Some((def, value)) = self.variant(e) {
+            if let Some(v) = value {
+                return Expr::Number(v);
+            }
+            let Expr::FieldAccess { field, source_line, .. } = e else { unreachable!() };
+            let message = format!("enum '{}' has no variant '{}'", def.name, field);
+            self.push(DiagnosticSeverity::Error, DiagnosticCode::UnknownVariant, message, *source_line);
+            return e.clone();
+        }
+        let b = |rs: &mut Self, e: &Expr| Box::new(rs.expr(e));
+        match e {
+            Expr::Call(ci) => Expr::Call(CallInfo { args: ci.args.iter().map(|a| self.expr(a)).collect(), ..ci.clone() }),
+            Expr::MethodCall(mc) => Expr::MethodCall(MethodCallInfo {
+                target: b(self, &mc.target),
+                args: mc.args.iter().map(|a| self.expr(a)).collect(),
+                ..mc.clone()
+            }),
+            Expr::Binary { op, left, right } => Expr::Binary { op: *op, left: b(self, left), right: b(self, right) },
+            Expr::Compare { op, left, right } => Expr::Compare { op: *op, left: b(self, left), right: b(self, right) },
+            Expr::Logic { op, left, right } => Expr::Logic { op: *op, left: b(self, left), right: b(self, right) },
+            Expr::Not(inner) => Expr::Not(b(self, inner)),
+            Expr::BitNot(inner) => Expr::BitNot(b(self, inner)),
+            Expr::List(items) => Expr::List(items.iter().map(|i| self.expr(i)).collect()),
+            Expr::Index { target, index } => Expr::Index { target: b(self, target), index: b(self, index) },
+            Expr::FieldAccess { target, field, source_line, col } => Expr::FieldAccess {
+                target: b(self, target),
+                field: field.clone(),
+                source_line: *source_line,
+                col: *col,
+            },
+            Expr::Number(_) | Expr::Fixed(_) | Expr::StringLit(_) | Expr::Ident(_) | Expr::StructInit { .. } => e.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lex;
+    use crate::parser::parse_with_filename;
+
+    fn resolve(src: &str) -> (Module, Vec<Diagnostic>) {
+        let module = parse_with_filename(&lex(src).unwrap(), "test.vpy").unwrap();
+        let mut diags = Vec::new();
+        (resolve_module(&module, &mut diags), diags)
+    }
+
+    #[test]
+    fn variants_become_numbers() {
+        let (m, d) = resolve("enum GameState: TITLE, MAP, GAME\nscreen = GameState.GAME\ndef main():\n    screen = GameState.map\n");
+        assert!(d.is_empty(), "{:?}", d);
+        assert!(matches!(&m.items[1], Item::GlobalLet { value: Expr::Number(2), .. }));
+        let Item::Function(f) = &m.items[2] else { panic!() };
+        assert!(matches!(&f.body[0], Stmt::Assign { value: Expr::Number(1), .. }));
+    }
+
+    #[test]
+    fn unknown_variant_is_an_error() {
+        let (_, d) = resolve("enum Dir: LEFT, RIGHT\ndef main():\n    x = Dir.UP\n");
+        assert_eq!(d.len(), 1);
+        assert_eq!(d[0].code, DiagnosticCode::UnknownVariant);
+        assert_eq!(d[0].line, Some(3));
+    }
+
+    #[test]
+    fn non_exhaustive_switch_warns() {
+        let src = "enum Dir: LEFT, RIGHT, UP\nh = 0\ndef main():\n    switch h:\n        case Dir.LEFT:\n            h = 1\n    switch h:\n        case Dir.UP:\n            h = 2\n        default:\n            h = 0\n";
+        let (_, d) = resolve(src);
+        assert_eq!(d.len(), 1);
+        assert_eq!(d[0].severity, DiagnosticSeverity::Warning);
+        assert_eq!(d[0].message, "switch over Dir has no case for RIGHT, UP and no default");
+        assert_eq!(d[0].line, Some(4));
+    }
+
+    #[test]
+    fn switch_uses_the_variable_enum() {
+        let src = "enum GameState: TITLE, MAP, GAME\nenum Dir: LEFT, RIGHT\nscreen = GameState.TITLE\ndef main():\n    switch screen:\n        case Dir.LEFT:\n            pass\n        case GameState.MAP:\n            pass\n        case 2:\n            pass\n";
+        let (_, d) = resolve(src);
+        assert_eq!(d.len(), 1);
+        assert_eq!(d[0].message, "switch over GameState has no case for TITLE and no default");
+        assert_eq!(d[0].line, Some(5));
+    }
+}
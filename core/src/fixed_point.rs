@@ -0,0 +1,873 @@
+//! Fixed-point (8.8) numbers
+//!
+//! A `fixed` value is a 16-bit word with 8 integer and 8 fractional bits, so it
+//! lives in the same storage as an int and add/sub/compare stay plain 16-bit ops.
+//! Decimal literals (`1.5`) lex to `Expr::Fixed(raw)`; `fixed(x)` and `int(x)`
+//! convert explicitly.
+//!
+//! Types are inferred: a variable, array, parameter or function result is
+//! fixed when any value stored into it is fixed, or when it is annotated
+//! `name: fixed` (see types.rs; struct fields only become fixed this way).
+//! `lower_module` then checks every expression and rewrites the module to
+//! integer-only AST before optimisation:
+//! - fixed * fixed -> FIXED_MUL(a, b), fixed / fixed -> FIXED_DIV(a, b)
+//! - fixed * int and fixed / int stay ordinary MUL/DIV (the result is fixed)
+//! - int(x) -> FIXED_TO_INT(x), fixed(x) -> x * 256
+//! - integer literals used as fixed are scaled at compile time (x + 1 -> x + 256)
+//!
+//! Mixing a fixed value with a non-literal int is an error; the fix is an
+//! explicit conversion.
+
+use crate::ast::*;
+use crate::codegen::{is_builtin, Diagnostic, DiagnosticCode, DiagnosticSeverity};
+use crate::types::{local_types, ScalarType};
+use std::collections::{HashMap, HashSet};
+
+/// One in 8.8
+pub const FIXED_ONE: i32 = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Ty {
+    Int,
+    Fixed,
+    /// Constant integer expression; takes the type of the other operand
+    Lit(i32),
+}
+
+/// Builtins whose result has the type of their arguments
+const GENERIC_BUILTINS: &[&str] = &["ABS", "MIN", "MAX", "CLAMP"];
+/// Builtins that print or store raw words, so a fixed argument is fine
+const RAW_BUILTINS: &[&str] = &["DEBUG_PRINT", "DEBUG_PRINT_LABELED", "POKE"];
+
+#[derive(Default)]
+struct Types {
+    globals: HashSet<String>,
+    /// Fixed locals per function (key: function name)
+    locals: HashMap<String, HashSet<String>>,
+    /// Fixed parameter positions per function
+    params: HashMap<String, HashSet<usize>>,
+    returns: HashSet<String>,
+    fields: HashSet<String>,
+}
+
+struct Scope<'a> {
+    func: &'a str,
+    locals: HashSet<String>,
+}
+
+struct Lowering<'a> {
+    types: Types,
+    functions: HashMap<String, &'a Function>,
+    global_names: HashSet<String>,
+    diagnostics: Vec<Diagnostic>,
+    line: usize,
+}
+
+/// Type-check fixed-point usage and lower it to integer operations.
+/// Errors are appended to `diagnostics`; the returned module is only meaningful
+/// when none were added.
+pub fn lower_module(module: &Module, diagnostics: &mut Vec<Diagnostic>) -> Module {
+    if !module_uses_fixed(module) {
+        return module.clone();
+    }
+    let mut lw = Lowering::new(module);
+    lw.infer(module);
+    let items = module.items.iter().map(|it| lw.lower_item(it)).collect();
+    diagnostics.extend(lw.diagnostics);
+    Module { items, meta: module.meta.clone(), imports: module.imports.clone() }
+}
+
+fn module_uses_fixed(module: &Module) -> bool {
+    fn expr(e: &Expr) -> bool {
+        match e {
+            Expr::Fixed(_) => true,
+            Expr::Call(ci) => matches!(ci.name.to_ascii_lowercase().as_str(), "fixed" | "int") || ci.args.iter().any(expr),
+            Expr::MethodCall(mc) => expr(&mc.target) || mc.args.iter().any(expr),
+            Expr::Binary { left, right, .. } | Expr::Compare { left, right, .. } | Expr::Logic { left, right, .. } => expr(left) || expr(right),
+            Expr::Not(inner) | Expr::BitNot(inner) => expr(inner),
+            Expr::List(items) => items.iter().any(expr),
+            Expr::Index { target, index } => expr(target) || expr(index),
+            Expr::FieldAccess { target, .. } => expr(target),
+            Expr::Number(_) | Expr::StringLit(_) | Expr::Ident(_) | Expr::StructInit { .. } => false,
+        }
+    }
+    fn body(stmts: &[Stmt]) -> bool {
+        stmts.iter().any(|s| {
+            let mut found = false;
+            visit_stmt(s, &mut |e| found |= expr(e));
+            found
+        })
+    }
+    let function = |f: &Function| body(&f.body) || local_types(f).values().any(|t| *t == ScalarType::Fixed);
+    module.items.iter().any(|it| match it {
+        Item::Function(f) => function(f),
+        Item::GlobalLet { type_annotation, .. } if is_fixed_annotation(type_annotation.as_ref()) => true,
+        Item::Const { value, .. } | Item::GlobalLet { value, .. } | Item::ExprStatement(value) => expr(value),
+        Item::StructDef(sd) => {
+            sd.fields.iter().any(|f| f.type_annotation.as_deref() == Some("fixed"))
+                || sd.methods.iter().chain(sd.constructor.iter()).any(function)
+        }
+        Item::VectorList { .. } | Item::Export(_) | Item::Enum(_) => false,
+    })
+}
+
+fn is_fixed_annotation(annotation: Option<&String>) -> bool {
+    annotation.is_some_and(|t| t == "fixed")
+}
+
+// visit_stmt: call `f` on every top-level expression of a statement tree
+fn visit_stmt(s: &Stmt, f: &mut dyn FnMut(&Expr)) {
+    match s {
+        Stmt::Assign { value, .. } | Stmt::Let { value, .. } | Stmt::CompoundAssign { value, .. } => f(value),
+        Stmt::For { start, end, step, body, .. } => {
+            f(start);
+            f(end);
+            if let Some(st) = step { f(st); }
+            body.iter().for_each(|b| visit_stmt(b, f));
+        }
+        Stmt::ForIn { iterable, body, .. } => { f(iterable); body.iter().for_each(|b| visit_stmt(b, f)); }
+        Stmt::While { cond, body, .. } => { f(cond); body.iter().for_each(|b| visit_stmt(b, f)); }
+        Stmt::If { cond, body, elifs, else_body, .. } => {
+            f(cond);
+            body.iter().for_each(|b| visit_stmt(b, f));
+            for (c, b) in elifs { f(c); b.iter().for_each(|s| visit_stmt(s, f)); }
+            if let Some(eb) = else_body { eb.iter().for_each(|b| visit_stmt(b, f)); }
+        }
+        Stmt::Switch { expr, cases, default, .. } => {
+            f(expr);
+            for (c, b) in cases { f(c); b.iter().for_each(|s| visit_stmt(s, f)); }
+            if let Some(d) = default { d.iter().for_each(|b| visit_stmt(b, f)); }
+        }
+        Stmt::Expr(e, _) => f(e),
+        Stmt::Return(Some(e), _) => f(e),
+        Stmt::Return(None, _) | Stmt::Break { .. } | Stmt::Continue { .. } | Stmt::Pass { .. } => {}
+    }
+}
+
+// literal_value: value of a constant integer expression (-3, 2 * 4)
+fn literal_value(e: &Expr) -> Option<i32> {
+    match e {
+        Expr::Number(n) => Some(*n),
+        Expr::Binary { op, left, right } => {
+            let (l, r) = (literal_value(left)?, literal_value(right)?);
+            match op {
+                BinOp::Add => Some(l.wrapping_add(r)),
+                BinOp::Sub => Some(l.wrapping_sub(r)),
+                BinOp::Mul => Some(l.wrapping_mul(r)),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn assign_target_expr(target: &AssignTarget) -> Expr {
+    match target {
+        AssignTarget::Ident { name, source_line, col } => Expr::Ident(IdentInfo { name: name.clone(), source_line: *source_line, col: *col }),
+        AssignTarget::Index { target, index, .. } => Expr::Index { target: target.clone(), index: index.clone() },
+        AssignTarget::FieldAccess { target, field, source_line, col } => Expr::FieldAccess { target: target.clone(), field: field.clone(), source_line: *source_line, col: *col },
+    }
+}
+
+fn call(name: &str, args: Vec<Expr>, line: usize) -> Expr {
+    Expr::Call(CallInfo { name: name.to_string(), source_line: line, col: 0, args })
+}
+
+fn op_symbol(op: BinOp) -> &'static str {
+    match op {
+        BinOp::Add => "+", BinOp::Sub => "-", BinOp::Mul => "*", BinOp::Div => "/", BinOp::FloorDiv => "//",
+        BinOp::Mod => "%", BinOp::Shl => "<<", BinOp::Shr => ">>", BinOp::BitAnd => "&", BinOp::BitOr => "|", BinOp::BitXor => "^",
+    }
+}
+
+impl<'a> Lowering<'a> {
+    fn new(module: &'a Module) -> Self {
+        let mut functions = HashMap::new();
+        let mut global_names = HashSet::new();
+        let mut fields = HashSet::new();
+        let mut globals = HashSet::new();
+        let mut params = HashMap::new();
+        for it in &module.items {
+            match it {
+                Item::Function(f) => {
+                    functions.insert(f.name.clone(), f);
+                    let annotated: HashSet<usize> = (0..f.params.len()).filter(|&i| is_fixed_annotation(f.param_types.get(i).and_then(|t| t.as_ref()))).collect();
+                    if !annotated.is_empty() { params.insert(f.name.clone(), annotated); }
+                }
+                Item::GlobalLet { name, type_annotation, .. } => {
+                    global_names.insert(name.clone());
+                    if is_fixed_annotation(type_annotation.as_ref()) { globals.insert(name.clone()); }
+                }
+                Item::Const { name, .. } => { global_names.insert(name.clone()); }
+                Item::StructDef(sd) => {
+                    for fd in &sd.fields {
+                        if fd.type_annotation.as_deref() == Some("fixed") { fields.insert(fd.name.clone()); }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Lowering { types: Types { fields, globals, params, ..Types::default() }, functions, global_names, diagnostics: Vec::new(), line: 0 }
+    }
+
+    fn error(&mut self, message: String) {
+        self.diagnostics.push(Diagnostic {
+            severity: DiagnosticSeverity::Error,
+            code: DiagnosticCode::TypeMismatch,
+            message,
+            line: if self.line > 0 { Some(self.line) } else { None },
+            col: None,
+        });
+    }
+
+    // scope_for: locals of a function body (params, lets and assignments to non-globals)
+    fn scope_for<'s>(&self, func: &'s str, params: &[String], body: &[Stmt]) -> Scope<'s> {
+        fn collect(stmts: &[Stmt], globals: &HashSet<String>, out: &mut HashSet<String>) {
+            for s in stmts {
+                match s {
+                    Stmt::Let { name, .. } => { out.insert(name.clone()); }
+                    Stmt::Assign { target: AssignTarget::Ident { name, .. }, .. } if !globals.contains(name) => { out.insert(name.clone()); }
+                    Stmt::For { var, body, .. } | Stmt::ForIn { var, body, .. } => { out.insert(var.clone()); collect(body, globals, out); }
+                    Stmt::While { body, .. } => collect(body, globals, out),
+                    Stmt::If { body, elifs, else_body, .. } => {
+                        collect(body, globals, out);
+                        for (_, b) in elifs { collect(b, globals, out); }
+                        if let Some(eb) = else_body { collect(eb, globals, out); }
+                    }
+                    Stmt::Switch { cases, default, .. } => {
+                        for (_, b) in cases { collect(b, globals, out); }
+                        if let Some(d) = default { collect(d, globals, out); }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        let mut locals: HashSet<String> = params.iter().cloned().collect();
+        collect(body, &self.global_names, &mut locals);
+        Scope { func, locals }
+    }
+
+    fn var_is_fixed(&self, scope: &Scope, name: &str) -> bool {
+        if scope.locals.contains(name) {
+            self.types.locals.get(scope.func).is_some_and(|l| l.contains(name))
+        } else {
+            self.types.globals.contains(name)
+        }
+    }
+
+    fn mark_var(&mut self, scope: &Scope, name: &str) -> bool {
+        if scope.locals.contains(name) {
+            self.types.locals.entry(scope.func.to_string()).or_default().insert(name.to_string())
+        } else {
+            self.types.globals.insert(name.to_string())
+        }
+    }
+
+    // ------------------------------------------------------------ inference
+
+    fn infer(&mut self, module: &Module) {
+        let top = Scope { func: "", locals: HashSet::new() };
+        loop {
+            let mut changed = false;
+            for it in &module.items {
+                match it {
+                    Item::Const { name, value, .. } | Item::GlobalLet { name, value, .. }
+                        if self.ty(&top, value) == Ty::Fixed => { changed |= self.mark_var(&top, name); }
+                    Item::Function(f) => {
+                        let scope = self.scope_for(&f.name, &f.params, &f.body);
+                        for s in &f.body { changed |= self.infer_stmt(&scope, s); }
+                        // Parameters are locals: copy inferred call-site types in
+                        if let Some(idx) = self.types.params.get(&f.name).cloned() {
+                            for i in idx {
+                                if let Some(p) = f.params.get(i) { changed |= self.mark_var(&scope, p); }
+                            }
+                        }
+                    }
+                    Item::StructDef(sd) => {
+                        for m in sd.methods.iter().chain(sd.constructor.iter()) {
+                            let key = format!("{}.{}", sd.name, m.name);
+                            let scope = self.scope_for(&key, &m.params, &m.body);
+                            for s in &m.body { changed |= self.infer_stmt(&scope, s); }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            if !changed { break; }
+        }
+    }
+
+    fn infer_stmt(&mut self, scope: &Scope, s: &Stmt) -> bool {
+        let mut changed = false;
+        match s {
+            Stmt::Assign { target, value, .. } | Stmt::CompoundAssign { target, value, .. }
+                if self.ty(scope, value) == Ty::Fixed =>
+            {
+                match target {
+                    AssignTarget::Ident { name, .. } => changed |= self.mark_var(scope, name),
+                    AssignTarget::Index { target, .. } => {
+                        if let Expr::Ident(id) = &**target { changed |= self.mark_var(scope, &id.name); }
+                    }
+                    AssignTarget::FieldAccess { .. } => {}
+                }
+            }
+            Stmt::Let { name, type_annotation, value, .. } if is_fixed_annotation(type_annotation.as_ref()) || self.ty(scope, value) == Ty::Fixed => {
+                changed |= self.mark_var(scope, name);
+            }
+            Stmt::For { var, start, .. } if self.ty(scope, start) == Ty::Fixed => {
+                changed |= self.mark_var(scope, var);
+            }
+            Stmt::ForIn { var, iterable, .. } if self.ty(scope, iterable) == Ty::Fixed => {
+                changed |= self.mark_var(scope, var);
+            }
+            Stmt::Return(Some(e), _) if self.ty(scope, e) == Ty::Fixed && self.functions.contains_key(scope.func) => {
+                changed |= self.types.returns.insert(scope.func.to_string());
+            }
+            _ => {}
+        }
+        // Call sites passing fixed arguments make the parameter fixed
+        let mut calls: Vec<(String, usize)> = Vec::new();
+        visit_stmt_shallow(s, &mut |e| self.fixed_call_args(scope, e, &mut calls));
+        for (name, i) in calls {
+            changed |= self.types.params.entry(name).or_default().insert(i);
+        }
+        match s {
+            Stmt::For { body, .. } | Stmt::ForIn { body, .. } | Stmt::While { body, .. } => {
+                for b in body { changed |= self.infer_stmt(scope, b); }
+            }
+            Stmt::If { body, elifs, else_body, .. } => {
+                for b in body { changed |= self.infer_stmt(scope, b); }
+                for (_, b) in elifs { for s in b { changed |= self.infer_stmt(scope, s); } }
+                if let Some(eb) = else_body { for b in eb { changed |= self.infer_stmt(scope, b); } }
+            }
+            Stmt::Switch { cases, default, .. } => {
+                for (_, b) in cases { for s in b { changed |= self.infer_stmt(scope, s); } }
+                if let Some(d) = default { for b in d { changed |= self.infer_stmt(scope, b); } }
+            }
+            _ => {}
+        }
+        changed
+    }
+
+    fn fixed_call_args(&self, scope: &Scope, e: &Expr, out: &mut Vec<(String, usize)>) {
+        match e {
+            Expr::Call(ci) => {
+                if self.functions.contains_key(&ci.name) {
+                    for (i, a) in ci.args.iter().enumerate() {
+                        if self.ty(scope, a) == Ty::Fixed { out.push((ci.name.clone(), i)); }
+                    }
+                }
+                for a in &ci.args { self.fixed_call_args(scope, a, out); }
+            }
+            Expr::MethodCall(mc) => {
+                self.fixed_call_args(scope, &mc.target, out);
+                for a in &mc.args { self.fixed_call_args(scope, a, out); }
+            }
+            Expr::Binary { left, right, .. } | Expr::Compare { left, right, .. } | Expr::Logic { left, right, .. } => {
+                self.fixed_call_args(scope, left, out);
+                self.fixed_call_args(scope, right, out);
+            }
+            Expr::Not(inner) | Expr::BitNot(inner) => self.fixed_call_args(scope, inner, out),
+            Expr::List(items) => for a in items { self.fixed_call_args(scope, a, out); },
+            Expr::Index { target, index } => {
+                self.fixed_call_args(scope, target, out);
+                self.fixed_call_args(scope, index, out);
+            }
+            Expr::FieldAccess { target, .. } => self.fixed_call_args(scope, target, out),
+            Expr::Number(_) | Expr::Fixed(_) | Expr::StringLit(_) | Expr::Ident(_) | Expr::StructInit { .. } => {}
+        }
+    }
+
+    // ty: type of an expression under the current inference state (no diagnostics)
+    fn ty(&self, scope: &Scope, e: &Expr) -> Ty {
+        if let Some(v) = literal_value(e) { return Ty::Lit(v); }
+        match e {
+            Expr::Fixed(_) => Ty::Fixed,
+            Expr::Ident(id) => if self.var_is_fixed(scope, &id.name) { Ty::Fixed } else { Ty::Int },
+            Expr::Index { target, .. } => match &**target {
+                Expr::Ident(id) if self.var_is_fixed(scope, &id.name) => Ty::Fixed,
+                _ => Ty::Int,
+            },
+            Expr::FieldAccess { field, .. } => if self.types.fields.contains(field) { Ty::Fixed } else { Ty::Int },
+            Expr::List(items) => if items.iter().any(|i| self.ty(scope, i) == Ty::Fixed) { Ty::Fixed } else { Ty::Int },
+            Expr::Binary { op, left, right } => {
+                // Constant operands were handled by literal_value above
+                let (l, r) = (self.ty(scope, left), self.ty(scope, right));
+                match op {
+                    BinOp::BitAnd | BinOp::BitOr | BinOp::BitXor => Ty::Int,
+                    BinOp::Shl | BinOp::Shr if l == Ty::Fixed => Ty::Fixed,
+                    BinOp::Shl | BinOp::Shr => Ty::Int,
+                    _ if l == Ty::Fixed || r == Ty::Fixed => Ty::Fixed,
+                    _ => Ty::Int,
+                }
+            }
+            Expr::Call(ci) => {
+                let up = ci.name.to_ascii_uppercase();
+                let up = up.strip_prefix("MATH_").unwrap_or(&up);
+                match up {
+                    "FIXED" => Ty::Fixed,
+                    "INT" => Ty::Int,
+                    _ if GENERIC_BUILTINS.contains(&up) => {
+                        ci.args.iter().fold(Ty::Int, |acc, a| acc.max_fixed(self.ty(scope, a)))
+                    }
+                    _ if self.types.returns.contains(&ci.name) => Ty::Fixed,
+                    _ => Ty::Int,
+                }
+            }
+            _ => Ty::Int,
+        }
+    }
+
+    // -------------------------------------------------------------- lowering
+
+    fn lower_item(&mut self, it: &Item) -> Item {
+        let top = Scope { func: "", locals: HashSet::new() };
+        match it {
+            Item::Const { name, value, source_line } => {
+                self.line = *source_line;
+                let want = if self.types.globals.contains(name) { Ty::Fixed } else { Ty::Int };
+                Item::Const { name: name.clone(), value: self.lower_as(&top, value, want, name), source_line: *source_line }
+            }
+            Item::GlobalLet { name, type_annotation, value, source_line } => {
+                self.line = *source_line;
+                let want = if self.types.globals.contains(name) { Ty::Fixed } else { Ty::Int };
+                Item::GlobalLet { name: name.clone(), type_annotation: type_annotation.clone(), value: self.lower_as(&top, value, want, name), source_line: *source_line }
+            }
+            Item::ExprStatement(e) => Item::ExprStatement(self.lower(&top, e).0),
+            Item::Function(f) => Item::Function(self.lower_function(f, &f.name)),
+            Item::StructDef(sd) => {
+                let mut sd = sd.clone();
+                let name = sd.name.clone();
+                sd.methods = sd.methods.iter().map(|m| self.lower_function(m, &format!("{}.{}", name, m.name))).collect();
+                sd.constructor = sd.constructor.as_ref().map(|c| self.lower_function(c, &format!("{}.{}", name, c.name)));
+                Item::StructDef(sd)
+            }
+            Item::VectorList { .. } | Item::Export(_) | Item::Enum(_) => it.clone(),
+        }
+    }
+
+    fn lower_function(&mut self, f: &Function, key: &str) -> Function {
+        let scope = self.scope_for(key, &f.params, &f.body);
+        let body = f.body.iter().map(|s| self.lower_stmt(&scope, s)).collect();
+        Function { name: f.name.clone(), line: f.line, params: f.params.clone(), param_types: f.param_types.clone(), body }
+    }
+
+    fn lower_body(&mut self, scope: &Scope, body: &[Stmt]) -> Vec<Stmt> {
+        body.iter().map(|s| self.lower_stmt(scope, s)).collect()
+    }
+
+    fn target_ty(&self, scope: &Scope, target: &AssignTarget) -> Ty {
+        match target {
+            AssignTarget::Ident { name, .. } => if self.var_is_fixed(scope, name) { Ty::Fixed } else { Ty::Int },
+            _ => self.ty(scope, &assign_target_expr(target)),
+        }
+    }
+
+    fn lower_target(&mut self, scope: &Scope, target: &AssignTarget) -> AssignTarget {
+        match target {
+            AssignTarget::Ident { .. } => target.clone(),
+            AssignTarget::Index { target: t, index, source_line, col } => AssignTarget::Index {
+                target: Box::new(self.lower(scope, t).0),
+                index: Box::new(self.lower_as(scope, index, Ty::Int, "array index")),
+                source_line: *source_line,
+                col: *col,
+            },
+            AssignTarget::FieldAccess { target: t, field, source_line, col } => AssignTarget::FieldAccess {
+                target: Box::new(self.lower(scope, t).0),
+                field: field.clone(),
+                source_line: *source_line,
+                col: *col,
+            },
+        }
+    }
+
+    fn target_name(target: &AssignTarget) -> String {
+        match target {
+            AssignTarget::Ident { name, .. } => name.clone(),
+            AssignTarget::Index { target, .. } => match &**target { Expr::Ident(id) => id.name.clone(), _ => "array".to_string() },
+            AssignTarget::FieldAccess { field, .. } => field.clone(),
+        }
+    }
+
+    fn lower_stmt(&mut self, scope: &Scope, s: &Stmt) -> Stmt {
+        self.line = s.source_line();
+        match s {
+            Stmt::Assign { target, value, source_line } => {
+                let want = self.target_ty(scope, target);
+                let value = self.lower_as(scope, value, want, &Self::target_name(target));
+                Stmt::Assign { target: self.lower_target(scope, target), value, source_line: *source_line }
+            }
+            Stmt::Let { name, type_annotation, value, source_line } => {
+                let want = if self.var_is_fixed(scope, name) { Ty::Fixed } else { Ty::Int };
+                Stmt::Let { name: name.clone(), type_annotation: type_annotation.clone(), value: self.lower_as(scope, value, want, name), source_line: *source_line }
+            }
+            Stmt::CompoundAssign { target, op, value, source_line } => {
+                // Lower `t op= v` as `t = t op v` and keep the compound form when the
+                // operator survived unchanged
+                let current = assign_target_expr(target);
+                let whole = Expr::Binary { op: *op, left: Box::new(current.clone()), right: Box::new(value.clone()) };
+                let want = self.target_ty(scope, target);
+                let lowered = self.lower_as(scope, &whole, want, &Self::target_name(target));
+                let lowered_target = self.lower_target(scope, target);
+                match lowered {
+                    Expr::Binary { op: lop, left, right } if lop == *op && *left == current => {
+                        Stmt::CompoundAssign { target: lowered_target, op: *op, value: *right, source_line: *source_line }
+                    }
+                    other => Stmt::Assign { target: lowered_target, value: other, source_line: *source_line },
+                }
+            }
+            Stmt::For { var, start, end, step, body, source_line } => {
+                let want = if self.var_is_fixed(scope, var) { Ty::Fixed } else { Ty::Int };
+                Stmt::For {
+                    var: var.clone(),
+                    start: self.lower_as(scope, start, want, var),
+                    end: self.lower_as(scope, end, want, var),
+                    step: step.as_ref().map(|st| self.lower_as(scope, st, want, var)),
+                    body: self.lower_body(scope, body),
+                    source_line: *source_line,
+                }
+            }
+            Stmt::ForIn { var, iterable, body, source_line } => Stmt::ForIn {
+                var: var.clone(),
+                iterable: self.lower(scope, iterable).0,
+                body: self.lower_body(scope, body),
+                source_line: *source_line,
+            },
+            Stmt::While { cond, body, source_line } => Stmt::While {
+                cond: self.lower(scope, cond).0,
+                body: self.lower_body(scope, body),
+                source_line: *source_line,
+            },
+            Stmt::If { cond, body, elifs, else_body, source_line } => Stmt::If {
+                cond: self.lower(scope, cond).0,
+                body: self.lower_body(scope, body),
+                elifs: elifs.iter().map(|(c, b)| (self.lower(scope, c).0, self.lower_body(scope, b))).collect(),
+                else_body: else_body.as_ref().map(|b| self.lower_body(scope, b)),
+                source_line: *source_line,
+            },
+            Stmt::Switch { expr, cases, default, source_line } => {
+                let (expr, ty) = self.lower(scope, expr);
+                let want = if ty == Ty::Fixed { Ty::Fixed } else { Ty::Int };
+                Stmt::Switch {
+                    expr,
+                    cases: cases.iter().map(|(c, b)| (self.lower_as(scope, c, want, "case"), self.lower_body(scope, b))).collect(),
+                    default: default.as_ref().map(|b| self.lower_body(scope, b)),
+                    source_line: *source_line,
+                }
+            }
+            Stmt::Expr(e, line) => Stmt::Expr(self.lower(scope, e).0, *line),
+            Stmt::Return(Some(e), line) => {
+                let want = if self.types.returns.contains(scope.func) { Ty::Fixed } else { Ty::Int };
+                Stmt::Return(Some(self.lower_as(scope, e, want, &format!("return of {}", scope.func))), *line)
+            }
+            Stmt::Return(None, _) | Stmt::Break { .. } | Stmt::Continue { .. } | Stmt::Pass { .. } => s.clone(),
+        }
+    }
+
+    // lower_as: lower `e` and convert it to `want` (integer literals are scaled,
+    // anything else must already have the right type)
+    fn lower_as(&mut self, scope: &Scope, e: &Expr, want: Ty, what: &str) -> Expr {
+        let (lowered, ty) = self.lower(scope, e);
+        self.coerce(lowered, ty, want, what)
+    }
+
+    fn coerce(&mut self, e: Expr, ty: Ty, want: Ty, what: &str) -> Expr {
+        match (ty, want) {
+            (Ty::Lit(v), Ty::Fixed) => Expr::Number(v.wrapping_mul(FIXED_ONE)),
+            (Ty::Int, Ty::Fixed) => {
+                self.error(format!("'{}' is fixed but is given an int value; convert it with fixed()", what));
+                e
+            }
+            (Ty::Fixed, Ty::Int) => {
+                self.error(format!("'{}' is int but is given a fixed value; convert it with int()", what));
+                e
+            }
+            _ => e,
+        }
+    }
+
+    fn lower(&mut self, scope: &Scope, e: &Expr) -> (Expr, Ty) {
+        if let Some(v) = literal_value(e) {
+            return (e.clone(), Ty::Lit(v));
+        }
+        match e {
+            Expr::Fixed(raw) => (Expr::Number(*raw), Ty::Fixed),
+            Expr::Number(_) | Expr::StringLit(_) | Expr::StructInit { .. } => (e.clone(), Ty::Int),
+            Expr::Ident(_) => { let ty = self.ty(scope, e); (e.clone(), ty) }
+            Expr::Binary { op, left, right } => self.lower_binary(scope, *op, left, right),
+            Expr::Compare { op, left, right } => {
+                let (l, lt) = self.lower(scope, left);
+                let (r, rt) = self.lower(scope, right);
+                let (l, r) = if lt == Ty::Fixed || rt == Ty::Fixed {
+                    (self.coerce(l, lt, Ty::Fixed, "comparison operand"), self.coerce(r, rt, Ty::Fixed, "comparison operand"))
+                } else {
+                    (l, r)
+                };
+                (Expr::Compare { op: *op, left: Box::new(l), right: Box::new(r) }, Ty::Int)
+            }
+            Expr::Logic { op, left, right } => {
+                let l = self.lower(scope, left).0;
+                let r = self.lower(scope, right).0;
+                (Expr::Logic { op: *op, left: Box::new(l), right: Box::new(r) }, Ty::Int)
+            }
+            Expr::Not(inner) => (Expr::Not(Box::new(self.lower(scope, inner).0)), Ty::Int),
+            Expr::BitNot(inner) => {
+                let (i, ty) = self.lower(scope, inner);
+                if ty == Ty::Fixed { self.error("'~' is not defined for fixed values".to_string()); }
+                (Expr::BitNot(Box::new(i)), Ty::Int)
+            }
+            Expr::List(items) => {
+                let ty = self.ty(scope, e);
+                let want = if ty == Ty::Fixed { Ty::Fixed } else { Ty::Int };
+                (Expr::List(items.iter().map(|i| self.lower_as(scope, i, want, "list element")).collect()), want)
+            }
+            Expr::Index { target, index } => {
+                let ty = self.ty(scope, e);
+                let t = self.lower(scope, target).0;
+                let i = self.lower_as(scope, index, Ty::Int, "array index");
+                (Expr::Index { target: Box::new(t), index: Box::new(i) }, ty)
+            }
+            Expr::FieldAccess { target, field, source_line, col } => {
+                let ty = self.ty(scope, e);
+                let t = self.lower(scope, target).0;
+                (Expr::FieldAccess { target: Box::new(t), field: field.clone(), source_line: *source_line, col: *col }, ty)
+            }
+            Expr::MethodCall(mc) => {
+                let mut mc = mc.clone();
+                mc.target = Box::new(self.lower(scope, &mc.target).0);
+                mc.args = mc.args.iter().map(|a| self.lower(scope, a).0).collect();
+                (Expr::MethodCall(mc), Ty::Int)
+            }
+            Expr::Call(ci) => self.lower_call(scope, ci),
+        }
+    }
+
+    fn lower_binary(&mut self, scope: &Scope, op: BinOp, left: &Expr, right: &Expr) -> (Expr, Ty) {
+        let (l, lt) = self.lower(scope, left);
+        let (r, rt) = self.lower(scope, right);
+        let bin = |op: BinOp, l: Expr, r: Expr| Expr::Binary { op, left: Box::new(l), right: Box::new(r) };
+        if lt != Ty::Fixed && rt != Ty::Fixed {
+            return (bin(op, l, r), Ty::Int);
+        }
+        let what = format!("operand of '{}'", op_symbol(op));
+        match op {
+            BinOp::Add | BinOp::Sub | BinOp::Mod => {
+                let l = self.coerce(l, lt, Ty::Fixed, &what);
+                let r = self.coerce(r, rt, Ty::Fixed, &what);
+                (bin(op, l, r), Ty::Fixed)
+            }
+            BinOp::Mul => {
+                if lt == Ty::Fixed && rt == Ty::Fixed {
+                    if let (Expr::Number(a), Expr::Number(b)) = (&l, &r) {
+                        return (Expr::Number((a * b) >> 8), Ty::Fixed);
+                    }
+                    (call("FIXED_MUL", vec![l, r], self.line), Ty::Fixed)
+                } else {
+                    // Scaling by an integer keeps the 8.8 format
+                    (bin(op, l, r), Ty::Fixed)
+                }
+            }
+            BinOp::Div | BinOp::FloorDiv => {
+                if rt == Ty::Fixed {
+                    let l = self.coerce(l, lt, Ty::Fixed, &what);
+                    if let (Expr::Number(a), Expr::Number(b)) = (&l, &r) {
+                        if *b != 0 { return (Expr::Number((a << 8) / b), Ty::Fixed); }
+                    }
+                    (call("FIXED_DIV", vec![l, r], self.line), Ty::Fixed)
+                } else {
+                    (bin(op, l, r), Ty::Fixed)
+                }
+            }
+            BinOp::Shl | BinOp::Shr => {
+                if rt == Ty::Fixed { self.error(format!("shift amount of '{}' must be an int", op_symbol(op))); }
+                (bin(op, l, r), lt)
+            }
+            BinOp::BitAnd | BinOp::BitOr | BinOp::BitXor => {
+                self.error(format!("'{}' is not defined for fixed values", op_symbol(op)));
+                (bin(op, l, r), Ty::Int)
+            }
+        }
+    }
+
+    fn lower_call(&mut self, scope: &Scope, ci: &CallInfo) -> (Expr, Ty) {
+        let up = ci.name.to_ascii_uppercase();
+        let core = up.strip_prefix("MATH_").unwrap_or(&up).to_string();
+        let rebuild = |args: Vec<Expr>| Expr::Call(CallInfo { name: ci.name.clone(), source_line: ci.source_line, col: ci.col, args });
+
+        if (core == "FIXED" || core == "INT") && ci.args.len() == 1 {
+            let (arg, ty) = self.lower(scope, &ci.args[0]);
+            return match (core.as_str(), ty) {
+                ("FIXED", Ty::Fixed) => (arg, Ty::Fixed),
+                ("FIXED", Ty::Lit(v)) => (Expr::Number(v.wrapping_mul(FIXED_ONE)), Ty::Fixed),
+                ("FIXED", _) => (Expr::Binary { op: BinOp::Mul, left: Box::new(arg), right: Box::new(Expr::Number(FIXED_ONE)) }, Ty::Fixed),
+                (_, Ty::Fixed) => match arg {
+                    Expr::Number(raw) => (Expr::Number(raw >> 8), Ty::Int),
+                    arg => (call("FIXED_TO_INT", vec![arg], ci.source_line), Ty::Int),
+                },
+                (_, ty) => (arg, ty),
+            };
+        }
+
+        let lowered: Vec<(Expr, Ty)> = ci.args.iter().map(|a| self.lower(scope, a)).collect();
+        if GENERIC_BUILTINS.contains(&core.as_str()) {
+            let ty = self.ty(scope, &Expr::Call(ci.clone()));
+            let want = if ty == Ty::Fixed { Ty::Fixed } else { Ty::Int };
+            let what = format!("argument of {}", ci.name);
+            let args = lowered.into_iter().map(|(a, t)| if want == Ty::Fixed { self.coerce(a, t, want, &what) } else { a }).collect();
+            return (rebuild(args), want);
+        }
+        if let Some(f) = self.functions.get(&ci.name).copied() {
+            let fixed_params = self.types.params.get(&ci.name).cloned().unwrap_or_default();
+            let args = lowered.into_iter().enumerate().map(|(i, (a, t))| {
+                let want = if fixed_params.contains(&i) { Ty::Fixed } else { Ty::Int };
+                let what = format!("parameter '{}' of {}", f.params.get(i).map(String::as_str).unwrap_or("?"), ci.name);
+                self.coerce(a, t, want, &what)
+            }).collect();
+            let ret = if self.types.returns.contains(&ci.name) { Ty::Fixed } else { Ty::Int };
+            return (rebuild(args), ret);
+        }
+        if is_builtin(&ci.name) && !RAW_BUILTINS.contains(&up.strip_prefix("VECTREX_").unwrap_or(&up)) {
+            for (_, t) in &lowered {
+                if *t == Ty::Fixed {
+                    self.error(format!("{} expects int arguments; convert fixed values with int()", ci.name));
+                    break;
+                }
+            }
+        }
+        (rebuild(lowered.into_iter().map(|(a, _)| a).collect()), Ty::Int)
+    }
+}
+
+impl Ty {
+    // max_fixed: fixed wins, then int, then literal
+    fn max_fixed(self, other: Ty) -> Ty {
+        match (self, other) {
+            (Ty::Fixed, _) | (_, Ty::Fixed) => Ty::Fixed,
+            (Ty::Int, _) | (_, Ty::Int) => Ty::Int,
+            (Ty::Lit(a), Ty::Lit(_)) => Ty::Lit(a),
+        }
+    }
+}
+
+// visit_stmt_shallow: expressions owned directly by `s` (not by nested bodies)
+fn visit_stmt_shallow(s: &Stmt, f: &mut dyn FnMut(&Expr)) {
+    match s {
+        Stmt::Assign { value, .. } | Stmt::Let { value, .. } | Stmt::CompoundAssign { value, .. } => f(value),
+        Stmt::For { start, end, step, .. } => { f(start); f(end); if let Some(st) = step { f(st); } }
+        Stmt::ForIn { iterable, .. } => f(iterable),
+        Stmt::While { cond, .. } => f(cond),
+        Stmt::If { cond, elifs, .. } => { f(cond); for (c, _) in elifs { f(c); } }
+        Stmt::Switch { expr, cases, .. } => { f(expr); for (c, _) in cases { f(c); } }
+        Stmt::Expr(e, _) | Stmt::Return(Some(e), _) => f(e),
+        Stmt::Return(None, _) | Stmt::Break { .. } | Stmt::Continue { .. } | Stmt::Pass { .. } => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lex;
+    use crate::parser::parse_with_filename;
+
+    fn lower_src(src: &str) -> (Module, Vec<Diagnostic>) {
+        let tokens = lex(src).expect("lex ok");
+        let module = parse_with_filename(&tokens, "fx.vpy").expect("parse ok");
+        let mut diags = Vec::new();
+        let lowered = lower_module(&module, &mut diags);
+        (lowered, diags)
+    }
+
+    fn first_fn_body(m: &Module) -> &[Stmt] {
+        m.items.iter().find_map(|it| if let Item::Function(f) = it { Some(f.body.as_slice()) } else { None }).unwrap()
+    }
+
+    fn assigned_value(s: &Stmt) -> &Expr {
+        match s {
+            Stmt::Assign { value, .. } | Stmt::Let { value, .. } => value,
+            other => panic!("not an assignment: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn literals_lower_to_raw_words() {
+        let (m, d) = lower_src("def main():\n    x = 1.5\n    x = x + 1\n");
+        assert!(d.is_empty(), "{:?}", d);
+        let body = first_fn_body(&m);
+        assert_eq!(assigned_value(&body[0]), &Expr::Number(384));
+        match assigned_value(&body[1]) {
+            Expr::Binary { op: BinOp::Add, right, .. } => assert_eq!(**right, Expr::Number(256)),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn fixed_products_use_helpers_and_int_scaling_does_not() {
+        let (m, d) = lower_src("def main():\n    v = 0.5\n    a = v * v\n    b = v * 3\n    c = 1 / v\n    n = int(a)\n");
+        assert!(d.is_empty(), "{:?}", d);
+        let body = first_fn_body(&m);
+        assert!(matches!(assigned_value(&body[1]), Expr::Call(ci) if ci.name == "FIXED_MUL"));
+        assert!(matches!(assigned_value(&body[2]), Expr::Binary { op: BinOp::Mul, .. }));
+        match assigned_value(&body[3]) {
+            Expr::Call(ci) if ci.name == "FIXED_DIV" => assert_eq!(ci.args[0], Expr::Number(256)),
+            other => panic!("{:?}", other),
+        }
+        assert!(matches!(assigned_value(&body[4]), Expr::Call(ci) if ci.name == "FIXED_TO_INT"));
+    }
+
+    #[test]
+    fn constant_fixed_arithmetic_folds() {
+        let (m, _) = lower_src("const G = 0.5 * 0.5\nconst H = 1.0 / 4.0\n");
+        let values: Vec<&Expr> = m.items.iter().filter_map(|it| if let Item::Const { value, .. } = it { Some(value) } else { None }).collect();
+        assert_eq!(values, vec![&Expr::Number(64), &Expr::Number(64)]);
+    }
+
+    #[test]
+    fn mixing_int_and_fixed_is_an_error() {
+        let (_, d) = lower_src("def main():\n    n = J1_X()\n    x = 1.5\n    y = x + n\n");
+        assert_eq!(d.len(), 1);
+        assert_eq!(d[0].code, DiagnosticCode::TypeMismatch);
+        assert_eq!(d[0].line, Some(4));
+        assert!(d[0].message.contains("fixed()"));
+    }
+
+    #[test]
+    fn builtins_reject_fixed_arguments() {
+        let (_, d) = lower_src("def main():\n    x = 1.5\n    MOVE(x, 0)\n    MOVE(int(x), 0)\n");
+        assert_eq!(d.len(), 1, "{:?}", d);
+        assert!(d[0].message.contains("int()"));
+    }
+
+    #[test]
+    fn types_flow_through_calls_and_returns() {
+        let (m, d) = lower_src("def half(v):\n    return v * 0.5\n\ndef main():\n    x = half(3.0)\n    y = x * x\n");
+        assert!(d.is_empty(), "{:?}", d);
+        let main = m.items.iter().find_map(|it| match it { Item::Function(f) if f.name == "main" => Some(f), _ => None }).unwrap();
+        assert!(matches!(assigned_value(&main.body[1]), Expr::Call(ci) if ci.name == "FIXED_MUL"));
+    }
+
+    #[test]
+    fn fixed_annotations_seed_the_type() {
+        let (m, d) = lower_src("def sq(v: fixed):\n    return v * v\n\ndef main():\n    x = sq(2.0)\n");
+        assert!(d.is_empty(), "{:?}", d);
+        let sq = m.items.iter().find_map(|it| match it { Item::Function(f) if f.name == "sq" => Some(f), _ => None }).unwrap();
+        assert!(matches!(&sq.body[0], Stmt::Return(Some(Expr::Call(ci)), _) if ci.name == "FIXED_MUL"));
+    }
+
+    #[test]
+    fn compound_assign_with_fixed_product_is_expanded() {
+        let (m, d) = lower_src("def main():\n    v = 2.0\n    v *= 0.75\n    v += 1\n");
+        assert!(d.is_empty(), "{:?}", d);
+        let body = first_fn_body(&m);
+        assert!(matches!(&body[1], Stmt::Assign { value: Expr::Call(ci), .. } if ci.name == "FIXED_MUL"));
+        assert!(matches!(&body[2], Stmt::CompoundAssign { value: Expr::Number(256), .. }));
+    }
+
+    #[test]
+    fn modules_without_fixed_are_untouched() {
+        let src = "def main():\n    x = 3\n    y = x * 2\n";
+        let (m, d) = lower_src(src);
+        assert!(d.is_empty());
+        let tokens = lex(src).unwrap();
+        assert_eq!(m, parse_with_filename(&tokens, "fx.vpy").unwrap());
+    }
+}
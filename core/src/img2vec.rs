@@ -0,0 +1,475 @@
+//! Bitmap → vector tracer (`vectrexc img2vec`)
+//!
+//! Turns PNG/JPEG/WebP artwork into a `.vec` resource. The image is reduced
+//! to luminance and outlines are found either by edge detection (Sobel with
+//! non-maximum suppression) or by following the borders of the ink regions.
+//! Outline pixels are linked into polylines, simplified with Douglas-Peucker
+//! until they fit the segment budget, and grouped into one layer per
+//! intensity band, with each layer's paths ordered by `GreedyThenTwoOpt` to
+//! keep beam travel short.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+
+use crate::path_ordering::{GreedyThenTwoOpt, PathOrderer};
+use crate::vecres::{Layer, Point, VecPath, VecResource};
+
+/// Weakest intensity given to traced paths (fainter lines are barely visible)
+const MIN_INTENSITY: u8 = 32;
+/// Outlines shorter than this many pixels are treated as noise
+const MIN_CHAIN_PIXELS: usize = 4;
+/// Largest delta a single Draw_Sync_List segment can encode
+const MAX_DELTA: i32 = 127;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum TraceMode {
+    /// Sobel edge detection: outlines every change in brightness
+    Edges,
+    /// Borders of the regions darker than the threshold (line art, silhouettes)
+    Contours,
+}
+
+#[derive(Clone, Debug)]
+pub struct TraceOptions {
+    pub mode: TraceMode,
+    /// Contours: luminance cut-off (default: Otsu). Edges: minimum edge
+    /// strength, 0-255 relative to the strongest edge (default 64).
+    pub threshold: Option<u8>,
+    /// Trace light shapes on a dark background
+    pub invert: bool,
+    /// Maximum number of line segments in the output
+    pub max_segments: usize,
+    /// Douglas-Peucker tolerance in Vectrex units
+    pub tolerance: f64,
+    /// The image is fitted into ±size Vectrex units
+    pub size: i16,
+    /// Intensity bands; each becomes a layer
+    pub levels: usize,
+    /// Longest image side after downscaling, in pixels
+    pub resolution: u32,
+}
+
+impl Default for TraceOptions {
+    fn default() -> Self {
+        Self {
+            mode: TraceMode::Contours,
+            threshold: None,
+            invert: false,
+            max_segments: 200,
+            tolerance: 1.5,
+            size: 127,
+            levels: 3,
+            resolution: 256,
+        }
+    }
+}
+
+/// 8-bit luminance image
+#[derive(Debug, Clone)]
+pub struct LumaImage {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl LumaImage {
+    fn at(&self, x: usize, y: usize) -> u8 {
+        self.pixels[y * self.width + x]
+    }
+
+    /// Neighbour of (x, y) at offset (dx, dy), if inside the image
+    fn offset(&self, x: usize, y: usize, dx: isize, dy: isize) -> Option<(usize, usize)> {
+        let nx = x.checked_add_signed(dx).filter(|&v| v < self.width)?;
+        let ny = y.checked_add_signed(dy).filter(|&v| v < self.height)?;
+        Some((nx, ny))
+    }
+}
+
+/// Load an image as luminance, downscaled so its longest side is at most
+/// `resolution`. Transparent pixels become background (white, or black when
+/// `invert` is set).
+pub fn load_image(path: &Path, resolution: u32, invert: bool) -> Result<LumaImage> {
+    let mut rgba = image::open(path)
+        .with_context(|| format!("Cannot read image {}", path.display()))?
+        .to_rgba8();
+    let (w, h) = rgba.dimensions();
+    if w.max(h) > resolution {
+        let scale = resolution as f64 / w.max(h) as f64;
+        let (nw, nh) = (((w as f64 * scale).round() as u32).max(1), ((h as f64 * scale).round() as u32).max(1));
+        rgba = image::imageops::resize(&rgba, nw, nh, image::imageops::FilterType::Triangle);
+    }
+    let background = if invert { 0.0 } else { 255.0 };
+    let pixels = rgba
+        .pixels()
+        .map(|p| {
+            let [r, g, b, a] = p.0;
+            let luma = 0.299 * r as f64 + 0.587 * g as f64 + 0.114 * b as f64;
+            let alpha = a as f64 / 255.0;
+            (luma * alpha + background * (1.0 - alpha)).round() as u8
+        })
+        .collect();
+    Ok(LumaImage { width: rgba.width() as usize, height: rgba.height() as usize, pixels })
+}
+
+/// Trace `image` into a vector resource named `name`.
+pub fn trace(image: &LumaImage, name: &str, options: &TraceOptions) -> VecResource {
+    let ink: Vec<u8> = image.pixels.iter().map(|&l| if options.invert { l } else { 255 - l }).collect();
+    let mask = match options.mode {
+        TraceMode::Contours => contour_mask(image, options),
+        TraceMode::Edges => edge_mask(image, options.threshold.unwrap_or(64)),
+    };
+    let chains: Vec<Chain> = link_chains(&mask, image)
+        .into_iter()
+        .filter(|pixels| pixels.len() >= MIN_CHAIN_PIXELS)
+        .map(|pixels| Chain::new(pixels, image, &ink))
+        .collect();
+
+    let paths = fit_budget(&chains, image, options);
+
+    let mut resource = VecResource::new(name);
+    resource.layers = intensity_layers(paths, options.levels.max(1));
+    resource
+}
+
+/// Darkest-threshold split of the luminance histogram (Otsu's method)
+pub fn otsu_threshold(image: &LumaImage) -> u8 {
+    let mut histogram = [0u64; 256];
+    for &l in &image.pixels {
+        histogram[l as usize] += 1;
+    }
+    let total = image.pixels.len() as f64;
+    let sum_all: f64 = histogram.iter().enumerate().map(|(i, &n)| i as f64 * n as f64).sum();
+    let (mut weight_bg, mut sum_bg) = (0.0, 0.0);
+    let (mut best, mut best_variance) = (128u8, -1.0);
+    for (t, &n) in histogram.iter().enumerate() {
+        weight_bg += n as f64;
+        sum_bg += t as f64 * n as f64;
+        let weight_fg = total - weight_bg;
+        if weight_bg == 0.0 || weight_fg == 0.0 {
+            continue;
+        }
+        let mean_bg = sum_bg / weight_bg;
+        let mean_fg = (sum_all - sum_bg) / weight_fg;
+        let variance = weight_bg * weight_fg * (mean_bg - mean_fg).powi(2);
+        if variance > best_variance {
+            best_variance = variance;
+            best = t as u8;
+        }
+    }
+    // Pixels <= best form the dark class
+    best.saturating_add(1)
+}
+
+/// Ink pixels that touch background (or the image border) on a 4-neighbour side
+fn contour_mask(image: &LumaImage, options: &TraceOptions) -> Vec<bool> {
+    let threshold = options.threshold.unwrap_or_else(|| otsu_threshold(image));
+    let is_ink = |x: usize, y: usize| {
+        let l = image.at(x, y);
+        if options.invert { l >= threshold } else { l < threshold }
+    };
+    let mut mask = vec![false; image.pixels.len()];
+    for y in 0..image.height {
+        for x in 0..image.width {
+            if !is_ink(x, y) {
+                continue;
+            }
+            let border = [(1, 0), (-1, 0), (0, 1), (0, -1)]
+                .iter()
+                .any(|&(dx, dy)| image.offset(x, y, dx, dy).is_none_or(|(nx, ny)| !is_ink(nx, ny)));
+            mask[y * image.width + x] = border;
+        }
+    }
+    mask
+}
+
+/// Sobel magnitude thinned by non-maximum suppression, normalised to 0-255
+fn edge_mask(image: &LumaImage, threshold: u8) -> Vec<bool> {
+    let (w, h) = (image.width, image.height);
+    let mut gx = vec![0.0f64; w * h];
+    let mut gy = vec![0.0f64; w * h];
+    let mut magnitude = vec![0.0f64; w * h];
+    // Clamp-to-edge sampling so shapes touching the border still produce edges
+    let at = |x: isize, y: isize| image.at(x.clamp(0, w as isize - 1) as usize, y.clamp(0, h as isize - 1) as usize) as f64;
+    for y in 0..h as isize {
+        for x in 0..w as isize {
+            let dx = (at(x + 1, y - 1) + 2.0 * at(x + 1, y) + at(x + 1, y + 1)) - (at(x - 1, y - 1) + 2.0 * at(x - 1, y) + at(x - 1, y + 1));
+            let dy = (at(x - 1, y + 1) + 2.0 * at(x, y + 1) + at(x + 1, y + 1)) - (at(x - 1, y - 1) + 2.0 * at(x, y - 1) + at(x + 1, y - 1));
+            let i = y as usize * w + x as usize;
+            gx[i] = dx;
+            gy[i] = dy;
+            magnitude[i] = (dx * dx + dy * dy).sqrt();
+        }
+    }
+    let max = magnitude.iter().cloned().fold(0.0, f64::max);
+    if max == 0.0 {
+        return vec![false; w * h];
+    }
+    let cutoff = threshold as f64 / 255.0 * max;
+    let mut mask = vec![false; w * h];
+    for y in 0..h {
+        for x in 0..w {
+            let i = y * w + x;
+            if magnitude[i] < cutoff || magnitude[i] == 0.0 {
+                continue;
+            }
+            // Compare against both neighbours along the gradient direction
+            let angle = gy[i].atan2(gx[i]).to_degrees().rem_euclid(180.0);
+            let (dx, dy) = match angle {
+                a if !(22.5..157.5).contains(&a) => (1, 0),
+                a if a < 67.5 => (1, 1),
+                a if a < 112.5 => (0, 1),
+                _ => (-1, 1),
+            };
+            let neighbour = |dx: isize, dy: isize| image.offset(x, y, dx, dy).map_or(0.0, |(nx, ny)| magnitude[ny * w + nx]);
+            mask[i] = magnitude[i] >= neighbour(dx, dy) && magnitude[i] >= neighbour(-dx, -dy);
+        }
+    }
+    mask
+}
+
+/// Walk 8-connected mask pixels into chains (straight neighbours first to avoid staircases)
+fn link_chains(mask: &[bool], image: &LumaImage) -> Vec<Vec<(usize, usize)>> {
+    const NEIGHBOURS: [(isize, isize); 8] = [(1, 0), (0, 1), (-1, 0), (0, -1), (1, 1), (-1, 1), (-1, -1), (1, -1)];
+    let w = image.width;
+    let mut visited = vec![false; mask.len()];
+    let next = |visited: &[bool], (x, y): (usize, usize)| {
+        NEIGHBOURS.iter().find_map(|&(dx, dy)| {
+            image.offset(x, y, dx, dy).filter(|&(nx, ny)| mask[ny * w + nx] && !visited[ny * w + nx])
+        })
+    };
+
+    let mut chains = Vec::new();
+    for start in 0..mask.len() {
+        if !mask[start] || visited[start] {
+            continue;
+        }
+        visited[start] = true;
+        let origin = (start % w, start / w);
+        let mut halves = [vec![origin], Vec::new()];
+        for half in halves.iter_mut() {
+            let mut current = origin;
+            while let Some(pixel) = next(&visited, current) {
+                visited[pixel.1 * w + pixel.0] = true;
+                half.push(pixel);
+                current = pixel;
+            }
+        }
+        let [forward, mut chain] = halves;
+        chain.reverse();
+        chain.extend(forward);
+        chains.push(chain);
+    }
+    chains
+}
+
+/// A linked outline in image space
+struct Chain {
+    pixels: Vec<(usize, usize)>,
+    closed: bool,
+    /// Mean ink strength along the chain (0-255)
+    strength: f64,
+}
+
+impl Chain {
+    fn new(pixels: Vec<(usize, usize)>, image: &LumaImage, ink: &[u8]) -> Self {
+        let (first, last) = (pixels[0], pixels[pixels.len() - 1]);
+        let closed = pixels.len() >= MIN_CHAIN_PIXELS && first.0.abs_diff(last.0) <= 1 && first.1.abs_diff(last.1) <= 1;
+        // Edge pixels sit on the transition, so sample the inkiest pixel around each
+        let strength = pixels
+            .iter()
+            .map(|&(x, y)| {
+                (-1..=1)
+                    .flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+                    .filter_map(|(dx, dy)| image.offset(x, y, dx, dy))
+                    .map(|(nx, ny)| ink[ny * image.width + nx])
+                    .max()
+                    .unwrap_or(0) as f64
+            })
+            .sum::<f64>()
+            / pixels.len() as f64;
+        Chain { pixels, closed, strength }
+    }
+}
+
+/// Simplify every chain, raising the tolerance (and finally dropping the
+/// shortest paths) until the total segment count fits the budget.
+fn fit_budget(chains: &[Chain], image: &LumaImage, options: &TraceOptions) -> Vec<VecPath> {
+    let scale = 2.0 * options.size as f64 / image.width.max(image.height) as f64;
+    let to_vectrex = |&(x, y): &(usize, usize)| {
+        ((x as f64 + 0.5 - image.width as f64 / 2.0) * scale, (image.height as f64 / 2.0 - y as f64 - 0.5) * scale)
+    };
+    let budget = options.max_segments.max(1);
+
+    // Coarsen until the budget fits, but stop before small outlines collapse to points
+    let max_epsilon = (options.size as f64 / 8.0).max(options.tolerance);
+    let mut epsilon = options.tolerance.max(0.1);
+    let mut paths = loop {
+        let paths = chains
+            .iter()
+            .filter_map(|chain| {
+                let points: Vec<(f64, f64)> = chain.pixels.iter().map(to_vectrex).collect();
+                let intensity = MIN_INTENSITY + (chain.strength * (127 - MIN_INTENSITY) as f64 / 255.0).round() as u8;
+                make_path(&points, chain.closed, epsilon, intensity)
+            })
+            .collect::<Vec<_>>();
+        if total_segments(&paths) <= budget {
+            return paths;
+        }
+        if epsilon * 1.5 > max_epsilon {
+            break paths;
+        }
+        epsilon *= 1.5;
+    };
+
+    // Still too detailed: keep the longest outlines
+    paths.sort_by(|a, b| path_length(b).total_cmp(&path_length(a)));
+    let mut kept = Vec::new();
+    let mut used = 0;
+    for path in paths {
+        let segments = segment_count(&path);
+        if used + segments <= budget {
+            used += segments;
+            kept.push(path);
+        }
+    }
+    kept
+}
+
+/// Simplified, integer, Draw_Sync_List-safe path; None if it degenerates to a point
+fn make_path(points: &[(f64, f64)], closed: bool, epsilon: f64, intensity: u8) -> Option<VecPath> {
+    let simplified = if closed {
+        let mut ring = points.to_vec();
+        ring.push(points[0]);
+        let mut simplified = douglas_peucker(&ring, epsilon);
+        simplified.pop();
+        simplified
+    } else {
+        douglas_peucker(points, epsilon)
+    };
+
+    let mut out: Vec<Point> = Vec::with_capacity(simplified.len());
+    for (x, y) in simplified {
+        let p = Point { x: x.round() as i16, y: y.round() as i16, intensity: None };
+        if out.last().is_none_or(|q| (q.x, q.y) != (p.x, p.y)) {
+            out.push(p);
+        }
+    }
+    if closed && out.len() > 1 && (out[0].x, out[0].y) == (out[out.len() - 1].x, out[out.len() - 1].y) {
+        out.pop();
+    }
+    if out.len() < 2 {
+        return None;
+    }
+    let closed = closed && out.len() > 2;
+
+    // Split segments whose deltas don't fit in a signed byte
+    let mut split = Vec::with_capacity(out.len());
+    let count = out.len();
+    let edges = if closed { count } else { count - 1 };
+    split.push(out[0]);
+    for i in 0..edges {
+        let (a, b) = (out[i], out[(i + 1) % count]);
+        let (dx, dy) = (b.x as i32 - a.x as i32, b.y as i32 - a.y as i32);
+        let pieces = ((dx.abs().max(dy.abs()) + MAX_DELTA - 1) / MAX_DELTA).max(1);
+        for k in 1..=pieces {
+            if i + 1 == count && k == pieces {
+                break; // closing segment ends at the first point
+            }
+            split.push(Point { x: (a.x as i32 + dx * k / pieces) as i16, y: (a.y as i32 + dy * k / pieces) as i16, intensity: None });
+        }
+    }
+
+    Some(VecPath { name: String::new(), intensity, closed, points: split })
+}
+
+/// Iterative Douglas-Peucker; endpoints are always kept
+fn douglas_peucker(points: &[(f64, f64)], epsilon: f64) -> Vec<(f64, f64)> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+    let mut stack = vec![(0, points.len() - 1)];
+    while let Some((a, b)) = stack.pop() {
+        let (mut farthest, mut distance) = (a, 0.0);
+        for i in a + 1..b {
+            let d = line_distance(points[i], points[a], points[b]);
+            if d > distance {
+                farthest = i;
+                distance = d;
+            }
+        }
+        if distance > epsilon {
+            keep[farthest] = true;
+            stack.push((a, farthest));
+            stack.push((farthest, b));
+        }
+    }
+    points.iter().zip(keep).filter(|(_, k)| *k).map(|(p, _)| *p).collect()
+}
+
+/// Distance from `p` to the line through `a` and `b` (to `a` if they coincide)
+fn line_distance(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length = (dx * dx + dy * dy).sqrt();
+    if length < 1e-9 {
+        return ((p.0 - a.0).powi(2) + (p.1 - a.1).powi(2)).sqrt();
+    }
+    ((p.0 - a.0) * dy - (p.1 - a.1) * dx).abs() / length
+}
+
+fn segment_count(path: &VecPath) -> usize {
+    path.points.len() - 1 + path.closed as usize
+}
+
+/// Line segments drawn for `paths` (closing segments included)
+pub fn total_segments(paths: &[VecPath]) -> usize {
+    paths.iter().map(segment_count).sum()
+}
+
+fn path_length(path: &VecPath) -> f64 {
+    let closing = path.closed.then(|| (path.points[path.points.len() - 1], path.points[0]));
+    path.points
+        .windows(2)
+        .map(|w| (w[0], w[1]))
+        .chain(closing)
+        .map(|(a, b)| (((b.x - a.x) as f64).powi(2) + ((b.y - a.y) as f64).powi(2)).sqrt())
+        .sum()
+}
+
+/// Quantise path intensities into `levels` bands, one layer per band
+/// (brightest first), each ordered to minimise beam travel.
+fn intensity_layers(paths: Vec<VecPath>, levels: usize) -> Vec<Layer> {
+    let span = (128 - MIN_INTENSITY as usize) as f64;
+    let band_of = |intensity: u8| (((intensity.saturating_sub(MIN_INTENSITY)) as f64 / span * levels as f64) as usize).min(levels - 1);
+    let band_intensity = |band: usize| (MIN_INTENSITY as usize + (band + 1) * (127 - MIN_INTENSITY as usize) / levels) as u8;
+
+    let mut bands: Vec<Vec<VecPath>> = vec![Vec::new(); levels];
+    for mut path in paths {
+        let band = band_of(path.intensity);
+        path.intensity = band_intensity(band);
+        bands[band].push(path);
+    }
+
+    let orderer = GreedyThenTwoOpt::default();
+    let mut index = 0;
+    let mut layers = Vec::new();
+    for (band, paths) in bands.into_iter().enumerate().rev() {
+        if paths.is_empty() {
+            continue;
+        }
+        let mut paths = orderer.order(paths);
+        for path in &mut paths {
+            path.name = format!("trace{}", index);
+            index += 1;
+        }
+        layers.push(Layer { name: format!("intensity_{}", band_intensity(band)), visible: true, paths });
+    }
+    if layers.is_empty() {
+        layers.push(Layer { name: "default".to_string(), visible: true, paths: Vec::new() });
+    }
+    layers
+}
@@ -5,6 +5,7 @@ use anyhow::{bail, Result};
 #[derive(Debug, Clone, PartialEq)]
 pub enum TokenKind {
     Def, Identifier(String), Number(i32), Newline, Indent, Dedent,
+    Fixed(i32),  // Literal decimal (1.5) en formato 8.8 (valor crudo: 384)
     LParen, RParen, LBracket, RBracket, Colon, Comma, Dot,
     Plus, Minus, Star, Slash, Percent,
     SlashSlash,  // División entera //
@@ -23,6 +24,7 @@ pub enum TokenKind {
     From, Import, As, Export,
     // OOP
     Struct,
+    Enum,
     Self_,
     Eof,
 }
@@ -235,8 +237,18 @@ fn lex_line(line: &str, line_no: usize, out: &mut Vec<Token>) -> Result<()> {
                     out.push(tok(TokenKind::Number(num), line_no, start));
                 } else {
                     while idx < chars.len() && chars[idx].is_ascii_digit() { idx += 1; }
-                    let num: i32 = line[start..idx].parse().unwrap();
-                    out.push(tok(TokenKind::Number(num), line_no, start));
+                    if idx + 1 < chars.len() && chars[idx] == '.' && chars[idx + 1].is_ascii_digit() {
+                        // Decimal literal -> fixed 8.8 (round to nearest 1/256)
+                        idx += 1;
+                        while idx < chars.len() && chars[idx].is_ascii_digit() { idx += 1; }
+                        let value: f64 = line[start..idx].parse().unwrap();
+                        let raw = (value * 256.0).round();
+                        if raw >= 32768.0 { bail!("Fixed literal {} out of range (max 127.996) line {}", &line[start..idx], line_no); }
+                        out.push(tok(TokenKind::Fixed(raw as i32), line_no, start));
+                    } else {
+                        let num: i32 = line[start..idx].parse().unwrap();
+                        out.push(tok(TokenKind::Number(num), line_no, start));
+                    }
                 }
             }
             '"' => {
@@ -316,6 +328,7 @@ fn lex_line(line: &str, line_no: usize, out: &mut Vec<Token>) -> Result<()> {
                     "as" => TokenKind::As,
                     "export" => TokenKind::Export,
                     "struct" => TokenKind::Struct,
+                    "enum" => TokenKind::Enum,
                     "self" => TokenKind::Self_,
                     _ => TokenKind::Identifier(ident.to_string()),
                 };
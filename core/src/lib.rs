@@ -8,11 +8,23 @@ pub mod resolver; // Multi-file import resolution
 pub mod unifier;  // AST unification for multi-file projects
 pub mod library;  // VPy library system (.vpylib)
 pub mod vecres;   // Vector resource format (.vec)
+#[path = "../../buildtools/vpy_codegen/src/path_ordering.rs"]
+pub mod path_ordering; // Beam-travel path ordering for .vec paths (shared with vpy_codegen)
+pub mod img2vec;  // Bitmap → .vec tracer (img2vec)
+pub mod vecsvg;   // SVG import/export for .vec (svg2vec / vec2svg)
 pub mod musres;   // Music resource format (.vmus)
+#[path = "../../buildtools/vpy_codegen/src/music_pack.rs"]
+pub mod music_pack; // Packed .vmus register streams (shared with vpy_codegen)
+pub mod midi2vmus; // Standard MIDI File → .vmus import
+pub mod psg_synth; // Offline AY-3-8912 renderer (render-audio)
 pub mod sfxres;   // Sound effects resource format (.vsfx)
 pub mod levelres; // Level resource format (.vplay)
 pub mod vplay_analyzer; // Automatic .vplay analysis for dynamic buffer sizing
 pub mod struct_layout; // Struct layout computation (Phase 2)
+pub mod fixed_point; // 8.8 fixed-point type checking + lowering
+pub mod enums; // enum variants -> constants, switch exhaustiveness
+pub mod types; // optional static type annotations (u8/i16/bool)
+pub mod cycles; // static cycle estimates (build --cycles)
 // pub mod linker;   // VPy linker (disabled - missing bincode dependency)
 pub mod backend;
 // Legacy emulator module removed; use vectrex_emulator crate instead.
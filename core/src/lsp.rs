@@ -46,6 +46,7 @@ struct VariableUsage {
     declaration_range: Option<Range>,
     last_write_range: Option<Range>,
     is_const: bool,
+    ty: Option<crate::types::ScalarType>, // Annotated type (None = 2-byte word)
 }
 
 /// Complete analysis of all variables in a module
@@ -92,10 +93,14 @@ pub fn get_builtin_arity(func_name: &str) -> Option<AritySpec> {
         "DRAW_VECTOR" => Some(AritySpec::Exact(3)),            // asset_name, x, y
         "DRAW_VECTOR_EX" => Some(AritySpec::Exact(5)),         // asset_name, x, y, mirror, intensity
         "PLAY_MUSIC" => Some(AritySpec::Exact(1)),             // music asset (background, loops)
-        "PLAY_SFX" => Some(AritySpec::Exact(1)),               // sound effect (one-shot)
+        "PLAY_SFX" => Some(AritySpec::Variable(1)),            // sound effect (one-shot): name [, priority [, channel]]
         "STOP_MUSIC" => Some(AritySpec::Exact(0)),             // stop background music
         "MUSIC_UPDATE" => Some(AritySpec::Exact(0)),           // process music frame
         
+        // Corrutinas (funciones con `yield`)
+        "CO_START" => Some(AritySpec::Variable(1)),            // coroutine, args...
+        "CO_RESUME" | "CO_ALIVE" | "CO_STOP" => Some(AritySpec::Exact(1)), // coroutine
+        
         // Funciones de dibujo con intensidad explícita
         "DRAW_POLYGON" => Some(AritySpec::Variable(4)),         // n, intensity, x1, y1, ... (minimum 4: count + intensity + at least one point)
         "DRAW_CIRCLE" => Some(AritySpec::Exact(4)),             // x, y, r, intensity
@@ -660,6 +665,36 @@ fn compute_diagnostics(uri: &Url, text: &str, locale: &str) -> Vec<Diagnostic> {
                 
                 // Generate diagnostics for unused variables and const suggestions
                 generate_usage_diagnostics(&analysis, locale, &mut diags);
+
+                // Enums: unknown variants and switches that miss variants
+                let mut enum_diags = Vec::new();
+                crate::enums::resolve_module(&module, &mut enum_diags);
+                for d in enum_diags {
+                    let (severity, code) = match d.severity {
+                        crate::codegen::DiagnosticSeverity::Error => (DiagnosticSeverity::ERROR, "unknown-variant"),
+                        crate::codegen::DiagnosticSeverity::Warning => (DiagnosticSeverity::WARNING, "non-exhaustive-switch"),
+                    };
+                    diags.push(Diagnostic {
+                        range: line_to_range(d.line.unwrap_or(1)),
+                        severity: Some(severity),
+                        code: Some(NumberOrString::String(code.to_string())),
+                        source: Some("vpy".to_string()),
+                        message: d.message,
+                        ..Default::default()
+                    });
+                }
+
+                // Backends ARM: builtins que el target del .vpyproj no soporta
+                for d in target_diagnostics(uri, &module) {
+                    diags.push(Diagnostic {
+                        range: line_to_range(d.line.unwrap_or(1)),
+                        severity: Some(DiagnosticSeverity::ERROR),
+                        code: Some(NumberOrString::String("unsupported-builtin".to_string())),
+                        source: Some("vpy".to_string()),
+                        message: d.message,
+                        ..Default::default()
+                    });
+                }
                 
                 parsed_module = Some(module);
             }
@@ -885,12 +920,13 @@ fn analyze_variable_usage(module: &Module) -> UsageAnalysis {
     // Phase 1: Collect declarations from top-level items
     for item in &module.items {
         match item {
-            Item::GlobalLet { name, value, source_line } => {
+            Item::GlobalLet { name, value, source_line, type_annotation, .. } => {
                 let mut usage = VariableUsage {
                     declared: true,
                     initialized: value != &Expr::Number(0), // Simplified check
                     write_count: 1,
                     declaration_range: Some(line_to_range(*source_line)),
+                    ty: crate::types::annotation_type(type_annotation.as_deref()),
                     ..Default::default()
                 };
                 analysis.variables.insert(name.clone(), usage);
@@ -958,7 +994,7 @@ fn analyze_statements(stmts: &[Stmt], analysis: &mut UsageAnalysis) {
                 // Analyze reads in RHS expression
                 analyze_expr(value, analysis);
             },
-            Stmt::Let { name, value, source_line } => {
+            Stmt::Let { name, value, source_line, .. } => {
                 // Local variable declaration (inside function)
                 let usage = VariableUsage {
                     declared: true,
@@ -1088,12 +1124,50 @@ fn analyze_expr(expr: &Expr, analysis: &mut UsageAnalysis) {
         Expr::FieldAccess { target, .. } => {
             analyze_expr(target, analysis);
         },
-        Expr::Number(_) | Expr::StringLit(_) | Expr::StructInit { .. } => {
+        Expr::Number(_) | Expr::Fixed(_) | Expr::StringLit(_) | Expr::StructInit { .. } => {
             // Literals don't reference variables
         },
     }
 }
 
+/// Errors the backend of the project's target reports (only ARM targets can reject builtins)
+fn target_diagnostics(uri: &Url, module: &Module) -> Vec<crate::codegen::Diagnostic> {
+    use crate::target::Target;
+    let Some(project) = uri
+        .to_file_path()
+        .ok()
+        .and_then(|p| crate::project::find_project_file(p.parent()?))
+        .and_then(|p| crate::project::load_project(&p).ok())
+    else {
+        return Vec::new();
+    };
+    let target = match project.build.target.as_str() {
+        "pitrex" => Target::Pitrex,
+        "vecfever" => Target::Vecfever,
+        "vextreme" => Target::Vextreme,
+        _ => return Vec::new(),
+    };
+    let (_, backend_diags) = crate::codegen::emit_asm_with_diagnostics(module, target, &crate::codegen::CodegenOptions::default());
+    backend_diags
+        .into_iter()
+        .filter(|d| matches!(d.code, crate::codegen::DiagnosticCode::UnsupportedBuiltin) && d.severity == crate::codegen::DiagnosticSeverity::Error)
+        .collect()
+}
+
+/// Enums declarados en el texto (`enum Nombre: A, B, C`), también con código a medio escribir
+fn enum_definitions(text: &str) -> Vec<(String, Vec<String>)> {
+    text.lines().filter_map(|line| {
+        let rest = line.trim().strip_prefix("enum ")?;
+        let (name, variants) = rest.split_once(':')?;
+        let variants = variants.split('#').next().unwrap_or("")
+            .split(',')
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+            .collect();
+        Some((name.trim().to_string(), variants))
+    }).collect()
+}
+
 /// Helper to convert line number to LSP Range
 fn line_to_range(line: usize) -> Range {
     let pos = Position {
@@ -1109,6 +1183,48 @@ fn line_to_range(line: usize) -> Range {
     }
 }
 
+/// Locate the `build --cycles` report for a source file: next to it (default
+/// output), or in a project `build/` directory with a matching `source` name
+fn find_cycle_report(source: &std::path::Path) -> Option<crate::cycles::CycleReport> {
+    let load = |p: &std::path::Path| -> Option<crate::cycles::CycleReport> {
+        serde_json::from_str(&std::fs::read_to_string(p).ok()?).ok()
+    };
+    if let Some(r) = load(&source.with_extension("cycles.json")) { return Some(r); }
+    let file_name = source.file_name()?.to_string_lossy().into_owned();
+    let dir = source.parent()?;
+    for build in [dir.join("build"), dir.parent()?.join("build")] {
+        let Ok(entries) = std::fs::read_dir(&build) else { continue };
+        for e in entries.flatten() {
+            let path = e.path();
+            if !path.to_string_lossy().ends_with(".cycles.json") { continue; }
+            if let Some(r) = load(&path) {
+                if r.source == file_name { return Some(r); }
+            }
+        }
+    }
+    None
+}
+
+/// Code lenses: worst case per function, plus lines costing at least 1% of the frame
+fn cycle_lenses(report: &crate::cycles::CycleReport) -> Vec<CodeLens> {
+    let lens = |line: usize, title: String| CodeLens {
+        range: line_to_range(line),
+        command: Some(Command { title, command: String::new(), arguments: None }),
+        data: None,
+    };
+    let mut lenses: Vec<CodeLens> = report.functions.iter()
+        .map(|f| lens(f.line, format!("≈ {}", f.summary(report.frame_budget))))
+        .collect();
+    let def_lines: std::collections::HashSet<usize> = report.functions.iter().map(|f| f.line).collect();
+    for l in &report.lines {
+        if l.total * 100 >= report.frame_budget && !def_lines.contains(&l.line) {
+            let runs = if l.executions > 1 { format!(" ({} × {})", l.cycles, l.executions) } else { String::new() };
+            lenses.push(lens(l.line, format!("≈ {} cycles{}", l.total, runs)));
+        }
+    }
+    lenses
+}
+
 /// Generate diagnostics for variable usage patterns
 fn generate_usage_diagnostics(analysis: &UsageAnalysis, locale: &str, diags: &mut Vec<Diagnostic>) {
     for (name, usage) in &analysis.variables {
@@ -1144,10 +1260,11 @@ fn generate_usage_diagnostics(analysis: &UsageAnalysis, locale: &str, diags: &mu
            usage.read_count > 0 && 
            !usage.is_const {
             if let Some(range) = &usage.declaration_range {
+                let saved = match usage.ty.map_or(2, crate::types::ScalarType::size) { 1 => "1 byte", _ => "2 bytes" };
                 let msg = if locale.starts_with("es") {
-                    format!("Variable '{}' nunca cambia - considera 'const' para ahorrar RAM (2 bytes)", name)
+                    format!("Variable '{}' nunca cambia - considera 'const' para ahorrar RAM ({})", name, saved)
                 } else {
-                    format!("Variable '{}' never changes - consider 'const' to save RAM (2 bytes)", name)
+                    format!("Variable '{}' never changes - consider 'const' to save RAM ({})", name, saved)
                 };
                 
                 diags.push(Diagnostic {
@@ -1225,6 +1342,7 @@ impl LanguageServer for Backend {
                 full: Some(SemanticTokensFullOptions::Bool(true)), 
             })), 
             hover_provider: Some(HoverProviderCapability::Simple(true)), 
+            code_lens_provider: Some(CodeLensOptions { resolve_provider: Some(false) }), 
             definition_provider: Some(OneOf::Left(true)), 
             rename_provider: Some(OneOf::Left(true)), 
             signature_help_provider: Some(SignatureHelpOptions { 
@@ -1298,7 +1416,7 @@ impl LanguageServer for Backend {
             // Declaraciones y estructuras VPy
             "VECTORLIST","CONST","VAR","META","TITLE","MUSIC","COPYRIGHT",
             // Palabras clave de control
-            "def","for","while","if","switch",
+            "def","for","while","if","switch","enum","yield",
             // Import keywords
             "from","import","export","as"
         ];
@@ -1310,6 +1428,37 @@ impl LanguageServer for Backend {
         ];
         
         let mut items = Vec::new();
+
+        // Enums: tras `Nombre.` solo se ofrecen sus variantes
+        let enums = enum_definitions(&text);
+        let before_cursor: String = text.lines().nth(params.text_document_position.position.line as usize)
+            .unwrap_or("")
+            .chars()
+            .take(params.text_document_position.position.character as usize)
+            .collect();
+        let qualifier = before_cursor.trim_end_matches(|c: char| c.is_alphanumeric() || c == '_');
+        if let Some(prefix) = qualifier.strip_suffix('.') {
+            let enum_name = prefix.rsplit(|c: char| !(c.is_alphanumeric() || c == '_')).next().unwrap_or("");
+            if let Some((name, variants)) = enums.iter().find(|(name, _)| name == enum_name) {
+                for (value, variant) in variants.iter().enumerate() {
+                    items.push(CompletionItem {
+                        label: variant.clone(),
+                        kind: Some(CompletionItemKind::ENUM_MEMBER),
+                        detail: Some(format!("{}.{} = {}", name, variant, value)),
+                        ..Default::default()
+                    });
+                }
+                return Ok(Some(CompletionResponse::Array(items)));
+            }
+        }
+        for (name, variants) in &enums {
+            items.push(CompletionItem {
+                label: name.clone(),
+                kind: Some(CompletionItemKind::ENUM),
+                detail: Some(format!("enum {}: {}", name, variants.join(", "))),
+                ..Default::default()
+            });
+        }
         
         // Añadir funciones unificadas como Keywords
         for &name in &unified_items {
@@ -1393,7 +1542,7 @@ impl LanguageServer for Backend {
         Ok(Some(CompletionResponse::Array(items))) 
     }
     async fn semantic_tokens_full(&self, params: SemanticTokensParams) -> LspResult<Option<SemanticTokensResult>> {
-        let uri = params.text_document.uri; let docs = self.docs.lock().unwrap(); let text = match docs.get(&uri) { Some(t) => t.clone(), None => return Ok(None) }; drop(docs); let lines: Vec<&str> = text.lines().collect(); let mut data: Vec<SemanticToken> = Vec::new(); let mut defs: Vec<SymbolDef> = Vec::new(); if let Ok(tokens) = lex(&text) { const KEYWORD: u32 = 0; const FUNCTION: u32 = 1; const VARIABLE: u32 = 2; const NUMBER: u32 = 4; const STRING: u32 = 5; const OPERATOR: u32 = 6; const ENUM_MEMBER: u32 = 7; const MOD_READONLY: u32 = 1 << 0; const MOD_DECL: u32 = 1 << 1; const MOD_DEFAULT_LIB: u32 = 1 << 2; fn keyword_len(kind: &TokenKind) -> Option<usize> { Some(match kind { TokenKind::Def => 3, TokenKind::If => 2, TokenKind::Elif => 4, TokenKind::Else => 4, TokenKind::For => 3, TokenKind::In => 2, TokenKind::Range => 5, TokenKind::Return => 6, TokenKind::While => 5, TokenKind::Break => 5, TokenKind::Continue => 8, TokenKind::Const => 5, TokenKind::VectorList => 10, TokenKind::Switch => 6, TokenKind::Case => 4, TokenKind::Default => 7, TokenKind::Meta => 4, TokenKind::And => 3, TokenKind::Or => 2, TokenKind::Not => 3, TokenKind::True => 4, TokenKind::False => 5, _ => return None }) } let mut raw: Vec<(u32,u32,u32,u32,u32)> = Vec::new(); for (idx, tk) in tokens.iter().enumerate() { let line1 = tk.line; if line1 == 0 { continue; } let line0 = (line1 - 1) as u32; let line_str = lines.get(line0 as usize).copied().unwrap_or(""); let indent = line_str.chars().take_while(|c| *c==' ').count() as u32; let base_col = indent + tk.col as u32; match &tk.kind { k if keyword_len(k).is_some() => { let length = keyword_len(k).unwrap() as u32; raw.push((line0, base_col, length, KEYWORD, 0)); } TokenKind::Identifier(name) => { let mut is_after_def = false; if idx > 0 { let mut j = idx as isize - 1; while j >= 0 { match tokens[j as usize].kind { TokenKind::Newline | TokenKind::Indent | TokenKind::Dedent => { j -= 1; continue; } TokenKind::Def => { is_after_def = true; } _ => {} } break; } } let upper = name.to_ascii_uppercase(); let is_builtin = is_builtin_function(name); let is_constant = upper.starts_with("I_"); if is_after_def { raw.push((line0, base_col, name.len() as u32, FUNCTION, MOD_DECL)); defs.push(SymbolDef { name: name.clone(), uri: uri.clone(), range: Range { start: Position { line: line0, character: base_col }, end: Position { line: line0, character: base_col + name.len() as u32 } } }); } else if is_builtin { raw.push((line0, base_col, name.len() as u32, FUNCTION, MOD_DEFAULT_LIB)); } else if is_constant { raw.push((line0, base_col, name.len() as u32, ENUM_MEMBER, MOD_READONLY)); } else { raw.push((line0, base_col, name.len() as u32, VARIABLE, 0)); } } TokenKind::Number(_)=> { let slice = &line_str[(base_col as usize)..]; let mut len = 0; for ch in slice.chars() { if ch.is_ascii_hexdigit() || ch=='x'||ch=='X'||ch=='b'||ch=='B' { len+=1; } else { break; } } if len==0 { len=1; } raw.push((line0, base_col, len as u32, NUMBER, 0)); } TokenKind::StringLit(s) => { let length = (s.len()+2) as u32; raw.push((line0, base_col, length, STRING, 0)); } TokenKind::Plus|TokenKind::Minus|TokenKind::Star|TokenKind::Slash|TokenKind::Percent|TokenKind::Amp|TokenKind::Pipe|TokenKind::Caret|TokenKind::Tilde|TokenKind::Dot|TokenKind::Colon|TokenKind::Comma|TokenKind::Equal|TokenKind::Lt|TokenKind::Gt => { raw.push((line0, base_col, 1, OPERATOR, 0)); } TokenKind::ShiftLeft|TokenKind::ShiftRight|TokenKind::EqEq|TokenKind::NotEq|TokenKind::Le|TokenKind::Ge => { raw.push((line0, base_col, 2, OPERATOR, 0)); } _ => {} } } raw.sort_by(|a,b| a.0.cmp(&b.0).then(a.1.cmp(&b.1))); let mut last_line=0; let mut last_col=0; let mut first=true; for (line,col,length,ttype,mods) in raw { let delta_line = if first { line } else { line - last_line }; let delta_start = if first { col } else if delta_line==0 { col - last_col } else { col }; data.push(SemanticToken { delta_line, delta_start, length, token_type: ttype, token_modifiers_bitset: mods }); last_line=line; last_col=col; first=false; } } if !defs.is_empty() { SYMBOLS.lock().unwrap().retain(|d| d.uri != uri); SYMBOLS.lock().unwrap().extend(defs); } else { SYMBOLS.lock().unwrap().retain(|d| d.uri != uri); } Ok(Some(SemanticTokensResult::Tokens(SemanticTokens { result_id: None, data }))) }
+        let uri = params.text_document.uri; let docs = self.docs.lock().unwrap(); let text = match docs.get(&uri) { Some(t) => t.clone(), None => return Ok(None) }; drop(docs); let lines: Vec<&str> = text.lines().collect(); let mut data: Vec<SemanticToken> = Vec::new(); let mut defs: Vec<SymbolDef> = Vec::new(); if let Ok(tokens) = lex(&text) { const KEYWORD: u32 = 0; const FUNCTION: u32 = 1; const VARIABLE: u32 = 2; const NUMBER: u32 = 4; const STRING: u32 = 5; const OPERATOR: u32 = 6; const ENUM_MEMBER: u32 = 7; const MOD_READONLY: u32 = 1 << 0; const MOD_DECL: u32 = 1 << 1; const MOD_DEFAULT_LIB: u32 = 1 << 2; fn keyword_len(kind: &TokenKind) -> Option<usize> { Some(match kind { TokenKind::Def => 3, TokenKind::If => 2, TokenKind::Elif => 4, TokenKind::Else => 4, TokenKind::For => 3, TokenKind::In => 2, TokenKind::Range => 5, TokenKind::Return => 6, TokenKind::While => 5, TokenKind::Break => 5, TokenKind::Continue => 8, TokenKind::Const => 5, TokenKind::VectorList => 10, TokenKind::Switch => 6, TokenKind::Enum => 4, TokenKind::Case => 4, TokenKind::Default => 7, TokenKind::Meta => 4, TokenKind::And => 3, TokenKind::Or => 2, TokenKind::Not => 3, TokenKind::True => 4, TokenKind::False => 5, _ => return None }) } let mut raw: Vec<(u32,u32,u32,u32,u32)> = Vec::new(); for (idx, tk) in tokens.iter().enumerate() { let line1 = tk.line; if line1 == 0 { continue; } let line0 = (line1 - 1) as u32; let line_str = lines.get(line0 as usize).copied().unwrap_or(""); let indent = line_str.chars().take_while(|c| *c==' ').count() as u32; let base_col = indent + tk.col as u32; match &tk.kind { k if keyword_len(k).is_some() => { let length = keyword_len(k).unwrap() as u32; raw.push((line0, base_col, length, KEYWORD, 0)); } TokenKind::Identifier(name) => { let mut is_after_def = false; if idx > 0 { let mut j = idx as isize - 1; while j >= 0 { match tokens[j as usize].kind { TokenKind::Newline | TokenKind::Indent | TokenKind::Dedent => { j -= 1; continue; } TokenKind::Def => { is_after_def = true; } _ => {} } break; } } let upper = name.to_ascii_uppercase(); let is_builtin = is_builtin_function(name); let is_constant = upper.starts_with("I_"); if is_after_def { raw.push((line0, base_col, name.len() as u32, FUNCTION, MOD_DECL)); defs.push(SymbolDef { name: name.clone(), uri: uri.clone(), range: Range { start: Position { line: line0, character: base_col }, end: Position { line: line0, character: base_col + name.len() as u32 } } }); } else if is_builtin { raw.push((line0, base_col, name.len() as u32, FUNCTION, MOD_DEFAULT_LIB)); } else if is_constant { raw.push((line0, base_col, name.len() as u32, ENUM_MEMBER, MOD_READONLY)); } else { raw.push((line0, base_col, name.len() as u32, VARIABLE, 0)); } } TokenKind::Number(_)=> { let slice = &line_str[(base_col as usize)..]; let mut len = 0; for ch in slice.chars() { if ch.is_ascii_hexdigit() || ch=='x'||ch=='X'||ch=='b'||ch=='B' { len+=1; } else { break; } } if len==0 { len=1; } raw.push((line0, base_col, len as u32, NUMBER, 0)); } TokenKind::StringLit(s) => { let length = (s.len()+2) as u32; raw.push((line0, base_col, length, STRING, 0)); } TokenKind::Plus|TokenKind::Minus|TokenKind::Star|TokenKind::Slash|TokenKind::Percent|TokenKind::Amp|TokenKind::Pipe|TokenKind::Caret|TokenKind::Tilde|TokenKind::Dot|TokenKind::Colon|TokenKind::Comma|TokenKind::Equal|TokenKind::Lt|TokenKind::Gt => { raw.push((line0, base_col, 1, OPERATOR, 0)); } TokenKind::ShiftLeft|TokenKind::ShiftRight|TokenKind::EqEq|TokenKind::NotEq|TokenKind::Le|TokenKind::Ge => { raw.push((line0, base_col, 2, OPERATOR, 0)); } _ => {} } } raw.sort_by(|a,b| a.0.cmp(&b.0).then(a.1.cmp(&b.1))); let mut last_line=0; let mut last_col=0; let mut first=true; for (line,col,length,ttype,mods) in raw { let delta_line = if first { line } else { line - last_line }; let delta_start = if first { col } else if delta_line==0 { col - last_col } else { col }; data.push(SemanticToken { delta_line, delta_start, length, token_type: ttype, token_modifiers_bitset: mods }); last_line=line; last_col=col; first=false; } } if !defs.is_empty() { SYMBOLS.lock().unwrap().retain(|d| d.uri != uri); SYMBOLS.lock().unwrap().extend(defs); } else { SYMBOLS.lock().unwrap().retain(|d| d.uri != uri); } Ok(Some(SemanticTokensResult::Tokens(SemanticTokens { result_id: None, data }))) }
     async fn hover(&self, params: HoverParams) -> LspResult<Option<Hover>> {
         eprintln!("[vpy_lsp][hover] request pos= {:?} uri= {}", params.text_document_position_params.position, params.text_document_position_params.text_document.uri);
         let pos = params.text_document_position_params.position; let uri = params.text_document_position_params.text_document.uri; let docs = self.docs.lock().unwrap(); let text = match docs.get(&uri) { Some(t)=>t.clone(), None=>return Ok(None) }; drop(docs); let line = text.lines().nth(pos.line as usize).unwrap_or(""); let chars: Vec<char> = line.chars().collect(); if (pos.character as usize) > chars.len() { return Ok(None); } let mut start = pos.character as isize; let mut end = pos.character as usize; while start > 0 && (chars[(start-1) as usize].is_alphanumeric() || chars[(start-1) as usize]=='_') { start -= 1; } while end < chars.len() && (chars[end].is_alphanumeric() || chars[end]=='_') { end += 1; } if start as usize >= end { return Ok(None); } let word = &line[start as usize .. end]; let upper = word.to_ascii_uppercase(); let loc = self.locale.lock().unwrap().clone(); if let Some(doc) = builtin_doc(&loc, &upper) { return Ok(Some(Hover { contents: HoverContents::Markup(MarkupContent { kind: MarkupKind::Markdown, value: doc }), range: None })); } if let Some(def) = SYMBOLS.lock().unwrap().iter().find(|d| d.name == word && d.uri == uri) { let template = tr(&loc, "hover.user_function.line"); let value = template.replacen("{}", &def.name, 1).replacen("{}", &(def.range.start.line + 1).to_string(), 1); return Ok(Some(Hover { contents: HoverContents::Markup(MarkupContent { kind: MarkupKind::Markdown, value }), range: Some(def.range.clone()) })); } Ok(None) }
@@ -1435,6 +1584,12 @@ impl LanguageServer for Backend {
         Ok(None)
     }
 
+    async fn code_lens(&self, params: CodeLensParams) -> LspResult<Option<Vec<CodeLens>>> {
+        // Cycle estimates come from the last `vectrexc build --cycles`
+        let Ok(path) = params.text_document.uri.to_file_path() else { return Ok(None) };
+        Ok(find_cycle_report(&path).map(|r| cycle_lenses(&r)))
+    }
+
     async fn code_action(&self, params: CodeActionParams) -> LspResult<Option<CodeActionResponse>> {
         eprintln!("[vpy_lsp][code_action] request for uri= {}", params.text_document.uri);
         
@@ -8,15 +8,24 @@ mod resolver; // Multi-file import resolution
 mod unifier;  // AST unification for multi-file projects
 mod library;  // Library system
 mod vecres;   // Vector resources (.vec)
+mod vecsvg;   // SVG import/export for .vec resources
 mod musres;   // Music resources (.vmus)
+mod midi2vmus; // Standard MIDI File → .vmus import
+mod psg_synth; // Offline AY-3-8912 renderer (render-audio)
 mod sfxres;   // Sound effects resources (.vsfx)
 mod levelres; // Level resources (.vplay)
 mod struct_layout; // Struct layout computation
+mod fixed_point; // 8.8 fixed-point type checking + lowering
+mod enums; // enum variants -> constants, switch exhaustiveness
+mod types; // optional static type annotations (u8/i16/bool)
+mod cycles; // static cycle estimates (build --cycles)
 
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::collections::HashMap;
 use clap::{Parser, Subcommand};
+use vectrex_lang::img2vec; // Bitmap → .vec tracer (orders paths with path_ordering)
+use vectrex_lang::music_pack; // Packed .vmus register streams (musres, psg_synth)
 use toml;
 
 #[allow(dead_code)]
@@ -67,6 +76,7 @@ fn discover_assets(source_path: &Path) -> Vec<codegen::AssetInfo> {
                             name: name.to_string(),
                             path: path.display().to_string(),
                             asset_type: codegen::AssetType::Vector,
+                            priority: 0,
                         });
                     }
                 }
@@ -86,6 +96,7 @@ fn discover_assets(source_path: &Path) -> Vec<codegen::AssetInfo> {
                             name: name.to_string(),
                             path: path.display().to_string(),
                             asset_type: codegen::AssetType::Music,
+                            priority: 0,
                         });
                     }
                 }
@@ -105,6 +116,7 @@ fn discover_assets(source_path: &Path) -> Vec<codegen::AssetInfo> {
                             name: name.to_string(),
                             path: path.display().to_string(),
                             asset_type: codegen::AssetType::Sfx,
+                            priority: sfxres::SfxResource::load(&path).map(|r| r.priority).unwrap_or(0),
                         });
                     }
                 }
@@ -124,6 +136,7 @@ fn discover_assets(source_path: &Path) -> Vec<codegen::AssetInfo> {
                             name: name.to_string(),
                             path: path.display().to_string(),
                             asset_type: codegen::AssetType::Level,
+                            priority: 0,
                         });
                     }
                 }
@@ -165,6 +178,7 @@ enum Commands {
         #[arg(short = 'p', long, help="Compilar proyecto .vpyproj (ignora -f si se especifica)")] project: bool,
         #[arg(short = 'f', long, help="Compilar archivo .vpy individual (default)")] file: bool,
         #[arg(long = "include-dir", help="Directorio con archivos include (VECTREX.I, etc)")] include_dir: Option<PathBuf>,
+        #[arg(long, help="Estimate worst-case CPU cycles per function and per line (also writes <out>.cycles.json)")] cycles: bool,
     },
     Lex { input: PathBuf },
     Ast { input: PathBuf },
@@ -194,6 +208,99 @@ enum Commands {
         #[arg(short, long)]
         out: Option<PathBuf>,
     },
+    /// Trace a bitmap (PNG/JPEG/WebP) into a vector resource (.vec)
+    #[command(name = "img2vec")]
+    Img2Vec {
+        /// Input image
+        input: PathBuf,
+        /// Output .vec file (default: same name with .vec extension)
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+        /// Tracing method
+        #[arg(long, value_enum, default_value = "contours")]
+        mode: img2vec::TraceMode,
+        /// Contours: luminance cut-off (default: automatic). Edges: minimum edge strength 0-255 (default 64)
+        #[arg(long)]
+        threshold: Option<u8>,
+        /// Trace light shapes on a dark background
+        #[arg(long)]
+        invert: bool,
+        /// Maximum number of line segments
+        #[arg(long, default_value = "200")]
+        segments: usize,
+        /// Douglas-Peucker tolerance in Vectrex units
+        #[arg(long, default_value = "1.5")]
+        tolerance: f64,
+        /// Fit the image into ±size Vectrex units
+        #[arg(long, default_value = "127")]
+        size: i16,
+        /// Intensity bands (one layer each)
+        #[arg(long, default_value = "3")]
+        levels: usize,
+        /// Longest image side after downscaling, in pixels
+        #[arg(long, default_value = "256")]
+        resolution: u32,
+    },
+    /// Export a vector resource (.vec) as SVG, one file per animation frame
+    #[command(name = "vec2svg")]
+    Vec2Svg {
+        /// Input .vec file
+        input: PathBuf,
+        /// Output .svg file, or output directory for animated resources (default: next to the input)
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+    },
+    /// Import an SVG drawing into a vector resource (.vec)
+    #[command(name = "svg2vec")]
+    Svg2Vec {
+        /// Input .svg file
+        input: PathBuf,
+        /// Output .vec file (default: same name with .vec extension)
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+        /// Curve flattening tolerance in Vectrex units
+        #[arg(long, default_value = "0.5")]
+        tolerance: f64,
+        /// Fit the larger viewBox side to this many Vectrex units (default: keep SVG units, scaling down to 256 if larger)
+        #[arg(long)]
+        size: Option<u16>,
+    },
+    /// Import a Standard MIDI File (type 0/1) as a music resource (.vmus)
+    #[command(name = "midi2vmus")]
+    Midi2Vmus {
+        /// Input .mid file
+        input: PathBuf,
+        /// Output .vmus file (default: same name with .vmus extension)
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+        /// Tracks played on PSG channels A, B and C, numbered as listed (default: the first three)
+        #[arg(long, value_delimiter = ',')]
+        tracks: Option<Vec<usize>>,
+        /// Ticks per beat of the resource
+        #[arg(long, default_value = "24")]
+        ticks_per_beat: u16,
+        /// Quantisation step in resource ticks (1 = off)
+        #[arg(long, default_value = "6")]
+        grid: u16,
+        /// PSG channel that plays the percussion noise
+        #[arg(long, value_enum, default_value = "c")]
+        noise_channel: midi2vmus::PsgChannel,
+    },
+    /// Render a music (.vmus) or sound effect (.vsfx) resource to a 44.1 kHz WAV
+    #[command(name = "render-audio")]
+    RenderAudio {
+        /// Input .vmus or .vsfx file
+        input: PathBuf,
+        /// Output .wav file (default: same name with .wav extension)
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+        /// Times a looping song is played through
+        #[arg(long, default_value = "1")]
+        loops: usize,
+        /// Stop after this many seconds
+        #[arg(long, default_value = "300")]
+        max_seconds: u32,
+    },
     /// Create a new vector resource
     #[command(name = "vec-new")]
     VecNew {
@@ -209,12 +316,13 @@ enum Commands {
 fn main() -> Result<()> {
     let cli = Cli::parse();
     match cli.command {
-        Commands::Build { input, out, target, title, bin, use_lwasm, dual, project, file: _, include_dir } => {
+        Commands::Build { input, out, target, title, bin, use_lwasm, dual, project, file: _, include_dir, cycles } => {
+            let flags = BuildFlags { bin, use_lwasm, dual, cycles };
             // Si -p está especificado o el input es .vpyproj, compilar como proyecto
             if project || input.extension().and_then(|e| e.to_str()) == Some("vpyproj") {
-                build_project_cmd(&input, bin, use_lwasm, dual, include_dir.as_ref())
+                build_project_cmd(&input, flags, include_dir.as_ref())
             } else {
-                build_cmd(&input, out.as_ref(), target, &title, bin, use_lwasm, dual, include_dir.as_ref(), None)
+                build_cmd(&input, out.as_ref(), target, &title, flags, include_dir.as_ref(), None)
             }
         },
         Commands::Lex { input } => lex_cmd(&input),
@@ -223,6 +331,26 @@ fn main() -> Result<()> {
         Commands::Init { name, path } => init_cmd(&name, path.as_ref()),
         Commands::Vec2Asm { input, out } => vec2asm_cmd(&input, out.as_ref()),
         Commands::VecNew { name, path } => vec_new_cmd(&name, path.as_ref()),
+        Commands::Img2Vec { input, out, mode, threshold, invert, segments, tolerance, size, levels, resolution } => {
+            let options = img2vec::TraceOptions { mode, threshold, invert, max_segments: segments, tolerance, size, levels, resolution };
+            img2vec_cmd(&input, out.as_ref(), &options)
+        }
+        Commands::Vec2Svg { input, out } => vec2svg_cmd(&input, out.as_ref()),
+        Commands::Svg2Vec { input, out, tolerance, size } => {
+            svg2vec_cmd(&input, out.as_ref(), &vecsvg::SvgImportOptions { tolerance, size })
+        }
+        Commands::RenderAudio { input, out, loops, max_seconds } => {
+            render_audio_cmd(&input, out.as_ref(), &psg_synth::RenderOptions { loops, max_seconds })
+        }
+        Commands::Midi2Vmus { input, out, tracks, ticks_per_beat, grid, noise_channel } => {
+            // Listed numbers start at 1
+            if tracks.as_ref().is_some_and(|t| t.contains(&0)) {
+                return Err(anyhow::anyhow!("track numbers start at 1"));
+            }
+            let tracks = tracks.map(|t| t.into_iter().map(|n| n - 1).collect());
+            let options = midi2vmus::MidiImportOptions { ticks_per_beat, grid, tracks, noise_channel };
+            midi2vmus_cmd(&input, out.as_ref(), &options)
+        }
     }
 }
 
@@ -336,6 +464,135 @@ fn vec2asm_cmd(input: &PathBuf, out: Option<&PathBuf>) -> Result<()> {
     Ok(())
 }
 
+// img2vec_cmd: trace a bitmap into a .vec resource
+fn img2vec_cmd(input: &PathBuf, out: Option<&PathBuf>, options: &img2vec::TraceOptions) -> Result<()> {
+    eprintln!("Tracing image: {:?} ({:?})", input, options.mode);
+    
+    let image = img2vec::load_image(input, options.resolution, options.invert)?;
+    let name = input.file_stem().and_then(|n| n.to_str()).unwrap_or("traced");
+    let resource = img2vec::trace(&image, name, options);
+    
+    let output_path = out.cloned().unwrap_or_else(|| input.with_extension("vec"));
+    resource.save(&output_path)?;
+    
+    let paths: Vec<_> = resource.layers.iter().flat_map(|l| l.paths.iter().cloned()).collect();
+    eprintln!("✓ Generated: {:?}", output_path);
+    eprintln!("  Layers: {}, Paths: {}, Points: {}, Segments: {}/{}",
+        resource.layers.len(), paths.len(), resource.point_count(), img2vec::total_segments(&paths), options.max_segments);
+    
+    Ok(())
+}
+
+// vec2svg_cmd: export a .vec resource as SVG (one file per animation frame)
+fn vec2svg_cmd(input: &PathBuf, out: Option<&PathBuf>) -> Result<()> {
+    eprintln!("Exporting vector resource: {:?}", input);
+    
+    let resource = vecres::VecResource::load(input)?;
+    let documents = vecsvg::export_svg(&resource);
+    let stem = input.file_stem().and_then(|n| n.to_str()).unwrap_or("vector");
+    
+    for (suffix, svg) in &documents {
+        let output_path = match out {
+            Some(path) if documents.len() == 1 && path.extension().is_some() => path.clone(),
+            Some(dir) => {
+                std::fs::create_dir_all(dir)?;
+                dir.join(format!("{}{}.svg", stem, suffix))
+            }
+            None => input.with_file_name(format!("{}{}.svg", stem, suffix)),
+        };
+        std::fs::write(&output_path, svg)?;
+        eprintln!("✓ Generated: {:?}", output_path);
+    }
+    eprintln!("  Documents: {}, Layers: {}, Points: {}", documents.len(), resource.layers.len(), resource.point_count());
+    
+    Ok(())
+}
+
+// svg2vec_cmd: import an SVG drawing as a .vec resource
+fn svg2vec_cmd(input: &PathBuf, out: Option<&PathBuf>, options: &vecsvg::SvgImportOptions) -> Result<()> {
+    eprintln!("Importing SVG: {:?}", input);
+    
+    let svg = fs::read_to_string(input)?;
+    let name = input.file_stem().and_then(|n| n.to_str()).unwrap_or("vector");
+    let resource = vecsvg::import_svg(&svg, name, options)?;
+    
+    let output_path = out.cloned().unwrap_or_else(|| input.with_extension("vec"));
+    resource.save(&output_path)?;
+    
+    let paths = resource.layers.iter().map(|l| l.paths.len()).sum::<usize>();
+    eprintln!("✓ Generated: {:?}", output_path);
+    eprintln!("  Layers: {}, Paths: {}, Points: {}", resource.layers.len(), paths, resource.point_count());
+    let out_of_range = resource.layers.iter()
+        .flat_map(|l| l.paths.iter())
+        .flat_map(|p| p.points.iter())
+        .filter(|p| p.x.abs() > 127 || p.y.abs() > 127)
+        .count();
+    if out_of_range > 0 {
+        eprintln!("  ⚠ {} points lie outside ±127; use --size to scale the drawing down", out_of_range);
+    }
+    
+    Ok(())
+}
+
+// midi2vmus_cmd: import a Standard MIDI File as a .vmus resource
+fn midi2vmus_cmd(input: &PathBuf, out: Option<&PathBuf>, options: &midi2vmus::MidiImportOptions) -> Result<()> {
+    eprintln!("Importing MIDI: {:?}", input);
+    
+    let bytes = fs::read(input)?;
+    let name = input.file_stem().and_then(|n| n.to_str()).unwrap_or("music");
+    let import = midi2vmus::import_midi(&bytes, name, options)?;
+    
+    for (i, track) in import.tracks.iter().enumerate() {
+        let target = match track.psg_channel {
+            Some(channel) => format!("→ {:?}", channel),
+            None => "(not mapped)".to_string(),
+        };
+        eprintln!("  {}. {:<20} ch {:>2}  {:>4} notes  {}", i + 1, track.name, track.channel + 1, track.notes, target);
+    }
+    if import.drum_hits > 0 {
+        eprintln!("  Percussion: {} hits → noise on {:?}", import.drum_hits, options.noise_channel);
+    }
+    
+    let output_path = out.cloned().unwrap_or_else(|| input.with_extension("vmus"));
+    import.resource.save(&output_path)?;
+    
+    eprintln!("✓ Generated: {:?}", output_path);
+    eprintln!("  Tempo: {} BPM, Notes: {}, Noise: {}, Length: {} ticks",
+        import.resource.tempo, import.resource.notes.len(), import.resource.noise.len(), import.resource.total_ticks);
+    for warning in &import.warnings {
+        eprintln!("  ⚠ {}", warning);
+    }
+    
+    Ok(())
+}
+
+// render_audio_cmd: play a resource's compiled PSG stream into a WAV file
+fn render_audio_cmd(input: &PathBuf, out: Option<&PathBuf>, options: &psg_synth::RenderOptions) -> Result<()> {
+    eprintln!("Rendering audio: {:?}", input);
+    
+    let rendering = match input.extension().and_then(|e| e.to_str()) {
+        Some(musres::VMUS_EXTENSION) => {
+            let resource = musres::MusicResource::load(input)?;
+            let name = input.file_stem().and_then(|n| n.to_str()).unwrap_or("music");
+            psg_synth::render_music(&resource, name, options)?
+        }
+        Some(sfxres::VSFX_EXTENSION) => psg_synth::render_sfx(&sfxres::SfxResource::load(input)?, options)?,
+        _ => return Err(anyhow::anyhow!("expected a .vmus or .vsfx file")),
+    };
+    
+    let output_path = out.cloned().unwrap_or_else(|| input.with_extension("wav"));
+    psg_synth::write_wav(&output_path, &rendering.samples)?;
+    
+    eprintln!("✓ Generated: {:?}", output_path);
+    eprintln!("  Frames: {} ({:.2} s at {} Hz), Register writes: {}",
+        rendering.frames, rendering.seconds(), psg_synth::FRAME_RATE, rendering.writes);
+    if !rendering.finished {
+        eprintln!("  ⚠ Stopped after {} s before the stream ended", options.max_seconds);
+    }
+    
+    Ok(())
+}
+
 // vec_new_cmd: create a new .vec resource
 fn vec_new_cmd(name: &str, path: Option<&PathBuf>) -> Result<()> {
     let base_path = path.cloned().unwrap_or_else(|| std::env::current_dir().unwrap());
@@ -366,7 +623,7 @@ fn vec_new_cmd(name: &str, path: Option<&PathBuf>) -> Result<()> {
 }
 
 // build_project_cmd: compile a .vpyproj project file
-fn build_project_cmd(project_path: &PathBuf, bin: bool, use_lwasm: bool, dual: bool, include_dir: Option<&PathBuf>) -> Result<()> {
+fn build_project_cmd(project_path: &PathBuf, flags: BuildFlags, include_dir: Option<&PathBuf>) -> Result<()> {
     eprintln!("=== PROJECT COMPILATION START ===");
     eprintln!("Project file: {}", project_path.display());
     
@@ -432,11 +689,42 @@ fn build_project_cmd(project_path: &PathBuf, bin: bool, use_lwasm: bool, dual: b
     });
     
     // Call regular build_cmd with project-resolved paths and output name
-    build_cmd(&entry_file, output_path.as_ref(), target, title, bin, use_lwasm, dual, include_dir, output_name.as_deref())
+    build_cmd(&entry_file, output_path.as_ref(), target, title, flags, include_dir, output_name.as_deref())
+}
+
+// BuildFlags: opciones de `build` que no dependen del proyecto
+#[derive(Clone, Copy)]
+struct BuildFlags {
+    bin: bool,       // ensamblar también el .bin
+    use_lwasm: bool, // lwasm externo en vez del ensamblador nativo
+    dual: bool,      // ensamblar con ambos y comparar
+    cycles: bool,    // informe estático de ciclos (--cycles)
+}
+
+/// Print the size of each music stream in the ROM and what the packed player costs
+fn report_music(asm: &str, assets: &[codegen::AssetInfo]) {
+    for asset in assets {
+        let symbol = asset.name.to_uppercase().replace(['-', ' '], "_");
+        if !asm.contains(&format!("_{}_MUSIC:", symbol)) {
+            continue;
+        }
+        let Ok(resource) = musres::MusicResource::load(Path::new(&asset.path)) else {
+            continue;
+        };
+        let stats = resource.pack_stats();
+        if resource.compression == musres::MusicCompression::Packed {
+            eprintln!("  Music {}: {} bytes packed ({} plain, {:.0}%), player {} cycles/frame max, {} average",
+                asset.name, stats.packed_bytes, stats.plain_bytes, stats.ratio() * 100.0, stats.max_cycles, stats.avg_cycles);
+        } else {
+            eprintln!("  Music {}: {} bytes (\"compression\": \"packed\" would take {}, {} cycles/frame max)",
+                asset.name, stats.plain_bytes, stats.packed_bytes, stats.max_cycles);
+        }
+    }
 }
 
 // build_cmd: run full pipeline (lex/parse/opt/codegen) and write assembly.
-fn build_cmd(path: &PathBuf, out: Option<&PathBuf>, tgt: target::Target, title: &str, bin: bool, use_lwasm: bool, dual: bool, include_dir: Option<&PathBuf>, output_name: Option<&str>) -> Result<()> {
+fn build_cmd(path: &PathBuf, out: Option<&PathBuf>, tgt: target::Target, title: &str, flags: BuildFlags, include_dir: Option<&PathBuf>, output_name: Option<&str>) -> Result<()> {
+    let BuildFlags { bin, use_lwasm, dual, cycles } = flags;
     eprintln!("=== COMPILATION PIPELINE START ===");
     eprintln!("Input file: {}", path.display());
     eprintln!("Target: {:?}", tgt);
@@ -581,6 +869,7 @@ fn build_cmd(path: &PathBuf, out: Option<&PathBuf>, tgt: target::Target, title:
                 mutable_arrays: std::collections::BTreeSet::new(), // Will be populated by backend
                 structs: std::collections::HashMap::new(), // Empty registry for non-struct code
                 type_context: std::collections::HashMap::new(), // Empty type context for non-struct code
+                global_types: std::collections::HashMap::new(), // Filled from annotations by codegen
                 buffer_requirements: buffer_requirements.as_ref().map(|r| codegen::BufferRequirements {
                     max_physics_objects: r.max_physics_objects,
                     needs_buffer: r.needs_buffer,
@@ -588,7 +877,7 @@ fn build_cmd(path: &PathBuf, out: Option<&PathBuf>, tgt: target::Target, title:
                 }),
             });
                 let base = path.file_stem().unwrap().to_string_lossy();
-                let out_path = out.cloned().unwrap_or_else(|| path.with_file_name(format!("{}-{}.asm", base, ct)));
+                let out_path = out.cloned().unwrap_or_else(|| path.with_file_name(format!("{}-{}.{}", base, ct, target::info(*ct).asm_ext)));
                 fs::write(&out_path, &asm)?;
                 eprintln!("Generated: {} (target={})", out_path.display(), ct);
             // fast_wait desactivado en modo minimal
@@ -602,6 +891,10 @@ fn build_cmd(path: &PathBuf, out: Option<&PathBuf>, tgt: target::Target, title:
         // Phase 0: Asset discovery
         eprintln!("Phase 0: Asset discovery...");
         let assets = discover_assets(&path);
+        let music_assets: Vec<codegen::AssetInfo> = assets.iter()
+            .filter(|a| a.asset_type == codegen::AssetType::Music)
+            .cloned()
+            .collect();
         
         // Phase 0.5: .vplay analysis for buffer sizing
         eprintln!("Phase 0.5: Analyzing .vplay files for dynamic buffer sizing...");
@@ -661,6 +954,7 @@ fn build_cmd(path: &PathBuf, out: Option<&PathBuf>, tgt: target::Target, title:
             mutable_arrays: std::collections::BTreeSet::new(), // Will be populated by backend
             structs: std::collections::HashMap::new(), // Will be populated by emit_asm_with_debug
             type_context: std::collections::HashMap::new(), // Will be populated during semantic validation
+            global_types: std::collections::HashMap::new(),
             buffer_requirements: buffer_requirements.as_ref().map(|r| codegen::BufferRequirements {
                 max_physics_objects: r.max_physics_objects,
                 needs_buffer: r.needs_buffer,
@@ -711,7 +1005,7 @@ fn build_cmd(path: &PathBuf, out: Option<&PathBuf>, tgt: target::Target, title:
         
         // Phase 5: Write ASM file
         eprintln!("Phase 5: Writing assembly file...");
-        let out_path = out.cloned().unwrap_or_else(|| path.with_extension("asm"));
+        let out_path = out.cloned().unwrap_or_else(|| path.with_extension(target::info(tgt).asm_ext));
         fs::write(&out_path, &asm).map_err(|e| {
             eprintln!("❌ PHASE 5 FAILED: Cannot write assembly file");
             eprintln!("   Output path: {}", out_path.display());
@@ -719,6 +1013,21 @@ fn build_cmd(path: &PathBuf, out: Option<&PathBuf>, tgt: target::Target, title:
             e
         })?;
         eprintln!("✓ Phase 5 SUCCESS: Written to {} (target={})", out_path.display(), tgt);
+        report_music(&asm, &music_assets);
+        
+        // Phase 5.2: Static cycle estimate (if requested)
+        if cycles {
+            if matches!(target::info(tgt).arch, target::CpuArch::M6809) {
+                let mut report = cycles::estimate(&final_module, &asm);
+                report.source = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+                println!("{}", cycles::format_report(&report));
+                let json_path = out_path.with_extension("cycles.json");
+                fs::write(&json_path, serde_json::to_string_pretty(&report)?)?;
+                eprintln!("✓ Phase 5.2 SUCCESS: Cycle report written to {}", json_path.display());
+            } else {
+                eprintln!("⚠ Warning: --cycles is only available for 6809 targets (target={})", tgt);
+            }
+        }
         
         // Phase 5.5: Write .pdb file if debug info available
         let mut debug_info_mut = debug_info;
@@ -935,7 +1244,16 @@ fn build_cmd(path: &PathBuf, out: Option<&PathBuf>, tgt: target::Target, title:
                 }
                 // Replace entire functions map with corrected entries
                 dbg.functions = corrected_functions;
-                
+
+                // Register VPy functions at their REAL entry addresses (used by profilers/debuggers)
+                for (name, label, start_line, end_line) in vpy_function_labels(&final_module) {
+                    let address = dbg.symbols.get(&label)
+                        .and_then(|addr| u16::from_str_radix(addr.trim_start_matches("0x"), 16).ok());
+                    if let Some(address) = address {
+                        dbg.add_function(name, address, start_line, end_line, "vpy");
+                    }
+                }
+
                 eprintln!("✓ Updated functions: {} functions", dbg.functions.len());
                 eprintln!("✓ Phase 6.6 SUCCESS: LineMap generation complete");
                 
@@ -967,6 +1285,36 @@ fn build_cmd(path: &PathBuf, out: Option<&PathBuf>, tgt: target::Target, title:
     }
 }
 
+/// VPy functions with the ASM label of their entry point: (name, label, start_line, end_line).
+/// main() is emitted inline after START and loop() as LOOP_BODY.
+fn vpy_function_labels(module: &ast::Module) -> Vec<(String, String, usize, usize)> {
+    fn entry(f: &ast::Function, name: String) -> (String, String, usize, usize) {
+        let label = match name.as_str() {
+            "main" => "START".to_string(),
+            "loop" => "LOOP_BODY".to_string(),
+            _ => name.to_uppercase(),
+        };
+        let end_line = f.body.last().map(|s| s.source_line()).unwrap_or(f.line).max(f.line);
+        (name, label, f.line, end_line)
+    }
+    let mut out = Vec::new();
+    for item in &module.items {
+        match item {
+            ast::Item::Function(f) => out.push(entry(f, f.name.clone())),
+            ast::Item::StructDef(s) => {
+                for m in &s.methods {
+                    out.push(entry(m, format!("{}_{}", s.name, m.name)));
+                }
+                if let Some(init) = &s.constructor {
+                    out.push(entry(init, format!("{}_INIT", s.name)));
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
 fn assemble_bin(asm_path: &PathBuf, use_lwasm: bool, include_dir: Option<&PathBuf>) -> Result<(HashMap<String, u16>, HashMap<usize, usize>, u16)> {
     let bin_path = asm_path.with_extension("bin");
     eprintln!("=== BINARY ASSEMBLY PHASE ===");
@@ -0,0 +1,513 @@
+//! Standard MIDI File import for music resources (`vectrexc midi2vmus`)
+//!
+//! Reads type 0 and type 1 SMFs and builds a `.vmus` resource. Melodic notes
+//! are grouped into tracks (one per MIDI track and channel pair, so a type 0
+//! file yields one track per channel) and up to three of them are mapped onto
+//! PSG channels A/B/C. General MIDI percussion (channel 10) becomes noise
+//! events. Times are rescaled to `ticks_per_beat` and snapped to a grid,
+//! velocities to PSG volumes 0-15. Each PSG channel plays one note at a time,
+//! so chords and overlapping notes are thinned and reported as warnings.
+
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{bail, Result};
+use clap::ValueEnum;
+
+use crate::musres::{MusicResource, NoiseEvent, NoteEvent};
+
+/// MIDI channel reserved for percussion in General MIDI (channel 10)
+const DRUM_CHANNEL: u8 = 9;
+/// PSG voices available for tones
+const VOICES: usize = 3;
+/// Songs are padded to a whole 4/4 bar
+const BEATS_PER_BAR: u32 = 4;
+/// Polyphony warnings listed before summarising the rest
+const MAX_LISTED: usize = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum PsgChannel {
+    A,
+    B,
+    C,
+}
+
+impl PsgChannel {
+    /// Bit in `NoiseEvent::channels`
+    pub fn mask(self) -> u8 {
+        match self {
+            PsgChannel::A => 1,
+            PsgChannel::B => 2,
+            PsgChannel::C => 4,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MidiImportOptions {
+    /// Ticks per beat of the resulting resource
+    pub ticks_per_beat: u16,
+    /// Quantisation step in resource ticks (1 keeps every position)
+    pub grid: u16,
+    /// Tracks (indices into `MidiImport::tracks`) played on A, B and C.
+    /// Default: the first three melodic tracks.
+    pub tracks: Option<Vec<usize>>,
+    /// PSG channel the noise generator is mixed into for percussion
+    pub noise_channel: PsgChannel,
+}
+
+impl Default for MidiImportOptions {
+    fn default() -> Self {
+        Self {
+            ticks_per_beat: 24,
+            grid: 6,
+            tracks: None,
+            noise_channel: PsgChannel::C,
+        }
+    }
+}
+
+/// A melodic note stream found in the file
+#[derive(Clone, Debug, PartialEq)]
+pub struct MidiTrack {
+    /// Track chunk index in the file
+    pub track: usize,
+    /// MIDI channel (0-15)
+    pub channel: u8,
+    /// Sequence/track name meta event, if any
+    pub name: String,
+    pub notes: usize,
+    /// PSG channel it was mapped to, if any
+    pub psg_channel: Option<PsgChannel>,
+}
+
+#[derive(Clone, Debug)]
+pub struct MidiImport {
+    pub resource: MusicResource,
+    /// Every melodic track, in file order
+    pub tracks: Vec<MidiTrack>,
+    /// Percussion notes turned into noise events
+    pub drum_hits: usize,
+    pub warnings: Vec<String>,
+}
+
+/// Parsed Standard MIDI File
+#[derive(Clone, Debug)]
+struct Smf {
+    /// Ticks per quarter note
+    division: u16,
+    tracks: Vec<TrackChunk>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct TrackChunk {
+    name: String,
+    notes: Vec<Span>,
+    /// (tick, microseconds per quarter note)
+    tempos: Vec<(u32, u32)>,
+}
+
+/// A note from its note-on to its note-off, in file ticks
+#[derive(Clone, Copy, Debug)]
+struct Span {
+    channel: u8,
+    key: u8,
+    velocity: u8,
+    start: u32,
+    end: u32,
+}
+
+/// Import a Standard MIDI File
+pub fn import_midi(bytes: &[u8], name: &str, options: &MidiImportOptions) -> Result<MidiImport> {
+    if options.ticks_per_beat == 0 || options.grid == 0 {
+        bail!("ticks per beat and grid must be at least 1");
+    }
+    let smf = parse_smf(bytes)?;
+    let mut warnings = Vec::new();
+
+    // Melodic tracks in file order, percussion apart
+    let mut streams: BTreeMap<(usize, u8), Vec<Span>> = BTreeMap::new();
+    let mut drums = Vec::new();
+    for (index, chunk) in smf.tracks.iter().enumerate() {
+        for span in &chunk.notes {
+            if span.channel == DRUM_CHANNEL {
+                drums.push(*span);
+            } else {
+                streams.entry((index, span.channel)).or_default().push(*span);
+            }
+        }
+    }
+    let mut tracks: Vec<MidiTrack> = streams
+        .iter()
+        .map(|(&(track, channel), spans)| MidiTrack {
+            track,
+            channel,
+            name: smf.tracks[track].name.clone(),
+            notes: spans.len(),
+            psg_channel: None,
+        })
+        .collect();
+
+    let mapping = match &options.tracks {
+        Some(selected) => {
+            if selected.len() > VOICES {
+                bail!("at most {} tracks can be mapped (PSG channels A/B/C), got {}", VOICES, selected.len());
+            }
+            for (i, &index) in selected.iter().enumerate() {
+                if selected[..i].contains(&index) {
+                    bail!("melodic track {} is mapped twice", index + 1);
+                }
+                if index >= tracks.len() {
+                    bail!("there is no melodic track {} (the file has {})", index + 1, tracks.len());
+                }
+            }
+            selected.clone()
+        }
+        None => (0..tracks.len().min(VOICES)).collect(),
+    };
+    for (i, &index) in mapping.iter().enumerate() {
+        tracks[index].psg_channel = Some([PsgChannel::A, PsgChannel::B, PsgChannel::C][i]);
+    }
+    for track in tracks.iter().filter(|t| t.psg_channel.is_none()) {
+        warnings.push(format!("{} not mapped to a PSG channel: {} notes dropped", describe(track), track.notes));
+    }
+
+    let tempo = tempo_bpm(&smf, &mut warnings);
+    let time = TimeScale { division: smf.division as u64, ticks_per_beat: options.ticks_per_beat as u64, grid: options.grid as u64 };
+
+    // All melodic notes, mapped or not, count towards polyphony
+    let all_melodic: Vec<(u32, u32)> = streams.values().flatten().map(|s| time.span(s)).collect();
+    check_polyphony(&all_melodic, options.ticks_per_beat, &mut warnings);
+
+    let keys: Vec<(usize, u8)> = streams.keys().copied().collect();
+    let mut notes = Vec::new();
+    for (i, &index) in mapping.iter().enumerate() {
+        let spans = &streams[&keys[index]];
+        let (voice, dropped) = monophonic(spans, &time);
+        if dropped > 0 {
+            warnings.push(format!(
+                "{}: {} chord notes dropped (channel {:?} plays one note at a time)",
+                describe(&tracks[index]), dropped, tracks[index].psg_channel.unwrap()
+            ));
+        }
+        for (n, (span, start, end)) in voice.into_iter().enumerate() {
+            notes.push(NoteEvent {
+                id: format!("{:?}{}", tracks[index].psg_channel.unwrap(), n + 1),
+                note: span.key,
+                start,
+                duration: end - start,
+                velocity: velocity_to_volume(span.velocity),
+                channel: i as u8,
+                envelope: None,
+                buzzer: false,
+            });
+        }
+    }
+
+    drums.sort_by_key(|s| (s.start, s.key));
+    let noise: Vec<NoiseEvent> = drums
+        .iter()
+        .enumerate()
+        .map(|(n, span)| {
+            let (start, end) = time.span(span);
+            NoiseEvent {
+                id: format!("N{}", n + 1),
+                start,
+                duration: end - start,
+                period: drum_noise_period(span.key),
+                channels: options.noise_channel.mask(),
+                velocity: velocity_to_volume(span.velocity),
+            }
+        })
+        .collect();
+
+    notes.sort_by_key(|n| (n.start, n.channel));
+    let last = notes.iter().map(|n| n.start + n.duration)
+        .chain(noise.iter().map(|n| n.start + n.duration))
+        .max()
+        .unwrap_or(0);
+    let bar = options.ticks_per_beat as u32 * BEATS_PER_BAR;
+    let total_ticks = last.div_ceil(bar).max(1) * bar;
+
+    let mut resource = MusicResource::new(name);
+    resource.tempo = tempo;
+    resource.ticks_per_beat = options.ticks_per_beat;
+    resource.total_ticks = total_ticks;
+    resource.loop_start = 0;
+    resource.loop_end = total_ticks;
+    resource.notes = notes;
+    resource.noise = noise;
+
+    Ok(MidiImport { resource, tracks, drum_hits: drums.len(), warnings })
+}
+
+/// "track 2 'Bass' (channel 3)" with 1-based numbers, as a DAW shows them
+fn describe(track: &MidiTrack) -> String {
+    if track.name.is_empty() {
+        format!("track {} (channel {})", track.track + 1, track.channel + 1)
+    } else {
+        format!("track {} '{}' (channel {})", track.track + 1, track.name, track.channel + 1)
+    }
+}
+
+/// MIDI velocity 1-127 to PSG volume 1-15 (0 stays silent)
+pub fn velocity_to_volume(velocity: u8) -> u8 {
+    if velocity == 0 {
+        return 0;
+    }
+    ((velocity.min(127) as u16 * 15 + 63) / 127).max(1) as u8
+}
+
+/// Noise period (0-31, lower = brighter) for a General MIDI percussion key
+pub fn drum_noise_period(key: u8) -> u8 {
+    match key {
+        35 | 36 => 28,                          // bass drums
+        37..=40 => 12,                          // side stick, snares, hand clap
+        42 | 44 => 1,                           // closed/pedal hi-hat
+        46 => 2,                                // open hi-hat
+        41 | 43 | 45 | 47 | 48 | 50 => {        // toms, low to high
+            28 - (key - 41) * 2
+        }
+        49 | 51..=53 | 55 | 57 | 59 => 4,       // crash, ride, splash, china
+        _ => 8,
+    }
+}
+
+/// File ticks → quantised resource ticks
+struct TimeScale {
+    division: u64,
+    ticks_per_beat: u64,
+    grid: u64,
+}
+
+impl TimeScale {
+    fn tick(&self, tick: u32) -> u32 {
+        let scaled = tick as u64 * self.ticks_per_beat;
+        // Round to the nearest grid step
+        let steps = (scaled + self.division * self.grid / 2) / (self.division * self.grid);
+        (steps * self.grid) as u32
+    }
+
+    /// Quantised (start, end); a note never shrinks below one grid step
+    fn span(&self, span: &Span) -> (u32, u32) {
+        let start = self.tick(span.start);
+        let end = self.tick(span.end).max(start + self.grid as u32);
+        (start, end)
+    }
+}
+
+/// One note at a time: at equal starts the highest note wins (melodies sit
+/// on top of chords), and a note is cut where the next one begins
+fn monophonic(spans: &[Span], time: &TimeScale) -> (Vec<(Span, u32, u32)>, usize) {
+    let mut quantised: Vec<(Span, u32, u32)> = spans.iter().map(|s| {
+        let (start, end) = time.span(s);
+        (*s, start, end)
+    }).collect();
+    quantised.sort_by(|a, b| a.1.cmp(&b.1).then(b.0.key.cmp(&a.0.key)));
+
+    let mut voice: Vec<(Span, u32, u32)> = Vec::new();
+    let mut dropped = 0;
+    for note in quantised {
+        match voice.last_mut() {
+            Some(prev) if prev.1 == note.1 => dropped += 1,
+            Some(prev) => {
+                prev.2 = prev.2.min(note.1);
+                voice.push(note);
+            }
+            None => voice.push(note),
+        }
+    }
+    (voice, dropped)
+}
+
+/// Warn where more notes sound at once than the PSG has voices
+fn check_polyphony(spans: &[(u32, u32)], ticks_per_beat: u16, warnings: &mut Vec<String>) {
+    let mut edges: Vec<(u32, i32)> = spans.iter()
+        .flat_map(|&(start, end)| [(start, 1), (end, -1)])
+        .collect();
+    // Ends before starts at the same tick: back-to-back notes do not overlap
+    edges.sort();
+
+    let mut sounding = 0;
+    let mut peaks: Vec<(u32, i32)> = Vec::new();
+    for (tick, delta) in edges {
+        sounding += delta;
+        if delta > 0 && sounding > VOICES as i32 {
+            match peaks.last_mut() {
+                Some(last) if last.0 == tick => last.1 = last.1.max(sounding),
+                _ => peaks.push((tick, sounding)),
+            }
+        }
+    }
+    for (tick, voices) in peaks.iter().take(MAX_LISTED) {
+        warnings.push(format!(
+            "{} notes sound at once at beat {} (tick {}); the PSG has {} voices",
+            voices, tick / ticks_per_beat as u32 + 1, tick, VOICES
+        ));
+    }
+    if peaks.len() > MAX_LISTED {
+        warnings.push(format!("... and {} more places with more than {} voices", peaks.len() - MAX_LISTED, VOICES));
+    }
+}
+
+/// First tempo of the song, in BPM (120 when the file sets none)
+fn tempo_bpm(smf: &Smf, warnings: &mut Vec<String>) -> u16 {
+    let mut tempos: Vec<(u32, u32)> = smf.tracks.iter().flat_map(|t| t.tempos.iter().copied()).collect();
+    tempos.sort();
+    tempos.dedup_by_key(|t| t.1);
+    let Some(&(_, micros)) = tempos.first() else {
+        return 120;
+    };
+    if tempos.len() > 1 {
+        warnings.push(format!("{} tempo changes ignored; the resource has a single tempo", tempos.len() - 1));
+    }
+    (60_000_000.0 / micros.max(1) as f64).round().clamp(1.0, u16::MAX as f64) as u16
+}
+
+/// Parse the header and the track chunks of a Standard MIDI File
+fn parse_smf(bytes: &[u8]) -> Result<Smf> {
+    let mut reader = Reader { bytes, pos: 0 };
+    if reader.take(4)? != b"MThd" {
+        bail!("not a Standard MIDI File (missing MThd header)");
+    }
+    let header_len = reader.u32()? as usize;
+    if header_len < 6 {
+        bail!("MIDI header too short ({} bytes)", header_len);
+    }
+    let format = reader.u16()?;
+    let count = reader.u16()?;
+    let division = reader.u16()?;
+    reader.take(header_len - 6)?;
+
+    if format > 1 {
+        bail!("MIDI format {} is not supported (only type 0 and type 1)", format);
+    }
+    if division & 0x8000 != 0 {
+        bail!("SMPTE time division is not supported (only ticks per quarter note)");
+    }
+    if division == 0 {
+        bail!("MIDI header has zero ticks per quarter note");
+    }
+
+    let mut tracks = Vec::new();
+    while tracks.len() < count as usize && reader.pos < bytes.len() {
+        let id = reader.take(4)?;
+        let len = reader.u32()? as usize;
+        let data = reader.take(len)?;
+        // Unknown chunk types must be skipped
+        if id == b"MTrk" {
+            tracks.push(parse_track(data).map_err(|e| anyhow::anyhow!("track {}: {}", tracks.len() + 1, e))?);
+        }
+    }
+    if tracks.len() < count as usize {
+        bail!("header announces {} tracks but the file holds {}", count, tracks.len());
+    }
+    Ok(Smf { division, tracks })
+}
+
+fn parse_track(data: &[u8]) -> Result<TrackChunk> {
+    let mut reader = Reader { bytes: data, pos: 0 };
+    let mut chunk = TrackChunk::default();
+    // Sounding notes per (channel, key), oldest first
+    let mut open: HashMap<(u8, u8), Vec<(u32, u8)>> = HashMap::new();
+    let mut tick = 0u32;
+    let mut running = None;
+
+    while reader.pos < data.len() {
+        tick = tick.saturating_add(reader.vlq()?);
+        let mut status = reader.u8()?;
+        if status < 0x80 {
+            // Running status: reuse the last channel status, this byte is data
+            status = running.ok_or_else(|| anyhow::anyhow!("running status without a previous event"))?;
+            reader.pos -= 1;
+        }
+        match status {
+            0xFF => {
+                let kind = reader.u8()?;
+                let len = reader.vlq()? as usize;
+                let body = reader.take(len)?;
+                match kind {
+                    0x03 if chunk.name.is_empty() => chunk.name = String::from_utf8_lossy(body).trim().to_string(),
+                    0x51 if len == 3 => {
+                        chunk.tempos.push((tick, u32::from_be_bytes([0, body[0], body[1], body[2]])));
+                    }
+                    0x2F => break,
+                    _ => {}
+                }
+            }
+            0xF0 | 0xF7 => {
+                let len = reader.vlq()? as usize;
+                reader.take(len)?;
+            }
+            0x80..=0xEF => {
+                running = Some(status);
+                let channel = status & 0x0F;
+                let data1 = reader.u8()?;
+                let data2 = if matches!(status & 0xF0, 0xC0 | 0xD0) { 0 } else { reader.u8()? };
+                match status & 0xF0 {
+                    0x90 if data2 > 0 => open.entry((channel, data1)).or_default().push((tick, data2)),
+                    0x80 | 0x90 => {
+                        let sounding = open.entry((channel, data1)).or_default();
+                        if !sounding.is_empty() {
+                            let (start, velocity) = sounding.remove(0);
+                            chunk.notes.push(Span { channel, key: data1, velocity, start, end: tick });
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            other => bail!("unexpected status byte ${:02X}", other),
+        }
+    }
+
+    // Notes never released last until the end of the track
+    for ((channel, key), sounding) in open {
+        for (start, velocity) in sounding {
+            chunk.notes.push(Span { channel, key, velocity, start, end: tick });
+        }
+    }
+    chunk.notes.sort_by_key(|n| (n.start, n.channel, n.key));
+    Ok(chunk)
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.pos + len > self.bytes.len() {
+            bail!("unexpected end of MIDI data");
+        }
+        let slice = &self.bytes[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let b = self.take(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let b = self.take(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    /// Variable-length quantity (at most four bytes)
+    fn vlq(&mut self) -> Result<u32> {
+        let mut value = 0u32;
+        for _ in 0..4 {
+            let byte = self.u8()?;
+            value = (value << 7) | (byte & 0x7F) as u32;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        bail!("variable-length quantity longer than four bytes")
+    }
+}
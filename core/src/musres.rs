@@ -7,6 +7,8 @@ use std::path::Path;
 use serde::{Deserialize, Serialize};
 use anyhow::Result;
 
+use crate::music_pack::{pack, plain_size, RegisterTimeline, TimelineEnd};
+
 /// Music resource file extension
 pub const VMUS_EXTENSION: &str = "vmus";
 
@@ -46,6 +48,42 @@ pub struct MusicResource {
     #[serde(default)]
     #[serde(rename = "loopEnd")]
     pub loop_end: u32,
+    /// Stream encoding in ROM
+    #[serde(default, skip_serializing_if = "MusicCompression::is_none")]
+    pub compression: MusicCompression,
+}
+
+/// How the compiled register stream is stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MusicCompression {
+    /// `FCB delay, count, (reg, val)*` per frame
+    #[default]
+    None,
+    /// Changed registers only, with back-references (see `music_pack`)
+    Packed,
+}
+
+impl MusicCompression {
+    fn is_none(&self) -> bool {
+        *self == MusicCompression::None
+    }
+}
+
+/// Sizes of both encodings of a song and the packed player's cost
+#[derive(Debug, Clone, Copy)]
+pub struct PackStats {
+    pub plain_bytes: usize,
+    pub packed_bytes: usize,
+    pub max_cycles: u32,
+    pub avg_cycles: u32,
+}
+
+impl PackStats {
+    /// Packed size as a fraction of the plain size
+    pub fn ratio(&self) -> f32 {
+        self.packed_bytes as f32 / self.plain_bytes.max(1) as f32
+    }
 }
 
 fn default_version() -> String {
@@ -75,6 +113,46 @@ pub struct NoteEvent {
     pub velocity: u8,
     /// PSG channel (0=A, 1=B, 2=C)
     pub channel: u8,
+    /// Drive the volume from the hardware envelope instead of `velocity`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub envelope: Option<HardwareEnvelope>,
+    /// Buzzer bass: the envelope is the waveform (tone off, envelope period
+    /// follows the note's pitch, shape from `envelope` or 8)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub buzzer: bool,
+}
+
+/// PSG hardware envelope (registers 11-13). There is a single generator,
+/// so every channel in envelope mode shares it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HardwareEnvelope {
+    /// Shape (0-15): 8 = sawtooth down, 10 = triangle, 12 = sawtooth up,
+    /// 13 = attack and hold, 0 = decay to silence
+    pub shape: u8,
+    /// Period (R11 low, R12 high): one ramp lasts 256 * period PSG clocks
+    #[serde(default)]
+    pub period: u16,
+}
+
+impl HardwareEnvelope {
+    /// Envelope period that makes a repeating shape sound at the given MIDI
+    /// note: triangles (10, 14) take two ramps per cycle, sawtooths one
+    pub fn period_for_note(midi: u8, shape: u8) -> u16 {
+        let freq_hz = 440.0 * 2.0_f64.powf((midi as f64 - 69.0) / 12.0);
+        let ramps = if shape & 0x0E == 0x0A { 512.0 } else { 256.0 };
+        ((1_500_000.0 / (ramps * freq_hz)).round() as u16).max(1)
+    }
+}
+
+impl NoteEvent {
+    /// Envelope this note plays with, if it uses the hardware envelope
+    pub fn hardware_envelope(&self) -> Option<HardwareEnvelope> {
+        if self.buzzer {
+            let shape = self.envelope.map_or(8, |e| e.shape & 0x0F);
+            return Some(HardwareEnvelope { shape, period: HardwareEnvelope::period_for_note(self.note, shape) });
+        }
+        self.envelope.map(|e| HardwareEnvelope { shape: e.shape & 0x0F, period: e.period })
+    }
 }
 
 /// A noise event (for percussion/effects)
@@ -125,6 +203,7 @@ impl MusicResource {
             noise: Vec::new(),
             loop_start: 0,
             loop_end: 384,
+            compression: MusicCompression::None,
         }
     }
     
@@ -152,9 +231,23 @@ impl MusicResource {
         asm.push_str(&format!("; Generated from {}.vmus (internal name: {})\n", asset_name, self.name));
         asm.push_str(&format!("; Tempo: {} BPM, Total events: {} (PSG Direct format)\n", 
             self.tempo, self.notes.len() + self.noise.len()));
-        asm.push_str("; Format: FCB count, FCB reg, val, ... (per frame), FCB 0 (end)\n");
+        let timeline = self.register_timeline();
+        let packed = (self.compression == MusicCompression::Packed).then(|| pack(&timeline, &symbol_name));
+        match &packed {
+            Some(packed) => asm.push_str(&format!("; Format: packed, {} bytes ({} plain), player {} cycles/frame max\n",
+                packed.bytes, plain_size(&timeline), packed.max_cycles)),
+            None => asm.push_str("; Format: FCB count, FCB reg, val, ... (per frame), FCB 0 (end)\n"),
+        }
+        if self.notes.iter().any(|n| n.envelope.is_some() || n.buzzer) {
+            asm.push_str("; Hardware envelope: one generator, the highest channel using it wins\n");
+        }
         asm.push_str("\n");
         
+        if let Some(packed) = packed {
+            asm.push_str(&packed.asm);
+            return asm;
+        }
+        
         asm.push_str(&format!("_{}_MUSIC:\n", symbol_name));
         
         // PSG Direct format (inspired by Christman2024):
@@ -163,6 +256,83 @@ impl MusicResource {
         
         asm.push_str("    ; Frame-based PSG register writes\n");
         
+        // Emit only frames where something changes, with a delay counter for unchanged frames
+        let mut last_emitted_frame = 0u32;
+        for (frame, writes) in &timeline.frames {
+            let frames_since_last = frame - last_emitted_frame;
+            
+            // ALWAYS emit delay counter first (0 for first frame, >0 for subsequent)
+            // This makes format consistent: FCB delay, FCB count, pairs...
+            asm.push_str(&format!("    FCB     {}              ; Delay {} frames (maintain previous state)\n",
+                frames_since_last, frames_since_last));
+            
+            // Emit frame data (number of register writes)
+            asm.push_str(&format!("    FCB     {}              ; Frame {} - {} register writes\n", 
+                writes.len(), frame, writes.len()));
+            for (reg, val) in writes {
+                // CRITICAL FIX: Generate TWO separate FCB statements per register write
+                // Old format "FCB 0,$59" generates [00, 59] but UPDATE_MUSIC_PSG reads pairs
+                // New format generates separate bytes for register number and value
+                asm.push_str(&format!("    FCB     {}               ; Reg {} number\n", reg, reg));
+                asm.push_str(&format!("    FCB     ${:02X}             ; Reg {} value\n", val, reg));
+            }
+            last_emitted_frame = *frame;
+        }
+        
+        match timeline.end {
+            TimelineEnd::Loop(loop_end_frame) => {
+                // Calculate delay until loop point (how many frames to wait after last change)
+                let frames_until_loop = loop_end_frame.saturating_sub(last_emitted_frame);
+                
+                // The player spends a frame on the loop command itself, so wait
+                // one frame less to keep every pass loop_end ticks long
+                if frames_until_loop > 1 {
+                    // Emit delay before loop marker to maintain last note duration
+                    asm.push_str(&format!("    FCB     {}              ; Delay {} frames before loop\n",
+                        frames_until_loop - 1, frames_until_loop - 1));
+                }
+                
+                // Loop marker: FCB $FF (special value that can't be a frame count), FDB address
+                // Using absolute address instead of relying on PSG_MUSIC_START memory variable
+                asm.push_str(&format!("    FCB     $FF             ; Loop command ($FF never valid as count)\n"));
+                asm.push_str(&format!("    FDB     _{}_MUSIC       ; Jump to start (absolute address)\n\n", symbol_name));
+            }
+            TimelineEnd::Stop(last_frame) => {
+                let frames_until_end = last_frame.saturating_sub(last_emitted_frame);
+                asm.push_str(&format!("    FCB     {}              ; Delay {} frames (last notes end)\n",
+                    frames_until_end, frames_until_end));
+                asm.push_str("    FCB     3               ; Release - 3 register writes\n");
+                for reg in 8..=10 {
+                    asm.push_str(&format!("    FCB     {}               ; Reg {} number\n", reg, reg));
+                    asm.push_str(&format!("    FCB     $00             ; Reg {} value\n", reg));
+                }
+                
+                // End marker: a frame with delay 0 and count 0 (the player reads
+                // the delay byte first, so a lone 0 would take the next byte as count)
+                asm.push_str("    FCB     0               ; Delay 0 frames\n");
+                asm.push_str("    FCB     0               ; End of music (count 0, no loop)\n\n");
+            }
+        }
+        
+        asm
+    }
+    
+    /// Plain and packed sizes of the compiled stream, and the packed player's cost
+    pub fn pack_stats(&self) -> PackStats {
+        let timeline = self.register_timeline();
+        let packed = pack(&timeline, "STATS");
+        PackStats {
+            plain_bytes: plain_size(&timeline),
+            packed_bytes: packed.bytes,
+            max_cycles: packed.max_cycles,
+            avg_cycles: packed.avg_cycles,
+        }
+    }
+    
+    /// Register writes per frame, as the player issues them: a frame is listed
+    /// only when its writes differ from the previous one (or it restarts the
+    /// envelope)
+    pub fn register_timeline(&self) -> RegisterTimeline {
         // Convert ticks to frames
         // Vectrex runs at 50Hz (PAL standard, not 60Hz NTSC)
         // ticks_per_second = tempo * ticks_per_beat / 60 (tempo is BPM)
@@ -198,7 +368,7 @@ impl MusicResource {
         
         // Track last emitted state to detect changes
         let mut last_reg_writes: Vec<(u8, u8)> = Vec::new();
-        let mut last_emitted_frame = 0u32; // Track when we last emitted data
+        let mut frames = Vec::new();
         
         // Process frames - emit only when something changes, add delay counter for unchanged frames
         while current_frame <= max_frame {
@@ -273,10 +443,15 @@ impl MusicResource {
             }
             
             // Generate register writes for active NOTE channels
+            let mut envelope: Option<(HardwareEnvelope, bool)> = None; // (envelope, note starts now)
             for (ch_idx, maybe_note) in chan_data.iter().enumerate() {
                 if let Some(note) = maybe_note {
                     let period = Self::midi_to_psg_period(note.note);
-                    let volume = note.velocity.min(15);
+                    let mut volume = note.velocity.min(15);
+                    if let Some(env) = note.hardware_envelope() {
+                        volume = 0x10; // Volume mode bit: level comes from the envelope
+                        envelope = Some((env, tick_to_frame(note.start) == current_frame));
+                    }
                     
                     // Frequency registers (2 per channel: low 8 bits, high 4 bits)
                     let reg_lo = (ch_idx * 2) as u8;
@@ -302,9 +477,9 @@ impl MusicResource {
             // Bits 3-5: noise enable (0=on, 1=off) for channels A,B,C
             let mut mixer = 0xFF; // Start with all disabled
             
-            // Enable tones for active note channels
+            // Enable tones for active note channels (a buzzer's waveform is the envelope)
             for (ch_idx, maybe_note) in chan_data.iter().enumerate() {
-                if maybe_note.is_some() {
+                if maybe_note.is_some_and(|n| !n.buzzer) {
                     mixer &= !(1 << ch_idx); // Enable tone for this channel (clear bit 0-2)
                 }
             }
@@ -340,32 +515,25 @@ impl MusicResource {
             // Write mixer register
             reg_writes.push((7, mixer));
             
+            // Envelope period every frame, shape only when a note starts:
+            // writing R13 restarts the envelope
+            let mut trigger = Vec::new();
+            if let Some((env, starts)) = envelope {
+                reg_writes.push((11, (env.period & 0xFF) as u8));
+                reg_writes.push((12, (env.period >> 8) as u8));
+                if starts {
+                    trigger.push((13, env.shape));
+                }
+            }
+            
             // Check if state changed compared to last frame
-            let state_changed = reg_writes != last_reg_writes;
+            let state_changed = reg_writes != last_reg_writes || !trigger.is_empty();
             
             if state_changed {
-                // Calculate how many frames to wait before applying this change
-                let frames_since_last = current_frame - last_emitted_frame;
-                
-                // ALWAYS emit delay counter first (0 for first frame, >0 for subsequent)
-                // This makes format consistent: FCB delay, FCB count, pairs...
-                asm.push_str(&format!("    FCB     {}              ; Delay {} frames (maintain previous state)\n",
-                    frames_since_last, frames_since_last));
-                
-                // Emit frame data (number of register writes)
-                asm.push_str(&format!("    FCB     {}              ; Frame {} - {} register writes\n", 
-                    reg_writes.len(), current_frame, reg_writes.len()));
-                for (reg, val) in &reg_writes {
-                    // CRITICAL FIX: Generate TWO separate FCB statements per register write
-                    // Old format "FCB 0,$59" generates [00, 59] but UPDATE_MUSIC_PSG reads pairs
-                    // New format generates separate bytes for register number and value
-                    asm.push_str(&format!("    FCB     {}               ; Reg {} number\n", reg, reg));
-                    asm.push_str(&format!("    FCB     ${:02X}             ; Reg {} value\n", val, reg));
-                }
-                
-                // Update last state
+                let mut writes = reg_writes.clone();
+                writes.extend(trigger);
+                frames.push((current_frame, writes));
                 last_reg_writes = reg_writes;
-                last_emitted_frame = current_frame;
             }
             
             // Check if we should continue BEFORE incrementing frame
@@ -380,30 +548,22 @@ impl MusicResource {
             }
         }
         
-        // Loop or end marker
         let loop_start_frame = tick_to_frame(self.loop_start);
         let loop_end_frame = tick_to_frame(self.loop_end);
-        
-        if loop_start_frame < loop_end_frame && loop_end_frame > 0 {
-            // Calculate delay until loop point (how many frames to wait after last change)
-            let frames_until_loop = loop_end_frame.saturating_sub(last_emitted_frame);
-            
-            if frames_until_loop > 0 {
-                // Emit delay before loop marker to maintain last note duration
-                asm.push_str(&format!("    FCB     {}              ; Delay {} frames before loop\n",
-                    frames_until_loop, frames_until_loop));
-            }
-            
-            // Loop marker: FCB $FF (special value that can't be a frame count), FDB address
-            // Using absolute address instead of relying on PSG_MUSIC_START memory variable
-            asm.push_str(&format!("    FCB     $FF             ; Loop command ($FF never valid as count)\n"));
-            asm.push_str(&format!("    FDB     _{}_MUSIC       ; Jump to start (absolute address)\n\n", symbol_name));
+        let end = if loop_start_frame < loop_end_frame && loop_end_frame > 0 {
+            TimelineEnd::Loop(loop_end_frame)
         } else {
-            // End marker (count=0, no more frames)
-            asm.push_str("    FCB     0               ; End of music (no loop)\n\n");
-        }
+            // Silence all channels when the last event ends (the loop above
+            // stops before emitting that frame)
+            let last_frame = self.notes.iter().map(|n| n.start + n.duration)
+                .chain(self.noise.iter().map(|n| n.start + n.duration))
+                .map(tick_to_frame)
+                .max()
+                .unwrap_or(0);
+            TimelineEnd::Stop(last_frame)
+        };
         
-        asm
+        RegisterTimeline { frames, end }
     }
     
     /// Convert MIDI note (0-127) to BIOS 6-bit frequency (0-63)
@@ -92,9 +92,11 @@ fn token_display_name(kind: &TokenKind) -> String {
         TokenKind::As => "'as'".to_string(),
         TokenKind::Export => "'export'".to_string(),
         TokenKind::Struct => "'struct'".to_string(),
+        TokenKind::Enum => "'enum'".to_string(),
         TokenKind::Self_ => "'self'".to_string(),
         TokenKind::Identifier(_) => "identifier".to_string(),
         TokenKind::Number(_) => "number".to_string(),
+        TokenKind::Fixed(_) => "fixed-point number".to_string(),
         TokenKind::StringLit(_) => "string".to_string(),
     }
 }
@@ -164,15 +166,16 @@ impl<'a> Parser<'a> {
                 items.push(Item::Const { name, value, source_line: const_line });
                 continue;
             }
-            // Global variable declaration: identifier = expression (Python-style, no keyword)
+            // Global variable declaration: identifier [: type] = expression (Python-style, no keyword)
             if self.check_identifier() {
                 let checkpoint = self.pos;
                 if let Ok(name) = self.identifier() {
+                    let type_annotation = if self.match_kind(&TokenKind::Colon) { self.identifier().ok() } else { None };
                     if self.match_kind(&TokenKind::Equal) {
                         let global_line = self.current_line();
                         let value = self.expression()?;
                         self.consume(TokenKind::Newline)?;
-                        items.push(Item::GlobalLet { name, value, source_line: global_line });
+                        items.push(Item::GlobalLet { name, type_annotation, value, source_line: global_line });
                         continue;
                     }
                 }
@@ -209,6 +212,13 @@ impl<'a> Parser<'a> {
                 continue;
             }
             
+            // Enum definition: enum Name: A, B, C
+            if self.check(TokenKind::Enum) {
+                let enum_def = self.parse_enum()?;
+                items.push(Item::Enum(enum_def));
+                continue;
+            }
+
             // Struct definition: struct Name:
             if self.check(TokenKind::Struct) {
                 let struct_def = self.parse_struct()?;
@@ -403,10 +413,13 @@ impl<'a> Parser<'a> {
         let name = self.identifier()?;
         self.consume(TokenKind::LParen)?;
         let mut params = Vec::new();
+        let mut param_types = Vec::new();
         self.skip_newlines(); // Allow newlines after opening paren
         if !self.check(TokenKind::RParen) {
             loop { 
                 params.push(self.identifier()?); 
+                // Optional annotation: def f(x: u8)
+                param_types.push(if self.match_kind(&TokenKind::Colon) { Some(self.identifier()?) } else { None });
                 self.skip_newlines(); // Allow newlines after parameter
                 if self.match_kind(&TokenKind::Comma) { 
                     self.skip_newlines(); // Allow newlines after comma
@@ -419,7 +432,7 @@ impl<'a> Parser<'a> {
         self.consume(TokenKind::RParen)?; self.consume(TokenKind::Colon)?; self.consume(TokenKind::Newline)?; self.consume(TokenKind::Indent)?;
         let mut body = Vec::new();
         while !self.match_kind(&TokenKind::Dedent) { body.push(self.statement()?); }
-        Ok(Function { name, line: func_line, params, body })
+        Ok(Function { name, line: func_line, params, param_types, body })
     }
 
     // Parse struct definition: struct Name:
@@ -477,6 +490,27 @@ impl<'a> Parser<'a> {
         Ok(StructDef { name, fields, methods, constructor, source_line })
     }
 
+    // Parse enum definition: enum Name: A, B, C
+    fn parse_enum(&mut self) -> Result<EnumDef> {
+        let source_line = self.peek().line;
+        self.consume(TokenKind::Enum)?;
+        let name = self.identifier()?;
+        self.consume(TokenKind::Colon)?;
+
+        let mut variants: Vec<String> = Vec::new();
+        loop {
+            let variant = self.identifier()?;
+            if variants.iter().any(|v| v.eq_ignore_ascii_case(&variant)) {
+                return self.err_here(&format!("Enum {} already has a variant {}", name, variant));
+            }
+            variants.push(variant);
+            if !self.match_kind(&TokenKind::Comma) || self.check(TokenKind::Newline) { break; }
+        }
+        self.consume(TokenKind::Newline)?;
+
+        Ok(EnumDef { name, variants, source_line })
+    }
+
     fn statement(&mut self) -> Result<Stmt> {
         let start_source_line = self.peek().line; // Capturar línea del statement
         
@@ -492,6 +526,16 @@ impl<'a> Parser<'a> {
         // Try to parse assignment (x = value or arr[i] = value)
         let checkpoint = self.pos;
         if let Ok(lhs_expr) = self.postfix() {
+            // Annotated local declaration: x: u8 = value
+            if let Expr::Ident(id) = &lhs_expr {
+                if self.match_kind(&TokenKind::Colon) {
+                    let type_annotation = Some(self.identifier()?);
+                    self.consume(TokenKind::Equal)?;
+                    let value = self.expression()?;
+                    self.consume(TokenKind::Newline)?;
+                    return Ok(Stmt::Let { name: id.name.clone(), type_annotation, value, source_line: start_source_line });
+                }
+            }
             // Check if followed by assignment operator
             if self.match_kind(&TokenKind::Equal) {
                 let rhs = self.expression()?;
@@ -638,6 +682,9 @@ impl<'a> Parser<'a> {
     fn primary(&mut self) -> Result<Expr> {
         if let Some(n) = self.match_number() {
             return Ok(Expr::Number(n));
+        } else if let TokenKind::Fixed(raw) = self.peek().kind {
+            self.pos += 1;
+            return Ok(Expr::Fixed(raw));
         } else if let Some(s) = self.match_string() {
             return Ok(Expr::StringLit(s));
         } else if self.match_kind(&TokenKind::Self_) {
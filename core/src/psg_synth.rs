@@ -0,0 +1,496 @@
+//! Offline AY-3-8912 synthesiser (`vectrexc render-audio`)
+//!
+//! Models the Vectrex PSG at its 1.5 MHz clock: three square-wave tone
+//! generators, the 17-bit noise LFSR, the mixer and the envelope generator,
+//! mixed through the chip's logarithmic volume table and resampled to
+//! 44.1 kHz. Music and sound effects are played from the register streams
+//! that `MusicResource::compile_to_asm` and `SfxResource::compile_to_asm`
+//! emit: the ASM is assembled and the bytes are walked one 50 Hz frame at a
+//! time, the way AUDIO_UPDATE and sfx_doframe do on the console. What you
+//! hear is what the compiled data says, not what the JSON intended.
+
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::backend::asm_to_binary::assemble_m6809;
+use crate::music_pack::{PackedReader, PACKED_MAGIC};
+use crate::musres::MusicResource;
+use crate::sfxres::SfxResource;
+
+/// PSG clock on the Vectrex (the 6809 E clock)
+pub const PSG_CLOCK: u32 = 1_500_000;
+/// WAV output rate
+pub const SAMPLE_RATE: u32 = 44_100;
+/// AUDIO_UPDATE runs once per frame (30000 cycles at 1.5 MHz)
+pub const FRAME_RATE: u32 = 50;
+
+/// Tone generators advance once every 8 PSG clocks
+const TICK_RATE: u32 = PSG_CLOCK / 8;
+/// DAC output per volume level, normalised (the chip steps ~3 dB per level)
+const VOLUME: [f32; 16] = [
+    0.0, 0.0137, 0.0205, 0.0291, 0.0423, 0.0618, 0.0847, 0.1369,
+    0.1691, 0.2647, 0.3527, 0.4499, 0.5704, 0.6873, 0.8482, 1.0,
+];
+/// AYFX end-of-effect marker
+const SFX_END: [u8; 2] = [0xD0, 0x20];
+/// `$D0 $21 shape, period-hi, period-lo`: hardware envelope, then the frame
+const SFX_ENVELOPE: [u8; 2] = [0xD0, 0x21];
+/// Music stream: delay byte that jumps back to the loop point
+const MUSIC_LOOP: u8 = 0xFF;
+
+/// AY-3-8912 sound generator
+#[derive(Debug, Clone)]
+pub struct Ay38912 {
+    regs: [u8; 16],
+    tone_count: [u16; 3],
+    tone_out: [bool; 3],
+    noise_count: u8,
+    /// 17-bit shift register, output is bit 0
+    lfsr: u32,
+    envelope_count: u32,
+    /// 15 down to 0 within a cycle
+    envelope_step: u8,
+    /// XOR mask: 0x0F while attacking (counting up)
+    envelope_attack: u8,
+    envelope_holding: bool,
+    /// Toggles each tick: noise and envelope run at half the tone rate
+    half: bool,
+    /// Ticks still owed to the next sample (fixed point, 1/SAMPLE_RATE)
+    phase: u32,
+    /// DC blocker state
+    last_in: f32,
+    last_out: f32,
+}
+
+impl Default for Ay38912 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Ay38912 {
+    pub fn new() -> Self {
+        let mut regs = [0; 16];
+        // Everything off, as after the BIOS's Clear_Sound
+        regs[7] = 0x3F;
+        Self {
+            regs,
+            tone_count: [0; 3],
+            tone_out: [false; 3],
+            noise_count: 0,
+            lfsr: 1,
+            envelope_count: 0,
+            envelope_step: 15,
+            envelope_attack: 0,
+            envelope_holding: true,
+            half: false,
+            phase: 0,
+            last_in: 0.0,
+            last_out: 0.0,
+        }
+    }
+
+    pub fn regs(&self) -> &[u8; 16] {
+        &self.regs
+    }
+
+    pub fn write(&mut self, reg: u8, value: u8) {
+        let reg = (reg & 0x0F) as usize;
+        self.regs[reg] = value;
+        if reg == 13 {
+            // Writing the shape restarts the envelope
+            self.envelope_count = 0;
+            self.envelope_step = 15;
+            self.envelope_attack = if value & 0x04 != 0 { 0x0F } else { 0 };
+            self.envelope_holding = false;
+        }
+    }
+
+    fn tone_period(&self, channel: usize) -> u16 {
+        let period = self.regs[channel * 2] as u16 | ((self.regs[channel * 2 + 1] as u16 & 0x0F) << 8);
+        period.max(1)
+    }
+
+    fn envelope_period(&self) -> u32 {
+        (self.regs[11] as u32 | (self.regs[12] as u32) << 8).max(1)
+    }
+
+    /// One tick of the tone clock (PSG clock / 8)
+    fn tick(&mut self) {
+        for channel in 0..3 {
+            self.tone_count[channel] += 1;
+            if self.tone_count[channel] >= self.tone_period(channel) {
+                self.tone_count[channel] = 0;
+                self.tone_out[channel] = !self.tone_out[channel];
+            }
+        }
+
+        self.half = !self.half;
+        if !self.half {
+            return;
+        }
+
+        self.noise_count += 1;
+        if self.noise_count >= (self.regs[6] & 0x1F).max(1) {
+            self.noise_count = 0;
+            let bit = (self.lfsr ^ (self.lfsr >> 3)) & 1;
+            self.lfsr = (self.lfsr >> 1) | (bit << 16);
+        }
+
+        self.envelope_count += 1;
+        if self.envelope_count >= self.envelope_period() {
+            self.envelope_count = 0;
+            self.step_envelope();
+        }
+    }
+
+    fn step_envelope(&mut self) {
+        if self.envelope_holding {
+            return;
+        }
+        if self.envelope_step > 0 {
+            self.envelope_step -= 1;
+            return;
+        }
+        let shape = self.regs[13];
+        let (continue_, alternate, hold) = (shape & 0x08 != 0, shape & 0x02 != 0, shape & 0x01 != 0);
+        if !continue_ {
+            // Shapes 0-7 fall silent after one cycle
+            self.envelope_holding = true;
+            self.envelope_attack = 0;
+        } else {
+            if alternate {
+                self.envelope_attack ^= 0x0F;
+            }
+            if hold {
+                self.envelope_holding = true;
+            } else {
+                self.envelope_step = 15;
+            }
+        }
+    }
+
+    fn envelope_level(&self) -> u8 {
+        self.envelope_step ^ self.envelope_attack
+    }
+
+    /// Instantaneous output, 0.0-1.0
+    fn output(&self) -> f32 {
+        let mixer = self.regs[7];
+        let noise = self.lfsr & 1 != 0;
+        let mut sum = 0.0;
+        for channel in 0..3 {
+            let tone_on = self.tone_out[channel] || mixer & (1 << channel) != 0;
+            let noise_on = noise || mixer & (8 << channel) != 0;
+            if tone_on && noise_on {
+                let amplitude = self.regs[8 + channel];
+                let level = if amplitude & 0x10 != 0 { self.envelope_level() } else { amplitude & 0x0F };
+                sum += VOLUME[level as usize];
+            }
+        }
+        sum / 3.0
+    }
+
+    /// Append `count` samples at `SAMPLE_RATE`, each the average of the
+    /// ticks it spans, with the DC offset of the unipolar DAC removed
+    pub fn render(&mut self, count: usize, out: &mut Vec<f32>) {
+        for _ in 0..count {
+            let mut sum = 0.0;
+            let mut ticks = 0;
+            while self.phase < TICK_RATE {
+                self.tick();
+                sum += self.output();
+                ticks += 1;
+                self.phase += SAMPLE_RATE;
+            }
+            self.phase -= TICK_RATE;
+            let level = sum / ticks as f32;
+            let sample = level - self.last_in + 0.995 * self.last_out;
+            self.last_in = level;
+            self.last_out = sample;
+            out.push(sample);
+        }
+    }
+}
+
+/// Register stream assembled at address 0 (loop targets are offsets)
+struct Stream {
+    bytes: Vec<u8>,
+}
+
+impl Stream {
+    fn assemble(asm: &str) -> Result<Self> {
+        let (bytes, _, _) = assemble_m6809(asm, 0).map_err(|e| anyhow!("cannot assemble the register stream: {}", e))?;
+        Ok(Self { bytes })
+    }
+
+    fn byte(&self, at: usize) -> Result<u8> {
+        self.bytes.get(at).copied().ok_or_else(|| anyhow!("register stream overruns its data at offset {}", at))
+    }
+}
+
+/// Frame-by-frame reader of the music format: `FCB delay, count, (reg, value)*`,
+/// `FCB 0` to end, `FCB $FF` + `FDB target` to loop
+struct MusicPlayer {
+    stream: Stream,
+    pos: usize,
+    /// Frames still to wait before the pending writes at `pos` apply
+    delay: u8,
+    loops: usize,
+    ended: bool,
+}
+
+impl MusicPlayer {
+    fn frame(&mut self, psg: &mut Ay38912) -> Result<usize> {
+        if self.ended {
+            return Ok(0);
+        }
+        if self.delay > 0 {
+            self.delay -= 1;
+            if self.delay > 0 {
+                return Ok(0);
+            }
+            return self.apply(psg);
+        }
+        let delay = self.stream.byte(self.pos)?;
+        self.pos += 1;
+        match delay {
+            MUSIC_LOOP => {
+                let target = u16::from_be_bytes([self.stream.byte(self.pos)?, self.stream.byte(self.pos + 1)?]);
+                self.pos = target as usize;
+                self.loops += 1;
+                Ok(0)
+            }
+            // The writes are due `delay` frames after the previous ones,
+            // and this call is already the first of those frames
+            0 | 1 => self.apply(psg),
+            _ => {
+                self.delay = delay - 1;
+                Ok(0)
+            }
+        }
+    }
+
+    fn apply(&mut self, psg: &mut Ay38912) -> Result<usize> {
+        let count = self.stream.byte(self.pos)?;
+        self.pos += 1;
+        match count {
+            0 => {
+                self.ended = true;
+                Ok(0)
+            }
+            MUSIC_LOOP => {
+                // A loop marker may follow a delay
+                self.pos -= 1;
+                self.frame(psg)
+            }
+            _ => {
+                for _ in 0..count {
+                    psg.write(self.stream.byte(self.pos)?, self.stream.byte(self.pos + 1)?);
+                    self.pos += 2;
+                }
+                Ok(count as usize)
+            }
+        }
+    }
+}
+
+/// AYFX reader (one frame per call, channel C), as `sfx_doframe`
+struct SfxPlayer {
+    stream: Stream,
+    pos: usize,
+    ended: bool,
+    /// $10 once the effect selected the hardware envelope
+    env_mode: u8,
+}
+
+impl SfxPlayer {
+    fn frame(&mut self, psg: &mut Ay38912) -> Result<usize> {
+        if self.ended {
+            return Ok(0);
+        }
+        let mut writes = 0;
+        let mut flag = self.stream.byte(self.pos)?;
+        if flag == SFX_ENVELOPE[0] && self.stream.byte(self.pos + 1)? == SFX_ENVELOPE[1] {
+            psg.write(11, self.stream.byte(self.pos + 4)?);
+            psg.write(12, self.stream.byte(self.pos + 3)?);
+            psg.write(13, self.stream.byte(self.pos + 2)?);
+            self.env_mode = 0x10;
+            self.pos += 5;
+            writes += 3;
+            flag = self.stream.byte(self.pos)?;
+        }
+        if flag == SFX_END[0] && self.stream.byte(self.pos + 1)? == SFX_END[1] {
+            psg.write(10, 0);
+            self.ended = true;
+            return Ok(writes + 1);
+        }
+        let mut data = self.pos + 1;
+        if flag & 0x20 != 0 {
+            // Tone period, big-endian
+            psg.write(5, self.stream.byte(data)?);
+            psg.write(4, self.stream.byte(data + 1)?);
+            data += 2;
+            writes += 2;
+        }
+        if flag & 0x40 != 0 {
+            psg.write(6, self.stream.byte(data)?);
+            data += 1;
+            writes += 1;
+        }
+        psg.write(10, (flag & 0x0F) | self.env_mode);
+        let mut mixer = psg.regs()[7];
+        mixer = if flag & 0x10 != 0 { mixer | 0x04 } else { mixer & !0x04 };
+        mixer = if flag & 0x80 != 0 { mixer | 0x20 } else { mixer & !0x20 };
+        psg.write(7, mixer);
+        self.pos = data;
+        Ok(writes + 2)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RenderOptions {
+    /// Times a looping song is played through (it stops at the loop marker)
+    pub loops: usize,
+    /// Hard stop, for songs that never end
+    pub max_seconds: u32,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self { loops: 1, max_seconds: 300 }
+    }
+}
+
+/// Rendered audio and what the register stream did
+#[derive(Debug, Clone)]
+pub struct Rendering {
+    /// Mono samples at `SAMPLE_RATE`, -1.0..1.0
+    pub samples: Vec<f32>,
+    /// 50 Hz frames played
+    pub frames: usize,
+    /// Register writes performed
+    pub writes: usize,
+    /// The stream ended (music end marker / AYFX end) rather than being cut off
+    pub finished: bool,
+}
+
+impl Rendering {
+    pub fn seconds(&self) -> f32 {
+        self.samples.len() as f32 / SAMPLE_RATE as f32
+    }
+}
+
+/// Samples making up frame `frame` (rounding spread evenly)
+fn frame_samples(frame: usize) -> usize {
+    let rate = SAMPLE_RATE as usize;
+    let fps = FRAME_RATE as usize;
+    (frame + 1) * rate / fps - frame * rate / fps
+}
+
+/// Render a music resource through its compiled register stream
+pub fn render_music(resource: &MusicResource, asset_name: &str, options: &RenderOptions) -> Result<Rendering> {
+    let stream = Stream::assemble(&resource.compile_to_asm(asset_name))?;
+    if stream.bytes.first() == Some(&PACKED_MAGIC) {
+        return render_packed_music(&stream, options);
+    }
+    let mut player = MusicPlayer { stream, pos: 0, delay: 0, loops: 0, ended: false };
+    let mut psg = Ay38912::new();
+    let mut rendering = Rendering { samples: Vec::new(), frames: 0, writes: 0, finished: false };
+
+    let max_frames = (options.max_seconds * FRAME_RATE) as usize;
+    while rendering.frames < max_frames {
+        rendering.writes += player.frame(&mut psg)?;
+        if player.ended {
+            rendering.finished = true;
+            break;
+        }
+        psg.render(frame_samples(rendering.frames), &mut rendering.samples);
+        rendering.frames += 1;
+        // The frame spent on the loop command still belongs to the pass
+        if player.loops >= options.loops.max(1) {
+            rendering.finished = true;
+            break;
+        }
+    }
+    Ok(rendering)
+}
+
+/// Packed streams (see `music_pack`): the loop jump takes no frame, so a
+/// pass ends when the next frame read has gone through it
+fn render_packed_music(stream: &Stream, options: &RenderOptions) -> Result<Rendering> {
+    let mut reader = PackedReader::new(&stream.bytes, 0).map_err(|e| anyhow!(e))?;
+    let mut psg = Ay38912::new();
+    let mut rendering = Rendering { samples: Vec::new(), frames: 0, writes: 0, finished: false };
+
+    let max_frames = (options.max_seconds * FRAME_RATE) as usize;
+    while rendering.frames < max_frames {
+        let Some(writes) = reader.next_frame().map_err(|e| anyhow!(e))? else {
+            rendering.finished = true;
+            break;
+        };
+        if reader.loops >= options.loops.max(1) {
+            rendering.finished = true;
+            break;
+        }
+        for &(reg, value) in &writes {
+            psg.write(reg, value);
+        }
+        rendering.writes += writes.len();
+        psg.render(frame_samples(rendering.frames), &mut rendering.samples);
+        rendering.frames += 1;
+    }
+    Ok(rendering)
+}
+
+/// Render a sound effect through its compiled AYFX stream
+pub fn render_sfx(resource: &SfxResource, options: &RenderOptions) -> Result<Rendering> {
+    let stream = Stream::assemble(&resource.compile_to_asm())?;
+    let mut player = SfxPlayer { stream, pos: 0, ended: false, env_mode: 0 };
+    let mut psg = Ay38912::new();
+    let mut rendering = Rendering { samples: Vec::new(), frames: 0, writes: 0, finished: false };
+
+    let max_frames = (options.max_seconds * FRAME_RATE) as usize;
+    while rendering.frames < max_frames {
+        rendering.writes += player.frame(&mut psg)?;
+        if player.ended {
+            rendering.finished = true;
+            break;
+        }
+        psg.render(frame_samples(rendering.frames), &mut rendering.samples);
+        rendering.frames += 1;
+    }
+    Ok(rendering)
+}
+
+/// 16-bit mono PCM WAV
+pub fn wav_bytes(samples: &[f32]) -> Vec<u8> {
+    let data_len = (samples.len() * 2) as u32;
+    let mut wav = Vec::with_capacity(44 + data_len as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    wav.extend_from_slice(&1u16.to_le_bytes()); // mono
+    wav.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    wav.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    for sample in samples {
+        let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        wav.extend_from_slice(&value.to_le_bytes());
+    }
+    wav
+}
+
+pub fn write_wav(path: &Path, samples: &[f32]) -> Result<()> {
+    if samples.is_empty() {
+        bail!("nothing to write: the register stream produced no frames");
+    }
+    std::fs::write(path, wav_bytes(samples))?;
+    Ok(())
+}
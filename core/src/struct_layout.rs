@@ -6,6 +6,7 @@
 /// - Validating struct definitions
 
 use crate::ast::{StructDef, FieldDef};
+use crate::types::{annotation_type, ScalarType};
 use std::collections::HashMap;
 
 /// Layout information for a struct
@@ -22,13 +23,15 @@ pub struct FieldLayout {
     pub name: String,
     pub offset: usize,
     pub size: usize,
+    /// Annotated scalar type (u8/i8/bool fields take 1 byte); None = untyped word
+    pub ty: Option<ScalarType>,
 }
 
 impl StructLayout {
     /// Compute layout for a struct definition
     /// 
-    /// Currently assumes all fields are 2-byte integers (M6809 word size)
-    /// Future: could parse type annotations to support different sizes
+    /// Fields are 2-byte words (M6809 word size) unless annotated with a
+    /// byte type (`hp: u8`), see types.rs
     pub fn from_struct_def(def: &StructDef) -> Result<Self, String> {
         // Validate no duplicate field names
         let mut seen_fields = std::collections::HashSet::new();
@@ -46,14 +49,14 @@ impl StructLayout {
         let mut current_offset = 0;
         
         for field in &def.fields {
-            // For now, all fields are 2 bytes (16-bit integers)
-            // Future: parse type_annotation to determine size
-            let size = 2;
+            let ty = annotation_type(field.type_annotation.as_deref());
+            let size = ty.map_or(2, ScalarType::size);
             
             fields.push(FieldLayout {
                 name: field.name.clone(),
                 offset: current_offset,
                 size,
+                ty,
             });
             
             current_offset += size;
@@ -129,6 +132,27 @@ mod tests {
         assert_eq!(layout.field_offset("y"), Some(2));
     }
 
+    #[test]
+    fn test_byte_fields_pack() {
+        let def = StructDef {
+            name: "Ship".to_string(),
+            fields: vec![
+                FieldDef { name: "hp".to_string(), type_annotation: Some("u8".to_string()), source_line: 1 },
+                FieldDef { name: "alive".to_string(), type_annotation: Some("bool".to_string()), source_line: 2 },
+                FieldDef { name: "x".to_string(), type_annotation: Some("i16".to_string()), source_line: 3 },
+            ],
+            source_line: 1,
+            constructor: None,
+            methods: vec![],
+        };
+
+        let layout = StructLayout::from_struct_def(&def).unwrap();
+        assert_eq!(layout.total_size, 4); // 1 + 1 + 2
+        assert_eq!(layout.field_offset("alive"), Some(1));
+        assert_eq!(layout.field_offset("x"), Some(2));
+        assert_eq!(layout.get_field("hp").unwrap().ty, Some(ScalarType::U8));
+    }
+
     #[test]
     fn test_duplicate_field_error() {
         let def = StructDef {
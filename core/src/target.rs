@@ -9,14 +9,51 @@ impl Display for Target { fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Resu
 
 pub enum CpuArch { M6809, Arm, CortexM }
 
-pub struct TargetInfo { pub name: &'static str, pub origin: &'static str, #[allow(dead_code)] pub init_label: &'static str, #[allow(dead_code)] pub line_routine: &'static str, pub arch: CpuArch }
+/// Vector SDK entry points called by the ARM backends (AAPCS, int arguments,
+/// coordinates in Vectrex units -128..127). Each platform's SDK glue exports these.
+pub struct SdkRoutines {
+    /// () -> void, once per frame before loop(): waits for the refresh and recalibrates the beam
+    pub wait_recal: &'static str,
+    /// (intensity) -> void
+    pub intensity: &'static str,
+    /// (x, y, const char *text) -> void, NUL-terminated text
+    pub text: &'static str,
+    /// () -> bitmask, bits 0-3 = pad 1 buttons 1-4, bits 4-7 = pad 2
+    pub buttons: &'static str,
+    /// (axis) -> -128..127, axis 0/1 = pad 1 X/Y, 2/3 = pad 2 X/Y
+    pub joystick: &'static str,
+}
+
+pub struct TargetInfo {
+    pub name: &'static str,
+    pub origin: &'static str,
+    /// () -> void, called once before main()
+    pub init_label: &'static str,
+    /// (x0, y0, x1, y1, intensity) -> void
+    pub line_routine: &'static str,
+    pub sdk: SdkRoutines,
+    /// Extension of the generated assembly file
+    pub asm_ext: &'static str,
+    pub arch: CpuArch,
+    /// The SDK glue exporting `init_label`, `line_routine` and `sdk` is not in
+    /// this repo; the output only links against a platform SDK that provides it
+    pub experimental: bool,
+}
+
+const BIOS_ROUTINES: SdkRoutines = SdkRoutines { wait_recal: "Wait_Recal", intensity: "Intensity_a", text: "Print_Str_d", buttons: "Read_Btns", joystick: "Joy_Digital" };
 
 pub fn info(t: Target) -> TargetInfo {
     match t {
-    Target::Vectrex => TargetInfo { name: "Vectrex", origin: "$0000", init_label: "INIT_ENGINE", line_routine: "line", arch: CpuArch::M6809 },
-    Target::Pitrex  => TargetInfo { name: "Pitrex", origin: "$0400", init_label: "PITREX_INIT", line_routine: "pitrex_line", arch: CpuArch::Arm },
-    Target::Vecfever=> TargetInfo { name: "VecFever", origin: "$8000", init_label: "VF_INIT", line_routine: "vf_line", arch: CpuArch::CortexM },
-    Target::Vextreme=> TargetInfo { name: "Vextreme", origin: "$6000", init_label: "VX_INIT", line_routine: "vx_line", arch: CpuArch::CortexM },
+    Target::Vectrex => TargetInfo { name: "Vectrex", origin: "$0000", init_label: "INIT_ENGINE", line_routine: "line", sdk: BIOS_ROUTINES, asm_ext: "asm", arch: CpuArch::M6809, experimental: false },
+    Target::Pitrex  => TargetInfo { name: "Pitrex", origin: "$0400", init_label: "pitrex_init", line_routine: "pitrex_line",
+        sdk: SdkRoutines { wait_recal: "pitrex_wait_recal", intensity: "pitrex_intensity", text: "pitrex_print", buttons: "pitrex_buttons", joystick: "pitrex_joystick" },
+        asm_ext: "s", arch: CpuArch::Arm, experimental: true },
+    Target::Vecfever=> TargetInfo { name: "VecFever", origin: "$8000", init_label: "vf_init", line_routine: "vf_line",
+        sdk: SdkRoutines { wait_recal: "vf_wait_recal", intensity: "vf_intensity", text: "vf_print", buttons: "vf_buttons", joystick: "vf_joystick" },
+        asm_ext: "s", arch: CpuArch::CortexM, experimental: true },
+    Target::Vextreme=> TargetInfo { name: "Vextreme", origin: "$6000", init_label: "vx_init", line_routine: "vx_line",
+        sdk: SdkRoutines { wait_recal: "vx_wait_recal", intensity: "vx_intensity", text: "vx_print", buttons: "vx_buttons", joystick: "vx_joystick" },
+        asm_ext: "s", arch: CpuArch::CortexM, experimental: true },
         Target::All => panic!("'All' is aggregate target and has no direct info")
     }
 }
@@ -0,0 +1,573 @@
+//! Optional static type annotations
+//!
+//! Globals, locals, parameters and struct fields may carry an annotation:
+//!
+//! ```text
+//! lives: u8 = 3
+//! def hit(damage: u8):
+//!     left: i16 = lives - damage
+//! ```
+//!
+//! `u8`, `i8` and `bool` get one byte of storage, `u16`, `i16` (`int`) and
+//! `fixed` get a word. Unannotated values keep the old untyped 16-bit behaviour
+//! and are never reported, so annotations can be added one variable at a time.
+//!
+//! The checker reports a `TypeMismatch` when a value whose type is known cannot
+//! be represented in the annotated type: an out-of-range literal, a wider or
+//! differently-signed variable, an int stored in a bool. `u8(x)`, `i8(x)`,
+//! `u16(x)`, `i16(x)` and `bool(x)` convert explicitly. Byte arithmetic wraps
+//! on store, like the hardware does.
+//!
+//! Fixed-vs-int mixing is left to `fixed_point`, which runs afterwards.
+
+use crate::ast::*;
+use crate::codegen::{Diagnostic, DiagnosticCode, DiagnosticSeverity};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScalarType {
+    U8,
+    I8,
+    U16,
+    I16,
+    Bool,
+    Fixed,
+}
+
+impl ScalarType {
+    /// Parse an annotation; `int` is an alias for `i16`
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "u8" => Some(ScalarType::U8),
+            "i8" => Some(ScalarType::I8),
+            "u16" => Some(ScalarType::U16),
+            "i16" | "int" => Some(ScalarType::I16),
+            "bool" => Some(ScalarType::Bool),
+            "fixed" => Some(ScalarType::Fixed),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ScalarType::U8 => "u8",
+            ScalarType::I8 => "i8",
+            ScalarType::U16 => "u16",
+            ScalarType::I16 => "i16",
+            ScalarType::Bool => "bool",
+            ScalarType::Fixed => "fixed",
+        }
+    }
+
+    /// Storage size in bytes
+    pub fn size(self) -> usize {
+        if self.is_byte() { 1 } else { 2 }
+    }
+
+    pub fn is_byte(self) -> bool {
+        matches!(self, ScalarType::U8 | ScalarType::I8 | ScalarType::Bool)
+    }
+
+    fn range(self) -> (i32, i32) {
+        match self {
+            ScalarType::U8 => (0, 255),
+            ScalarType::I8 => (-128, 127),
+            ScalarType::U16 => (0, 65535),
+            ScalarType::I16 | ScalarType::Fixed => (-32768, 32767),
+            ScalarType::Bool => (0, 1),
+        }
+    }
+
+    fn contains(self, other: ScalarType) -> bool {
+        let (lo, hi) = self.range();
+        let (olo, ohi) = other.range();
+        lo <= olo && ohi <= hi
+    }
+}
+
+/// Type of an annotation string, if it names a scalar type
+pub fn annotation_type(annotation: Option<&str>) -> Option<ScalarType> {
+    annotation.and_then(ScalarType::parse)
+}
+
+/// Annotated top-level variables (name -> type)
+pub fn global_types(module: &Module) -> HashMap<String, ScalarType> {
+    module.items.iter().filter_map(|it| match it {
+        Item::GlobalLet { name, type_annotation, .. } => annotation_type(type_annotation.as_deref()).map(|t| (name.clone(), t)),
+        _ => None,
+    }).collect()
+}
+
+/// Annotated parameters and locals of one function (name -> type)
+pub fn local_types(f: &Function) -> HashMap<String, ScalarType> {
+    fn walk(stmts: &[Stmt], out: &mut HashMap<String, ScalarType>) {
+        for s in stmts {
+            match s {
+                Stmt::Let { name, type_annotation, .. } => {
+                    if let Some(t) = annotation_type(type_annotation.as_deref()) { out.insert(name.clone(), t); }
+                }
+                Stmt::For { body, .. } | Stmt::ForIn { body, .. } | Stmt::While { body, .. } => walk(body, out),
+                Stmt::If { body, elifs, else_body, .. } => {
+                    walk(body, out);
+                    for (_, b) in elifs { walk(b, out); }
+                    if let Some(eb) = else_body { walk(eb, out); }
+                }
+                Stmt::Switch { cases, default, .. } => {
+                    for (_, b) in cases { walk(b, out); }
+                    if let Some(d) = default { walk(d, out); }
+                }
+                _ => {}
+            }
+        }
+    }
+    let mut out = HashMap::new();
+    for (p, t) in f.params.iter().zip(f.param_types.iter()) {
+        if let Some(t) = annotation_type(t.as_deref()) { out.insert(p.clone(), t); }
+    }
+    walk(&f.body, &mut out);
+    out
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Ty {
+    /// Unannotated value: never checked
+    Dyn,
+    /// Constant integer expression
+    Lit(i32),
+    Known(ScalarType),
+}
+
+struct Checker<'a> {
+    globals: HashMap<String, ScalarType>,
+    /// Parameter types per function (key: function name or "Struct.method")
+    signatures: HashMap<String, Vec<Option<ScalarType>>>,
+    /// Field types per struct
+    fields: HashMap<String, HashMap<String, ScalarType>>,
+    struct_names: Vec<&'a str>,
+    diagnostics: Vec<Diagnostic>,
+    line: usize,
+}
+
+struct Scope {
+    locals: HashMap<String, ScalarType>,
+    /// Variables holding a struct instance (name -> struct)
+    instances: HashMap<String, String>,
+    self_struct: Option<String>,
+}
+
+/// Check annotated types. Errors are appended to `diagnostics`.
+pub fn check_module(module: &Module, diagnostics: &mut Vec<Diagnostic>) {
+    let mut ck = Checker::new(module);
+    ck.check_annotations(module);
+    for it in &module.items {
+        match it {
+            Item::GlobalLet { name, value, source_line, .. } => {
+                ck.line = *source_line;
+                let empty = Scope { locals: HashMap::new(), instances: HashMap::new(), self_struct: None };
+                if let Some(&t) = ck.globals.get(name) {
+                    if matches!(value, Expr::List(_)) {
+                        ck.error(format!("'{}' is an array; only scalar variables can be annotated", name));
+                    } else {
+                        let vt = ck.ty(&empty, value);
+                        ck.expect(name, t, vt);
+                    }
+                }
+            }
+            Item::Function(f) => ck.check_function(f, None),
+            Item::StructDef(sd) => {
+                for m in sd.methods.iter().chain(sd.constructor.iter()) { ck.check_function(m, Some(&sd.name)); }
+            }
+            _ => {}
+        }
+    }
+    diagnostics.extend(ck.diagnostics);
+}
+
+impl<'a> Checker<'a> {
+    fn new(module: &'a Module) -> Self {
+        let mut signatures = HashMap::new();
+        let mut fields = HashMap::new();
+        let mut struct_names = Vec::new();
+        let sig = |f: &Function| f.param_types.iter().map(|t| annotation_type(t.as_deref())).collect::<Vec<_>>();
+        for it in &module.items {
+            match it {
+                Item::Function(f) => { signatures.insert(f.name.clone(), sig(f)); }
+                Item::StructDef(sd) => {
+                    struct_names.push(sd.name.as_str());
+                    for m in sd.methods.iter().chain(sd.constructor.iter()) {
+                        signatures.insert(format!("{}.{}", sd.name, m.name), sig(m));
+                    }
+                    let types = sd.fields.iter()
+                        .filter_map(|fd| annotation_type(fd.type_annotation.as_deref()).map(|t| (fd.name.clone(), t)))
+                        .collect();
+                    fields.insert(sd.name.clone(), types);
+                }
+                _ => {}
+            }
+        }
+        Checker { globals: global_types(module), signatures, fields, struct_names, diagnostics: Vec::new(), line: 0 }
+    }
+
+    fn push(&mut self, code: DiagnosticCode, message: String) {
+        self.diagnostics.push(Diagnostic {
+            severity: DiagnosticSeverity::Error,
+            code,
+            message,
+            line: if self.line > 0 { Some(self.line) } else { None },
+            col: None,
+        });
+    }
+
+    fn error(&mut self, message: String) {
+        self.push(DiagnosticCode::TypeMismatch, message);
+    }
+
+    // check_annotations: every annotation names a scalar type (fields may also name a struct)
+    fn check_annotations(&mut self, module: &Module) {
+        let mut bad: Vec<(usize, String, String)> = Vec::new();
+        let mut check = |ann: &Option<String>, what: &str, line: usize, allow_struct: bool| {
+            if let Some(a) = ann {
+                if ScalarType::parse(a).is_none() && !(allow_struct && self.struct_names.contains(&a.as_str())) {
+                    bad.push((line, a.clone(), what.to_string()));
+                }
+            }
+        };
+        fn lets(stmts: &[Stmt], f: &mut dyn FnMut(&Option<String>, &str, usize)) {
+            for s in stmts {
+                match s {
+                    Stmt::Let { name, type_annotation, source_line, .. } => f(type_annotation, name, *source_line),
+                    Stmt::For { body, .. } | Stmt::ForIn { body, .. } | Stmt::While { body, .. } => lets(body, f),
+                    Stmt::If { body, elifs, else_body, .. } => {
+                        lets(body, f);
+                        for (_, b) in elifs { lets(b, f); }
+                        if let Some(eb) = else_body { lets(eb, f); }
+                    }
+                    Stmt::Switch { cases, default, .. } => {
+                        for (_, b) in cases { lets(b, f); }
+                        if let Some(d) = default { lets(d, f); }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        let function = |f: &Function, check: &mut dyn FnMut(&Option<String>, &str, usize)| {
+            for (p, t) in f.params.iter().zip(f.param_types.iter()) { check(t, p, f.line); }
+            lets(&f.body, check);
+        };
+        for it in &module.items {
+            match it {
+                Item::GlobalLet { name, type_annotation, source_line, .. } => check(type_annotation, name, *source_line, false),
+                Item::Function(f) => function(f, &mut |t, n, l| check(t, n, l, false)),
+                Item::StructDef(sd) => {
+                    for fd in &sd.fields { check(&fd.type_annotation, &fd.name, fd.source_line, true); }
+                    for m in sd.methods.iter().chain(sd.constructor.iter()) { function(m, &mut |t, n, l| check(t, n, l, false)); }
+                }
+                _ => {}
+            }
+        }
+        for (line, ann, what) in bad {
+            self.line = line;
+            self.push(DiagnosticCode::UnknownType, format!("unknown type '{}' for '{}' (expected u8, i8, u16, i16, bool or fixed)", ann, what));
+        }
+    }
+
+    fn check_function(&mut self, f: &Function, owner: Option<&str>) {
+        let mut scope = Scope { locals: local_types(f), instances: HashMap::new(), self_struct: owner.map(str::to_string) };
+        for s in &f.body { self.check_stmt(&mut scope, s); }
+    }
+
+    fn var_type(&self, scope: &Scope, name: &str) -> Option<ScalarType> {
+        scope.locals.get(name).or_else(|| self.globals.get(name)).copied()
+    }
+
+    fn field_type(&self, scope: &Scope, target: &Expr, field: &str) -> Option<ScalarType> {
+        let Expr::Ident(id) = target else { return None };
+        let owner = if id.name == "self" { scope.self_struct.as_ref()? } else { scope.instances.get(&id.name)? };
+        self.fields.get(owner)?.get(field).copied()
+    }
+
+    fn check_stmt(&mut self, scope: &mut Scope, s: &Stmt) {
+        self.line = s.source_line();
+        match s {
+            Stmt::Assign { target, value, .. } => {
+                if let (AssignTarget::Ident { name, .. }, Expr::StructInit { struct_name, .. }) = (target, value) {
+                    scope.instances.insert(name.clone(), struct_name.clone());
+                }
+                self.check_exprs(scope, value);
+                self.check_store(scope, target, self.ty(scope, value));
+            }
+            Stmt::CompoundAssign { target, op, value, .. } => {
+                self.check_exprs(scope, value);
+                let current = match target {
+                    AssignTarget::Ident { name, .. } => self.var_type(scope, name).map_or(Ty::Dyn, Ty::Known),
+                    AssignTarget::FieldAccess { target, field, .. } => self.field_type(scope, target, field).map_or(Ty::Dyn, Ty::Known),
+                    AssignTarget::Index { .. } => Ty::Dyn,
+                };
+                let result = self.binary(*op, current, self.ty(scope, value));
+                self.check_store(scope, target, result);
+            }
+            Stmt::Let { name, value, .. } => {
+                self.check_exprs(scope, value);
+                if let Some(t) = scope.locals.get(name).copied() {
+                    let vt = self.ty(scope, value);
+                    self.expect(name, t, vt);
+                }
+            }
+            Stmt::For { var, start, end, step, body, .. } => {
+                for e in [Some(start), Some(end), step.as_ref()].into_iter().flatten() { self.check_exprs(scope, e); }
+                if let Some(t) = self.var_type(scope, var) {
+                    let (st, et) = (self.ty(scope, start), self.ty(scope, end));
+                    self.expect(var, t, st);
+                    self.expect(var, t, et);
+                }
+                for b in body { self.check_stmt(scope, b); }
+                self.line = s.source_line();
+            }
+            Stmt::ForIn { iterable, body, .. } => {
+                self.check_exprs(scope, iterable);
+                for b in body { self.check_stmt(scope, b); }
+            }
+            Stmt::While { cond, body, .. } => {
+                self.check_exprs(scope, cond);
+                for b in body { self.check_stmt(scope, b); }
+            }
+            Stmt::If { cond, body, elifs, else_body, .. } => {
+                self.check_exprs(scope, cond);
+                for b in body { self.check_stmt(scope, b); }
+                for (c, b) in elifs {
+                    self.check_exprs(scope, c);
+                    for s in b { self.check_stmt(scope, s); }
+                }
+                if let Some(eb) = else_body { for b in eb { self.check_stmt(scope, b); } }
+            }
+            Stmt::Switch { expr, cases, default, .. } => {
+                self.check_exprs(scope, expr);
+                for (c, b) in cases {
+                    self.check_exprs(scope, c);
+                    for s in b { self.check_stmt(scope, s); }
+                }
+                if let Some(d) = default { for b in d { self.check_stmt(scope, b); } }
+            }
+            Stmt::Expr(e, _) | Stmt::Return(Some(e), _) => self.check_exprs(scope, e),
+            Stmt::Return(None, _) | Stmt::Break { .. } | Stmt::Continue { .. } | Stmt::Pass { .. } => {}
+        }
+    }
+
+    fn check_store(&mut self, scope: &Scope, target: &AssignTarget, value: Ty) {
+        match target {
+            AssignTarget::Ident { name, .. } => {
+                if let Some(t) = self.var_type(scope, name) { self.expect(name, t, value); }
+            }
+            AssignTarget::FieldAccess { target, field, .. } => {
+                if let Some(t) = self.field_type(scope, target, field) { self.expect(field, t, value); }
+            }
+            AssignTarget::Index { .. } => {}
+        }
+    }
+
+    // check_exprs: argument checks for calls to functions with annotated parameters
+    fn check_exprs(&mut self, scope: &Scope, e: &Expr) {
+        match e {
+            Expr::Call(ci) => {
+                for a in &ci.args { self.check_exprs(scope, a); }
+                if let Some(sig) = self.signatures.get(&ci.name).cloned() {
+                    for (i, a) in ci.args.iter().enumerate() {
+                        if let Some(Some(t)) = sig.get(i) {
+                            let at = self.ty(scope, a);
+                            self.expect(&format!("argument {} of {}()", i + 1, ci.name), *t, at);
+                        }
+                    }
+                }
+            }
+            Expr::MethodCall(mc) => {
+                self.check_exprs(scope, &mc.target);
+                for a in &mc.args { self.check_exprs(scope, a); }
+                let owner = match &*mc.target {
+                    Expr::Ident(id) if id.name == "self" => scope.self_struct.clone(),
+                    Expr::Ident(id) => scope.instances.get(&id.name).cloned(),
+                    _ => None,
+                };
+                let key = owner.map(|o| format!("{}.{}", o, mc.method_name));
+                if let Some(sig) = key.and_then(|k| self.signatures.get(&k).cloned()) {
+                    for (i, a) in mc.args.iter().enumerate() {
+                        if let Some(Some(t)) = sig.get(i) {
+                            let at = self.ty(scope, a);
+                            self.expect(&format!("argument {} of {}()", i + 1, mc.method_name), *t, at);
+                        }
+                    }
+                }
+            }
+            Expr::Binary { left, right, .. } | Expr::Compare { left, right, .. } | Expr::Logic { left, right, .. } => {
+                self.check_exprs(scope, left);
+                self.check_exprs(scope, right);
+            }
+            Expr::Not(inner) | Expr::BitNot(inner) => self.check_exprs(scope, inner),
+            Expr::List(items) => for i in items { self.check_exprs(scope, i); },
+            Expr::Index { target, index } => {
+                self.check_exprs(scope, target);
+                self.check_exprs(scope, index);
+            }
+            Expr::FieldAccess { target, .. } => self.check_exprs(scope, target),
+            Expr::Number(_) | Expr::Fixed(_) | Expr::StringLit(_) | Expr::Ident(_) | Expr::StructInit { .. } => {}
+        }
+    }
+
+    // expect: report when a value of type `value` cannot be stored in `what: target`
+    fn expect(&mut self, what: &str, target: ScalarType, value: Ty) {
+        let what = if what.starts_with("argument") { what.to_string() } else { format!("'{}'", what) };
+        match value {
+            Ty::Dyn => {}
+            _ if target == ScalarType::Fixed => {}
+            Ty::Lit(n) => {
+                let (lo, hi) = target.range();
+                if n < lo || n > hi {
+                    self.error(format!("{} is {} but is given {}, which does not fit ({}..{})", what, target.name(), n, lo, hi));
+                }
+            }
+            Ty::Known(ScalarType::Fixed) => {
+                self.error(format!("{} is {} but is given a fixed value; convert it with int()", what, target.name()));
+            }
+            Ty::Known(ScalarType::Bool) => {}
+            Ty::Known(v) if target == ScalarType::Bool => {
+                self.error(format!("{} is bool but is given a {} value; compare it or convert it with bool()", what, v.name()));
+            }
+            Ty::Known(v) => {
+                if !target.contains(v) {
+                    self.error(format!("{} is {} but is given a {} value; convert it with {}()", what, target.name(), v.name(), target.name()));
+                }
+            }
+        }
+    }
+
+    fn ty(&self, scope: &Scope, e: &Expr) -> Ty {
+        match e {
+            Expr::Number(n) => Ty::Lit(*n),
+            Expr::Fixed(_) => Ty::Known(ScalarType::Fixed),
+            Expr::Ident(id) => self.var_type(scope, &id.name).map_or(Ty::Dyn, Ty::Known),
+            Expr::FieldAccess { target, field, .. } => self.field_type(scope, target, field).map_or(Ty::Dyn, Ty::Known),
+            Expr::Compare { .. } | Expr::Logic { .. } | Expr::Not(_) => Ty::Known(ScalarType::Bool),
+            Expr::Binary { op, left, right } => self.binary(*op, self.ty(scope, left), self.ty(scope, right)),
+            Expr::BitNot(inner) => match self.ty(scope, inner) {
+                Ty::Lit(n) => Ty::Lit(!n),
+                t => t,
+            },
+            Expr::Call(ci) if ci.args.len() == 1 => match ci.name.as_str() {
+                "fixed" => Ty::Known(ScalarType::Fixed),
+                "int" => Ty::Known(ScalarType::I16),
+                name => ScalarType::parse(name).map_or(Ty::Dyn, Ty::Known),
+            },
+            _ => Ty::Dyn,
+        }
+    }
+
+    // binary: result type of `a op b`; byte arithmetic with literals stays byte-typed (and wraps)
+    fn binary(&self, op: BinOp, a: Ty, b: Ty) -> Ty {
+        use ScalarType::*;
+        match (a, b) {
+            (Ty::Dyn, _) | (_, Ty::Dyn) => Ty::Dyn,
+            (Ty::Lit(x), Ty::Lit(y)) => fold(op, x, y).map_or(Ty::Dyn, Ty::Lit),
+            (Ty::Known(Fixed), _) | (_, Ty::Known(Fixed)) => Ty::Known(Fixed),
+            (Ty::Known(t), Ty::Lit(_)) | (Ty::Lit(_), Ty::Known(t)) => Ty::Known(if t == Bool { U8 } else { t }),
+            (Ty::Known(x), Ty::Known(y)) => {
+                let (x, y) = (if x == Bool { U8 } else { x }, if y == Bool { U8 } else { y });
+                Ty::Known(if x.contains(y) { x } else if y.contains(x) { y } else { I16 })
+            }
+        }
+    }
+}
+
+fn fold(op: BinOp, l: i32, r: i32) -> Option<i32> {
+    Some(match op {
+        BinOp::Add => l.wrapping_add(r),
+        BinOp::Sub => l.wrapping_sub(r),
+        BinOp::Mul => l.wrapping_mul(r),
+        BinOp::Div | BinOp::FloorDiv => l.checked_div(r)?,
+        BinOp::Mod => l.checked_rem(r)?,
+        BinOp::Shl => l.wrapping_shl((r & 0xF) as u32),
+        BinOp::Shr => l >> (r & 0xF),
+        BinOp::BitAnd => l & r,
+        BinOp::BitOr => l | r,
+        BinOp::BitXor => l ^ r,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lex;
+    use crate::parser::parse_with_filename;
+
+    fn check(src: &str) -> Vec<Diagnostic> {
+        let module = parse_with_filename(&lex(src).unwrap(), "types.vpy").unwrap();
+        let mut diags = Vec::new();
+        check_module(&module, &mut diags);
+        diags
+    }
+
+    #[test]
+    fn annotations_parse_on_globals_locals_params_and_fields() {
+        let src = "lives: u8 = 3\n\nstruct Ship:\n    x: i8\n    alive: bool\n\ndef hit(damage: u8, n):\n    left: i16 = lives - damage\n";
+        let module = parse_with_filename(&lex(src).unwrap(), "types.vpy").unwrap();
+        assert_eq!(global_types(&module).get("lives"), Some(&ScalarType::U8));
+        let Item::Function(f) = &module.items[2] else { panic!("expected function") };
+        assert_eq!(f.param_types, vec![Some("u8".to_string()), None]);
+        let locals = local_types(f);
+        assert_eq!(locals.get("damage"), Some(&ScalarType::U8));
+        assert_eq!(locals.get("left"), Some(&ScalarType::I16));
+        assert!(!locals.contains_key("n"));
+        assert!(check(src).is_empty());
+    }
+
+    #[test]
+    fn literals_must_fit() {
+        assert!(check("x: u8 = 255\ny: i8 = -128\nf: bool = True\n").is_empty());
+        let d = check("x: u8 = 256\n");
+        assert_eq!(d.len(), 1);
+        assert!(d[0].message.contains("'x' is u8 but is given 256"));
+        assert_eq!(check("flag: bool = 2\n").len(), 1);
+        assert_eq!(check("def main():\n    y: i8 = 200\n")[0].line, Some(2));
+    }
+
+    #[test]
+    fn narrowing_needs_a_conversion() {
+        let src = "big: i16 = 1000\nsmall: u8 = 0\n\ndef main():\n    small = big\n";
+        let d = check(src);
+        assert_eq!(d.len(), 1);
+        assert_eq!(d[0].code, DiagnosticCode::TypeMismatch);
+        assert!(d[0].message.contains("convert it with u8()"));
+        assert!(check("big: i16 = 1000\nsmall: u8 = 0\n\ndef main():\n    small = u8(big)\n    big = small\n").is_empty());
+        // signedness differs even at the same width
+        assert_eq!(check("a: i8 = 0\nb: u8 = 0\n\ndef main():\n    b = a\n").len(), 1);
+    }
+
+    #[test]
+    fn byte_arithmetic_with_literals_stays_byte_typed() {
+        assert!(check("x: u8 = 0\n\ndef main():\n    x = x + 1\n    x += 10\n").is_empty());
+        assert_eq!(check("x: u8 = 0\ny: i16 = 5\n\ndef main():\n    x += y\n").len(), 1);
+    }
+
+    #[test]
+    fn bools_take_comparisons_only() {
+        assert!(check("n: u8 = 3\nflag: bool = False\n\ndef main():\n    flag = n > 2\n    n = flag\n").is_empty());
+        let d = check("n: u8 = 3\nflag: bool = False\n\ndef main():\n    flag = n\n");
+        assert!(d[0].message.contains("bool()"));
+    }
+
+    #[test]
+    fn unannotated_values_are_not_checked() {
+        assert!(check("small: u8 = 0\n\ndef main():\n    v = J1_X()\n    small = v\n    small = J1_Y()\n").is_empty());
+    }
+
+    #[test]
+    fn call_arguments_and_struct_fields_are_checked() {
+        let src = "big: i16 = 0\n\nstruct Ship:\n    hp: u8\n\n    def damage(n: u8):\n        self.hp = self.hp - n\n\ndef heal(amount: u8):\n    pass\n\ndef main():\n    heal(300)\n    s = Ship()\n    s.hp = big\n    s.damage(big)\n";
+        let d = check(src);
+        assert_eq!(d.iter().map(|d| d.line.unwrap()).collect::<Vec<_>>(), vec![13, 15, 16]);
+    }
+
+    #[test]
+    fn unknown_annotations_are_reported() {
+        let d = check("x: u9 = 0\n\nstruct P:\n    pos: Vec2\n    next: P\n");
+        assert_eq!(d.iter().filter(|d| d.code == DiagnosticCode::UnknownType).count(), 2);
+    }
+}
@@ -206,7 +206,7 @@ pub fn unify_modules(
                         source_line: *source_line
                     });
                 }
-                Item::GlobalLet { name, value, source_line } => {
+                Item::GlobalLet { name, type_annotation, value, source_line } => {
                     let unified_name = name_map.get(&(module_id.clone(), name.clone()))
                         .cloned()
                         .unwrap_or_else(|| name.clone());
@@ -214,6 +214,7 @@ pub fn unify_modules(
                     let unified_value = rewrite_expr(value, &module_id, &symbols, &name_map, options);
                     unified_items.push(Item::GlobalLet { 
                         name: unified_name, 
+                        type_annotation: type_annotation.clone(),
                         value: unified_value,
                         source_line: *source_line
                     });
@@ -238,8 +239,9 @@ pub fn unify_modules(
                 Item::Export(_) => {
                     // Export declarations are metadata, not included in output
                 }
-                Item::StructDef(_) => {
+                Item::StructDef(_) | Item::Enum(_) => {
                     // Phase 3 - struct definitions included as-is for now
+                    // Enums too: variants are resolved by enum name after unification
                     unified_items.push(item.clone());
                 }
             }
@@ -296,6 +298,7 @@ fn rewrite_function(
         name: new_name.to_string(),
         line: f.line,
         params: f.params.clone(),
+        param_types: f.param_types.clone(),
         body: f.body.iter()
             .map(|s| rewrite_stmt(s, current_module, symbols, name_map, options))
             .collect(),
@@ -318,9 +321,10 @@ fn rewrite_stmt(
                 source_line: *source_line,
             }
         }
-        Stmt::Let { name, value, source_line } => {
+        Stmt::Let { name, type_annotation, value, source_line } => {
             Stmt::Let {
                 name: name.clone(),
+                type_annotation: type_annotation.clone(),
                 value: rewrite_expr(value, current_module, symbols, name_map, options),
                 source_line: *source_line,
             }
@@ -490,6 +494,7 @@ fn rewrite_expr(
         }
         // Literals pass through unchanged
         Expr::Number(n) => Expr::Number(*n),
+        Expr::Fixed(n) => Expr::Fixed(*n),
         Expr::StringLit(s) => Expr::StringLit(s.clone()),
         Expr::StructInit { struct_name, source_line, col } => {
             // Phase 3 - struct init passes through for now
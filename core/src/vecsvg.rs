@@ -0,0 +1,839 @@
+//! SVG import/export for `.vec` resources (`vectrexc svg2vec` / `vec2svg`)
+//!
+//! Export writes Inkscape-friendly SVG: the viewBox is the resource canvas
+//! centred on the origin, every layer is an Inkscape layer (`<g>`), every
+//! path a labelled `<path>` whose stroke opacity carries its intensity.
+//! Vectrex Y points up, SVG Y points down, so Y is mirrored both ways.
+//!
+//! Import reads `<path>`, `<polyline>`, `<polygon>`, `<line>`, `<rect>`,
+//! `<circle>` and `<ellipse>`, applies `transform`s, and flattens Béziers and
+//! arcs into polylines within a tolerance given in Vectrex units. Each
+//! top-level group becomes a layer (shapes outside any group go to
+//! `default`); nested groups are merged into their top-level layer.
+//! Per-point intensity overrides are not represented in SVG.
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{anyhow, bail, Context, Result};
+use roxmltree::{Document, Node};
+
+use crate::vecres::{Canvas, Layer, Point, VecPath, VecResource};
+
+const SVG_NS: &str = "http://www.w3.org/2000/svg";
+const INKSCAPE_NS: &str = "http://www.inkscape.org/namespaces/inkscape";
+/// Stroke colour of exported paths (the classic Vectrex phosphor look)
+const STROKE_COLOR: &str = "#7fff7f";
+/// Recursion limit when subdividing Béziers
+const MAX_BEZIER_DEPTH: u32 = 16;
+
+#[derive(Clone, Debug)]
+pub struct SvgImportOptions {
+    /// Maximum distance between a curve and its flattened polyline, in Vectrex units
+    pub tolerance: f64,
+    /// Fit the larger viewBox side to this many Vectrex units. `None` keeps
+    /// SVG user units as-is when the viewBox is at most 256 wide and high
+    /// (which is what `vec2svg` writes) and scales larger drawings down to 256.
+    pub size: Option<u16>,
+}
+
+impl Default for SvgImportOptions {
+    fn default() -> Self {
+        Self { tolerance: 0.5, size: None }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Export
+// ---------------------------------------------------------------------------
+
+/// Render a resource as SVG documents, as `(file-name suffix, SVG text)`.
+/// Without animations this is a single document (empty suffix) holding every
+/// layer; otherwise one document per animation frame, suffixed
+/// `_{animation}_{frame}`, holding just that frame's layer.
+pub fn export_svg(resource: &VecResource) -> Vec<(String, String)> {
+    if resource.animations.is_empty() {
+        let layers: Vec<&Layer> = resource.layers.iter().collect();
+        return vec![(String::new(), layers_to_svg(resource, &layers, None))];
+    }
+
+    let mut documents = Vec::new();
+    for animation in &resource.animations {
+        for (index, frame) in animation.frames.iter().enumerate() {
+            let layers: Vec<&Layer> = resource.layers.iter().filter(|l| l.name == frame.layer).collect();
+            let title = format!("{} {} frame {} ({} ms)", resource.name, animation.name, index, frame.duration);
+            let svg = layers_to_svg(resource, &layers, Some(&title));
+            documents.push((format!("_{}_{}", animation.name, index), svg));
+        }
+    }
+    documents
+}
+
+/// One SVG document containing the given layers
+pub fn layers_to_svg(resource: &VecResource, layers: &[&Layer], title: Option<&str>) -> String {
+    let (width, height) = (resource.canvas.width.max(1) as i32, resource.canvas.height.max(1) as i32);
+    let mut svg = String::new();
+    svg.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    svg.push_str(&format!(
+        "<svg xmlns=\"{}\" xmlns:inkscape=\"{}\" width=\"{}\" height=\"{}\" viewBox=\"{} {} {} {}\" style=\"background:#000\">\n",
+        SVG_NS, INKSCAPE_NS, width, height, -width / 2, -height / 2, width, height
+    ));
+    svg.push_str(&format!("  <title>{}</title>\n", escape(title.unwrap_or(&resource.name))));
+
+    let mut ids = HashSet::new();
+    for layer in layers {
+        let layer_id = unique_id(&mut ids, &layer.name, "layer");
+        let hidden = if layer.visible || layers.len() == 1 { "" } else { " style=\"display:none\"" };
+        svg.push_str(&format!(
+            "  <g id=\"{}\" inkscape:label=\"{}\" inkscape:groupmode=\"layer\"{}>\n",
+            escape(&layer_id), escape(&layer.name), hidden
+        ));
+        for (index, path) in layer.paths.iter().enumerate() {
+            if path.points.is_empty() {
+                continue;
+            }
+            let fallback = format!("{}_{}", layer.name, index);
+            let id = unique_id(&mut ids, &path.name, &fallback);
+            let label = if path.name.is_empty() { String::new() } else { format!(" inkscape:label=\"{}\"", escape(&path.name)) };
+            svg.push_str(&format!(
+                "    <path id=\"{}\"{} d=\"{}\" fill=\"none\" stroke=\"{}\" stroke-opacity=\"{:.4}\" stroke-width=\"1\" stroke-linejoin=\"round\"/>\n",
+                escape(&id), label, path_data(path), STROKE_COLOR, path.intensity.min(127) as f64 / 127.0
+            ));
+        }
+        svg.push_str("  </g>\n");
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn path_data(path: &VecPath) -> String {
+    let mut d = String::new();
+    for (i, p) in path.points.iter().enumerate() {
+        let command = if i == 0 { "M" } else { "L" };
+        d.push_str(&format!("{}{} {} ", command, p.x, -(p.y as i32)));
+    }
+    if path.closed {
+        d.push('Z');
+    }
+    d.trim_end().to_string()
+}
+
+fn unique_id(used: &mut HashSet<String>, name: &str, fallback: &str) -> String {
+    let base: String = if name.is_empty() { fallback } else { name }
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '_' || c == '-' || c == '.' { c } else { '_' })
+        .collect();
+    let base = if base.starts_with(|c: char| c.is_alphabetic() || c == '_') { base } else { format!("_{}", base) };
+    let mut id = base.clone();
+    let mut n = 2;
+    while !used.insert(id.clone()) {
+        id = format!("{}_{}", base, n);
+        n += 1;
+    }
+    id
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// ---------------------------------------------------------------------------
+// Import
+// ---------------------------------------------------------------------------
+
+/// Parse an SVG document into a resource named `name`
+pub fn import_svg(svg: &str, name: &str, options: &SvgImportOptions) -> Result<VecResource> {
+    let document = Document::parse(svg).context("Invalid SVG (XML parse error)")?;
+    let root = document.root_element();
+    if root.tag_name().name() != "svg" {
+        bail!("Not an SVG document (root element is <{}>)", root.tag_name().name());
+    }
+
+    let viewport = viewport(root);
+    let scale = match (options.size, viewport) {
+        (Some(size), Some((_, _, w, h))) => size as f64 / w.max(h),
+        (None, Some((_, _, w, h))) if w.max(h) > 256.0 => 256.0 / w.max(h),
+        _ => 1.0,
+    };
+
+    let mut importer = Importer {
+        tolerance: options.tolerance.max(0.01) / scale,
+        layers: Vec::new(),
+        counters: HashMap::new(),
+    };
+    let root_style = Style::default().child(root);
+    for child in root.children().filter(|n| n.is_element()) {
+        if child.tag_name().name() == "g" {
+            let style = root_style.child(child);
+            let label = child
+                .attribute((INKSCAPE_NS, "label"))
+                .or_else(|| child.attribute("id"))
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("layer{}", importer.layers.len()));
+            importer.layers.push(RawLayer { name: label, visible: !style.hidden, paths: Vec::new() });
+            let layer = importer.layers.len() - 1;
+            for grandchild in child.children().filter(|n| n.is_element()) {
+                importer.walk(grandchild, &style, Some(layer))?;
+            }
+        } else {
+            importer.walk(child, &root_style, None)?;
+        }
+    }
+
+    // Map SVG user space to Vectrex space: viewBox centre at the origin, Y up
+    let (cx, cy, width, height) = match viewport {
+        Some((x, y, w, h)) => (x + w / 2.0, y + h / 2.0, w, h),
+        None => {
+            let (min, max) = importer.bounds();
+            ((min.0 + max.0) / 2.0, (min.1 + max.1) / 2.0, max.0 - min.0, max.1 - min.1)
+        }
+    };
+    // Without a viewport the drawing's own bounds are fitted instead
+    let scale = match (options.size, viewport) {
+        (Some(size), None) if width.max(height) > 0.0 => size as f64 / width.max(height),
+        _ => scale,
+    };
+    let to_vectrex = |(x, y): (f64, f64)| Point {
+        x: ((x - cx) * scale).round().clamp(i16::MIN as f64, i16::MAX as f64) as i16,
+        y: ((cy - y) * scale).round().clamp(i16::MIN as f64, i16::MAX as f64) as i16,
+        intensity: None,
+    };
+
+    let mut resource = VecResource::new(name);
+    resource.canvas = Canvas {
+        width: ((width * scale).round() as u16).max(1),
+        height: ((height * scale).round() as u16).max(1),
+        ..Canvas::default()
+    };
+    let layers: Vec<Layer> = importer
+        .layers
+        .into_iter()
+        .map(|raw| Layer {
+            name: raw.name,
+            visible: raw.visible,
+            paths: raw
+                .paths
+                .into_iter()
+                .filter_map(|path| {
+                    // Drop points that only coincide after rounding (dense curve
+                    // flattening); vertices repeated in the source are kept
+                    let mut points: Vec<Point> = Vec::with_capacity(path.points.len());
+                    let mut previous = None;
+                    for raw in path.points {
+                        let p = to_vectrex(raw);
+                        let collapsed = points.last().is_some_and(|q| q.x == p.x && q.y == p.y) && previous != Some(raw);
+                        if !collapsed {
+                            points.push(p);
+                        }
+                        previous = Some(raw);
+                    }
+                    let (first, last) = (points[0], points[points.len() - 1]);
+                    if path.closed && points.len() > 2 && first.x == last.x && first.y == last.y {
+                        points.pop();
+                    }
+                    (points.len() >= 2).then_some(VecPath { name: path.name, intensity: path.intensity, closed: path.closed, points })
+                })
+                .collect(),
+        })
+        .collect();
+    if !layers.is_empty() {
+        resource.layers = layers;
+    }
+    Ok(resource)
+}
+
+/// viewBox, or (0, 0, width, height) when only the size is given
+fn viewport(root: Node) -> Option<(f64, f64, f64, f64)> {
+    if let Some(view_box) = root.attribute("viewBox") {
+        let v = parse_numbers(view_box);
+        if v.len() == 4 && v[2] > 0.0 && v[3] > 0.0 {
+            return Some((v[0], v[1], v[2], v[3]));
+        }
+    }
+    let length = |name: &str| root.attribute(name).and_then(|s| parse_numbers(s.trim_end_matches(|c: char| c.is_alphabetic() || c == '%')).first().copied());
+    match (length("width"), length("height")) {
+        (Some(w), Some(h)) if w > 0.0 && h > 0.0 => Some((0.0, 0.0, w, h)),
+        _ => None,
+    }
+}
+
+struct RawPath {
+    name: String,
+    intensity: u8,
+    closed: bool,
+    points: Vec<(f64, f64)>,
+}
+
+struct RawLayer {
+    name: String,
+    visible: bool,
+    paths: Vec<RawPath>,
+}
+
+struct Importer {
+    /// Flattening tolerance in SVG user units (before element transforms)
+    tolerance: f64,
+    layers: Vec<RawLayer>,
+    /// Per-tag counters for naming shapes without an id
+    counters: HashMap<String, usize>,
+}
+
+impl Importer {
+    fn default_layer(&mut self) -> usize {
+        if let Some(index) = self.layers.iter().position(|l| l.name == "default") {
+            return index;
+        }
+        self.layers.push(RawLayer { name: "default".to_string(), visible: true, paths: Vec::new() });
+        self.layers.len() - 1
+    }
+
+    /// Import `node` into `layer` (`None`: the `default` layer, created on first use)
+    fn walk(&mut self, node: Node, parent: &Style, layer: Option<usize>) -> Result<()> {
+        if node.tag_name().namespace().is_some_and(|ns| ns != SVG_NS) {
+            return Ok(());
+        }
+        let style = parent.child(node);
+        if style.hidden {
+            return Ok(());
+        }
+        let tag = node.tag_name().name();
+        if tag == "g" || tag == "a" {
+            for child in node.children().filter(|n| n.is_element()) {
+                self.walk(child, &style, layer)?;
+            }
+            return Ok(());
+        }
+
+        let mut pen = Flattener::new(style.transform, self.tolerance);
+        let number = |name: &str| node.attribute(name).and_then(|s| parse_numbers(s).first().copied()).unwrap_or(0.0);
+        match tag {
+            "path" => {
+                let d = node.attribute("d").unwrap_or_default();
+                parse_path_data(d, &mut pen).with_context(|| format!("Invalid path data in <path id=\"{}\">", node.attribute("id").unwrap_or("?")))?;
+            }
+            "line" => {
+                pen.move_to((number("x1"), number("y1")));
+                pen.line_to((number("x2"), number("y2")));
+            }
+            "polyline" | "polygon" => {
+                let coords = parse_numbers(node.attribute("points").unwrap_or_default());
+                for (i, pair) in coords.chunks_exact(2).enumerate() {
+                    if i == 0 { pen.move_to((pair[0], pair[1])) } else { pen.line_to((pair[0], pair[1])) }
+                }
+                if tag == "polygon" {
+                    pen.close();
+                }
+            }
+            "rect" => {
+                let (x, y, w, h) = (number("x"), number("y"), number("width"), number("height"));
+                if w <= 0.0 || h <= 0.0 {
+                    return Ok(());
+                }
+                let (rx, ry) = match (node.attribute("rx").map(|_| number("rx")), node.attribute("ry").map(|_| number("ry"))) {
+                    (Some(rx), Some(ry)) => (rx, ry),
+                    (Some(r), None) | (None, Some(r)) => (r, r),
+                    (None, None) => (0.0, 0.0),
+                };
+                let (rx, ry) = (rx.clamp(0.0, w / 2.0), ry.clamp(0.0, h / 2.0));
+                if rx > 0.0 && ry > 0.0 {
+                    pen.move_to((x + rx, y));
+                    pen.line_to((x + w - rx, y));
+                    pen.arc_to(rx, ry, 0.0, false, true, (x + w, y + ry));
+                    pen.line_to((x + w, y + h - ry));
+                    pen.arc_to(rx, ry, 0.0, false, true, (x + w - rx, y + h));
+                    pen.line_to((x + rx, y + h));
+                    pen.arc_to(rx, ry, 0.0, false, true, (x, y + h - ry));
+                    pen.line_to((x, y + ry));
+                    pen.arc_to(rx, ry, 0.0, false, true, (x + rx, y));
+                } else {
+                    pen.move_to((x, y));
+                    pen.line_to((x + w, y));
+                    pen.line_to((x + w, y + h));
+                    pen.line_to((x, y + h));
+                }
+                pen.close();
+            }
+            "circle" | "ellipse" => {
+                let (cx, cy) = (number("cx"), number("cy"));
+                let (rx, ry) = if tag == "circle" { (number("r"), number("r")) } else { (number("rx"), number("ry")) };
+                if rx <= 0.0 || ry <= 0.0 {
+                    return Ok(());
+                }
+                pen.move_to((cx + rx, cy));
+                pen.arc_to(rx, ry, 0.0, false, true, (cx - rx, cy));
+                pen.arc_to(rx, ry, 0.0, false, true, (cx + rx, cy));
+                pen.close();
+            }
+            _ => return Ok(()),
+        }
+
+        // Labels survive where ids had to be made unique
+        let base = match node.attribute((INKSCAPE_NS, "label")).or_else(|| node.attribute("id")) {
+            Some(label) => label.to_string(),
+            None => {
+                let counter = self.counters.entry(tag.to_string()).or_insert(0);
+                *counter += 1;
+                format!("{}{}", tag, counter)
+            }
+        };
+        let intensity = style.intensity();
+        let layer = match layer {
+            Some(layer) => layer,
+            None => self.default_layer(),
+        };
+        for (index, (points, closed)) in pen.finish().into_iter().enumerate() {
+            let name = if index == 0 { base.clone() } else { format!("{}_{}", base, index) };
+            self.layers[layer].paths.push(RawPath { name, intensity, closed, points });
+        }
+        Ok(())
+    }
+
+    fn bounds(&self) -> ((f64, f64), (f64, f64)) {
+        let mut min = (f64::MAX, f64::MAX);
+        let mut max = (f64::MIN, f64::MIN);
+        for p in self.layers.iter().flat_map(|l| l.paths.iter()).flat_map(|p| p.points.iter()) {
+            min = (min.0.min(p.0), min.1.min(p.1));
+            max = (max.0.max(p.0), max.1.max(p.1));
+        }
+        if min.0 > max.0 {
+            ((0.0, 0.0), (0.0, 0.0))
+        } else {
+            (min, max)
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Styles and transforms
+// ---------------------------------------------------------------------------
+
+/// Affine transform `[a b c d e f]` as in SVG `matrix()`
+#[derive(Clone, Copy, Debug)]
+struct Transform([f64; 6]);
+
+impl Transform {
+    const IDENTITY: Transform = Transform([1.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+
+    /// `self` applied after `inner`
+    fn then(self, inner: Transform) -> Transform {
+        let [a, b, c, d, e, f] = self.0;
+        let [a2, b2, c2, d2, e2, f2] = inner.0;
+        Transform([
+            a * a2 + c * b2,
+            b * a2 + d * b2,
+            a * c2 + c * d2,
+            b * c2 + d * d2,
+            a * e2 + c * f2 + e,
+            b * e2 + d * f2 + f,
+        ])
+    }
+
+    fn apply(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        let [a, b, c, d, e, f] = self.0;
+        (a * x + c * y + e, b * x + d * y + f)
+    }
+
+    /// Largest stretch factor, to turn output tolerances into local ones
+    fn scale(&self) -> f64 {
+        let [a, b, c, d, ..] = self.0;
+        (a * a + b * b).max(c * c + d * d).sqrt().max(1e-9)
+    }
+
+    fn parse(text: &str) -> Result<Transform> {
+        let mut result = Transform::IDENTITY;
+        let mut rest = text.trim();
+        while !rest.is_empty() {
+            let open = rest.find('(').ok_or_else(|| anyhow!("Invalid transform '{}'", text))?;
+            let close = rest.find(')').ok_or_else(|| anyhow!("Invalid transform '{}'", text))?;
+            let name = rest[..open].trim_matches(|c: char| c.is_whitespace() || c == ',');
+            let args = parse_numbers(&rest[open + 1..close]);
+            let arg = |i: usize, default: f64| args.get(i).copied().unwrap_or(default);
+            let step = match name {
+                "matrix" if args.len() == 6 => Transform([args[0], args[1], args[2], args[3], args[4], args[5]]),
+                "translate" => Transform([1.0, 0.0, 0.0, 1.0, arg(0, 0.0), arg(1, 0.0)]),
+                "scale" => Transform([arg(0, 1.0), 0.0, 0.0, arg(1, arg(0, 1.0)), 0.0, 0.0]),
+                "rotate" => {
+                    let (sin, cos) = arg(0, 0.0).to_radians().sin_cos();
+                    let (cx, cy) = (arg(1, 0.0), arg(2, 0.0));
+                    Transform([1.0, 0.0, 0.0, 1.0, cx, cy])
+                        .then(Transform([cos, sin, -sin, cos, 0.0, 0.0]))
+                        .then(Transform([1.0, 0.0, 0.0, 1.0, -cx, -cy]))
+                }
+                "skewX" => Transform([1.0, 0.0, arg(0, 0.0).to_radians().tan(), 1.0, 0.0, 0.0]),
+                "skewY" => Transform([1.0, arg(0, 0.0).to_radians().tan(), 0.0, 1.0, 0.0, 0.0]),
+                _ => bail!("Unsupported transform '{}'", &rest[..=close]),
+            };
+            result = result.then(step);
+            rest = rest[close + 1..].trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+        }
+        Ok(result)
+    }
+}
+
+/// Inherited presentation state
+#[derive(Clone, Copy, Debug)]
+struct Style {
+    transform: Transform,
+    /// Product of every ancestor's `opacity`
+    opacity: f64,
+    stroke_opacity: f64,
+    fill_opacity: f64,
+    stroked: bool,
+    hidden: bool,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self { transform: Transform::IDENTITY, opacity: 1.0, stroke_opacity: 1.0, fill_opacity: 1.0, stroked: true, hidden: false }
+    }
+}
+
+impl Style {
+    fn child(&self, node: Node) -> Style {
+        let declarations: HashMap<&str, &str> = node
+            .attribute("style")
+            .unwrap_or_default()
+            .split(';')
+            .filter_map(|d| d.split_once(':'))
+            .map(|(k, v)| (k.trim(), v.trim()))
+            .collect();
+        // CSS declarations win over presentation attributes
+        let property = |name: &str| declarations.get(name).copied().or_else(|| node.attribute(name)).map(str::trim);
+        let fraction = |name: &str| {
+            property(name).and_then(|v| match v.strip_suffix('%') {
+                Some(percent) => percent.parse::<f64>().ok().map(|p| p / 100.0),
+                None => v.parse::<f64>().ok(),
+            })
+            .map(|v| v.clamp(0.0, 1.0))
+        };
+
+        let mut style = *self;
+        if let Some(transform) = node.attribute("transform").and_then(|t| Transform::parse(t).ok()) {
+            style.transform = self.transform.then(transform);
+        }
+        style.opacity *= fraction("opacity").unwrap_or(1.0);
+        style.stroke_opacity = fraction("stroke-opacity").unwrap_or(self.stroke_opacity);
+        style.fill_opacity = fraction("fill-opacity").unwrap_or(self.fill_opacity);
+        if let Some(stroke) = property("stroke") {
+            style.stroked = stroke != "none";
+        }
+        style.hidden = property("display") == Some("none") || property("visibility") == Some("hidden");
+        style
+    }
+
+    /// Stroke opacity (fill opacity for unstroked shapes) scaled to 0-127
+    fn intensity(&self) -> u8 {
+        let alpha = if self.stroked { self.stroke_opacity } else { self.fill_opacity };
+        (alpha * self.opacity * 127.0).round().clamp(0.0, 127.0) as u8
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Geometry: path data and flattening
+// ---------------------------------------------------------------------------
+
+/// Turns drawing commands (in local coordinates) into transformed polylines
+struct Flattener {
+    transform: Transform,
+    /// Tolerance in local coordinates
+    tolerance: f64,
+    current: (f64, f64),
+    start: (f64, f64),
+    points: Vec<(f64, f64)>,
+    subpaths: Vec<(Vec<(f64, f64)>, bool)>,
+}
+
+impl Flattener {
+    fn new(transform: Transform, tolerance: f64) -> Self {
+        Self { transform, tolerance: tolerance / transform.scale(), current: (0.0, 0.0), start: (0.0, 0.0), points: Vec::new(), subpaths: Vec::new() }
+    }
+
+    fn flush(&mut self, closed: bool) {
+        if self.points.len() > 1 {
+            let points = std::mem::take(&mut self.points);
+            self.subpaths.push((points.iter().map(|&p| self.transform.apply(p)).collect(), closed));
+        }
+        self.points.clear();
+    }
+
+    fn move_to(&mut self, p: (f64, f64)) {
+        self.flush(false);
+        self.points.push(p);
+        self.current = p;
+        self.start = p;
+    }
+
+    fn line_to(&mut self, p: (f64, f64)) {
+        if self.points.is_empty() {
+            self.points.push(self.current);
+        }
+        self.points.push(p);
+        self.current = p;
+    }
+
+    fn close(&mut self) {
+        self.flush(true);
+        self.current = self.start;
+    }
+
+    fn cubic_to(&mut self, c1: (f64, f64), c2: (f64, f64), end: (f64, f64)) {
+        let start = self.current;
+        self.subdivide(start, c1, c2, end, 0);
+        self.current = end;
+    }
+
+    fn quad_to(&mut self, control: (f64, f64), end: (f64, f64)) {
+        let start = self.current;
+        let c1 = (start.0 + 2.0 / 3.0 * (control.0 - start.0), start.1 + 2.0 / 3.0 * (control.1 - start.1));
+        let c2 = (end.0 + 2.0 / 3.0 * (control.0 - end.0), end.1 + 2.0 / 3.0 * (control.1 - end.1));
+        self.cubic_to(c1, c2, end);
+    }
+
+    /// De Casteljau subdivision until the control points hug the chord
+    fn subdivide(&mut self, p0: (f64, f64), p1: (f64, f64), p2: (f64, f64), p3: (f64, f64), depth: u32) {
+        let flat = distance_to_line(p1, p0, p3).max(distance_to_line(p2, p0, p3)) <= self.tolerance;
+        if flat || depth >= MAX_BEZIER_DEPTH {
+            self.line_to(p3);
+            return;
+        }
+        let mid = |a: (f64, f64), b: (f64, f64)| ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0);
+        let (p01, p12, p23) = (mid(p0, p1), mid(p1, p2), mid(p2, p3));
+        let (p012, p123) = (mid(p01, p12), mid(p12, p23));
+        let split = mid(p012, p123);
+        self.subdivide(p0, p01, p012, split, depth + 1);
+        self.subdivide(split, p123, p23, p3, depth + 1);
+    }
+
+    /// SVG elliptical arc (endpoint parameterisation, SVG 1.1 appendix F.6)
+    fn arc_to(&mut self, rx: f64, ry: f64, rotation: f64, large_arc: bool, sweep: bool, end: (f64, f64)) {
+        let start = self.current;
+        let (mut rx, mut ry) = (rx.abs(), ry.abs());
+        if rx < 1e-9 || ry < 1e-9 || (start.0 - end.0).hypot(start.1 - end.1) < 1e-9 {
+            self.line_to(end);
+            return;
+        }
+        let (sin, cos) = rotation.to_radians().sin_cos();
+        let (dx, dy) = ((start.0 - end.0) / 2.0, (start.1 - end.1) / 2.0);
+        let (x1, y1) = (cos * dx + sin * dy, -sin * dx + cos * dy);
+        let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+        if lambda > 1.0 {
+            rx *= lambda.sqrt();
+            ry *= lambda.sqrt();
+        }
+        let numerator = (rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1).max(0.0);
+        let denominator = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+        let mut coefficient = (numerator / denominator).sqrt();
+        if large_arc == sweep {
+            coefficient = -coefficient;
+        }
+        let (cx1, cy1) = (coefficient * rx * y1 / ry, -coefficient * ry * x1 / rx);
+        let (cx, cy) = (cos * cx1 - sin * cy1 + (start.0 + end.0) / 2.0, sin * cx1 + cos * cy1 + (start.1 + end.1) / 2.0);
+
+        let angle = |ux: f64, uy: f64| uy.atan2(ux);
+        let theta1 = angle((x1 - cx1) / rx, (y1 - cy1) / ry);
+        let mut delta = angle((-x1 - cx1) / rx, (-y1 - cy1) / ry) - theta1;
+        if sweep && delta < 0.0 {
+            delta += std::f64::consts::TAU;
+        } else if !sweep && delta > 0.0 {
+            delta -= std::f64::consts::TAU;
+        }
+
+        // Largest step whose chord stays within tolerance of the ellipse
+        let radius = rx.max(ry);
+        let max_step = if self.tolerance >= radius { std::f64::consts::FRAC_PI_2 } else { 2.0 * (1.0 - self.tolerance / radius).acos() };
+        let steps = ((delta.abs() / max_step.max(1e-3)).ceil() as usize).clamp(1, 1024);
+        for i in 1..steps {
+            let t = theta1 + delta * i as f64 / steps as f64;
+            let (x, y) = (rx * t.cos(), ry * t.sin());
+            self.line_to((cos * x - sin * y + cx, sin * x + cos * y + cy));
+        }
+        self.line_to(end);
+    }
+
+    fn finish(mut self) -> Vec<(Vec<(f64, f64)>, bool)> {
+        self.flush(false);
+        self.subpaths
+    }
+}
+
+fn distance_to_line(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length = dx.hypot(dy);
+    if length < 1e-12 {
+        return (p.0 - a.0).hypot(p.1 - a.1);
+    }
+    ((p.0 - a.0) * dy - (p.1 - a.1) * dx).abs() / length
+}
+
+/// Numbers in an attribute value (`1,2 -3.5e1-4` style lists)
+fn parse_numbers(text: &str) -> Vec<f64> {
+    let mut lexer = PathLexer::new(text);
+    let mut numbers = Vec::new();
+    while lexer.at_number() {
+        match lexer.number() {
+            Ok(n) => numbers.push(n),
+            Err(_) => break,
+        }
+    }
+    numbers
+}
+
+struct PathLexer<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> PathLexer<'a> {
+    fn new(text: &'a str) -> Self {
+        Self { bytes: text.as_bytes(), pos: 0 }
+    }
+
+    fn skip_separators(&mut self) {
+        while self.pos < self.bytes.len() && (self.bytes[self.pos].is_ascii_whitespace() || self.bytes[self.pos] == b',') {
+            self.pos += 1;
+        }
+    }
+
+    fn at_number(&mut self) -> bool {
+        self.skip_separators();
+        matches!(self.bytes.get(self.pos), Some(b'0'..=b'9' | b'-' | b'+' | b'.'))
+    }
+
+    fn command(&mut self) -> Option<u8> {
+        self.skip_separators();
+        let c = *self.bytes.get(self.pos)?;
+        if c.is_ascii_alphabetic() {
+            self.pos += 1;
+            Some(c)
+        } else {
+            None
+        }
+    }
+
+    fn number(&mut self) -> Result<f64> {
+        self.skip_separators();
+        let start = self.pos;
+        let mut seen_dot = false;
+        let mut seen_exp = false;
+        if matches!(self.bytes.get(self.pos), Some(b'-' | b'+')) {
+            self.pos += 1;
+        }
+        while let Some(&c) = self.bytes.get(self.pos) {
+            match c {
+                b'0'..=b'9' => {}
+                b'.' if !seen_dot && !seen_exp => seen_dot = true,
+                b'e' | b'E' if !seen_exp => {
+                    seen_exp = true;
+                    if matches!(self.bytes.get(self.pos + 1), Some(b'-' | b'+')) {
+                        self.pos += 1;
+                    }
+                }
+                _ => break,
+            }
+            self.pos += 1;
+        }
+        let text = std::str::from_utf8(&self.bytes[start..self.pos]).unwrap_or_default();
+        text.parse::<f64>().map_err(|_| anyhow!("Expected a number at offset {}", start))
+    }
+
+    /// Arc flags may be packed without separators (`a5 5 0 011 1`)
+    fn flag(&mut self) -> Result<bool> {
+        self.skip_separators();
+        match self.bytes.get(self.pos) {
+            Some(b'0') => { self.pos += 1; Ok(false) }
+            Some(b'1') => { self.pos += 1; Ok(true) }
+            _ => Err(anyhow!("Expected an arc flag at offset {}", self.pos)),
+        }
+    }
+
+    fn point(&mut self) -> Result<(f64, f64)> {
+        Ok((self.number()?, self.number()?))
+    }
+}
+
+/// Interpret SVG path data, drawing into `pen`
+fn parse_path_data(d: &str, pen: &mut Flattener) -> Result<()> {
+    let mut lexer = PathLexer::new(d);
+    let mut command = match lexer.command() {
+        Some(c) if c.eq_ignore_ascii_case(&b'M') => c,
+        Some(c) => bail!("Path data must start with M, found '{}'", c as char),
+        None if lexer.at_number() => bail!("Path data must start with M"),
+        None => return Ok(()),
+    };
+    // Reflection point for S/T
+    let mut last_control: Option<(f64, f64)> = None;
+    loop {
+        let relative = command.is_ascii_lowercase();
+        let origin = if relative { pen.current } else { (0.0, 0.0) };
+        let offset = |p: (f64, f64)| (p.0 + origin.0, p.1 + origin.1);
+        let reflected = |control: Option<(f64, f64)>, current: (f64, f64)| match control {
+            Some(c) => (2.0 * current.0 - c.0, 2.0 * current.1 - c.1),
+            None => current,
+        };
+        let mut control = None;
+        match command.to_ascii_uppercase() {
+            b'M' => {
+                pen.move_to(offset(lexer.point()?));
+                // Further coordinate pairs are implicit line-tos
+                command = if relative { b'l' } else { b'L' };
+            }
+            b'L' => pen.line_to(offset(lexer.point()?)),
+            b'H' => {
+                let x = lexer.number()? + origin.0;
+                pen.line_to((x, pen.current.1));
+            }
+            b'V' => {
+                let y = lexer.number()? + origin.1;
+                pen.line_to((pen.current.0, y));
+            }
+            b'C' => {
+                let (c1, c2, end) = (offset(lexer.point()?), offset(lexer.point()?), offset(lexer.point()?));
+                pen.cubic_to(c1, c2, end);
+                control = Some(c2);
+            }
+            b'S' => {
+                let c1 = reflected(last_control, pen.current);
+                let (c2, end) = (offset(lexer.point()?), offset(lexer.point()?));
+                pen.cubic_to(c1, c2, end);
+                control = Some(c2);
+            }
+            b'Q' => {
+                let (c, end) = (offset(lexer.point()?), offset(lexer.point()?));
+                pen.quad_to(c, end);
+                control = Some(c);
+            }
+            b'T' => {
+                let c = reflected(last_control, pen.current);
+                let end = offset(lexer.point()?);
+                pen.quad_to(c, end);
+                control = Some(c);
+            }
+            b'A' => {
+                let (rx, ry, rotation) = (lexer.number()?, lexer.number()?, lexer.number()?);
+                let (large_arc, sweep) = (lexer.flag()?, lexer.flag()?);
+                let end = offset(lexer.point()?);
+                pen.arc_to(rx, ry, rotation, large_arc, sweep, end);
+            }
+            b'Z' => pen.close(),
+            _ => bail!("Unknown path command '{}'", command as char),
+        }
+
+        let previous = command;
+        if lexer.at_number() && !command.eq_ignore_ascii_case(&b'Z') {
+            // Implicit repetition of the previous command
+        } else {
+            match lexer.command() {
+                Some(next) => command = next,
+                None if lexer.pos >= lexer.bytes.len() => break,
+                None => bail!("Unexpected character at offset {} in path data", lexer.pos),
+            }
+        }
+        // S only reflects a previous C/S control point, T only a previous Q/T one
+        let curve_family = |c: u8| match c.to_ascii_uppercase() {
+            b'C' | b'S' => 1,
+            b'Q' | b'T' => 2,
+            _ => 0,
+        };
+        last_control = control.filter(|_| curve_family(previous) != 0 && curve_family(previous) == curve_family(command));
+    }
+    Ok(())
+}
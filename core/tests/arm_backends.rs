@@ -0,0 +1,132 @@
+// Pitrex (ARM) and VecFever/Vextreme (Cortex-M) backends.
+//
+// The emitted assembly is compared against checked-in fixtures under
+// tests/fixtures/arm. Run with UPDATE_FIXTURES=1 to regenerate them after an
+// intentional codegen change.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::PathBuf;
+
+use vectrex_lang::codegen::{emit_asm, emit_asm_with_diagnostics, CodegenOptions, DiagnosticCode, DiagnosticSeverity};
+use vectrex_lang::target::Target;
+use vectrex_lang::{lex, parse_with_filename, resolver, unifier, Module};
+
+fn opts() -> CodegenOptions {
+    CodegenOptions {
+        title: "ARM DEMO".to_string(),
+        auto_loop: true,
+        diag_freeze: false,
+        force_extended_jsr: false,
+        _bank_size: 0,
+        per_frame_silence: false,
+        debug_init_draw: false,
+        blink_intensity: false,
+        exclude_ram_org: false,
+        fast_wait: false,
+        source_path: None,
+        output_name: None,
+        assets: vec![],
+        const_values: BTreeMap::new(),
+        const_arrays: BTreeMap::new(),
+        const_string_arrays: BTreeSet::new(),
+        mutable_arrays: BTreeSet::new(),
+        structs: HashMap::new(),
+        type_context: HashMap::new(),
+        global_types: HashMap::new(),
+        buffer_requirements: None,
+    }
+}
+
+fn fixtures() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/arm")
+}
+
+fn parse(src: &str) -> Module {
+    let tokens = lex(src).expect("lex ok");
+    parse_with_filename(&tokens, "demo.vpy").expect("parse ok")
+}
+
+fn check_fixture(target: Target) {
+    let src = std::fs::read_to_string(fixtures().join("demo.vpy")).unwrap();
+    let asm = emit_asm(&parse(&src), target, &opts());
+    assert!(!asm.is_empty(), "codegen failed for {}", target);
+
+    let path = fixtures().join(format!("demo.{}.s", target));
+    if std::env::var_os("UPDATE_FIXTURES").is_some() {
+        std::fs::write(&path, &asm).unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(&path).unwrap_or_default();
+    if asm != expected {
+        let line = asm.lines().zip(expected.lines()).position(|(a, e)| a != e).unwrap_or(0) + 1;
+        panic!(
+            "{} output differs from {} at line {} (UPDATE_FIXTURES=1 to accept):\n  got:      {}\n  expected: {}",
+            target,
+            path.display(),
+            line,
+            asm.lines().nth(line - 1).unwrap_or("<eof>"),
+            expected.lines().nth(line - 1).unwrap_or("<eof>")
+        );
+    }
+}
+
+#[test]
+fn pitrex_matches_fixture() {
+    check_fixture(Target::Pitrex);
+}
+
+#[test]
+fn vecfever_matches_fixture() {
+    check_fixture(Target::Vecfever);
+}
+
+#[test]
+fn vextreme_links_against_its_own_sdk() {
+    let src = std::fs::read_to_string(fixtures().join("demo.vpy")).unwrap();
+    let asm = emit_asm(&parse(&src), Target::Vextreme, &opts());
+    for sym in ["vx_init", "vx_wait_recal", "vx_line", "vx_print", "vx_buttons", "vx_joystick", "vx_intensity"] {
+        assert!(asm.contains(sym), "missing SDK call {}", sym);
+    }
+    assert!(asm.contains(".thumb_func"));
+    assert!(!asm.contains("pitrex_") && !asm.contains("vf_"));
+}
+
+#[test]
+fn unsupported_builtins_fail_the_build() {
+    let (asm, diags) = emit_asm_with_diagnostics(&parse("def loop():\n    PLAY_SFX(\"boom\")\n"), Target::Pitrex, &opts());
+    assert!(asm.is_empty());
+    let err = diags
+        .iter()
+        .find(|d| d.severity == DiagnosticSeverity::Error)
+        .expect("unsupported builtin should be an error");
+    assert!(matches!(err.code, DiagnosticCode::UnsupportedBuiltin));
+    assert_eq!(err.message, "PLAY_SFX is not supported on Pitrex");
+    assert_eq!(err.line, Some(2));
+}
+
+#[test]
+fn sdk_targets_are_reported_as_experimental() {
+    let (asm, diags) = emit_asm_with_diagnostics(&parse("def loop():\n    MOVE(0, 0)\n"), Target::Vecfever, &opts());
+    assert!(!asm.is_empty());
+    assert!(diags
+        .iter()
+        .any(|d| d.severity == DiagnosticSeverity::Warning && d.message.starts_with("VecFever is experimental")));
+}
+
+#[test]
+fn imported_modules_are_emitted_with_prefixed_symbols() {
+    let dir = tempfile::tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir_all(&src_dir).unwrap();
+    std::fs::write(src_dir.join("utils.vpy"), "speed = 4\n\ndef scale(v):\n    return v * speed\n").unwrap();
+    std::fs::write(src_dir.join("main.vpy"), "from utils import scale\n\ndef loop():\n    MOVE(0, 0)\n    DRAW_TO(scale(10), 0)\n").unwrap();
+
+    let mut res = resolver::ModuleResolver::new(dir.path().to_path_buf());
+    res.load_project(&src_dir.join("main.vpy")).unwrap();
+    let unified = unifier::unify_modules(&res, "main", &unifier::UnifyOptions::default()).unwrap();
+    let asm = emit_asm(&unified.module, Target::Vecfever, &opts());
+
+    assert!(asm.contains("BL vpy_utils_scale"));
+    assert!(asm.contains("vpy_utils_scale:"));
+    assert!(asm.contains("VAR_UTILS_SPEED: .word 4"));
+}
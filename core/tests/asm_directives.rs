@@ -0,0 +1,52 @@
+// Directives in the vectrexc copy of the native assembler: macros,
+// conditionals, REPT, STRUCT, ALIGN and SETDP in one lwasm-style source,
+// plus label forms and `*`.
+
+use vectrex_lang::backend::asm_to_binary::assemble_m6809;
+
+const SOURCE: &str = "\
+VARIANT EQU 1
+Obj     STRUCT
+x       RMB 1
+y       RMB 1
+        ENDSTRUCT
+CLEAR   MACRO
+        CLR \\1,X
+        ENDM
+        SETDP $D0
+        IF VARIANT
+        CLEAR Obj.y
+        ELSE
+        CLEAR Obj.x
+        ENDIF
+        REPT 2
+        LSRA
+        ENDR
+        STA $D001
+        ALIGN 4
+        LDB #sizeof{Obj}
+";
+
+#[test]
+fn directives_expand() {
+    let (bytes, _, _) = assemble_m6809(SOURCE, 0xC000).unwrap();
+    assert_eq!(bytes, vec![0x6F, 0x01, 0x44, 0x44, 0x97, 0x01, 0x00, 0x00, 0xC6, 0x02]);
+}
+
+#[test]
+fn labels_with_and_without_colon_and_current_location() {
+    let source = "\
+START   LDA #1
+        BRA *
+LOOP:   LDB #2 ; code after a label
+        BNE LOOP
+HERE    EQU *
+        FDB *,HERE
+RTS
+        BRA START
+";
+    let (bytes, _, symbols) = assemble_m6809(source, 0xC000).unwrap();
+    assert_eq!(bytes, vec![0x86, 0x01, 0x20, 0xFE, 0xC6, 0x02, 0x26, 0xFC, 0xC0, 0x08, 0xC0, 0x08, 0x39, 0x20, 0xF1]);
+    assert_eq!(symbols.get("START"), Some(&0xC000));
+    assert_eq!(symbols.get("LOOP"), Some(&0xC004));
+}
@@ -23,12 +23,13 @@ fn builtin_arities_stable() {
         Case { name: "WAIT_RECAL", ok_arity: 0, bad_arity: 1 },
         Case { name: "PLAY_MUSIC1", ok_arity: 0, bad_arity: 1 },
         Case { name: "DBG_STATIC_VL", ok_arity: 0, bad_arity: 1 },
+        Case { name: "PLAY_SFX", ok_arity: 2, bad_arity: 3 },
     ];
 
     for c in cases {        
         // Construir función main con llamada de aridad correcta
         let ok_args: Vec<Expr> = (0..c.ok_arity).map(|i| Expr::Number(i as i32)).collect();
-        let ok_module = Module { items: vec![Item::Function(Function { name: "main".into(), line: 0, params: vec![], body: vec![
+        let ok_module = Module { items: vec![Item::Function(Function { name: "main".into(), line: 0, params: vec![], param_types: vec![], body: vec![
             Stmt::Expr(Expr::Call(CallInfo { name: c.name.into(), source_line: 0, col: 0, args: ok_args }), 0)
         ]})], imports: vec![], meta: ModuleMeta::default() };
         let (_asm, diags) = emit_asm_with_diagnostics(&ok_module, Target::Vectrex, &CodegenOptions { title: "t".into(), auto_loop: false, diag_freeze: false, force_extended_jsr: false, _bank_size: 0, per_frame_silence: false, debug_init_draw: false, blink_intensity: false, exclude_ram_org: false, fast_wait: false, source_path: None, assets: vec![],
@@ -38,6 +39,7 @@ fn builtin_arities_stable() {
             mutable_arrays: std::collections::BTreeSet::new(),
             structs: std::collections::HashMap::new(),
             type_context: std::collections::HashMap::new(),
+            global_types: std::collections::HashMap::new(),
             output_name: None,
             buffer_requirements: None,
         });
@@ -45,7 +47,7 @@ fn builtin_arities_stable() {
 
         // Construir función main con llamada de aridad incorrecta
         let bad_args: Vec<Expr> = (0..c.bad_arity).map(|i| Expr::Number(i as i32)).collect();
-        let bad_module = Module { items: vec![Item::Function(Function { name: "main".into(), line: 0, params: vec![], body: vec![
+        let bad_module = Module { items: vec![Item::Function(Function { name: "main".into(), line: 0, params: vec![], param_types: vec![], body: vec![
             Stmt::Expr(Expr::Call(CallInfo { name: c.name.into(), source_line: 0, col: 0, args: bad_args }), 0)
         ]})], imports: vec![], meta: ModuleMeta::default() };
         let (_asm_bad, diags_bad) = emit_asm_with_diagnostics(&bad_module, Target::Vectrex, &CodegenOptions { title: "t".into(), auto_loop: false, diag_freeze: false, force_extended_jsr: false, _bank_size: 0, per_frame_silence: false, debug_init_draw: false, blink_intensity: false, exclude_ram_org: false, fast_wait: false, source_path: None, assets: vec![],
@@ -55,6 +57,7 @@ fn builtin_arities_stable() {
             mutable_arrays: std::collections::BTreeSet::new(),
             structs: std::collections::HashMap::new(),
             type_context: std::collections::HashMap::new(),
+            global_types: std::collections::HashMap::new(),
             output_name: None,
             buffer_requirements: None,
         });
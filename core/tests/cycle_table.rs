@@ -0,0 +1,64 @@
+// Static cycle table (backend/m6809_opcodes.rs) against the emulator's 6809
+// core: every line is assembled, executed once and the elapsed cycles compared.
+
+use vectrex_emulator::CPU;
+use vectrex_lang::backend::asm_to_binary::assemble_m6809;
+use vectrex_lang::backend::m6809_opcodes::instruction_cycles;
+
+const CODE: u16 = 0x1000;
+
+// measured: cycles the emulator spends on the first instruction of `line`
+fn measured(line: &str) -> u64 {
+    let src = format!("VAR EQU $C880\n    {}\nTARGET:\n    NOP\n", line);
+    let (bytes, _, _) = assemble_m6809(&src, CODE).unwrap_or_else(|e| panic!("{}: {}", line, e));
+    let mut cpu = CPU::default();
+    cpu.bus.mem[CODE as usize..CODE as usize + bytes.len()].copy_from_slice(&bytes);
+    cpu.pc = CODE;
+    cpu.s = 0xCBE0;
+    cpu.u = 0xCB00;
+    cpu.x = 0xC900;
+    cpu.y = 0xC980;
+    cpu.dp = 0xC8;
+    let before = cpu.cycles;
+    cpu.step();
+    cpu.cycles - before
+}
+
+fn estimated(line: &str) -> u64 {
+    let code = line.split(';').next().unwrap().trim();
+    let (m, o) = code.split_once(char::is_whitespace).unwrap_or((code, ""));
+    let operand: String = o.chars().filter(|c| !c.is_whitespace()).collect();
+    instruction_cycles(m, &operand).unwrap_or_else(|| panic!("no cycles for {}", line)) as u64
+}
+
+const LINES: &[&str] = &[
+    // inherent
+    "NOP", "SEX", "MUL", "ABX", "DAA", "CLRA", "COMB", "NEGA", "INCB", "TSTA", "LSRB", "ASLA", "RTS",
+    "ANDCC #$FF", "ORCC #$00", "EXG A,B", "TFR A,B", "PSHS D,X", "PULS A", "PSHU Y,X,B",
+    // 8-bit
+    "LDA #1", "LDB <$10", "LDA VAR", "ADDA ,X", "SUBB 5,X", "CMPA -100,Y",
+    "LDA A,X", "LDB B,Y", "ANDA D,X", "LDA ,X+", "LDB ,X++", "LDA ,-Y", "LDB ,--X",
+    "STA VAR", "STB <$20", "STA 3,S", "EORA #$FF", "BITB VAR", "ADCA #1", "ADCB VAR",
+    // 16-bit
+    "LDD #1000", "LDD VAR", "LDU ,X", "STD VAR", "STX 4,S",
+    "ADDD #1", "SUBD VAR", "ADDD 20,X", "LDD 0,S",
+    "LDY #1", "STY VAR", "CMPD #5", "CMPU #0", "STS VAR",
+    // read-modify-write
+    "CLR VAR", "DEC ,X", "NEG VAR", "COM ,X", "ROR <$10", "ASR 2,Y",
+    // indirect / PC-relative
+    "LDA [,X]", "LDX [4,Y]", "LEAX TARGET,PCR",
+    // LEA / flow
+    "LEAX 1,X", "LEAS -4,S", "LEAU D,U",
+    "BRA TARGET", "BNE TARGET", "BSR TARGET", "LBRA TARGET", "LBSR TARGET", "LBNE TARGET",
+    "JSR TARGET", "JMP TARGET",
+];
+
+#[test]
+fn table_matches_the_emulator() {
+    let mut mismatches = Vec::new();
+    for line in LINES {
+        let (est, got) = (estimated(line), measured(line));
+        if est != got { mismatches.push(format!("{}: table {} emulator {}", line, est, got)); }
+    }
+    assert!(mismatches.is_empty(), "\n{}", mismatches.join("\n"));
+}
@@ -0,0 +1,182 @@
+// Fixed-point 8.8 type: end-to-end codegen and the FIXMUL/FIXDIV runtime helpers.
+//
+// The helpers are assembled with the native assembler and executed on the
+// emulator's 6809 core against a host-side reference.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use vectrex_emulator::CPU;
+use vectrex_lang::backend::asm_to_binary::assemble_m6809;
+use vectrex_lang::backend::m6809::emit_fixed_helpers;
+use vectrex_lang::codegen::{emit_asm, emit_asm_with_diagnostics, CodegenOptions, DiagnosticCode};
+use vectrex_lang::target::Target;
+use vectrex_lang::{lex, parse_with_filename, Module};
+
+fn opts() -> CodegenOptions {
+    CodegenOptions {
+        title: "FIXED".to_string(),
+        auto_loop: true,
+        diag_freeze: false,
+        force_extended_jsr: false,
+        _bank_size: 0,
+        per_frame_silence: false,
+        debug_init_draw: false,
+        blink_intensity: false,
+        exclude_ram_org: false,
+        fast_wait: false,
+        source_path: None,
+        output_name: None,
+        assets: vec![],
+        const_values: BTreeMap::new(),
+        const_arrays: BTreeMap::new(),
+        const_string_arrays: BTreeSet::new(),
+        mutable_arrays: BTreeSet::new(),
+        structs: HashMap::new(),
+        type_context: HashMap::new(),
+        global_types: HashMap::new(),
+        buffer_requirements: None,
+    }
+}
+
+fn parse(src: &str) -> Module {
+    let tokens = lex(src).expect("lex ok");
+    parse_with_filename(&tokens, "fixed.vpy").expect("parse ok")
+}
+
+const PHYSICS: &str = "\
+gravity = 0.25
+y = 100.0
+vy = 0.0
+
+def main():
+    SET_INTENSITY(127)
+
+def loop():
+    step()
+    MOVE(0, int(y))
+    DRAW_TO(10, int(y))
+
+def step():
+    vy = vy - gravity
+    y = y + vy * 0.5
+    if y < -100:
+        vy = vy / -1.25
+";
+
+// ------------------------------------------------------------------ codegen
+
+#[test]
+fn vectrex_program_uses_mul_based_helpers() {
+    let asm = emit_asm(&parse(PHYSICS), Target::Vectrex, &opts());
+    assert!(!asm.is_empty(), "codegen failed");
+    assert!(asm.contains("JSR FIXMUL"));
+    assert!(asm.contains("JSR FIXDIV"));
+    assert!(asm.contains("FIXMUL:") && asm.contains("FIXDIV:"));
+    assert!(asm.contains("FIX_A"), "helper RAM not allocated");
+    // 0.25 and 100.0 are stored as raw 8.8 words
+    assert!(asm.contains("#64") && asm.contains("#25600"));
+    // int() takes the integer byte
+    assert!(asm.contains("    LDB RESULT\n    SEX\n"));
+}
+
+#[test]
+fn add_and_sub_stay_plain_16_bit() {
+    let asm = emit_asm(&parse("x = 1.5\n\ndef main():\n    x = 0.5\n\ndef loop():\n    x = x + 0.25\n"), Target::Vectrex, &opts());
+    assert!(asm.contains("ADDD"));
+    assert!(!asm.contains("JSR FIXMUL") && !asm.contains("FIXMUL:"));
+}
+
+#[test]
+fn arm_targets_lower_fixed_ops_inline() {
+    let asm = emit_asm(&parse(PHYSICS), Target::Pitrex, &opts());
+    assert!(asm.contains("ASR r0, r0, #8"));
+    assert!(asm.contains("LSL r0, r0, #8"));
+}
+
+#[test]
+fn mixing_without_conversion_is_reported() {
+    let src = "def loop():\n    n = J1_X()\n    x = 0.5\n    x = x + n\n";
+    let (asm, diags) = emit_asm_with_diagnostics(&parse(src), Target::Vectrex, &opts());
+    assert!(asm.is_empty());
+    let err = diags.iter().find(|d| d.code == DiagnosticCode::TypeMismatch).expect("type error");
+    assert_eq!(err.line, Some(4));
+}
+
+#[test]
+fn literals_out_of_range_are_rejected() {
+    assert!(lex("x = 128.0\n").is_err());
+    assert!(lex("x = 127.5\n").is_ok());
+}
+
+// ------------------------------------------------------------ runtime helpers
+
+const RAM: u16 = 0xC880;
+const CODE: u16 = 0x1000;
+
+// run_helper: FIX_A = a, FIX_B = b, JSR helper, return RESULT
+fn run_helper(helper: &str, a: i16, b: i16) -> i16 {
+    let mut src = String::new();
+    let mut at = RAM;
+    for (name, size) in [("RESULT", 2), ("FIX_A", 2), ("FIX_B", 2), ("FIX_RES", 2), ("FIX_N", 3), ("FIX_R", 2), ("FIX_SIGN", 1), ("FIX_CNT", 1)] {
+        src.push_str(&format!("{} EQU ${:04X}\n", name, at));
+        at += size;
+    }
+    src.push_str(&format!(
+        "    LDD #{}\n    STD FIX_A\n    LDD #{}\n    STD FIX_B\n    JSR {}\nHALT:\n    BRA HALT\n",
+        a, b, helper
+    ));
+    emit_fixed_helpers(&mut src);
+
+    let (bytes, _, symbols) = assemble_m6809(&src, CODE).expect("helpers assemble");
+    let halt = symbols["HALT"];
+
+    let mut cpu = CPU::default();
+    cpu.bus.mem[CODE as usize..CODE as usize + bytes.len()].copy_from_slice(&bytes);
+    cpu.pc = CODE;
+    cpu.s = 0xCBE0;
+    for _ in 0..20_000 {
+        if cpu.pc == halt {
+            let r = RAM as usize;
+            return i16::from_be_bytes([cpu.bus.mem[r], cpu.bus.mem[r + 1]]);
+        }
+        cpu.step();
+    }
+    panic!("{}({}, {}) did not return", helper, a, b);
+}
+
+fn reference_mul(a: i16, b: i16) -> i16 {
+    let mag = ((a as i32).unsigned_abs() * (b as i32).unsigned_abs()) >> 8;
+    let v = if (a < 0) != (b < 0) { (mag as i32).wrapping_neg() } else { mag as i32 };
+    v as i16
+}
+
+fn reference_div(a: i16, b: i16) -> i16 {
+    let mag = if b == 0 { 0x7FFF } else { ((a as i32).unsigned_abs() << 8) / (b as i32).unsigned_abs() };
+    let v = if (a < 0) != (b < 0) { (mag as i32).wrapping_neg() } else { mag as i32 };
+    v as i16
+}
+
+const SAMPLES: &[i16] = &[0, 1, 64, 128, 256, 384, -256, -384, 640, -1000, 2560, 12345, -32000, 32767, 3];
+
+#[test]
+fn fixmul_matches_reference() {
+    assert_eq!(run_helper("FIXMUL", 384, 512), 768); // 1.5 * 2.0 = 3.0
+    assert_eq!(run_helper("FIXMUL", -192, -192), 144); // -0.75 * -0.75 = 0.5625
+    for &a in SAMPLES {
+        for &b in SAMPLES {
+            assert_eq!(run_helper("FIXMUL", a, b), reference_mul(a, b), "FIXMUL({}, {})", a, b);
+        }
+    }
+}
+
+#[test]
+fn fixdiv_matches_reference() {
+    assert_eq!(run_helper("FIXDIV", 768, 512), 384); // 3.0 / 2.0 = 1.5
+    assert_eq!(run_helper("FIXDIV", 256, 768), 85); // 1/3
+    assert_eq!(run_helper("FIXDIV", -256, 0), -0x7FFF);
+    for &a in SAMPLES {
+        for &b in SAMPLES {
+            assert_eq!(run_helper("FIXDIV", a, b), reference_div(a, b), "FIXDIV({}, {})", a, b);
+        }
+    }
+}
@@ -0,0 +1,387 @@
+@ --- Cortex-M backend (VecFever) --- title='ARM DEMO' origin=$8000 ---
+@ SDK: vf_init, vf_line, vf_wait_recal, vf_intensity, vf_print, vf_buttons, vf_joystick
+    .syntax unified
+    .thumb
+    .text
+
+    .balign 4
+    .global main
+    .type main, %function
+    .thumb_func
+main:
+    PUSH {r7, lr}
+    MOV r7, sp
+    BL vf_init
+    BL vpy_main
+.Lframe:
+    BL vf_wait_recal
+    BL vpy_loop
+    B .Lframe
+
+    .balign 4
+    .type vpy_Ship_step, %function
+    .thumb_func
+vpy_Ship_step:
+    PUSH {r7, lr}
+    SUB sp, sp, #8
+    MOV r7, sp
+    STR r0, [r7, #0]
+    STR r1, [r7, #4]
+    LDR r0, [r7, #0]
+    LDR r0, [r0, #0]
+    STR r0, [sp, #-8]!
+    LDR r0, [r7, #4]
+    MOV r1, #3
+    MUL r0, r0, r1
+    SXTH r0, r0
+    MOV r1, r0
+    LDR r0, [sp], #8
+    ADD r0, r0, r1
+    SXTH r0, r0
+    LDR r1, [r7, #0]
+    STR r0, [r1, #0]
+    LDR r0, [r7, #0]
+    LDR r0, [r0, #0]
+.Lvpy_Ship_step_ret:
+    MOV sp, r7
+    ADD sp, sp, #8
+    POP {r7, pc}
+    .ltorg
+
+    .balign 4
+    .type vpy_Ship_init, %function
+    .thumb_func
+vpy_Ship_init:
+    PUSH {r7, lr}
+    SUB sp, sp, #16
+    MOV r7, sp
+    STR r0, [r7, #0]
+    STR r1, [r7, #4]
+    STR r2, [r7, #8]
+    LDR r0, [r7, #4]
+    LDR r1, [r7, #0]
+    STR r0, [r1, #0]
+    LDR r0, [r7, #8]
+    LDR r1, [r7, #0]
+    STR r0, [r1, #4]
+.Lvpy_Ship_init_ret:
+    MOV sp, r7
+    ADD sp, sp, #16
+    POP {r7, pc}
+    .ltorg
+
+    .balign 4
+    .type vpy_add5, %function
+    .thumb_func
+vpy_add5:
+    PUSH {r7, lr}
+    SUB sp, sp, #24
+    MOV r7, sp
+    STR r0, [r7, #0]
+    STR r1, [r7, #4]
+    STR r2, [r7, #8]
+    STR r3, [r7, #12]
+    LDR r0, [r7, #32]
+    STR r0, [r7, #16]
+    LDR r0, [r7, #0]
+    LDR r1, [r7, #4]
+    ADD r0, r0, r1
+    SXTH r0, r0
+    LDR r1, [r7, #8]
+    ADD r0, r0, r1
+    SXTH r0, r0
+    LDR r1, [r7, #12]
+    ADD r0, r0, r1
+    SXTH r0, r0
+    LDR r1, [r7, #16]
+    ADD r0, r0, r1
+    SXTH r0, r0
+.Lvpy_add5_ret:
+    MOV sp, r7
+    ADD sp, sp, #24
+    POP {r7, pc}
+    .ltorg
+
+    .balign 4
+    .type vpy_main, %function
+    .thumb_func
+vpy_main:
+    PUSH {r7, lr}
+    MOV r7, sp
+    MOV r0, #100
+    BL __vpy_set_intensity
+.Lvpy_main_ret:
+    MOV sp, r7
+    POP {r7, pc}
+    .ltorg
+
+    .balign 4
+    .type vpy_loop, %function
+    .thumb_func
+vpy_loop:
+    PUSH {r7, lr}
+    SUB sp, sp, #24
+    MOV r7, sp
+    BL vf_wait_recal
+    ADD r0, r7, #0
+    MOV r1, #0
+    STR r1, [r0, #0]
+    STR r1, [r0, #4]
+    ADD r0, r7, #0
+    MOV r1, #1
+    MOV r2, #2
+    BL vpy_Ship_init
+    MOV r0, #0
+    BL vf_joystick
+    BL __vpy_digital
+    MOV r1, r0
+    ADD r0, r7, #0
+    BL vpy_Ship_step
+    MOV r0, #0
+    STR r0, [r7, #8]
+    LDR r0, =VAR_XS
+    SUB sp, sp, #8
+    STR r0, [sp]
+    MOV r0, #0
+    STR r0, [sp, #4]
+.Lforin1:
+    LDR r1, [sp]
+    LDR r0, [sp, #4]
+    LDR r2, [r1, #-4]
+    CMP r0, r2
+    BGE .Lfinend3
+    LDR r0, [r1, r0, LSL #2]
+    STR r0, [r7, #12]
+    LDR r0, [r7, #8]
+    LDR r1, [r7, #12]
+    ADD r0, r0, r1
+    SXTH r0, r0
+    STR r0, [r7, #8]
+.Lfinext2:
+    LDR r0, [sp, #4]
+    ADD r0, r0, #1
+    STR r0, [sp, #4]
+    B .Lforin1
+.Lfinend3:
+    ADD sp, sp, #8
+    MOV r0, #0
+    STR r0, [r7, #16]
+.Lfor4:
+    LDR r0, [r7, #16]
+    CMP r0, #3
+    BGE .Lfend6
+    LDR r0, [r7, #16]
+    LDR r1, =VAR_XS
+    ADD r0, r1, r0, LSL #2
+    LDR r0, [r0]
+    MOV r1, #7
+    BL __aeabi_idivmod
+    MOV r0, r1
+    STR r0, [sp, #-8]!
+    LDR r0, [r7, #16]
+    LDR r1, =VAR_XS
+    ADD r0, r1, r0, LSL #2
+    MOV r1, r0
+    LDR r0, [sp], #8
+    STR r0, [r1]
+.Lfnext5:
+    LDR r0, [r7, #16]
+    ADD r0, r0, #1
+    SXTH r0, r0
+    STR r0, [r7, #16]
+    B .Lfor4
+.Lfend6:
+    BL vf_buttons
+    AND r0, r0, #1
+    CMP r0, #1
+    BNE .Lelse8
+    LDR r0, =VAR_SCORE
+    LDR r0, [r0]
+    LDR r1, =1000
+    CMP r0, r1
+    BGE .Lelse8
+    LDR r0, =VAR_SCORE
+    LDR r0, [r0]
+    STR r0, [sp, #-8]!
+    SUB sp, sp, #24
+    MOV r0, #1
+    STR r0, [sp, #0]
+    MOV r0, #2
+    STR r0, [sp, #4]
+    MOV r0, #3
+    STR r0, [sp, #8]
+    MOV r0, #4
+    STR r0, [sp, #12]
+    LDR r0, [r7, #0]
+    STR r0, [sp, #16]
+    POP {r0-r3}
+    BL vpy_add5
+    ADD sp, sp, #8
+    MOV r1, r0
+    LDR r0, [sp], #8
+    ADD r0, r0, r1
+    SXTH r0, r0
+    LDR r1, =VAR_SCORE
+    STR r0, [r1]
+    B .Lendif7
+.Lelse8:
+    LDR r0, =VAR_SCORE
+    LDR r0, [r0]
+    LDR r1, =5000
+    CMP r0, r1
+    BLE .Lelse9
+    MOV r0, #0
+    LDR r1, =VAR_SCORE
+    STR r0, [r1]
+    B .Lendif7
+.Lelse9:
+.Lendif7:
+.Lwhile10:
+    LDR r0, [r7, #8]
+    CMP r0, #10
+    BLE .Lwend11
+    LDR r0, [r7, #8]
+    MOV r1, #2
+    BL __aeabi_idiv
+    SXTH r0, r0
+    STR r0, [r7, #8]
+    LDR r0, [r7, #8]
+    CMP r0, #20
+    BNE .Lelse13
+    B .Lwend11
+.Lelse13:
+.Lendif12:
+    B .Lwhile10
+.Lwend11:
+    LDR r0, =VAR_SCORE
+    LDR r0, [r0]
+    STR r0, [sp, #-8]!
+    MOV r0, #1
+    LDR r1, [sp]
+    CMP r1, r0
+    BEQ .Lcase15
+    ADD sp, sp, #8
+    LDR r0, =VAR_SCORE
+    LDR r0, [r0]
+    SUB r0, r0, #5
+    SXTH r0, r0
+    CMP r0, #0
+    BGE .Labs16
+    RSB r0, r0, #0
+.Labs16:
+    LDR r1, =VAR_SCORE
+    STR r0, [r1]
+    B .Lswend14
+.Lcase15:
+    ADD sp, sp, #8
+    MOV r0, #2
+    LDR r1, =VAR_SCORE
+    STR r0, [r1]
+    B .Lswend14
+.Lswend14:
+    SUB sp, sp, #8
+    LDR r0, [r7, #0]
+    STR r0, [sp, #0]
+    LDR r0, [r7, #4]
+    STR r0, [sp, #4]
+    POP {r0, r1}
+    BL __vpy_move
+    MOV r0, #0
+    LDR r1, =VAR_SCORE
+    LDR r1, [r1]
+    SUB r0, r0, r1
+    SXTH r0, r0
+    MOV r1, r0
+    LDR r0, =VAR_SCORE
+    LDR r0, [r0]
+    BL __vpy_draw_to
+    SUB sp, sp, #24
+    MVN r0, #9
+    STR r0, [sp, #0]
+    MVN r0, #9
+    STR r0, [sp, #4]
+    MOV r0, #10
+    STR r0, [sp, #8]
+    MOV r0, #10
+    STR r0, [sp, #12]
+    MOV r0, #127
+    STR r0, [sp, #16]
+    POP {r0-r3}
+    BL vf_line
+    ADD sp, sp, #8
+    MVN r0, #49
+    MOV r1, #60
+    LDR r2, =STR_0
+    BL vf_print
+.Lvpy_loop_ret:
+    MOV sp, r7
+    ADD sp, sp, #24
+    POP {r7, pc}
+    .ltorg
+
+    .balign 4
+    .type __vpy_digital, %function
+    .thumb_func
+__vpy_digital:
+    CMP r0, #32
+    BGE 1f
+    CMN r0, #32
+    BLE 2f
+    MOV r0, #0
+    BX lr
+1:  MOV r0, #1
+    BX lr
+2:  MVN r0, #0
+    BX lr
+    .ltorg
+
+    .balign 4
+    .type __vpy_draw_to, %function
+    .thumb_func
+__vpy_draw_to:
+    PUSH {r4, lr}
+    LDR r12, =__vpy_pen
+    MOV r2, r0
+    MOV r3, r1
+    LDR r0, [r12]
+    LDR r1, [r12, #4]
+    STR r2, [r12]
+    STR r3, [r12, #4]
+    LDR r4, [r12, #8]
+    SUB sp, sp, #8
+    STR r4, [sp]
+    BL vf_line
+    ADD sp, sp, #8
+    POP {r4, pc}
+    .ltorg
+
+    .balign 4
+    .type __vpy_move, %function
+    .thumb_func
+__vpy_move:
+    LDR r2, =__vpy_pen
+    STR r0, [r2]
+    STR r1, [r2, #4]
+    BX lr
+    .ltorg
+
+    .balign 4
+    .type __vpy_set_intensity, %function
+    .thumb_func
+__vpy_set_intensity:
+    LDR r1, =__vpy_pen
+    STR r0, [r1, #8]
+    B vf_intensity
+    .ltorg
+
+    .section .rodata
+STR_0: .asciz "HELLO"
+
+    .data
+    .balign 4
+VAR_SCORE: .word 0
+    .word 3
+VAR_XS:
+    .word 10, -20, 300
+__vpy_pen: .word 0, 0, 127
+
@@ -0,0 +1,164 @@
+//! img2vec tracer tests: synthetic bitmaps → .vec resources
+
+use vectrex_lang::img2vec::{load_image, otsu_threshold, total_segments, trace, LumaImage, TraceMode, TraceOptions};
+use vectrex_lang::vecres::{VecPath, VecResource};
+
+/// White canvas with filled rectangles (x0, y0, x1, y1, luminance)
+fn canvas(width: usize, height: usize, rects: &[(usize, usize, usize, usize, u8)]) -> LumaImage {
+    let mut pixels = vec![255u8; width * height];
+    for &(x0, y0, x1, y1, luma) in rects {
+        for y in y0..y1 {
+            for x in x0..x1 {
+                pixels[y * width + x] = luma;
+            }
+        }
+    }
+    LumaImage { width, height, pixels }
+}
+
+fn all_paths(res: &VecResource) -> Vec<&VecPath> {
+    res.layers.iter().flat_map(|l| l.paths.iter()).collect()
+}
+
+/// Every segment (closing ones included) must fit a Draw_Sync_List delta
+fn assert_deltas_fit(res: &VecResource) {
+    for path in all_paths(res) {
+        let n = path.points.len();
+        let edges = if path.closed { n } else { n - 1 };
+        for i in 0..edges {
+            let (a, b) = (path.points[i], path.points[(i + 1) % n]);
+            assert!((b.x - a.x).abs() <= 127 && (b.y - a.y).abs() <= 127, "delta too large in {}: {:?} → {:?}", path.name, a, b);
+        }
+    }
+}
+
+#[test]
+fn test_contour_of_square_is_closed_quad() {
+    let image = canvas(64, 64, &[(16, 16, 48, 48, 0)]);
+    let res = trace(&image, "square", &TraceOptions { size: 64, ..TraceOptions::default() });
+    let paths = all_paths(&res);
+    assert_eq!(paths.len(), 1);
+    let square = paths[0];
+    assert!(square.closed);
+    assert_eq!(square.points.len(), 4, "{:?}", square.points);
+    // 32px square in a 64px image fitted into ±64 → corners near ±31
+    for p in &square.points {
+        assert!((p.x.abs() - 31).abs() <= 2 && (p.y.abs() - 31).abs() <= 2, "{:?}", p);
+    }
+    assert_eq!(square.intensity, 127);
+}
+
+#[test]
+fn test_edges_mode_outlines_square() {
+    let image = canvas(64, 64, &[(16, 16, 48, 48, 0)]);
+    let options = TraceOptions { mode: TraceMode::Edges, size: 64, ..TraceOptions::default() };
+    let res = trace(&image, "square", &options);
+    let paths = all_paths(&res);
+    assert!(!paths.is_empty());
+    let xs: Vec<i16> = paths.iter().flat_map(|p| p.points.iter().map(|q| q.x)).collect();
+    let ys: Vec<i16> = paths.iter().flat_map(|p| p.points.iter().map(|q| q.y)).collect();
+    let (min_x, max_x) = (*xs.iter().min().unwrap(), *xs.iter().max().unwrap());
+    let (min_y, max_y) = (*ys.iter().min().unwrap(), *ys.iter().max().unwrap());
+    assert!((min_x + 32).abs() <= 3 && (max_x - 32).abs() <= 3, "x range {}..{}", min_x, max_x);
+    assert!((min_y + 32).abs() <= 3 && (max_y - 32).abs() <= 3, "y range {}..{}", min_y, max_y);
+}
+
+#[test]
+fn test_intensity_follows_luminance_into_layers() {
+    let image = canvas(96, 48, &[(8, 8, 40, 40, 0), (56, 8, 88, 40, 150)]);
+    let options = TraceOptions { threshold: Some(200), ..TraceOptions::default() };
+    let res = trace(&image, "pair", &options);
+    assert_eq!(res.layers.len(), 2, "{:?}", res.layers.iter().map(|l| &l.name).collect::<Vec<_>>());
+    let (bright, dim) = (&res.layers[0], &res.layers[1]);
+    assert!(bright.paths[0].intensity > dim.paths[0].intensity);
+    assert_eq!(bright.name, format!("intensity_{}", bright.paths[0].intensity));
+    // The black square is on the left, the gray one on the right
+    assert!(bright.paths[0].points.iter().all(|p| p.x < 0));
+    assert!(dim.paths[0].points.iter().all(|p| p.x > 0));
+}
+
+#[test]
+fn test_segment_budget_is_respected() {
+    // A grid of small blobs yields far more outline than the budget allows
+    let rects: Vec<_> = (0..10)
+        .flat_map(|row| (0..10).map(move |col| (col * 12 + 2, row * 12 + 2, col * 12 + 9, row * 12 + 9, 0u8)))
+        .collect();
+    let image = canvas(120, 120, &rects);
+    for budget in [12, 60, 150] {
+        let res = trace(&image, "grid", &TraceOptions { max_segments: budget, ..TraceOptions::default() });
+        let paths: Vec<VecPath> = all_paths(&res).into_iter().cloned().collect();
+        assert!(!paths.is_empty());
+        assert!(total_segments(&paths) <= budget, "{} segments for budget {}", total_segments(&paths), budget);
+    }
+}
+
+#[test]
+fn test_output_fits_vectrex_range_and_compiles() {
+    // A frame hugging the border: long edges must be split into ≤127 deltas
+    let image = canvas(100, 100, &[(0, 0, 100, 3, 0), (0, 97, 100, 100, 0), (0, 0, 3, 100, 0), (97, 0, 100, 100, 0)]);
+    let res = trace(&image, "frame", &TraceOptions::default());
+    assert!(!all_paths(&res).is_empty());
+    for p in all_paths(&res).iter().flat_map(|p| p.points.iter()) {
+        assert!(p.x.abs() <= 127 && p.y.abs() <= 127, "{:?}", p);
+    }
+    assert_deltas_fit(&res);
+    let asm = res.compile_to_asm();
+    assert!(asm.contains("_FRAME_VECTORS:"));
+}
+
+#[test]
+fn test_douglas_peucker_tolerance_controls_detail() {
+    // A disc: coarser tolerance → fewer points
+    let (w, h) = (80usize, 80usize);
+    let mut pixels = vec![255u8; w * h];
+    for y in 0..h {
+        for x in 0..w {
+            let (dx, dy) = (x as f64 - 40.0, y as f64 - 40.0);
+            if dx * dx + dy * dy < 30.0 * 30.0 {
+                pixels[y * w + x] = 0;
+            }
+        }
+    }
+    let image = LumaImage { width: w, height: h, pixels };
+    let fine = trace(&image, "disc", &TraceOptions { tolerance: 0.5, ..TraceOptions::default() });
+    let coarse = trace(&image, "disc", &TraceOptions { tolerance: 6.0, ..TraceOptions::default() });
+    let count = |r: &VecResource| r.point_count();
+    assert!(count(&coarse) < count(&fine), "coarse {} vs fine {}", count(&coarse), count(&fine));
+    assert!(count(&coarse) >= 5);
+}
+
+#[test]
+fn test_otsu_and_invert() {
+    let image = canvas(32, 32, &[(8, 8, 24, 24, 40)]);
+    let t = otsu_threshold(&image);
+    assert!(t > 40, "threshold {}", t);
+
+    // Light square on black, traced with --invert
+    let mut dark = image.clone();
+    for p in dark.pixels.iter_mut() {
+        *p = 255 - *p;
+    }
+    let res = trace(&dark, "inv", &TraceOptions { invert: true, ..TraceOptions::default() });
+    assert_eq!(all_paths(&res).len(), 1);
+}
+
+#[test]
+fn test_load_png_with_transparency() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sprite.png");
+    let mut img = image::RgbaImage::from_pixel(400, 200, image::Rgba([0, 0, 0, 0]));
+    for y in 50..150 {
+        for x in 100..300 {
+            img.put_pixel(x, y, image::Rgba([0, 0, 0, 255]));
+        }
+    }
+    img.save(&path).unwrap();
+
+    let luma = load_image(&path, 100, false).unwrap();
+    assert_eq!((luma.width, luma.height), (100, 50));
+    // Transparent → white background, opaque black → ink
+    assert_eq!(luma.pixels[0], 255);
+    assert_eq!(luma.pixels[25 * 100 + 50], 0);
+    let res = trace(&luma, "sprite", &TraceOptions::default());
+    assert_eq!(all_paths(&res).len(), 1);
+}
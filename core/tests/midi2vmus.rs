@@ -0,0 +1,214 @@
+//! midi2vmus tests: hand-built Standard MIDI Files → .vmus resources
+
+use vectrex_lang::midi2vmus::{drum_noise_period, import_midi, velocity_to_volume, MidiImportOptions, PsgChannel};
+
+/// Variable-length quantity
+fn vlq(mut value: u32) -> Vec<u8> {
+    let mut bytes = vec![(value & 0x7F) as u8];
+    value >>= 7;
+    while value > 0 {
+        bytes.insert(0, (value & 0x7F) as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes
+}
+
+/// Track chunk from (delta, event bytes) pairs; end-of-track is appended
+fn track(events: &[(u32, Vec<u8>)]) -> Vec<u8> {
+    let mut data = Vec::new();
+    for (delta, event) in events {
+        data.extend(vlq(*delta));
+        data.extend(event);
+    }
+    data.extend([0x00, 0xFF, 0x2F, 0x00]);
+    let mut chunk = b"MTrk".to_vec();
+    chunk.extend((data.len() as u32).to_be_bytes());
+    chunk.extend(data);
+    chunk
+}
+
+fn smf(format: u16, division: u16, tracks: &[Vec<u8>]) -> Vec<u8> {
+    let mut bytes = b"MThd".to_vec();
+    bytes.extend(6u32.to_be_bytes());
+    bytes.extend(format.to_be_bytes());
+    bytes.extend((tracks.len() as u16).to_be_bytes());
+    bytes.extend(division.to_be_bytes());
+    for t in tracks {
+        bytes.extend(t);
+    }
+    bytes
+}
+
+fn on(channel: u8, key: u8, velocity: u8) -> Vec<u8> {
+    vec![0x90 | channel, key, velocity]
+}
+
+fn off(channel: u8, key: u8) -> Vec<u8> {
+    vec![0x80 | channel, key, 0]
+}
+
+fn name(text: &str) -> Vec<u8> {
+    let mut event = vec![0xFF, 0x03, text.len() as u8];
+    event.extend(text.as_bytes());
+    event
+}
+
+/// 100 BPM = 600000 µs per quarter note
+fn tempo_100() -> Vec<u8> {
+    vec![0xFF, 0x51, 0x03, 0x09, 0x27, 0xC0]
+}
+
+/// Monophonic line of quarter notes on one channel
+fn line(channel: u8, keys: &[u8], division: u32) -> Vec<(u32, Vec<u8>)> {
+    keys.iter()
+        .flat_map(|&k| [(0, on(channel, k, 100)), (division, off(channel, k))])
+        .collect()
+}
+
+#[test]
+fn type0_channels_and_drums() {
+    // Melody on channel 1, bass on channel 2, kick + hi-hat on channel 10
+    let division = 96;
+    let events = vec![
+        (0, tempo_100()),
+        (0, on(0, 72, 127)),
+        (0, on(1, 48, 64)),
+        (0, on(9, 36, 100)),
+        (24, off(9, 36)),
+        (24, on(9, 42, 80)),
+        (24, off(9, 42)),
+        (24, off(0, 72)),
+        (0, off(1, 48)),
+    ];
+    let bytes = smf(0, division, &[track(&events)]);
+    let import = import_midi(&bytes, "song", &MidiImportOptions::default()).unwrap();
+    let music = &import.resource;
+
+    assert_eq!(music.tempo, 100);
+    assert_eq!(music.ticks_per_beat, 24);
+    assert_eq!(import.tracks.len(), 2);
+    assert_eq!(import.tracks[0].psg_channel, Some(PsgChannel::A));
+    assert_eq!(import.tracks[1].psg_channel, Some(PsgChannel::B));
+
+    assert_eq!(music.notes.len(), 2);
+    let melody = music.notes.iter().find(|n| n.channel == 0).unwrap();
+    assert_eq!((melody.note, melody.start, melody.duration, melody.velocity), (72, 0, 24, 15));
+    let bass = music.notes.iter().find(|n| n.channel == 1).unwrap();
+    assert_eq!((bass.note, bass.velocity), (48, 8));
+
+    assert_eq!(import.drum_hits, 2);
+    assert_eq!(music.noise.len(), 2);
+    assert_eq!(music.noise[0].period, drum_noise_period(36));
+    assert_eq!(music.noise[1].period, drum_noise_period(42));
+    assert_eq!(music.noise[1].start, 12);
+    assert!(music.noise.iter().all(|n| n.channels == 4));
+
+    // Padded to one 4/4 bar and looping over it
+    assert_eq!(music.total_ticks, 96);
+    assert_eq!(music.loop_end, 96);
+    assert!(import.warnings.is_empty(), "{:?}", import.warnings);
+}
+
+#[test]
+fn type1_track_mapping_and_unmapped_warning() {
+    let division = 48;
+    let tracks = vec![
+        track(&[(0, tempo_100())]),
+        track(&[vec![(0, name("Lead"))], line(0, &[60, 62], 48)].concat()),
+        track(&[vec![(0, name("Bass"))], line(1, &[36], 96)].concat()),
+        track(&[vec![(0, name("Pad"))], line(2, &[64], 96)].concat()),
+        track(&[vec![(0, name("Arp"))], line(3, &[67], 96)].concat()),
+    ];
+    let bytes = smf(1, division, &tracks);
+
+    let default = import_midi(&bytes, "song", &MidiImportOptions::default()).unwrap();
+    assert_eq!(default.tracks.len(), 4);
+    assert_eq!(default.tracks[0].name, "Lead");
+    assert_eq!(default.tracks[3].psg_channel, None);
+    assert!(default.warnings.iter().any(|w| w.contains("'Arp'") && w.contains("not mapped")));
+    assert!(default.warnings.iter().any(|w| w.contains("4 notes sound at once")));
+
+    let options = MidiImportOptions { tracks: Some(vec![3, 0]), ..Default::default() };
+    let mapped = import_midi(&bytes, "song", &options).unwrap();
+    let arp: Vec<_> = mapped.resource.notes.iter().filter(|n| n.channel == 0).collect();
+    assert_eq!(arp.len(), 1);
+    assert_eq!(arp[0].note, 67);
+    let lead: Vec<_> = mapped.resource.notes.iter().filter(|n| n.channel == 1).map(|n| n.note).collect();
+    assert_eq!(lead, vec![60, 62]);
+    assert!(mapped.resource.notes.iter().all(|n| n.channel < 2));
+
+    assert!(import_midi(&bytes, "song", &MidiImportOptions { tracks: Some(vec![4]), ..Default::default() }).is_err());
+    assert!(import_midi(&bytes, "song", &MidiImportOptions { tracks: Some(vec![1, 1]), ..Default::default() }).is_err());
+}
+
+#[test]
+fn quantisation_snaps_to_grid() {
+    // Division 96 → 24 ticks per beat: tick 50 is 12.5, nearest sixteenth (6) is 12
+    let events = vec![(50, on(0, 60, 100)), (20, off(0, 60))];
+    let bytes = smf(0, 96, &[track(&events)]);
+    let import = import_midi(&bytes, "q", &MidiImportOptions::default()).unwrap();
+    let note = &import.resource.notes[0];
+    assert_eq!(note.start, 12);
+    // 17.5 rounds to 18, one grid step long
+    assert_eq!(note.duration, 6);
+
+    let fine = import_midi(&bytes, "q", &MidiImportOptions { grid: 1, ..Default::default() }).unwrap();
+    assert_eq!(fine.resource.notes[0].start, 13);
+}
+
+#[test]
+fn chords_keep_top_note_and_overlaps_are_cut() {
+    let events = vec![
+        (0, on(0, 60, 100)),
+        (0, on(0, 64, 100)),
+        (0, on(0, 67, 100)),
+        (48, on(0, 72, 100)),
+        (48, off(0, 60)),
+        (0, off(0, 64)),
+        (0, off(0, 67)),
+        (48, off(0, 72)),
+    ];
+    let bytes = smf(0, 96, &[track(&events)]);
+    let import = import_midi(&bytes, "c", &MidiImportOptions::default()).unwrap();
+    let notes: Vec<_> = import.resource.notes.iter().map(|n| (n.note, n.start, n.duration)).collect();
+    // Top of the chord until the next note starts
+    assert_eq!(notes, vec![(67, 0, 12), (72, 12, 24)]);
+    assert!(import.warnings.iter().any(|w| w.contains("2 chord notes dropped")));
+    assert!(import.warnings.iter().any(|w| w.contains("4 notes sound at once")));
+}
+
+#[test]
+fn running_status_and_note_on_zero_velocity() {
+    // 90 3C 64 | 60 3C 00 (running status, note-on velocity 0 = note-off)
+    let mut data = vec![0x00, 0x90, 0x3C, 0x64, 0x60, 0x3C, 0x00, 0x00, 0x3E, 0x40, 0x60, 0x3E, 0x00];
+    data.extend([0x00, 0xFF, 0x2F, 0x00]);
+    let mut chunk = b"MTrk".to_vec();
+    chunk.extend((data.len() as u32).to_be_bytes());
+    chunk.extend(data);
+    let bytes = smf(0, 96, &[chunk]);
+
+    let import = import_midi(&bytes, "r", &MidiImportOptions::default()).unwrap();
+    let notes: Vec<_> = import.resource.notes.iter().map(|n| (n.note, n.start, n.duration)).collect();
+    assert_eq!(notes, vec![(60, 0, 24), (62, 24, 24)]);
+    assert_eq!(import.resource.tempo, 120);
+}
+
+#[test]
+fn velocity_scale() {
+    assert_eq!(velocity_to_volume(0), 0);
+    assert_eq!(velocity_to_volume(1), 1);
+    assert_eq!(velocity_to_volume(64), 8);
+    assert_eq!(velocity_to_volume(127), 15);
+}
+
+#[test]
+fn rejects_unsupported_files() {
+    assert!(import_midi(b"RIFF....", "x", &MidiImportOptions::default()).is_err());
+    let format2 = smf(2, 96, &[track(&[])]);
+    assert!(import_midi(&format2, "x", &MidiImportOptions::default()).unwrap_err().to_string().contains("format 2"));
+    let smpte = smf(0, 0xE728, &[track(&[])]);
+    assert!(import_midi(&smpte, "x", &MidiImportOptions::default()).unwrap_err().to_string().contains("SMPTE"));
+    let mut truncated = smf(0, 96, &[track(&line(0, &[60], 96))]);
+    truncated.truncate(truncated.len() - 6);
+    assert!(import_midi(&truncated, "x", &MidiImportOptions::default()).is_err());
+}
@@ -0,0 +1,114 @@
+//! Packed music streams: round trip through the assembler and the Rust decoder
+
+use vectrex_lang::backend::asm_to_binary::assemble_m6809;
+use vectrex_lang::music_pack::{decode_packed, FrameWrites};
+use vectrex_lang::musres::{HardwareEnvelope, MusicCompression, MusicResource, NoteEvent};
+use vectrex_lang::psg_synth::{render_music, RenderOptions};
+
+fn note(id: &str, note: u8, start: u32, duration: u32, channel: u8) -> NoteEvent {
+    NoteEvent { id: id.into(), note, start, duration, velocity: 12, channel, envelope: None, buzzer: false }
+}
+
+/// Four bars of the same two-channel riff, looped
+fn riff_song() -> MusicResource {
+    let mut music = MusicResource::new("riff");
+    music.tempo = 120;
+    music.ticks_per_beat = 24;
+    for bar in 0..4 {
+        let base = bar * 96;
+        for (i, pitch) in [60, 64, 67, 72].iter().enumerate() {
+            music.notes.push(note(&format!("m{}_{}", bar, i), *pitch, base + i as u32 * 24, 20, 0));
+        }
+        music.notes.push(note(&format!("b{}", bar), 36, base, 90, 1));
+    }
+    music.total_ticks = 384;
+    music.loop_start = 0;
+    music.loop_end = 384;
+    music
+}
+
+/// Register state after each frame (`None` until first written), and whether
+/// the frame restarted the envelope
+fn states(frames: &[FrameWrites]) -> Vec<([Option<u8>; 14], bool)> {
+    let mut state = [None; 14];
+    frames.iter().map(|writes| {
+        for &(reg, val) in writes {
+            state[reg as usize] = Some(val);
+        }
+        (state, writes.iter().any(|&(reg, _)| reg == 13))
+    }).collect()
+}
+
+/// Assemble the packed stream, decode it and compare with the compiler's timeline
+fn assert_round_trip(music: &MusicResource, loops: bool) {
+    let mut packed = music.clone();
+    packed.compression = MusicCompression::Packed;
+    let asm = packed.compile_to_asm("SONG");
+    let (bytes, _, _) = assemble_m6809(&asm, 0x1000).unwrap();
+
+    let (decoded, looped) = decode_packed(&bytes, 0x1000).unwrap();
+    assert_eq!(looped, loops);
+    let expected = music.register_timeline().expand();
+    assert_eq!(decoded.len(), expected.len());
+    assert_eq!(states(&decoded), states(&expected));
+    assert_eq!(bytes.len(), packed.pack_stats().packed_bytes);
+}
+
+#[test]
+fn looping_song_round_trips() {
+    assert_round_trip(&riff_song(), true);
+}
+
+#[test]
+fn one_shot_song_round_trips() {
+    let mut music = riff_song();
+    music.loop_end = 0;
+    assert_round_trip(&music, false);
+}
+
+#[test]
+fn envelope_triggers_survive_packing() {
+    let mut music = riff_song();
+    for n in music.notes.iter_mut().filter(|n| n.channel == 1) {
+        n.envelope = Some(HardwareEnvelope { shape: 0x0E, period: 0 });
+        n.buzzer = true;
+    }
+    assert_round_trip(&music, true);
+}
+
+#[test]
+fn repeated_bars_use_references() {
+    let mut music = riff_song();
+    music.compression = MusicCompression::Packed;
+    let asm = music.compile_to_asm("RIFF");
+    assert!(asm.contains("; Replay"), "no back-reference in:\n{}", asm);
+    assert!(asm.contains("; Format: packed"));
+
+    let stats = music.pack_stats();
+    assert!(stats.packed_bytes * 2 < stats.plain_bytes, "{:?}", stats);
+    assert!(stats.avg_cycles <= stats.max_cycles);
+    assert!(stats.max_cycles > 0 && stats.max_cycles < 3000, "{:?}", stats);
+}
+
+#[test]
+fn compression_is_read_from_json() {
+    let json = r#"{"version":"1.0","name":"x","author":"","tempo":120,"ticksPerBeat":24,
+        "totalTicks":96,"notes":[],"noise":[],"loopStart":0,"loopEnd":0,"compression":"packed"}"#;
+    let music: MusicResource = serde_json::from_str(json).unwrap();
+    assert_eq!(music.compression, MusicCompression::Packed);
+    let plain = serde_json::to_string(&MusicResource::new("y")).unwrap();
+    assert!(!plain.contains("compression"));
+}
+
+#[test]
+fn packed_song_renders_like_plain() {
+    let plain = riff_song();
+    let mut packed = plain.clone();
+    packed.compression = MusicCompression::Packed;
+    let options = RenderOptions { loops: 2, max_seconds: 30 };
+    let a = render_music(&plain, "riff", &options).unwrap();
+    let b = render_music(&packed, "riff", &options).unwrap();
+    assert!(a.finished && b.finished);
+    assert_eq!(a.frames, b.frames);
+    assert!(b.writes < a.writes);
+}
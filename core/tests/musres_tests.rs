@@ -1,7 +1,7 @@
 /// Music resource tests
 /// Separated from musres.rs to keep production code clean
 
-use vectrex_lang::musres::{MusicResource, NoteEvent};
+use vectrex_lang::musres::{HardwareEnvelope, MusicResource, NoteEvent};
 
 #[test]
 fn test_create_music() {
@@ -33,6 +33,8 @@ fn test_compile_to_asm() {
         duration: 48,
         velocity: 12,
         channel: 0,
+        envelope: None,
+        buzzer: false,
     });
     
     let symbol_name = music.name.to_uppercase().replace("-", "_").replace(" ", "_");
@@ -41,3 +43,72 @@ fn test_compile_to_asm() {
     assert!(asm.contains("FCB") || asm.contains("FDB")); // ASM directives present
     assert!(asm.contains("; Frame-based PSG")); // Verify format description
 }
+
+/// (reg, value) pairs of each frame in the compiled stream, loop/end markers excluded
+fn frame_writes(asm: &str) -> Vec<Vec<(u8, u8)>> {
+    let bytes: Vec<u8> = asm.lines()
+        .filter_map(|l| l.trim().strip_prefix("FCB"))
+        .map(|v| {
+            let v = v.split(';').next().unwrap().trim();
+            match v.strip_prefix('$') {
+                Some(hex) => u8::from_str_radix(hex, 16).unwrap(),
+                None => v.parse().unwrap(),
+            }
+        })
+        .collect();
+    let mut frames = Vec::new();
+    let mut i = 0;
+    while i + 1 < bytes.len() && bytes[i + 1] != 0 && bytes[i + 1] != 0xFF {
+        let count = bytes[i + 1] as usize;
+        frames.push(bytes[i + 2..i + 2 + 2 * count].chunks(2).map(|p| (p[0], p[1])).collect());
+        i += 2 + 2 * count;
+    }
+    frames
+}
+
+#[test]
+fn test_buzzer_note_drives_envelope() {
+    let mut music = MusicResource::new("bass");
+    music.total_ticks = 48;
+    music.loop_end = 0;
+    for (i, start) in [0, 24].into_iter().enumerate() {
+        music.notes.push(NoteEvent {
+            id: format!("b{}", i),
+            note: 45, // A2, 110 Hz
+            start,
+            duration: 24,
+            velocity: 15,
+            channel: 1,
+            envelope: None,
+            buzzer: true,
+        });
+    }
+    let asm = music.compile_to_asm("bass");
+    let frames = frame_writes(&asm);
+    let period = HardwareEnvelope::period_for_note(45, 8);
+    assert_eq!(period, 53);
+
+    let first = &frames[0];
+    assert!(first.contains(&(9, 0x10)), "volume B in envelope mode");
+    assert!(first.contains(&(11, 53)) && first.contains(&(12, 0)));
+    assert!(first.contains(&(13, 8)), "shape written when the note starts");
+    let mixer = first.iter().find(|(r, _)| *r == 7).unwrap().1;
+    assert_eq!(mixer & 0x02, 0x02, "tone B off: the envelope is the waveform");
+
+    // Same state, but the second note retriggers the envelope
+    assert_eq!(frames.iter().filter(|f| f.contains(&(13, 8))).count(), 2);
+}
+
+#[test]
+fn test_envelope_note_json() {
+    let json = r#"{"id":"n","note":60,"start":0,"duration":24,"velocity":0,"channel":0,
+                   "envelope":{"shape":10,"period":300}}"#;
+    let note: NoteEvent = serde_json::from_str(json).unwrap();
+    assert!(!note.buzzer);
+    assert_eq!(note.hardware_envelope(), Some(HardwareEnvelope { shape: 10, period: 300 }));
+    // Notes without envelope serialize as before
+    let plain = NoteEvent { envelope: None, ..note };
+    assert!(!serde_json::to_string(&plain).unwrap().contains("envelope"));
+    // Triangles take two ramps per cycle
+    assert_eq!(HardwareEnvelope::period_for_note(45, 10), 27);
+}
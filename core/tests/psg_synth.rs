@@ -0,0 +1,166 @@
+//! PSG synthesiser tests: chip model, compiled music/SFX streams, WAV output
+
+use vectrex_lang::musres::{HardwareEnvelope, MusicResource, NoteEvent};
+use vectrex_lang::psg_synth::{render_music, render_sfx, wav_bytes, Ay38912, RenderOptions, FRAME_RATE, SAMPLE_RATE};
+use vectrex_lang::sfxres::SfxResource;
+
+/// Rising zero crossings per second
+fn frequency(samples: &[f32]) -> f32 {
+    let crossings = samples.windows(2).filter(|w| w[0] < 0.0 && w[1] >= 0.0).count();
+    crossings as f32 * SAMPLE_RATE as f32 / samples.len() as f32
+}
+
+fn rms(samples: &[f32]) -> f32 {
+    (samples.iter().map(|s| s * s).sum::<f32>() / samples.len().max(1) as f32).sqrt()
+}
+
+#[test]
+fn tone_frequency_follows_period() {
+    // 1.5 MHz / (16 * 213) ≈ 440 Hz
+    let mut psg = Ay38912::new();
+    psg.write(0, 213);
+    psg.write(1, 0);
+    psg.write(8, 15);
+    psg.write(7, 0x3E);
+    let mut samples = Vec::new();
+    psg.render(SAMPLE_RATE as usize, &mut samples);
+    let hz = frequency(&samples[4410..]);
+    assert!((hz - 440.0).abs() < 5.0, "got {} Hz", hz);
+}
+
+#[test]
+fn silent_when_mixer_or_volume_off() {
+    let mut psg = Ay38912::new();
+    psg.write(0, 100);
+    psg.write(8, 15);
+    // Tone disabled in the mixer: constant level, no sound once DC settles
+    let mut samples = Vec::new();
+    psg.render(4410, &mut samples);
+    assert!(rms(&samples[2205..]) < 0.01);
+
+    psg.write(7, 0x3E);
+    psg.write(8, 0);
+    samples.clear();
+    psg.render(4410, &mut samples);
+    assert!(rms(&samples[2205..]) < 0.01);
+}
+
+#[test]
+fn noise_is_not_periodic_tone() {
+    let mut psg = Ay38912::new();
+    psg.write(6, 8);
+    psg.write(7, 0x37); // noise on A only
+    psg.write(8, 15);
+    let mut samples = Vec::new();
+    psg.render(8820, &mut samples);
+    assert!(rms(&samples) > 0.05);
+    let distinct: std::collections::HashSet<i32> = samples.iter().map(|s| (s * 1000.0) as i32).collect();
+    assert!(distinct.len() > 50);
+}
+
+#[test]
+fn envelope_attack_then_hold() {
+    // Shape $0D (/¯¯¯) with a fast period: level climbs to 15 and stays
+    let mut psg = Ay38912::new();
+    psg.write(0, 10);
+    psg.write(7, 0x3E);
+    psg.write(8, 0x10);
+    psg.write(11, 4);
+    psg.write(12, 0);
+    psg.write(13, 0x0D);
+    let mut samples = Vec::new();
+    psg.render(4410, &mut samples);
+    let early = rms(&samples[..8]);
+    let late = rms(&samples[2205..]);
+    assert!(late > early, "early {} late {}", early, late);
+    assert!(late > 0.1);
+}
+
+fn one_note_song() -> MusicResource {
+    let mut music = MusicResource::new("beep");
+    music.tempo = 120;
+    music.ticks_per_beat = 24;
+    music.total_ticks = 48;
+    music.loop_end = 0;
+    music.notes.push(NoteEvent { id: "n1".into(), note: 69, start: 0, duration: 24, velocity: 15, channel: 0, envelope: None, buzzer: false });
+    music
+}
+
+#[test]
+fn music_plays_compiled_stream_at_50hz() {
+    // One beat at 120 BPM is half a second: 25 frames of A4
+    let rendering = render_music(&one_note_song(), "beep", &RenderOptions::default()).unwrap();
+    assert!(rendering.finished);
+    assert!(rendering.writes > 0);
+    let frames = rendering.frames as i64;
+    assert!((frames - 25).abs() <= 1, "{} frames", frames);
+
+    let per_frame = (SAMPLE_RATE / FRAME_RATE) as usize;
+    let note = &rendering.samples[2 * per_frame..20 * per_frame];
+    let hz = frequency(note);
+    assert!((hz - 440.0).abs() < 10.0, "got {} Hz", hz);
+}
+
+#[test]
+fn looping_music_repeats() {
+    let mut music = one_note_song();
+    music.loop_start = 0;
+    music.loop_end = 48;
+    let once = render_music(&music, "beep", &RenderOptions { loops: 1, max_seconds: 10 }).unwrap();
+    let twice = render_music(&music, "beep", &RenderOptions { loops: 2, max_seconds: 10 }).unwrap();
+    assert!(once.finished && twice.finished);
+    assert_eq!(twice.frames, once.frames * 2);
+
+    let capped = render_music(&music, "beep", &RenderOptions { loops: 100, max_seconds: 2 }).unwrap();
+    assert!(!capped.finished);
+    assert_eq!(capped.frames, 2 * FRAME_RATE as usize);
+}
+
+#[test]
+fn sfx_plays_every_frame_until_end_marker() {
+    let sfx = SfxResource::preset_blip();
+    let rendering = render_sfx(&sfx, &RenderOptions::default()).unwrap();
+    assert!(rendering.finished);
+    let expected = (sfx.duration_ms as usize * FRAME_RATE as usize / 1000).max(1);
+    assert_eq!(rendering.frames, expected);
+    assert!(rms(&rendering.samples) > 0.01);
+}
+
+#[test]
+fn buzzer_bass_sounds_at_note_pitch() {
+    let mut music = one_note_song();
+    music.notes[0].note = 45; // A2
+    music.notes[0].buzzer = true;
+    let rendering = render_music(&music, "buzz", &RenderOptions::default()).unwrap();
+    let per_frame = (SAMPLE_RATE / FRAME_RATE) as usize;
+    let note = &rendering.samples[2 * per_frame..20 * per_frame];
+    assert!(rms(note) > 0.05);
+    let hz = frequency(note);
+    assert!((hz - 110.0).abs() < 5.0, "got {} Hz", hz);
+}
+
+#[test]
+fn sfx_hardware_envelope() {
+    // Shape 0 (\___): one 340 ms decay, then silence however loud the frames say
+    let mut sfx = SfxResource::preset_blip();
+    sfx.duration_ms = 1000;
+    sfx.hardware_envelope = Some(HardwareEnvelope { shape: 0, period: 2000 });
+    let rendering = render_sfx(&sfx, &RenderOptions::default()).unwrap();
+    assert!(rendering.finished);
+    assert_eq!(rendering.frames, 50, "the envelope command takes no frame");
+    let per_frame = (SAMPLE_RATE / FRAME_RATE) as usize;
+    let start = rms(&rendering.samples[..10 * per_frame]);
+    let end = rms(&rendering.samples[30 * per_frame..40 * per_frame]);
+    assert!(start > 0.05 && end < 0.01, "start {} end {}", start, end);
+}
+
+#[test]
+fn wav_header() {
+    let wav = wav_bytes(&[0.0, 1.0, -1.0]);
+    assert_eq!(&wav[0..4], b"RIFF");
+    assert_eq!(&wav[8..16], b"WAVEfmt ");
+    assert_eq!(u32::from_le_bytes(wav[24..28].try_into().unwrap()), SAMPLE_RATE);
+    assert_eq!(u32::from_le_bytes(wav[40..44].try_into().unwrap()), 6);
+    assert_eq!(i16::from_le_bytes([wav[46], wav[47]]), i16::MAX);
+    assert_eq!(wav.len(), 44 + 6);
+}
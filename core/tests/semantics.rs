@@ -5,8 +5,8 @@ use vectrex_lang::codegen::{DiagnosticCode, emit_asm_with_diagnostics};
 fn semantics_valid_decl_and_use() {
     let module = Module { items: vec![
         Item::Const { name: "C1".to_string(), value: Expr::Number(5), source_line: 0 },
-        Item::Function(Function { name: "main".to_string(), line: 0, params: vec!["p".to_string()], body: vec![
-            Stmt::Let { name: "x".to_string(), value: Expr::Ident(IdentInfo { name: "p".into(), source_line: 0, col: 0 }), source_line: 0 },
+        Item::Function(Function { name: "main".to_string(), line: 0, params: vec!["p".to_string()], param_types: vec![], body: vec![
+            Stmt::Let { name: "x".to_string(), type_annotation: None, value: Expr::Ident(IdentInfo { name: "p".into(), source_line: 0, col: 0 }), source_line: 0 },
             Stmt::Assign { target: AssignTarget::Ident { name: "x".to_string(), source_line: 0, col: 0 }, value: Expr::Binary { op: BinOp::Add, left: Box::new(Expr::Ident(IdentInfo { name:"x".into(), source_line: 0, col: 0 })), right: Box::new(Expr::Ident(IdentInfo { name:"C1".into(), source_line: 0, col: 0 })) }, source_line: 0 },
             Stmt::Return(Some(Expr::Ident(IdentInfo { name:"x".into(), source_line: 0, col: 0 })), 0)
         ]})
@@ -31,6 +31,7 @@ fn semantics_valid_decl_and_use() {
         mutable_arrays: std::collections::BTreeSet::new(),
         structs: std::collections::HashMap::new(),
         type_context: std::collections::HashMap::new(),
+        global_types: std::collections::HashMap::new(),
         output_name: None,
         buffer_requirements: None,
     });
@@ -41,7 +42,7 @@ fn semantics_valid_decl_and_use() {
 #[test]
 fn semantics_undefined_use_reports_error() {
     let module = Module { items: vec![
-        Item::Function(Function { name: "f".to_string(), line: 0, params: vec![], body: vec![
+        Item::Function(Function { name: "f".to_string(), line: 0, params: vec![], param_types: vec![], body: vec![
             Stmt::Expr(Expr::Ident(IdentInfo { name:"y".into(), source_line: 0, col: 0 }), 0)
         ]})
     ], imports: vec![], meta: ModuleMeta::default() };
@@ -53,6 +54,7 @@ fn semantics_undefined_use_reports_error() {
         mutable_arrays: std::collections::BTreeSet::new(),
         structs: std::collections::HashMap::new(),
         type_context: std::collections::HashMap::new(),
+        global_types: std::collections::HashMap::new(),
         output_name: None,
         buffer_requirements: None,
     });
@@ -63,7 +65,7 @@ fn semantics_undefined_use_reports_error() {
 fn semantics_valid_builtin_arity() {
     // FRAME_BEGIN(intensity=Expr::Number)
     let module = Module { items: vec![
-        Item::Function(Function { name: "g".to_string(), line: 0, params: vec![], body: vec![
+        Item::Function(Function { name: "g".to_string(), line: 0, params: vec![], param_types: vec![], body: vec![
             Stmt::Expr(Expr::Call(CallInfo { name: "FRAME_BEGIN".into(), source_line: 0, col: 0, args: vec![Expr::Number(10)] }), 0),
             Stmt::Return(None, 0)
         ]})
@@ -76,6 +78,7 @@ fn semantics_valid_builtin_arity() {
         mutable_arrays: std::collections::BTreeSet::new(),
         structs: std::collections::HashMap::new(),
         type_context: std::collections::HashMap::new(),
+        global_types: std::collections::HashMap::new(),
         output_name: None,
         buffer_requirements: None,
     });
@@ -84,7 +87,7 @@ fn semantics_valid_builtin_arity() {
 #[test]
 fn semantics_bad_builtin_arity_reports_error() {
     let module = Module { items: vec![
-        Item::Function(Function { name: "h".to_string(), line: 0, params: vec![], body: vec![
+        Item::Function(Function { name: "h".to_string(), line: 0, params: vec![], param_types: vec![], body: vec![
             // DRAW_LINE necesita 5 args; damos 4
             Stmt::Expr(Expr::Call(CallInfo { name: "DRAW_LINE".into(), source_line: 0, col: 0, args: vec![Expr::Number(0),Expr::Number(0),Expr::Number(1),Expr::Number(1)] }), 0)
         ]})
@@ -97,6 +100,7 @@ fn semantics_bad_builtin_arity_reports_error() {
         mutable_arrays: std::collections::BTreeSet::new(),
         structs: std::collections::HashMap::new(),
         type_context: std::collections::HashMap::new(),
+        global_types: std::collections::HashMap::new(),
         output_name: None,
         buffer_requirements: None,
     });
@@ -106,8 +110,8 @@ fn semantics_bad_builtin_arity_reports_error() {
 #[test]
 fn semantics_unused_var_warning() {
     let module = Module { items: vec![
-        Item::Function(Function { name: "w".to_string(), line: 0, params: vec![], body: vec![
-            Stmt::Let { name: "x".into(), value: Expr::Number(1), source_line: 0 },
+        Item::Function(Function { name: "w".to_string(), line: 0, params: vec![], param_types: vec![], body: vec![
+            Stmt::Let { name: "x".into(), type_annotation: None, value: Expr::Number(1), source_line: 0 },
             Stmt::Return(None, 0)
         ]})
     ], imports: vec![], meta: ModuleMeta::default() };
@@ -119,6 +123,7 @@ fn semantics_unused_var_warning() {
         mutable_arrays: std::collections::BTreeSet::new(),
         structs: std::collections::HashMap::new(),
         type_context: std::collections::HashMap::new(),
+        global_types: std::collections::HashMap::new(),
         output_name: None,
         buffer_requirements: None,
     });
@@ -0,0 +1,103 @@
+//! PLAY_SFX con prioridad en el reproductor de un solo canal (canal C).
+//!
+//! La prioridad por defecto sale del asset, el segundo argumento la sustituye,
+//! y PLAY_SFX_RUNTIME (ejecutado en el 6809 del emulador) solo deja que un
+//! efecto de prioridad igual o mayor interrumpa al que suena. El argumento de
+//! canal se rechaza como error de aridad.
+
+use std::path::PathBuf;
+
+use vectrex_emulator::CPU;
+use vectrex_lang::backend::asm_to_binary::assemble_m6809;
+use vectrex_lang::codegen::{emit_asm_with_diagnostics, AssetInfo, AssetType, CodegenOptions, DiagnosticCode, DiagnosticSeverity};
+use vectrex_lang::target::Target;
+use vectrex_lang::{lex, parse_with_filename};
+
+fn write_sfx(dir: &std::path::Path, name: &str, priority: u8) -> AssetInfo {
+    let json = format!(
+        r#"{{"version":"1.0","name":"{name}","category":"custom","duration_ms":200,"priority":{priority},
+ "oscillator":{{"frequency":440,"channel":0,"duty":50}},
+ "envelope":{{"attack":0,"decay":0,"sustain":15,"release":0,"peak":15}},
+ "pitch":{{"enabled":false,"start_mult":1.0,"end_mult":1.0,"curve":0}},
+ "noise":{{"enabled":false,"period":0,"volume":0,"decay_ms":0}},
+ "modulation":{{"arpeggio":false,"arpeggio_notes":[],"arpeggio_speed":100,"vibrato":false,"vibrato_depth":0,"vibrato_speed":0}}}}"#
+    );
+    let path = dir.join(format!("{}.vsfx", name));
+    std::fs::write(&path, json).unwrap();
+    AssetInfo { name: name.to_string(), path: path.display().to_string(), asset_type: AssetType::Sfx, priority }
+}
+
+fn compile(tag: &str, src: &str) -> (String, Vec<vectrex_lang::codegen::Diagnostic>) {
+    let dir: PathBuf = std::env::temp_dir().join(format!("vectrexc_sfx_{}_{}", tag, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let assets = vec![write_sfx(&dir, "zap", 3), write_sfx(&dir, "blip", 0)];
+    let tokens = lex(src).expect("lex ok");
+    let module = parse_with_filename(&tokens, "sfx.vpy").expect("parse ok");
+    let opts = CodegenOptions { title: "SFX".to_string(), auto_loop: true, assets, ..Default::default() };
+    let result = emit_asm_with_diagnostics(&module, Target::Vectrex, &opts);
+    let _ = std::fs::remove_dir_all(&dir);
+    result
+}
+
+const RAM: u16 = 0xC880;
+const CODE: u16 = 0x1000;
+
+/// Run PLAY_SFX_RUNTIME (as emitted in `asm`) once per (priority, pointer)
+/// request and return SFX_PTR after each
+fn run_requests(asm: &str, requests: &[(u8, u16)]) -> Vec<u16> {
+    let start = asm.find("PLAY_SFX_RUNTIME:").expect("runtime emitted");
+    let end = start + asm[start..].find("SFX_UPDATE:").expect("SFX_UPDATE follows");
+    let mut src = format!("SFX_PTR EQU ${:04X}\nSFX_ACTIVE EQU ${:04X}\nSFX_PRIORITY EQU ${:04X}\nSFX_ENV_MODE EQU ${:04X}\n",
+        RAM, RAM + 2, RAM + 3, RAM + 4);
+    for (i, (priority, pointer)) in requests.iter().enumerate() {
+        src.push_str(&format!("    LDA #{}\n    LDX #${:04X}\n    JSR PLAY_SFX_RUNTIME\nSTEP{}:\n", priority, pointer, i));
+    }
+    src.push_str("HALT:\n    BRA HALT\n");
+    src.push_str(&asm[start..end]);
+
+    let (bytes, _, symbols) = assemble_m6809(&src, CODE).expect("runtime assembles");
+    let mut cpu = CPU::default();
+    cpu.bus.mem[CODE as usize..CODE as usize + bytes.len()].copy_from_slice(&bytes);
+    cpu.pc = CODE;
+    cpu.s = 0xCBE0;
+    let mut pointers = Vec::new();
+    for _ in 0..1_000 {
+        if pointers.len() < requests.len() && cpu.pc == symbols[&format!("STEP{}", pointers.len())] {
+            let r = RAM as usize;
+            pointers.push(u16::from_be_bytes([cpu.bus.mem[r], cpu.bus.mem[r + 1]]));
+        }
+        if cpu.pc == symbols["HALT"] {
+            return pointers;
+        }
+        cpu.step();
+    }
+    panic!("PLAY_SFX_RUNTIME did not return");
+}
+
+#[test]
+fn priority_defaults_to_the_effect_and_can_be_overridden() {
+    let (asm, diags) = compile("args", "def main():\n    PLAY_SFX(\"zap\")\n    PLAY_SFX(\"blip\", 5)\n\ndef loop():\n    WAIT_RECAL()\n");
+    assert!(diags.iter().all(|d| d.severity != DiagnosticSeverity::Error), "{:?}", diags);
+    let zap = asm.find("PLAY_SFX(\"zap\")").unwrap();
+    assert!(asm[zap..].starts_with("PLAY_SFX(\"zap\") - play sound effect (one-shot)\n    LDA #3 "), "{}", &asm[zap..zap + 120]);
+    let blip = asm.find("PLAY_SFX(\"blip\")").unwrap();
+    assert!(asm[blip..].contains("    LDA RESULT+1\n    LDX #_BLIP_SFX\n"));
+}
+
+#[test]
+fn lower_priority_effects_do_not_interrupt() {
+    let (asm, _) = compile("runtime", "def main():\n    PLAY_SFX(\"zap\")\n\ndef loop():\n    WAIT_RECAL()\n");
+    let pointers = run_requests(&asm, &[(3, 0x1111), (1, 0x2222), (3, 0x3333), (4, 0x4444), (0, 0x5555)]);
+    assert_eq!(pointers, vec![0x1111, 0x1111, 0x3333, 0x4444, 0x4444]);
+}
+
+#[test]
+fn channel_argument_is_rejected() {
+    let (asm, diags) = compile("channel", "def main():\n    PLAY_SFX(\"zap\", 1, 0)\n\ndef loop():\n    WAIT_RECAL()\n");
+    let err = diags
+        .iter()
+        .find(|d| d.code == DiagnosticCode::ArityMismatch && d.severity == DiagnosticSeverity::Error)
+        .expect("channel argument accepted");
+    assert_eq!(err.line, Some(2));
+    assert!(asm.is_empty());
+}
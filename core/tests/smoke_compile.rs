@@ -37,6 +37,7 @@ def loop():
         mutable_arrays: std::collections::BTreeSet::new(),
         structs: std::collections::HashMap::new(),
         type_context: std::collections::HashMap::new(),
+        global_types: std::collections::HashMap::new(),
         output_name: None,
         buffer_requirements: None,
     };
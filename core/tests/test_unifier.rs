@@ -39,6 +39,7 @@ fn test_opts() -> CodegenOptions {
         mutable_arrays: BTreeSet::new(),
         structs: HashMap::new(),
         type_context: HashMap::new(),
+        global_types: HashMap::new(),
         buffer_requirements: None,
     }
 }
@@ -48,6 +49,7 @@ fn create_function(name: &str, body: Vec<Stmt>) -> Function {
         name: name.to_string(),
         line: 1,
         params: vec![],
+        param_types: vec![],
         body,
     }
 }
@@ -130,6 +132,7 @@ fn test_compilation_with_unused_variable() {
             Item::Function(create_function("loop", vec![])),
             Item::GlobalLet {
                 name: "unused_var".to_string(),
+                type_annotation: None,
                 value: Expr::Number(99),
                 source_line: 2,
             },
@@ -154,11 +157,13 @@ fn test_compilation_with_referenced_variable() {
             ])),
             Item::GlobalLet {
                 name: "used_var".to_string(),
+                type_annotation: None,
                 value: Expr::Number(42),
                 source_line: 1,
             },
             Item::GlobalLet {
                 name: "unused_var".to_string(),
+                type_annotation: None,
                 value: Expr::Number(99),
                 source_line: 2,
             },
@@ -0,0 +1,192 @@
+// Optional static type annotations: diagnostics, byte-sized storage and the
+// LDB/STB sequences picked for u8/i8/bool variables.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use vectrex_emulator::CPU;
+use vectrex_lang::backend::asm_to_binary::assemble_m6809;
+use vectrex_lang::backend::m6809::{load_scalar, store_scalar};
+use vectrex_lang::codegen::{emit_asm, emit_asm_with_debug, emit_asm_with_diagnostics, CodegenOptions, DiagnosticCode};
+use vectrex_lang::target::Target;
+use vectrex_lang::types::ScalarType;
+use vectrex_lang::{lex, parse_with_filename, Module};
+
+fn opts() -> CodegenOptions {
+    CodegenOptions {
+        title: "TYPES".to_string(),
+        auto_loop: true,
+        diag_freeze: false,
+        force_extended_jsr: false,
+        _bank_size: 0,
+        per_frame_silence: false,
+        debug_init_draw: false,
+        blink_intensity: false,
+        exclude_ram_org: false,
+        fast_wait: false,
+        source_path: None,
+        output_name: None,
+        assets: vec![],
+        const_values: BTreeMap::new(),
+        const_arrays: BTreeMap::new(),
+        const_string_arrays: BTreeSet::new(),
+        mutable_arrays: BTreeSet::new(),
+        structs: HashMap::new(),
+        type_context: HashMap::new(),
+        global_types: HashMap::new(),
+        buffer_requirements: None,
+    }
+}
+
+fn parse(src: &str) -> Module {
+    let tokens = lex(src).expect("lex ok");
+    parse_with_filename(&tokens, "types.vpy").expect("parse ok")
+}
+
+const GAME: &str = "\
+hp: u8 = 200
+score: i16 = -5
+alive: bool = True
+
+struct Ship:
+    x: i8
+    life: u8
+    def hit(n: u8):
+        self.life = self.life - n
+
+def main():
+    SET_INTENSITY(127)
+
+def loop():
+    d: i8 = -3
+    hp = hp - 1
+    s = Ship()
+    s.x = d
+    s.hit(2)
+    MOVE(s.x, score)
+    f(4)
+
+def f(k: u8):
+    t: u8 = k + 1
+    return t
+";
+
+// ------------------------------------------------------------------ codegen
+
+// game_asm: the debug path builds the struct registry that methods need
+fn game_asm() -> String {
+    let (asm, _, diags) = emit_asm_with_debug(&parse(GAME), Target::Vectrex, &opts());
+    assert!(!asm.is_empty(), "codegen failed: {:?}", diags);
+    asm
+}
+
+#[test]
+fn byte_globals_get_one_byte_of_ram() {
+    let asm = game_asm();
+    assert!(asm.contains("VAR_HP               EQU $C880+$14   ; User variable (u8) (1 bytes)"));
+    assert!(asm.contains("VAR_SCORE            EQU $C880+$15   ; User variable (i16) (2 bytes)"));
+    assert!(asm.contains("VAR_ALIVE            EQU $C880+$17   ; User variable (bool) (1 bytes)"));
+    assert!(asm.contains("    STB VAR_HP\n") && asm.contains("    STD VAR_SCORE\n"));
+    assert!(asm.contains("    LDB VAR_HP\n    CLRA\n"));
+}
+
+#[test]
+fn byte_locals_and_params_pack_the_frame() {
+    let asm = game_asm();
+    let f = &asm[asm.find("F: ; function").expect("f emitted")..];
+    let f = &f[..f.find("RTS").unwrap()];
+    assert!(f.contains("LEAS -2,S"), "k and t take one byte each:\n{}", f);
+    assert!(f.contains("    LDD VAR_ARG0\n    STB 0,S ; param 0\n"));
+    assert!(f.contains("    STB 1,S\n"));
+    assert!(f.contains("    LDB 1,S\n    CLRA\n"));
+    // i8 local is sign-extended when read back
+    assert!(asm.contains("    LDB 0,S\n    SEX\n"));
+}
+
+#[test]
+fn byte_struct_fields_use_byte_access() {
+    let asm = game_asm();
+    let hit = &asm[asm.find("SHIP_HIT:").expect("method emitted")..];
+    let hit = &hit[..hit.find("RTS").unwrap()];
+    // method params follow self in VAR_ARG0
+    assert!(hit.contains("    LDD VAR_ARG1\n    STB 0,S ; param 0\n"));
+    assert!(hit.contains("    LDB 1,X\n    CLRA\n"));
+    assert!(hit.contains("    STB 1,X\n"));
+}
+
+#[test]
+fn untyped_programs_keep_word_storage() {
+    let asm = emit_asm(&parse("n = 3\n\ndef main():\n    n = 4\n\ndef loop():\n    n = n + 1\n"), Target::Vectrex, &opts());
+    assert!(asm.contains("VAR_N                EQU $C880+$14   ; User variable (2 bytes)"));
+    assert!(!asm.contains("STB VAR_N"));
+}
+
+#[test]
+fn mismatches_are_reported_with_their_line() {
+    let src = "hp: u8 = 0\n\ndef loop():\n    hp = 300\n";
+    let (asm, diags) = emit_asm_with_diagnostics(&parse(src), Target::Vectrex, &opts());
+    assert!(asm.is_empty());
+    let err = diags.iter().find(|d| d.code == DiagnosticCode::TypeMismatch).expect("type error");
+    assert_eq!(err.line, Some(4));
+    assert!(err.message.contains("300"), "{}", err.message);
+
+    let (_, diags) = emit_asm_with_diagnostics(&parse("x: u7 = 0\n\ndef loop():\n    pass\n"), Target::Vectrex, &opts());
+    assert!(diags.iter().any(|d| d.code == DiagnosticCode::UnknownType));
+}
+
+#[test]
+fn const_suggestion_counts_the_bytes_of_the_type() {
+    let src = "lives: u8 = 3\nspeed = 2\n\ndef main():\n    pass\n\ndef loop():\n    SET_INTENSITY(lives + speed)\n";
+    let (_, diags) = emit_asm_with_diagnostics(&parse(src), Target::Vectrex, &opts());
+    let hint = |line| diags.iter().find(|d| d.code == DiagnosticCode::SuggestConst && d.line == Some(line)).expect("const hint");
+    assert!(hint(1).message.ends_with("(1 byte)"), "{}", hint(1).message);
+    assert!(hint(2).message.ends_with("(2 bytes)"), "{}", hint(2).message);
+}
+
+#[test]
+fn conversions_make_assignments_legal() {
+    let src = "hp: u8 = 0\n\ndef main():\n    pass\n\ndef loop():\n    n = J1_X()\n    hp = u8(n)\n";
+    let (asm, diags) = emit_asm_with_diagnostics(&parse(src), Target::Vectrex, &opts());
+    assert!(!asm.is_empty(), "{:?}", diags);
+    assert!(asm.contains("    LDD RESULT\n    CLRA\n    STD RESULT\n"));
+}
+
+// ------------------------------------------------------------ load/store sequences
+
+const RAM: u16 = 0xC880;
+const CODE: u16 = 0x1000;
+
+// round_trip: store D=value through `ty`, load it back, return RESULT
+fn round_trip(ty: ScalarType, value: i16) -> i16 {
+    let mut src = format!("RESULT EQU ${:04X}\nSLOT EQU ${:04X}\n", RAM, RAM + 2);
+    src.push_str(&format!("    LDD #${:04X}\n    STD SLOT\n    LDD #{}\n", 0xA5A5u16, value));
+    src.push_str(&store_scalar("SLOT", Some(ty)));
+    src.push_str("    LDD #0\n");
+    src.push_str(&load_scalar("SLOT", Some(ty)));
+    src.push_str("    STD RESULT\nHALT:\n    BRA HALT\n");
+
+    let (bytes, _, symbols) = assemble_m6809(&src, CODE).expect("sequence assembles");
+    let halt = symbols["HALT"];
+    let mut cpu = CPU::default();
+    cpu.bus.mem[CODE as usize..CODE as usize + bytes.len()].copy_from_slice(&bytes);
+    cpu.pc = CODE;
+    cpu.s = 0xCBE0;
+    for _ in 0..100 {
+        if cpu.pc == halt {
+            let r = RAM as usize;
+            return i16::from_be_bytes([cpu.bus.mem[r], cpu.bus.mem[r + 1]]);
+        }
+        cpu.step();
+    }
+    panic!("{:?} round trip did not finish", ty);
+}
+
+#[test]
+fn scalar_round_trips_on_the_6809() {
+    assert_eq!(round_trip(ScalarType::U8, 200), 200);
+    assert_eq!(round_trip(ScalarType::U8, 0x1234), 0x34);
+    assert_eq!(round_trip(ScalarType::I8, -3), -3);
+    assert_eq!(round_trip(ScalarType::I8, 0x80), -128);
+    assert_eq!(round_trip(ScalarType::Bool, 1), 1);
+    assert_eq!(round_trip(ScalarType::I16, -1000), -1000);
+    assert_eq!(round_trip(ScalarType::U16, 40000u16 as i16), 40000u16 as i16);
+}
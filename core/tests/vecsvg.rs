@@ -0,0 +1,237 @@
+//! SVG import/export tests for .vec resources (svg2vec / vec2svg)
+
+use vectrex_lang::vecres::{AnimFrame, Animation, Layer, Point, VecPath, VecResource};
+use vectrex_lang::vecsvg::{export_svg, import_svg, SvgImportOptions};
+
+fn pt(x: i16, y: i16) -> Point {
+    Point { x, y, intensity: None }
+}
+
+fn path(name: &str, intensity: u8, closed: bool, points: &[(i16, i16)]) -> VecPath {
+    VecPath { name: name.to_string(), intensity, closed, points: points.iter().map(|&(x, y)| pt(x, y)).collect() }
+}
+
+fn import(svg: &str) -> VecResource {
+    import_svg(svg, "test", &SvgImportOptions::default()).unwrap()
+}
+
+fn coords(path: &VecPath) -> Vec<(i16, i16)> {
+    path.points.iter().map(|p| (p.x, p.y)).collect()
+}
+
+fn sample_resource() -> VecResource {
+    let mut res = VecResource::new("ship");
+    res.canvas.width = 200;
+    res.canvas.height = 160;
+    res.layers = vec![
+        Layer {
+            name: "hull".to_string(),
+            visible: true,
+            paths: vec![
+                path("body", 127, true, &[(0, 40), (-30, -20), (30, -20)]),
+                path("fin", 64, false, &[(-100, -80), (-60, 0), (-60, 0), (100, 79)]),
+            ],
+        },
+        Layer { name: "flame".to_string(), visible: false, paths: vec![path("flame", 33, false, &[(-5, -20), (0, -35), (5, -20)])] },
+    ];
+    res
+}
+
+#[test]
+fn test_round_trip_preserves_layers_and_points() {
+    let original = sample_resource();
+    let documents = export_svg(&original);
+    assert_eq!(documents.len(), 1);
+    assert_eq!(documents[0].0, "");
+
+    let back = import(&documents[0].1);
+    assert_eq!((back.canvas.width, back.canvas.height), (200, 160));
+    assert_eq!(back.layers.len(), 2);
+    for (a, b) in original.layers.iter().zip(&back.layers) {
+        assert_eq!(a.name, b.name);
+        assert_eq!(a.visible, b.visible);
+        assert_eq!(a.paths.len(), b.paths.len());
+        for (p, q) in a.paths.iter().zip(&b.paths) {
+            assert_eq!(p.name, q.name);
+            assert_eq!(p.closed, q.closed);
+            assert_eq!(p.intensity, q.intensity, "intensity of {}", p.name);
+            assert_eq!(coords(p), coords(q), "points of {}", p.name);
+        }
+    }
+}
+
+#[test]
+fn test_export_one_document_per_animation_frame() {
+    let mut res = sample_resource();
+    res.layers[1].visible = true;
+    res.animations = vec![Animation {
+        name: "thrust".to_string(),
+        frames: vec![
+            AnimFrame { layer: "hull".to_string(), duration: 100 },
+            AnimFrame { layer: "flame".to_string(), duration: 50 },
+        ],
+    }];
+    let documents = export_svg(&res);
+    let suffixes: Vec<&str> = documents.iter().map(|(s, _)| s.as_str()).collect();
+    assert_eq!(suffixes, ["_thrust_0", "_thrust_1"]);
+    assert!(documents[1].1.contains("frame 1 (50 ms)"));
+
+    let frame1 = import(&documents[1].1);
+    assert_eq!(frame1.layers.len(), 1);
+    assert_eq!(frame1.layers[0].name, "flame");
+    assert_eq!(coords(&frame1.layers[0].paths[0]), vec![(-5, -20), (0, -35), (5, -20)]);
+}
+
+#[test]
+fn test_import_basic_shapes_in_viewbox() {
+    let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 256 256">
+        <line id="l" x1="28" y1="128" x2="228" y2="128"/>
+        <polyline points="128,28 128,228 138,228"/>
+        <polygon points="100,100 156,100 128,50"/>
+        <rect x="78" y="78" width="100" height="50"/>
+    </svg>"#;
+    let res = import(svg);
+    assert_eq!(res.layers.len(), 1);
+    assert_eq!(res.layers[0].name, "default");
+    let paths = &res.layers[0].paths;
+    let names: Vec<&str> = paths.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, ["l", "polyline1", "polygon1", "rect1"]);
+
+    // viewBox centre → origin, SVG y down → Vectrex y up
+    assert_eq!(coords(&paths[0]), vec![(-100, 0), (100, 0)]);
+    assert!(!paths[0].closed);
+    assert_eq!(coords(&paths[1]), vec![(0, 100), (0, -100), (10, -100)]);
+    assert_eq!(coords(&paths[2]), vec![(-28, 28), (28, 28), (0, 78)]);
+    assert!(paths[2].closed);
+    assert_eq!(coords(&paths[3]), vec![(-50, 50), (50, 50), (50, 0), (-50, 0)]);
+    assert!(paths[3].closed);
+}
+
+/// Largest distance from a circle of radius `r` around (cx, cy), in Vectrex units
+fn radial_error(path: &VecPath, cx: f64, cy: f64, r: f64) -> f64 {
+    path.points.iter().map(|p| ((p.x as f64 - cx).hypot(p.y as f64 - cy) - r).abs()).fold(0.0, f64::max)
+}
+
+#[test]
+fn test_circle_flattened_within_tolerance() {
+    let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="-128 -128 256 256"><circle cx="0" cy="0" r="100"/></svg>"#;
+    let coarse = import_svg(svg, "c", &SvgImportOptions { tolerance: 4.0, size: None }).unwrap();
+    let fine = import_svg(svg, "c", &SvgImportOptions { tolerance: 0.5, size: None }).unwrap();
+    let (coarse, fine) = (&coarse.layers[0].paths[0], &fine.layers[0].paths[0]);
+    assert!(coarse.closed && fine.closed);
+    assert!(coarse.points.len() < fine.points.len());
+    assert!(coarse.points.len() >= 8, "{} points", coarse.points.len());
+    // Vertices sit on the circle (up to rounding)
+    assert!(radial_error(fine, 0.0, 0.0, 100.0) <= 0.75);
+    // Chord midpoints stay within the tolerance (+ rounding)
+    for (i, a) in coarse.points.iter().enumerate() {
+        let b = coarse.points[(i + 1) % coarse.points.len()];
+        let mid = ((a.x + b.x) as f64 / 2.0, (a.y + b.y) as f64 / 2.0);
+        assert!(100.0 - mid.0.hypot(mid.1) <= 4.0 + 1.0, "chord {:?} → {:?}", a, b);
+    }
+}
+
+#[test]
+fn test_path_commands_curves_and_arcs() {
+    // Relative arc with packed flags: upper half of a circle of radius 40 around (128, 128)
+    let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 256 256">
+        <path id="arc" d="M88 128a40 40 0 0180 0"/>
+        <path id="curve" d="M28 228 C 28 128, 228 128, 228 228"/>
+        <path id="steps" d="m 10,10 h 20 v 20 H 10 z M 50 50 l 10 0 10 10"/>
+        <path id="quad" d="M0 128 Q 64 0 128 128 T 256 128"/>
+    </svg>"#;
+    let res = import(svg);
+    let paths = &res.layers[0].paths;
+    let by_name = |name: &str| paths.iter().find(|p| p.name == name).unwrap_or_else(|| panic!("no path {}", name));
+
+    let arc = by_name("arc");
+    assert_eq!(arc.points.first().map(|p| (p.x, p.y)), Some((-40, 0)));
+    assert_eq!(arc.points.last().map(|p| (p.x, p.y)), Some((40, 0)));
+    assert!(radial_error(arc, 0.0, 0.0, 40.0) <= 0.75);
+    // Sweep flag 1 in SVG (clockwise, y down) bulges upwards in Vectrex space
+    assert!(arc.points.iter().all(|p| p.y >= 0));
+    assert!(arc.points.iter().any(|p| p.y == 40));
+
+    // Cubic: vertices lie on B(t); the apex at t=0.5 is y = 228 - 75 = 153 → Vectrex -25
+    let curve = by_name("curve");
+    assert!(curve.points.len() > 4);
+    let apex = curve.points.iter().map(|p| p.y).max().unwrap();
+    assert!((apex + 25).abs() <= 1, "apex {}", apex);
+
+    // Subpaths become separate paths; implicit line-tos after m/l are relative
+    assert_eq!(coords(by_name("steps")), vec![(-118, 118), (-98, 118), (-98, 98), (-118, 98)]);
+    assert!(by_name("steps").closed);
+    assert_eq!(coords(by_name("steps_1")), vec![(-78, 78), (-68, 78), (-58, 68)]);
+
+    // Smooth quadratic mirrors the control point: second hump goes the other way
+    let quad = by_name("quad");
+    assert!(quad.points.iter().any(|p| p.y > 50) && quad.points.iter().any(|p| p.y < -50));
+}
+
+#[test]
+fn test_groups_become_layers() {
+    let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape" viewBox="-128 -128 256 256">
+        <line x1="0" y1="0" x2="10" y2="0"/>
+        <g inkscape:label="Ship" inkscape:groupmode="layer" transform="translate(10,20)">
+            <g transform="scale(2)"><line x1="0" y1="0" x2="10" y2="0"/></g>
+            <line x1="0" y1="0" x2="0" y2="10" style="display:none"/>
+        </g>
+        <g id="hidden" style="display:none"><line x1="0" y1="0" x2="1" y2="1"/></g>
+        <defs><line x1="0" y1="0" x2="5" y2="5"/></defs>
+    </svg>"#;
+    let res = import(svg);
+    let names: Vec<&str> = res.layers.iter().map(|l| l.name.as_str()).collect();
+    assert_eq!(names, ["default", "Ship", "hidden"]);
+    assert_eq!(res.layers[0].paths.len(), 1);
+    // Nested group merged into its layer with both transforms applied; hidden shape skipped
+    assert_eq!(res.layers[1].paths.len(), 1);
+    assert_eq!(coords(&res.layers[1].paths[0]), vec![(10, -20), (30, -20)]);
+    assert!(res.layers[1].visible);
+    assert!(!res.layers[2].visible);
+    assert_eq!(res.layers[2].paths.len(), 1);
+}
+
+#[test]
+fn test_stroke_opacity_maps_to_intensity() {
+    let svg = r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="-128 -128 256 256">
+        <line id="full" x1="0" y1="0" x2="10" y2="0" stroke="#fff"/>
+        <line id="half" x1="0" y1="0" x2="10" y2="0" stroke-opacity="0.5"/>
+        <line id="quarter" x1="0" y1="0" x2="10" y2="0" stroke-opacity="0.9" style="stroke-opacity:25%"/>
+        <g opacity="0.5"><line id="faded" x1="0" y1="0" x2="10" y2="0" stroke-opacity="0.5"/></g>
+        <rect id="filled" width="10" height="10" stroke="none" fill-opacity="0.2"/>
+    </svg>"##;
+    let res = import(svg);
+    let intensity = |name: &str| res.layers.iter().flat_map(|l| &l.paths).find(|p| p.name == name).unwrap().intensity;
+    assert_eq!(intensity("full"), 127);
+    assert_eq!(intensity("half"), 64);
+    assert_eq!(intensity("quarter"), 32);
+    assert_eq!(intensity("faded"), 32);
+    assert_eq!(intensity("filled"), 25);
+}
+
+#[test]
+fn test_scaling_large_drawings() {
+    // A4 in millimetres: scaled down so the long side spans 256 units
+    let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="210mm" height="297mm" viewBox="0 0 210 297">
+        <line x1="0" y1="0" x2="210" y2="297"/>
+    </svg>"#;
+    let res = import(svg);
+    assert_eq!((res.canvas.width, res.canvas.height), (181, 256));
+    assert_eq!(coords(&res.layers[0].paths[0]), vec![(-91, 128), (91, -128)]);
+
+    let fitted = import_svg(svg, "a4", &SvgImportOptions { tolerance: 0.5, size: Some(200) }).unwrap();
+    assert_eq!(coords(&fitted.layers[0].paths[0]), vec![(-71, 100), (71, -100)]);
+
+    // No viewBox or size: the drawing's bounds are centred
+    let bare = import(r#"<svg xmlns="http://www.w3.org/2000/svg"><polyline points="100,100 140,100 140,120"/></svg>"#);
+    assert_eq!(coords(&bare.layers[0].paths[0]), vec![(-20, 10), (20, 10), (20, -10)]);
+}
+
+#[test]
+fn test_import_errors() {
+    assert!(import_svg("<html/>", "x", &SvgImportOptions::default()).is_err());
+    assert!(import_svg("<svg", "x", &SvgImportOptions::default()).is_err());
+    let bad = r#"<svg xmlns="http://www.w3.org/2000/svg"><path id="p" d="M 0 0 L 10"/></svg>"#;
+    let err = import_svg(bad, "x", &SvgImportOptions::default()).unwrap_err();
+    assert!(format!("{:#}", err).contains("<path id=\"p\">"), "{:#}", err);
+}
@@ -0,0 +1,206 @@
+//! Analog section and vector beam model
+//!
+//! Models the DAC sample-and-hold circuits (X, Y, RELATIVE/ZERO reference,
+//! BRIGHTNESS), the joystick comparator and the X/Y integrators. Instead of
+//! rasterising, the beam records each lit stretch as a line segment, the same
+//! approach as the JSVecX `alg_*` code: a segment starts when BLANK goes high,
+//! and ends when BLANK drops or the integrator slope/brightness changes.
+//!
+//! Coordinates are reported relative to the screen centre with Y pointing up.
+//! One unit is one DAC step integrated for one E-clock cycle, so a `Moveto`
+//! of 127 at scale $7F moves roughly 127 * 127 units.
+
+use crate::via::Via;
+
+/// Integrator range (same as JSVecX `ALG_MAX_X` / `ALG_MAX_Y`)
+pub const BEAM_MAX_X: i32 = 33000;
+pub const BEAM_MAX_Y: i32 = 41000;
+const CENTER_X: i32 = BEAM_MAX_X / 2;
+const CENTER_Y: i32 = BEAM_MAX_Y / 2;
+
+/// One lit vector drawn by the beam
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Segment {
+    pub x0: i32,
+    pub y0: i32,
+    pub x1: i32,
+    pub y1: i32,
+    /// Brightness sample-and-hold value (0-127)
+    pub intensity: u8,
+}
+
+impl Segment {
+    pub fn is_dot(&self) -> bool {
+        self.x0 == self.x1 && self.y0 == self.y1
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Beam {
+    /// Sample-and-hold values (unsigned, 0x80 = centre)
+    pub rsh: i32,
+    pub xsh: i32,
+    pub ysh: i32,
+    pub zsh: i32,
+    /// Joystick pot channels (0x00..0xFF, 0x80 = centred)
+    pub joy: [i32; 4],
+    jsh: i32,
+    /// Comparator output as seen on PB5
+    pub compare: u8,
+
+    dx: i32,
+    dy: i32,
+    pub curr_x: i32,
+    pub curr_y: i32,
+
+    vectoring: bool,
+    vec_x0: i32,
+    vec_y0: i32,
+    vec_x1: i32,
+    vec_y1: i32,
+    vec_dx: i32,
+    vec_dy: i32,
+    vec_intensity: i32,
+
+    /// Segments completed since the last `take_segments()`
+    pub segments: Vec<Segment>,
+}
+
+impl Default for Beam {
+    fn default() -> Self {
+        Beam {
+            rsh: 128,
+            xsh: 128,
+            ysh: 128,
+            zsh: 0,
+            joy: [128; 4],
+            jsh: 128,
+            compare: 0,
+            dx: 0,
+            dy: 0,
+            curr_x: CENTER_X,
+            curr_y: CENTER_Y,
+            vectoring: false,
+            vec_x0: 0,
+            vec_y0: 0,
+            vec_x1: 0,
+            vec_y1: 0,
+            vec_dx: 0,
+            vec_dy: 0,
+            vec_intensity: 0,
+            segments: Vec::new(),
+        }
+    }
+}
+
+impl Beam {
+    /// Port A feeds the DAC, which always drives the X sample-and-hold.
+    pub fn set_dac(&mut self, ora: u8, orb: u8) {
+        self.xsh = (ora ^ 0x80) as i32;
+        self.update_mux(orb);
+    }
+
+    /// Port B bits 1-2 select the mux channel; bit 0 low enables the demultiplexer.
+    pub fn update_mux(&mut self, orb: u8) {
+        let enabled = orb & 0x01 == 0;
+        match orb & 0x06 {
+            0x00 => {
+                self.jsh = self.joy[0];
+                if enabled {
+                    self.ysh = self.xsh;
+                }
+            }
+            0x02 => {
+                self.jsh = self.joy[1];
+                if enabled {
+                    self.rsh = self.xsh;
+                }
+            }
+            0x04 => {
+                self.jsh = self.joy[2];
+                if enabled {
+                    self.zsh = if self.xsh > 0x80 { self.xsh - 0x80 } else { 0 };
+                }
+            }
+            _ => {
+                self.jsh = self.joy[3];
+            }
+        }
+
+        self.compare = if self.jsh > self.xsh { 0x20 } else { 0 };
+        self.dx = self.xsh - self.rsh;
+        self.dy = self.rsh - self.ysh;
+    }
+
+    fn in_range(&self) -> bool {
+        (0..BEAM_MAX_X).contains(&self.curr_x) && (0..BEAM_MAX_Y).contains(&self.curr_y)
+    }
+
+    fn emit(&mut self) {
+        self.segments.push(Segment {
+            x0: self.vec_x0 - CENTER_X,
+            y0: CENTER_Y - self.vec_y0,
+            x1: self.vec_x1 - CENTER_X,
+            y1: CENTER_Y - self.vec_y1,
+            intensity: (self.vec_intensity & 0xFF) as u8,
+        });
+    }
+
+    fn start_vector(&mut self, dx: i32, dy: i32) {
+        self.vec_x0 = self.curr_x;
+        self.vec_y0 = self.curr_y;
+        self.vec_x1 = self.curr_x;
+        self.vec_y1 = self.curr_y;
+        self.vec_dx = dx;
+        self.vec_dy = dy;
+        self.vec_intensity = self.zsh & 0xFF;
+    }
+
+    /// Advance the integrators by one E-clock cycle using the VIA control lines.
+    pub fn tick(&mut self, via: &Via) {
+        let beam_on = via.beam_on();
+
+        let (sig_dx, sig_dy) = if via.zero_asserted() {
+            (CENTER_X - self.curr_x, CENTER_Y - self.curr_y)
+        } else if via.ramp_active() {
+            (self.dx, self.dy)
+        } else {
+            (0, 0)
+        };
+
+        if !self.vectoring {
+            if beam_on && self.in_range() {
+                self.vectoring = true;
+                self.start_vector(sig_dx, sig_dy);
+            }
+        } else if !beam_on {
+            self.vectoring = false;
+            self.emit();
+        } else if sig_dx != self.vec_dx || sig_dy != self.vec_dy || (self.zsh & 0xFF) != self.vec_intensity {
+            self.emit();
+            if self.in_range() {
+                self.start_vector(sig_dx, sig_dy);
+            } else {
+                self.vectoring = false;
+            }
+        }
+
+        self.curr_x += sig_dx;
+        self.curr_y += sig_dy;
+
+        if self.vectoring && self.in_range() {
+            self.vec_x1 = self.curr_x;
+            self.vec_y1 = self.curr_y;
+        }
+    }
+
+    /// Current beam position in centred coordinates (Y up)
+    pub fn position(&self) -> (i32, i32) {
+        (self.curr_x - CENTER_X, CENTER_Y - self.curr_y)
+    }
+
+    /// Drain completed segments
+    pub fn take_segments(&mut self) -> Vec<Segment> {
+        std::mem::take(&mut self.segments)
+    }
+}
@@ -0,0 +1,201 @@
+//! Vectrex memory map
+//!
+//! | Range         | Device                                            |
+//! |---------------|---------------------------------------------------|
+//! | $0000-$7FFF   | Cartridge (banked: $0000-$3FFF window, $4000-$7FFF last bank) |
+//! | $C800-$CFFF   | 1K RAM, mirrored                                  |
+//! | $D000-$D7FF   | 6522 VIA, mirrored every 16 bytes                 |
+//! | $DF00         | Bank select register (multibank cartridges)      |
+//! | $E000-$FFFF   | BIOS ROM (write-protected once loaded)            |
+//!
+//! Anything not claimed by a device falls through to the flat 64K `mem`
+//! array, which also backs RAM and ROM. Tests can therefore poke programs
+//! straight into `bus.mem` without loading a BIOS or cartridge.
+
+use crate::beam::Beam;
+use crate::psg::Psg;
+use crate::via::Via;
+
+pub const RAM_START: u16 = 0xC800;
+pub const BANK_SELECT: u16 = 0xDF00;
+pub const BANK_SIZE: usize = 0x4000;
+
+#[derive(Debug, Clone)]
+pub struct Bus {
+    /// Flat 64K backing store
+    pub mem: Vec<u8>,
+    pub via: Via,
+    pub psg: Psg,
+    pub beam: Beam,
+    /// E-clock cycles elapsed (advanced by `tick`)
+    pub cycle: u64,
+    cart: Vec<u8>,
+    bank: usize,
+    bios_loaded: bool,
+}
+
+impl Default for Bus {
+    fn default() -> Self {
+        Bus {
+            mem: vec![0; 0x10000],
+            via: Via::default(),
+            psg: Psg::default(),
+            beam: Beam::default(),
+            cycle: 0,
+            cart: Vec::new(),
+            bank: 0,
+            bios_loaded: false,
+        }
+    }
+}
+
+impl Bus {
+    pub fn bios_loaded(&self) -> bool {
+        self.bios_loaded
+    }
+
+    /// Load a 4K ($F000) or 8K ($E000) BIOS image. Returns false for other sizes.
+    pub fn load_bios(&mut self, data: &[u8]) -> bool {
+        let base = match data.len() {
+            0x1000 => 0xF000,
+            0x2000 => 0xE000,
+            _ => return false,
+        };
+        self.mem[base..base + data.len()].copy_from_slice(data);
+        self.bios_loaded = true;
+        true
+    }
+
+    /// Insert a cartridge image. Images up to 32K are mapped linearly; larger
+    /// images are split into 16K banks with the last bank fixed at $4000.
+    pub fn load_cartridge(&mut self, data: &[u8]) {
+        self.cart = data.to_vec();
+        self.bank = 0;
+    }
+
+    pub fn cartridge_len(&self) -> usize {
+        self.cart.len()
+    }
+
+    /// Currently selected bank in the $0000-$3FFF window
+    pub fn current_bank(&self) -> usize {
+        self.bank
+    }
+
+    fn is_banked(&self) -> bool {
+        self.cart.len() > 0x8000
+    }
+
+    fn cart_offset(&self, addr: u16) -> usize {
+        let addr = addr as usize;
+        if !self.is_banked() {
+            return addr;
+        }
+        if addr < BANK_SIZE {
+            self.bank * BANK_SIZE + addr
+        } else {
+            let last = self.cart.len().div_ceil(BANK_SIZE) - 1;
+            last * BANK_SIZE + (addr - BANK_SIZE)
+        }
+    }
+
+    fn ram_index(addr: u16) -> usize {
+        (RAM_START | (addr & 0x03FF)) as usize
+    }
+
+    /// CPU read with device side effects
+    pub fn read(&mut self, addr: u16) -> u8 {
+        if addr & 0xE000 == 0xC000 {
+            if addr & 0x0800 != 0 {
+                return self.mem[Self::ram_index(addr)];
+            }
+            return self.io_read(addr as u8 & 0x0F);
+        }
+        if addr < 0x8000 && !self.cart.is_empty() {
+            return self.cart.get(self.cart_offset(addr)).copied().unwrap_or(0xFF);
+        }
+        self.mem[addr as usize]
+    }
+
+    /// Side-effect free read for debuggers, mailboxes and snapshots
+    pub fn peek(&self, addr: u16) -> u8 {
+        if addr & 0xE000 == 0xC000 {
+            if addr & 0x0800 != 0 {
+                return self.mem[Self::ram_index(addr)];
+            }
+            return self.via.peek(addr as u8 & 0x0F);
+        }
+        if addr < 0x8000 && !self.cart.is_empty() {
+            return self.cart.get(self.cart_offset(addr)).copied().unwrap_or(0xFF);
+        }
+        self.mem[addr as usize]
+    }
+
+    /// CPU write. RAM and VIA decode overlap in the $D800-$DFFF mirror, so a
+    /// single write can hit both (as on real hardware).
+    pub fn write(&mut self, addr: u16, v: u8) {
+        if addr & 0xE000 == 0xC000 {
+            if addr & 0x0800 != 0 {
+                self.mem[Self::ram_index(addr)] = v;
+            }
+            if addr & 0x1000 != 0 {
+                self.io_write(addr as u8 & 0x0F, v);
+            }
+            if addr == BANK_SELECT && self.is_banked() {
+                self.bank = v as usize % self.cart.len().div_ceil(BANK_SIZE);
+            }
+            return;
+        }
+        if addr >= 0xE000 && self.bios_loaded {
+            return;
+        }
+        if addr < 0x8000 && !self.cart.is_empty() {
+            return;
+        }
+        self.mem[addr as usize] = v;
+    }
+
+    fn io_read(&mut self, reg: u8) -> u8 {
+        match reg {
+            0x0 => {
+                // PB5 is the comparator input; PB7 belongs to timer 1 when ACR bit 7 is set
+                let orb = self.via.read(0);
+                if self.via.acr & 0x80 != 0 {
+                    (orb & 0x5F) | self.via.t1_pb7 | self.beam.compare
+                } else {
+                    (orb & 0xDF) | self.beam.compare
+                }
+            }
+            0x1 | 0xF => {
+                let ora = self.via.read(reg);
+                if Psg::drives_port_a(self.via.orb) {
+                    self.psg.read_data()
+                } else {
+                    ora
+                }
+            }
+            _ => self.via.read(reg),
+        }
+    }
+
+    fn io_write(&mut self, reg: u8, v: u8) {
+        if self.via.write(reg, v) {
+            let (ora, orb) = (self.via.ora, self.via.orb);
+            self.psg.bus_cycle(orb, ora, self.cycle);
+            if reg == 0 {
+                self.beam.update_mux(orb);
+            } else {
+                self.beam.set_dac(ora, orb);
+            }
+        }
+    }
+
+    /// Advance VIA timers and the beam integrators by `cycles` E-clock cycles.
+    pub fn tick(&mut self, cycles: u64) {
+        for _ in 0..cycles {
+            self.via.tick();
+            self.beam.tick(&self.via);
+            self.cycle += 1;
+        }
+    }
+}
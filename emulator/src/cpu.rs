@@ -0,0 +1,1399 @@
+//! MC6809 CPU core
+//!
+//! Cycle-counted interpreter for the full documented MC6809 instruction set
+//! (pages 1, 2 and 3, all addressing modes including indexed indirect and
+//! PC-relative). Cycle counts follow the Motorola MC6809 datasheet; indexed
+//! post-byte overhead is added on top of the base count exactly as listed in
+//! the "Indexed Addressing Modes" table.
+//!
+//! `step()` either services one pending interrupt or executes one instruction
+//! and returns `false` when it hits an illegal opcode (the PC is left just
+//! after the offending byte so callers can report it).
+
+use crate::bus::Bus;
+
+/// Interrupt and reset vectors (big-endian words at the top of the address space)
+pub const VEC_SWI3: u16 = 0xFFF2;
+pub const VEC_SWI2: u16 = 0xFFF4;
+pub const VEC_FIRQ: u16 = 0xFFF6;
+pub const VEC_IRQ: u16 = 0xFFF8;
+pub const VEC_SWI: u16 = 0xFFFA;
+pub const VEC_NMI: u16 = 0xFFFC;
+pub const VEC_RESET: u16 = 0xFFFE;
+
+/// Default system stack (BIOS `Vec_Default_Stk`)
+pub const DEFAULT_STACK: u16 = 0xCBEA;
+
+/// BIOS entry points observed by the CPU for frame/intensity bookkeeping
+pub const BIOS_WAIT_RECAL: u16 = 0xF192;
+pub const BIOS_INTENSITY_A: u16 = 0xF2AB;
+const BIOS_DRAW_VL_ENTRIES: [u16; 6] = [0xF3CE, 0xF3DA, 0xF3DD, 0xF3DF, 0xF410, 0xF46E];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WaitState {
+    Running,
+    /// CWAI: entire state already stacked, waiting for an unmasked interrupt
+    Cwai,
+    /// SYNC: waiting for any interrupt line (masked or not)
+    Sync,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Immediate,
+    Direct,
+    Indexed,
+    Extended,
+}
+
+impl Mode {
+    /// Addressing mode encoded in bits 4-5 of the opcode for the $80-$FF block
+    fn from_column(op: u8) -> Mode {
+        match (op >> 4) & 0x03 {
+            0 => Mode::Immediate,
+            1 => Mode::Direct,
+            2 => Mode::Indexed,
+            _ => Mode::Extended,
+        }
+    }
+
+    /// Addressing mode of the $00-$0F / $60-$7F read-modify-write block
+    fn from_rmw(op: u8) -> Mode {
+        match op >> 4 {
+            0x0 => Mode::Direct,
+            0x6 => Mode::Indexed,
+            _ => Mode::Extended,
+        }
+    }
+}
+
+/// Base cycles for an 8-bit ALU/load/store op in the $80-$FF block
+fn cycles8(mode: Mode) -> u64 {
+    match mode {
+        Mode::Immediate => 2,
+        Mode::Direct | Mode::Indexed => 4,
+        Mode::Extended => 5,
+    }
+}
+
+/// Base cycles for SUBD/ADDD/CMPX (page 1)
+fn cycles16_alu(mode: Mode) -> u64 {
+    match mode {
+        Mode::Immediate => 4,
+        Mode::Direct | Mode::Indexed => 6,
+        Mode::Extended => 7,
+    }
+}
+
+/// Base cycles for 16-bit loads/stores (LDD/STD/LDX/STX/LDU/STU)
+fn cycles16_ldst(mode: Mode) -> u64 {
+    match mode {
+        Mode::Immediate => 3,
+        Mode::Direct | Mode::Indexed => 5,
+        Mode::Extended => 6,
+    }
+}
+
+/// Base cycles for read-modify-write memory ops (NEG/COM/.../CLR/TST)
+fn cycles_rmw(mode: Mode) -> u64 {
+    match mode {
+        Mode::Extended => 7,
+        _ => 6,
+    }
+}
+
+/// MC6809 processor state plus the attached Vectrex bus.
+#[derive(Debug, Clone)]
+pub struct CPU {
+    pub a: u8,
+    pub b: u8,
+    pub dp: u8,
+    pub x: u16,
+    pub y: u16,
+    pub u: u16,
+    pub s: u16,
+    pub pc: u16,
+
+    pub cc_e: bool,
+    pub cc_f: bool,
+    pub cc_h: bool,
+    pub cc_i: bool,
+    pub cc_n: bool,
+    pub cc_z: bool,
+    pub cc_v: bool,
+    pub cc_c: bool,
+
+    /// Total elapsed E-clock cycles
+    pub cycles: u64,
+
+    /// Interrupt request lines. IRQ/FIRQ are sampled before each step and
+    /// cleared when serviced; the machine harness re-asserts them from the VIA.
+    pub irq_pending: bool,
+    pub firq_pending: bool,
+    pub nmi_pending: bool,
+    /// True while an IRQ handler is running (set on entry, cleared by RTI)
+    pub in_irq_handler: bool,
+
+    /// Number of BIOS `Wait_Recal` calls observed (one per displayed frame)
+    pub frame_count: u64,
+    /// Last value passed to BIOS `Intensity_a`
+    pub last_intensity: u8,
+    /// Number of BIOS `Draw_VL*` calls observed
+    pub draw_vl_count: u64,
+
+    pub bus: Bus,
+
+    wait: WaitState,
+    /// Executed-instruction counters: page 1 at 0..256, page 2 at 256..512, page 3 at 512..768
+    opcode_counts: Vec<u64>,
+}
+
+impl Default for CPU {
+    fn default() -> Self {
+        CPU {
+            a: 0,
+            b: 0,
+            dp: 0,
+            x: 0,
+            y: 0,
+            u: 0,
+            s: DEFAULT_STACK,
+            pc: 0,
+            cc_e: false,
+            cc_f: false,
+            cc_h: false,
+            cc_i: false,
+            cc_n: false,
+            cc_z: false,
+            cc_v: false,
+            cc_c: false,
+            cycles: 0,
+            irq_pending: false,
+            firq_pending: false,
+            nmi_pending: false,
+            in_irq_handler: false,
+            frame_count: 0,
+            last_intensity: 0,
+            draw_vl_count: 0,
+            bus: Bus::default(),
+            wait: WaitState::Running,
+            opcode_counts: vec![0; 768],
+        }
+    }
+}
+
+impl CPU {
+    // ------------------------------------------------------------------
+    // Register helpers
+    // ------------------------------------------------------------------
+
+    pub fn d(&self) -> u16 {
+        u16::from_be_bytes([self.a, self.b])
+    }
+
+    pub fn set_d(&mut self, v: u16) {
+        let [a, b] = v.to_be_bytes();
+        self.a = a;
+        self.b = b;
+    }
+
+    /// Condition code register packed as EFHINZVC
+    pub fn cc(&self) -> u8 {
+        (self.cc_e as u8) << 7
+            | (self.cc_f as u8) << 6
+            | (self.cc_h as u8) << 5
+            | (self.cc_i as u8) << 4
+            | (self.cc_n as u8) << 3
+            | (self.cc_z as u8) << 2
+            | (self.cc_v as u8) << 1
+            | (self.cc_c as u8)
+    }
+
+    pub fn set_cc(&mut self, v: u8) {
+        self.cc_e = v & 0x80 != 0;
+        self.cc_f = v & 0x40 != 0;
+        self.cc_h = v & 0x20 != 0;
+        self.cc_i = v & 0x10 != 0;
+        self.cc_n = v & 0x08 != 0;
+        self.cc_z = v & 0x04 != 0;
+        self.cc_v = v & 0x02 != 0;
+        self.cc_c = v & 0x01 != 0;
+    }
+
+    /// Copy a 4K or 8K BIOS image to the top of the address space and
+    /// write-protect it. Returns false for unsupported sizes.
+    pub fn load_bios(&mut self, data: &[u8]) -> bool {
+        self.bus.load_bios(data)
+    }
+
+    /// Hardware reset: DP=0, I and F masked, PC from the reset vector.
+    pub fn reset(&mut self) {
+        self.dp = 0;
+        self.set_cc(0x50);
+        self.wait = WaitState::Running;
+        self.irq_pending = false;
+        self.firq_pending = false;
+        self.nmi_pending = false;
+        self.in_irq_handler = false;
+        self.pc = self.read16(VEC_RESET);
+    }
+
+    // ------------------------------------------------------------------
+    // Memory helpers
+    // ------------------------------------------------------------------
+
+    fn read8(&mut self, addr: u16) -> u8 {
+        self.bus.read(addr)
+    }
+
+    fn write8(&mut self, addr: u16, v: u8) {
+        self.bus.write(addr, v);
+    }
+
+    fn read16(&mut self, addr: u16) -> u16 {
+        let hi = self.read8(addr);
+        let lo = self.read8(addr.wrapping_add(1));
+        u16::from_be_bytes([hi, lo])
+    }
+
+    fn write16(&mut self, addr: u16, v: u16) {
+        let [hi, lo] = v.to_be_bytes();
+        self.write8(addr, hi);
+        self.write8(addr.wrapping_add(1), lo);
+    }
+
+    fn fetch8(&mut self) -> u8 {
+        let v = self.read8(self.pc);
+        self.pc = self.pc.wrapping_add(1);
+        v
+    }
+
+    fn fetch16(&mut self) -> u16 {
+        let v = self.read16(self.pc);
+        self.pc = self.pc.wrapping_add(2);
+        v
+    }
+
+    fn push8_s(&mut self, v: u8) {
+        self.s = self.s.wrapping_sub(1);
+        self.write8(self.s, v);
+    }
+
+    fn push16_s(&mut self, v: u16) {
+        let [hi, lo] = v.to_be_bytes();
+        self.push8_s(lo);
+        self.push8_s(hi);
+    }
+
+    fn pull8_s(&mut self) -> u8 {
+        let v = self.read8(self.s);
+        self.s = self.s.wrapping_add(1);
+        v
+    }
+
+    fn pull16_s(&mut self) -> u16 {
+        let hi = self.pull8_s();
+        let lo = self.pull8_s();
+        u16::from_be_bytes([hi, lo])
+    }
+
+    // ------------------------------------------------------------------
+    // Flag helpers
+    // ------------------------------------------------------------------
+
+    fn set_nz8(&mut self, v: u8) {
+        self.cc_n = v & 0x80 != 0;
+        self.cc_z = v == 0;
+    }
+
+    fn set_nz16(&mut self, v: u16) {
+        self.cc_n = v & 0x8000 != 0;
+        self.cc_z = v == 0;
+    }
+
+    fn add8(&mut self, a: u8, m: u8, carry: bool) -> u8 {
+        let c = carry as u16;
+        let r = a as u16 + m as u16 + c;
+        let r8 = r as u8;
+        self.cc_h = ((a & 0x0F) + (m & 0x0F) + c as u8) & 0x10 != 0;
+        self.cc_c = r & 0x100 != 0;
+        self.cc_v = (!(a ^ m) & (a ^ r8) & 0x80) != 0;
+        self.set_nz8(r8);
+        r8
+    }
+
+    fn sub8(&mut self, a: u8, m: u8, borrow: bool) -> u8 {
+        let r = (a as u16).wrapping_sub(m as u16).wrapping_sub(borrow as u16);
+        let r8 = r as u8;
+        self.cc_c = r & 0x100 != 0;
+        self.cc_v = ((a ^ m) & (a ^ r8) & 0x80) != 0;
+        self.set_nz8(r8);
+        r8
+    }
+
+    fn add16(&mut self, a: u16, m: u16) -> u16 {
+        let r = a as u32 + m as u32;
+        let r16 = r as u16;
+        self.cc_c = r & 0x1_0000 != 0;
+        self.cc_v = (!(a ^ m) & (a ^ r16) & 0x8000) != 0;
+        self.set_nz16(r16);
+        r16
+    }
+
+    fn sub16(&mut self, a: u16, m: u16) -> u16 {
+        let r = (a as u32).wrapping_sub(m as u32);
+        let r16 = r as u16;
+        self.cc_c = r & 0x1_0000 != 0;
+        self.cc_v = ((a ^ m) & (a ^ r16) & 0x8000) != 0;
+        self.set_nz16(r16);
+        r16
+    }
+
+    fn logic8(&mut self, r: u8) -> u8 {
+        self.set_nz8(r);
+        self.cc_v = false;
+        r
+    }
+
+    // Read-modify-write unary operations shared by A, B and memory forms
+
+    fn op_neg(&mut self, v: u8) -> u8 {
+        self.sub8(0, v, false)
+    }
+
+    fn op_com(&mut self, v: u8) -> u8 {
+        let r = !v;
+        self.set_nz8(r);
+        self.cc_v = false;
+        self.cc_c = true;
+        r
+    }
+
+    fn op_lsr(&mut self, v: u8) -> u8 {
+        self.cc_c = v & 0x01 != 0;
+        let r = v >> 1;
+        self.set_nz8(r);
+        r
+    }
+
+    fn op_ror(&mut self, v: u8) -> u8 {
+        let r = (v >> 1) | ((self.cc_c as u8) << 7);
+        self.cc_c = v & 0x01 != 0;
+        self.set_nz8(r);
+        r
+    }
+
+    fn op_asr(&mut self, v: u8) -> u8 {
+        self.cc_c = v & 0x01 != 0;
+        let r = (v >> 1) | (v & 0x80);
+        self.set_nz8(r);
+        r
+    }
+
+    fn op_asl(&mut self, v: u8) -> u8 {
+        self.cc_c = v & 0x80 != 0;
+        self.cc_v = ((v ^ (v << 1)) & 0x80) != 0;
+        let r = v << 1;
+        self.set_nz8(r);
+        r
+    }
+
+    fn op_rol(&mut self, v: u8) -> u8 {
+        let r = (v << 1) | self.cc_c as u8;
+        self.cc_c = v & 0x80 != 0;
+        self.cc_v = ((v ^ (v << 1)) & 0x80) != 0;
+        self.set_nz8(r);
+        r
+    }
+
+    fn op_dec(&mut self, v: u8) -> u8 {
+        let r = v.wrapping_sub(1);
+        self.cc_v = v == 0x80;
+        self.set_nz8(r);
+        r
+    }
+
+    fn op_inc(&mut self, v: u8) -> u8 {
+        let r = v.wrapping_add(1);
+        self.cc_v = v == 0x7F;
+        self.set_nz8(r);
+        r
+    }
+
+    fn op_tst(&mut self, v: u8) -> u8 {
+        self.set_nz8(v);
+        self.cc_v = false;
+        v
+    }
+
+    fn op_clr(&mut self) -> u8 {
+        self.cc_n = false;
+        self.cc_z = true;
+        self.cc_v = false;
+        self.cc_c = false;
+        0
+    }
+
+    /// Apply the unary operation selected by the low nibble of a $00/$40/$50/$60/$70 opcode.
+    /// Returns None for the undefined slots (x1, x2, x5, xB).
+    fn unary(&mut self, low: u8, v: u8) -> Option<u8> {
+        Some(match low {
+            0x0 => self.op_neg(v),
+            0x3 => self.op_com(v),
+            0x4 => self.op_lsr(v),
+            0x6 => self.op_ror(v),
+            0x7 => self.op_asr(v),
+            0x8 => self.op_asl(v),
+            0x9 => self.op_rol(v),
+            0xA => self.op_dec(v),
+            0xC => self.op_inc(v),
+            0xD => self.op_tst(v),
+            0xF => self.op_clr(),
+            _ => return None,
+        })
+    }
+
+    // ------------------------------------------------------------------
+    // Effective addresses
+    // ------------------------------------------------------------------
+
+    fn index_reg(&self, sel: u8) -> u16 {
+        match sel & 3 {
+            0 => self.x,
+            1 => self.y,
+            2 => self.u,
+            _ => self.s,
+        }
+    }
+
+    fn set_index_reg(&mut self, sel: u8, v: u16) {
+        match sel & 3 {
+            0 => self.x = v,
+            1 => self.y = v,
+            2 => self.u = v,
+            _ => self.s = v,
+        }
+    }
+
+    /// Decode an indexed post-byte, adding its cycle overhead.
+    fn ea_indexed(&mut self) -> u16 {
+        let post = self.fetch8();
+        let reg = (post >> 5) & 0x03;
+
+        if post & 0x80 == 0 {
+            // 5-bit signed offset, never indirect
+            let off = (((post & 0x1F) << 3) as i8 >> 3) as i16 as u16;
+            self.cycles += 1;
+            return self.index_reg(reg).wrapping_add(off);
+        }
+
+        let indirect = post & 0x10 != 0;
+        let r = self.index_reg(reg);
+        let ea = match post & 0x0F {
+            0x0 => {
+                self.set_index_reg(reg, r.wrapping_add(1));
+                self.cycles += 2;
+                r
+            }
+            0x1 => {
+                self.set_index_reg(reg, r.wrapping_add(2));
+                self.cycles += 3;
+                r
+            }
+            0x2 => {
+                let v = r.wrapping_sub(1);
+                self.set_index_reg(reg, v);
+                self.cycles += 2;
+                v
+            }
+            0x3 => {
+                let v = r.wrapping_sub(2);
+                self.set_index_reg(reg, v);
+                self.cycles += 3;
+                v
+            }
+            0x4 => r,
+            0x5 => {
+                self.cycles += 1;
+                r.wrapping_add(self.b as i8 as i16 as u16)
+            }
+            0x6 => {
+                self.cycles += 1;
+                r.wrapping_add(self.a as i8 as i16 as u16)
+            }
+            0x8 => {
+                let off = self.fetch8() as i8 as i16 as u16;
+                self.cycles += 1;
+                r.wrapping_add(off)
+            }
+            0x9 => {
+                let off = self.fetch16();
+                self.cycles += 4;
+                r.wrapping_add(off)
+            }
+            0xB => {
+                self.cycles += 4;
+                r.wrapping_add(self.d())
+            }
+            0xC => {
+                let off = self.fetch8() as i8 as i16 as u16;
+                self.cycles += 1;
+                self.pc.wrapping_add(off)
+            }
+            0xD => {
+                let off = self.fetch16();
+                self.cycles += 5;
+                self.pc.wrapping_add(off)
+            }
+            0xF => {
+                // [n16] extended indirect (only valid with the indirect bit)
+                self.cycles += 2;
+                self.fetch16()
+            }
+            // $x7, $xA, $xE are undefined; behave like ,R
+            _ => r,
+        };
+
+        if indirect {
+            self.cycles += 3;
+            self.read16(ea)
+        } else {
+            ea
+        }
+    }
+
+    fn ea(&mut self, mode: Mode) -> u16 {
+        match mode {
+            Mode::Direct => u16::from_be_bytes([self.dp, self.fetch8()]),
+            Mode::Extended => self.fetch16(),
+            Mode::Indexed => self.ea_indexed(),
+            Mode::Immediate => {
+                let ea = self.pc;
+                self.pc = self.pc.wrapping_add(1);
+                ea
+            }
+        }
+    }
+
+    fn operand8(&mut self, mode: Mode) -> u8 {
+        if mode == Mode::Immediate {
+            self.fetch8()
+        } else {
+            let ea = self.ea(mode);
+            self.read8(ea)
+        }
+    }
+
+    fn operand16(&mut self, mode: Mode) -> u16 {
+        if mode == Mode::Immediate {
+            self.fetch16()
+        } else {
+            let ea = self.ea(mode);
+            self.read16(ea)
+        }
+    }
+
+    // ------------------------------------------------------------------
+    // Branch conditions
+    // ------------------------------------------------------------------
+
+    fn condition(&self, op: u8) -> bool {
+        match op & 0x0F {
+            0x0 => true,
+            0x1 => false,
+            0x2 => !(self.cc_c || self.cc_z),
+            0x3 => self.cc_c || self.cc_z,
+            0x4 => !self.cc_c,
+            0x5 => self.cc_c,
+            0x6 => !self.cc_z,
+            0x7 => self.cc_z,
+            0x8 => !self.cc_v,
+            0x9 => self.cc_v,
+            0xA => !self.cc_n,
+            0xB => self.cc_n,
+            0xC => self.cc_n == self.cc_v,
+            0xD => self.cc_n != self.cc_v,
+            0xE => !self.cc_z && self.cc_n == self.cc_v,
+            _ => self.cc_z || self.cc_n != self.cc_v,
+        }
+    }
+
+    // ------------------------------------------------------------------
+    // Stack frames and interrupts
+    // ------------------------------------------------------------------
+
+    /// Push registers selected by a PSHS/PSHU mask. Returns the number of bytes pushed.
+    fn push_regs(&mut self, mask: u8, user: bool) -> u64 {
+        let mut bytes = 0;
+        let other = if user { self.s } else { self.u };
+        let mut sp = if user { self.u } else { self.s };
+        let mut push8 = |cpu: &mut CPU, v: u8| {
+            sp = sp.wrapping_sub(1);
+            cpu.write8(sp, v);
+            bytes += 1;
+        };
+        if mask & 0x80 != 0 {
+            let [hi, lo] = self.pc.to_be_bytes();
+            push8(self, lo);
+            push8(self, hi);
+        }
+        if mask & 0x40 != 0 {
+            let [hi, lo] = other.to_be_bytes();
+            push8(self, lo);
+            push8(self, hi);
+        }
+        if mask & 0x20 != 0 {
+            let [hi, lo] = self.y.to_be_bytes();
+            push8(self, lo);
+            push8(self, hi);
+        }
+        if mask & 0x10 != 0 {
+            let [hi, lo] = self.x.to_be_bytes();
+            push8(self, lo);
+            push8(self, hi);
+        }
+        if mask & 0x08 != 0 {
+            let v = self.dp;
+            push8(self, v);
+        }
+        if mask & 0x04 != 0 {
+            let v = self.b;
+            push8(self, v);
+        }
+        if mask & 0x02 != 0 {
+            let v = self.a;
+            push8(self, v);
+        }
+        if mask & 0x01 != 0 {
+            let v = self.cc();
+            push8(self, v);
+        }
+        if user {
+            self.u = sp;
+        } else {
+            self.s = sp;
+        }
+        bytes
+    }
+
+    /// Pull registers selected by a PULS/PULU mask. Returns the number of bytes pulled.
+    fn pull_regs(&mut self, mask: u8, user: bool) -> u64 {
+        let mut bytes = 0;
+        let mut sp = if user { self.u } else { self.s };
+        let mut pull8 = |cpu: &mut CPU| -> u8 {
+            let v = cpu.read8(sp);
+            sp = sp.wrapping_add(1);
+            bytes += 1;
+            v
+        };
+        if mask & 0x01 != 0 {
+            let v = pull8(self);
+            self.set_cc(v);
+        }
+        if mask & 0x02 != 0 {
+            self.a = pull8(self);
+        }
+        if mask & 0x04 != 0 {
+            self.b = pull8(self);
+        }
+        if mask & 0x08 != 0 {
+            self.dp = pull8(self);
+        }
+        if mask & 0x10 != 0 {
+            let hi = pull8(self);
+            let lo = pull8(self);
+            self.x = u16::from_be_bytes([hi, lo]);
+        }
+        if mask & 0x20 != 0 {
+            let hi = pull8(self);
+            let lo = pull8(self);
+            self.y = u16::from_be_bytes([hi, lo]);
+        }
+        if mask & 0x40 != 0 {
+            let hi = pull8(self);
+            let lo = pull8(self);
+            let v = u16::from_be_bytes([hi, lo]);
+            if user {
+                self.s = v;
+            } else {
+                self.u = v;
+            }
+        }
+        if mask & 0x80 != 0 {
+            let hi = pull8(self);
+            let lo = pull8(self);
+            self.pc = u16::from_be_bytes([hi, lo]);
+        }
+        if user {
+            self.u = sp;
+        } else {
+            self.s = sp;
+        }
+        bytes
+    }
+
+    /// Stack the entire machine state on S (E set), as for IRQ/NMI/SWI/CWAI.
+    fn push_entire(&mut self) {
+        self.cc_e = true;
+        self.push_regs(0xFF, false);
+    }
+
+    fn vector_to(&mut self, vector: u16) {
+        self.pc = self.read16(vector);
+    }
+
+    /// Service the highest-priority pending interrupt, if any can be taken.
+    fn service_interrupts(&mut self) -> bool {
+        let stacked = self.wait == WaitState::Cwai;
+
+        if self.nmi_pending {
+            self.nmi_pending = false;
+            if !stacked {
+                self.push_entire();
+            }
+            self.cc_i = true;
+            self.cc_f = true;
+            self.vector_to(VEC_NMI);
+            self.cycles += if stacked { 7 } else { 19 };
+            self.wait = WaitState::Running;
+            return true;
+        }
+
+        if self.firq_pending && !self.cc_f {
+            self.firq_pending = false;
+            if !stacked {
+                self.cc_e = false;
+                self.push_regs(0x81, false);
+            }
+            self.cc_i = true;
+            self.cc_f = true;
+            self.vector_to(VEC_FIRQ);
+            self.cycles += if stacked { 7 } else { 10 };
+            self.wait = WaitState::Running;
+            return true;
+        }
+
+        if self.irq_pending && !self.cc_i {
+            self.irq_pending = false;
+            if !stacked {
+                self.push_entire();
+            }
+            self.cc_i = true;
+            self.in_irq_handler = true;
+            self.vector_to(VEC_IRQ);
+            self.cycles += if stacked { 7 } else { 19 };
+            self.wait = WaitState::Running;
+            return true;
+        }
+
+        false
+    }
+
+    // ------------------------------------------------------------------
+    // TFR / EXG
+    // ------------------------------------------------------------------
+
+    fn reg_is_16bit(code: u8) -> Option<bool> {
+        match code {
+            0x0..=0x5 => Some(true),
+            0x8..=0xB => Some(false),
+            _ => None,
+        }
+    }
+
+    fn read_reg(&self, code: u8) -> u16 {
+        match code {
+            0x0 => self.d(),
+            0x1 => self.x,
+            0x2 => self.y,
+            0x3 => self.u,
+            0x4 => self.s,
+            0x5 => self.pc,
+            0x8 => self.a as u16,
+            0x9 => self.b as u16,
+            0xA => self.cc() as u16,
+            _ => self.dp as u16,
+        }
+    }
+
+    fn write_reg(&mut self, code: u8, v: u16) {
+        match code {
+            0x0 => self.set_d(v),
+            0x1 => self.x = v,
+            0x2 => self.y = v,
+            0x3 => self.u = v,
+            0x4 => self.s = v,
+            0x5 => self.pc = v,
+            0x8 => self.a = v as u8,
+            0x9 => self.b = v as u8,
+            0xA => self.set_cc(v as u8),
+            _ => self.dp = v as u8,
+        }
+    }
+
+    /// Decode a TFR/EXG post-byte. Mixed 8/16-bit pairs and undefined codes are rejected.
+    fn transfer_pair(post: u8) -> Option<(u8, u8)> {
+        let src = post >> 4;
+        let dst = post & 0x0F;
+        match (Self::reg_is_16bit(src), Self::reg_is_16bit(dst)) {
+            (Some(a), Some(b)) if a == b => Some((src, dst)),
+            _ => None,
+        }
+    }
+
+    // ------------------------------------------------------------------
+    // Execution
+    // ------------------------------------------------------------------
+
+    /// Record BIOS routine entries used for frame/intensity statistics.
+    fn observe_bios_entry(&mut self) {
+        if !self.bus.bios_loaded() {
+            return;
+        }
+        match self.pc {
+            BIOS_WAIT_RECAL => self.frame_count += 1,
+            BIOS_INTENSITY_A => self.last_intensity = self.a,
+            pc if BIOS_DRAW_VL_ENTRIES.contains(&pc) => self.draw_vl_count += 1,
+            _ => {}
+        }
+    }
+
+    /// Service one interrupt or execute one instruction.
+    /// Returns false if an illegal opcode (or an illegal TFR/EXG pair) was fetched.
+    pub fn step(&mut self) -> bool {
+        if self.service_interrupts() {
+            return true;
+        }
+
+        match self.wait {
+            WaitState::Running => {}
+            WaitState::Cwai => {
+                self.cycles += 1;
+                return true;
+            }
+            WaitState::Sync => {
+                if self.irq_pending || self.firq_pending || self.nmi_pending {
+                    // Masked line releases SYNC without servicing
+                    self.wait = WaitState::Running;
+                } else {
+                    self.cycles += 1;
+                    return true;
+                }
+            }
+        }
+
+        self.observe_bios_entry();
+
+        let op = self.fetch8();
+        match op {
+            0x10 => {
+                let op2 = self.fetch8();
+                self.opcode_counts[256 + op2 as usize] += 1;
+                self.exec_page2(op2)
+            }
+            0x11 => {
+                let op2 = self.fetch8();
+                self.opcode_counts[512 + op2 as usize] += 1;
+                self.exec_page3(op2)
+            }
+            _ => {
+                self.opcode_counts[op as usize] += 1;
+                self.exec_page1(op)
+            }
+        }
+    }
+
+    fn illegal(&mut self) -> bool {
+        self.cycles += 2;
+        false
+    }
+
+    fn exec_page1(&mut self, op: u8) -> bool {
+        match op {
+            // Read-modify-write on memory: direct, indexed, extended
+            0x00..=0x0F | 0x60..=0x7F => {
+                let mode = Mode::from_rmw(op);
+                let low = op & 0x0F;
+                if low == 0x0E {
+                    // JMP
+                    self.cycles += match mode {
+                        Mode::Extended => 4,
+                        _ => 3,
+                    };
+                    self.pc = self.ea(mode);
+                    return true;
+                }
+                if matches!(low, 0x1 | 0x2 | 0x5 | 0xB) {
+                    return self.illegal();
+                }
+                self.cycles += cycles_rmw(mode);
+                let ea = self.ea(mode);
+                let v = self.read8(ea);
+                let r = self.unary(low, v).unwrap_or(v);
+                if low != 0xD {
+                    self.write8(ea, r);
+                }
+                true
+            }
+
+            0x12 => {
+                self.cycles += 2;
+                true
+            }
+            0x13 => {
+                self.cycles += 4;
+                self.wait = WaitState::Sync;
+                true
+            }
+            0x16 => {
+                let off = self.fetch16();
+                self.pc = self.pc.wrapping_add(off);
+                self.cycles += 5;
+                true
+            }
+            0x17 => {
+                let off = self.fetch16();
+                let ret = self.pc;
+                self.push16_s(ret);
+                self.pc = self.pc.wrapping_add(off);
+                self.cycles += 9;
+                true
+            }
+            0x19 => {
+                // DAA
+                let a = self.a;
+                let msn = a & 0xF0;
+                let lsn = a & 0x0F;
+                let mut cf: u16 = 0;
+                if lsn > 0x09 || self.cc_h {
+                    cf |= 0x06;
+                }
+                if (msn > 0x80 && lsn > 0x09) || msn > 0x90 || self.cc_c {
+                    cf |= 0x60;
+                }
+                let r = a as u16 + cf;
+                self.cc_c = self.cc_c || r & 0x100 != 0;
+                self.a = r as u8;
+                self.set_nz8(self.a);
+                self.cc_v = false;
+                self.cycles += 2;
+                true
+            }
+            0x1A => {
+                let m = self.fetch8();
+                let cc = self.cc() | m;
+                self.set_cc(cc);
+                self.cycles += 3;
+                true
+            }
+            0x1C => {
+                let m = self.fetch8();
+                let cc = self.cc() & m;
+                self.set_cc(cc);
+                self.cycles += 3;
+                true
+            }
+            0x1D => {
+                // SEX
+                self.a = if self.b & 0x80 != 0 { 0xFF } else { 0x00 };
+                self.set_nz16(self.d());
+                self.cc_v = false;
+                self.cycles += 2;
+                true
+            }
+            0x1E => {
+                let post = self.fetch8();
+                self.cycles += 8;
+                match Self::transfer_pair(post) {
+                    Some((r1, r2)) => {
+                        let v1 = self.read_reg(r1);
+                        let v2 = self.read_reg(r2);
+                        self.write_reg(r1, v2);
+                        self.write_reg(r2, v1);
+                        true
+                    }
+                    None => false,
+                }
+            }
+            0x1F => {
+                let post = self.fetch8();
+                self.cycles += 6;
+                match Self::transfer_pair(post) {
+                    Some((src, dst)) => {
+                        let v = self.read_reg(src);
+                        self.write_reg(dst, v);
+                        true
+                    }
+                    None => false,
+                }
+            }
+
+            // Short branches
+            0x20..=0x2F => {
+                let off = self.fetch8() as i8 as i16 as u16;
+                if self.condition(op) {
+                    self.pc = self.pc.wrapping_add(off);
+                }
+                self.cycles += 3;
+                true
+            }
+
+            // LEAX/LEAY (set Z), LEAS/LEAU (no flags)
+            0x30..=0x33 => {
+                self.cycles += 4;
+                let ea = self.ea_indexed();
+                match op {
+                    0x30 => {
+                        self.x = ea;
+                        self.cc_z = ea == 0;
+                    }
+                    0x31 => {
+                        self.y = ea;
+                        self.cc_z = ea == 0;
+                    }
+                    0x32 => self.s = ea,
+                    _ => self.u = ea,
+                }
+                true
+            }
+            0x34 | 0x36 => {
+                let mask = self.fetch8();
+                let bytes = self.push_regs(mask, op == 0x36);
+                self.cycles += 5 + bytes;
+                true
+            }
+            0x35 | 0x37 => {
+                let mask = self.fetch8();
+                let bytes = self.pull_regs(mask, op == 0x37);
+                self.cycles += 5 + bytes;
+                true
+            }
+            0x39 => {
+                self.pc = self.pull16_s();
+                self.cycles += 5;
+                true
+            }
+            0x3A => {
+                self.x = self.x.wrapping_add(self.b as u16);
+                self.cycles += 3;
+                true
+            }
+            0x3B => {
+                // RTI
+                let cc = self.pull8_s();
+                self.set_cc(cc);
+                if self.cc_e {
+                    self.pull_regs(0xFE, false);
+                    self.cycles += 15;
+                } else {
+                    self.pc = self.pull16_s();
+                    self.cycles += 6;
+                }
+                self.in_irq_handler = false;
+                true
+            }
+            0x3C => {
+                // CWAI
+                let m = self.fetch8();
+                let cc = self.cc() & m;
+                self.set_cc(cc);
+                self.push_entire();
+                self.wait = WaitState::Cwai;
+                self.cycles += 20;
+                true
+            }
+            0x3D => {
+                // MUL
+                let r = self.a as u16 * self.b as u16;
+                self.set_d(r);
+                self.cc_z = r == 0;
+                self.cc_c = r & 0x80 != 0;
+                self.cycles += 11;
+                true
+            }
+            0x3F => {
+                // SWI
+                self.push_entire();
+                self.cc_i = true;
+                self.cc_f = true;
+                self.vector_to(VEC_SWI);
+                self.cycles += 19;
+                true
+            }
+
+            // Inherent A / B unary ops
+            0x40..=0x5F => {
+                let low = op & 0x0F;
+                let on_b = op >= 0x50;
+                let v = if on_b { self.b } else { self.a };
+                match self.unary(low, v) {
+                    Some(r) => {
+                        if on_b {
+                            self.b = r;
+                        } else {
+                            self.a = r;
+                        }
+                        self.cycles += 2;
+                        true
+                    }
+                    None => self.illegal(),
+                }
+            }
+
+            // BSR / JSR
+            0x8D => {
+                let off = self.fetch8() as i8 as i16 as u16;
+                let ret = self.pc;
+                self.push16_s(ret);
+                self.pc = self.pc.wrapping_add(off);
+                self.cycles += 7;
+                true
+            }
+            0x9D | 0xAD | 0xBD => {
+                let mode = Mode::from_column(op);
+                self.cycles += if mode == Mode::Extended { 8 } else { 7 };
+                let ea = self.ea(mode);
+                let ret = self.pc;
+                self.push16_s(ret);
+                self.pc = ea;
+                true
+            }
+
+            // Illegal immediate stores
+            0x87 | 0x8F | 0xC7 | 0xCD | 0xCF => self.illegal(),
+
+            // Accumulator A / D / X block ($80-$BF)
+            0x80..=0xBF => self.exec_acc(op, false),
+            // Accumulator B / D / U block ($C0-$FF)
+            0xC0..=0xFF => self.exec_acc(op, true),
+
+            _ => self.illegal(),
+        }
+    }
+
+    /// $80-$FF: 8-bit ALU ops on A (or B), plus the 16-bit column (SUBD/ADDD, CMPX/LDD,
+    /// LDX/LDU, STX/STU, STD).
+    fn exec_acc(&mut self, op: u8, on_b: bool) -> bool {
+        let mode = Mode::from_column(op);
+        let low = op & 0x0F;
+
+        match low {
+            0x3 => {
+                self.cycles += cycles16_alu(mode);
+                let m = self.operand16(mode);
+                let d = self.d();
+                let r = if on_b { self.add16(d, m) } else { self.sub16(d, m) };
+                self.set_d(r);
+                return true;
+            }
+            0xC => {
+                if on_b {
+                    // LDD
+                    self.cycles += cycles16_ldst(mode);
+                    let v = self.operand16(mode);
+                    self.set_d(v);
+                    self.set_nz16(v);
+                    self.cc_v = false;
+                } else {
+                    // CMPX
+                    self.cycles += cycles16_alu(mode);
+                    let m = self.operand16(mode);
+                    self.sub16(self.x, m);
+                }
+                return true;
+            }
+            0xD => {
+                // STD (JSR/BSR handled by the caller)
+                self.cycles += cycles16_ldst(mode);
+                let ea = self.ea(mode);
+                let v = self.d();
+                self.write16(ea, v);
+                self.set_nz16(v);
+                self.cc_v = false;
+                return true;
+            }
+            0xE => {
+                // LDX / LDU
+                self.cycles += cycles16_ldst(mode);
+                let v = self.operand16(mode);
+                if on_b {
+                    self.u = v;
+                } else {
+                    self.x = v;
+                }
+                self.set_nz16(v);
+                self.cc_v = false;
+                return true;
+            }
+            0xF => {
+                // STX / STU
+                self.cycles += cycles16_ldst(mode);
+                let ea = self.ea(mode);
+                let v = if on_b { self.u } else { self.x };
+                self.write16(ea, v);
+                self.set_nz16(v);
+                self.cc_v = false;
+                return true;
+            }
+            0x7 => {
+                // STA / STB
+                self.cycles += cycles8(mode);
+                let ea = self.ea(mode);
+                let v = if on_b { self.b } else { self.a };
+                self.write8(ea, v);
+                self.logic8(v);
+                return true;
+            }
+            _ => {}
+        }
+
+        self.cycles += cycles8(mode);
+        let m = self.operand8(mode);
+        let acc = if on_b { self.b } else { self.a };
+        let result = match low {
+            0x0 => Some(self.sub8(acc, m, false)),
+            0x1 => {
+                self.sub8(acc, m, false);
+                None
+            }
+            0x2 => {
+                let c = self.cc_c;
+                Some(self.sub8(acc, m, c))
+            }
+            0x4 => Some(self.logic8(acc & m)),
+            0x5 => {
+                self.logic8(acc & m);
+                None
+            }
+            0x6 => Some(self.logic8(m)),
+            0x8 => Some(self.logic8(acc ^ m)),
+            0x9 => {
+                let c = self.cc_c;
+                Some(self.add8(acc, m, c))
+            }
+            0xA => Some(self.logic8(acc | m)),
+            0xB => Some(self.add8(acc, m, false)),
+            _ => return self.illegal(),
+        };
+        if let Some(r) = result {
+            if on_b {
+                self.b = r;
+            } else {
+                self.a = r;
+            }
+        }
+        true
+    }
+
+    fn exec_page2(&mut self, op: u8) -> bool {
+        match op {
+            0x21..=0x2F => {
+                let off = self.fetch16();
+                self.cycles += 5;
+                if self.condition(op) {
+                    self.pc = self.pc.wrapping_add(off);
+                    self.cycles += 1;
+                }
+                true
+            }
+            0x3F => {
+                self.push_entire();
+                self.vector_to(VEC_SWI2);
+                self.cycles += 20;
+                true
+            }
+            0x83 | 0x93 | 0xA3 | 0xB3 | 0x8C | 0x9C | 0xAC | 0xBC => {
+                let mode = Mode::from_column(op);
+                self.cycles += cycles16_alu(mode) + 1;
+                let m = self.operand16(mode);
+                let lhs = if op & 0x0F == 0x03 { self.d() } else { self.y };
+                self.sub16(lhs, m);
+                true
+            }
+            0x8E | 0x9E | 0xAE | 0xBE | 0xCE | 0xDE | 0xEE | 0xFE => {
+                let mode = Mode::from_column(op);
+                self.cycles += cycles16_ldst(mode) + 1;
+                let v = self.operand16(mode);
+                if op >= 0xC0 {
+                    self.s = v;
+                } else {
+                    self.y = v;
+                }
+                self.set_nz16(v);
+                self.cc_v = false;
+                true
+            }
+            0x9F | 0xAF | 0xBF | 0xDF | 0xEF | 0xFF => {
+                let mode = Mode::from_column(op);
+                self.cycles += cycles16_ldst(mode) + 1;
+                let ea = self.ea(mode);
+                let v = if op >= 0xC0 { self.s } else { self.y };
+                self.write16(ea, v);
+                self.set_nz16(v);
+                self.cc_v = false;
+                true
+            }
+            _ => self.illegal(),
+        }
+    }
+
+    fn exec_page3(&mut self, op: u8) -> bool {
+        match op {
+            0x3F => {
+                self.push_entire();
+                self.vector_to(VEC_SWI3);
+                self.cycles += 20;
+                true
+            }
+            0x83 | 0x93 | 0xA3 | 0xB3 | 0x8C | 0x9C | 0xAC | 0xBC => {
+                let mode = Mode::from_column(op);
+                self.cycles += cycles16_alu(mode) + 1;
+                let m = self.operand16(mode);
+                let lhs = if op & 0x0F == 0x03 { self.u } else { self.s };
+                self.sub16(lhs, m);
+                true
+            }
+            _ => self.illegal(),
+        }
+    }
+
+    // ------------------------------------------------------------------
+    // Metrics
+    // ------------------------------------------------------------------
+
+    /// Number of times an opcode was executed. `page` is 1, 2 or 3.
+    pub fn opcode_count(&self, page: u8, op: u8) -> u64 {
+        let base = match page {
+            2 => 256,
+            3 => 512,
+            _ => 0,
+        };
+        self.opcode_counts[base + op as usize]
+    }
+
+    /// Human-readable opcode histogram (top 32 entries) plus frame counters.
+    pub fn metrics_pretty(&self) -> String {
+        let mut entries: Vec<(usize, u64)> = self
+            .opcode_counts
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|(_, n)| *n > 0)
+            .collect();
+        entries.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+        let mut out = format!(
+            "cycles={} frames={} draw_vl={} last_intensity={}\n",
+            self.cycles, self.frame_count, self.draw_vl_count, self.last_intensity
+        );
+        for (idx, count) in entries.into_iter().take(32) {
+            let prefix = match idx / 256 {
+                1 => "10 ",
+                2 => "11 ",
+                _ => "",
+            };
+            out.push_str(&format!("{}{:02X}: {}\n", prefix, idx % 256, count));
+        }
+        out
+    }
+}
@@ -0,0 +1,18 @@
+use thiserror::Error;
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum EmulatorError {
+    #[error("BIOS image must be 4096 or 8192 bytes (got {0})")]
+    InvalidBios(usize),
+
+    #[error("Illegal opcode ${opcode:02X} at ${pc:04X}")]
+    IllegalOpcode { pc: u16, opcode: u8 },
+
+    #[error("No Wait_Recal within {cycles} cycles (PC=${pc:04X})")]
+    FrameTimeout { cycles: u64, pc: u16 },
+
+    #[error("Invalid snapshot at line {line}: {message}")]
+    InvalidSnapshot { line: usize, message: String },
+}
+
+pub type EmulatorResult<T> = Result<T, EmulatorError>;
@@ -0,0 +1,36 @@
+//! Vectrex emulator (headless)
+//!
+//! Cycle-counted MC6809 core, 6522 VIA with timers and shift register,
+//! AY-3-8912 register model and a vector beam model that records line
+//! segments instead of rasterising. Used by `cargo test` to run compiled
+//! ROMs without the JSVecX IDE panel, and (with the `wasm` feature) as a
+//! WebAssembly module.
+//!
+//! # Module Structure
+//!
+//! - `cpu.rs`: MC6809 instruction set and interrupt handling
+//! - `bus.rs`: Vectrex memory map (RAM, VIA, BIOS, banked cartridge)
+//! - `via.rs`: 6522 VIA registers, timers, shift register
+//! - `psg.rs`: AY-3-8912 register model and button port
+//! - `beam.rs`: DAC sample-and-holds, integrators, segment recording
+//! - `machine.rs`: `Vectrex` harness with frame-by-frame execution
+//! - `snapshot.rs`: golden-frame snapshot format, diff and SVG rendering
+
+pub mod beam;
+pub mod bus;
+pub mod cpu;
+pub mod error;
+pub mod machine;
+pub mod psg;
+pub mod snapshot;
+pub mod via;
+
+#[cfg(feature = "wasm")]
+pub mod wasm_api;
+
+pub use beam::Segment;
+pub use bus::Bus;
+pub use cpu::CPU;
+pub use error::{EmulatorError, EmulatorResult};
+pub use machine::{locate_bios, Frame, Vectrex, CPU_HZ, CYCLES_PER_FRAME};
+pub use snapshot::{FrameDiff, FrameSnapshot, Tolerance};
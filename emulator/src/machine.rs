@@ -0,0 +1,189 @@
+//! Headless Vectrex machine
+//!
+//! Ties the CPU, VIA, PSG and beam together and steps them in lockstep:
+//! each instruction's cycle count is replayed through the VIA timers and the
+//! analog integrators. Frames are delimited by calls to BIOS `Wait_Recal`,
+//! which every VPy program makes once per `loop()` iteration.
+
+use std::path::{Path, PathBuf};
+
+use crate::beam::Segment;
+use crate::cpu::{CPU, DEFAULT_STACK};
+use crate::error::{EmulatorError, EmulatorResult};
+
+/// E-clock frequency (1.5 MHz)
+pub const CPU_HZ: u64 = 1_500_000;
+/// Cycles in one 50 Hz refresh (the BIOS Wait_Recal budget)
+pub const CYCLES_PER_FRAME: u64 = CPU_HZ / 50;
+
+/// Vectors drawn between two `Wait_Recal` calls
+#[derive(Debug, Clone, Default)]
+pub struct Frame {
+    /// Frame number (1 = first frame after reset)
+    pub number: u64,
+    /// Cycles spent since the previous `Wait_Recal`
+    pub cycles: u64,
+    pub segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Vectrex {
+    pub cpu: CPU,
+}
+
+/// Find the BIOS image shipped with the IDE, searching upwards from `start`.
+pub fn locate_bios(start: &Path) -> Option<PathBuf> {
+    const CANDIDATES: [&str; 2] = ["ide/frontend/src/assets/bios.bin", "ide/frontend/dist/bios.bin"];
+    let mut dir = Some(start);
+    while let Some(d) = dir {
+        for rel in CANDIDATES {
+            let candidate = d.join(rel);
+            if candidate.is_file() {
+                return Some(candidate);
+            }
+        }
+        dir = d.parent();
+    }
+    None
+}
+
+impl Vectrex {
+    /// Create a machine with the given BIOS image. Call `load_cartridge` and `reset` next.
+    pub fn new(bios: &[u8]) -> EmulatorResult<Self> {
+        let mut cpu = CPU::default();
+        if !cpu.load_bios(bios) {
+            return Err(EmulatorError::InvalidBios(bios.len()));
+        }
+        Ok(Vectrex { cpu })
+    }
+
+    pub fn load_cartridge(&mut self, rom: &[u8]) {
+        self.cpu.bus.load_cartridge(rom);
+    }
+
+    /// Power-on reset. Sets the BIOS warm-boot flag first when `skip_intro`
+    /// is true so the GCE logo sequence is bypassed.
+    pub fn reset(&mut self, skip_intro: bool) {
+        if skip_intro {
+            // Vec_Cold_Flag ($CBFE) == $7321 selects the warm start path
+            self.cpu.bus.mem[0xCBFE] = 0x73;
+            self.cpu.bus.mem[0xCBFF] = 0x21;
+        }
+        self.cpu.reset();
+    }
+
+    /// Execute one instruction (or interrupt entry) and advance the hardware.
+    /// Returns the cycles consumed.
+    pub fn step(&mut self) -> EmulatorResult<u64> {
+        self.cpu.irq_pending = self.cpu.bus.via.irq();
+        let pc = self.cpu.pc;
+        let before = self.cpu.cycles;
+        if !self.cpu.step() {
+            return Err(EmulatorError::IllegalOpcode { pc, opcode: self.cpu.bus.peek(pc) });
+        }
+        let used = self.cpu.cycles - before;
+        self.cpu.bus.tick(used);
+        Ok(used)
+    }
+
+    /// Run for at least `cycles` cycles.
+    pub fn run_cycles(&mut self, cycles: u64) -> EmulatorResult<()> {
+        let target = self.cpu.cycles + cycles;
+        while self.cpu.cycles < target {
+            self.step()?;
+        }
+        Ok(())
+    }
+
+    /// Run the BIOS title sequence until execution first enters cartridge space.
+    /// Segments drawn by the BIOS are discarded.
+    pub fn run_until_cartridge(&mut self, max_cycles: u64) -> EmulatorResult<()> {
+        let start = self.cpu.cycles;
+        while self.cpu.pc >= 0x8000 {
+            self.step()?;
+            if self.cpu.cycles - start > max_cycles {
+                return Err(EmulatorError::FrameTimeout { cycles: max_cycles, pc: self.cpu.pc });
+            }
+        }
+        self.cpu.bus.beam.take_segments();
+        Ok(())
+    }
+
+    /// Address of the first instruction after the cartridge header
+    /// (copyright string, music pointer, title blocks, terminating zero).
+    pub fn cartridge_entry(&self) -> Option<u16> {
+        let mut addr: u16 = 0;
+        while self.peek(addr) != 0x80 {
+            addr = addr.checked_add(1).filter(|a| *a < 0x100)?;
+        }
+        addr += 3; // $80 + music pointer
+        while self.peek(addr) != 0 {
+            addr += 4; // height, width, rel y, rel x
+            while self.peek(addr) != 0x80 {
+                addr = addr.checked_add(1).filter(|a| *a < 0x100)?;
+            }
+            addr += 1;
+        }
+        Some(addr + 1)
+    }
+
+    /// Fast boot for headless runs: let the BIOS initialise the hardware up to
+    /// its first `Wait_Recal`, then jump straight to the cartridge entry point
+    /// instead of playing the title sequence.
+    pub fn boot_cartridge(&mut self) -> EmulatorResult<()> {
+        self.reset(true);
+        self.run_frame(CYCLES_PER_FRAME * 4)?;
+        self.cpu.bus.beam.take_segments();
+        let entry = self.cartridge_entry().unwrap_or(0);
+        self.cpu.pc = entry;
+        self.cpu.dp = 0xD0;
+        self.cpu.s = DEFAULT_STACK;
+        Ok(())
+    }
+
+    /// Run until the next `Wait_Recal` call and return everything drawn since the previous one.
+    pub fn run_frame(&mut self, max_cycles: u64) -> EmulatorResult<Frame> {
+        let start_frame = self.cpu.frame_count;
+        let start_cycles = self.cpu.cycles;
+        loop {
+            self.step()?;
+            if self.cpu.frame_count != start_frame {
+                break;
+            }
+            if self.cpu.cycles - start_cycles > max_cycles {
+                return Err(EmulatorError::FrameTimeout { cycles: max_cycles, pc: self.cpu.pc });
+            }
+        }
+        Ok(Frame {
+            number: self.cpu.frame_count,
+            cycles: self.cpu.cycles - start_cycles,
+            segments: self.cpu.bus.beam.take_segments(),
+        })
+    }
+
+    /// Run `count` frames, allowing up to four refresh periods per frame.
+    pub fn run_frames(&mut self, count: usize) -> EmulatorResult<Vec<Frame>> {
+        (0..count).map(|_| self.run_frame(CYCLES_PER_FRAME * 4)).collect()
+    }
+
+    /// Side-effect free memory read
+    pub fn peek(&self, addr: u16) -> u8 {
+        self.cpu.bus.peek(addr)
+    }
+
+    pub fn peek16(&self, addr: u16) -> u16 {
+        u16::from_be_bytes([self.peek(addr), self.peek(addr.wrapping_add(1))])
+    }
+
+    /// Set pressed buttons: bits 0-3 = pad 1 buttons 1-4, bits 4-7 = pad 2 buttons 1-4.
+    pub fn set_buttons(&mut self, pressed: u8) {
+        self.cpu.bus.psg.buttons = !pressed;
+    }
+
+    /// Set an analog stick position (-128..=127 per axis) for pad 0 or 1.
+    pub fn set_joystick(&mut self, pad: usize, x: i8, y: i8) {
+        let base = (pad & 1) * 2;
+        self.cpu.bus.beam.joy[base] = (x as i32) + 128;
+        self.cpu.bus.beam.joy[base + 1] = (y as i32) + 128;
+    }
+}
@@ -0,0 +1,87 @@
+//! AY-3-8912 PSG register model
+//!
+//! The PSG sits behind VIA port A (data) with BDIR/BC1 on PB4/PB3. This model
+//! tracks the 16 registers, the latched register address and the I/O port
+//! (register 14) that carries the four controller buttons per pad. It does not
+//! synthesise audio; every accepted register write can optionally be logged
+//! with its cycle stamp so tests can inspect the sound stream.
+
+/// Register 14 is the input port wired to the joystick buttons.
+pub const REG_IO_PORT_A: u8 = 14;
+
+/// One register write as seen on the PSG bus
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PsgWrite {
+    pub cycle: u64,
+    pub reg: u8,
+    pub value: u8,
+}
+
+#[derive(Debug, Clone)]
+pub struct Psg {
+    pub regs: [u8; 16],
+    /// Currently latched register address
+    pub selected: u8,
+    /// Button state presented on the I/O port (active low, 0xFF = nothing pressed)
+    pub buttons: u8,
+    /// When true, every register write is appended to `writes`
+    pub log_writes: bool,
+    pub writes: Vec<PsgWrite>,
+}
+
+impl Default for Psg {
+    fn default() -> Self {
+        Psg {
+            regs: [0; 16],
+            selected: 0,
+            buttons: 0xFF,
+            log_writes: false,
+            writes: Vec::new(),
+        }
+    }
+}
+
+impl Psg {
+    /// Apply the bus mode selected by PB3/PB4 (`orb & 0x18`) with `data` on port A.
+    pub fn bus_cycle(&mut self, orb: u8, data: u8, cycle: u64) {
+        match orb & 0x18 {
+            // BDIR=1, BC1=0: write to latched register
+            0x10 if self.selected != REG_IO_PORT_A => {
+                self.write_register(self.selected, data, cycle);
+            }
+            // BDIR=1, BC1=1: latch address (upper nibble must be zero)
+            0x18 if data & 0xF0 == 0 => {
+                self.selected = data & 0x0F;
+            }
+            // 0x08 is a read (handled by `drives_port_a`), 0x00 inactive
+            _ => {}
+        }
+    }
+
+    pub fn write_register(&mut self, reg: u8, value: u8, cycle: u64) {
+        let reg = reg & 0x0F;
+        self.regs[reg as usize] = value;
+        if self.log_writes {
+            self.writes.push(PsgWrite { cycle, reg, value });
+        }
+    }
+
+    /// True when the PSG is driving port A (BDIR=0, BC1=1)
+    pub fn drives_port_a(orb: u8) -> bool {
+        orb & 0x18 == 0x08
+    }
+
+    /// Value presented on port A during a read cycle
+    pub fn read_data(&self) -> u8 {
+        if self.selected == REG_IO_PORT_A {
+            self.buttons
+        } else {
+            self.regs[self.selected as usize]
+        }
+    }
+
+    /// Drain the logged register writes
+    pub fn take_writes(&mut self) -> Vec<PsgWrite> {
+        std::mem::take(&mut self.writes)
+    }
+}
@@ -0,0 +1,289 @@
+//! Golden-frame vector snapshots
+//!
+//! A snapshot is the list of segments drawn in selected frames, stored as
+//! plain text so it can be checked in and reviewed:
+//!
+//! ```text
+//! # vectrex frame snapshot
+//! frame 30
+//! -4064 0 4064 0 127
+//! ```
+//!
+//! Each segment line is `x0 y0 x1 y1 intensity` in integrator units.
+//! `diff_frame` matches expected and actual segments within a `Tolerance`
+//! (draw order is not significant, direction is), and `render_svg` draws the
+//! two frames side by side with the mismatches highlighted.
+
+use std::fmt;
+
+use crate::beam::Segment;
+use crate::error::{EmulatorError, EmulatorResult};
+use crate::machine::Frame;
+
+const HEADER: &str = "# vectrex frame snapshot";
+
+/// Segments drawn in one frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameSnapshot {
+    pub frame: u64,
+    pub segments: Vec<Segment>,
+}
+
+impl From<Frame> for FrameSnapshot {
+    fn from(frame: Frame) -> Self {
+        FrameSnapshot { frame: frame.number, segments: frame.segments }
+    }
+}
+
+/// Maximum allowed difference per coordinate and per intensity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Tolerance {
+    pub position: i32,
+    pub intensity: u8,
+}
+
+impl Tolerance {
+    pub fn matches(&self, a: &Segment, b: &Segment) -> bool {
+        (a.x0 - b.x0).abs() <= self.position
+            && (a.y0 - b.y0).abs() <= self.position
+            && (a.x1 - b.x1).abs() <= self.position
+            && (a.y1 - b.y1).abs() <= self.position
+            && a.intensity.abs_diff(b.intensity) <= self.intensity
+    }
+}
+
+/// Serialize frames to the snapshot text format
+pub fn to_text(frames: &[FrameSnapshot]) -> String {
+    let mut out = String::new();
+    out.push_str(HEADER);
+    out.push('\n');
+    for frame in frames {
+        out.push_str(&format!("frame {}\n", frame.frame));
+        for seg in &frame.segments {
+            out.push_str(&format_segment(seg));
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Parse the snapshot text format. Blank lines and `#` comments are ignored.
+pub fn parse(text: &str) -> EmulatorResult<Vec<FrameSnapshot>> {
+    let mut frames: Vec<FrameSnapshot> = Vec::new();
+    for (idx, raw) in text.lines().enumerate() {
+        let line_no = idx + 1;
+        let line = raw.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let invalid = |message: &str| EmulatorError::InvalidSnapshot { line: line_no, message: message.to_string() };
+
+        if let Some(number) = line.strip_prefix("frame ") {
+            let frame = number.trim().parse().map_err(|_| invalid("bad frame number"))?;
+            frames.push(FrameSnapshot { frame, segments: Vec::new() });
+            continue;
+        }
+
+        let fields: Vec<i32> = line
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map_err(|_| invalid("expected `x0 y0 x1 y1 intensity`"))?;
+        let [x0, y0, x1, y1, intensity] = fields[..] else {
+            return Err(invalid("expected `x0 y0 x1 y1 intensity`"));
+        };
+        let intensity = u8::try_from(intensity).map_err(|_| invalid("intensity out of range"))?;
+        let frame = frames.last_mut().ok_or_else(|| invalid("segment before first `frame` line"))?;
+        frame.segments.push(Segment { x0, y0, x1, y1, intensity });
+    }
+    Ok(frames)
+}
+
+fn format_segment(seg: &Segment) -> String {
+    format!("{} {} {} {} {}", seg.x0, seg.y0, seg.x1, seg.y1, seg.intensity)
+}
+
+/// Segments that did not match between expected and actual
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FrameDiff {
+    pub frame: u64,
+    /// Expected segments with no match in the actual frame
+    pub missing: Vec<Segment>,
+    /// Actual segments with no match in the expected frame
+    pub unexpected: Vec<Segment>,
+}
+
+impl FrameDiff {
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.unexpected.is_empty()
+    }
+}
+
+impl fmt::Display for FrameDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "frame {}: {} missing, {} unexpected", self.frame, self.missing.len(), self.unexpected.len())?;
+        for seg in &self.missing {
+            writeln!(f, "- {}", format_segment(seg))?;
+        }
+        for seg in &self.unexpected {
+            writeln!(f, "+ {}", format_segment(seg))?;
+        }
+        Ok(())
+    }
+}
+
+/// Match every expected segment against the closest unused actual segment
+/// within tolerance.
+pub fn diff_frame(expected: &FrameSnapshot, actual: &FrameSnapshot, tolerance: Tolerance) -> FrameDiff {
+    let distance = |a: &Segment, b: &Segment| {
+        (a.x0 - b.x0).abs() + (a.y0 - b.y0).abs() + (a.x1 - b.x1).abs() + (a.y1 - b.y1).abs()
+    };
+
+    let mut used = vec![false; actual.segments.len()];
+    let mut missing = Vec::new();
+    for exp in &expected.segments {
+        let best = actual
+            .segments
+            .iter()
+            .enumerate()
+            .filter(|(i, act)| !used[*i] && tolerance.matches(exp, act))
+            .min_by_key(|(_, act)| distance(exp, act));
+        match best {
+            Some((i, _)) => used[i] = true,
+            None => missing.push(*exp),
+        }
+    }
+
+    let unexpected = actual
+        .segments
+        .iter()
+        .zip(&used)
+        .filter(|(_, used)| !**used)
+        .map(|(seg, _)| *seg)
+        .collect();
+
+    FrameDiff { frame: expected.frame, missing, unexpected }
+}
+
+/// Render expected (left) and actual (right) side by side. Missing segments
+/// are drawn red on the left, unexpected ones green on the right.
+pub fn render_svg(expected: &FrameSnapshot, actual: &FrameSnapshot, diff: &FrameDiff) -> String {
+    const PANEL: i32 = 400;
+    const MARGIN: i32 = 10;
+
+    let extent = expected
+        .segments
+        .iter()
+        .chain(&actual.segments)
+        .flat_map(|s| [s.x0.abs(), s.y0.abs(), s.x1.abs(), s.y1.abs()])
+        .max()
+        .unwrap_or(0)
+        .max(1) as f64;
+    let half = (PANEL / 2 - MARGIN) as f64;
+    let project = |x: i32, y: i32, origin: i32| {
+        let px = origin as f64 + PANEL as f64 / 2.0 + x as f64 / extent * half;
+        let py = PANEL as f64 / 2.0 + 20.0 - y as f64 / extent * half;
+        (px, py)
+    };
+
+    let mut out = String::new();
+    out.push_str(&format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">\n",
+        PANEL * 2,
+        PANEL + 20
+    ));
+    out.push_str(&format!("<rect width=\"{}\" height=\"{}\" fill=\"black\"/>\n", PANEL * 2, PANEL + 20));
+    out.push_str(&format!(
+        "<line x1=\"{p}\" y1=\"0\" x2=\"{p}\" y2=\"{h}\" stroke=\"#444\"/>\n",
+        p = PANEL,
+        h = PANEL + 20
+    ));
+    out.push_str(&format!(
+        "<text x=\"{}\" y=\"15\" fill=\"white\" font-family=\"monospace\" font-size=\"12\">expected (frame {})</text>\n",
+        MARGIN, expected.frame
+    ));
+    out.push_str(&format!(
+        "<text x=\"{}\" y=\"15\" fill=\"white\" font-family=\"monospace\" font-size=\"12\">actual (frame {})</text>\n",
+        PANEL + MARGIN,
+        actual.frame
+    ));
+
+    let mut panel = |segments: &[Segment], highlight: &[Segment], origin: i32, colour: &str| {
+        let mut remaining = highlight.to_vec();
+        for seg in segments {
+            let flagged = remaining.iter().position(|h| h == seg).map(|i| remaining.swap_remove(i)).is_some();
+            let (x0, y0) = project(seg.x0, seg.y0, origin);
+            let (x1, y1) = project(seg.x1, seg.y1, origin);
+            let (stroke, opacity) = if flagged {
+                (colour, 1.0)
+            } else {
+                ("white", 0.25 + 0.75 * seg.intensity.min(127) as f64 / 127.0)
+            };
+            out.push_str(&format!(
+                "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"{}\" stroke-opacity=\"{:.2}\" stroke-width=\"{}\" stroke-linecap=\"round\"/>\n",
+                x0,
+                y0,
+                x1,
+                y1,
+                stroke,
+                opacity,
+                if flagged { 2 } else { 1 }
+            ));
+        }
+    };
+    panel(&expected.segments, &diff.missing, 0, "red");
+    panel(&actual.segments, &diff.unexpected, PANEL, "lime");
+
+    out.push_str("</svg>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seg(x0: i32, y0: i32, x1: i32, y1: i32) -> Segment {
+        Segment { x0, y0, x1, y1, intensity: 0x7F }
+    }
+
+    #[test]
+    fn text_format_round_trips() {
+        let frames = vec![
+            FrameSnapshot { frame: 30, segments: vec![seg(-4064, 0, 4064, 0), seg(0, 0, 0, 0)] },
+            FrameSnapshot { frame: 60, segments: Vec::new() },
+        ];
+        let text = to_text(&frames);
+        assert!(text.starts_with(HEADER));
+        assert_eq!(parse(&text).unwrap(), frames);
+    }
+
+    #[test]
+    fn parse_reports_line_of_bad_segment() {
+        let err = parse("frame 1\n1 2 3\n").unwrap_err();
+        assert!(matches!(err, EmulatorError::InvalidSnapshot { line: 2, .. }));
+    }
+
+    #[test]
+    fn diff_ignores_order_and_respects_tolerance() {
+        let expected = FrameSnapshot { frame: 1, segments: vec![seg(0, 0, 100, 0), seg(0, 0, 0, 100)] };
+        let actual = FrameSnapshot { frame: 1, segments: vec![seg(2, 0, 0, 101), seg(1, 0, 100, 0)] };
+
+        let tolerance = Tolerance { position: 2, intensity: 0 };
+        assert!(diff_frame(&expected, &actual, tolerance).is_empty());
+
+        let diff = diff_frame(&expected, &actual, Tolerance::default());
+        assert_eq!(diff.missing.len(), 2);
+        assert_eq!(diff.unexpected.len(), 2);
+        assert!(diff.to_string().contains("- 0 0 100 0"));
+    }
+
+    #[test]
+    fn svg_highlights_mismatches() {
+        let expected = FrameSnapshot { frame: 1, segments: vec![seg(0, 0, 100, 0)] };
+        let actual = FrameSnapshot { frame: 1, segments: vec![seg(0, 0, 0, 100)] };
+        let diff = diff_frame(&expected, &actual, Tolerance::default());
+        let svg = render_svg(&expected, &actual, &diff);
+        assert!(svg.contains("stroke=\"red\""));
+        assert!(svg.contains("stroke=\"lime\""));
+    }
+}
@@ -0,0 +1,340 @@
+//! 6522 VIA (Versatile Interface Adapter)
+//!
+//! Register-level model of the VIA at $D000 as wired in the Vectrex:
+//! port A drives the DAC / PSG data bus, port B selects the analog mux and
+//! PSG bus mode, CA2 is ZERO, CB2 is BLANK and PB7 is RAMP. Timer 1, timer 2
+//! and the shift register are stepped once per E-clock cycle via `tick()`.
+//! Behaviour mirrors the JSVecX core used by the IDE so both emulators agree.
+
+/// Interrupt flag bits
+pub const IFR_CA2: u8 = 0x01;
+pub const IFR_SR: u8 = 0x04;
+pub const IFR_T2: u8 = 0x20;
+pub const IFR_T1: u8 = 0x40;
+pub const IFR_IRQ: u8 = 0x80;
+
+/// VIA register state and timers.
+#[derive(Debug, Clone)]
+pub struct Via {
+    pub ora: u8,
+    pub orb: u8,
+    pub ddra: u8,
+    pub ddrb: u8,
+    pub acr: u8,
+    pub pcr: u8,
+    pub ifr: u8,
+    pub ier: u8,
+
+    pub t1_counter: u16,
+    pub t1_latch_lo: u8,
+    pub t1_latch_hi: u8,
+    t1_running: bool,
+    t1_irq_armed: bool,
+    /// Timer 1 output on PB7 (0x80 = high, ramp off)
+    pub t1_pb7: u8,
+
+    pub t2_counter: u16,
+    pub t2_latch_lo: u8,
+    t2_running: bool,
+    t2_irq_armed: bool,
+
+    pub sr: u8,
+    /// Number of bits shifted since the last SR access (8 = idle)
+    sr_bits: u8,
+    sr_counter: u8,
+    sr_clock: bool,
+    /// Current shift-register output on CB2
+    pub cb2_shift: u8,
+
+    /// CA2 output level (0 = ZERO asserted)
+    pub ca2: u8,
+    /// CB2 output level when CB2 is under PCR control (0 = BLANK)
+    pub cb2_manual: u8,
+}
+
+impl Default for Via {
+    fn default() -> Self {
+        Via {
+            ora: 0,
+            orb: 0,
+            ddra: 0,
+            ddrb: 0,
+            acr: 0,
+            pcr: 0,
+            ifr: 0,
+            ier: 0,
+            t1_counter: 0,
+            t1_latch_lo: 0,
+            t1_latch_hi: 0,
+            t1_running: false,
+            t1_irq_armed: false,
+            t1_pb7: 0x80,
+            t2_counter: 0,
+            t2_latch_lo: 0,
+            t2_running: false,
+            t2_irq_armed: false,
+            sr: 0,
+            sr_bits: 8,
+            sr_counter: 0,
+            sr_clock: false,
+            cb2_shift: 0,
+            ca2: 1,
+            cb2_manual: 1,
+        }
+    }
+}
+
+impl Via {
+    fn update_irq(&mut self) {
+        if (self.ifr & 0x7F) & (self.ier & 0x7F) != 0 {
+            self.ifr |= IFR_IRQ;
+        } else {
+            self.ifr &= 0x7F;
+        }
+    }
+
+    /// IRQ output line (active when any enabled flag is set)
+    pub fn irq(&self) -> bool {
+        self.ifr & IFR_IRQ != 0
+    }
+
+    /// Read a register with its side effects (timer/SR flag clearing, handshakes).
+    /// Port registers return the raw output latches; the bus merges external inputs.
+    pub fn read(&mut self, reg: u8) -> u8 {
+        match reg & 0x0F {
+            0x0 => self.orb,
+            0x1 => {
+                if self.pcr & 0x0E == 0x08 {
+                    self.ca2 = 0;
+                }
+                self.ora
+            }
+            0xF => self.ora,
+            0x2 => self.ddrb,
+            0x3 => self.ddra,
+            0x4 => {
+                self.ifr &= !IFR_T1;
+                self.t1_running = false;
+                self.t1_irq_armed = false;
+                self.t1_pb7 = 0x80;
+                self.update_irq();
+                self.t1_counter as u8
+            }
+            0x5 => (self.t1_counter >> 8) as u8,
+            0x6 => self.t1_latch_lo,
+            0x7 => self.t1_latch_hi,
+            0x8 => {
+                self.ifr &= !IFR_T2;
+                self.t2_running = false;
+                self.t2_irq_armed = false;
+                self.update_irq();
+                self.t2_counter as u8
+            }
+            0x9 => (self.t2_counter >> 8) as u8,
+            0xA => {
+                self.ifr &= !IFR_SR;
+                self.sr_bits = 0;
+                self.sr_clock = true;
+                self.update_irq();
+                self.sr
+            }
+            0xB => self.acr,
+            0xC => self.pcr,
+            0xD => self.ifr,
+            _ => self.ier | 0x80,
+        }
+    }
+
+    /// Read a register without side effects (debuggers, snapshots).
+    pub fn peek(&self, reg: u8) -> u8 {
+        match reg & 0x0F {
+            0x0 => self.orb,
+            0x1 | 0xF => self.ora,
+            0x2 => self.ddrb,
+            0x3 => self.ddra,
+            0x4 => self.t1_counter as u8,
+            0x5 => (self.t1_counter >> 8) as u8,
+            0x6 => self.t1_latch_lo,
+            0x7 => self.t1_latch_hi,
+            0x8 => self.t2_counter as u8,
+            0x9 => (self.t2_counter >> 8) as u8,
+            0xA => self.sr,
+            0xB => self.acr,
+            0xC => self.pcr,
+            0xD => self.ifr,
+            _ => self.ier | 0x80,
+        }
+    }
+
+    /// Write a register. Returns true when port A or port B changed, so the bus
+    /// can propagate the new levels to the PSG and analog section.
+    pub fn write(&mut self, reg: u8, v: u8) -> bool {
+        match reg & 0x0F {
+            0x0 => {
+                self.orb = v;
+                if self.pcr & 0xE0 == 0x80 {
+                    self.cb2_manual = 0;
+                }
+                return true;
+            }
+            0x1 | 0xF => {
+                if reg & 0x0F == 0x1 && self.pcr & 0x0E == 0x08 {
+                    self.ca2 = 0;
+                }
+                self.ora = v;
+                return true;
+            }
+            0x2 => self.ddrb = v,
+            0x3 => self.ddra = v,
+            0x4 | 0x6 => self.t1_latch_lo = v,
+            0x5 => {
+                self.t1_latch_hi = v;
+                self.t1_counter = u16::from_be_bytes([self.t1_latch_hi, self.t1_latch_lo]);
+                self.ifr &= !IFR_T1;
+                self.t1_running = true;
+                self.t1_irq_armed = true;
+                self.t1_pb7 = 0;
+                self.update_irq();
+            }
+            0x7 => self.t1_latch_hi = v,
+            0x8 => self.t2_latch_lo = v,
+            0x9 => {
+                self.t2_counter = u16::from_be_bytes([v, self.t2_latch_lo]);
+                self.ifr &= !IFR_T2;
+                self.t2_running = true;
+                self.t2_irq_armed = true;
+                self.update_irq();
+            }
+            0xA => {
+                self.sr = v;
+                self.ifr &= !IFR_SR;
+                self.sr_bits = 0;
+                self.sr_clock = true;
+                self.update_irq();
+            }
+            0xB => self.acr = v,
+            0xC => {
+                self.pcr = v;
+                self.ca2 = if self.pcr & 0x0E == 0x0C { 0 } else { 1 };
+                self.cb2_manual = if self.pcr & 0xE0 == 0xC0 { 0 } else { 1 };
+            }
+            0xD => {
+                self.ifr &= !(v & 0x7F);
+                self.update_irq();
+            }
+            _ => {
+                if v & 0x80 != 0 {
+                    self.ier |= v & 0x7F;
+                } else {
+                    self.ier &= !(v & 0x7F);
+                }
+                self.update_irq();
+            }
+        }
+        false
+    }
+
+    /// ZERO line (CA2 low pulls the integrators to the origin)
+    pub fn zero_asserted(&self) -> bool {
+        self.ca2 == 0
+    }
+
+    /// BLANK line level: 1 = beam on. Driven by the shift register when ACR selects
+    /// shift-out mode, otherwise by the manual CB2 level.
+    pub fn beam_on(&self) -> bool {
+        if self.acr & 0x10 != 0 {
+            self.cb2_shift != 0
+        } else {
+            self.cb2_manual != 0
+        }
+    }
+
+    /// RAMP line: integrators run while PB7 is low (timer 1 owns PB7 when ACR bit 7 is set).
+    pub fn ramp_active(&self) -> bool {
+        let level = if self.acr & 0x80 != 0 { self.t1_pb7 } else { self.orb & 0x80 };
+        level == 0
+    }
+
+    /// Advance timers and the shift register by one E-clock cycle.
+    pub fn tick(&mut self) {
+        if self.t1_running {
+            let (next, wrapped) = self.t1_counter.overflowing_sub(1);
+            self.t1_counter = next;
+            if wrapped {
+                if self.acr & 0x40 != 0 {
+                    // Free-running mode: toggle PB7 and reload
+                    self.ifr |= IFR_T1;
+                    self.update_irq();
+                    self.t1_pb7 = 0x80 - self.t1_pb7;
+                    self.t1_counter = u16::from_be_bytes([self.t1_latch_hi, self.t1_latch_lo]);
+                } else if self.t1_irq_armed {
+                    // One-shot mode
+                    self.ifr |= IFR_T1;
+                    self.update_irq();
+                    self.t1_pb7 = 0x80;
+                    self.t1_irq_armed = false;
+                }
+            }
+        }
+
+        if self.t2_running && self.acr & 0x20 == 0 {
+            let (next, wrapped) = self.t2_counter.overflowing_sub(1);
+            self.t2_counter = next;
+            if wrapped && self.t2_irq_armed {
+                self.ifr |= IFR_T2;
+                self.update_irq();
+                self.t2_irq_armed = false;
+            }
+        }
+
+        // Shift clock derived from the low byte of timer 2
+        let mut t2_shift = false;
+        let (next, wrapped) = self.sr_counter.overflowing_sub(1);
+        self.sr_counter = next;
+        if wrapped {
+            self.sr_counter = self.t2_latch_lo;
+            t2_shift = self.sr_clock;
+            self.sr_clock = !self.sr_clock;
+        }
+
+        if self.sr_bits < 8 {
+            match self.acr & 0x1C {
+                0x04 if t2_shift => {
+                    self.sr <<= 1;
+                    self.sr_bits += 1;
+                }
+                0x08 => {
+                    self.sr <<= 1;
+                    self.sr_bits += 1;
+                }
+                0x10 if t2_shift => self.shift_out(),
+                0x14 if t2_shift => {
+                    self.shift_out();
+                    self.sr_bits += 1;
+                }
+                0x18 => {
+                    self.shift_out();
+                    self.sr_bits += 1;
+                }
+                _ => {}
+            }
+            if self.sr_bits == 8 {
+                self.ifr |= IFR_SR;
+                self.update_irq();
+            }
+        }
+
+        // Pulse modes restore CA2/CB2 high after one cycle
+        if self.pcr & 0x0E == 0x0A {
+            self.ca2 = 1;
+        }
+        if self.pcr & 0xE0 == 0xA0 {
+            self.cb2_manual = 1;
+        }
+    }
+
+    fn shift_out(&mut self) {
+        self.cb2_shift = (self.sr >> 7) & 1;
+        self.sr = (self.sr << 1) | self.cb2_shift;
+    }
+}
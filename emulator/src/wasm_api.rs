@@ -1,15 +1,14 @@
-#[cfg(feature = "wasm")]
+//! WebAssembly bindings (enabled with the `wasm` feature)
+
 use wasm_bindgen::prelude::*;
 
-// Removed: now provided by vectrex_emulator crate
+use crate::CPU;
 
-#[cfg(feature = "wasm")]
 #[wasm_bindgen]
 pub struct WasmEmu {
     cpu: CPU,
 }
 
-#[cfg(feature = "wasm")]
 #[wasm_bindgen]
 impl WasmEmu {
     #[wasm_bindgen(constructor)]
@@ -37,10 +36,10 @@ impl WasmEmu {
     #[wasm_bindgen]
     pub fn reset(&mut self) { self.cpu.reset(); }
 
-    /// Step up to `count` instructions (or until an unimplemented opcode halts). Returns executed count.
+    /// Step up to `count` instructions (or until an illegal opcode halts). Returns executed count.
     #[wasm_bindgen]
     pub fn step(&mut self, count: u32) -> u32 {
-        let mut executed = 0; for _ in 0..count { if !self.cpu.step() { break; } executed += 1; } executed
+        let mut executed = 0; for _ in 0..count { if !self.step_one() { break; } executed += 1; } executed
     }
 
     /// Run until a WAIT_RECAL BIOS call is observed or max instructions hit. Returns executed instructions.
@@ -48,7 +47,7 @@ impl WasmEmu {
     #[wasm_bindgen]
     pub fn run_until_wait_recal(&mut self, max_instructions: u32) -> u32 {
         let start_frames = self.cpu.frame_count; let mut executed = 0;
-        while executed < max_instructions { if !self.cpu.step() { break; } executed += 1; if self.cpu.frame_count != start_frames { break; } }
+        while executed < max_instructions { if !self.step_one() { break; } executed += 1; if self.cpu.frame_count != start_frames { break; } }
         executed
     }
 
@@ -66,5 +65,21 @@ impl WasmEmu {
     /// Snapshot opcode metrics (human readable multiline string).
     #[wasm_bindgen]
     pub fn metrics(&self) -> String { self.cpu.metrics_pretty() }
-// End of wasm_api.rs
 }
+
+impl WasmEmu {
+    /// One CPU step with VIA/beam advanced by the consumed cycles.
+    fn step_one(&mut self) -> bool {
+        self.cpu.irq_pending = self.cpu.bus.via.irq();
+        let before = self.cpu.cycles;
+        if !self.cpu.step() { return false; }
+        let used = self.cpu.cycles - before;
+        self.cpu.bus.tick(used);
+        true
+    }
+}
+
+impl Default for WasmEmu {
+    fn default() -> Self { Self::new() }
+}
+// End of wasm_api.rs
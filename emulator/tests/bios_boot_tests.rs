@@ -0,0 +1,92 @@
+use vectrex_emulator::{locate_bios, Vectrex, CYCLES_PER_FRAME};
+
+/// Minimal cartridge: standard header, then a loop that draws one diagonal line per frame.
+///
+/// ```text
+/// loop: JSR Wait_Recal       ; BD F1 92
+///       JSR Intensity_5F     ; BD F2 A5
+///       LDA #$7F             ; 86 7F
+///       STA <$04             ; 97 04   (VIA T1 low = scale, DP=$D0)
+///       LDD #$0000           ; CC 00 00
+///       JSR Moveto_d         ; BD F3 12 (releases ZERO)
+///       LDA #$40             ; 86 40   (dy)
+///       LDB #$20             ; C6 20   (dx)
+///       JSR Draw_Line_d      ; BD F3 DF
+///       BRA loop             ; 20 E5
+/// ```
+fn line_cart() -> Vec<u8> {
+    let mut rom = Vec::new();
+    rom.extend_from_slice(b"g GCE 1982\x80");
+    rom.extend_from_slice(&[0xFD, 0x0D]); // music
+    rom.extend_from_slice(&[0xF8, 0x50, 0x20, 0xD0]); // height, width, rel y, rel x
+    rom.extend_from_slice(b"TEST\x80");
+    rom.push(0x00);
+    rom.extend_from_slice(&[
+        0xBD, 0xF1, 0x92, //
+        0xBD, 0xF2, 0xA5, //
+        0x86, 0x7F, 0x97, 0x04, //
+        0xCC, 0x00, 0x00, 0xBD, 0xF3, 0x12, //
+        0x86, 0x40, 0xC6, 0x20, //
+        0xBD, 0xF3, 0xDF, //
+        0x20, 0xE5,
+    ]);
+    rom
+}
+
+fn boot(rom: &[u8]) -> Option<Vectrex> {
+    let bios_path = locate_bios(std::path::Path::new(env!("CARGO_MANIFEST_DIR")))?;
+    let bios = std::fs::read(bios_path).ok()?;
+    let mut vectrex = Vectrex::new(&bios).expect("valid BIOS");
+    vectrex.load_cartridge(rom);
+    vectrex.reset(true);
+    Some(vectrex)
+}
+
+#[test]
+fn bios_reset_vector_enters_cold_start() {
+    let Some(vectrex) = boot(&line_cart()) else { return };
+    assert_eq!(vectrex.cpu.pc, 0xF000, "BIOS reset vector points at Cold_Start");
+}
+
+#[test]
+fn cartridge_loop_draws_one_line_per_frame() {
+    let Some(mut vectrex) = boot(&line_cart()) else { return };
+
+    vectrex.run_until_cartridge(CYCLES_PER_FRAME * 300).expect("title sequence");
+    vectrex.run_frame(CYCLES_PER_FRAME * 4).expect("first cartridge frame");
+    let frame = vectrex.run_frame(CYCLES_PER_FRAME * 4).expect("frame");
+    let lines: Vec<_> = frame.segments.iter().filter(|s| !s.is_dot()).collect();
+    assert_eq!(lines.len(), 1, "segments: {:?}", frame.segments);
+    let seg = lines[0];
+
+    let dx = seg.x1 - seg.x0;
+    let dy = seg.y1 - seg.y0;
+    // dy = $40 * $7F, dx = $20 * $7F (integrator units)
+    assert_eq!(dy, 0x40 * 0x7F);
+    assert_eq!(dx, 0x20 * 0x7F);
+    assert_eq!(seg.intensity, 0x5F, "Intensity_5F sets brightness");
+}
+
+#[test]
+fn frames_are_delimited_by_wait_recal() {
+    let Some(mut vectrex) = boot(&line_cart()) else { return };
+    vectrex.run_until_cartridge(CYCLES_PER_FRAME * 300).expect("title sequence");
+    let frames = vectrex.run_frames(3).expect("frames");
+    assert_eq!(frames.len(), 3);
+    assert_eq!(frames[2].number, frames[0].number + 2);
+    // Wait_Recal paces the loop on timer 2: one frame per 50 Hz refresh
+    let last = &frames[2];
+    assert!(last.cycles.abs_diff(CYCLES_PER_FRAME) < 100, "cycles={}", last.cycles);
+}
+
+#[test]
+fn boot_cartridge_skips_title_sequence() {
+    let Some(mut vectrex) = boot(&line_cart()) else { return };
+    assert_eq!(vectrex.cartridge_entry(), Some(0x0017));
+    vectrex.boot_cartridge().expect("boot");
+    assert_eq!(vectrex.cpu.pc, 0x0017);
+    vectrex.run_frame(CYCLES_PER_FRAME * 4).expect("first cartridge frame");
+    let frame = vectrex.run_frame(CYCLES_PER_FRAME * 4).expect("frame");
+    assert!(frame.number < 5, "title sequence was not skipped");
+    assert_eq!(frame.segments.iter().filter(|s| !s.is_dot()).count(), 1);
+}
@@ -4,9 +4,8 @@ use vectrex_emulator::CPU;
 #[test]
 fn irq_mask_prevents_and_then_allows_irq() {
     let mut cpu = CPU::default();
-    // Install IRQ vector at FFF6/FFF7 -> 0x0200
-    cpu.bus.mem[0xFFF6] = 0x00; cpu.bus.mem[0xFFF7] = 0x02;
-    cpu.bus.mem[0xFFF6] = 0x00; cpu.bus.mem[0xFFF7] = 0x02;
+    // Install IRQ vector at FFF8/FFF9 (big-endian) -> 0x0200
+    cpu.bus.mem[0xFFF8] = 0x02; cpu.bus.mem[0xFFF9] = 0x00;
     // IRQ handler at 0x0200: CLRA ; RTI
     cpu.bus.mem[0x0200] = 0x4F; cpu.bus.mem[0x0200] = 0x4F;
     cpu.bus.mem[0x0201] = 0x3B; cpu.bus.mem[0x0201] = 0x3B;
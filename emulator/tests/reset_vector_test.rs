@@ -0,0 +1,13 @@
+use vectrex_emulator::CPU;
+
+#[test]
+fn reset_uses_vector() {
+    let mut cpu = CPU::default();
+    // Install reset vector bytes at FFFE/FFFF (big-endian) -> 0x1234
+    cpu.bus.mem[0xFFFE] = 0x12; cpu.bus.mem[0xFFFF] = 0x34;
+    cpu.reset();
+    assert_eq!(cpu.pc, 0x1234, "PC should load from reset vector");
+    // Reset masks IRQ and FIRQ and clears E (MC6809 datasheet)
+    assert!(cpu.cc_i && cpu.cc_f && !cpu.cc_e, "Reset should set I and F and clear E");
+    assert_eq!(cpu.dp, 0, "DP cleared on reset");
+}
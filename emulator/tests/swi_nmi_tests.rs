@@ -1,14 +1,10 @@
 use vectrex_emulator::CPU;
 
-// NOTE: If crate path differs, we may need to use crate::emulator or pseudo_python::core etc.
-// Placeholder test scaffolding; will be updated after verifying module paths.
-
 #[test]
 fn swi_full_frame_restores_registers() {
     let mut cpu = CPU::default();
-    // Install SWI vector at FFF8/FFF9 pointing to 0x0200
-    cpu.bus.mem[0xFFF8] = 0x00; cpu.bus.mem[0xFFF9] = 0x02;
-    cpu.bus.mem[0xFFF8] = 0x00; cpu.bus.mem[0xFFF9] = 0x02;
+    // Install SWI vector at FFFA/FFFB pointing to 0x0200
+    cpu.bus.mem[0xFFFA] = 0x02; cpu.bus.mem[0xFFFB] = 0x00;
     // Handler at 0x0200: CLRA ; RTI
     cpu.bus.mem[0x0200] = 0x4F; // CLRA
     cpu.bus.mem[0x0201] = 0x3B; // RTI
@@ -40,12 +36,12 @@ fn swi_full_frame_restores_registers() {
 #[test]
 fn swi2_vectors_correctly() {
     let mut cpu = CPU::default();
-    // SWI2 vector FFF2/FFF3 -> 0x0300
-    cpu.bus.mem[0xFFF2] = 0x00; cpu.bus.mem[0xFFF3] = 0x03; cpu.bus.mem[0xFFF2] = 0x00; cpu.bus.mem[0xFFF3] = 0x03;
+    // SWI2 vector FFF4/FFF5 -> 0x0300
+    cpu.bus.mem[0xFFF4] = 0x03; cpu.bus.mem[0xFFF5] = 0x00;
     cpu.bus.mem[0x0300] = 0x3B; cpu.bus.mem[0x0300] = 0x3B; // RTI only
 
     cpu.pc = 0x0100;
-    // Encode SWI2 sequence (prefix 0x10 then 0x3F based on our temporary mapping)
+    // SWI2 is prefix 0x10 then 0x3F
     cpu.bus.mem[0x0100] = 0x10; cpu.bus.mem[0x0100] = 0x10;
     cpu.bus.mem[0x0101] = 0x3F; cpu.bus.mem[0x0101] = 0x3F;
 
@@ -57,8 +53,8 @@ fn swi2_vectors_correctly() {
 #[test]
 fn swi3_vectors_correctly() {
     let mut cpu = CPU::default();
-    // SWI3 vector FFF0/FFF1 -> 0x0310
-    cpu.bus.mem[0xFFF0] = 0x10; cpu.bus.mem[0xFFF1] = 0x03; cpu.bus.mem[0xFFF0] = 0x10; cpu.bus.mem[0xFFF1] = 0x03;
+    // SWI3 vector FFF2/FFF3 -> 0x0310
+    cpu.bus.mem[0xFFF2] = 0x03; cpu.bus.mem[0xFFF3] = 0x10;
     cpu.bus.mem[0x0310] = 0x3B; cpu.bus.mem[0x0310] = 0x3B; // RTI
 
     cpu.pc = 0x0100;
@@ -74,8 +70,8 @@ fn swi3_vectors_correctly() {
 #[test]
 fn nmi_full_frame_masks_irqs() {
     let mut cpu = CPU::default();
-    // NMI vector FFFA/FFFB -> 0x0400
-    cpu.bus.mem[0xFFFA] = 0x00; cpu.bus.mem[0xFFFB] = 0x04; cpu.bus.mem[0xFFFA] = 0x00; cpu.bus.mem[0xFFFB] = 0x04;
+    // NMI vector FFFC/FFFD -> 0x0400
+    cpu.bus.mem[0xFFFC] = 0x04; cpu.bus.mem[0xFFFD] = 0x00;
     cpu.bus.mem[0x0400] = 0x3B; cpu.bus.mem[0x0400] = 0x3B; // RTI only
 
     cpu.pc = 0x0100;